@@ -0,0 +1,85 @@
+//! Benchmarks for `DocCache` reads and writes, alone and under contention.
+//!
+//! Run with `cargo bench --bench cache`. The contended cases spawn several
+//! tasks on a multi-threaded runtime that hammer the same cache, which is the
+//! access pattern of an HTTP server with many concurrent sessions.
+
+use std::hint::black_box;
+
+use cratedocs_mcp::tools::DocCache;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const ENTRY: &str = "# Struct Sender\n\nSends values to the associated Receiver.\n";
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime")
+}
+
+fn bench_uncontended(c: &mut Criterion) {
+    let rt = runtime();
+    let cache = DocCache::new();
+    rt.block_on(async {
+        for i in 0..1_000 {
+            cache.set(format!("crate{}", i), ENTRY.to_string()).await;
+        }
+    });
+
+    let cache = &cache;
+    let mut group = c.benchmark_group("cache_uncontended");
+    group.bench_function("get_hit", |b| {
+        b.to_async(&rt).iter(|| async move { black_box(cache.get("crate500").await) })
+    });
+    group.bench_function("get_miss", |b| {
+        b.to_async(&rt).iter(|| async move { black_box(cache.get("missing").await) })
+    });
+    group.bench_function("set", |b| {
+        b.to_async(&rt)
+            .iter(|| async move { cache.set("crate500".to_string(), ENTRY.to_string()).await })
+    });
+    group.finish();
+}
+
+fn bench_contended(c: &mut Criterion) {
+    const OPS_PER_TASK: usize = 100;
+    let rt = runtime();
+
+    let mut group = c.benchmark_group("cache_contended");
+    for tasks in [2, 8, 32] {
+        group.throughput(Throughput::Elements((tasks * OPS_PER_TASK) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(tasks), &tasks, |b, &tasks| {
+            let cache = DocCache::new();
+            b.to_async(&rt).iter(|| {
+                let cache = cache.clone();
+                async move {
+                    let handles: Vec<_> = (0..tasks)
+                        .map(|task| {
+                            let cache = cache.clone();
+                            tokio::spawn(async move {
+                                for op in 0..OPS_PER_TASK {
+                                    let key = format!("crate{}", (task * OPS_PER_TASK + op) % 64);
+                                    // Mostly reads with occasional writes, like real traffic
+                                    if op % 10 == 0 {
+                                        cache.set(key, ENTRY.to_string()).await;
+                                    } else {
+                                        black_box(cache.get(&key).await);
+                                    }
+                                }
+                            })
+                        })
+                        .collect();
+                    for handle in handles {
+                        handle.await.unwrap();
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_uncontended, bench_contended);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! Benchmarks for HTML-to-markdown conversion of rustdoc pages.
+//!
+//! Run with `cargo bench --bench conversion`. Inputs are the captured fixtures
+//! from `tests/fixtures`, plus synthetic "large" pages built by repeating the
+//! implementation sections, which approximates heavily generic crate roots.
+
+use std::hint::black_box;
+use std::path::Path;
+
+use cratedocs_mcp::tools::html_to_markdown;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn load_fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path.display(), e))
+}
+
+// Repeat the body of a page `times` times inside a single document
+fn enlarge(html: &str, times: usize) -> String {
+    let start = html.find("<main>").unwrap_or(0);
+    let end = html.rfind("</main>").map(|idx| idx + "</main>".len()).unwrap_or(html.len());
+    let body = &html[start..end];
+    let mut page = String::with_capacity(body.len() * times + 64);
+    page.push_str("<!DOCTYPE html><html><body>");
+    for _ in 0..times {
+        page.push_str(body);
+    }
+    page.push_str("</body></html>");
+    page
+}
+
+fn bench_fixtures(c: &mut Criterion) {
+    let mut group = c.benchmark_group("convert_fixture");
+    for fixture in [
+        "crate_page.html",
+        "crate_root.html",
+        "struct_page.html",
+        "trait_page.html",
+        "enum_page.html",
+    ] {
+        let html = load_fixture(fixture);
+        group.throughput(Throughput::Bytes(html.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fixture), &html, |b, html| {
+            b.iter(|| html_to_markdown(black_box(html)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_large_pages(c: &mut Criterion) {
+    let struct_page = load_fixture("struct_page.html");
+    let mut group = c.benchmark_group("convert_large_page");
+    group.sample_size(20);
+    for times in [10, 50, 200] {
+        let html = enlarge(&struct_page, times);
+        group.throughput(Throughput::Bytes(html.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(times), &html, |b, html| {
+            b.iter(|| html_to_markdown(black_box(html)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_fixtures, bench_large_pages);
+criterion_main!(benches);
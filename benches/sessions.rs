@@ -0,0 +1,47 @@
+//! Benchmark of concurrent MCP sessions on the HTTP/SSE transport.
+//!
+//! Run with `cargo bench --bench sessions`. Each iteration opens a number of
+//! sessions against a local server and drives interleaved `lookup_crate` calls
+//! through all of them with the harness of `tests/session_tests.rs`. Lookups are
+//! answered locally and cached after the first iteration, so this measures the
+//! session multiplexing path rather than fetching and conversion.
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use reqwest::Client;
+use support::{run_session, start_server};
+
+const CALLS_PER_SESSION: usize = 8;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime")
+}
+
+fn bench_sessions(c: &mut Criterion) {
+    let rt = runtime();
+    let server = rt.block_on(start_server());
+    let client = Client::new();
+
+    let mut group = c.benchmark_group("sse_sessions");
+    group.sample_size(10);
+    for sessions in [1, 8, 32] {
+        group.throughput(Throughput::Elements((sessions * CALLS_PER_SESSION) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(sessions), &sessions, |b, &sessions| {
+            let (client, addr) = (&client, server.addr);
+            b.to_async(&rt).iter(|| async move {
+                let runs = (0..sessions).map(|session| run_session(client, addr, session, CALLS_PER_SESSION));
+                futures::future::join_all(runs).await
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_sessions);
+criterion_main!(benches);
@@ -1,14 +1,38 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use cratedocs_mcp::tools::DocRouter;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use cratedocs_mcp::tools::{
+    format_export_summary, item_probe_order, CratePolicy, markdown_to_html, markdown_to_text, run_batch_line, split_error_data, user_agent, CrateOverrides, DiskStore, DocCache, DocRouter, LocalDocs, Mirror, OversizePolicy, PrefetchConfig, SearchBackend, SearchIndexCache, UsageStats,
+    DEFAULT_CIRCUIT_COOLDOWN, DEFAULT_CIRCUIT_FAILURE_THRESHOLD, DEFAULT_EXPORT_MAX_PAGES, DEFAULT_HTTP_MAX_CONTENT_BYTES, DEFAULT_INDEX_CACHE_BYTES, DEFAULT_INDEX_TTL, DEFAULT_MAX_CONCURRENT_REQUESTS, DEFAULT_MAX_RESPONSE_BYTES,
+    DEFAULT_REFRESH_INTERVAL_SECS, DEFAULT_REFRESH_TOP, DEFAULT_STDIO_MAX_CONTENT_BYTES, DEFAULT_SUBSCRIPTION_REFRESH_SECS, DEFAULT_WATCH_INTERVAL_SECS, SessionSubscriptions, USAGE_FILE,
+};
+use cratedocs_mcp::tools::docs::lockfile::parse_lockfile;
+use cratedocs_mcp::transport::extensions::route_client_messages;
+use cratedocs_mcp::transport::http_sse_server::resolve_bind_address;
+use cratedocs_mcp::transport::jsonrpc_frame_codec::{Framing, SharedFraming};
+use cratedocs_mcp::transport::notifications::forward_with_notifications;
 use mcp_core::Content;
 use mcp_server::router::RouterService;
 use mcp_server::{ByteTransport, Router, Server};
 use serde_json::json;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{stdin, stdout};
+use tokio::sync::mpsc;
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{self, EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{self, fmt::format::FmtSpan, EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Size of the pipes between the stdio server and stdin/stdout
+const STDIO_BUFFER_SIZE: usize = 1 << 16;
+
+/// Responses given by the transport itself that may wait to be written to stdout
+const REPLY_BUFFER_SIZE: usize = 16;
+
+/// Longest the HTTP server waits for open connections, such as SSE streams, after a
+/// shutdown signal
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
 
 #[derive(Parser)]
 #[command(author, version = "0.1.0", about, long_about = None)]
@@ -26,16 +50,119 @@ enum Commands {
         /// Enable debug logging
         #[arg(short, long)]
         debug: bool,
+
+        /// Directory for log files (defaults to $XDG_STATE_HOME/cratedocs-mcp/logs)
+        #[arg(long)]
+        log_dir: Option<PathBuf>,
+
+        /// How often log files are rotated
+        #[arg(long, value_enum, default_value = "daily")]
+        log_rotation: LogRotation,
+
+        /// Maximum number of rotated log files to keep (unlimited if not set)
+        #[arg(long)]
+        log_max_files: Option<usize>,
+
+        /// Largest tool result in bytes sent at once; longer results are truncated
+        /// with a marker giving the offset to continue from (0 for no limit)
+        #[arg(long, default_value_t = DEFAULT_STDIO_MAX_CONTENT_BYTES)]
+        max_content_bytes: usize,
+
+        /// How messages are delimited on stdin and stdout; `auto` detects it from the
+        /// client's first message
+        #[arg(long, value_enum, default_value = "auto")]
+        framing: StdioFraming,
+
+        #[command(flatten)]
+        router: RouterArgs,
     },
     /// Run the server with HTTP/SSE interface
     Http {
-        /// Address to bind the HTTP server to
-        #[arg(short, long, default_value = "127.0.0.1:8080")]
-        address: String,
+        /// Addresses to bind the HTTP server to, repeated or comma-separated
+        /// (e.g. 127.0.0.1:8080, [::1]:8080, localhost:8080)
+        #[arg(short, long, value_delimiter = ',', default_value = "127.0.0.1:8080")]
+        address: Vec<String>,
         
         /// Enable debug logging
         #[arg(short, long)]
         debug: bool,
+
+        /// Largest tool result in bytes sent at once; longer results are truncated
+        /// with a marker giving the offset to continue from (0 for no limit)
+        #[arg(long, default_value_t = DEFAULT_HTTP_MAX_CONTENT_BYTES)]
+        max_content_bytes: usize,
+
+        /// Periodically revalidate the most accessed crate and item docs, fetching them
+        /// again when a new version of their crate is published
+        #[arg(long)]
+        refresh_popular: bool,
+
+        /// Number of most accessed cache entries --refresh-popular keeps fresh
+        #[arg(long, default_value_t = DEFAULT_REFRESH_TOP)]
+        refresh_top: usize,
+
+        /// Seconds between refreshes of the most accessed cache entries
+        #[arg(long, default_value_t = DEFAULT_REFRESH_INTERVAL_SECS)]
+        refresh_interval: u64,
+
+        /// Don't serve the playground page at /, which lets browsers try lookups
+        #[arg(long)]
+        no_playground: bool,
+
+        #[command(flatten)]
+        router: RouterArgs,
+    },
+    /// Manage the documentation cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Write the docs of crates as markdown files, one per module and item page, for
+    /// grepping or feeding into other tools
+    Export {
+        /// Crates to export, as name or name@version (latest when no version is given)
+        crates: Vec<String>,
+
+        /// Export every crates.io package locked in this Cargo.lock, at its locked version
+        #[arg(long)]
+        lockfile: Option<PathBuf>,
+
+        /// Directory to write to; each crate goes to <OUT_DIR>/<crate>/<version>/
+        #[arg(short, long, default_value = "crate-docs")]
+        out_dir: PathBuf,
+
+        /// Most module and item pages exported per crate
+        #[arg(long, default_value_t = DEFAULT_EXPORT_MAX_PAGES)]
+        max_pages: usize,
+
+        /// Enable debug logging
+        #[arg(short, long)]
+        debug: bool,
+
+        #[command(flatten)]
+        router: RouterArgs,
+    },
+    /// Save crate pages, item pages and search indexes to --mirror-dir, for serving
+    /// item-level lookups from disk, even --offline
+    Mirror {
+        /// Crates to mirror, as name or name@version (e.g. tokio,serde,axum@0.7)
+        #[arg(long, value_delimiter = ',', required = true)]
+        crates: Vec<String>,
+
+        /// Versions to mirror of each crate not given as name@version (e.g. latest,1.35.0)
+        #[arg(long, value_delimiter = ',', default_value = "latest")]
+        versions: Vec<String>,
+
+        /// Most module and item pages mirrored per crate version
+        #[arg(long, default_value_t = DEFAULT_EXPORT_MAX_PAGES)]
+        max_pages: usize,
+
+        /// Enable debug logging
+        #[arg(short, long)]
+        debug: bool,
+
+        #[command(flatten)]
+        router: RouterArgs,
     },
     /// Test tools directly from the CLI
     Test {
@@ -62,8 +189,12 @@ enum Commands {
         /// Result limit for search_crates
         #[arg(long)]
         limit: Option<u32>,
+
+        /// Search backend for search_crates (crates-io or lib-rs)
+        #[arg(long)]
+        backend: Option<String>,
         
-        /// Output format (markdown, text, json)
+        /// Output format (markdown, text, html, json)
         #[arg(long, default_value = "markdown")]
         format: Option<String>,
         
@@ -71,19 +202,307 @@ enum Commands {
         #[arg(long)]
         output: Option<String>,
         
+        /// Maximum size in bytes of a single upstream response
+        #[arg(long, default_value_t = DEFAULT_MAX_RESPONSE_BYTES)]
+        max_download_bytes: usize,
+        
+        /// Read tool invocations from stdin, one JSON object per line such as
+        /// {"tool": "lookup_crate", "arguments": {"crate_name": "serde"}}, and write
+        /// one JSON result per line to stdout instead of running --tool once
+        #[arg(long)]
+        stdin: bool,
+
         /// Enable debug logging
         #[arg(short, long)]
         debug: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Fetch the docs of every crates.io dependency locked in a Cargo.lock into the
+    /// cache directory, so a server using it answers lookups for that project at once
+    Warm {
+        /// The project's lockfile
+        #[arg(long, default_value = "Cargo.lock")]
+        lockfile: PathBuf,
+
+        /// Enable debug logging
+        #[arg(short, long)]
+        debug: bool,
+
+        #[command(flatten)]
+        router: RouterArgs,
+    },
+    /// Write the cache directory's entries, or those of some crates, to one compressed
+    /// bundle to import into a cache elsewhere (e.g. CI or an air-gapped machine)
+    Export {
+        /// File to write the bundle to
+        #[arg(long, default_value = "cratedocs-cache.jsonl.gz")]
+        bundle: PathBuf,
+
+        /// Only export the entries of these crates (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        crates: Vec<String>,
+
+        /// The cache directory to export
+        #[arg(long, env = "CRATEDOCS_CACHE_DIR")]
+        cache_dir: PathBuf,
+    },
+    /// Add the entries of a bundle written by cache export to the cache directory
+    Import {
+        /// The bundle to import
+        bundle: PathBuf,
+
+        /// The cache directory to import into
+        #[arg(long, env = "CRATEDOCS_CACHE_DIR")]
+        cache_dir: PathBuf,
+    },
+}
+
+/// Documentation router options shared by the server commands
+#[derive(Args)]
+struct RouterArgs {
+    /// Maximum size in bytes of a single upstream response
+    #[arg(long, default_value_t = DEFAULT_MAX_RESPONSE_BYTES)]
+    max_download_bytes: usize,
+
+    /// Upstream HTTP requests in flight at once across all sessions and tools;
+    /// further requests wait their turn (0 for no limit)
+    #[arg(long, env = "CRATEDOCS_MAX_CONCURRENT_REQUESTS", default_value_t = DEFAULT_MAX_CONCURRENT_REQUESTS)]
+    max_concurrent_requests: usize,
+
+    /// Consecutive failed requests to docs.rs, crates.io or another upstream after
+    /// which requests to it fail fast for a cooldown (0 to keep sending them)
+    #[arg(long, env = "CRATEDOCS_CIRCUIT_FAILURE_THRESHOLD", default_value_t = DEFAULT_CIRCUIT_FAILURE_THRESHOLD)]
+    circuit_failure_threshold: u32,
+
+    /// Seconds requests to a failing upstream fail fast before it is tried again
+    #[arg(long, env = "CRATEDOCS_CIRCUIT_COOLDOWN", default_value_t = DEFAULT_CIRCUIT_COOLDOWN.as_secs())]
+    circuit_cooldown: u64,
+
+    /// User-Agent sent to docs.rs, crates.io and other upstreams
+    #[arg(long, env = "CRATEDOCS_USER_AGENT")]
+    user_agent: Option<String>,
+
+    /// How to reach the operator of this deployment (e.g. an email address),
+    /// added to the User-Agent as the crates.io crawling policy asks
+    #[arg(long, env = "CRATEDOCS_CONTACT")]
+    contact: Option<String>,
+
+    /// crates.io API token for higher rate limits; only sent to crates.io
+    #[arg(long, env = "CRATEDOCS_CRATES_IO_TOKEN", hide_env_values = true)]
+    crates_io_token: Option<String>,
+
+    /// Prefetch linked items and dependency docs in the background after crate lookups
+    #[arg(long)]
+    prefetch: bool,
+
+    /// Crates to fetch into the cache at startup (e.g. tokio,serde,axum@0.7)
+    #[arg(long, env = "CRATEDOCS_PRELOAD", value_delimiter = ',')]
+    preload: Vec<String>,
+
+    /// JSON config file with per-crate defaults (version, features, docs.rs target, aliases)
+    #[arg(long, env = "CRATEDOCS_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Persist the documentation cache, and the usage counts usage_top lists, in this
+    /// directory across restarts
+    #[arg(long, env = "CRATEDOCS_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Largest cache entry in bytes kept in memory as-is (unlimited if not set)
+    #[arg(long)]
+    cache_max_entry_bytes: Option<usize>,
+
+    /// What to do with cache entries above --cache-max-entry-bytes
+    #[arg(long, value_enum, default_value = "truncate")]
+    cache_oversize_policy: CacheOversizePolicy,
+
+    /// Backend search_crates uses unless a call picks one
+    #[arg(long, env = "CRATEDOCS_SEARCH_BACKEND", value_enum, default_value = "crates-io")]
+    search_backend: CliSearchBackend,
+
+    /// Crates to watch for new releases from startup (e.g. tokio,axum)
+    #[arg(long, env = "CRATEDOCS_WATCH", value_delimiter = ',')]
+    watch: Vec<String>,
+
+    /// Seconds between checks of watched crates for new releases
+    #[arg(long, default_value_t = DEFAULT_WATCH_INTERVAL_SECS)]
+    watch_interval: u64,
+
+    /// Seconds between re-reads of subscribed resources to detect changes
+    #[arg(long, default_value_t = DEFAULT_SUBSCRIPTION_REFRESH_SECS)]
+    subscription_refresh_interval: u64,
+
+    /// Seconds a downloaded rustdoc search index is reused
+    #[arg(long, default_value_t = DEFAULT_INDEX_TTL.as_secs())]
+    index_cache_ttl: u64,
+
+    /// Memory budget in bytes for cached rustdoc search indexes
+    #[arg(long, default_value_t = DEFAULT_INDEX_CACHE_BYTES)]
+    index_cache_bytes: usize,
+
+    /// Enable the build_local_docs tool for Cargo workspaces below these directories;
+    /// building runs the workspaces' build scripts and proc macros, so only trusted code
+    #[arg(long, env = "CRATEDOCS_LOCAL_DOCS_ROOT", value_delimiter = ',')]
+    local_docs_root: Vec<PathBuf>,
+
+    /// Split results above --max-content-bytes into up to this many content blocks
+    /// before truncating them
+    #[arg(long, default_value_t = 1)]
+    max_content_parts: usize,
+
+    /// Seconds a call of a tool may take before it fails, per tool
+    /// (e.g. search_crates=5,lookup_crate=15,semver_check=60)
+    #[arg(long, env = "CRATEDOCS_TOOL_TIMEOUTS", value_delimiter = ',')]
+    tool_timeout: Vec<String>,
+
+    /// Page kinds lookup_item tries first when an item's kind is unknown (e.g. trait,struct);
+    /// the others follow in the default order
+    #[arg(long, env = "CRATEDOCS_PROBE_ORDER", value_delimiter = ',')]
+    probe_order: Vec<String>,
+
+    /// Only allow looking up these crates; a trailing * matches a prefix (e.g. tokio,tokio-*)
+    #[arg(long, env = "CRATEDOCS_ALLOW_CRATES", value_delimiter = ',')]
+    allow_crates: Vec<String>,
+
+    /// Refuse looking up these crates, even when allowed; a trailing * matches a prefix
+    #[arg(long, env = "CRATEDOCS_DENY_CRATES", value_delimiter = ',')]
+    deny_crates: Vec<String>,
+
+    /// Documentation sources to fetch nothing from: std, docs.rs, crates.io, lib.rs, github,
+    /// crates.io-readme, repository or local (e.g. lib.rs,github); tools needing only
+    /// disabled sources fail
+    #[arg(long, env = "CRATEDOCS_DISABLE_SOURCES", value_delimiter = ',')]
+    disable_sources: Vec<String>,
+
+    /// Serve crate docs saved by the mirror command from this directory ahead of docs.rs
+    #[arg(long, env = "CRATEDOCS_MIRROR_DIR")]
+    mirror_dir: Option<PathBuf>,
+
+    /// Send no requests upstream, answering only from --mirror-dir and local docs
+    #[arg(long, requires = "mirror_dir")]
+    offline: bool,
+
+    /// Log tool calls taking at least this many seconds at WARN, with their arguments,
+    /// upstream URLs and where the time went (e.g. 2.5)
+    #[arg(long, env = "CRATEDOCS_SLOW_CALL_THRESHOLD")]
+    slow_call_threshold: Option<f64>,
+}
+
+/// Handling of oversized cache entries
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CacheOversizePolicy {
+    /// Keep a truncated copy with a truncation note
+    Truncate,
+    /// Keep the full entry on disk only (requires --cache-dir to be cached at all)
+    Spill,
+}
+
+impl From<CacheOversizePolicy> for OversizePolicy {
+    fn from(policy: CacheOversizePolicy) -> Self {
+        match policy {
+            CacheOversizePolicy::Truncate => OversizePolicy::Truncate,
+            CacheOversizePolicy::Spill => OversizePolicy::SpillToDisk,
+        }
+    }
+}
+
+/// Crate search services
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CliSearchBackend {
+    /// The crates.io search API
+    CratesIo,
+    /// lib.rs, with relevance ranking and categories
+    LibRs,
+}
+
+impl From<CliSearchBackend> for SearchBackend {
+    fn from(backend: CliSearchBackend) -> Self {
+        match backend {
+            CliSearchBackend::CratesIo => SearchBackend::CratesIo,
+            CliSearchBackend::LibRs => SearchBackend::LibRs,
+        }
+    }
+}
+
+/// Rotation policy for the stdio server log files
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Message framing of the stdio server
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum StdioFraming {
+    Auto,
+    /// One JSON-RPC message per line
+    Newline,
+    /// LSP-style `Content-Length` headers before each message
+    ContentLength,
+}
+
+impl From<StdioFraming> for SharedFraming {
+    fn from(framing: StdioFraming) -> Self {
+        match framing {
+            StdioFraming::Auto => SharedFraming::default(),
+            StdioFraming::Newline => SharedFraming::fixed(Framing::Newline),
+            StdioFraming::ContentLength => SharedFraming::fixed(Framing::ContentLength),
+        }
+    }
+}
+
+/// Logging options for the stdio server
+struct LogConfig {
+    dir: Option<PathBuf>,
+    rotation: LogRotation,
+    max_files: Option<usize>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Stdio { debug } => run_stdio_server(debug).await,
-        Commands::Http { address, debug } => run_http_server(address, debug).await,
+        Commands::Stdio { debug, log_dir, log_rotation, log_max_files, max_content_bytes, framing, router } => {
+            run_stdio_server(debug, LogConfig {
+                dir: log_dir,
+                rotation: log_rotation,
+                max_files: log_max_files,
+            }, max_content_bytes, framing.into(), router).await
+        }
+        Commands::Http { address, debug, max_content_bytes, refresh_popular, refresh_top, refresh_interval, no_playground, router } => {
+            let refresh = refresh_popular.then_some((refresh_top, refresh_interval));
+            run_http_server(address, debug, max_content_bytes, refresh, !no_playground, router).await
+        }
+        Commands::Cache { command: CacheCommands::Warm { lockfile, debug, router } } => {
+            warm_cache(lockfile, debug, router).await
+        }
+        Commands::Cache { command: CacheCommands::Export { bundle, crates, cache_dir } } => {
+            export_cache(bundle, crates, cache_dir)
+        }
+        Commands::Cache { command: CacheCommands::Import { bundle, cache_dir } } => import_cache(bundle, cache_dir),
+        Commands::Export { crates, lockfile, out_dir, max_pages, debug, router } => {
+            export_docs(crates, lockfile, out_dir, max_pages, debug, router).await
+        }
+        Commands::Mirror { crates, versions, max_pages, debug, router } => {
+            mirror_docs(crates, versions, max_pages, debug, router).await
+        }
         Commands::Test { 
             tool, 
             crate_name, 
@@ -91,8 +510,11 @@ async fn main() -> Result<()> {
             query, 
             version, 
             limit,
+            backend,
             format,
             output,
+            max_download_bytes,
+            stdin,
             debug 
         } => run_test_tool(TestToolConfig {
             tool,
@@ -101,23 +523,217 @@ async fn main() -> Result<()> {
             query,
             version,
             limit,
+            backend,
             format,
             output,
+            max_download_bytes,
+            stdin,
             debug
         }).await,
     }
 }
 
-async fn run_stdio_server(debug: bool) -> Result<()> {
+/// Default log directory following the XDG base directory spec
+///
+/// Uses `$XDG_STATE_HOME/cratedocs-mcp/logs`, falling back to
+/// `~/.local/state/cratedocs-mcp/logs` and finally the system temp directory,
+/// so the server never depends on the (possibly read-only) working directory.
+fn default_log_dir() -> PathBuf {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(|home| PathBuf::from(home).join(".local").join("state"))
+        })
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir);
+
+    state_dir.join("cratedocs-mcp").join("logs")
+}
+
+/// Create a documentation router from the command line options, capping
+/// results at `max_content_bytes` for the transport it serves (0 for no cap)
+fn build_router(args: &RouterArgs, max_content_bytes: usize) -> Result<DocRouter> {
+    let crate_overrides = match &args.config {
+        Some(path) => CrateOverrides::load(path).map_err(|e| anyhow::anyhow!(e))?,
+        None => CrateOverrides::default(),
+    };
+    let mut cache = match &args.cache_dir {
+        Some(cache_dir) => {
+            let store = DiskStore::open(cache_dir).map_err(|e| {
+                anyhow::anyhow!("Failed to open cache directory {}: {}", cache_dir.display(), e)
+            })?;
+            DocCache::with_disk(store)
+        }
+        None => DocCache::new(),
+    };
+    if let Some(max_entry_bytes) = args.cache_max_entry_bytes {
+        cache = cache.with_max_entry_bytes(max_entry_bytes, args.cache_oversize_policy.into());
+    }
+    let mut builder = DocRouter::builder()
+        .max_response_bytes(args.max_download_bytes)
+        .max_concurrent_requests(args.max_concurrent_requests)
+        .circuit_breaker(args.circuit_failure_threshold, Duration::from_secs(args.circuit_cooldown))
+        .user_agent(user_agent(args.user_agent.as_deref(), args.contact.as_deref()))
+        .disable_sources(&args.disable_sources)
+        .cache(cache)
+        .usage(load_usage(args.cache_dir.as_deref()))
+        .crate_overrides(crate_overrides)
+        .search_backend(args.search_backend.into())
+        .search_index_cache(SearchIndexCache::new(
+            Duration::from_secs(args.index_cache_ttl),
+            args.index_cache_bytes,
+        ))
+        .crate_policy(CratePolicy::new(&args.allow_crates, &args.deny_crates));
+    if let Some(token) = args.crates_io_token.as_ref().map(|token| token.trim()).filter(|token| !token.is_empty()) {
+        builder = builder.crates_io_token(token.to_string());
+    }
+    if args.prefetch {
+        builder = builder.prefetch(PrefetchConfig::default());
+    }
+    if let Some(mirror_dir) = &args.mirror_dir {
+        builder = builder.mirror(Mirror::new(mirror_dir), args.offline);
+    }
+    if !args.local_docs_root.is_empty() {
+        builder = builder.local_docs(LocalDocs::new(args.local_docs_root.clone()));
+    }
+    if max_content_bytes > 0 {
+        builder = builder.max_content(max_content_bytes, args.max_content_parts);
+    }
+    if !args.probe_order.is_empty() {
+        let probe_order = item_probe_order(&args.probe_order).map_err(|e| anyhow::anyhow!("Invalid --probe-order: {}", e))?;
+        builder = builder.probe_order(probe_order);
+    }
+    if let Some(secs) = args.slow_call_threshold {
+        let threshold = Duration::try_from_secs_f64(secs)
+            .map_err(|_| anyhow::anyhow!("Invalid --slow-call-threshold {}: expected seconds", secs))?;
+        builder = builder.slow_call_threshold(threshold);
+    }
+    for spec in &args.tool_timeout {
+        let (tool_name, timeout) = parse_tool_timeout(spec)?;
+        builder = builder.tool_timeout(tool_name, timeout);
+    }
+    builder.build().map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Usage counts kept in the cache directory, if there is one; unreadable counts are
+/// left alone and counting happens in memory, rather than keeping the server from starting
+fn load_usage(cache_dir: Option<&Path>) -> UsageStats {
+    let Some(cache_dir) = cache_dir else { return UsageStats::default() };
+    let path = cache_dir.join(USAGE_FILE);
+    UsageStats::load(&path).unwrap_or_else(|e| {
+        tracing::warn!("Failed to read the usage counts in {}, counting in memory only: {}", path.display(), e);
+        UsageStats::default()
+    })
+}
+
+/// Parse a `tool=seconds` timeout
+fn parse_tool_timeout(spec: &str) -> Result<(&str, Duration)> {
+    let invalid = || anyhow::anyhow!("Invalid --tool-timeout {}: expected tool=seconds", spec);
+    let (tool_name, secs) = spec.split_once('=').ok_or_else(invalid)?;
+    let secs: f64 = secs.trim().parse().map_err(|_| invalid())?;
+    let timeout = Duration::try_from_secs_f64(secs).map_err(|_| invalid())?;
+    if timeout.is_zero() {
+        return Err(invalid());
+    }
+    Ok((tool_name.trim(), timeout))
+}
+
+/// Warm the router's cache with the listed crates without delaying startup
+fn spawn_preload(router: &DocRouter, crates: Vec<String>) {
+    if crates.is_empty() {
+        return;
+    }
+    let router = router.clone();
+    tokio::spawn(async move {
+        let loaded = router.preload(&crates).await;
+        tracing::info!("Preloaded {} of {} crates", loaded, crates.len());
+    });
+}
+
+/// Periodically re-read subscribed resources, notifying subscribers of the ones that changed
+fn spawn_subscription_refresh(router: &DocRouter, interval_secs: u64) {
+    let router = router.clone();
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+        // Subscribing reads the resource, so there is nothing to compare at the first tick
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            let changed = router.refresh_subscriptions().await;
+            if !changed.is_empty() {
+                tracing::info!("{} subscribed resource(s) changed", changed.len());
+            }
+        }
+    });
+}
+
+/// Periodically check watched crates for new releases for as long as the server runs
+fn spawn_watch(router: &DocRouter, crates: Vec<String>, interval_secs: u64) {
+    let router = router.clone();
+    tokio::spawn(async move {
+        router.watch.add(&crates).await;
+        let mut ticks = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+        loop {
+            // The first tick completes immediately, which records the baseline versions
+            ticks.tick().await;
+            let updates = router.check_watched().await;
+            if !updates.is_empty() {
+                tracing::info!("Found {} new release(s) of watched crates", updates.len());
+            }
+        }
+    });
+}
+
+/// Periodically revalidate the `top` most accessed cache entries for as long as the server runs
+fn spawn_popular_refresh(router: &DocRouter, top: usize, interval_secs: u64) {
+    let router = router.clone();
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+        // Nothing has been accessed yet at the first tick
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            let refreshed = router.refresh_popular(top).await;
+            if refreshed > 0 {
+                tracing::info!("Refreshed {} popular cache entries at new versions", refreshed);
+            }
+        }
+    });
+}
+
+async fn run_stdio_server(
+    debug: bool,
+    log_config: LogConfig,
+    max_content_bytes: usize,
+    framing: SharedFraming,
+    router_args: RouterArgs,
+) -> Result<()> {
     // Set up file appender for logging
-    let file_appender = RollingFileAppender::new(Rotation::DAILY, "logs", "stdio-server.log");
+    let log_dir = log_config.dir.unwrap_or_else(default_log_dir);
+    std::fs::create_dir_all(&log_dir).map_err(|e| {
+        anyhow::anyhow!("Failed to create log directory {}: {}", log_dir.display(), e)
+    })?;
+
+    let mut appender_builder = RollingFileAppender::builder()
+        .rotation(log_config.rotation.into())
+        .filename_prefix("stdio-server.log");
+    if let Some(max_files) = log_config.max_files {
+        appender_builder = appender_builder.max_log_files(max_files);
+    }
+    let file_appender = appender_builder.build(&log_dir)?;
+    // Log lines are written on a background thread; dropping the guard flushes them
+    let (log_writer, log_guard) = tracing_appender::non_blocking(file_appender);
 
     // Initialize the tracing subscriber with file logging
     let level = if debug { tracing::Level::DEBUG } else { tracing::Level::INFO };
     
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive(level.into()))
-        .with_writer(file_appender)
+        .with_writer(log_writer)
+        .with_span_events(span_events(debug))
         .with_target(false)
         .with_thread_ids(true)
         .with_file(true)
@@ -125,19 +741,92 @@ async fn run_stdio_server(debug: bool) -> Result<()> {
         .init();
 
     tracing::info!("Starting MCP documentation server in STDIN/STDOUT mode");
+    tracing::info!("Writing logs to {}", log_dir.display());
 
     // Create an instance of our documentation router
-    let router = RouterService(DocRouter::new());
+    let router = build_router(&router_args, max_content_bytes)?;
+    spawn_preload(&router, router_args.preload);
+    spawn_watch(&router, router_args.watch, router_args.watch_interval);
+    spawn_subscription_refresh(&router, router_args.subscription_refresh_interval);
+
+    // The server writes responses into a pipe; forwarding them to stdout lets
+    // resource update notifications be interleaved, and lets responses be framed
+    // the way the client frames its requests
+    let (server_output, server_writer) = tokio::io::simplex(STDIO_BUFFER_SIZE);
+    let (replies, reply_receiver) = mpsc::channel(REPLY_BUFFER_SIZE);
+    let session = SessionSubscriptions::default();
+    let forward = tokio::spawn(forward_with_notifications(
+        server_output,
+        stdout(),
+        router.watch.subscribe(),
+        session.clone(),
+        reply_receiver,
+        framing.clone(),
+    ));
+
+    // Requests the server doesn't implement are answered on the way in
+    let (server_input, input_writer) = tokio::io::simplex(STDIO_BUFFER_SIZE);
+    let input = tokio::spawn(route_client_messages(stdin(), input_writer, router.clone(), session, replies, framing));
+
+    // On a shutdown signal, stop reading stdin: the server sees the end of its input
+    // once the request in flight is answered, and the server stops as when the client
+    // closes stdin
+    let input_abort = input.abort_handle();
+    let signalled = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    tokio::spawn({
+        let signalled = signalled.clone();
+        async move {
+            shutdown_signal().await;
+            tracing::info!("Shutting down after the request in flight");
+            signalled.store(true, std::sync::atomic::Ordering::SeqCst);
+            input_abort.abort();
+        }
+    });
 
     // Create and run the server
-    let server = Server::new(router);
-    let transport = ByteTransport::new(stdin(), stdout());
+    let server = Server::new(RouterService(router.clone()));
+    let transport = ByteTransport::new(server_input, server_writer);
 
     tracing::info!("Documentation server initialized and ready to handle requests");
-    Ok(server.run(transport).await?)
+    let result = server.run(transport).await;
+    let forwarded = forward.await;
+    if let Err(e) = router.cache.flush().await {
+        tracing::warn!("Failed to flush the persistent cache: {}", e);
+    }
+    if let Err(e) = router.usage.save().await {
+        tracing::warn!("Failed to save the usage counts: {}", e);
+    }
+    tracing::info!("Documentation server stopped");
+    drop(log_guard);
+    result?;
+    forwarded??;
+    // The blocking read of stdin can't be cancelled and would keep the runtime from exiting
+    if signalled.load(std::sync::atomic::Ordering::SeqCst) {
+        std::process::exit(0);
+    }
+    Ok(())
+}
+
+/// Span events to log: with `--debug`, every span as it closes, so each tool call
+/// and its phases (cache lookup, upstream fetch, conversion) show with their timing
+fn span_events(debug: bool) -> FmtSpan {
+    if debug {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    }
 }
 
-async fn run_http_server(address: String, debug: bool) -> Result<()> {
+/// Run the HTTP server, revalidating the most accessed cache entries every so often
+/// when `refresh` gives how many and the seconds between refreshes
+async fn run_http_server(
+    addresses: Vec<String>,
+    debug: bool,
+    max_content_bytes: usize,
+    refresh: Option<(usize, u64)>,
+    playground: bool,
+    router_args: RouterArgs,
+) -> Result<()> {
     // Setup tracing
     let level = if debug { "debug" } else { "info" };
     
@@ -146,23 +835,129 @@ async fn run_http_server(address: String, debug: bool) -> Result<()> {
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| format!("{},{}", level, env!("CARGO_CRATE_NAME")).into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_span_events(span_events(debug)))
         .init();
 
-    // Parse socket address
-    let addr: SocketAddr = address.parse()?;
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let listeners = bind_listeners(&addresses).await?;
+    for listener in &listeners {
+        tracing::info!("Access the Rust Documentation Server at http://{}/sse", listener.local_addr()?);
+        if playground {
+            tracing::info!("Try lookups in the playground at http://{}/", listener.local_addr()?);
+        }
+    }
 
-    tracing::debug!("Rust Documentation Server listening on {}", listener.local_addr()?);
-    tracing::info!("Access the Rust Documentation Server at http://{}/sse", addr);
-    
-    // Create app and run server
-    let app = cratedocs_mcp::transport::http_sse_server::App::new();
-    axum::serve(listener, app.router()).await?;
-    
+    let router = build_router(&router_args, max_content_bytes)?;
+    spawn_preload(&router, router_args.preload);
+    spawn_watch(&router, router_args.watch, router_args.watch_interval);
+    spawn_subscription_refresh(&router, router_args.subscription_refresh_interval);
+    if let Some((top, interval_secs)) = refresh {
+        spawn_popular_refresh(&router, top, interval_secs);
+    }
+
+    // Create app and run server; all sessions share the router's cache
+    let app = cratedocs_mcp::transport::http_sse_server::App::with_router(router.clone()).with_playground(playground);
+    let (stop, stopping) = tokio::sync::watch::channel(false);
+    let servers = listeners.into_iter().map(|listener| {
+        let mut stopping = stopping.clone();
+        axum::serve(listener, app.router())
+            .with_graceful_shutdown(async move {
+                let _ = stopping.wait_for(|stopping| *stopping).await;
+            })
+            .into_future()
+    });
+    let servers = futures::future::try_join_all(servers);
+    // SSE streams stay open until their clients leave, so they only get a grace period
+    let grace_period = async {
+        shutdown_signal().await;
+        tracing::info!("Shutting down after the requests in flight");
+        let _ = stop.send(true);
+        tokio::time::sleep(SHUTDOWN_GRACE_PERIOD).await;
+    };
+    tokio::select! {
+        result = servers => {
+            result?;
+        }
+        _ = grace_period => tracing::info!("Closing the connections still open after {:?}", SHUTDOWN_GRACE_PERIOD),
+    }
+
+    if let Err(e) = router.cache.flush().await {
+        tracing::warn!("Failed to flush the persistent cache: {}", e);
+    }
+    if let Err(e) = router.usage.save().await {
+        tracing::warn!("Failed to save the usage counts: {}", e);
+    }
+    tracing::info!("Documentation server stopped");
     Ok(())
 }
 
+/// Bind every address of the `--address` options
+///
+/// Binding `[::]` also accepts IPv4 connections on most systems, so a wildcard IPv4
+/// address on the same port is skipped when the IPv6 one already holds it.
+async fn bind_listeners(specs: &[String]) -> Result<Vec<tokio::net::TcpListener>> {
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for spec in specs {
+        for addr in resolve_bind_address(spec).await? {
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+    }
+    // Wildcard IPv6 first, so it takes the port before its IPv4 counterpart is tried
+    addrs.sort_by_key(|addr| !(addr.is_ipv6() && addr.ip().is_unspecified()));
+
+    let mut listeners: Vec<tokio::net::TcpListener> = Vec::new();
+    for addr in addrs {
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listeners.push(listener),
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && covered_by_dual_stack(addr, &listeners) => {
+                tracing::info!("Not binding {} separately: [::]:{} accepts IPv4 connections too", addr, addr.port());
+            }
+            Err(e) => return Err(anyhow::anyhow!("Failed to bind {}: {}", addr, e)),
+        }
+    }
+    Ok(listeners)
+}
+
+/// Whether a wildcard IPv4 address is already served by a bound wildcard IPv6 listener
+fn covered_by_dual_stack(addr: SocketAddr, listeners: &[tokio::net::TcpListener]) -> bool {
+    addr.is_ipv4()
+        && addr.ip().is_unspecified()
+        && listeners.iter().filter_map(|listener| listener.local_addr().ok()).any(|bound| {
+            bound.is_ipv6() && bound.ip().is_unspecified() && bound.port() == addr.port()
+        })
+}
+
+/// Resolve on SIGINT (Ctrl-C) or, on Unix, SIGTERM, which is how MCP clients and
+/// service managers stop servers
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
 /// Configuration for the test tool
 struct TestToolConfig {
     tool: String,
@@ -171,11 +966,176 @@ struct TestToolConfig {
     query: Option<String>,
     version: Option<String>,
     limit: Option<u32>,
+    backend: Option<String>,
     format: Option<String>,
     output: Option<String>,
+    max_download_bytes: usize,
+    stdin: bool,
     debug: bool,
 }
 
+/// Warm the on-disk cache with the docs of every crate locked in a Cargo.lock
+async fn warm_cache(lockfile: PathBuf, debug: bool, router_args: RouterArgs) -> Result<()> {
+    // The cache only outlives this command on disk
+    if router_args.cache_dir.is_none() {
+        return Err(anyhow::anyhow!("cache warm needs --cache-dir (or CRATEDOCS_CACHE_DIR) to keep what it fetches"));
+    }
+    let level = if debug { tracing::Level::DEBUG } else { tracing::Level::INFO };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let contents = std::fs::read_to_string(&lockfile)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", lockfile.display(), e))?;
+    let router = build_router(&router_args, 0)?;
+    let summary = router
+        .warm_from_lockfile(&contents)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    print!("{}", summary);
+    Ok(())
+}
+
+/// Write the entries of a cache directory to a bundle file
+fn export_cache(bundle: PathBuf, crates: Vec<String>, cache_dir: PathBuf) -> Result<()> {
+    let store = DiskStore::open(&cache_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to open cache directory {}: {}", cache_dir.display(), e))?;
+    let file = std::fs::File::create(&bundle).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", bundle.display(), e))?;
+    let exported = store
+        .export_bundle(std::io::BufWriter::new(file), &crates)
+        .map_err(|e| anyhow::anyhow!("Failed to export the cache: {}", e))?;
+    println!("Exported {} cache entries to {}", exported, bundle.display());
+    Ok(())
+}
+
+/// Add the entries of a bundle file to a cache directory
+fn import_cache(bundle: PathBuf, cache_dir: PathBuf) -> Result<()> {
+    let store = DiskStore::open(&cache_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to open cache directory {}: {}", cache_dir.display(), e))?;
+    let file = std::fs::File::open(&bundle).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", bundle.display(), e))?;
+    let imported = store
+        .import_bundle(std::io::BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("Failed to import {}: {}", bundle.display(), e))?;
+    println!("Imported {} cache entries into {}", imported, cache_dir.display());
+    Ok(())
+}
+
+/// Export the docs of the given crates, or those locked in a Cargo.lock, as markdown files
+async fn export_docs(
+    crates: Vec<String>,
+    lockfile: Option<PathBuf>,
+    out_dir: PathBuf,
+    max_pages: usize,
+    debug: bool,
+    router_args: RouterArgs,
+) -> Result<()> {
+    let level = if debug { tracing::Level::DEBUG } else { tracing::Level::INFO };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    let mut specs: Vec<(String, Option<String>)> = crates
+        .iter()
+        .map(|spec| match spec.split_once('@') {
+            Some((name, version)) => (name.trim().to_string(), Some(version.trim().to_string())),
+            None => (spec.trim().to_string(), None),
+        })
+        .collect();
+    if let Some(lockfile) = &lockfile {
+        let contents = std::fs::read_to_string(lockfile)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", lockfile.display(), e))?;
+        let packages = parse_lockfile(&contents).map_err(|e| anyhow::anyhow!(e))?;
+        specs.extend(
+            packages
+                .into_iter()
+                .filter(|package| package.from_crates_io)
+                .map(|package| (package.name, Some(package.version))),
+        );
+    }
+    if specs.is_empty() {
+        return Err(anyhow::anyhow!("Name the crates to export, or pass --lockfile"));
+    }
+
+    let router = build_router(&router_args, 0)?;
+    let mut failed = 0;
+    for (crate_name, version) in &specs {
+        match router.export_crate(crate_name, version.as_deref(), &out_dir, max_pages).await {
+            Ok(summary) => print!("{}", format_export_summary(&summary, "exported")),
+            Err(e) => {
+                failed += 1;
+                let message = e.to_string();
+                eprintln!("Failed to export {}: {}", crate_name, split_error_data(&message).0);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} crates could not be exported", failed, specs.len()));
+    }
+    Ok(())
+}
+
+/// Save the docs of crates to the mirror directory, at each of `versions` unless a
+/// crate is given as name@version
+async fn mirror_docs(
+    crates: Vec<String>,
+    versions: Vec<String>,
+    max_pages: usize,
+    debug: bool,
+    mut router_args: RouterArgs,
+) -> Result<()> {
+    let level = if debug { tracing::Level::DEBUG } else { tracing::Level::INFO };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .without_time()
+        .with_target(false)
+        .init();
+
+    if router_args.offline {
+        return Err(anyhow::anyhow!("The mirror command fetches from docs.rs, so it can't run --offline"));
+    }
+    // Fetch from upstream rather than serving what's already mirrored
+    let mirror_dir = router_args
+        .mirror_dir
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("Pass --mirror-dir or set CRATEDOCS_MIRROR_DIR to the directory to mirror to"))?;
+    let mirror = Mirror::new(&mirror_dir);
+
+    let specs: Vec<(String, Option<String>)> = crates
+        .iter()
+        .flat_map(|spec| match spec.split_once('@') {
+            Some((name, version)) => vec![(name.trim().to_string(), Some(version.trim().to_string()))],
+            None => versions
+                .iter()
+                .map(|version| {
+                    let version = Some(version.trim()).filter(|version| *version != "latest");
+                    (spec.trim().to_string(), version.map(str::to_string))
+                })
+                .collect(),
+        })
+        .collect();
+
+    let router = build_router(&router_args, 0)?;
+    let mut failed = 0;
+    for (crate_name, version) in &specs {
+        match router.mirror_crate(&mirror, crate_name, version.as_deref(), max_pages).await {
+            Ok(summary) => print!("{}", format_export_summary(&summary, "mirrored")),
+            Err(e) => {
+                failed += 1;
+                let message = e.to_string();
+                eprintln!("Failed to mirror {}: {}", crate_name, split_error_data(&message).0);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} crate versions could not be mirrored", failed, specs.len()));
+    }
+    Ok(())
+}
+
 /// Run a direct test of a documentation tool from the CLI
 async fn run_test_tool(config: TestToolConfig) -> Result<()> {
     let TestToolConfig {
@@ -185,8 +1145,11 @@ async fn run_test_tool(config: TestToolConfig) -> Result<()> {
         query,
         version,
         limit,
+        backend,
         format,
         output,
+        max_download_bytes,
+        stdin,
         debug,
     } = config;
     // Print help information if the tool is "help"
@@ -199,31 +1162,77 @@ async fn run_test_tool(config: TestToolConfig) -> Result<()> {
         println!("  cargo run --bin cratedocs -- test --tool lookup_item --crate-name serde --item-path Serialize --version 1.0.147");
         println!("  cargo run --bin cratedocs -- test --tool search_crates --query logger --limit 5");
         println!("  cargo run --bin cratedocs -- test --tool search_crates --query logger --format json");
+        println!("  cargo run --bin cratedocs -- test --tool search_crates --query logger --backend lib-rs");
         println!("  cargo run --bin cratedocs -- test --tool lookup_crate --crate-name tokio --output tokio-docs.md");
+        println!("  cargo run --bin cratedocs -- test --stdin < calls.jsonl > results.jsonl");
         println!("\nAvailable tools:");
         println!("  lookup_crate   - Look up documentation for a Rust crate");
         println!("  lookup_item    - Look up documentation for a specific item in a crate");
         println!("                   Format: 'module::path::ItemName' (e.g., 'sync::mpsc::Sender')");
         println!("                   The tool will try to detect if it's a struct, enum, trait, fn, or macro");
-        println!("  search_crates  - Search for crates on crates.io");
+        println!("                   (including attribute and derive macros)");
+        println!("  search_crates  - Search for crates on crates.io or lib.rs (--backend)");
         println!("  help           - Show this help information");
         println!("\nOutput options:");
-        println!("  --format       - Output format: markdown (default), text, json");
+        println!("  --format       - Output format: markdown (default), text, html, json");
         println!("  --output       - Write output to a file instead of stdout");
+        println!("\nBatch mode:");
+        println!("  --stdin        - Read one tool call per line from stdin, e.g.");
+        println!("                   {{\"id\": 1, \"tool\": \"lookup_crate\", \"arguments\": {{\"crate_name\": \"serde\"}}}}");
+        println!("                   and write one JSON result per line to stdout, with the call's id");
+        println!("                   and either the result's content or an error");
         return Ok(());
     }
     
-    // Set up console logging
+    // Set up console logging, on stderr in batch mode where stdout carries the results
     let level = if debug { tracing::Level::DEBUG } else { tracing::Level::INFO };
     
-    tracing_subscriber::fmt()
+    let logging = tracing_subscriber::fmt()
         .with_max_level(level)
         .without_time()
-        .with_target(false)
-        .init();
+        .with_target(false);
+    if stdin {
+        logging.with_writer(std::io::stderr).init();
+    } else {
+        logging.init();
+    }
 
     // Create router instance
-    let router = DocRouter::new();
+    let router = build_router(&RouterArgs {
+        max_download_bytes,
+        max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+        circuit_failure_threshold: DEFAULT_CIRCUIT_FAILURE_THRESHOLD,
+        circuit_cooldown: DEFAULT_CIRCUIT_COOLDOWN.as_secs(),
+        user_agent: None,
+        contact: None,
+        crates_io_token: None,
+        prefetch: false,
+        preload: Vec::new(),
+        config: None,
+        cache_dir: None,
+        cache_max_entry_bytes: None,
+        cache_oversize_policy: CacheOversizePolicy::Truncate,
+        search_backend: CliSearchBackend::CratesIo,
+        watch: Vec::new(),
+        watch_interval: DEFAULT_WATCH_INTERVAL_SECS,
+        subscription_refresh_interval: DEFAULT_SUBSCRIPTION_REFRESH_SECS,
+        index_cache_ttl: DEFAULT_INDEX_TTL.as_secs(),
+        index_cache_bytes: DEFAULT_INDEX_CACHE_BYTES,
+        local_docs_root: Vec::new(),
+        max_content_parts: 1,
+        tool_timeout: Vec::new(),
+        probe_order: Vec::new(),
+        allow_crates: Vec::new(),
+        deny_crates: Vec::new(),
+        disable_sources: Vec::new(),
+        mirror_dir: None,
+        offline: false,
+        slow_call_threshold: None,
+    }, 0)?;
+
+    if stdin {
+        return run_batch(&router).await;
+    }
     
     tracing::info!("Testing tool: {}", tool);
     
@@ -260,6 +1269,7 @@ async fn run_test_tool(config: TestToolConfig) -> Result<()> {
             json!({
                 "query": query,
                 "limit": limit,
+                "backend": backend,
             })
         },
         _ => return Err(anyhow::anyhow!("Unknown tool: {}", tool)),
@@ -272,7 +1282,15 @@ async fn run_test_tool(config: TestToolConfig) -> Result<()> {
     let result = match router.call_tool(&tool, arguments).await {
         Ok(result) => result,
         Err(e) => {
-            eprintln!("\nERROR: {}", e);
+            let message = e.to_string();
+            let (message, data) = split_error_data(&message);
+            eprintln!("\nERROR: {}", message);
+            if let Some(data) = data {
+                eprintln!("Code: {}", data.code.as_str());
+                for suggestion in &data.suggestions {
+                    eprintln!("  - {}", suggestion);
+                }
+            }
             eprintln!("\nTip: Try these suggestions:");
             eprintln!("  - For crate docs: cargo run --bin cratedocs -- test --tool lookup_crate --crate-name tokio");
             eprintln!("  - For item lookup: cargo run --bin cratedocs -- test --tool lookup_item --crate-name tokio --item-path sync::mpsc::Sender");
@@ -332,20 +1350,10 @@ async fn run_test_tool(config: TestToolConfig) -> Result<()> {
                                 Err(_) => content_str,
                             }
                         } else {
-                            // For markdown content, use a simple approach to convert to plain text
-                            // This is a very basic conversion - more sophisticated would need a proper markdown parser
-                            content_str
-                                .replace("# ", "")
-                                .replace("## ", "")
-                                .replace("### ", "")
-                                .replace("#### ", "")
-                                .replace("##### ", "")
-                                .replace("###### ", "")
-                                .replace("**", "")
-                                .replace("*", "")
-                                .replace("`", "")
+                            markdown_to_text(&content_str)
                         }
                     },
+                    "html" => markdown_to_html(&content_str),
                     _ => content_str, // Default to original markdown for "markdown" or any other format
                 };
                 
@@ -384,4 +1392,30 @@ async fn run_test_tool(config: TestToolConfig) -> Result<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Run the tool calls read from stdin one after another, writing each result as a
+/// JSON line to stdout as soon as it is done
+async fn run_batch(router: &DocRouter) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let mut lines = BufReader::new(stdin()).lines();
+    let mut out = stdout();
+    let (mut calls, mut failed) = (0, 0);
+    let mut line_number = 0;
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        let Some(result) = run_batch_line(router, line_number, &line).await else {
+            continue;
+        };
+        calls += 1;
+        if result.get("error").is_some() {
+            failed += 1;
+        }
+        out.write_all(result.to_string().as_bytes()).await?;
+        out.write_all(b"\n").await?;
+        out.flush().await?;
+    }
+    tracing::info!("Ran {} tool calls, {} failed", calls, failed);
+    Ok(())
+}
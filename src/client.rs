@@ -0,0 +1,126 @@
+//! Typed access to the documentation lookups, for Rust programs embedding the
+//! fetching and caching without going through MCP
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), mcp_core::ToolError> {
+//! use cratedocs_mcp::client::DocsClient;
+//!
+//! let client = DocsClient::new();
+//! let doc = client.lookup_item("tokio", "sync::Mutex", None).await?;
+//! println!("{} {}: {}", doc.crate_name, doc.version, doc.source_url);
+//! # Ok(())
+//! # }
+//! ```
+
+use mcp_core::ToolError;
+use serde::Serialize;
+
+use crate::tools::docs::docs::{split_doc_header, DocHeader};
+use crate::tools::docs::search::{CrateSearchResult, CrateSearchResults};
+use crate::tools::DocRouter;
+
+/// Results of a crates.io search, most relevant first
+pub type SearchResults = CrateSearchResults;
+
+/// One crate of `SearchResults`
+pub type SearchResult = CrateSearchResult;
+
+/// A crate's documentation, as `lookup_crate` returns it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CrateDoc {
+    pub crate_name: String,
+    /// Version the docs are of, `latest` when it couldn't be resolved
+    pub version: String,
+    /// Page the docs were converted from
+    pub source_url: String,
+    /// Why the docs came from the crates.io README or the repository instead of docs.rs
+    pub fallback: Option<String>,
+    /// Whether crates.io couldn't be reached, so `version` may not be the newest release
+    pub stale: bool,
+    pub markdown: String,
+}
+
+/// An item's documentation, as `lookup_item` returns it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ItemDoc {
+    pub crate_name: String,
+    /// Path of the item below the crate, as it was asked for
+    pub item_path: String,
+    /// Version the docs are of, `latest` when it couldn't be resolved
+    pub version: String,
+    /// Page the docs were converted from
+    pub source_url: String,
+    /// Whether crates.io couldn't be reached, so `version` may not be the newest release
+    pub stale: bool,
+    pub markdown: String,
+}
+
+/// Looks up documentation through a `DocRouter`, with its fetcher, sources and cache,
+/// returning typed results instead of tool output
+///
+/// Clones share the router's cache. Tool-level behavior such as session defaults,
+/// usage counts and result size limits doesn't apply.
+#[derive(Clone, Default)]
+pub struct DocsClient {
+    router: DocRouter,
+}
+
+impl DocsClient {
+    /// A client fetching from the upstream documentation sources with a fresh in-memory cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A client looking up through `router`, e.g. one with a disk cache or a custom fetcher
+    pub fn with_router(router: DocRouter) -> Self {
+        Self { router }
+    }
+
+    pub fn router(&self) -> &DocRouter {
+        &self.router
+    }
+
+    /// The documentation of a crate, of its latest release when no version is given
+    pub async fn lookup_crate(&self, crate_name: &str, version: Option<&str>) -> Result<CrateDoc, ToolError> {
+        let doc = self
+            .router
+            .lookup_crate(crate_name.to_string(), version.map(str::to_string))
+            .await?;
+        let header = parse_header(&doc)?;
+        Ok(CrateDoc {
+            crate_name: header.crate_name.to_string(),
+            version: header.version.to_string(),
+            source_url: header.source_url.to_string(),
+            fallback: header.fallback.map(str::to_string),
+            stale: header.stale,
+            markdown: header.markdown.to_string(),
+        })
+    }
+
+    /// The documentation of an item, e.g. `sync::Mutex` of `tokio`, of the crate's latest
+    /// release when no version is given
+    pub async fn lookup_item(&self, crate_name: &str, item_path: &str, version: Option<&str>) -> Result<ItemDoc, ToolError> {
+        let doc = self
+            .router
+            .lookup_item(crate_name.to_string(), item_path.to_string(), version.map(str::to_string))
+            .await?;
+        let header = parse_header(&doc)?;
+        Ok(ItemDoc {
+            crate_name: header.crate_name.to_string(),
+            item_path: item_path.to_string(),
+            version: header.version.to_string(),
+            source_url: header.source_url.to_string(),
+            stale: header.stale,
+            markdown: header.markdown.to_string(),
+        })
+    }
+
+    /// Search crates.io for up to `limit` crates (at most 100)
+    pub async fn search(&self, query: &str, limit: u32) -> Result<SearchResults, ToolError> {
+        self.router.search_crates_io(query, limit.min(100)).await
+    }
+}
+
+fn parse_header(doc: &str) -> Result<DocHeader<'_>, ToolError> {
+    split_doc_header(doc).ok_or_else(|| ToolError::ExecutionError("Documentation is missing its version and source".to_string()))
+}
@@ -1,3 +1,3 @@
+pub mod client;
 pub mod tools;
 pub mod transport;
-
@@ -0,0 +1,75 @@
+use mcp_core::Content;
+use mcp_server::Router;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::docs::DocRouter;
+use super::errors::split_error_data;
+
+/// One tool invocation of a JSONL batch, e.g.
+/// `{"id": 1, "tool": "lookup_crate", "arguments": {"crate_name": "serde"}}`
+#[derive(Clone, Debug, Deserialize)]
+pub struct BatchCall {
+    /// Any JSON value, echoed in the result to match it to its invocation
+    #[serde(default)]
+    pub id: Option<Value>,
+    /// Name of the tool; `name` is accepted too, as in MCP `tools/call` requests
+    #[serde(alias = "name")]
+    pub tool: String,
+    #[serde(default = "empty_arguments")]
+    pub arguments: Value,
+}
+
+fn empty_arguments() -> Value {
+    json!({})
+}
+
+/// Run the tool invocation on one line of a JSONL batch and return its result, to be
+/// written as one JSON line, or `None` for a blank line
+///
+/// Results carry the 1-based `line` number, the invocation's `id` and `tool`, and either
+/// the text `content` of the result or an `error` with its `message` and, when the tool
+/// gave one, its error `code` and `suggestions`. Lines that aren't a valid invocation
+/// give an error result too, so every line of input has a line of output.
+pub async fn run_batch_line(router: &DocRouter, line_number: usize, line: &str) -> Option<Value> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    let call: BatchCall = match serde_json::from_str(line) {
+        Ok(call) => call,
+        Err(e) => {
+            let result = json!({
+                "line": line_number,
+                "error": { "message": format!("Invalid tool invocation: {}", e) },
+            });
+            return Some(result);
+        }
+    };
+
+    let mut result = json!({ "line": line_number, "id": call.id, "tool": call.tool });
+    match router.call_tool(&call.tool, call.arguments).await {
+        Ok(contents) => {
+            let texts: Vec<String> = contents
+                .into_iter()
+                .filter_map(|content| match content {
+                    Content::Text(text) => Some(text.text),
+                    _ => None,
+                })
+                .collect();
+            result["content"] = json!(texts);
+        }
+        Err(e) => {
+            let message = e.to_string();
+            let (message, data) = split_error_data(&message);
+            let mut error = json!({ "message": message });
+            if let Some(data) = data {
+                error["code"] = json!(data.code.as_str());
+                if !data.suggestions.is_empty() {
+                    error["suggestions"] = json!(data.suggestions);
+                }
+            }
+            result["error"] = error;
+        }
+    }
+    Some(result)
+}
@@ -0,0 +1,134 @@
+use scraper::{Html, Selector};
+
+/// Editions covered by the Edition Guide, each with a `rust-{edition}/` chapter
+pub const EDITIONS: [&str; 4] = ["2015", "2018", "2021", "2024"];
+
+/// An official Rust mdBook
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Book {
+    EditionGuide,
+    Cargo,
+    Nomicon,
+    /// Published on rust-lang.github.io rather than doc.rust-lang.org
+    AsyncBook,
+}
+
+impl Book {
+    pub const ALL: [Book; 4] = [Self::EditionGuide, Self::Cargo, Self::Nomicon, Self::AsyncBook];
+
+    /// Parse the name used by the `book` tool argument, which is the book's path
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|book| book.path() == name)
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::EditionGuide => "The Rust Edition Guide",
+            Self::Cargo => "The Cargo Book",
+            Self::Nomicon => "The Rustonomicon",
+            Self::AsyncBook => "Asynchronous Programming in Rust",
+        }
+    }
+
+    /// Location of the book below its host
+    pub fn path(self) -> &'static str {
+        match self {
+            Self::EditionGuide => "edition-guide",
+            Self::Cargo => "cargo",
+            Self::Nomicon => "nomicon",
+            Self::AsyncBook => "async-book",
+        }
+    }
+}
+
+/// A chapter from a book's table of contents
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    /// Page path relative to the book root, e.g. `rust-2021/reserving-syntax.html`
+    pub path: String,
+}
+
+/// Read the chapters from the sidebar of an mdBook page
+///
+/// mdBook 0.4.41 and later move the sidebar to `toc.html`; older versions
+/// inline it in every page. Both use `ol.chapter` lists of links.
+pub fn parse_chapters(html: &str) -> Vec<Chapter> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse("ol.chapter li a[href]") else {
+        return Vec::new();
+    };
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for link in document.select(&selector) {
+        let Some(href) = link.value().attr("href") else { continue };
+        if href.contains("://") || href.starts_with('#') {
+            continue;
+        }
+        let path = href.trim_start_matches("./").split('#').next().unwrap_or_default().to_string();
+        // Numbered chapters render as "<strong>3.1.</strong> Title"
+        let text = link.text().collect::<String>();
+        let title = text
+            .trim()
+            .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if path.is_empty() || title.is_empty() || chapters.iter().any(|chapter| chapter.path == path) {
+            continue;
+        }
+        chapters.push(Chapter { title, path });
+    }
+    chapters
+}
+
+/// The chapter whose title (or, failing that, path) best matches `topic`
+///
+/// An exact title match wins; otherwise the chapter sharing the most words with
+/// the topic, preferring shorter titles and earlier chapters on ties.
+pub fn find_chapter<'a>(chapters: &'a [Chapter], topic: &str) -> Option<&'a Chapter> {
+    let topic_words = words(topic);
+    if let Some(exact) = chapters.iter().find(|chapter| words(&chapter.title) == topic_words) {
+        return Some(exact);
+    }
+    chapters
+        .iter()
+        .filter_map(|chapter| {
+            let title_words = words(&chapter.title);
+            let path_words = words(&chapter.path);
+            let score = topic_words
+                .iter()
+                .filter(|word| title_words.contains(word) || path_words.contains(word))
+                .count();
+            (score > 0).then_some((score, title_words.len(), chapter))
+        })
+        // Highest score, then shortest title; `min_by` keeps the first of equal elements
+        .min_by(|(a_score, a_len, _), (b_score, b_len, _)| b_score.cmp(a_score).then(a_len.cmp(b_len)))
+        .map(|(_, _, chapter)| chapter)
+}
+
+// Lowercased words of a title, topic or path, ignoring punctuation and the `html` extension
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty() && *word != "html")
+        .map(|word| word.to_ascii_lowercase())
+        .collect()
+}
+
+/// The chapter text of an mdBook page, without the sidebar, menu and navigation
+pub fn chapter_content(html: &str) -> String {
+    let document = Html::parse_document(html);
+    Selector::parse("main")
+        .ok()
+        .and_then(|selector| document.select(&selector).next().map(|main| main.inner_html()))
+        .unwrap_or_else(|| html.to_string())
+}
+
+/// Render a book's chapters as a markdown list
+pub fn format_chapter_list(book: Book, chapters: &[Chapter]) -> String {
+    let mut markdown = format!("# {}\n\n", book.title());
+    for chapter in chapters {
+        markdown.push_str(&format!("- {} (`{}`)\n", chapter.title, chapter.path));
+    }
+    markdown.push_str("\nPass a chapter title as `topic` to read it.\n");
+    markdown
+}
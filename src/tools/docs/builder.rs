@@ -0,0 +1,296 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use mcp_server::Router;
+use reqwest::Client;
+
+use super::docs::{DocCache, DocRouter};
+use super::fetcher::{BaseUrls, DocFetcher, ReqwestFetcher};
+use super::local::LocalDocs;
+use super::mirror::Mirror;
+use super::overrides::CrateOverrides;
+use super::policy::CratePolicy;
+use super::prefetch::PrefetchConfig;
+use super::search::SearchBackend;
+use super::search_index::SearchIndexCache;
+use super::sources::{DocSource, SourceRegistry, LOCAL_SOURCE};
+use super::usage::UsageStats;
+
+/// Configures a `DocRouter` together with the fetcher and sources under it (see
+/// `DocRouter::builder`)
+///
+/// Settings left alone keep the defaults `DocRouter::new` has. `build` wraps the
+/// fetcher in the order that keeps every setting effective: mirrored and local docs
+/// ahead of the upstream sources, and the crate policy around all of them.
+#[derive(Default)]
+pub struct DocRouterBuilder {
+    client: Option<Client>,
+    request_timeout: Option<Duration>,
+    base_urls: Option<BaseUrls>,
+    user_agent: Option<String>,
+    crates_io_token: Option<String>,
+    max_response_bytes: Option<usize>,
+    max_concurrent_requests: Option<usize>,
+    circuit_breaker: Option<(u32, Duration)>,
+    fetcher: Option<Arc<dyn DocFetcher>>,
+    sources: Vec<Arc<dyn DocSource>>,
+    disabled_sources: Vec<String>,
+    mirror: Option<(Mirror, bool)>,
+    local_docs: Option<LocalDocs>,
+    crate_policy: CratePolicy,
+    crate_overrides: CrateOverrides,
+    cache: Option<DocCache>,
+    latest_ttl: Option<Duration>,
+    usage: Option<UsageStats>,
+    search_backend: Option<SearchBackend>,
+    search_index_cache: Option<SearchIndexCache>,
+    prefetch: Option<PrefetchConfig>,
+    max_content_bytes: Option<usize>,
+    max_content_parts: Option<usize>,
+    probe_order: Option<Vec<&'static str>>,
+    tool_timeouts: Vec<(String, Duration)>,
+    slow_call_threshold: Option<Duration>,
+}
+
+impl DocRouterBuilder {
+    /// Send upstream requests through `client` instead of a default one, e.g. one with
+    /// a proxy or custom TLS roots; `request_timeout` doesn't apply to it
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Fail upstream requests that take longer than `timeout`
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Talk to these hosts instead of docs.rs, crates.io and the others, e.g. a mock server
+    pub fn base_urls(mut self, base_urls: BaseUrls) -> Self {
+        self.base_urls = Some(base_urls);
+        self
+    }
+
+    /// Send `user_agent` instead of the default User-Agent (see `user_agent()`)
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    /// Authenticate crates.io API requests with `token`
+    pub fn crates_io_token(mut self, token: String) -> Self {
+        self.crates_io_token = Some(token);
+        self
+    }
+
+    /// Abort downloads whose body exceeds `max_response_bytes`
+    pub fn max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = Some(max_response_bytes);
+        self
+    }
+
+    /// Keep at most `limit` upstream requests in flight at once (0 for no limit)
+    pub fn max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = Some(limit);
+        self
+    }
+
+    /// Stop sending requests to an upstream host for `cooldown` once `threshold`
+    /// requests to it failed in a row
+    pub fn circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breaker = Some((threshold, cooldown));
+        self
+    }
+
+    /// Fetch through `fetcher` instead of a `ReqwestFetcher`, ignoring the HTTP settings
+    pub fn fetcher(mut self, fetcher: Arc<dyn DocFetcher>) -> Self {
+        self.fetcher = Some(fetcher);
+        self
+    }
+
+    /// Ask `source` before the upstream sources, after the sources added earlier
+    pub fn source(mut self, source: Arc<dyn DocSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Fetch nothing from the sources named in `names` (see `UPSTREAM_SOURCES` and `LOCAL_SOURCE`)
+    pub fn disable_sources(mut self, names: &[String]) -> Self {
+        self.disabled_sources.extend(names.iter().map(|name| name.trim().to_string()));
+        self
+    }
+
+    /// Serve crate docs saved by `cratedocs mirror` ahead of docs.rs (see `DocRouter::with_mirror`)
+    pub fn mirror(mut self, mirror: Mirror, offline: bool) -> Self {
+        self.mirror = Some((mirror, offline));
+        self
+    }
+
+    /// Enable `build_local_docs` and serve the crates it builds (see `DocRouter::with_local_docs`)
+    pub fn local_docs(mut self, local_docs: LocalDocs) -> Self {
+        self.local_docs = Some(local_docs);
+        self
+    }
+
+    /// Refuse crates `policy` doesn't allow (see `DocRouter::with_crate_policy`)
+    pub fn crate_policy(mut self, policy: CratePolicy) -> Self {
+        self.crate_policy = policy;
+        self
+    }
+
+    /// Fill per-crate defaults into tool calls, and read the docs.rs targets they name
+    pub fn crate_overrides(mut self, crate_overrides: CrateOverrides) -> Self {
+        self.crate_overrides = crate_overrides;
+        self
+    }
+
+    pub fn cache(mut self, cache: DocCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set how long "latest" keeps resolving to the same version
+    pub fn latest_ttl(mut self, ttl: Duration) -> Self {
+        self.latest_ttl = Some(ttl);
+        self
+    }
+
+    pub fn usage(mut self, usage: UsageStats) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn search_backend(mut self, backend: SearchBackend) -> Self {
+        self.search_backend = Some(backend);
+        self
+    }
+
+    pub fn search_index_cache(mut self, search_indexes: SearchIndexCache) -> Self {
+        self.search_index_cache = Some(search_indexes);
+        self
+    }
+
+    pub fn prefetch(mut self, config: PrefetchConfig) -> Self {
+        self.prefetch = Some(config);
+        self
+    }
+
+    /// Cap the size of each tool result at `max_content_bytes`, in up to `max_content_parts` blocks
+    pub fn max_content(mut self, max_content_bytes: usize, max_content_parts: usize) -> Self {
+        self.max_content_bytes = Some(max_content_bytes);
+        self.max_content_parts = Some(max_content_parts);
+        self
+    }
+
+    pub fn probe_order(mut self, probe_order: Vec<&'static str>) -> Self {
+        self.probe_order = Some(probe_order);
+        self
+    }
+
+    /// Fail calls of `tool_name` that take longer than `timeout`
+    pub fn tool_timeout(mut self, tool_name: &str, timeout: Duration) -> Self {
+        self.tool_timeouts.push((tool_name.to_string(), timeout));
+        self
+    }
+
+    /// Log calls taking `threshold` or longer at WARN
+    pub fn slow_call_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_call_threshold = Some(threshold);
+        self
+    }
+
+    /// The configured router, failing on sources or tools that don't exist
+    pub fn build(self) -> Result<DocRouter, String> {
+        let fetcher = match self.fetcher {
+            Some(fetcher) => fetcher,
+            None => {
+                let client = match (self.client, self.request_timeout) {
+                    (Some(client), _) => client,
+                    (None, Some(timeout)) => Client::builder()
+                        .timeout(timeout)
+                        .build()
+                        .map_err(|e| format!("Failed to create the HTTP client: {}", e))?,
+                    (None, None) => Client::new(),
+                };
+                let mut fetcher = ReqwestFetcher::new(client).with_doc_targets(self.crate_overrides.targets());
+                if let Some(base_urls) = self.base_urls {
+                    fetcher = fetcher.with_base_urls(base_urls);
+                }
+                if let Some(user_agent) = self.user_agent {
+                    fetcher = fetcher.with_user_agent(user_agent);
+                }
+                if let Some(token) = self.crates_io_token {
+                    fetcher = fetcher.with_crates_io_token(token);
+                }
+                if let Some(max_response_bytes) = self.max_response_bytes {
+                    fetcher = fetcher.with_max_response_bytes(max_response_bytes);
+                }
+                if let Some(limit) = self.max_concurrent_requests {
+                    fetcher = fetcher.with_max_concurrent_requests(limit);
+                }
+                if let Some((threshold, cooldown)) = self.circuit_breaker {
+                    fetcher = fetcher.with_circuit_breaker(threshold, cooldown);
+                }
+                Arc::new(fetcher)
+            }
+        };
+
+        // Local docs aren't in the registry; they're served ahead of it below when enabled
+        let local_enabled = !self.disabled_sources.iter().any(|name| name == LOCAL_SOURCE);
+        let disabled: Vec<String> = self.disabled_sources.into_iter().filter(|name| name != LOCAL_SOURCE).collect();
+        let mut sources = SourceRegistry::upstream(fetcher);
+        for source in self.sources.into_iter().rev() {
+            sources = sources.with_source_first(source);
+        }
+        let sources = sources.disable(&disabled).map_err(|e| format!("Invalid disabled sources: {}", e))?;
+
+        let mut router = DocRouter::with_sources(sources)
+            .with_crate_overrides(self.crate_overrides)
+            .with_search_backend(self.search_backend.unwrap_or_default());
+        if let Some(cache) = self.cache {
+            router = router.with_cache(cache);
+        }
+        if let Some(ttl) = self.latest_ttl {
+            router = router.with_latest_ttl(ttl);
+        }
+        if let Some(usage) = self.usage {
+            router = router.with_usage(usage);
+        }
+        if let Some(search_indexes) = self.search_index_cache {
+            router = router.with_search_index_cache(search_indexes);
+        }
+        if let Some(config) = self.prefetch {
+            router = router.with_prefetch(config);
+        }
+        // Below local docs, so local builds are still served offline
+        if let Some((mirror, offline)) = self.mirror {
+            router = router.with_mirror(mirror, offline);
+        }
+        if let Some(local_docs) = self.local_docs.filter(|_| local_enabled) {
+            router = router.with_local_docs(local_docs);
+        }
+        if let Some(max_content_bytes) = self.max_content_bytes {
+            router = router.with_max_content_bytes(max_content_bytes);
+        }
+        if let Some(max_content_parts) = self.max_content_parts {
+            router = router.with_max_content_parts(max_content_parts);
+        }
+        if let Some(probe_order) = self.probe_order {
+            router = router.with_probe_order(probe_order);
+        }
+        if !self.crate_policy.is_empty() {
+            router = router.with_crate_policy(self.crate_policy);
+        }
+        if let Some(threshold) = self.slow_call_threshold {
+            router = router.with_slow_call_threshold(threshold);
+        }
+        for (tool_name, timeout) in self.tool_timeouts {
+            if !router.list_tools().iter().any(|tool| tool.name == tool_name) {
+                return Err(format!("Unknown tool in a tool timeout: {}", tool_name));
+            }
+            router = router.with_tool_timeout(&tool_name, timeout);
+        }
+        Ok(router)
+    }
+}
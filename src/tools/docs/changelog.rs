@@ -0,0 +1,156 @@
+use serde_json::Value;
+
+use super::sections::headings;
+use super::versions::Version;
+
+/// Maximum number of releases `release_notes` returns for a version range
+pub const MAX_RELEASE_NOTES: usize = 20;
+
+/// Changelog files looked for in a crate's repository, in order; `{crate}` stands for
+/// the crate's directory in a workspace repository
+pub const CHANGELOG_FILES: [&str; 4] = ["CHANGELOG.md", "{crate}/CHANGELOG.md", "CHANGES.md", "RELEASES.md"];
+
+/// The notes of one release of a crate, from its changelog or a release on its code host
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReleaseNotes {
+    pub version: Version,
+    /// Changelog heading or release name, e.g. `[0.7.0] - 2024-01-02`
+    pub title: String,
+    pub notes: String,
+}
+
+/// Parse a version the way changelogs write them, where `0.7` stands for `0.7.0`
+pub fn parse_loose_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let numbers_end = version.find(['-', '+']).unwrap_or(version.len());
+    let (numbers, rest) = version.split_at(numbers_end);
+    if numbers.matches('.').count() == 1 {
+        Version::parse(&format!("{}.0{}", numbers, rest))
+    } else {
+        Version::parse(version)
+    }
+}
+
+// The first version in a heading or tag, e.g. `0.7.1` in "[0.7.1] - 2024-01-02", "v0.7.1"
+// or "tokio-0.7.1", along with the text before it
+fn version_in(text: &str) -> Option<(&str, Version)> {
+    let bytes = text.as_bytes();
+    for (start, c) in text.char_indices() {
+        if !c.is_ascii_digit() || (start > 0 && (bytes[start - 1].is_ascii_digit() || bytes[start - 1] == b'.')) {
+            continue;
+        }
+        let end = text[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')))
+            .map_or(text.len(), |len| start + len);
+        let candidate = text[start..end].trim_end_matches(['.', '-', '+']);
+        // Dates such as 2024-01-02 have no dot and don't parse
+        if let Some(version) = parse_loose_version(candidate) {
+            return Some((&text[..start], version));
+        }
+    }
+    None
+}
+
+/// Split a markdown changelog into releases, newest first as changelogs are written
+///
+/// Releases are the headings naming a version, taken at the highest heading level
+/// that has any, so that e.g. `### Fixed in 0.7.1` below `## 0.7.1` isn't a release
+/// of its own. "Unreleased" sections are skipped.
+pub fn parse_changelog(markdown: &str) -> Vec<ReleaseNotes> {
+    let versioned: Vec<_> = headings(markdown)
+        .into_iter()
+        .filter_map(|heading| Some((version_in(&heading.title)?.1, heading)))
+        .collect();
+    let Some(level) = versioned.iter().map(|(_, heading)| heading.level).min() else {
+        return Vec::new();
+    };
+    versioned
+        .into_iter()
+        .filter(|(_, heading)| heading.level == level)
+        .map(|(version, heading)| ReleaseNotes {
+            version,
+            title: heading.title.clone(),
+            notes: heading.body(markdown).to_string(),
+        })
+        .collect()
+}
+
+/// Releases of a crate from a GitHub or GitLab releases API response
+///
+/// Tags must name only the version (`0.7.1`, `v0.7.1`) or prefix it with the crate
+/// name (`tokio-0.7.1`, `tokio@0.7.1`), so releases of other crates in a workspace
+/// repository are skipped, as are drafts.
+pub fn parse_repository_releases(json: &str, crate_name: &str) -> Option<Vec<ReleaseNotes>> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let releases = value.as_array()?;
+    Some(
+        releases
+            .iter()
+            .filter(|release| !release.get("draft").and_then(|v| v.as_bool()).unwrap_or(false))
+            .filter_map(|release| {
+                let tag = release.get("tag_name")?.as_str()?;
+                let (prefix, version) = version_in(tag)?;
+                if !is_tag_prefix_of(prefix, crate_name) {
+                    return None;
+                }
+                // GitHub calls the notes `body`, GitLab `description`
+                let notes = release
+                    .get("body")
+                    .or_else(|| release.get("description"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let title = release
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or(tag);
+                Some(ReleaseNotes {
+                    version,
+                    title: title.trim().to_string(),
+                    notes: notes.trim().to_string(),
+                })
+            })
+            .collect(),
+    )
+}
+
+// Whether the text before the version in a tag leaves it a tag of `crate_name`
+fn is_tag_prefix_of(prefix: &str, crate_name: &str) -> bool {
+    let prefix = prefix.trim_end_matches(['v', 'V']);
+    prefix.is_empty()
+        || prefix
+            .strip_suffix(['-', '_', '@', '/'])
+            .is_some_and(|name| name.eq_ignore_ascii_case(crate_name))
+}
+
+/// The releases after `from` up to and including `to` (any newer one without `to`),
+/// newest first
+pub fn releases_between<'a>(releases: &'a [ReleaseNotes], from: &Version, to: Option<&Version>) -> Vec<&'a ReleaseNotes> {
+    let mut selected: Vec<&ReleaseNotes> = releases
+        .iter()
+        .filter(|release| &release.version > from && to.map_or(true, |to| &release.version <= to))
+        .collect();
+    selected.sort_by(|a, b| b.version.cmp(&a.version));
+    // Changelogs sometimes repeat a version, e.g. in a summary at the top
+    selected.dedup_by(|a, b| a.version == b.version);
+    selected
+}
+
+/// Render releases under a title, noting the source they were read from and how
+/// many older releases were left out
+pub fn format_changes(title: &str, source: &str, releases: &[&ReleaseNotes], omitted: usize) -> String {
+    let mut markdown = format!("# {}\nSource: {}\n", title, source);
+    for release in releases {
+        markdown.push_str(&format!("\n## {}\n", release.title));
+        if !release.notes.is_empty() {
+            markdown.push_str(&format!("\n{}\n", release.notes));
+        }
+    }
+    if omitted > 0 {
+        markdown.push_str(&format!(
+            "\n{} older releases in the range are not shown; narrow the range to see them.\n",
+            omitted
+        ));
+    }
+    markdown
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// Consecutive failures after which requests to an upstream stop being sent
+pub const DEFAULT_CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long an upstream is left alone once its circuit opens
+pub const DEFAULT_CIRCUIT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Per-upstream circuit breaker, so an outage fails fast instead of stacking up timeouts
+///
+/// After `threshold` failures in a row the circuit of that upstream opens and requests
+/// are refused for `cooldown`. The first request after the cooldown is let through as a
+/// trial: a success closes the circuit, another failure opens it again right away.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    /// Shared by every clone, keyed by upstream host
+    circuits: Arc<Mutex<std::collections::HashMap<String, Circuit>>>,
+}
+
+#[derive(Default)]
+struct Circuit {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CIRCUIT_FAILURE_THRESHOLD, DEFAULT_CIRCUIT_COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    /// Open a circuit after `threshold` consecutive failures (0 never opens one)
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            circuits: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        }
+    }
+
+    /// `Err` with the time left until the next trial while the circuit of `upstream` is open
+    pub async fn check(&self, upstream: &str) -> Result<(), Duration> {
+        let mut circuits = self.circuits.lock().await;
+        let Some(circuit) = circuits.get_mut(upstream) else {
+            return Ok(());
+        };
+        match circuit.open_until {
+            Some(until) if until > Instant::now() => Err(until - Instant::now()),
+            Some(_) => {
+                // Let one trial through; concurrent callers keep waiting for its outcome
+                circuit.open_until = Some(Instant::now() + self.cooldown);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub async fn record_success(&self, upstream: &str) {
+        let mut circuits = self.circuits.lock().await;
+        if circuits.remove(upstream).is_some_and(|circuit| circuit.open_until.is_some()) {
+            tracing::info!("{} is responding again; closing its circuit", upstream);
+        }
+    }
+
+    /// Count a failure, returning whether it opened the circuit
+    pub async fn record_failure(&self, upstream: &str) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+        let mut circuits = self.circuits.lock().await;
+        let circuit = circuits.entry(upstream.to_string()).or_default();
+        circuit.failures = circuit.failures.saturating_add(1);
+        if circuit.failures < self.threshold {
+            return false;
+        }
+        circuit.open_until = Some(Instant::now() + self.cooldown);
+        tracing::warn!(
+            "{} failed {} times in a row; refusing requests to it for {}s",
+            upstream,
+            circuit.failures,
+            self.cooldown.as_secs()
+        );
+        true
+    }
+
+}
@@ -0,0 +1,163 @@
+use super::sections::declaration;
+
+/// Lines of unchanged context shown around each change
+pub const DIFF_CONTEXT_LINES: usize = 3;
+
+/// Largest number of line pairs compared to find the smallest diff; past it the
+/// lines between the common start and end are shown as replaced wholesale
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Unified diff of two texts by line, with `context` unchanged lines around each
+/// change, or `None` when they have the same lines
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str, context: usize) -> Option<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let script = edit_script(&old_lines, &new_lines);
+    let changes: Vec<usize> = script
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return None;
+    }
+
+    let mut diff = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut next = 0;
+    while next < changes.len() {
+        // Changes closer than twice the context share a hunk
+        let (first, mut last) = (changes[next], changes[next]);
+        while next + 1 < changes.len() && changes[next + 1] <= last + 2 * context + 1 {
+            next += 1;
+            last = changes[next];
+        }
+        next += 1;
+        let (start, end) = (first.saturating_sub(context), (last + context + 1).min(script.len()));
+        let hunk = &script[start..end];
+
+        let old_start = script[..start].iter().filter(|(op, _)| *op != Op::Insert).count();
+        let new_start = script[..start].iter().filter(|(op, _)| *op != Op::Delete).count();
+        let old_len = hunk.iter().filter(|(op, _)| *op != Op::Insert).count();
+        let new_len = hunk.iter().filter(|(op, _)| *op != Op::Delete).count();
+        diff.push_str(&format!("@@ -{} +{} @@\n", hunk_range(old_start, old_len), hunk_range(new_start, new_len)));
+        for (op, line) in hunk {
+            diff.push(match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            });
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    Some(diff)
+}
+
+// The lines of both texts in order, each marked as kept, removed or added, from the
+// longest common subsequence of the lines between their common start and end
+fn edit_script<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let (n, m) = (old_middle.len(), new_middle.len());
+
+    let mut script: Vec<(Op, &str)> = old[..prefix].iter().map(|line| (Op::Equal, *line)).collect();
+    if n.saturating_mul(m) > MAX_DIFF_CELLS {
+        script.extend(old_middle.iter().map(|line| (Op::Delete, *line)));
+        script.extend(new_middle.iter().map(|line| (Op::Insert, *line)));
+    } else {
+        // Length of the longest common subsequence of `old_middle[i..]` and `new_middle[j..]`
+        let width = m + 1;
+        let mut common = vec![0u32; (n + 1) * width];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                common[i * width + j] = if old_middle[i] == new_middle[j] {
+                    common[(i + 1) * width + j + 1] + 1
+                } else {
+                    common[(i + 1) * width + j].max(common[i * width + j + 1])
+                };
+            }
+        }
+        // Removals go before additions, as diff(1) lists them
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                script.push((Op::Equal, old_middle[i]));
+                i += 1;
+                j += 1;
+            } else if i < n && (j == m || common[(i + 1) * width + j] >= common[i * width + j + 1]) {
+                script.push((Op::Delete, old_middle[i]));
+                i += 1;
+            } else {
+                script.push((Op::Insert, new_middle[j]));
+                j += 1;
+            }
+        }
+    }
+    script.extend(old[old.len() - suffix..].iter().map(|line| (Op::Equal, *line)));
+    script
+}
+
+// A hunk's line range as unified diffs give it: 1-based, with the length unless it's
+// one line, and the line before the hunk when it's empty
+fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// The documentation of an item in one version of its crate, one side of `format_item_diff`
+pub struct DiffSide<'a> {
+    pub version: &'a str,
+    /// Page the docs were converted from, empty when unknown
+    pub source_url: &'a str,
+    pub markdown: &'a str,
+}
+
+/// Format how the declaration and the docs of an item changed between two versions of
+/// its crate as markdown, each as a unified diff
+///
+/// The diffs are fenced with four backticks, since the docs' own code fences show up
+/// in them indented by the diff markers.
+pub fn format_item_diff(crate_name: &str, item_path: &str, old: &DiffSide, new: &DiffSide) -> String {
+    let label = |side: &DiffSide| format!("{} {} {}", crate_name, side.version, item_path);
+    let mut output = format!("# {} of {}: {} to {}\n", item_path, crate_name, old.version, new.version);
+    if !old.source_url.is_empty() || !new.source_url.is_empty() {
+        output.push('\n');
+    }
+    for side in [old, new].into_iter().filter(|side| !side.source_url.is_empty()) {
+        output.push_str(&format!("Source ({}): {}\n", side.version, side.source_url));
+    }
+
+    output.push_str("\n## Declaration\n\n");
+    match (declaration(old.markdown), declaration(new.markdown)) {
+        (Some(before), Some(after)) => match unified_diff(&before, &after, &label(old), &label(new), DIFF_CONTEXT_LINES) {
+            Some(diff) => output.push_str(&format!("````diff\n{}````\n", diff)),
+            None => output.push_str(&format!("Unchanged:\n\n```rust\n{}\n```\n", after)),
+        },
+        _ => output.push_str("Not found on both pages; see the documentation diff.\n"),
+    }
+
+    output.push_str("\n## Documentation\n\n");
+    match unified_diff(old.markdown, new.markdown, &label(old), &label(new), DIFF_CONTEXT_LINES) {
+        Some(diff) => output.push_str(&format!("````diff\n{}````\n", diff)),
+        None => output.push_str("Unchanged.\n"),
+    }
+    output
+}
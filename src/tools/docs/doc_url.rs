@@ -0,0 +1,119 @@
+use reqwest::Url;
+
+use super::books::Book;
+use super::fetcher::{is_rust_channel, is_std_crate, STD_CRATES};
+
+/// Hosts whose pages `lookup_url` fetches
+pub const ALLOWED_DOC_HOSTS: [&str; 2] = ["docs.rs", "doc.rust-lang.org"];
+
+/// A documentation page addressed by a docs.rs or doc.rust-lang.org URL
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocUrl {
+    /// `docs.rs/crate/{crate}/{version}`, the crate's overview page
+    CrateOverview { crate_name: String, version: Option<String> },
+    /// A rustdoc page, below the crate's library directory: `docs.rs/{crate}/{version}/{lib}/{path}`,
+    /// or `doc.rust-lang.org/{channel}/{std,core,alloc}/{path}` with `stable` as the default channel
+    Page {
+        crate_name: String,
+        version: Option<String>,
+        path: String,
+    },
+    /// `doc.rust-lang.org/{book}/{path}`
+    Book { book: Book, path: String },
+}
+
+impl DocUrl {
+    /// Parse a pasted documentation URL into the page it names and its `#anchor`, if any;
+    /// a version of `latest` stands for no version
+    pub fn parse(url: &str) -> Result<(Self, Option<String>), String> {
+        let parsed = Url::parse(url.trim()).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Unsupported URL scheme {}; expected an http(s) URL", parsed.scheme()));
+        }
+        let host = parsed.host_str().unwrap_or_default();
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        if !ALLOWED_DOC_HOSTS.contains(&host) {
+            return Err(format!(
+                "{} is not a documentation host lookup_url reads (allowed: {})",
+                host,
+                ALLOWED_DOC_HOSTS.join(", ")
+            ));
+        }
+
+        let segments: Vec<&str> = parsed.path_segments().map(|segments| segments.collect()).unwrap_or_default();
+        let anchor = parsed.fragment().filter(|fragment| !fragment.is_empty()).map(str::to_string);
+        let page = if host == "docs.rs" {
+            Self::from_docs_rs(&segments)?
+        } else {
+            Self::from_rust_docs(&segments)?
+        };
+        Ok((page, anchor))
+    }
+
+    /// Whether the page is of a fixed release, as opposed to "latest" or a moving toolchain
+    /// channel, so it can be cached
+    pub fn is_pinned(&self) -> bool {
+        match self {
+            Self::CrateOverview { version, .. } => version.is_some(),
+            Self::Page { crate_name, version, .. } => {
+                version.as_deref().is_some_and(|version| !is_std_crate(crate_name) || version.contains('.'))
+            }
+            Self::Book { .. } => true,
+        }
+    }
+
+    fn from_docs_rs(segments: &[&str]) -> Result<Self, String> {
+        let version = |segment: Option<&&str>| {
+            segment
+                .filter(|version| !version.is_empty() && **version != "latest")
+                .map(|version| version.to_string())
+        };
+        match segments {
+            [] | [""] => Err("The URL names no crate".to_string()),
+            ["crate", crate_name, rest @ ..] if !crate_name.is_empty() => Ok(Self::CrateOverview {
+                crate_name: crate_name.to_string(),
+                version: version(rest.first()),
+            }),
+            [crate_name, _, "src", ..] => Err(format!("Source pages of {} aren't supported", crate_name)),
+            [crate_name, rest @ ..] => Ok(Self::Page {
+                crate_name: crate_name.to_string(),
+                version: version(rest.first()),
+                // Past the version comes the library directory, then the page
+                path: page_path(rest.get(2..).unwrap_or_default()),
+            }),
+        }
+    }
+
+    fn from_rust_docs(segments: &[&str]) -> Result<Self, String> {
+        let (channel, rest) = match segments {
+            [channel, rest @ ..] if is_rust_channel(channel) => (Some(channel.to_string()), rest),
+            rest => (None, rest),
+        };
+        match rest {
+            [crate_name, path @ ..] if STD_CRATES.contains(crate_name) => Ok(Self::Page {
+                crate_name: crate_name.to_string(),
+                version: channel,
+                path: page_path(path),
+            }),
+            [name, path @ ..] if channel.is_none() => match Book::parse(name).filter(|book| *book != Book::AsyncBook) {
+                Some(book) => Ok(Self::Book { book, path: page_path(path) }),
+                None => Err(format!(
+                    "doc.rust-lang.org/{} isn't supported; lookup_url reads {} and the edition guide, Cargo book and Rustonomicon",
+                    name,
+                    STD_CRATES.join(", ")
+                )),
+            },
+            _ => Err("The URL names no standard library crate or book".to_string()),
+        }
+    }
+}
+
+// The page below a documentation root, which is its index when the URL ends at a directory
+fn page_path(segments: &[&str]) -> String {
+    let path = segments.join("/");
+    if path.is_empty() || path.ends_with('/') {
+        format!("{}index.html", path)
+    } else {
+        path
+    }
+}
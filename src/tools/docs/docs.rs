@@ -1,4 +1,4 @@
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::{future::Future, path::{Path, PathBuf}, pin::Pin, sync::Arc, time::{Duration, Instant, SystemTime}};
 
 use mcp_core::{
     handler::{PromptError, ResourceError},
@@ -6,16 +6,173 @@ use mcp_core::{
     protocol::ServerCapabilities,
     Content, Resource, Tool, ToolError,
 };
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{FuturesUnordered, StreamExt};
 use mcp_server::router::CapabilitiesBuilder;
 use reqwest::Client;
 use serde_json::{json, Value};
 use tokio::sync::Mutex;
+use tracing::Instrument;
 use html2md::parse_html;
 
+use super::books::{chapter_content, find_chapter, format_chapter_list, parse_chapters, Book, EDITIONS};
+use super::builder::DocRouterBuilder;
+use super::changelog::{
+    format_changes, parse_changelog, parse_loose_version, parse_repository_releases, releases_between, CHANGELOG_FILES,
+    MAX_RELEASE_NOTES,
+};
+use super::errors::{
+    crate_not_found, ensure_error_data, error_data, error_message, split_error_data, version_not_found, with_error_data, DocsError, ErrorCode,
+    ErrorData,
+};
+use super::diff::{format_item_diff, DiffSide};
+use super::doc_url::DocUrl;
+use super::export::{export_file_path, ExportSummary};
+use super::extract::{anchor_section, member_names, member_section, page_links, source_href, unstable_features, PageLink};
+use super::features::{format_feature_graph, has_feature};
+use super::fetcher::{is_rust_channel, is_std_crate, DocFetcher, DocPage, ReqwestFetcher};
+use super::highlight::{highlight, MAX_HIGHLIGHT_TERMS};
+use super::history::{collect_documents, record_document, HistoryEntry, SessionHistory, HISTORY_RESOURCE_URI};
+use super::imports::{imported_paths, ImportTarget, MAX_EXPLAINED_IMPORTS, STD_ROOTS};
+use super::overrides::CrateOverrides;
+use super::policy::{CratePolicy, PolicyFetcher};
+use super::manifest::{manifest_package_name, parse_manifest_dependencies, ManifestDependency};
+use super::overview::{
+    format_comparison, format_dependency_digest, parse_crate_summary, DependencyDigest, MAX_COMPARED_CRATES,
+    MAX_DIGEST_DEPENDENCIES,
+};
+use super::local::LocalDocs;
+use super::mirror::{Mirror, MirrorFetcher};
+use super::lockfile::parse_lockfile;
+use super::persist::DiskStore;
+use super::plain_text::OutputFormat;
+use super::prefetch::{linked_item_paths, parse_direct_dependencies, parse_latest_versions, parse_max_version, PrefetchConfig};
+use super::releases::{
+    date_days_ago, format_releases, is_iso_date, parse_crate_versions, parse_just_updated, releases_since,
+    DEFAULT_RELEASE_WINDOW_DAYS,
+};
+use super::repository::{is_failed_build_page, parse_repository_url, RepoHost, RepoRef};
+use super::resolve::{
+    format_resolution, format_tree, newest_matching, resolve, Resolution, DEFAULT_TREE_DEPTH, MAX_RESOLVED_CRATES,
+};
+use super::resources::{resource_templates, DocResource};
+use super::rust_releases::{
+    format_release_notes, parse_release_notes, parse_rust_version, RustRelease, MAX_RUST_RELEASES, RUST_RELEASES_FILE,
+};
+use super::rustdoc_json::{format_json_item, JsonItem, RustdocJson, RustdocJsonCache};
+use super::search::{
+    format_definitions, format_lib_rs_results, parse_crates_io_results, parse_lib_rs_results, parse_search_crate_names, CrateSearchResults,
+    SearchBackend, POPULAR_CRATES,
+};
+use super::sections::{
+    format_outline, headings, page_lead, preamble, reflow_declaration, select_sections, without_generated_impls,
+};
+use super::sources::{Capabilities, FetcherSource, SourceRegistry};
+use super::session::{SessionDefaults, SessionSettings};
+use super::search_index::{search_index_file, IndexItem, SearchIndex, SearchIndexCache};
+#[cfg(feature = "semver")]
+use super::semver::{format_report, PublicApi};
+use super::snippet::{dependency_snippet, parse_version_features, VersionPolicy};
+use super::source_stats::{format_source_stats, source_stats};
+use super::sparse_index::{
+    find_version, latest_prerelease, latest_stable_version, latest_version, parse_index_entry, IndexVersion,
+};
+use super::targets::{format_doc_targets, parse_doc_targets};
+use super::subscriptions::{ResourceSubscriptions, SessionSubscriptions};
+use super::timing::{timed, timed_sync, CallBreakdown, Phase};
+use super::usage::{UsageStats, DEFAULT_USAGE_TOP, MAX_USAGE_TOP};
+use super::versions::{Version, VersionReq};
+use super::watch::{Update, WatchList, UPDATES_RESOURCE_URI};
+use super::weight::{format_weight, parse_crate_size};
+
+/// Page kinds `lookup_item` probes for a path it can't resolve otherwise, in default order
+pub const ITEM_PAGE_KINDS: [&str; 7] = ["struct", "enum", "trait", "fn", "macro", "attr", "derive"];
+
+/// Maximum number of crates fetched at once by `DocRouter::preload` and watch-list checks
+const PRELOAD_CONCURRENCY: usize = 4;
+
+/// Default number of most accessed cache entries the HTTP server keeps fresh
+pub const DEFAULT_REFRESH_TOP: usize = 50;
+
+/// Default seconds between refreshes of the most accessed cache entries
+pub const DEFAULT_REFRESH_INTERVAL_SECS: u64 = 15 * 60;
+
+/// Maximum number of items `lookup_items` resolves at once
+const LOOKUP_ITEMS_CONCURRENCY: usize = 4;
+
+/// Maximum number of items accepted by one `lookup_items` call
+const MAX_LOOKUP_ITEMS: usize = 20;
+
+/// Maximum number of crate search indexes `find_defining_crate` loads at once
+const DEFINING_CRATE_CONCURRENCY: usize = 8;
+
+/// Number of crates.io search results whose indexes `find_defining_crate` also searches
+const DEFINING_CRATE_SEARCH_RESULTS: u32 = 5;
+
+/// Standard library docs channels accepted by the `channel` argument
+const CHANNELS: [&str; 3] = ["stable", "beta", "nightly"];
+
+/// Library feature chapters of the unstable book, by feature gate
+const UNSTABLE_BOOK_URL: &str = "https://doc.rust-lang.org/nightly/unstable-book/library-features";
+
+/// Source line of items rendered from rustdoc JSON because their page was unavailable
+const RUSTDOC_JSON_SOURCE: &str = "docs.rs rustdoc JSON";
+
+/// Default cap on the size of one tool result sent over stdio
+pub const DEFAULT_STDIO_MAX_CONTENT_BYTES: usize = 1024 * 1024;
+
+/// Default cap on the size of one tool result sent over HTTP/SSE
+pub const DEFAULT_HTTP_MAX_CONTENT_BYTES: usize = 2 * 1024 * 1024;
+
+/// Bytes kept free below the content cap for the truncation marker
+const TRUNCATION_MARKER_BYTES: usize = 64;
+
+/// Convert a documentation page from HTML to markdown
+///
+/// All tools go through this function so conversion behaviour is defined in
+/// one place (and covered by the golden-file tests in `tests/conversion_tests.rs`).
+/// The page is sanitized first, so nothing upstream injected survives conversion.
+pub fn html_to_markdown(html: &str) -> String {
+    timed_sync(Phase::Conversion, || parse_html(&sanitize_html(html)))
+}
+
+/// Strip scripts, styles, event handler attributes and other active content
+/// from HTML, keeping its text and structure
+pub fn sanitize_html(html: &str) -> String {
+    ammonia::Builder::default()
+        // The page title would otherwise be kept as a stray line of text
+        .add_clean_content_tags(["title", "noscript", "template"])
+        .clean(html)
+        .to_string()
+}
+
+/// What the cache does with entries larger than its per-entry limit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Store only the first `max_entry_bytes` bytes, followed by a truncation note
+    #[default]
+    Truncate,
+    /// Keep the full entry in the persistent layer only, never in memory
+    /// (oversized entries are not cached at all without a persistent layer)
+    SpillToDisk,
+}
+
+/// Most keys whose accesses the cache counts; past it, keys no longer cached in memory
+/// are forgotten first, then the least read ones
+pub const MAX_TRACKED_ACCESSES: usize = 10_000;
+
 // Cache for documentation lookups to avoid repeated requests
 #[derive(Clone)]
 pub struct DocCache {
     cache: Arc<Mutex<std::collections::HashMap<String, String>>>,
+    /// Number of times each key was stored or hit, for at most `MAX_TRACKED_ACCESSES`
+    /// keys; misses aren't counted, so keys nothing was ever cached under never are
+    accesses: Arc<Mutex<std::collections::HashMap<String, u64>>>,
+    /// Optional persistent layer backing the in-memory map
+    disk: Option<Arc<DiskStore>>,
+    /// Largest entry (in bytes) kept in memory as-is (unlimited when `None`)
+    max_entry_bytes: Option<usize>,
+    oversize_policy: OversizePolicy,
 }
 
 impl Default for DocCache {
@@ -28,24 +185,230 @@ impl DocCache {
     pub fn new() -> Self {
         Self {
             cache: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            accesses: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            disk: None,
+            max_entry_bytes: None,
+            oversize_policy: OversizePolicy::default(),
+        }
+    }
+
+    /// Create a cache that also persists entries to `disk`
+    pub fn with_disk(disk: DiskStore) -> Self {
+        Self {
+            disk: Some(Arc::new(disk)),
+            ..Self::new()
         }
     }
 
+    /// Limit the size of a single entry, handling larger ones according to `policy`
+    pub fn with_max_entry_bytes(mut self, max_entry_bytes: usize, policy: OversizePolicy) -> Self {
+        self.max_entry_bytes = Some(max_entry_bytes);
+        self.oversize_policy = policy;
+        self
+    }
+
     pub async fn get(&self, key: &str) -> Option<String> {
-        let cache = self.cache.lock().await;
-        cache.get(key).cloned()
+        timed(Phase::CacheLookup, async {
+            let cached = self.cache.lock().await.get(key).cloned();
+            let value = match cached {
+                Some(value) => value,
+                None => {
+                    // Fall back to the persistent layer and keep the hit in memory if it fits
+                    let value = self.disk.as_ref()?.get(key).await?;
+                    if !self.is_oversized(&value) {
+                        self.cache.lock().await.insert(key.to_string(), value.clone());
+                    }
+                    value
+                }
+            };
+            self.record_access(key).await;
+            Some(value)
+        })
+        .await
+    }
+
+    // Count an access of `key`, making room for it when `MAX_TRACKED_ACCESSES` keys are counted
+    async fn record_access(&self, key: &str) {
+        let mut accesses = self.accesses.lock().await;
+        if !accesses.contains_key(key) && accesses.len() >= MAX_TRACKED_ACCESSES {
+            {
+                let cache = self.cache.lock().await;
+                accesses.retain(|key, _| cache.contains_key(key));
+            }
+            if accesses.len() >= MAX_TRACKED_ACCESSES {
+                let least_read = accesses
+                    .iter()
+                    .min_by(|(a_key, a_count), (b_key, b_count)| a_count.cmp(b_count).then_with(|| b_key.cmp(a_key)))
+                    .map(|(key, _)| key.clone());
+                if let Some(least_read) = least_read {
+                    accesses.remove(&least_read);
+                }
+            }
+        }
+        *accesses.entry(key.to_string()).or_default() += 1;
+    }
+
+    pub async fn set(&self, key: String, mut value: String) {
+        timed(Phase::CacheStore, async move {
+            if let Some(limit) = self.max_entry_bytes.filter(|limit| value.len() > *limit) {
+                match self.oversize_policy {
+                    OversizePolicy::Truncate => value = truncate_entry(value, limit),
+                    OversizePolicy::SpillToDisk => {
+                        if let Some(disk) = &self.disk {
+                            disk.set(&key, &value).await;
+                            self.record_access(&key).await;
+                        }
+                        return;
+                    }
+                }
+            }
+
+            if let Some(disk) = &self.disk {
+                disk.set(&key, &value).await;
+            }
+            self.record_access(&key).await;
+            let mut cache = self.cache.lock().await;
+            cache.insert(key, value);
+        })
+        .await
+    }
+
+    /// Wait for writes to the persistent layer to finish, so nothing is lost when the
+    /// process exits
+    pub async fn flush(&self) -> std::io::Result<()> {
+        match &self.disk {
+            Some(disk) => disk.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    /// The `limit` most accessed keys, most accessed first
+    pub async fn most_accessed(&self, limit: usize) -> Vec<String> {
+        self.most_accessed_matching(limit, |_| true).await
+    }
+
+    /// The `limit` most accessed keys among those `keep` accepts, most accessed first
+    pub async fn most_accessed_matching(&self, limit: usize, keep: impl Fn(&str) -> bool) -> Vec<String> {
+        let accesses = self.accesses.lock().await;
+        let mut keys: Vec<(&String, &u64)> = accesses.iter().filter(|(key, _)| keep(key)).collect();
+        keys.sort_by(|(a_key, a_count), (b_key, b_count)| b_count.cmp(a_count).then_with(|| a_key.cmp(b_key)));
+        keys.into_iter().take(limit).map(|(key, _)| key.clone()).collect()
+    }
+
+    fn is_oversized(&self, value: &str) -> bool {
+        self.max_entry_bytes.is_some_and(|limit| value.len() > limit)
+    }
+}
+
+// Cut an entry down to `limit` bytes on a char boundary and say so at the end
+fn truncate_entry(mut value: String, limit: usize) -> String {
+    let original_len = value.len();
+    let mut end = limit;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    value.truncate(end);
+    value.push_str(&format!(
+        "\n\n[truncated: cached entry exceeded {} bytes (was {} bytes)]",
+        limit, original_len
+    ));
+    value
+}
+
+/// How long an unversioned lookup keeps resolving to the same concrete version
+pub const DEFAULT_LATEST_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Short-lived mapping from crate name to the version "latest" currently means
+///
+/// Unversioned lookups are cached under the concrete version, so this alias is
+/// the only thing that has to expire when a new release is published.
+#[derive(Clone)]
+pub struct LatestVersions {
+    ttl: Duration,
+    entries: Arc<Mutex<std::collections::HashMap<String, (String, Instant)>>>,
+}
+
+impl Default for LatestVersions {
+    fn default() -> Self {
+        Self::new(DEFAULT_LATEST_TTL)
+    }
+}
+
+impl LatestVersions {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(std::collections::HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, crate_name: &str) -> Option<String> {
+        let entries = self.entries.lock().await;
+        entries
+            .get(crate_name)
+            .filter(|(_, resolved_at)| resolved_at.elapsed() < self.ttl)
+            .map(|(version, _)| version.clone())
+    }
+
+    /// The last version "latest" resolved to, even when it has expired; served
+    /// while crates.io can't be reached
+    pub async fn get_stale(&self, crate_name: &str) -> Option<String> {
+        let entries = self.entries.lock().await;
+        entries.get(crate_name).map(|(version, _)| version.clone())
     }
 
-    pub async fn set(&self, key: String, value: String) {
-        let mut cache = self.cache.lock().await;
-        cache.insert(key, value);
+    pub async fn set(&self, crate_name: &str, version: &str) {
+        let mut entries = self.entries.lock().await;
+        entries.insert(crate_name.to_string(), (version.to_string(), Instant::now()));
     }
 }
 
 #[derive(Clone)]
 pub struct DocRouter {
-    pub client: Client,
+    pub fetcher: Arc<dyn DocFetcher>,
+    /// The fetchers below the crate policy, which `with_local_docs` and `with_mirror`
+    /// wrap; `fetcher` is this behind the policy, when there is one
+    unrestricted_fetcher: Arc<dyn DocFetcher>,
     pub cache: DocCache,
+    /// Concrete versions that unversioned lookups currently resolve to
+    pub latest_versions: LatestVersions,
+    /// Background prefetching of related docs (disabled when `None`)
+    pub prefetch: Option<PrefetchConfig>,
+    /// Backend `search_crates` uses when the call doesn't pick one
+    pub search_backend: SearchBackend,
+    /// Crates checked periodically for new releases
+    pub watch: WatchList,
+    /// Resources clients subscribed to, re-read periodically to detect changes
+    pub subscriptions: ResourceSubscriptions,
+    /// Parsed rustdoc search indexes, used to correct item paths
+    pub search_indexes: SearchIndexCache,
+    /// Crates documented by local `cargo doc` builds (the build tool is disabled when `None`)
+    pub local_docs: Option<LocalDocs>,
+    /// Parsed docs.rs rustdoc JSON, used to resolve item paths without probing
+    pub rustdoc_json: RustdocJsonCache,
+    /// Largest tool result in bytes returned at once; longer results are cut off
+    /// with a marker giving the `offset` to continue from (unlimited when `None`)
+    pub max_content_bytes: Option<usize>,
+    /// Number of content blocks of `max_content_bytes` a long result is split
+    /// into before it is cut off
+    pub max_content_parts: usize,
+    /// Longest time a call of each tool may take; tools not listed have no limit
+    pub tool_timeouts: std::collections::HashMap<String, Duration>,
+    /// Order in which `lookup_item` probes page kinds, before what it learned per crate
+    pub probe_order: Vec<&'static str>,
+    /// Per-crate defaults and aliases filled into tool calls
+    pub crate_overrides: CrateOverrides,
+    /// Crates the operator allows looking up
+    pub crate_policy: CratePolicy,
+    /// Calls taking at least this long are logged at WARN with their arguments, upstream
+    /// URLs and timing breakdown (not logged when `None`)
+    pub slow_call_threshold: Option<Duration>,
+    /// How often each crate and item was looked up
+    pub usage: UsageStats,
+    /// Tool calls and documents of the session this router serves (see `for_session`)
+    pub history: SessionHistory,
+    /// Defaults the session this router serves set with `configure_session`
+    pub session_defaults: SessionDefaults,
 }
 
 impl Default for DocRouter {
@@ -55,192 +418,2637 @@ impl Default for DocRouter {
 }
 
 impl DocRouter {
+    /// A router fetching from the upstream documentation sources
     pub fn new() -> Self {
+        Self::with_sources(SourceRegistry::upstream(Arc::new(ReqwestFetcher::new(Client::new()))))
+    }
+
+    /// Configure a router, its HTTP client and its sources in one place
+    pub fn builder() -> DocRouterBuilder {
+        DocRouterBuilder::default()
+    }
+
+    /// Create a router that retrieves pages from the given sources
+    pub fn with_sources(sources: SourceRegistry) -> Self {
+        Self::with_fetcher(Arc::new(sources))
+    }
+
+    /// Create a router that retrieves pages through the given fetcher
+    pub fn with_fetcher(fetcher: Arc<dyn DocFetcher>) -> Self {
         Self {
-            client: Client::new(),
+            fetcher: fetcher.clone(),
+            unrestricted_fetcher: fetcher,
             cache: DocCache::new(),
+            latest_versions: LatestVersions::default(),
+            prefetch: None,
+            search_backend: SearchBackend::default(),
+            watch: WatchList::default(),
+            subscriptions: ResourceSubscriptions::default(),
+            search_indexes: SearchIndexCache::default(),
+            local_docs: None,
+            rustdoc_json: RustdocJsonCache::default(),
+            max_content_bytes: None,
+            max_content_parts: 1,
+            tool_timeouts: std::collections::HashMap::new(),
+            probe_order: ITEM_PAGE_KINDS.to_vec(),
+            crate_overrides: CrateOverrides::default(),
+            crate_policy: CratePolicy::default(),
+            slow_call_threshold: None,
+            usage: UsageStats::default(),
+            history: SessionHistory::default(),
+            session_defaults: SessionDefaults::default(),
         }
     }
 
-    // Fetch crate documentation from docs.rs
-    async fn lookup_crate(&self, crate_name: String, version: Option<String>) -> Result<String, ToolError> {
-        // Check cache first
-        let cache_key = if let Some(ver) = &version {
-            format!("{}:{}", crate_name, ver)
+    /// A router for a new client session: it shares everything with this one but
+    /// records its calls in a history of its own and has its own session defaults
+    pub fn for_session(&self) -> Self {
+        Self {
+            history: SessionHistory::default(),
+            session_defaults: SessionDefaults::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Use the given cache instead of a fresh in-memory one
+    pub fn with_cache(mut self, cache: DocCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Set how long "latest" keeps resolving to the same version before crates.io is asked again
+    pub fn with_latest_ttl(mut self, ttl: Duration) -> Self {
+        self.latest_versions = LatestVersions::new(ttl);
+        self
+    }
+
+    /// Enable background prefetching of related documentation after crate lookups
+    pub fn with_prefetch(mut self, config: PrefetchConfig) -> Self {
+        self.prefetch = Some(config);
+        self
+    }
+
+    /// Set the default backend for `search_crates`
+    pub fn with_search_backend(mut self, backend: SearchBackend) -> Self {
+        self.search_backend = backend;
+        self
+    }
+
+    /// Use the given cache for rustdoc search indexes
+    pub fn with_search_index_cache(mut self, search_indexes: SearchIndexCache) -> Self {
+        self.search_indexes = search_indexes;
+        self
+    }
+
+    /// Cap the size of each tool result, so transports whose clients can't
+    /// handle large messages get them in parts
+    pub fn with_max_content_bytes(mut self, max_content_bytes: usize) -> Self {
+        self.max_content_bytes = Some(max_content_bytes);
+        self
+    }
+
+    /// Return long results as up to `max_content_parts` content blocks of at
+    /// most `max_content_bytes` each, for clients that handle multiple parts
+    pub fn with_max_content_parts(mut self, max_content_parts: usize) -> Self {
+        self.max_content_parts = max_content_parts.max(1);
+        self
+    }
+
+    /// Fail calls of `tool_name` that take longer than `timeout` instead of
+    /// waiting on a slow upstream indefinitely
+    pub fn with_tool_timeout(mut self, tool_name: &str, timeout: Duration) -> Self {
+        self.tool_timeouts.insert(tool_name.to_string(), timeout);
+        self
+    }
+
+    /// Log calls taking `threshold` or longer at WARN with what they were asked and where
+    /// their time went, to spot pathological crates or a slow upstream
+    pub fn with_slow_call_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_call_threshold = Some(threshold);
+        self
+    }
+
+    /// Count lookups in `usage`, e.g. counts loaded from a file to add up across restarts
+    pub fn with_usage(mut self, usage: UsageStats) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Set the order in which `lookup_item` probes page kinds (see `item_probe_order`)
+    pub fn with_probe_order(mut self, probe_order: Vec<&'static str>) -> Self {
+        self.probe_order = probe_order;
+        self
+    }
+
+    /// Fill per-crate defaults into tool calls (the docs.rs targets they name are up to the fetcher)
+    pub fn with_crate_overrides(mut self, crate_overrides: CrateOverrides) -> Self {
+        self.crate_overrides = crate_overrides;
+        self
+    }
+
+    /// Refuse crates `policy` doesn't allow, both in tool arguments and in every request
+    /// the fetcher makes for a crate, so refused crates never reach docs.rs or crates.io
+    ///
+    /// The policy is the outermost layer of the fetcher, applied once over the fetchers
+    /// `with_local_docs` and `with_mirror` add, whatever order they're called in.
+    pub fn with_crate_policy(mut self, policy: CratePolicy) -> Self {
+        self.crate_policy = policy;
+        self.apply_crate_policy();
+        self
+    }
+
+    // Put the crate policy in front of the unrestricted fetchers, once, as the outermost layer
+    fn apply_crate_policy(&mut self) {
+        self.fetcher = if self.crate_policy.is_empty() {
+            self.unrestricted_fetcher.clone()
         } else {
-            crate_name.clone()
+            Arc::new(PolicyFetcher::new(self.crate_policy.clone(), self.unrestricted_fetcher.clone()))
         };
+    }
 
-        if let Some(doc) = self.cache.get(&cache_key).await {
-            return Ok(doc);
+    /// Enable the `build_local_docs` tool and serve the crates it builds through the
+    /// `local` source ahead of the current fetcher, so they work with every lookup tool
+    pub fn with_local_docs(mut self, local_docs: LocalDocs) -> Self {
+        let sources = SourceRegistry::new()
+            .with_source(Arc::new(local_docs.clone()))
+            .with_source(Arc::new(FetcherSource::new("upstream", Capabilities::ALL, self.unrestricted_fetcher)));
+        self.unrestricted_fetcher = Arc::new(sources);
+        self.local_docs = Some(local_docs);
+        self.apply_crate_policy();
+        self
+    }
+
+    /// Serve crate docs saved by `cratedocs mirror` ahead of docs.rs; `offline` stops
+    /// every other upstream request, so only mirrored crates can be looked up
+    pub fn with_mirror(mut self, mirror: Mirror, offline: bool) -> Self {
+        let upstream = (!offline).then_some(self.unrestricted_fetcher);
+        self.unrestricted_fetcher = Arc::new(MirrorFetcher::new(mirror, upstream));
+        self.apply_crate_policy();
+        self
+    }
+
+    /// Fetch and cache the docs of every crates.io package locked in a `Cargo.lock`, at
+    /// its exact version, so lookups while working in that project hit the cache
+    ///
+    /// Path, git and other-registry packages are skipped. Returns a summary of what was cached.
+    pub async fn warm_from_lockfile(&self, lockfile: &str) -> Result<String, ToolError> {
+        let packages = parse_lockfile(lockfile).map_err(ToolError::InvalidParameters)?;
+        let specs: Vec<String> = packages
+            .iter()
+            .filter(|package| package.from_crates_io)
+            .map(|package| format!("{}@{}", package.name, package.version))
+            .collect();
+        let skipped = packages.len() - specs.len();
+
+        let loaded = self.preload(&specs).await;
+        let mut summary = format!("Cached the docs of {} of {} crates.io packages locked in Cargo.lock", loaded, specs.len());
+        if loaded < specs.len() {
+            summary.push_str(&format!("; {} could not be fetched", specs.len() - loaded));
+        }
+        if skipped > 0 {
+            summary.push_str(&format!("; skipped {} path, git and other-registry packages", skipped));
         }
+        summary.push('\n');
+        Ok(summary)
+    }
 
-        // Construct the docs.rs URL for the crate
-        let url = if let Some(ver) = version {
-            format!("https://docs.rs/crate/{}/{}/", crate_name, ver)
-        } else {
-            format!("https://docs.rs/crate/{}/", crate_name)
+    /// Write the docs of a crate as markdown below `out_dir`: the crate root to
+    /// `<crate>/<version>/index.md` and every module and item page its search index
+    /// lists, up to `max_pages`, to the same path as on docs.rs (`sync/mpsc/struct.Sender.md`)
+    ///
+    /// Pages that fail are counted in the summary rather than stopping the export; only
+    /// a crate root that can't be fetched or a directory that can't be written fails it.
+    pub async fn export_crate(
+        &self,
+        crate_name: &str,
+        version: Option<&str>,
+        out_dir: &Path,
+        max_pages: usize,
+    ) -> Result<ExportSummary, ToolError> {
+        let version = self.resolve_version(crate_name, version).await;
+        let version_label = version.clone().unwrap_or_else(|| "latest".to_string());
+        let dir = out_dir.join(crate_name).join(&version_label);
+        let write = |path: PathBuf, markdown: String| async move {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, markdown).await
         };
 
-        // Fetch the documentation page
-        let response = self.client.get(&url)
-            .header("User-Agent", "CrateDocs/0.1.0 (https://github.com/d6e/cratedocs-mcp)")
-            .send()
+        let (root, _) = self.fetch_crate_doc(crate_name, version.as_deref()).await?;
+        write(dir.join("index.md"), root)
             .await
-            .map_err(|e| {
-                ToolError::ExecutionError(format!("Failed to fetch documentation: {}", e))
-            })?;
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to write to {}: {}", dir.display(), e)))?;
 
-        if !response.status().is_success() {
-            return Err(ToolError::ExecutionError(format!(
-                "Failed to fetch documentation. Status: {}",
-                response.status()
-            )));
+        let index = self.search_index(crate_name, version.as_deref()).await;
+        let mut items: Vec<&IndexItem> = index
+            .as_deref()
+            .map(|index| index.items().iter().filter(|item| item.page_path() != "index.html").collect())
+            .unwrap_or_default();
+        let skipped = items.len().saturating_sub(max_pages);
+        items.truncate(max_pages);
+        let listed = items.len();
+
+        let (dir_ref, version) = (&dir, version.as_deref());
+        let failed: Vec<String> = futures::stream::iter(items)
+            .map(|item| async move {
+                let page_path = item.page_path();
+                let result = match export_file_path(&page_path) {
+                    Some(file) => match self.fetcher.get_item_page(crate_name, version, &page_path).await {
+                        Ok(page) => {
+                            let markdown = with_doc_header(crate_name, version, &page.url, html_to_markdown(&page.html));
+                            write(dir_ref.join(file), markdown).await.map_err(|e| e.to_string())
+                        }
+                        Err(e) => Err(error_message(e)),
+                    },
+                    None => Err(format!("unexpected page path {}", page_path)),
+                };
+                result.err().map(|e| {
+                    tracing::debug!("Failed to export {}: {}", item.path, e);
+                    item.path.clone()
+                })
+            })
+            .buffered(PRELOAD_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+
+        Ok(ExportSummary {
+            crate_name: crate_name.to_string(),
+            version: version_label,
+            written: 1 + listed - failed.len(),
+            listed,
+            failed,
+            skipped,
+            has_index: index.is_some(),
+            dir,
+        })
+    }
+
+    /// Save a crate version's docs.rs crate page, its rustdoc pages and search index,
+    /// and the crate's index entry to `mirror`, for serving with `with_mirror`
+    ///
+    /// Pages are stored as fetched, so the mirror answers item-level queries the same
+    /// way docs.rs does. Like `export_crate`, failing item pages are counted in the
+    /// summary, and at most `max_pages` item pages are saved.
+    pub async fn mirror_crate(
+        &self,
+        mirror: &Mirror,
+        crate_name: &str,
+        version: Option<&str>,
+        max_pages: usize,
+    ) -> Result<ExportSummary, ToolError> {
+        let Some(version) = self.resolve_version(crate_name, version).await else {
+            return Err(DocsError::Unavailable {
+                message: format!("Failed to resolve the latest version of {}", crate_name),
+                retry_in: None,
+            }
+            .into());
+        };
+        let version = version.as_str();
+        let dir = mirror.version_dir(crate_name, version);
+        let write_error = |e: std::io::Error| ToolError::ExecutionError(format!("Failed to write to {}: {}", dir.display(), e));
+
+        match self.fetcher.get_index_entry(crate_name).await {
+            Ok(entry) => mirror.write_index_entry(crate_name, &entry).await.map_err(write_error)?,
+            Err(e) => tracing::debug!("Failed to fetch the index entry of {}: {}", crate_name, e),
         }
+        let page = self.fetcher.get_crate_page(crate_name, Some(version)).await?;
+        mirror.write_crate_page(crate_name, version, &page.html).await.map_err(write_error)?;
+        let root = self.fetcher.get_item_page(crate_name, Some(version), "index.html").await?;
+        mirror.write_page(crate_name, version, "index.html", &root.html).await.map_err(write_error)?;
 
-        let html_body = response.text().await.map_err(|e| {
-            ToolError::ExecutionError(format!("Failed to read response body: {}", e))
-        })?;
-        
-        // Convert HTML to markdown
-        let markdown_body = parse_html(&html_body);
+        let file = search_index_file(&root.html);
+        let index = match self.fetcher.get_doc_file(crate_name, Some(version), &file).await {
+            Ok(js) => {
+                mirror.write_file(crate_name, version, &file, &js).await.map_err(write_error)?;
+                SearchIndex::parse(&js, crate_name)
+            }
+            Err(e) => {
+                tracing::debug!("Failed to fetch the search index of {} {}: {}", crate_name, version, e);
+                None
+            }
+        };
+        let mut items: Vec<&IndexItem> = index
+            .as_ref()
+            .map(|index| index.items().iter().filter(|item| item.page_path() != "index.html").collect())
+            .unwrap_or_default();
+        let skipped = items.len().saturating_sub(max_pages);
+        items.truncate(max_pages);
+        let listed = items.len();
 
-        // Cache the markdown result
-        self.cache.set(cache_key, markdown_body.clone()).await;
-        
-        Ok(markdown_body)
+        let failed: Vec<String> = futures::stream::iter(items)
+            .map(|item| async move {
+                let page_path = item.page_path();
+                let result = match self.fetcher.get_item_page(crate_name, Some(version), &page_path).await {
+                    Ok(page) => mirror
+                        .write_page(crate_name, version, &page_path, &page.html)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(error_message(e)),
+                };
+                result.err().map(|e| {
+                    tracing::debug!("Failed to mirror {}: {}", item.path, e);
+                    item.path.clone()
+                })
+            })
+            .buffered(PRELOAD_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+
+        Ok(ExportSummary {
+            crate_name: crate_name.to_string(),
+            version: version.to_string(),
+            written: 1 + listed - failed.len(),
+            listed,
+            failed,
+            skipped,
+            has_index: index.is_some(),
+            dir,
+        })
     }
 
-    // Search crates.io for crates matching a query
-    async fn search_crates(&self, query: String, limit: Option<u32>) -> Result<String, ToolError> {
-        let limit = limit.unwrap_or(10).min(100); // Cap at 100 results
-        
-        let url = format!("https://crates.io/api/v1/crates?q={}&per_page={}", query, limit);
-        
-        let response = self.client.get(&url)
-            .header("User-Agent", "CrateDocs/0.1.0 (https://github.com/d6e/cratedocs-mcp)")
-            .send()
+    /// Fetch and cache the given crates, e.g. `["tokio", "axum@0.7"]`
+    ///
+    /// Failures are logged and skipped so one bad entry doesn't stop the rest.
+    /// Returns the number of crates that are now cached.
+    pub async fn preload(&self, crates: &[String]) -> usize {
+        futures::stream::iter(crates)
+            .map(|spec| async move {
+                let (crate_name, version) = match spec.split_once('@') {
+                    Some((name, ver)) => (name.trim(), Some(ver.trim())),
+                    None => (spec.trim(), None),
+                };
+                match self.fetch_crate_doc(crate_name, version).await {
+                    Ok(_) => true,
+                    Err(e) => {
+                        tracing::warn!("Failed to preload {}: {}", spec, e);
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(PRELOAD_CONCURRENCY)
+            .filter(|loaded| futures::future::ready(*loaded))
+            .count()
             .await
-            .map_err(|e| {
-                ToolError::ExecutionError(format!("Failed to search crates.io: {}", e))
-            })?;
+    }
 
-        if !response.status().is_success() {
-            return Err(ToolError::ExecutionError(format!(
-                "Failed to search crates.io. Status: {}",
-                response.status()
-            )));
+    /// Revalidate the crate and item docs among the `limit` most accessed cache entries,
+    /// so popular docs stay cached at the version "latest" currently means
+    ///
+    /// Docs of a concrete version never change, so only entries of the version "latest"
+    /// last resolved to can go stale, and only once that resolution has expired. Their
+    /// crates are resolved again, and entries are fetched at the new version when one was
+    /// published. Returns the number of entries fetched.
+    pub async fn refresh_popular(&self, limit: usize) -> usize {
+        // Internal entries (search indexes, probe stats, tables of contents) are read on
+        // every lookup, so only docs entries compete for the `limit` places
+        let entries: Vec<(String, String, Option<String>)> = self
+            .cache
+            .most_accessed_matching(limit, |key| parse_doc_key(key).is_some())
+            .await
+            .iter()
+            .filter_map(|key| parse_doc_key(key))
+            .collect();
+
+        // Resolve each crate once, and only when its latest version is due for a check
+        let mut new_versions: std::collections::HashMap<String, String> = std::collections::HashMap::new();
+        for (crate_name, version, _) in &entries {
+            if new_versions.contains_key(crate_name) || self.latest_versions.get(crate_name).await.is_some() {
+                continue;
+            }
+            if self.latest_versions.get_stale(crate_name).await.as_ref() != Some(version) {
+                continue;
+            }
+            match self.latest_published_version(crate_name).await {
+                Ok(Some(latest)) => {
+                    self.latest_versions.set(crate_name, &latest).await;
+                    new_versions.insert(crate_name.clone(), latest);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Failed to revalidate the latest version of {}: {}", crate_name, e),
+            }
         }
 
-        let body = response.text().await.map_err(|e| {
-            ToolError::ExecutionError(format!("Failed to read response body: {}", e))
-        })?;
-        
-        // Check if response is JSON (API response) or HTML (web page)
-        if body.trim().starts_with('{') {
-            // This is likely JSON data, return as is
-            Ok(body)
-        } else {
-            // This is likely HTML, convert to markdown
-            Ok(parse_html(&body))
+        let stale: Vec<(String, String, Option<String>)> = entries
+            .into_iter()
+            .filter_map(|(crate_name, version, item_path)| {
+                let latest = new_versions.get(&crate_name).filter(|latest| **latest != version)?.clone();
+                Some((crate_name, latest, item_path))
+            })
+            .collect();
+        futures::stream::iter(stale)
+            .map(|(crate_name, version, item_path)| async move {
+                let result = match item_path {
+                    Some(item_path) => self.lookup_item(crate_name.clone(), item_path, Some(version)).await.map(|_| ()),
+                    None => self.fetch_crate_doc(&crate_name, Some(&version)).await.map(|_| ()),
+                };
+                match result {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::debug!("Failed to refresh the docs of {}: {}", crate_name, e);
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(PRELOAD_CONCURRENCY)
+            .filter(|refreshed| futures::future::ready(*refreshed))
+            .count()
+            .await
+    }
+
+    /// Look up the latest version of every watched crate and record new releases
+    ///
+    /// Crates crates.io can't answer for are skipped until the next check.
+    pub async fn check_watched(&self) -> Vec<Update> {
+        let crates = self.watch.crates().await;
+        let latest = futures::stream::iter(crates)
+            .map(|crate_name| async move {
+                match self.latest_published_version(&crate_name).await {
+                    Ok(Some(version)) => Some((crate_name, version)),
+                    Ok(None) => None,
+                    Err(e) => {
+                        tracing::debug!("Failed to check {} for updates: {}", crate_name, e);
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(PRELOAD_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+        self.watch.record(latest).await
+    }
+
+    /// Subscribe `session` to the resource at `uri`, reading it once so later
+    /// refreshes can tell whether it changed
+    pub async fn subscribe_resource(&self, session: &SessionSubscriptions, uri: &str) -> Result<(), ResourceError> {
+        let content = self.resource_content(uri).await?;
+        if session.insert(uri).await {
+            self.subscriptions.add(uri, &content).await;
         }
+        Ok(())
     }
 
-    // Get documentation for a specific item in a crate
-    async fn lookup_item(&self, crate_name: String, mut item_path: String, version: Option<String>) -> Result<String, ToolError> {
-        // Strip crate name prefix from the item path if it exists
-        let crate_prefix = format!("{}::", crate_name);
-        if item_path.starts_with(&crate_prefix) {
-            item_path = item_path[crate_prefix.len()..].to_string();
+    pub async fn unsubscribe_resource(&self, session: &SessionSubscriptions, uri: &str) {
+        if session.remove(uri).await {
+            self.subscriptions.remove(uri).await;
+        }
+    }
+
+    /// Drop every subscription of a session that ended
+    pub async fn end_session(&self, session: &SessionSubscriptions) {
+        for uri in session.take().await {
+            self.subscriptions.remove(&uri).await;
+        }
+    }
+
+    /// Re-read every subscribed resource and announce the ones whose content changed
+    ///
+    /// Reads go through the cache, so only resources whose cached copy expired,
+    /// such as `latest` pages once "latest" moves to a new release, are fetched
+    /// again. The updates resource is announced by the watch list instead, and the
+    /// history resource, which differs per session, isn't announced.
+    pub async fn refresh_subscriptions(&self) -> Vec<String> {
+        let uris: Vec<String> = self
+            .subscriptions
+            .uris()
+            .await
+            .into_iter()
+            .filter(|uri| uri != UPDATES_RESOURCE_URI && uri != HISTORY_RESOURCE_URI)
+            .collect();
+        let changed: Vec<String> = futures::stream::iter(uris)
+            .map(|uri| async move {
+                match self.resource_content(&uri).await {
+                    Ok(content) => self.subscriptions.record(&uri, &content).await.then_some(uri),
+                    Err(e) => {
+                        tracing::debug!("Failed to refresh {}: {}", uri, e);
+                        None
+                    }
+                }
+            })
+            .buffer_unordered(PRELOAD_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+        for uri in &changed {
+            self.watch.announce(uri);
+        }
+        changed
+    }
+
+    // Read the resource at `uri`, fetching documentation through the cache
+    async fn resource_content(&self, uri: &str) -> Result<String, ResourceError> {
+        if uri == UPDATES_RESOURCE_URI {
+            return Ok(self.watch.updates_markdown().await);
+        }
+        if uri == HISTORY_RESOURCE_URI {
+            return Ok(self.history.markdown());
+        }
+        let doc = match DocResource::parse(uri) {
+            Some(DocResource::Crate { crate_name, version }) => self.lookup_crate(crate_name, version).await,
+            Some(DocResource::Item { crate_name, version, item_path }) => {
+                self.lookup_item(crate_name, item_path, version).await
+            }
+            None => return Err(ResourceError::NotFound(format!("Resource {} not found", uri))),
+        };
+        doc.map_err(|e| match e {
+            ToolError::NotFound(message) => ResourceError::NotFound(message),
+            other => ResourceError::ExecutionError(error_message(other)),
+        })
+    }
+
+    // Fetch crate documentation from docs.rs
+    pub(crate) async fn lookup_crate(&self, crate_name: String, version: Option<String>) -> Result<String, ToolError> {
+        let (markdown_body, fetched_html) = self.fetch_crate_doc(&crate_name, version.as_deref()).await?;
+        self.note_document(&crate_name, version.as_deref(), None).await;
+
+        // Warm the cache with the pages agents usually ask for next
+        if let (Some(config), Some(html_body)) = (self.prefetch.clone(), fetched_html) {
+            let router = self.clone();
+            tokio::spawn(async move {
+                router.prefetch_related(crate_name, version, html_body, config).await;
+            });
         }
 
+        Ok(markdown_body)
+    }
+
+    // Get the markdown for a crate page, returning the raw HTML as well when
+    // it had to be fetched (i.e. on a cache miss)
+    async fn fetch_crate_doc(&self, crate_name: &str, version: Option<&str>) -> Result<(String, Option<String>), ToolError> {
+        check_std_channel(crate_name, version)?;
+        let (version, stale) = self.resolve_version_or_stale(crate_name, version).await;
+        let version = version.as_deref();
+        let mark_stale = |doc: String| match version.filter(|_| stale) {
+            Some(ver) => with_stale_note(crate_name, ver, doc),
+            None => doc,
+        };
+
         // Check cache first
-        let cache_key = if let Some(ver) = &version {
-            format!("{}:{}:{}", crate_name, ver, item_path)
+        let cache_key = if let Some(ver) = version {
+            format!("{}:{}", crate_name, ver)
         } else {
-            format!("{}:{}", crate_name, item_path)
+            crate_name.to_string()
         };
 
         if let Some(doc) = self.cache.get(&cache_key).await {
-            return Ok(doc);
+            return Ok((mark_stale(doc), None));
         }
 
-        // Process the item path to determine the item type
-        // Format: module::path::ItemName
-        // Need to split into module path and item name, and guess item type
-        let parts: Vec<&str> = item_path.split("::").collect();
-        
-        if parts.is_empty() {
-            return Err(ToolError::InvalidParameters(
-                "Invalid item path. Expected format: module::path::ItemName".to_string()
-            ));
-        }
-        
-        let item_name = parts.last().unwrap().to_string();
-        let module_path = if parts.len() > 1 {
-            parts[..parts.len()-1].join("/")
-        } else {
-            String::new()
-        };
-        
-        // Try different item types (struct, enum, trait, fn)
-        let item_types = ["struct", "enum", "trait", "fn", "macro"];
-        let mut last_error = None;
-        
-        for item_type in item_types.iter() {
-            // Construct the docs.rs URL for the specific item
-            let url = if let Some(ver) = version.clone() {
-                if module_path.is_empty() {
-                    format!("https://docs.rs/{}/{}/{}/{}.{}.html", crate_name, ver, crate_name, item_type, item_name)
-                } else {
-                    format!("https://docs.rs/{}/{}/{}/{}/{}.{}.html", crate_name, ver, crate_name, module_path, item_type, item_name)
-                }
-            } else {
-                if module_path.is_empty() {
-                    format!("https://docs.rs/{}/latest/{}/{}.{}.html", crate_name, crate_name, item_type, item_name)
-                } else {
-                    format!("https://docs.rs/{}/latest/{}/{}/{}.{}.html", crate_name, crate_name, module_path, item_type, item_name)
+        // Fetch the documentation page, falling back to other sources when docs.rs has no build
+        let page = match self.fetcher.get_crate_page(crate_name, version).await {
+            Ok(page) if !is_failed_build_page(&page.html) => page,
+            result => {
+                // A 404 or a failed build lasts; any other error may be gone on the next lookup
+                let no_build = match &result {
+                    Ok(_) => true,
+                    Err(e) => error_data(e).is_some_and(|data| data.code == ErrorCode::NotFound),
+                };
+                let reason = match result {
+                    Err(e) => error_message(e),
+                    Ok(page) => format!("docs.rs failed to build {} ({})", crate_name, page.url),
+                };
+                // The standard library has no crates.io entry or repository to fall back to
+                if is_std_crate(crate_name) {
+                    let error = if no_build {
+                        DocsError::NotFound(reason)
+                    } else {
+                        DocsError::Unavailable { message: reason, retry_in: None }
+                    };
+                    return Err(error.into());
                 }
-            };
-            
-            // Try to fetch the documentation page
-            let response = match self.client.get(&url)
-                .header("User-Agent", "CrateDocs/0.1.0 (https://github.com/d6e/cratedocs-mcp)")
-                .send().await {
-                Ok(resp) => resp,
-                Err(e) => {
-                    last_error = Some(e.to_string());
-                    continue;
+                // Next in line are the fallback sources, such as the README crates.io shows
+                let doc = match self.fetcher.get_fallback_doc(crate_name, version, &reason).await {
+                    Ok(doc) => doc,
+                    Err(e) => return Err(self.missing_crate_error(crate_name, version, e).await),
+                };
+                // Cached fallbacks never expire, so one served during an outage would keep
+                // standing in for the docs once docs.rs is back, as would a default branch
+                // that moves on
+                if no_build && doc.pinned {
+                    self.cache.set(cache_key, doc.markdown.clone()).await;
                 }
-            };
-            
-            // If found, process and return
-            if response.status().is_success() {
-                let html_body = response.text().await.map_err(|e| {
-                    ToolError::ExecutionError(format!("Failed to read response body: {}", e))
-                })?;
-                
-                // Convert HTML to markdown
-                let markdown_body = parse_html(&html_body);
-                
-                // Cache the markdown result
-                self.cache.set(cache_key, markdown_body.clone()).await;
-                
-                return Ok(markdown_body);
-            }
-            
-            last_error = Some(format!("Status code: {}", response.status()));
-        }
+                return Ok((mark_stale(doc.markdown), None));
+            }
+        };
+        let resolved_version = page.resolved_version(crate_name).or_else(|| version.map(str::to_string));
         
-        // If we got here, none of the item types worked
-        Err(ToolError::ExecutionError(format!(
-            "Failed to fetch item documentation. No matching item found. Last error: {}",
-            last_error.unwrap_or_else(|| "Unknown error".to_string())
-        )))
+        // Convert HTML to markdown
+        let markdown_body = with_doc_header(crate_name, resolved_version.as_deref(), &page.url, html_to_markdown(&page.html));
+
+        // Cache the markdown result under the concrete version when "latest" couldn't
+        // be resolved up front but the page told us which version it is
+        let cache_key = match resolved_version.filter(|_| version.is_none()) {
+            Some(resolved) => {
+                self.latest_versions.set(crate_name, &resolved).await;
+                format!("{}:{}", crate_name, resolved)
+            }
+            None => cache_key,
+        };
+        self.cache.set(cache_key, markdown_body.clone()).await;
+        
+        Ok((mark_stale(markdown_body), Some(page.html)))
     }
-}
 
-impl mcp_server::Router for DocRouter {
+    // Update the session's defaults from the arguments of `configure_session`, returning them
+    fn configure_session(&self, arguments: &Value) -> Result<SessionSettings, ToolError> {
+        let reset = arguments.get("reset").and_then(|v| v.as_bool()).unwrap_or(false);
+        let mut settings = if reset { SessionSettings::default() } else { self.session_defaults.get() };
+        match arguments.get("channel").and_then(|v| v.as_str()) {
+            Some("none") => settings.channel = None,
+            Some(channel) if CHANNELS.contains(&channel) => settings.channel = Some(channel.to_string()),
+            Some(channel) => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Unknown channel {}. Expected stable, beta, nightly or none",
+                    channel
+                )))
+            }
+            None => {}
+        }
+        if let Some(format) = arguments.get("default_format").and_then(|v| v.as_str()) {
+            settings.format = Some(OutputFormat::parse(format).ok_or_else(|| {
+                ToolError::InvalidParameters(format!("Unknown format {}. Expected markdown, text or html", format))
+            })?);
+        }
+        if let Some(summary) = arguments.get("summary").and_then(|v| v.as_bool()) {
+            settings.summary = summary;
+        }
+        if let Some(tokens) = arguments.get("token_budget").and_then(|v| v.as_u64()) {
+            settings.token_budget = (tokens > 0).then_some(tokens as usize);
+        }
+        self.session_defaults.set(settings.clone());
+        Ok(settings)
+    }
+
+    // Whether a crate is published, suggesting the name with `-` and `_` swapped when it isn't
+    async fn crate_exists(&self, crate_name: String) -> Result<String, ToolError> {
+        if self.published_versions(&crate_name).await?.is_some() {
+            return Ok(json!({ "crate_name": crate_name, "exists": true }).to_string());
+        }
+        let mut result = json!({ "crate_name": crate_name, "exists": false });
+        if let Some(similar) = self.similar_crate_name(&crate_name).await {
+            result["did_you_mean"] = json!(similar);
+        }
+        Ok(result.to_string())
+    }
+
+    // Whether a version of a crate is published, and if so whether it was yanked
+    async fn version_exists(&self, crate_name: String, version: String) -> Result<String, ToolError> {
+        let Some(versions) = self.published_versions(&crate_name).await? else {
+            let mut result = json!({ "crate_name": crate_name, "version": version, "exists": false, "crate_exists": false });
+            if let Some(similar) = self.similar_crate_name(&crate_name).await {
+                result["did_you_mean"] = json!(similar);
+            }
+            return Ok(result.to_string());
+        };
+        let result = match find_version(&versions, &version) {
+            Some(entry) => json!({ "crate_name": crate_name, "version": version, "exists": true, "yanked": entry.yanked }),
+            None => json!({
+                "crate_name": crate_name,
+                "version": version,
+                "exists": false,
+                "crate_exists": true,
+                "latest_version": latest_version(&versions),
+            }),
+        };
+        Ok(result.to_string())
+    }
+
+    // The latest release of a crate and the newest pre-release when it's newer, from the
+    // sparse index or else the crates.io API
+    async fn latest_version(&self, crate_name: String) -> Result<String, ToolError> {
+        let (stable, prerelease) = match self.published_versions(&crate_name).await {
+            Ok(Some(versions)) => (latest_stable_version(&versions), latest_prerelease(&versions)),
+            Ok(None) => return Err(crate_not_found(format!("Crate {} not found", crate_name), &crate_name)),
+            Err(e) => {
+                tracing::debug!("Sparse index unavailable for {}: {}", crate_name, e);
+                let body = self.fetcher.get_crates_io_api(&format!("crates/{}", crate_name)).await?;
+                parse_latest_versions(&body).ok_or_else(|| {
+                    DocsError::Decode(format!("Failed to read the versions of {} from crates.io", crate_name))
+                })?
+            }
+        };
+        if let Some(stable) = &stable {
+            self.latest_versions.set(&crate_name, stable).await;
+        }
+        Ok(json!({ "crate_name": crate_name, "latest": stable, "latest_prerelease": prerelease }).to_string())
+    }
+
+    // Every published version of a crate from the sparse index, `None` when the crate isn't published
+    async fn published_versions(&self, crate_name: &str) -> Result<Option<Vec<IndexVersion>>, ToolError> {
+        match self.fetcher.get_index_entry(crate_name).await {
+            Ok(body) => Ok(Some(parse_index_entry(&body))),
+            Err(e) if error_data(&e).is_some_and(|data| data.code == ErrorCode::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    // The crate name with `-` and `_` swapped, when that one is published
+    async fn similar_crate_name(&self, crate_name: &str) -> Option<String> {
+        let swapped: String = crate_name
+            .chars()
+            .map(|c| match c {
+                '-' => '_',
+                '_' => '-',
+                c => c,
+            })
+            .collect();
+        if swapped == crate_name {
+            return None;
+        }
+        matches!(self.published_versions(&swapped).await, Ok(Some(_))).then_some(swapped)
+    }
+
+    // Tell a crate or version that isn't published apart from other lookup failures,
+    // going by the sparse index
+    async fn missing_crate_error(&self, crate_name: &str, version: Option<&str>, error: ToolError) -> ToolError {
+        match self.fetcher.get_index_entry(crate_name).await {
+            Ok(body) => {
+                let versions = parse_index_entry(&body);
+                match version {
+                    Some(version) if find_version(&versions, version).is_none() => {
+                        version_not_found(error_message(error), &versions)
+                    }
+                    _ => error,
+                }
+            }
+            Err(e) if error_data(&e).is_some_and(|data| data.code == ErrorCode::NotFound) => {
+                crate_not_found(error_message(error), crate_name)
+            }
+            Err(_) => error,
+        }
+    }
+
+    // Pin an unversioned lookup to the concrete version "latest" currently points to,
+    // or `None` when it can't be resolved (the lookup then falls back to "latest")
+    async fn resolve_version(&self, crate_name: &str, version: Option<&str>) -> Option<String> {
+        self.resolve_version_or_stale(crate_name, version).await.0
+    }
+
+    // Like `resolve_version`, also saying whether the version is an expired "latest"
+    // served because crates.io couldn't be asked for the current one
+    async fn resolve_version_or_stale(&self, crate_name: &str, version: Option<&str>) -> (Option<String>, bool) {
+        if let Some(ver) = version {
+            return (Some(ver.to_string()), false);
+        }
+        // Standard library docs are versioned by channel, which "stable" stands in for
+        if is_std_crate(crate_name) {
+            return (None, false);
+        }
+        if let Some(local_docs) = &self.local_docs {
+            if let Some(build) = local_docs.build_version(crate_name).await {
+                return (Some(build), false);
+            }
+        }
+        if let Some(ver) = self.latest_versions.get(crate_name).await {
+            return (Some(ver), false);
+        }
+
+        match self.latest_published_version(crate_name).await {
+            Ok(ver) => {
+                let Some(ver) = ver else {
+                    return (None, false);
+                };
+                self.latest_versions.set(crate_name, &ver).await;
+                (Some(ver), false)
+            }
+            Err(e) => {
+                tracing::debug!("Failed to resolve latest version of {}: {}", crate_name, e);
+                match self.latest_versions.get_stale(crate_name).await {
+                    Some(ver) => (Some(ver), true),
+                    None => (None, false),
+                }
+            }
+        }
+    }
+
+    // The version crates.io currently calls latest, from the sparse index or
+    // else the crates.io API (`None` if the crate has no such version)
+    async fn latest_published_version(&self, crate_name: &str) -> Result<Option<String>, ToolError> {
+        if let Some(versions) = self.index_versions(crate_name).await {
+            return Ok(latest_version(&versions));
+        }
+        let body = self.fetcher.get_crates_io_api(&format!("crates/{}", crate_name)).await?;
+        Ok(parse_max_version(&body))
+    }
+
+    // Every published version of a crate from the sparse index, or `None` when the
+    // index can't be read and the crates.io API has to be asked instead
+    async fn index_versions(&self, crate_name: &str) -> Option<Vec<IndexVersion>> {
+        match self.fetcher.get_index_entry(crate_name).await {
+            Ok(body) => Some(parse_index_entry(&body)).filter(|versions| !versions.is_empty()),
+            Err(e) => {
+                tracing::debug!("Sparse index unavailable for {}: {}", crate_name, e);
+                None
+            }
+        }
+    }
+
+    // Prefetch the most-linked items of a crate page and its direct dependencies
+    async fn prefetch_related(&self, crate_name: String, version: Option<String>, html_body: String, config: PrefetchConfig) {
+        let mut tasks: Vec<BoxFuture<'_, ()>> = Vec::new();
+
+        for item_path in linked_item_paths(&html_body, &crate_name).into_iter().take(config.max_items) {
+            let crate_name = crate_name.clone();
+            let version = version.clone();
+            tasks.push(async move {
+                if let Err(e) = self.lookup_item(crate_name.clone(), item_path.clone(), version).await {
+                    tracing::debug!("Prefetch of {}::{} failed: {}", crate_name, item_path, e);
+                }
+            }.boxed());
+        }
+
+        match self.direct_dependencies(&crate_name, version.as_deref()).await {
+            Ok(dependencies) => {
+                for dependency in dependencies.into_iter().take(config.max_dependencies) {
+                    tasks.push(async move {
+                        if let Err(e) = self.fetch_crate_doc(&dependency, None).await {
+                            tracing::debug!("Prefetch of crate {} failed: {}", dependency, e);
+                        }
+                    }.boxed());
+                }
+            }
+            Err(e) => tracing::debug!("Failed to list dependencies of {}: {}", crate_name, e),
+        }
+
+        futures::stream::iter(tasks)
+            .for_each_concurrent(config.concurrency.max(1), |task| task)
+            .await;
+    }
+
+    // List the required direct dependencies of a crate version
+    async fn direct_dependencies(&self, crate_name: &str, version: Option<&str>) -> Result<Vec<String>, ToolError> {
+        let version = self.resolve_version(crate_name, version).await.ok_or_else(|| {
+            DocsError::NotFound(format!("No published version found for {}", crate_name))
+        })?;
+
+        if let Some(entry) = self.index_versions(crate_name).await.and_then(|versions| find_version(&versions, &version).cloned()) {
+            return Ok(entry
+                .dependencies
+                .into_iter()
+                .filter(|dep| dep.kind == "normal" && !dep.optional)
+                .map(|dep| dep.name)
+                .collect());
+        }
+        let body = self
+            .fetcher
+            .get_crates_io_api(&format!("crates/{}/{}/dependencies", crate_name, version))
+            .await?;
+        Ok(parse_direct_dependencies(&body))
+    }
+
+    // Build the Cargo.toml entry and `cargo add` command for a crate, pinned to the
+    // latest (or the given) published version
+    async fn dependency_snippet(
+        &self,
+        crate_name: String,
+        version: Option<String>,
+        features: Vec<String>,
+        policy: VersionPolicy,
+    ) -> Result<String, ToolError> {
+        let version = self
+            .resolve_version(&crate_name, version.as_deref())
+            .await
+            .ok_or_else(|| crate_not_found(format!("Crate {} not found on crates.io", crate_name), &crate_name))?;
+
+        // Confirms the version exists and tells us which features it declares
+        let indexed = self.index_versions(&crate_name).await.and_then(|versions| find_version(&versions, &version).cloned());
+        let known_features = match indexed {
+            Some(entry) => Some(entry.features.into_keys().collect::<Vec<_>>()),
+            None => {
+                let body = self
+                    .fetcher
+                    .get_crates_io_api(&format!("crates/{}/{}", crate_name, version))
+                    .await
+                    .map_err(|e| {
+                        // Upstream trouble keeps its own code
+                        let upstream = error_data(&e).filter(|data| data.code != ErrorCode::NotFound);
+                        let message = format!("Version {} of {} not found on crates.io: {}", version, crate_name, error_message(e));
+                        match upstream {
+                            Some(data) => with_error_data(ToolError::ExecutionError(message), data),
+                            None => version_not_found(message, &[]),
+                        }
+                    })?;
+                parse_version_features(&body)
+            }
+        };
+
+        let mut output = format!(
+            "{} {}\n\n{}",
+            crate_name,
+            version,
+            dependency_snippet(&crate_name, &policy.requirement(&version), &features)
+        );
+
+        // Features enabled through optional dependencies may not be listed, so
+        // unknown names are flagged rather than rejected
+        if let Some(known) = known_features {
+            let unknown: Vec<&str> = features
+                .iter()
+                .filter(|feature| !known.contains(feature))
+                .map(|feature| feature.as_str())
+                .collect();
+            if !unknown.is_empty() {
+                output.push_str(&format!(
+                    "\nNote: {} {} does not declare these features: {} (declared: {})\n",
+                    crate_name,
+                    version,
+                    unknown.join(", "),
+                    known.join(", ")
+                ));
+            }
+        }
+
+        Ok(output)
+    }
+
+    // Resolve the dependency requirements of a crate version to the concrete versions
+    // a fresh build would pick, reading index entries a round at a time
+    async fn resolve_graph(
+        &self,
+        crate_name: &str,
+        version: Option<String>,
+        features: &[String],
+        default_features: bool,
+        transitive: bool,
+    ) -> Result<Resolution, ToolError> {
+        let versions = self.index_versions(crate_name).await.ok_or_else(|| {
+            crate_not_found(format!("Crate {} not found in the crates.io index", crate_name), crate_name)
+        })?;
+        let version = match version {
+            Some(version) => version,
+            None => latest_version(&versions)
+                .ok_or_else(|| DocsError::NotFound(format!("No published version found for {}", crate_name)))?,
+        };
+        if find_version(&versions, &version).is_none() {
+            let message = format!("Version {} of {} not found in the crates.io index", version, crate_name);
+            return Err(version_not_found(message, &versions));
+        }
+
+        let mut index = std::collections::HashMap::from([(crate_name.to_string(), versions)]);
+        let mut unavailable = std::collections::BTreeSet::new();
+        loop {
+            let resolution = resolve(crate_name, &version, features, default_features, transitive, &index, &unavailable);
+            let budget = MAX_RESOLVED_CRATES.saturating_sub(index.len() + unavailable.len());
+            if resolution.missing.is_empty() || budget == 0 {
+                return Ok(resolution);
+            }
+
+            let fetched: Vec<(String, Option<Vec<IndexVersion>>)> = futures::stream::iter(resolution.missing.into_iter().take(budget))
+                .map(|name| async move {
+                    let versions = self.index_versions(&name).await;
+                    (name, versions)
+                })
+                .buffer_unordered(PRELOAD_CONCURRENCY)
+                .collect()
+                .await;
+            for (name, versions) in fetched {
+                match versions {
+                    Some(versions) => {
+                        index.insert(name, versions);
+                    }
+                    None => {
+                        unavailable.insert(name);
+                    }
+                }
+            }
+        }
+    }
+
+    // Resolve the dependencies of a crate version and render them as markdown lists,
+    // or as a tree `tree_depth` levels deep
+    async fn resolve_dependencies(
+        &self,
+        crate_name: String,
+        version: Option<String>,
+        features: Vec<String>,
+        default_features: bool,
+        transitive: bool,
+        tree_depth: Option<usize>,
+    ) -> Result<String, ToolError> {
+        let resolution = self.resolve_graph(&crate_name, version, &features, default_features, transitive).await?;
+        Ok(match tree_depth {
+            Some(depth) => format_tree(&resolution, depth),
+            None => format_resolution(&resolution),
+        })
+    }
+
+    // Estimate what depending on a crate adds: how many crates it pulls in and how
+    // much they weigh to download, with `.crate` sizes from the crates.io API
+    async fn dependency_weight(
+        &self,
+        crate_name: String,
+        version: Option<String>,
+        features: Vec<String>,
+        default_features: bool,
+    ) -> Result<String, ToolError> {
+        let resolution = self.resolve_graph(&crate_name, version, &features, default_features, true).await?;
+
+        let crates = std::iter::once((resolution.crate_name.clone(), resolution.version.clone()))
+            .chain(resolution.crates.iter().map(|resolved| (resolved.name.clone(), resolved.version.clone())));
+        let sizes: std::collections::HashMap<String, u64> = futures::stream::iter(crates)
+            .map(|(name, version)| async move {
+                let size = match self.fetcher.get_crates_io_api(&format!("crates/{}/{}", name, version)).await {
+                    Ok(body) => parse_crate_size(&body),
+                    Err(e) => {
+                        tracing::debug!("Failed to read the size of {} {}: {}", name, version, e);
+                        None
+                    }
+                };
+                size.map(|size| (format!("{} {}", name, version), size))
+            })
+            .buffer_unordered(PRELOAD_CONCURRENCY)
+            .filter_map(|size| async move { size })
+            .collect()
+            .await;
+        Ok(format_weight(&resolution, &sizes))
+    }
+
+    // Count the files, lines of Rust and `unsafe` uses in the published source of a crate version
+    async fn crate_source_stats(&self, crate_name: String, version: Option<String>) -> Result<String, ToolError> {
+        let version = self
+            .resolve_version(&crate_name, version.as_deref())
+            .await
+            .ok_or_else(|| crate_not_found(format!("Crate {} not found on crates.io", crate_name), &crate_name))?;
+
+        let cache_key = format!("source-stats:{}:{}", crate_name, version);
+        if let Some(report) = self.cache.get(&cache_key).await {
+            return Ok(report);
+        }
+
+        let archive = self.fetcher.get_crate_archive(&crate_name, &version).await?;
+        let stats = source_stats(&archive).map_err(|e| DocsError::Decode(format!("{} {}: {}", crate_name, version, e)))?;
+        let report = format_source_stats(&crate_name, &version, &stats);
+        self.cache.set(cache_key, report.clone()).await;
+        Ok(report)
+    }
+
+    // Show which features of a crate version turn on which other features and optional dependencies
+    async fn feature_graph(&self, crate_name: String, version: Option<String>, feature: Option<String>) -> Result<String, ToolError> {
+        let versions = self.index_versions(&crate_name).await.ok_or_else(|| {
+            crate_not_found(format!("Crate {} not found in the crates.io index", crate_name), &crate_name)
+        })?;
+        let version = match version {
+            Some(version) => version,
+            None => latest_version(&versions)
+                .ok_or_else(|| DocsError::NotFound(format!("No published version found for {}", crate_name)))?,
+        };
+        let entry = find_version(&versions, &version).ok_or_else(|| {
+            version_not_found(format!("Version {} of {} not found in the crates.io index", version, crate_name), &versions)
+        })?;
+
+        if let Some(feature) = &feature {
+            if !has_feature(entry, feature) {
+                let declared: Vec<&str> = entry.features.keys().map(|name| name.as_str()).collect();
+                return Err(ToolError::InvalidParameters(format!(
+                    "{} {} has no feature {} (declared: {})",
+                    crate_name,
+                    version,
+                    feature,
+                    declared.join(", ")
+                )));
+            }
+        }
+        Ok(format_feature_graph(&crate_name, entry, feature.as_deref()))
+    }
+
+    // List the targets docs.rs documented a crate version for, saying whether `target` is one of them
+    async fn doc_targets(&self, crate_name: String, version: Option<String>, target: Option<String>) -> Result<String, ToolError> {
+        if is_std_crate(&crate_name) {
+            return Err(ToolError::InvalidParameters(format!(
+                "{} is documented on doc.rust-lang.org, which has no per-target builds",
+                crate_name
+            )));
+        }
+        let version = self.resolve_version(&crate_name, version.as_deref()).await;
+        let cache_key = version.as_ref().map(|ver| format!("doc-targets:{}:{}", crate_name, ver));
+        let cached = match &cache_key {
+            Some(key) => self.cache.get(key).await.and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok()),
+            None => None,
+        };
+        let (version, targets) = match cached {
+            Some(targets) => (version.unwrap_or_default(), targets),
+            None => {
+                let page = self.fetcher.get_crate_page(&crate_name, version.as_deref()).await?;
+                let resolved = page.resolved_version(&crate_name).or(version).unwrap_or_else(|| "latest".to_string());
+                if is_failed_build_page(&page.html) {
+                    return Err(DocsError::NotFound(format!(
+                        "docs.rs failed to build {} {}, so it has no documented targets ({})",
+                        crate_name, resolved, page.url
+                    ))
+                    .into());
+                }
+                let targets = parse_doc_targets(&page.html);
+                if targets.is_empty() {
+                    return Err(DocsError::Decode(format!(
+                        "docs.rs lists no targets for {} {} ({})",
+                        crate_name, resolved, page.url
+                    ))
+                    .into());
+                }
+                if resolved != "latest" {
+                    let json = serde_json::to_string(&targets).expect("target names always serialize");
+                    self.cache.set(format!("doc-targets:{}:{}", crate_name, resolved), json).await;
+                }
+                (resolved, targets)
+            }
+        };
+        Ok(format_doc_targets(&crate_name, &version, &targets, target.as_deref().map(str::trim)))
+    }
+
+    // Report which crates define an item with the given name, searching the rustdoc
+    // indexes of the given crates, the top crates.io matches and popular crates
+    async fn find_defining_crate(&self, name: String, crates: Vec<String>) -> Result<String, ToolError> {
+        // Only the last segment is looked up, so `serde::Deserialize` works too
+        let name = name.rsplit("::").next().unwrap_or(&name).trim().to_string();
+        if name.is_empty() {
+            return Err(ToolError::InvalidParameters("name must not be empty".to_string()));
+        }
+
+        let search_results = match self.fetcher.search(&name, DEFINING_CRATE_SEARCH_RESULTS).await {
+            Ok(body) => parse_search_crate_names(&body),
+            Err(e) => {
+                tracing::debug!("crates.io search for {} failed: {}", name, e);
+                Vec::new()
+            }
+        };
+        let mut candidates: Vec<String> = Vec::new();
+        for crate_name in crates
+            .into_iter()
+            .chain(search_results)
+            .chain(POPULAR_CRATES.iter().map(|c| c.to_string()))
+        {
+            if !candidates.contains(&crate_name) {
+                candidates.push(crate_name);
+            }
+        }
+
+        let name_ref = name.as_str();
+        let definitions: Vec<(String, Vec<IndexItem>)> = futures::stream::iter(candidates.iter())
+            .map(|crate_name| async move {
+                let index = self.search_index(crate_name, None).await?;
+                let items: Vec<IndexItem> = index.find(name_ref).into_iter().cloned().collect();
+                (!items.is_empty()).then(|| (crate_name.clone(), items))
+            })
+            .buffered(DEFINING_CRATE_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await;
+
+        Ok(format_definitions(&name, &definitions, candidates.len()))
+    }
+
+    // Tabulate the crates.io facts about several crates side by side
+    async fn compare_crates(&self, crate_names: Vec<String>) -> Result<String, ToolError> {
+        if crate_names.is_empty() || crate_names.len() > MAX_COMPARED_CRATES {
+            return Err(ToolError::InvalidParameters(format!(
+                "crate_names must contain between 1 and {} crates",
+                MAX_COMPARED_CRATES
+            )));
+        }
+
+        let responses = futures::future::join_all(crate_names.iter().map(|crate_name| async move {
+            let body = self.fetcher.get_crates_io_api(&format!("crates/{}", crate_name)).await;
+            (crate_name, body)
+        }))
+        .await;
+
+        let mut summaries = Vec::new();
+        let mut failed = Vec::new();
+        for (crate_name, body) in responses {
+            match body.ok().as_deref().and_then(parse_crate_summary) {
+                Some(summary) => {
+                    self.latest_versions.set(crate_name, &summary.version).await;
+                    summaries.push(summary);
+                }
+                None => failed.push(crate_name.clone()),
+            }
+        }
+        if summaries.is_empty() {
+            return Err(DocsError::NotFound(format!(
+                "None of the crates were found on crates.io: {}",
+                failed.join(", ")
+            ))
+            .into());
+        }
+        Ok(format_comparison(&summaries, &failed))
+    }
+
+    // Release notes of one Rust version, or of every release after `from_version` up to `version`
+    async fn rust_release_notes(&self, version: String, from_version: Option<String>) -> Result<String, ToolError> {
+        let parse = |version: &str| {
+            parse_rust_version(version).ok_or_else(|| {
+                ToolError::InvalidParameters(format!("Invalid Rust version {}. Expected e.g. 1.75 or 1.75.0", version))
+            })
+        };
+        let to = parse(&version)?;
+        let from = from_version.as_deref().map(parse).transpose()?;
+        if from.is_some_and(|from| from >= to) {
+            return Err(ToolError::InvalidParameters("from_version must be older than version".to_string()));
+        }
+
+        let cache_key = format!("rust-releases:{}..{}", from_version.as_deref().unwrap_or_default(), version);
+        if let Some(notes) = self.cache.get(&cache_key).await {
+            return Ok(notes);
+        }
+
+        let repo = RepoRef {
+            host: RepoHost::GitHub,
+            owner: "rust-lang".to_string(),
+            name: "rust".to_string(),
+            git_ref: None,
+        };
+        let body = self
+            .fetcher
+            .get_repository_file(&repo, RUST_RELEASES_FILE)
+            .await
+            .map_err(|e| DocsError::Unavailable {
+                message: format!("Failed to fetch Rust release notes: {}", error_message(e)),
+                retry_in: None,
+            })?;
+        let releases = parse_release_notes(&body);
+
+        let markdown = match (from, from_version.as_deref()) {
+            (Some(from), Some(from_version)) => {
+                let selected: Vec<&RustRelease> = releases
+                    .iter()
+                    .filter(|release| release.number().is_some_and(|number| number > from && number <= to))
+                    .collect();
+                if selected.is_empty() {
+                    return Err(ToolError::NotFound(format!(
+                        "No Rust releases after {} up to {}",
+                        from_version, version
+                    )));
+                }
+                let shown = selected.len().min(MAX_RUST_RELEASES);
+                format_release_notes(
+                    &format!("Changes from Rust {} to {}", from_version, version),
+                    &selected[..shown],
+                    selected.len() - shown,
+                )
+            }
+            _ => {
+                let release = releases
+                    .iter()
+                    .find(|release| release.number() == Some(to))
+                    .ok_or_else(|| ToolError::NotFound(format!("No release notes for Rust {}", version)))?;
+                format_release_notes(&format!("Rust {} release notes", release.version), &[release], 0)
+            }
+        };
+        self.cache.set(cache_key, markdown.clone()).await;
+        Ok(markdown)
+    }
+
+    // Release notes of a crate after `from_version` up to `to_version` (the latest version
+    // without one), from the changelog in its repository or else the releases on its code host
+    async fn release_notes(&self, crate_name: String, from_version: String, to_version: Option<String>) -> Result<String, ToolError> {
+        let parse = |version: &str| {
+            parse_loose_version(version).ok_or_else(|| {
+                ToolError::InvalidParameters(format!("Invalid version {}. Expected e.g. 0.7 or 0.7.1", version))
+            })
+        };
+        let from = parse(&from_version)?;
+        let to_version = match to_version {
+            Some(version) => Some(version),
+            None => self.latest_published_version(&crate_name).await.ok().flatten(),
+        };
+        let to = to_version.as_deref().map(parse).transpose()?;
+        if to.as_ref().is_some_and(|to| &from >= to) {
+            return Err(ToolError::InvalidParameters("from_version must be older than to_version".to_string()));
+        }
+
+        let cache_key = to_version
+            .as_ref()
+            .map(|to_version| format!("release-notes:{}:{}..{}", crate_name, from_version, to_version));
+        if let Some(key) = &cache_key {
+            if let Some(notes) = self.cache.get(key).await {
+                return Ok(notes);
+            }
+        }
+
+        let crate_info = self.fetcher.get_crates_io_api(&format!("crates/{}", crate_name)).await?;
+        let repo = parse_repository_url(&crate_info)
+            .and_then(|url| RepoRef::parse(&url))
+            .ok_or_else(|| {
+                ToolError::NotFound(format!(
+                    "{} lists no GitHub or GitLab repository to read release notes from",
+                    crate_name
+                ))
+            })?;
+
+        let title = format!(
+            "Changes in {} after {} up to {}",
+            crate_name,
+            from_version,
+            to_version.as_deref().unwrap_or("the latest version")
+        );
+        let mut found = None;
+        for file in CHANGELOG_FILES {
+            let path = file.replace("{crate}", &crate_name);
+            let Ok(changelog) = self.fetcher.get_repository_file(&repo, &path).await else {
+                continue;
+            };
+            let releases = parse_changelog(&changelog);
+            let selected = releases_between(&releases, &from, to.as_ref());
+            if !selected.is_empty() {
+                let shown = selected.len().min(MAX_RELEASE_NOTES);
+                found = Some(format_changes(&title, &repo.file_url(&path), &selected[..shown], selected.len() - shown));
+                break;
+            }
+        }
+        if found.is_none() {
+            match self.fetcher.list_repository_releases(&repo).await {
+                Ok(listing) => {
+                    let releases = parse_repository_releases(&listing, &crate_name).unwrap_or_default();
+                    let selected = releases_between(&releases, &from, to.as_ref());
+                    if !selected.is_empty() {
+                        let shown = selected.len().min(MAX_RELEASE_NOTES);
+                        found = Some(format_changes(&title, &repo.releases_url(), &selected[..shown], selected.len() - shown));
+                    }
+                }
+                Err(e) => tracing::debug!("Failed to list releases of {}: {}", repo.url(), error_message(e)),
+            }
+        }
+
+        let notes = found.ok_or_else(|| {
+            ToolError::NotFound(format!(
+                "No release notes for {} after {} up to {} in the changelog or releases of {}",
+                crate_name,
+                from_version,
+                to_version.as_deref().unwrap_or("the latest version"),
+                repo.url()
+            ))
+        })?;
+        if let Some(key) = cache_key {
+            self.cache.set(key, notes.clone()).await;
+        }
+        Ok(notes)
+    }
+
+    // Read the chapter of a book best matching `topic`, or list the chapters without one.
+    // `section` limits the search to chapters below that path (e.g. `rust-2021/`).
+    async fn lookup_book(&self, book: Book, topic: Option<String>, section: Option<&str>) -> Result<String, ToolError> {
+        // mdBook 0.4.41+ serves the sidebar separately; older books only have it inline
+        let toc_key = format!("book:{}:toc", book.path());
+        let toc = match self.cache.get(&toc_key).await {
+            Some(toc) => toc,
+            None => {
+                let page = match self.fetcher.get_book_page(book, "toc.html").await {
+                    Ok(page) => page,
+                    Err(_) => self.fetcher.get_book_page(book, "index.html").await?,
+                };
+                self.cache.set(toc_key, page.html.clone()).await;
+                page.html
+            }
+        };
+        let chapters: Vec<_> = parse_chapters(&toc)
+            .into_iter()
+            .filter(|chapter| section.map_or(true, |section| chapter.path.starts_with(section)))
+            .collect();
+        if chapters.is_empty() {
+            return Err(DocsError::Decode(format!("No chapters found in {}", book.title())).into());
+        }
+
+        let Some(topic) = topic.filter(|topic| !topic.trim().is_empty()) else {
+            return Ok(format_chapter_list(book, &chapters));
+        };
+        let chapter = find_chapter(&chapters, &topic).ok_or_else(|| {
+            ToolError::NotFound(format!(
+                "No chapter of {} matches '{}'. Call without a topic to list the chapters",
+                book.title(),
+                topic
+            ))
+        })?;
+
+        let cache_key = format!("book:{}:{}", book.path(), chapter.path);
+        if let Some(doc) = self.cache.get(&cache_key).await {
+            return Ok(doc);
+        }
+        let page = self.fetcher.get_book_page(book, &chapter.path).await?;
+        let markdown = format!(
+            "{}: {}\nSource: {}\n\n{}",
+            book.title(),
+            chapter.title,
+            page.url,
+            html_to_markdown(&chapter_content(&page.html))
+        );
+        self.cache.set(cache_key, markdown.clone()).await;
+        Ok(markdown)
+    }
+
+    // Fetch the page a docs.rs or doc.rust-lang.org URL points to, narrowed to the
+    // section of its `#anchor` when the page has one
+    async fn lookup_url(&self, url: String) -> Result<String, ToolError> {
+        let (target, anchor) = DocUrl::parse(&url).map_err(ToolError::InvalidParameters)?;
+        let pinned = target.is_pinned();
+        let cache_key = format!("url:{}", url.trim());
+        if pinned {
+            if let Some(doc) = self.cache.get(&cache_key).await {
+                return Ok(doc);
+            }
+        }
+
+        let page = self.fetch_doc_url(&target).await?;
+        let section = anchor.as_deref().and_then(|anchor| anchor_markdown(&page.html, anchor));
+        let source = match (&anchor, &section) {
+            (Some(anchor), Some(_)) => format!("{}#{}", page.url, anchor),
+            _ => page.url.clone(),
+        };
+        let markdown = match (&target, section) {
+            (_, Some(section)) => section,
+            (DocUrl::Book { .. }, None) => html_to_markdown(&chapter_content(&page.html)),
+            (_, None) => html_to_markdown(&page.html),
+        };
+
+        let doc = match &target {
+            DocUrl::Book { book, .. } => format!("{}\nSource: {}\n\n{}", book.title(), source, markdown),
+            DocUrl::CrateOverview { crate_name, version } | DocUrl::Page { crate_name, version, .. } => {
+                let version = page.resolved_version(crate_name).or_else(|| version.clone());
+                with_doc_header(crate_name, version.as_deref(), &source, markdown)
+            }
+        };
+        if pinned {
+            self.cache.set(cache_key, doc.clone()).await;
+        }
+        Ok(doc)
+    }
+
+    async fn fetch_doc_url(&self, target: &DocUrl) -> Result<DocPage, ToolError> {
+        match target {
+            DocUrl::CrateOverview { crate_name, version } => self.fetcher.get_crate_page(crate_name, version.as_deref()).await,
+            DocUrl::Page { crate_name, version, path } => self.fetcher.get_item_page(crate_name, version.as_deref(), path).await,
+            DocUrl::Book { book, path } => self.fetcher.get_book_page(*book, path).await,
+        }
+    }
+
+    // Footnotes linking the item of a lookup result, and the members and items it mentions,
+    // to their docs.rs pages and source, read from the page named by its `Source:` line
+    async fn source_links(&self, doc: &str) -> String {
+        let Some(url) = doc.lines().find_map(|line| line.strip_prefix("Source: ")) else {
+            return String::new();
+        };
+        let Ok((target, _)) = DocUrl::parse(url) else {
+            return "\n\nSource links: none, this documentation doesn't come from docs.rs or doc.rust-lang.org\n".to_string();
+        };
+
+        let cache_key = format!("links:{}", url.split('#').next().unwrap_or(url));
+        let cached = if target.is_pinned() {
+            self.cache.get(&cache_key).await.and_then(|json| serde_json::from_str::<Vec<PageLink>>(&json).ok())
+        } else {
+            None
+        };
+        let links = match cached {
+            Some(links) => links,
+            None => match self.fetch_doc_url(&target).await {
+                Ok(page) => {
+                    let links = page_links(&page.html, &page.url);
+                    if target.is_pinned() {
+                        let json = serde_json::to_string(&links).expect("page links always serialize");
+                        self.cache.set(cache_key, json).await;
+                    }
+                    links
+                }
+                Err(e) => return format!("\n\nSource links unavailable: {}\n", error_message(e)),
+            },
+        };
+        format_source_links(doc, &links)
+    }
+
+    // Heading outline of a crate's root page or an item's page, to pick a `section` from
+    async fn page_outline(&self, crate_name: String, item_path: Option<String>, version: Option<String>) -> Result<String, ToolError> {
+        let doc = match item_path {
+            Some(item_path) => self.lookup_item(crate_name, item_path, version).await?,
+            None => self.lookup_crate(crate_name, version).await?,
+        };
+        match preamble(&doc) {
+            "" => Ok(format_outline(&doc)),
+            preamble => Ok(format!("{}\n\n{}", preamble, format_outline(&doc))),
+        }
+    }
+
+    // Document a local workspace with `cargo doc` so its crates can be looked up like published ones
+    async fn build_local_docs(&self, path: String) -> Result<String, ToolError> {
+        let local_docs = self.local_docs.as_ref().ok_or_else(|| {
+            ToolError::ExecutionError(
+                "Local documentation builds are disabled; start the server with --local-docs-root".to_string(),
+            )
+        })?;
+        let (dir, crates) = local_docs.build(&path).await?;
+        if crates.is_empty() {
+            return Err(ToolError::ExecutionError(format!(
+                "cargo doc produced no crate documentation in {}",
+                dir.display()
+            )));
+        }
+        Ok(format!(
+            "Documented {} crate(s) in {}: {}\nLook them up with lookup_crate and lookup_item.\n",
+            crates.len(),
+            dir.display(),
+            crates.join(", ")
+        ))
+    }
+
+    // A digest of every dependency a Cargo.toml declares, read from `manifest` or from the
+    // Cargo.toml of the workspace at `path` (below a --local-docs-root only)
+    async fn project_dependencies(&self, manifest: Option<String>, path: Option<String>) -> Result<String, ToolError> {
+        let manifest = match (manifest, path) {
+            (Some(manifest), _) => manifest,
+            (None, Some(path)) => {
+                let local_docs = self.local_docs.as_ref().ok_or_else(|| {
+                    ToolError::InvalidParameters(
+                        "Reading manifests by path needs --local-docs-root; pass the Cargo.toml contents as manifest instead"
+                            .to_string(),
+                    )
+                })?;
+                let file = local_docs.workspace_dir(&path)?.join("Cargo.toml");
+                tokio::fs::read_to_string(&file)
+                    .await
+                    .map_err(|e| ToolError::ExecutionError(format!("Failed to read {}: {}", file.display(), e)))?
+            }
+            (None, None) => return Err(ToolError::InvalidParameters("manifest or path is required".to_string())),
+        };
+
+        let mut dependencies = parse_manifest_dependencies(&manifest).map_err(ToolError::InvalidParameters)?;
+        let project = manifest_package_name(&manifest).unwrap_or_else(|| "the workspace".to_string());
+        let unlisted: Vec<String> = dependencies
+            .split_off(dependencies.len().min(MAX_DIGEST_DEPENDENCIES))
+            .into_iter()
+            .map(|dependency| dependency.name)
+            .collect();
+        let digests: Vec<DependencyDigest> = futures::stream::iter(dependencies)
+            .map(|dependency| self.dependency_digest(dependency))
+            .buffered(PRELOAD_CONCURRENCY)
+            .collect()
+            .await;
+        Ok(format_dependency_digest(&project, &digests, &unlisted))
+    }
+
+    // Look up one dependency on crates.io and in the sparse index; failures leave the
+    // digest without the facts they would have given
+    async fn dependency_digest(&self, dependency: ManifestDependency) -> DependencyDigest {
+        let mut digest = DependencyDigest {
+            dependency,
+            summary: None,
+            resolved: None,
+            default_features: Vec::new(),
+            other_features: Vec::new(),
+        };
+        if digest.dependency.source.is_some() {
+            return digest;
+        }
+        let crate_name = digest.dependency.crate_name().to_string();
+        digest.summary = match self.fetcher.get_crates_io_api(&format!("crates/{}", crate_name)).await {
+            Ok(body) => parse_crate_summary(&body),
+            Err(e) => {
+                tracing::debug!("Failed to look up {} on crates.io: {}", crate_name, e);
+                None
+            }
+        };
+
+        let Some(versions) = self.index_versions(&crate_name).await else {
+            return digest;
+        };
+        digest.resolved = digest
+            .dependency
+            .req
+            .as_deref()
+            .and_then(VersionReq::parse)
+            .and_then(|req| newest_matching(&versions, &req));
+        let version = digest.resolved.clone().or_else(|| latest_version(&versions));
+        if let Some(entry) = version.and_then(|version| find_version(&versions, &version)) {
+            digest.default_features = entry
+                .features
+                .get("default")
+                .into_iter()
+                .flatten()
+                .filter(|feature| !feature.starts_with("dep:"))
+                .cloned()
+                .collect();
+            digest.other_features = entry
+                .features
+                .keys()
+                .filter(|feature| *feature != "default" && !feature.starts_with('_'))
+                .cloned()
+                .collect();
+        }
+        digest
+    }
+
+    // Find the paths a source snippet imports or spells out, map each to its crate through
+    // the manifest's dependencies (taking the root as the crate's name without one) and
+    // return the opening of every item's docs. Roots that aren't a crate, such as the
+    // project's own modules, are searched for by the item's name instead.
+    async fn explain_imports(&self, source: String, manifest: Option<String>) -> Result<String, ToolError> {
+        let dependencies = match &manifest {
+            Some(manifest) => parse_manifest_dependencies(manifest).map_err(ToolError::InvalidParameters)?,
+            None => Vec::new(),
+        };
+        // Dependencies are imported under their name with dashes turned into underscores
+        let known_roots: Vec<String> = dependencies.iter().map(|dependency| dependency.name.replace('-', "_")).collect();
+
+        let mut paths = imported_paths(&source, &known_roots);
+        if paths.is_empty() {
+            return Err(ToolError::InvalidParameters(
+                "No use declarations or qualified paths to crate items found in the source".to_string(),
+            ));
+        }
+        let unlisted = paths.split_off(paths.len().min(MAX_EXPLAINED_IMPORTS));
+
+        // The crate and version behind each root the paths use
+        let mut targets: Vec<(String, ImportTarget)> = Vec::new();
+        for root in paths.iter().filter_map(|path| path.split("::").next()) {
+            if targets.iter().any(|(known, _)| known == root) {
+                continue;
+            }
+            let dependency = known_roots
+                .iter()
+                .position(|known| known == root)
+                .map(|i| &dependencies[i]);
+            let target = match dependency {
+                Some(dependency) => match &dependency.source {
+                    Some(source) => ImportTarget::NotOnDocsRs(format!("{} ({})", dependency.name, source)),
+                    None => ImportTarget::Crate(dependency.crate_name().to_string(), self.manifest_version(dependency).await),
+                },
+                None if STD_ROOTS.contains(&root) => ImportTarget::Crate(root.to_string(), None),
+                // Without a manifest any root may be a crate, and is searched for if it isn't
+                None if manifest.is_none() => ImportTarget::MaybeCrate(root.to_string()),
+                None => ImportTarget::Unknown,
+            };
+            targets.push((root.to_string(), target));
+        }
+
+        let crate_names: Vec<String> = dependencies.iter().map(|dependency| dependency.crate_name().to_string()).collect();
+        let (targets, crate_names) = (&targets, &crate_names);
+        let sections: Vec<String> = futures::stream::iter(paths)
+            .map(|path| async move {
+                let heading = format!("## {}", path);
+                let (root, item_path) = path.split_once("::").unwrap_or((&path, ""));
+                let target = targets.iter().find(|(known, _)| known == root).map(|(_, target)| target);
+                let lookup = match target {
+                    Some(ImportTarget::NotOnDocsRs(dependency)) => {
+                        return format!("{}\n\nFrom the {} dependency, which has no docs on docs.rs", heading, dependency)
+                    }
+                    Some(ImportTarget::Crate(crate_name, version)) => {
+                        return match self.lookup_item(crate_name.clone(), item_path.to_string(), version.clone()).await {
+                            Ok(doc) => format!("{}\n\n{}", heading, reflow_declaration(page_lead(&doc))),
+                            Err(e) => format!("{}\n\nError: {}", heading, error_message(e)),
+                        };
+                    }
+                    Some(ImportTarget::MaybeCrate(crate_name)) => {
+                        self.lookup_item(crate_name.clone(), item_path.to_string(), None).await.ok()
+                    }
+                    Some(ImportTarget::Unknown) | None => None,
+                };
+                if let Some(doc) = lookup {
+                    return format!("{}\n\n{}", heading, reflow_declaration(page_lead(&doc)));
+                }
+                match self.find_defining_crate(path.clone(), crate_names.clone()).await {
+                    Ok(definitions) => {
+                        // Without the title, which would nest a page heading in the section
+                        let found = definitions.split_once("\n\n").map_or(definitions.as_str(), |(_, rest)| rest);
+                        format!("{}\n\nNo docs at this path; crates defining the name:\n\n{}", heading, found.trim_end())
+                    }
+                    Err(e) => format!("{}\n\nError: {}", heading, error_message(e)),
+                }
+            })
+            .buffered(LOOKUP_ITEMS_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut markdown = format!("# Imports\n\n{}\n", sections.join("\n\n"));
+        if !unlisted.is_empty() {
+            markdown.push_str(&format!(
+                "\nNot looked up, past the first {}: {}\n",
+                MAX_EXPLAINED_IMPORTS,
+                unlisted.join(", ")
+            ));
+        }
+        Ok(markdown)
+    }
+
+    // The newest version of a dependency its requirement allows, or `None` for the latest
+    async fn manifest_version(&self, dependency: &ManifestDependency) -> Option<String> {
+        let req = dependency.req.as_deref().and_then(VersionReq::parse)?;
+        let versions = self.index_versions(dependency.crate_name()).await?;
+        newest_matching(&versions, &req)
+    }
+
+    // List versions published since `since` (default: the last 30 days), either for the
+    // given crates or, without any, for the most recently updated crates on crates.io
+    async fn recent_releases(&self, crate_names: Vec<String>, since: Option<String>, limit: usize) -> Result<String, ToolError> {
+        let since = match since {
+            Some(since) if !is_iso_date(&since) => {
+                return Err(ToolError::InvalidParameters(format!(
+                    "Invalid since date {}. Expected YYYY-MM-DD",
+                    since
+                )));
+            }
+            Some(since) => since,
+            None => date_days_ago(SystemTime::now(), DEFAULT_RELEASE_WINDOW_DAYS),
+        };
+
+        if crate_names.is_empty() {
+            let body = self.fetcher.get_crates_io_api("summary").await?;
+            let releases = parse_just_updated(&body)
+                .ok_or_else(|| DocsError::Decode("Unexpected crates.io summary response".to_string()))?;
+            return Ok(format_releases("recently updated crates", &since, &releases_since(releases, &since), limit));
+        }
+
+        let responses = futures::future::join_all(crate_names.iter().map(|crate_name| async move {
+            let path = format!("crates/{}/versions", crate_name);
+            (crate_name, self.fetcher.get_crates_io_api(&path).await)
+        }))
+        .await;
+
+        let mut releases = Vec::new();
+        let mut failed = Vec::new();
+        for (crate_name, response) in responses {
+            match response.ok().and_then(|body| parse_crate_versions(&body, crate_name)) {
+                Some(versions) => releases.extend(versions),
+                None => failed.push(crate_name.as_str()),
+            }
+        }
+        if failed.len() == crate_names.len() {
+            return Err(DocsError::Unavailable {
+                message: format!("Failed to fetch versions of {} from crates.io", failed.join(", ")),
+                retry_in: None,
+            }
+            .into());
+        }
+
+        let mut output = format_releases(&crate_names.join(", "), &since, &releases_since(releases, &since), limit);
+        if !failed.is_empty() {
+            output.push_str(&format!("\nNote: could not fetch versions of {}\n", failed.join(", ")));
+        }
+        Ok(output)
+    }
+
+    // Add crates to (or remove them from) the watch list, taking the current
+    // versions of new entries as the baseline
+    async fn watch_crates(&self, crate_names: Vec<String>, remove: bool) -> Result<String, ToolError> {
+        if crate_names.is_empty() {
+            return Err(ToolError::InvalidParameters("crate_names must not be empty".to_string()));
+        }
+        if remove {
+            self.watch.remove(&crate_names).await;
+        } else {
+            self.watch.add(&crate_names).await;
+            self.check_watched().await;
+        }
+
+        let watched = self.watch.crates().await;
+        Ok(format!(
+            "Watching {} crate(s): {}\n\nNew releases are listed in the {} resource, which is checked periodically.\n",
+            watched.len(),
+            watched.join(", "),
+            UPDATES_RESOURCE_URI
+        ))
+    }
+
+    // Compare the public APIs of two versions of a crate and report breaking changes
+    #[cfg(feature = "semver")]
+    async fn semver_check(&self, crate_name: String, from_version: String, to_version: Option<String>) -> Result<String, ToolError> {
+        let to_version = self
+            .resolve_version(&crate_name, to_version.as_deref())
+            .await
+            .ok_or_else(|| crate_not_found(format!("Crate {} not found on crates.io", crate_name), &crate_name))?;
+
+        let cache_key = format!("semver:{}:{}:{}", crate_name, from_version, to_version);
+        if let Some(report) = self.cache.get(&cache_key).await {
+            return Ok(report);
+        }
+
+        let (old_json, new_json) = futures::try_join!(
+            self.fetcher.get_rustdoc_json(&crate_name, &from_version),
+            self.fetcher.get_rustdoc_json(&crate_name, &to_version),
+        )?;
+        let parse = |json: &str, version: &str| {
+            PublicApi::from_rustdoc_json(json)
+                .map_err(|e| DocsError::Conversion(format!("{} {}: {}", crate_name, version, e)))
+        };
+        let old_api = parse(&old_json, &from_version)?;
+        let new_api = parse(&new_json, &to_version)?;
+
+        let report = format_report(&crate_name, &from_version, &to_version, &old_api.breaking_changes(&new_api));
+        self.cache.set(cache_key, report.clone()).await;
+        Ok(report)
+    }
+
+    // Diff the docs of an item, and its declaration, between two versions of its crate
+    async fn diff_item(
+        &self,
+        crate_name: String,
+        item_path: String,
+        from_version: String,
+        to_version: Option<String>,
+    ) -> Result<String, ToolError> {
+        let item_path = item_path.strip_prefix(&format!("{}::", crate_name)).unwrap_or(&item_path).to_string();
+        let (old_doc, new_doc) = futures::try_join!(
+            self.lookup_item(crate_name.clone(), item_path.clone(), Some(from_version.clone())),
+            self.lookup_item(crate_name.clone(), item_path.clone(), to_version.clone()),
+        )?;
+        let side = |doc: &str, version: &str| -> (String, String, String) {
+            match split_doc_header(doc) {
+                Some(header) => (header.version.to_string(), header.source_url.to_string(), header.markdown.to_string()),
+                None => (version.to_string(), String::new(), doc.to_string()),
+            }
+        };
+        let (old_version, old_url, old_markdown) = side(&old_doc, &from_version);
+        let (new_version, new_url, new_markdown) = side(&new_doc, to_version.as_deref().unwrap_or("latest"));
+        Ok(format_item_diff(
+            &crate_name,
+            &item_path,
+            &DiffSide { version: &old_version, source_url: &old_url, markdown: &old_markdown },
+            &DiffSide { version: &new_version, source_url: &new_url, markdown: &new_markdown },
+        ))
+    }
+
+    // Search crates.io for crates matching a query
+    async fn search_crates(&self, query: String, limit: Option<u32>, backend: Option<SearchBackend>) -> Result<String, ToolError> {
+        let limit = limit.unwrap_or(10).min(100); // Cap at 100 results
+
+        if backend.unwrap_or(self.search_backend) == SearchBackend::LibRs {
+            let html = self.fetcher.search_lib_rs(&query).await?;
+            let results = parse_lib_rs_results(&html, limit as usize);
+            return Ok(format_lib_rs_results(&query, &results));
+        }
+        
+        // Re-emitted in a shape of our own, so clients don't depend on the API's
+        let results = self.search_crates_io(&query, limit).await?;
+        Ok(serde_json::to_string_pretty(&results).expect("search results always serialize"))
+    }
+
+    // Search crates.io for up to `limit` crates
+    pub(crate) async fn search_crates_io(&self, query: &str, limit: u32) -> Result<CrateSearchResults, ToolError> {
+        let body = self.fetcher.search(query, limit).await?;
+        parse_crates_io_results(query, &body)
+            .ok_or_else(|| DocsError::Decode("Failed to search crates.io: the response is not search results".to_string()).into())
+    }
+
+    // Get documentation for a specific item in a crate
+    pub(crate) async fn lookup_item(&self, crate_name: String, item_path: String, version: Option<String>) -> Result<String, ToolError> {
+        self.lookup_item_in_order(crate_name, item_path, version, None).await
+    }
+
+    // `lookup_item`, probing page kinds in `probe_order` instead of the learned order when given
+    async fn lookup_item_in_order(
+        &self,
+        crate_name: String,
+        mut item_path: String,
+        version: Option<String>,
+        probe_order: Option<Vec<&'static str>>,
+    ) -> Result<String, ToolError> {
+        // Strip crate name prefix from the item path if it exists
+        let crate_prefix = format!("{}::", crate_name);
+        if item_path.starts_with(&crate_prefix) {
+            item_path = item_path[crate_prefix.len()..].to_string();
+        }
+        let doc = self.item_doc(crate_name.clone(), item_path.clone(), version.clone(), probe_order).await?;
+        self.note_document(&crate_name, version.as_deref(), Some(&item_path)).await;
+        Ok(doc)
+    }
+
+    // Note a crate or item page returned by the current call for the session history,
+    // under the version "latest" resolves to at the moment, so reading it again hits
+    // the cache even after "latest" moved on
+    async fn note_document(&self, crate_name: &str, version: Option<&str>, item_path: Option<&str>) {
+        let version = match version {
+            Some(version) => Some(version.to_string()),
+            None => self.latest_versions.get(crate_name).await,
+        };
+        let crate_name = crate_name.to_string();
+        let resource = match item_path {
+            Some(item_path) => DocResource::Item { crate_name, version, item_path: item_path.to_string() },
+            None => DocResource::Crate { crate_name, version },
+        };
+        record_document(resource.uri());
+    }
+
+    // The body of `lookup_item_in_order`, for an item path without the crate's name
+    async fn item_doc(
+        &self,
+        crate_name: String,
+        mut item_path: String,
+        version: Option<String>,
+        probe_order: Option<Vec<&'static str>>,
+    ) -> Result<String, ToolError> {
+        // `Builder#method.worker_threads`, as docs.rs links are shared, narrows the page to that anchor
+        let cache_path = item_path.clone();
+        let anchor = match item_path.split_once('#') {
+            Some((path, anchor)) => {
+                let anchor = anchor.to_string();
+                item_path = path.to_string();
+                Some(anchor).filter(|anchor| !anchor.is_empty())
+            }
+            None => None,
+        };
+        check_std_channel(&crate_name, version.as_deref())?;
+        let (version, stale) = self.resolve_version_or_stale(&crate_name, version.as_deref()).await;
+        let mark_stale = |doc: String| match version.as_deref().filter(|_| stale) {
+            Some(ver) => with_stale_note(&crate_name, ver, doc),
+            None => doc,
+        };
+
+        // Check cache first
+        let cache_key = if let Some(ver) = &version {
+            format!("{}:{}:{}", crate_name, ver, cache_path)
+        } else {
+            format!("{}:{}", crate_name, cache_path)
+        };
+
+        if let Some(doc) = self.cache.get(&cache_key).await {
+            return Ok(mark_stale(doc));
+        }
+
+        // The crate's rustdoc JSON names the item's exact kind, so its page is one
+        // request away; without JSON, or for paths it doesn't know, probe for it
+        let page = match self.json_item(&crate_name, version.as_deref(), &item_path).await {
+            Some((path, item)) => {
+                let relative_path = IndexItem { path: path.clone(), kind: item.kind }.page_path();
+                match self.fetcher.get_item_page(&crate_name, version.as_deref(), &relative_path).await {
+                    Ok(page) => page,
+                    // The JSON still documents the item when its page can't be fetched
+                    Err(e) => {
+                        tracing::debug!("Rendering {} from rustdoc JSON: {}", path, e);
+                        let markdown = with_doc_header(&crate_name, version.as_deref(), RUSTDOC_JSON_SOURCE, format_json_item(&path, &item));
+                        self.cache.set(cache_key, markdown.clone()).await;
+                        return Ok(mark_stale(markdown));
+                    }
+                }
+            }
+            None => {
+                let probe_order = match probe_order {
+                    Some(probe_order) => probe_order,
+                    None => self.learned_probe_order(&crate_name).await,
+                };
+                self.probe_item_page(&crate_name, version.as_deref(), &item_path, &probe_order).await?
+            }
+        };
+
+        let resolved_version = page.resolved_version(&crate_name).or_else(|| version.clone());
+        let (url, markdown) = match &anchor {
+            Some(anchor) => {
+                let section = anchor_markdown(&page.html, anchor).ok_or_else(|| {
+                    let error = ToolError::NotFound(format!("{} has no anchor #{}", item_path, anchor));
+                    let suggestion = format!(
+                        "List the anchors of the page with page_outline {{\"crate_name\": \"{}\", \"item_path\": \"{}\"}}",
+                        crate_name, item_path
+                    );
+                    with_error_data(error, ErrorData::new(ErrorCode::NotFound).with_suggestions(vec![suggestion]))
+                })?;
+                (format!("{}#{}", page.url, anchor), section)
+            }
+            None => (page.url.clone(), html_to_markdown(&page.html)),
+        };
+        
+        // Convert HTML to markdown
+        let mut markdown_body = with_doc_header(&crate_name, resolved_version.as_deref(), &url, markdown);
+        if is_std_crate(&crate_name) {
+            markdown_body.push_str(&feature_gates_note(&unstable_features(&page.html)));
+        }
+        
+        // Cache the markdown result under the concrete version when "latest"
+        // couldn't be resolved up front but the page told us which version it is
+        let cache_key = match resolved_version.filter(|_| version.is_none()) {
+            Some(resolved) => {
+                self.latest_versions.set(&crate_name, &resolved).await;
+                format!("{}:{}:{}", crate_name, resolved, cache_path)
+            }
+            None => cache_key,
+        };
+        self.cache.set(cache_key, markdown_body.clone()).await;
+        
+        Ok(mark_stale(markdown_body))
+    }
+
+    // Find an item's page by probing every page kind it could have, falling back
+    // to the crate's search index when the path names none of them
+    async fn probe_item_page(
+        &self,
+        crate_name: &str,
+        version: Option<&str>,
+        item_path: &str,
+        item_types: &[&'static str],
+    ) -> Result<DocPage, ToolError> {
+        // Process the item path to determine the item type
+        // Format: module::path::ItemName
+        // Need to split into module path and item name, and guess item type
+        let parts: Vec<&str> = item_path.split("::").collect();
+        
+        if parts.is_empty() {
+            return Err(ToolError::InvalidParameters(
+                "Invalid item path. Expected format: module::path::ItemName".to_string()
+            ));
+        }
+        
+        let item_name = parts.last().unwrap().to_string();
+        let module_path = if parts.len() > 1 {
+            parts[..parts.len()-1].join("/")
+        } else {
+            String::new()
+        };
+        
+        // Try different item types (struct, enum, trait, fn, and the three macro
+        // flavours), probing all of them concurrently so a miss costs one round trip
+        let mut probes: Vec<BoxFuture<'_, Result<DocPage, ToolError>>> = item_types
+            .iter()
+            .map(|item_type| {
+                // Construct the page path for the specific item below the crate root
+                let relative_path = item_page_path(&module_path, item_type, &item_name);
+                async move {
+                    self.fetcher
+                        .get_item_page(crate_name, version, &relative_path)
+                        .await
+                }
+                .boxed()
+            })
+            .collect();
+
+        // `Type::member` paths: as a lower-priority fallback, look the last segment
+        // up as a member of its parent type's page (e.g. `mpsc::Sender::send`)
+        if parts.len() > 1 {
+            let parent_name = parts[parts.len() - 2];
+            let parent_module = parts[..parts.len() - 2].join("/");
+            let member_name = item_name.as_str();
+            for parent_type in ["struct", "enum", "trait", "union"] {
+                let relative_path = item_page_path(&parent_module, parent_type, parent_name);
+                probes.push(
+                    async move {
+                        let page = self
+                            .fetcher
+                            .get_item_page(crate_name, version, &relative_path)
+                            .await?;
+                        let (anchor, section) = member_section(&page.html, member_name).ok_or_else(|| {
+                            ToolError::NotFound(format!("{} has no member named {}", parent_name, member_name))
+                        })?;
+                        let (before, after) = match parent_type {
+                            "trait" => (trait_method_note(&page.url, parent_name, &anchor, &section), String::new()),
+                            "enum" if anchor.starts_with("variant.") => {
+                                (String::new(), other_members_note(&page.html, parent_name, "variant", member_name))
+                            }
+                            "struct" | "union" if anchor.starts_with("structfield.") => {
+                                (String::new(), other_members_note(&page.html, parent_name, "structfield", member_name))
+                            }
+                            _ => (String::new(), String::new()),
+                        };
+                        Ok(DocPage {
+                            url: format!("{}#{}", page.url, anchor),
+                            html: format!("{}{}{}", before, section, after),
+                        })
+                    }
+                    .boxed(),
+                );
+            }
+        }
+        
+        match first_success_in_order(probes).await {
+            Ok((idx, page)) => {
+                if let Some(kind) = item_types.get(idx) {
+                    self.record_probe_success(crate_name, kind).await;
+                }
+                Ok(page)
+            }
+            // None of the item types worked; the path may be wrong or name a re-export,
+            // so ask the crate's search index where the item actually lives
+            Err(last_error) => self.corrected_item_page(crate_name, version, item_path).await.map_err(|candidates| {
+                let hint = if candidates.is_empty() {
+                    String::new()
+                } else {
+                    format!(". Did you mean one of: {}", candidates.join(", "))
+                };
+                let message = format!(
+                    "Failed to fetch item documentation. No matching item found. Last error: {}{}",
+                    last_error.map(|e| split_error_data(&e.to_string()).0.to_string()).unwrap_or_else(|| "Unknown error".to_string()),
+                    hint
+                );
+                let suggestions = candidates.into_iter().map(|path| format!("Look up {}", path)).collect();
+                DocsError::ItemNotFound { message, suggestions }.into()
+            }),
+        }
+    }
+
+    // The configured probe order with the kinds found most often in this crate moved to the front,
+    // so e.g. serde's traits are tried before structs that mostly don't exist
+    async fn learned_probe_order(&self, crate_name: &str) -> Vec<&'static str> {
+        let successes = self.probe_successes(crate_name).await;
+        let mut order = self.probe_order.clone();
+        // Stable, so kinds found equally often keep their configured order
+        order.sort_by_key(|kind| std::cmp::Reverse(successes.get(*kind).copied().unwrap_or(0)));
+        order
+    }
+
+    // How often each page kind was the one found for an item of the crate, kept in the
+    // cache (and so on disk with --cache-dir) as `trait=12,struct=3`
+    async fn probe_successes(&self, crate_name: &str) -> std::collections::HashMap<String, u64> {
+        let Some(stats) = self.cache.get(&format!("probe-stats:{}", crate_name)).await else {
+            return std::collections::HashMap::new();
+        };
+        stats
+            .split(',')
+            .filter_map(|entry| {
+                let (kind, count) = entry.split_once('=')?;
+                Some((kind.to_string(), count.parse().ok()?))
+            })
+            .collect()
+    }
+
+    async fn record_probe_success(&self, crate_name: &str, kind: &str) {
+        let mut successes = self.probe_successes(crate_name).await;
+        *successes.entry(kind.to_string()).or_insert(0) += 1;
+        let mut entries: Vec<(String, u64)> = successes.into_iter().collect();
+        entries.sort();
+        let stats: Vec<String> = entries.iter().map(|(kind, count)| format!("{}={}", kind, count)).collect();
+        self.cache.set(format!("probe-stats:{}", crate_name), stats.join(",")).await;
+    }
+
+    // The rustdoc JSON entry of an item, with the item's full path
+    async fn json_item(&self, crate_name: &str, version: Option<&str>, item_path: &str) -> Option<(String, JsonItem)> {
+        let json = self.rustdoc_json(crate_name, version).await?;
+        let path = format!("{}::{}", crate_name.replace('-', "_"), item_path);
+        let item = json.item(&path)?.clone();
+        Some((path, item))
+    }
+
+    /// The parsed rustdoc JSON docs.rs built for a crate version, from the JSON
+    /// cache when possible, or `None` when there is none (older builds, std
+    /// and local crates)
+    pub async fn rustdoc_json(&self, crate_name: &str, version: Option<&str>) -> Option<Arc<RustdocJson>> {
+        if is_std_crate(crate_name) || version.is_some_and(|version| version.starts_with("local-")) {
+            return None;
+        }
+        let version = version.unwrap_or("latest");
+        let key = format!("{}:{}", crate_name, version);
+        if let Some(cached) = self.rustdoc_json.get(&key).await {
+            return cached;
+        }
+
+        // Definite unavailability is cached too, so crates without JSON cost one request
+        // an hour; failures that may pass (timeouts, 5xx, size limits) aren't
+        let json = match self.fetcher.get_rustdoc_json(crate_name, version).await {
+            Ok(json) => RustdocJson::parse(&json)
+                .map_err(|e| tracing::debug!("Unusable rustdoc JSON for {}: {}", key, e))
+                .ok()
+                .map(Arc::new),
+            Err(e) if error_data(&e).is_some_and(|data| data.code == ErrorCode::NotFound) => {
+                tracing::debug!("No rustdoc JSON for {}: {}", key, e);
+                None
+            }
+            Err(e) => {
+                tracing::debug!("Failed to fetch rustdoc JSON for {}: {}", key, e);
+                return None;
+            }
+        };
+        self.rustdoc_json.set(key, json.clone()).await;
+        json
+    }
+
+    /// Templates of the `docs://` URIs `read_resource` serves, for
+    /// `resources/templates/list`
+    pub fn resource_templates(&self) -> Vec<Value> {
+        resource_templates()
+    }
+
+    // Look up several items with bounded concurrency, returning one result per
+    // item in request order; a failed item doesn't fail the batch
+    async fn lookup_items(&self, items: Vec<(String, String, Option<String>)>) -> Vec<String> {
+        futures::stream::iter(items)
+            .map(|(crate_name, item_path, version)| async move {
+                let heading = format!("## {}::{}", crate_name, item_path);
+                match self.lookup_item(crate_name, item_path, version).await {
+                    Ok(doc) => format!("{}\n\n{}", heading, doc),
+                    Err(e) => format!("{}\n\nError: {}", heading, error_message(e)),
+                }
+            })
+            .buffered(LOOKUP_ITEMS_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    // Find an item by name in the crate's search index and fetch its page when the
+    // index knows exactly one item of that name. Otherwise the error lists the
+    // candidates, if any.
+    async fn corrected_item_page(&self, crate_name: &str, version: Option<&str>, item_path: &str) -> Result<DocPage, Vec<String>> {
+        let Some(index) = self.search_index(crate_name, version).await else {
+            return Err(Vec::new());
+        };
+        let name = item_path.rsplit("::").next().unwrap_or(item_path);
+        let candidates = index.find(name);
+        match candidates.as_slice() {
+            [item] => {
+                let page = self
+                    .fetcher
+                    .get_item_page(crate_name, version, &item.page_path())
+                    .await
+                    .map_err(|_| Vec::new())?;
+                let note = format!(
+                    "<p><em>{}::{} is documented at {}</em></p>",
+                    crate_name, item_path, item.path
+                );
+                Ok(DocPage {
+                    url: page.url,
+                    html: format!("{}{}", note, page.html),
+                })
+            }
+            [] => Err(Vec::new()),
+            items => Err(items.iter().map(|item| item.path.clone()).collect()),
+        }
+    }
+
+    /// The parsed rustdoc search index of a crate version, from the index cache
+    /// when possible, or `None` when docs.rs has no index we can read
+    pub async fn search_index(&self, crate_name: &str, version: Option<&str>) -> Option<Arc<SearchIndex>> {
+        let key = format!("{}:{}", crate_name, version.unwrap_or("latest"));
+        if let Some(index) = self.search_indexes.get(&key).await {
+            return Some(index);
+        }
+
+        // The crate root page names the (versioned) index file it loads
+        let root = self.fetcher.get_item_page(crate_name, version, "index.html").await;
+        let file = root.map(|page| search_index_file(&page.html)).unwrap_or_else(|_| "search-index.js".to_string());
+        let js = match self.fetcher.get_doc_file(crate_name, version, &file).await {
+            Ok(js) => js,
+            Err(e) => {
+                tracing::debug!("Failed to fetch the search index of {}: {}", key, e);
+                return None;
+            }
+        };
+        let Some(index) = SearchIndex::parse(&js, crate_name) else {
+            tracing::debug!("Unrecognized search index format for {}", key);
+            return None;
+        };
+
+        let index = Arc::new(index);
+        self.search_indexes.set(key, index.clone()).await;
+        Some(index)
+    }
+}
+
+// The crate, version and item path of a crate (`tokio:1.35.0`) or item
+// (`tokio:1.35.0:sync::Mutex`) docs cache key; `None` for other keys
+fn parse_doc_key(key: &str) -> Option<(String, String, Option<String>)> {
+    let mut parts = key.splitn(3, ':');
+    let (crate_name, version) = (parts.next()?, parts.next()?);
+    Version::parse(version)?;
+    Some((crate_name.to_string(), version.to_string(), parts.next().map(str::to_string)))
+}
+
+// Say whether a trait method must be implemented or comes with a default
+// implementation, linking to the default's source when there is one
+fn trait_method_note(page_url: &str, trait_name: &str, anchor: &str, section: &str) -> String {
+    if anchor.starts_with("tymethod.") {
+        return format!("<p><em>Required method of trait {}</em></p>", trait_name);
+    }
+    let source = source_href(section)
+        .and_then(|href| reqwest::Url::parse(page_url).ok()?.join(&href).ok())
+        .map(|url| format!(" (<a href=\"{}\">source</a>)", url))
+        .unwrap_or_default();
+    format!(
+        "<p><em>Provided method of trait {}, with a default implementation{}</em></p>",
+        trait_name, source
+    )
+}
+
+// List the remaining variants or fields of a type so a single one has context
+fn other_members_note(type_html: &str, type_name: &str, kind: &str, member: &str) -> String {
+    let others: Vec<String> = member_names(type_html, kind)
+        .into_iter()
+        .filter(|name| name != member)
+        .map(|name| format!("<code>{}</code>", name))
+        .collect();
+    if others.is_empty() {
+        return String::new();
+    }
+    let label = if kind == "variant" { "variants" } else { "fields" };
+    format!("<p>Other {} of {}: {}</p>", label, type_name, others.join(", "))
+}
+
+/// A full probe order from a list of preferred page kinds: those first, in the given
+/// order, then the remaining kinds of `ITEM_PAGE_KINDS`
+pub fn item_probe_order(preferred: &[String]) -> Result<Vec<&'static str>, String> {
+    let mut order: Vec<&'static str> = Vec::new();
+    for kind in preferred {
+        let kind = ITEM_PAGE_KINDS
+            .iter()
+            .find(|known| **known == kind.trim())
+            .ok_or_else(|| format!("Unknown item kind {}. Expected one of: {}", kind, ITEM_PAGE_KINDS.join(", ")))?;
+        if !order.contains(kind) {
+            order.push(kind);
+        }
+    }
+    order.extend(ITEM_PAGE_KINDS.iter().filter(|kind| !order.contains(kind)));
+    Ok(order)
+}
+
+// Page path of an item below the crate root, e.g. `sync/mpsc/struct.Sender.html`
+fn item_page_path(module_path: &str, item_type: &str, item_name: &str) -> String {
+    if module_path.is_empty() {
+        format!("{}.{}.html", item_type, item_name)
+    } else {
+        format!("{}/{}.{}.html", module_path, item_type, item_name)
+    }
+}
+
+// Standard library docs exist per channel or Rust release, not per crate version
+fn check_std_channel(crate_name: &str, version: Option<&str>) -> Result<(), ToolError> {
+    match version {
+        Some(version) if is_std_crate(crate_name) && !is_rust_channel(version) => Err(ToolError::InvalidParameters(format!(
+            "Invalid version '{}' for {}: expected stable, beta, nightly or a Rust release such as 1.75.0",
+            version, crate_name
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// The strings of the array argument `name`, none when it's missing
+fn string_array_arg(arguments: &Value, name: &str) -> Vec<String> {
+    arguments
+        .get(name)
+        .and_then(|v| v.as_array())
+        .map(|values| values.iter().filter_map(|v| v.as_str()).map(|v| v.to_string()).collect())
+        .unwrap_or_default()
+}
+
+// Whether the crate's default features are enabled, as they are unless `default_features` is false
+fn default_features_arg(arguments: &Value) -> bool {
+    arguments.get("default_features").and_then(|v| v.as_bool()).unwrap_or(true)
+}
+
+// Pick the docs to read from the `version` and `channel` arguments; a channel
+// stands in for the version of a standard library crate
+fn channel_version(crate_name: &str, version: Option<String>, channel: Option<&str>) -> Result<Option<String>, ToolError> {
+    let Some(channel) = channel else {
+        return Ok(version);
+    };
+    if !CHANNELS.contains(&channel) {
+        return Err(ToolError::InvalidParameters(format!(
+            "Unknown channel {}. Expected stable, beta or nightly",
+            channel
+        )));
+    }
+    if !is_std_crate(crate_name) {
+        return Err(ToolError::InvalidParameters(format!(
+            "channel only applies to std, core and alloc, not {}",
+            crate_name
+        )));
+    }
+    if version.is_some() {
+        return Err(ToolError::InvalidParameters("Pass either version or channel, not both".to_string()));
+    }
+    Ok(Some(channel.to_string()))
+}
+
+// List the feature gates a nightly-only item needs, linking their unstable book chapters
+fn feature_gates_note(features: &[String]) -> String {
+    if features.is_empty() {
+        return String::new();
+    }
+    let mut note = String::from("\n\n## Feature gates\n\nNightly-only APIs on this page need:\n\n");
+    for feature in features {
+        note.push_str(&format!(
+            "- `#![feature({})]` — {}/{}.html\n",
+            feature,
+            UNSTABLE_BOOK_URL,
+            feature.replace('_', "-")
+        ));
+    }
+    note
+}
+
+// Prefix converted docs with the exact crate version and page they came from
+fn with_doc_header(crate_name: &str, version: Option<&str>, url: &str, markdown: String) -> String {
+    format!(
+        "Documentation for {} {}\nSource: {}\n\n{}",
+        crate_name,
+        version.unwrap_or("latest"),
+        url,
+        markdown
+    )
+}
+
+// The crate, version and source a doc starts with and the markdown after them, undoing
+// `with_doc_header`, `with_stale_note` and the header of the fallbacks, `None` for
+// docs without a header
+pub(crate) fn split_doc_header(doc: &str) -> Option<DocHeader<'_>> {
+    let (stale, doc) = match doc.strip_prefix("[stale: ") {
+        Some(rest) => (true, rest.split_once("]\n\n")?.1),
+        None => (false, doc),
+    };
+    let (first_line, rest) = doc.strip_prefix("Documentation for ")?.split_once('\n')?;
+    let (crate_name, version) = first_line.rsplit_once(' ')?;
+    let (source, markdown) = rest.strip_prefix("Source: ")?.split_once('\n')?;
+    // Fallbacks note why after the URL: `https://... (repository fallback: <reason>)`
+    let (source_url, fallback) = match source.split_once(" (") {
+        Some((url, note)) => (url, note.strip_suffix(')').and_then(|note| note.split_once(": ")).map(|(_, reason)| reason)),
+        None => (source, None),
+    };
+    Some(DocHeader {
+        crate_name,
+        version,
+        source_url,
+        fallback,
+        stale,
+        markdown: markdown.trim_start_matches('\n'),
+    })
+}
+
+// A doc taken apart by `split_doc_header`
+pub(crate) struct DocHeader<'a> {
+    pub crate_name: &'a str,
+    pub version: &'a str,
+    pub source_url: &'a str,
+    pub fallback: Option<&'a str>,
+    pub stale: bool,
+    pub markdown: &'a str,
+}
+
+// The part of a page under `#anchor` as markdown: the section of the heading linking
+// to it (`#examples`), or else rustdoc's section of the member with that id
+// (`#method.worker_threads`)
+fn anchor_markdown(html: &str, anchor: &str) -> Option<String> {
+    let markdown = html_to_markdown(html);
+    let heading = headings(&markdown).into_iter().find(|heading| heading.anchor.as_deref() == Some(anchor));
+    match heading {
+        Some(heading) => Some(heading.section(&markdown).to_string()),
+        None => anchor_section(html, anchor).map(|section| html_to_markdown(&section)),
+    }
+}
+
+// The page link first, then every member and linked item the result mentions by name
+fn format_source_links(doc: &str, links: &[PageLink]) -> String {
+    // The URL on the `Source:` line mentions the crate and modules without the docs doing so
+    let body = match doc.split_once("\nSource: ") {
+        Some((_, rest)) => rest.split_once('\n').map_or("", |(_, body)| body),
+        None => doc,
+    };
+    let lines: Vec<String> = links
+        .iter()
+        .enumerate()
+        .filter(|(i, link)| *i == 0 || mentions(body, &link.name))
+        .map(|(_, link)| match &link.source {
+            Some(source) => format!("- `{}`: {} ([src]({}))", link.name, link.url, source),
+            None => format!("- `{}`: {}", link.name, link.url),
+        })
+        .collect();
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("\n\nSource links:\n{}\n", lines.join("\n"))
+}
+
+// Whether `name` occurs in `text` as a whole identifier
+fn mentions(text: &str, name: &str) -> bool {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(name).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + name.len()..].chars().next();
+        !before.is_some_and(is_ident) && !after.is_some_and(is_ident)
+    })
+}
+
+// The opening of a lookup result for `summary`, saying how to get the rest
+fn doc_summary(doc: String) -> String {
+    let lead = page_lead(&doc);
+    if lead.len() == doc.trim_end().len() {
+        return reflow_declaration(&doc);
+    }
+    format!("{}\n\n[summary: pass summary=false for the whole page]\n", reflow_declaration(lead))
+}
+
+// Narrow a lookup result to the sections titled `section`, keeping the lines above its first heading
+fn doc_section(doc: &str, section: &str) -> Result<String, ToolError> {
+    let Some(sections) = select_sections(doc, section) else {
+        let mut titles: Vec<String> = Vec::new();
+        for heading in headings(doc).into_iter().filter(|heading| heading.level > 1) {
+            if !titles.contains(&heading.title) {
+                titles.push(heading.title);
+            }
+        }
+        let data = ErrorData::new(ErrorCode::NotFound)
+            .with_suggestions(titles.iter().map(|title| format!("Use section {}", title)).collect());
+        return Err(with_error_data(
+            ToolError::NotFound(format!("The page has no {} section", section)),
+            data,
+        ));
+    };
+    match preamble(doc) {
+        "" => Ok(sections),
+        preamble => Ok(format!("{}\n\n{}", preamble, sections)),
+    }
+}
+
+// Mark documentation of an expired "latest" version served while crates.io is unreachable
+fn with_stale_note(crate_name: &str, version: &str, markdown: String) -> String {
+    format!(
+        "[stale: crates.io can't be reached, so this is {} {}, the latest version when last checked; \
+         a newer release may exist]\n\n{}",
+        crate_name, version, markdown
+    )
+}
+
+// The parts of a tool result starting at `offset`, each at most `max_bytes`
+// long and cut at line breaks where possible. Past `max_parts` the result is
+// truncated, ending with a marker giving the offset the rest starts at, and
+// `block`, the index of the text among the result's blocks, when it has several.
+fn content_parts(
+    text: String,
+    offset: usize,
+    max_bytes: Option<usize>,
+    max_parts: usize,
+    block: Option<usize>,
+) -> Result<Vec<String>, ToolError> {
+    if offset > 0 && offset >= text.len() {
+        return Err(ToolError::InvalidParameters(format!(
+            "offset {} is past the end of the result ({} bytes)",
+            offset,
+            text.len()
+        )));
+    }
+    let mut start = (offset..=text.len()).find(|&i| text.is_char_boundary(i)).unwrap_or(text.len());
+    let Some(max_bytes) = max_bytes.filter(|&max_bytes| text.len() - start > max_bytes) else {
+        return Ok(vec![if start == 0 { text } else { text[start..].to_string() }]);
+    };
+
+    let mut parts = Vec::new();
+    loop {
+        let rest = &text[start..];
+        if rest.len() <= max_bytes {
+            parts.push(rest.to_string());
+            break;
+        }
+        if parts.len() + 1 >= max_parts.max(1) {
+            let end = part_end(rest, max_bytes.saturating_sub(TRUNCATION_MARKER_BYTES));
+            let continuation = match block {
+                Some(block) => format!("offset={} and block={}", start + end, block),
+                None => format!("offset={}", start + end),
+            };
+            parts.push(format!(
+                "{}\n[truncated: use {} to continue]",
+                rest[..end].trim_end_matches('\n'),
+                continuation
+            ));
+            break;
+        }
+        let end = part_end(rest, max_bytes);
+        parts.push(rest[..end].trim_end_matches('\n').to_string());
+        start += end;
+    }
+    Ok(parts)
+}
+
+// Length of the next part of `rest` within `budget` bytes (at least one
+// character); cutting at the last line break keeps markdown blocks intact,
+// unless that loses too much
+fn part_end(rest: &str, budget: usize) -> usize {
+    let budget = budget.clamp(1, rest.len());
+    let end = (1..=budget).rev().find(|&i| rest.is_char_boundary(i)).unwrap_or_else(|| {
+        rest.char_indices().nth(1).map(|(i, _)| i).unwrap_or(rest.len())
+    });
+    match rest[..end].rfind('\n').filter(|&line_end| line_end >= end / 2) {
+        Some(line_end) => line_end + 1,
+        None => end,
+    }
+}
+
+/// Run fallible probes concurrently and return the first success in priority order
+///
+/// A lower-priority result is only returned once every higher-priority probe
+/// has failed, so the outcome is the same as trying them one by one, but the
+/// worst case costs a single round trip. Returns the index of the winning
+/// probe, or the error of the last probe if all of them fail.
+async fn first_success_in_order<F, T>(probes: Vec<F>) -> Result<(usize, T), Option<ToolError>>
+where
+    F: Future<Output = Result<T, ToolError>>,
+{
+    let mut results: Vec<Option<Result<T, ToolError>>> = probes.iter().map(|_| None).collect();
+    let mut pending: FuturesUnordered<_> = probes
+        .into_iter()
+        .enumerate()
+        .map(|(idx, probe)| async move { (idx, probe.await) })
+        .collect();
+    let mut next = 0;
+
+    while let Some((idx, result)) = pending.next().await {
+        results[idx] = Some(result);
+        // Skip over failures; stop at the first probe that is still running
+        while next < results.len() {
+            match &results[next] {
+                None => break,
+                Some(Err(_)) => next += 1,
+                Some(Ok(_)) => {
+                    if let Some(Ok(value)) = results[next].take() {
+                        return Ok((next, value));
+                    }
+                }
+            }
+        }
+    }
+
+    Err(results.pop().flatten().and_then(Result::err))
+}
+
+impl mcp_server::Router for DocRouter {
     fn name(&self) -> String {
         "rust-docs".to_string()
     }
@@ -256,52 +3064,628 @@ impl mcp_server::Router for DocRouter {
     fn capabilities(&self) -> ServerCapabilities {
         CapabilitiesBuilder::new()
             .with_tools(true)
-            .with_resources(false, false)
+            .with_resources(true, false)
             .with_prompts(false)
             .build()
     }
 
     fn list_tools(&self) -> Vec<Tool> {
-        vec![
+        let mut tools = vec![
+            Tool::new(
+                "lookup_crate".to_string(),
+                "Look up documentation for a Rust crate (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate to look up"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version of the crate (optional, defaults to latest); for std, core and alloc a channel (stable, beta, nightly) or Rust release such as 1.75.0, defaulting to stable"
+                        },
+                        "channel": {
+                            "type": "string",
+                            "enum": ["stable", "beta", "nightly"],
+                            "description": "Documentation channel of std, core or alloc (optional, alternative to version); nightly includes unstable APIs and their feature gates"
+                        },
+                        "section": {
+                            "type": "string",
+                            "description": "Return only the page's section with this heading (optional), e.g. Examples, Methods, Trait Implementations or Panics"
+                        },
+                        "summary": {
+                            "type": "boolean",
+                            "description": "Return only the opening of the page: its title, declaration and summary (optional, defaults to false or the session's configure_session setting; ignored with section)"
+                        },
+                        "source_links": {
+                            "type": "boolean",
+                            "description": "Append footnotes linking the item, and the members and items the result mentions, to their docs.rs pages and [src] source (optional, defaults to false)"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "search_crates".to_string(),
+                "Search for Rust crates on crates.io (returns JSON: query, total, and crates with name, description, version, downloads, docs_url and repository) or lib.rs (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "The search query"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of results to return (optional, defaults to 10, max 100)"
+                        },
+                        "backend": {
+                            "type": "string",
+                            "enum": ["crates-io", "lib-rs"],
+                            "description": "Search service (optional, defaults to the server's configured backend): crates-io matches names and keywords, lib-rs ranks by relevance and popularity"
+                        }
+                    },
+                    "required": ["query"]
+                }),
+            ),
+            Tool::new(
+                "lookup_item".to_string(),
+                "Look up documentation for a specific item in a Rust crate (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "item_path": {
+                            "type": "string",
+                            "description": "Path to the item (e.g., 'vec::Vec' or 'crate_name::vec::Vec' - crate prefix will be automatically stripped), or to a method of a type (e.g., 'vec::Vec::push'); a docs.rs #anchor narrows the page to that section (e.g., 'runtime::Builder#method.worker_threads')"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version of the crate (optional, defaults to latest); for std, core and alloc a channel (stable, beta, nightly) or Rust release such as 1.75.0, defaulting to stable"
+                        },
+                        "channel": {
+                            "type": "string",
+                            "enum": ["stable", "beta", "nightly"],
+                            "description": "Documentation channel of std, core or alloc (optional, alternative to version); nightly includes unstable APIs and their feature gates"
+                        },
+                        "section": {
+                            "type": "string",
+                            "description": "Return only the page's section with this heading (optional), e.g. Examples, Methods, Trait Implementations or Panics"
+                        },
+                        "summary": {
+                            "type": "boolean",
+                            "description": "Return only the opening of the page: its title, declaration and summary (optional, defaults to false or the session's configure_session setting; ignored with section)"
+                        },
+                        "source_links": {
+                            "type": "boolean",
+                            "description": "Append footnotes linking the item, and the members and items the result mentions, to their docs.rs pages and [src] source (optional, defaults to false)"
+                        },
+                        "hide_generated_impls": {
+                            "type": "boolean",
+                            "description": "Leave out the Auto Trait Implementations, Blanket Implementations and Methods from Deref sections (optional, defaults to false)"
+                        },
+                        "probe_order": {
+                            "type": "array",
+                            "items": { "type": "string", "enum": ["struct", "enum", "trait", "fn", "macro", "attr", "derive"] },
+                            "description": "Page kinds to try first when the item's kind is unknown, e.g. [\"trait\"] for serde (optional; defaults to the server's order, adapted to what was found in the crate before)"
+                        }
+                    },
+                    "required": ["crate_name", "item_path"]
+                }),
+            ),
+            Tool::new(
+                "lookup_items".to_string(),
+                format!(
+                    "Look up documentation for several items in one call (up to {}); returns one markdown result per item, in order, headed by its path",
+                    MAX_LOOKUP_ITEMS
+                ),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "items": {
+                            "type": "array",
+                            "description": "The items to look up",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "crate_name": {
+                                        "type": "string",
+                                        "description": "The name of the crate"
+                                    },
+                                    "item_path": {
+                                        "type": "string",
+                                        "description": "Path to the item, as for lookup_item"
+                                    },
+                                    "version": {
+                                        "type": "string",
+                                        "description": "The version of the crate (optional, defaults to latest)"
+                                    }
+                                },
+                                "required": ["crate_name", "item_path"]
+                            }
+                        }
+                    },
+                    "required": ["items"]
+                }),
+            ),
+            Tool::new(
+                "dependency_snippet".to_string(),
+                "Get the exact Cargo.toml [dependencies] line and matching `cargo add` command for a crate, using its latest published version".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version to depend on (optional, defaults to the latest stable release)"
+                        },
+                        "features": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Features to enable (optional)"
+                        },
+                        "version_policy": {
+                            "type": "string",
+                            "enum": ["caret", "minor", "major", "exact"],
+                            "description": "How to write the version requirement: caret (\"1.0.200\", default), minor (\"1.0\"), major (\"1\") or exact (\"=1.0.200\")"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "resolve_dependencies".to_string(),
+                "Resolve a crate version's dependency requirements to the concrete versions Cargo would pick today, including transitive dependencies, with the features each ends up with (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version whose dependencies to resolve (optional, defaults to latest)"
+                        },
+                        "features": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Features to enable on the crate (optional)"
+                        },
+                        "default_features": {
+                            "type": "boolean",
+                            "description": "Whether to enable the crate's default features (optional, defaults to true)"
+                        },
+                        "transitive": {
+                            "type": "boolean",
+                            "description": "Whether to resolve dependencies of dependencies too (optional, defaults to true)"
+                        },
+                        "tree": {
+                            "type": "boolean",
+                            "description": "Draw the dependencies as a tree like `cargo tree` instead of lists (optional, defaults to false)"
+                        },
+                        "depth": {
+                            "type": "integer",
+                            "minimum": 1,
+                            "description": format!("How many levels of the tree to draw (optional, defaults to {})", DEFAULT_TREE_DEPTH)
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "dependency_weight".to_string(),
+                "Estimate how heavy a dependency is: how many crates it pulls in, directly and transitively, and their total download size, with the largest ones listed (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version of the crate (optional, defaults to latest)"
+                        },
+                        "features": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Features to enable on the crate (optional)"
+                        },
+                        "default_features": {
+                            "type": "boolean",
+                            "description": "Whether to enable the crate's default features (optional, defaults to true)"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "source_stats".to_string(),
+                "Report the approximate source size of a crate version (files, lines of Rust, unsafe blocks, functions and impls) from its published .crate archive, as a quick complexity and risk signal (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version of the crate (optional, defaults to latest)"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "feature_graph".to_string(),
+                "Show which features of a crate enable which other features and optional dependencies, as nested lists, including what `default` turns on (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version of the crate (optional, defaults to latest)"
+                        },
+                        "feature": {
+                            "type": "string",
+                            "description": "Only show this feature and everything it turns on (optional)"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "doc_targets".to_string(),
+                "List the targets (platforms) docs.rs documented a crate version for, default target first, e.g. to check a target before looking up target-specific items (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "version": {
+                            "type": "string",
+                            "description": "The version of the crate (optional, defaults to latest)"
+                        },
+                        "target": {
+                            "type": "string",
+                            "description": "Target triple to check, e.g. x86_64-pc-windows-msvc (optional)"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "diff_item".to_string(),
+                "Diff the documentation of an item between two versions of its crate, e.g. to see whether its signature or behavior changed in 2.0: unified diffs of its declaration and of its docs (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
+                        },
+                        "item_path": {
+                            "type": "string",
+                            "description": "Path to the item, as for lookup_item"
+                        },
+                        "from_version": {
+                            "type": "string",
+                            "description": "The older version to compare"
+                        },
+                        "to_version": {
+                            "type": "string",
+                            "description": "The newer version to compare (optional, defaults to latest)"
+                        }
+                    },
+                    "required": ["crate_name", "item_path", "from_version"]
+                }),
+            ),
+            Tool::new(
+                "find_defining_crate".to_string(),
+                "Find which crates define a type, trait, function or macro with a given name (e.g. DateTime, Deserialize), with full paths, by searching the rustdoc indexes of matching and popular crates (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "name": {
+                            "type": "string",
+                            "description": "The bare item name, e.g. 'DateTime'"
+                        },
+                        "crates": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Additional crates to search first, e.g. the project's dependencies (optional)"
+                        }
+                    },
+                    "required": ["name"]
+                }),
+            ),
+            Tool::new(
+                "compare_crates".to_string(),
+                "Compare crates side by side: latest version, downloads, license, MSRV and description from crates.io (returns a markdown table)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_names": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": format!("The crates to compare (at most {})", MAX_COMPARED_CRATES)
+                        }
+                    },
+                    "required": ["crate_names"]
+                }),
+            ),
+            Tool::new(
+                "recent_releases".to_string(),
+                "List recently published crate versions, for the given crates or across crates.io (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_names": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Crates to list releases of (optional, defaults to the most recently updated crates on crates.io)"
+                        },
+                        "since": {
+                            "type": "string",
+                            "description": "Only list releases published on or after this date, as YYYY-MM-DD (optional, defaults to 30 days ago)"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of releases to return (optional, defaults to 20, max 100)"
+                        }
+                    },
+                    "required": []
+                }),
+            ),
             Tool::new(
-                "lookup_crate".to_string(),
-                "Look up documentation for a Rust crate (returns markdown)".to_string(),
+                "watch_crates".to_string(),
+                format!(
+                    "Watch crates for new releases; releases found by the periodic check are listed in the {} resource",
+                    UPDATES_RESOURCE_URI
+                ),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_names": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Crates to watch"
+                        },
+                        "remove": {
+                            "type": "boolean",
+                            "description": "Stop watching the given crates instead (optional, defaults to false)"
+                        }
+                    },
+                    "required": ["crate_names"]
+                }),
+            ),
+            Tool::new(
+                "rust_release_notes".to_string(),
+                "Get the release notes of a Rust version, or of every release between two toolchain versions (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "version": {
+                            "type": "string",
+                            "description": "The Rust version, e.g. 1.75 or 1.75.0"
+                        },
+                        "from_version": {
+                            "type": "string",
+                            "description": "Toolchain version being upgraded from (optional); returns the notes of every release after it up to version"
+                        }
+                    },
+                    "required": ["version"]
+                }),
+            ),
+            Tool::new(
+                "edition_guide".to_string(),
+                "Read a chapter of the Rust Edition Guide, e.g. what changed in an edition and how to migrate (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "topic": {
+                            "type": "string",
+                            "description": "Chapter to read, e.g. 'disjoint capture in closures' or 'RPIT lifetime capture' (optional; lists the chapters when omitted)"
+                        },
+                        "edition": {
+                            "type": "string",
+                            "enum": EDITIONS,
+                            "description": "Only consider the chapters about this edition (optional)"
+                        }
+                    },
+                    "required": []
+                }),
+            ),
+            Tool::new(
+                "cargo_book".to_string(),
+                "Read the chapter of the Cargo Book on a topic, e.g. profiles, features, workspaces or registries (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "topic": {
+                            "type": "string",
+                            "description": "The topic or chapter title, e.g. 'profiles', 'workspaces' or 'source replacement'"
+                        }
+                    },
+                    "required": ["topic"]
+                }),
+            ),
+            Tool::new(
+                "lookup_book".to_string(),
+                "Read a chapter of an official Rust book: the Rustonomicon for unsafe code, the Async Book for async concepts, the Edition Guide or the Cargo Book (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "book": {
+                            "type": "string",
+                            "enum": Book::ALL.map(Book::path),
+                            "description": "The book to read"
+                        },
+                        "topic": {
+                            "type": "string",
+                            "description": "The topic or chapter title, e.g. 'variance' or 'pinning' (optional; lists the chapters when omitted)"
+                        }
+                    },
+                    "required": ["book"]
+                }),
+            ),
+            Tool::new(
+                "lookup_url".to_string(),
+                "Get the documentation page a docs.rs or doc.rust-lang.org URL points to, e.g. one pasted by the user; a #anchor narrows it to that section (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "url": {
+                            "type": "string",
+                            "description": "The page URL, e.g. https://docs.rs/tokio/latest/tokio/runtime/struct.Builder.html#method.worker_threads"
+                        },
+                        "source_links": {
+                            "type": "boolean",
+                            "description": "Append footnotes linking the item, and the members and items the result mentions, to their docs.rs pages and [src] source (optional, defaults to false)"
+                        },
+                        "hide_generated_impls": {
+                            "type": "boolean",
+                            "description": "Leave out the Auto Trait Implementations, Blanket Implementations and Methods from Deref sections (optional, defaults to false)"
+                        }
+                    },
+                    "required": ["url"]
+                }),
+            ),
+            Tool::new(
+                "crate_exists".to_string(),
+                "Check whether a crate is published on crates.io, reading only its sparse index entry; a cheap way to validate a name before a full lookup or a Cargo.toml entry (returns JSON)".to_string(),
                 json!({
                     "type": "object",
                     "properties": {
                         "crate_name": {
                             "type": "string",
-                            "description": "The name of the crate to look up"
+                            "description": "The name of the crate"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "version_exists".to_string(),
+                "Check whether a version of a crate is published on crates.io and whether it was yanked, reading only the crate's sparse index entry (returns JSON)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
                         },
                         "version": {
                             "type": "string",
-                            "description": "The version of the crate (optional, defaults to latest)"
+                            "description": "The exact version, e.g. 1.0.200"
+                        }
+                    },
+                    "required": ["crate_name", "version"]
+                }),
+            ),
+            Tool::new(
+                "latest_version".to_string(),
+                "Get the latest stable version of a crate, and the newest pre-release when there is a newer one, without fetching any documentation (returns JSON)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "crate_name": {
+                            "type": "string",
+                            "description": "The name of the crate"
                         }
                     },
                     "required": ["crate_name"]
                 }),
             ),
             Tool::new(
-                "search_crates".to_string(),
-                "Search for Rust crates on crates.io (returns JSON or markdown)".to_string(),
+                "release_notes".to_string(),
+                "Get the changelog entries or GitHub/GitLab release notes of a crate for every release after one version up to another, to answer what changed between them".to_string(),
                 json!({
                     "type": "object",
                     "properties": {
-                        "query": {
+                        "crate_name": {
                             "type": "string",
-                            "description": "The search query"
+                            "description": "The name of the crate"
                         },
-                        "limit": {
-                            "type": "integer",
-                            "description": "Maximum number of results to return (optional, defaults to 10, max 100)"
+                        "from_version": {
+                            "type": "string",
+                            "description": "The version upgraded from, e.g. 0.6 or 0.6.3; its own notes are not included"
+                        },
+                        "to_version": {
+                            "type": "string",
+                            "description": "The version upgraded to (defaults to the latest version)"
                         }
                     },
-                    "required": ["query"]
+                    "required": ["crate_name", "from_version"]
                 }),
             ),
             Tool::new(
-                "lookup_item".to_string(),
-                "Look up documentation for a specific item in a Rust crate (returns markdown)".to_string(),
+                "warm_cache".to_string(),
+                "Fetch the docs of every crates.io dependency locked in a project's Cargo.lock at its exact version into the cache, so later lookups while working in that project are instant".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "lockfile": {
+                            "type": "string",
+                            "description": "The contents of the project's Cargo.lock"
+                        }
+                    },
+                    "required": ["lockfile"]
+                }),
+            ),
+            Tool::new(
+                "project_dependencies".to_string(),
+                "Summarize every dependency of a project from its Cargo.toml: one-line description, the version its requirement resolves to against the latest one, docs link and notable features".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "manifest": {
+                            "type": "string",
+                            "description": "The contents of the project's Cargo.toml"
+                        },
+                        "path": {
+                            "type": "string",
+                            "description": "Directory of the project's Cargo.toml instead of its contents (only below the server's --local-docs-root directories)"
+                        }
+                    }
+                }),
+            ),
+            Tool::new(
+                "explain_imports".to_string(),
+                "Explain the imports of a Rust source file: find its use declarations and fully qualified paths, map each to its crate through the project's Cargo.toml (or by name) and return the opening of every item's docs in one call (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "source": {
+                            "type": "string",
+                            "description": "The Rust source code, e.g. the contents of a file"
+                        },
+                        "manifest": {
+                            "type": "string",
+                            "description": "The contents of the project's Cargo.toml, to map renamed dependencies and pick the versions their requirements resolve to (optional; roots are taken as crate names without it)"
+                        }
+                    },
+                    "required": ["source"]
+                }),
+            ),
+            Tool::new(
+                "page_outline".to_string(),
+                "List the headings of a crate's documentation page or an item's page, with their anchors, to fetch just one of them with the section argument of lookup_crate or lookup_item (returns markdown)".to_string(),
                 json!({
                     "type": "object",
                     "properties": {
@@ -311,31 +3695,344 @@ impl mcp_server::Router for DocRouter {
                         },
                         "item_path": {
                             "type": "string",
-                            "description": "Path to the item (e.g., 'vec::Vec' or 'crate_name::vec::Vec' - crate prefix will be automatically stripped)"
+                            "description": "Path to the item, as for lookup_item (optional; outlines the crate's root page when omitted)"
                         },
                         "version": {
                             "type": "string",
-                            "description": "The version of the crate (optional, defaults to latest)"
+                            "description": "The version of the crate (optional, defaults to latest); for std, core and alloc a channel (stable, beta, nightly) or Rust release such as 1.75.0, defaulting to stable"
+                        },
+                        "channel": {
+                            "type": "string",
+                            "enum": ["stable", "beta", "nightly"],
+                            "description": "Documentation channel of std, core or alloc (optional, alternative to version)"
+                        }
+                    },
+                    "required": ["crate_name"]
+                }),
+            ),
+            Tool::new(
+                "usage_top".to_string(),
+                "List the crates and items looked up most often on this server, e.g. to see which dependencies keep being asked about or what to preload (returns markdown)".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "kind": {
+                            "type": "string",
+                            "enum": ["crates", "items", "all"],
+                            "description": "List crates, items (as crate::item_path) or both (optional, defaults to all)"
+                        },
+                        "limit": {
+                            "type": "integer",
+                            "description": "Maximum number of crates and of items to list (optional, defaults to 10, max 100)"
+                        }
+                    },
+                    "required": []
+                }),
+            ),
+            Tool::new(
+                "configure_session".to_string(),
+                "Set defaults for the rest of this session's calls: the channel of std lookups, the output format, summary mode and a token budget per result; calls that pass an argument themselves keep it. Returns the session's settings as JSON; call it without arguments to read them".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "channel": {
+                            "type": "string",
+                            "enum": ["stable", "beta", "nightly", "none"],
+                            "description": "Channel of std, core and alloc lookups given no version or channel, or none to clear it (optional)"
+                        },
+                        "default_format": {
+                            "type": "string",
+                            "enum": ["markdown", "text", "html"],
+                            "description": "Format of results of calls given no format (optional)"
+                        },
+                        "summary": {
+                            "type": "boolean",
+                            "description": "Have lookup_crate and lookup_item return only the opening of pages unless a call passes summary=false or a section (optional)"
+                        },
+                        "token_budget": {
+                            "type": "integer",
+                            "description": "Rough cap on the size of each result in tokens, about 4 bytes each; longer results are cut off with the offset to continue from, or 0 for no cap (optional)"
+                        },
+                        "reset": {
+                            "type": "boolean",
+                            "description": "Clear every setting before applying the others (optional, defaults to false)"
+                        }
+                    },
+                    "required": []
+                }),
+            ),
+        ];
+
+        if self.local_docs.is_some() {
+            tools.push(Tool::new(
+                "build_local_docs".to_string(),
+                "Build documentation for a local Cargo workspace with cargo doc --no-deps, after which its crates (including private ones) can be looked up with lookup_crate and lookup_item".to_string(),
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Path of the workspace (the directory containing Cargo.toml)"
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            ));
+        }
+
+        #[cfg(feature = "semver")]
+        tools.push(Tool::new(
+            "semver_check".to_string(),
+            "Report breaking changes between two versions of a crate, comparing their public APIs from docs.rs rustdoc JSON".to_string(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "crate_name": {
+                        "type": "string",
+                        "description": "The name of the crate"
+                    },
+                    "from_version": {
+                        "type": "string",
+                        "description": "The version being upgraded from"
+                    },
+                    "to_version": {
+                        "type": "string",
+                        "description": "The version being upgraded to (optional, defaults to latest)"
+                    }
+                },
+                "required": ["crate_name", "from_version"]
+            }),
+        ));
+
+        // Every tool's output can be rendered in another format, highlighted and read in parts
+        for tool in &mut tools {
+            if let Some(properties) = tool.input_schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+                properties.insert(
+                    "format".to_string(),
+                    json!({
+                        "type": "string",
+                        "enum": ["markdown", "text", "html"],
+                        "description": "Output format (optional, defaults to markdown); text renders the markdown as plain text, html as sanitized HTML"
+                    }),
+                );
+                properties.insert(
+                    "highlight".to_string(),
+                    json!({
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Terms to wrap in **bold** wherever they occur, ignoring case, with their match counts per section at the top (optional, up to 10)"
+                    }),
+                );
+                properties.insert(
+                    "offset".to_string(),
+                    json!({
+                        "type": "integer",
+                        "description": "Byte offset to continue a truncated result from (optional), as given by its [truncated: use offset=N to continue] marker"
+                    }),
+                );
+                properties.insert(
+                    "block".to_string(),
+                    json!({
+                        "type": "integer",
+                        "description": "Block of a multi-block result (such as lookup_items) to continue from offset (optional), as given by its [truncated: use offset=N and block=I to continue] marker; only that block is returned"
+                    }),
+                );
+            }
+        }
+
+        tools
+    }
+
+    fn call_tool(
+        &self,
+        tool_name: &str,
+        arguments: Value,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
+        let this = self.clone();
+        let tool_name = tool_name.to_string();
+        let mut arguments = arguments.clone();
+        // The session's defaults don't apply to the call changing them, which takes
+        // parameters of the same names
+        let settings = match tool_name.as_str() {
+            "configure_session" => SessionSettings::default(),
+            _ => self.session_defaults.get(),
+        };
+        if !self.crate_overrides.is_empty() || !settings.is_default() {
+            if let Some(tool) = self.list_tools().into_iter().find(|tool| tool.name == tool_name) {
+                self.crate_overrides.apply(&tool.input_schema, &mut arguments);
+                settings.apply(&tool.input_schema, &mut arguments);
+            }
+        }
+
+        let span = tracing::debug_span!("tool_call", tool = %tool_name, elapsed_ms = tracing::field::Empty);
+        let slow_call = self.slow_call_threshold.map(|threshold| (threshold, tool_name.clone(), arguments.clone()));
+        let history = self.history.clone();
+        let (call_name, call_arguments) = (tool_name.clone(), arguments.to_string());
+        let call = async move {
+            let (format, offset, block, highlight_terms) = timed_sync(Phase::Arguments, || {
+                // Refused crates fail with the policy error rather than whatever lookup fallback gives up last
+                let item_crates = arguments.get("items").and_then(|items| items.as_array()).into_iter().flatten();
+                for argument in std::iter::once(&arguments).chain(item_crates) {
+                    if let Some(crate_name) = argument.get("crate_name").and_then(|v| v.as_str()) {
+                        this.crate_policy.check(crate_name)?;
+                    }
+                }
+
+                let format = match arguments.get("format").and_then(|v| v.as_str()) {
+                    Some(format) => OutputFormat::parse(format).ok_or_else(|| {
+                        ToolError::InvalidParameters(format!("Unknown format {}. Expected markdown, text or html", format))
+                    })?,
+                    None => OutputFormat::default(),
+                };
+                let offset = arguments.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                let block = arguments.get("block").and_then(|v| v.as_u64()).map(|block| block as usize);
+                let highlight_terms: Vec<String> = match arguments.get("highlight") {
+                    Some(Value::Array(terms)) => terms.iter().filter_map(|v| v.as_str()).map(|s| s.to_string()).collect(),
+                    Some(Value::String(term)) => vec![term.clone()],
+                    _ => Vec::new(),
+                };
+                if highlight_terms.len() > MAX_HIGHLIGHT_TERMS {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "highlight takes at most {} terms",
+                        MAX_HIGHLIGHT_TERMS
+                    )));
+                }
+                Ok::<_, ToolError>((format, offset, block, highlight_terms))
+            })?;
+
+            let run = async { match tool_name.as_str() {
+                "lookup_crate" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    let channel = arguments.get("channel").and_then(|v| v.as_str());
+                    let version = channel_version(&crate_name, version, channel)?;
+                    
+                    let section = arguments.get("section").and_then(|v| v.as_str());
+                    let summary = arguments.get("summary").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let source_links = arguments.get("source_links").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                    let doc = this.lookup_crate(crate_name, version).await?;
+                    let mut doc = match section {
+                        Some(section) => doc_section(&doc, section)?,
+                        None if summary => doc_summary(doc),
+                        None => doc,
+                    };
+                    if source_links {
+                        doc.push_str(&this.source_links(&doc).await);
+                    }
+                    Ok(vec![Content::text(doc)])
+                }
+                "search_crates" => {
+                    let query = arguments
+                        .get("query")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("query is required".to_string()))?
+                        .to_string();
+                    
+                    let limit = arguments
+                        .get("limit")
+                        .and_then(|v| v.as_u64())
+                        .map(|v| v as u32);
+
+                    let backend = match arguments.get("backend").and_then(|v| v.as_str()) {
+                        Some(backend) => Some(SearchBackend::parse(backend).ok_or_else(|| {
+                            ToolError::InvalidParameters(format!(
+                                "Unknown backend {}. Expected crates-io or lib-rs",
+                                backend
+                            ))
+                        })?),
+                        None => None,
+                    };
+                    
+                    let results = this.search_crates(query, limit, backend).await?;
+                    Ok(vec![Content::text(results)])
+                }
+                "lookup_item" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let item_path = arguments
+                        .get("item_path")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("item_path is required".to_string()))?
+                        .to_string();
+                    
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    let channel = arguments.get("channel").and_then(|v| v.as_str());
+                    let version = channel_version(&crate_name, version, channel)?;
+                    
+                    let section = arguments.get("section").and_then(|v| v.as_str());
+                    let summary = arguments.get("summary").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let source_links = arguments.get("source_links").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                    let hide_generated_impls = arguments.get("hide_generated_impls").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                    let probe_order = match arguments.get("probe_order").and_then(|v| v.as_array()) {
+                        Some(kinds) => {
+                            let kinds: Vec<String> = kinds.iter().filter_map(|kind| kind.as_str()).map(|kind| kind.to_string()).collect();
+                            Some(item_probe_order(&kinds).map_err(ToolError::InvalidParameters)?)
                         }
-                    },
-                    "required": ["crate_name", "item_path"]
-                }),
-            ),
-        ]
-    }
+                        None => None,
+                    };
 
-    fn call_tool(
-        &self,
-        tool_name: &str,
-        arguments: Value,
-    ) -> Pin<Box<dyn Future<Output = Result<Vec<Content>, ToolError>> + Send + 'static>> {
-        let this = self.clone();
-        let tool_name = tool_name.to_string();
-        let arguments = arguments.clone();
+                    let mut doc = this.lookup_item_in_order(crate_name, item_path, version, probe_order).await?;
+                    if hide_generated_impls {
+                        doc = without_generated_impls(&doc);
+                    }
+                    let mut doc = match section {
+                        Some(section) => doc_section(&doc, section)?,
+                        None if summary => doc_summary(doc),
+                        None => doc,
+                    };
+                    if source_links {
+                        doc.push_str(&this.source_links(&doc).await);
+                    }
+                    Ok(vec![Content::text(doc)])
+                }
+                "lookup_items" => {
+                    let entries = arguments
+                        .get("items")
+                        .and_then(|v| v.as_array())
+                        .ok_or_else(|| ToolError::InvalidParameters("items is required".to_string()))?;
+                    if entries.is_empty() || entries.len() > MAX_LOOKUP_ITEMS {
+                        return Err(ToolError::InvalidParameters(format!(
+                            "items must contain between 1 and {} entries",
+                            MAX_LOOKUP_ITEMS
+                        )));
+                    }
 
-        Box::pin(async move {
-            match tool_name.as_str() {
-                "lookup_crate" => {
+                    let items = entries
+                        .iter()
+                        .enumerate()
+                        .map(|(i, entry)| {
+                            let field = |name: &str| entry.get(name).and_then(|v| v.as_str()).map(|s| s.to_string());
+                            let missing = |name: &str| ToolError::InvalidParameters(format!("items[{}].{} is required", i, name));
+                            Ok((
+                                field("crate_name").ok_or_else(|| missing("crate_name"))?,
+                                field("item_path").ok_or_else(|| missing("item_path"))?,
+                                field("version"),
+                            ))
+                        })
+                        .collect::<Result<Vec<_>, ToolError>>()?;
+
+                    let docs = this.lookup_items(items).await;
+                    Ok(docs.into_iter().map(Content::text).collect())
+                }
+                "dependency_snippet" => {
                     let crate_name = arguments
                         .get("crate_name")
                         .and_then(|v| v.as_str())
@@ -347,25 +4044,125 @@ impl mcp_server::Router for DocRouter {
                         .and_then(|v| v.as_str())
                         .map(|s| s.to_string());
                     
-                    let doc = this.lookup_crate(crate_name, version).await?;
-                    Ok(vec![Content::text(doc)])
+                    let features = string_array_arg(&arguments, "features");
+                    
+                    let policy = match arguments.get("version_policy").and_then(|v| v.as_str()) {
+                        Some(policy) => VersionPolicy::parse(policy).ok_or_else(|| {
+                            ToolError::InvalidParameters(format!(
+                                "Unknown version_policy {}. Expected caret, minor, major or exact",
+                                policy
+                            ))
+                        })?,
+                        None => VersionPolicy::default(),
+                    };
+                    
+                    let snippet = this.dependency_snippet(crate_name, version, features, policy).await?;
+                    Ok(vec![Content::text(snippet)])
                 }
-                "search_crates" => {
-                    let query = arguments
-                        .get("query")
+                "resolve_dependencies" => {
+                    let crate_name = arguments
+                        .get("crate_name")
                         .and_then(|v| v.as_str())
-                        .ok_or_else(|| ToolError::InvalidParameters("query is required".to_string()))?
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
                         .to_string();
                     
-                    let limit = arguments
-                        .get("limit")
-                        .and_then(|v| v.as_u64())
-                        .map(|v| v as u32);
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
                     
-                    let results = this.search_crates(query, limit).await?;
-                    Ok(vec![Content::text(results)])
+                    let features = string_array_arg(&arguments, "features");
+                    
+                    let default_features = default_features_arg(&arguments);
+                    let transitive = arguments.get("transitive").and_then(|v| v.as_bool()).unwrap_or(true);
+                    
+                    let tree = arguments.get("tree").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let depth = match arguments.get("depth").and_then(|v| v.as_u64()) {
+                        Some(0) => return Err(ToolError::InvalidParameters("depth must be at least 1".to_string())),
+                        Some(depth) => depth as usize,
+                        None => DEFAULT_TREE_DEPTH,
+                    };
+                    
+                    let resolved = this
+                        .resolve_dependencies(crate_name, version, features, default_features, transitive, tree.then_some(depth))
+                        .await?;
+                    Ok(vec![Content::text(resolved)])
                 }
-                "lookup_item" => {
+                "dependency_weight" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let features = string_array_arg(&arguments, "features");
+                    
+                    let default_features = default_features_arg(&arguments);
+                    
+                    let weight = this.dependency_weight(crate_name, version, features, default_features).await?;
+                    Ok(vec![Content::text(weight)])
+                }
+                "source_stats" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let stats = this.crate_source_stats(crate_name, version).await?;
+                    Ok(vec![Content::text(stats)])
+                }
+                "feature_graph" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let feature = arguments
+                        .get("feature")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let graph = this.feature_graph(crate_name, version, feature).await?;
+                    Ok(vec![Content::text(graph)])
+                }
+                "doc_targets" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let target = arguments
+                        .get("target")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let targets = this.doc_targets(crate_name, version, target).await?;
+                    Ok(vec![Content::text(targets)])
+                }
+                "diff_item" => {
                     let crate_name = arguments
                         .get("crate_name")
                         .and_then(|v| v.as_str())
@@ -378,29 +4175,467 @@ impl mcp_server::Router for DocRouter {
                         .ok_or_else(|| ToolError::InvalidParameters("item_path is required".to_string()))?
                         .to_string();
                     
+                    let from_version = arguments
+                        .get("from_version")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("from_version is required".to_string()))?
+                        .to_string();
+                    
+                    let to_version = arguments
+                        .get("to_version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let diff = this.diff_item(crate_name, item_path, from_version, to_version).await?;
+                    Ok(vec![Content::text(diff)])
+                }
+                "find_defining_crate" => {
+                    let name = arguments
+                        .get("name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("name is required".to_string()))?
+                        .to_string();
+
+                    let crates = arguments
+                        .get("crates")
+                        .and_then(|v| v.as_array())
+                        .map(|crates| {
+                            crates
+                                .iter()
+                                .filter_map(|c| c.as_str())
+                                .map(|c| c.to_string())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let definitions = this.find_defining_crate(name, crates).await?;
+                    Ok(vec![Content::text(definitions)])
+                }
+                "compare_crates" => {
+                    let crate_names = arguments
+                        .get("crate_names")
+                        .and_then(|v| v.as_array())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_names is required".to_string()))?
+                        .iter()
+                        .filter_map(|name| name.as_str())
+                        .map(|name| name.to_string())
+                        .collect();
+
+                    let table = this.compare_crates(crate_names).await?;
+                    Ok(vec![Content::text(table)])
+                }
+                "recent_releases" => {
+                    let crate_names = arguments
+                        .get("crate_names")
+                        .and_then(|v| v.as_array())
+                        .map(|names| {
+                            names
+                                .iter()
+                                .filter_map(|name| name.as_str())
+                                .map(|name| name.to_string())
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    let since = arguments
+                        .get("since")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let limit = arguments
+                        .get("limit")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(20)
+                        .min(100) as usize;
+
+                    let releases = this.recent_releases(crate_names, since, limit).await?;
+                    Ok(vec![Content::text(releases)])
+                }
+                "usage_top" => {
+                    let (crates, items) = match arguments.get("kind").and_then(|v| v.as_str()).unwrap_or("all") {
+                        "crates" => (true, false),
+                        "items" => (false, true),
+                        "all" => (true, true),
+                        kind => {
+                            return Err(ToolError::InvalidParameters(format!(
+                                "Unknown kind {}. Expected crates, items or all",
+                                kind
+                            )))
+                        }
+                    };
+                    let limit = arguments
+                        .get("limit")
+                        .and_then(|v| v.as_u64())
+                        .map_or(DEFAULT_USAGE_TOP, |limit| (limit as usize).clamp(1, MAX_USAGE_TOP));
+                    Ok(vec![Content::text(this.usage.top_markdown(limit, crates, items))])
+                }
+                "configure_session" => {
+                    let settings = this.configure_session(&arguments)?;
+                    Ok(vec![Content::text(settings.to_json().to_string())])
+                }
+                "watch_crates" => {
+                    let crate_names: Vec<String> = arguments
+                        .get("crate_names")
+                        .and_then(|v| v.as_array())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_names is required".to_string()))?
+                        .iter()
+                        .filter_map(|name| name.as_str())
+                        .map(|name| name.to_string())
+                        .collect();
+
+                    let remove = arguments
+                        .get("remove")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+
+                    let watched = this.watch_crates(crate_names, remove).await?;
+                    Ok(vec![Content::text(watched)])
+                }
+                "rust_release_notes" => {
                     let version = arguments
                         .get("version")
                         .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("version is required".to_string()))?
+                        .to_string();
+
+                    let from_version = arguments
+                        .get("from_version")
+                        .and_then(|v| v.as_str())
                         .map(|s| s.to_string());
-                    
-                    let doc = this.lookup_item(crate_name, item_path, version).await?;
+
+                    let notes = this.rust_release_notes(version, from_version).await?;
+                    Ok(vec![Content::text(notes)])
+                }
+                "edition_guide" => {
+                    let topic = arguments
+                        .get("topic")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let section = match arguments.get("edition").and_then(|v| v.as_str()) {
+                        Some(edition) if EDITIONS.contains(&edition) => Some(format!("rust-{}/", edition)),
+                        Some(edition) => {
+                            return Err(ToolError::InvalidParameters(format!(
+                                "Unknown edition {}. Expected one of {}",
+                                edition,
+                                EDITIONS.join(", ")
+                            )));
+                        }
+                        None => None,
+                    };
+
+                    let doc = this.lookup_book(Book::EditionGuide, topic, section.as_deref()).await?;
+                    Ok(vec![Content::text(doc)])
+                }
+                "cargo_book" => {
+                    let topic = arguments
+                        .get("topic")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("topic is required".to_string()))?
+                        .to_string();
+
+                    let doc = this.lookup_book(Book::Cargo, Some(topic), None).await?;
+                    Ok(vec![Content::text(doc)])
+                }
+                "lookup_book" => {
+                    let name = arguments
+                        .get("book")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("book is required".to_string()))?;
+                    let book = Book::parse(name).ok_or_else(|| {
+                        ToolError::InvalidParameters(format!(
+                            "Unknown book {}. Expected one of {}",
+                            name,
+                            Book::ALL.map(Book::path).join(", ")
+                        ))
+                    })?;
+
+                    let topic = arguments
+                        .get("topic")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+
+                    let doc = this.lookup_book(book, topic, None).await?;
+                    Ok(vec![Content::text(doc)])
+                }
+                "lookup_url" => {
+                    let url = arguments
+                        .get("url")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("url is required".to_string()))?
+                        .to_string();
+
+                    let source_links = arguments.get("source_links").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                    let hide_generated_impls = arguments.get("hide_generated_impls").and_then(|v| v.as_bool()).unwrap_or(false);
+
+                    let mut doc = this.lookup_url(url).await?;
+                    if hide_generated_impls {
+                        doc = without_generated_impls(&doc);
+                    }
+                    if source_links {
+                        doc.push_str(&this.source_links(&doc).await);
+                    }
                     Ok(vec![Content::text(doc)])
                 }
+                "crate_exists" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+
+                    let result = this.crate_exists(crate_name).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "version_exists" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("version is required".to_string()))?
+                        .trim()
+                        .to_string();
+
+                    let result = this.version_exists(crate_name, version).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "latest_version" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+
+                    let result = this.latest_version(crate_name).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "release_notes" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    let from_version = arguments
+                        .get("from_version")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("from_version is required".to_string()))?
+                        .to_string();
+                    let to_version = arguments.get("to_version").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+                    let result = this.release_notes(crate_name, from_version, to_version).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "warm_cache" => {
+                    let lockfile = arguments
+                        .get("lockfile")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("lockfile is required".to_string()))?;
+
+                    let result = this.warm_from_lockfile(lockfile).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "project_dependencies" => {
+                    let manifest = arguments.get("manifest").and_then(|v| v.as_str()).map(|s| s.to_string());
+                    let path = arguments.get("path").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+                    let result = this.project_dependencies(manifest, path).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "explain_imports" => {
+                    let source = arguments
+                        .get("source")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("source is required".to_string()))?
+                        .to_string();
+                    let manifest = arguments.get("manifest").and_then(|v| v.as_str()).map(|s| s.to_string());
+
+                    let result = this.explain_imports(source, manifest).await?;
+                    Ok(vec![Content::text(result)])
+                }
+                "page_outline" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    let item_path = arguments.get("item_path").and_then(|v| v.as_str()).map(|s| s.to_string());
+                    let version = arguments
+                        .get("version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    let channel = arguments.get("channel").and_then(|v| v.as_str());
+                    let version = channel_version(&crate_name, version, channel)?;
+
+                    let outline = this.page_outline(crate_name, item_path, version).await?;
+                    Ok(vec![Content::text(outline)])
+                }
+                "build_local_docs" => {
+                    let path = arguments
+                        .get("path")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("path is required".to_string()))?
+                        .to_string();
+
+                    let built = this.build_local_docs(path).await?;
+                    Ok(vec![Content::text(built)])
+                }
+                #[cfg(feature = "semver")]
+                "semver_check" => {
+                    let crate_name = arguments
+                        .get("crate_name")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("crate_name is required".to_string()))?
+                        .to_string();
+                    
+                    let from_version = arguments
+                        .get("from_version")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| ToolError::InvalidParameters("from_version is required".to_string()))?
+                        .to_string();
+                    
+                    let to_version = arguments
+                        .get("to_version")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string());
+                    
+                    let report = this.semver_check(crate_name, from_version, to_version).await?;
+                    Ok(vec![Content::text(report)])
+                }
                 _ => Err(ToolError::NotFound(format!("Tool {} not found", tool_name))),
+            } };
+            let contents = match this.tool_timeouts.get(&tool_name) {
+                Some(limit) => tokio::time::timeout(*limit, run).await.map_err(|_| DocsError::Timeout {
+                    tool: tool_name.clone(),
+                    limit: *limit,
+                })?,
+                None => run.await,
+            }?;
+
+            // Only lookups that worked count, so typos don't make the top lists
+            let item_lookups = arguments.get("items").and_then(|items| items.as_array()).into_iter().flatten();
+            for argument in std::iter::once(&arguments).chain(item_lookups) {
+                if let Some(crate_name) = argument.get("crate_name").and_then(|v| v.as_str()) {
+                    this.usage.record(crate_name, argument.get("item_path").and_then(|v| v.as_str()));
+                }
             }
-        })
+
+            // A token budget caps the whole result, so it comes in one part
+            let (max_bytes, max_parts) = match settings.token_budget {
+                Some(_) => (settings.max_content_bytes(this.max_content_bytes), 1),
+                None => (this.max_content_bytes, this.max_content_parts),
+            };
+            // Each text block of a multi-block result (such as one per item of lookup_items) is
+            // truncated on its own, so continuing one takes its index as well as the offset
+            let blocks = contents.iter().filter(|content| matches!(content, Content::Text(_))).count();
+            match block {
+                Some(block) if block >= blocks => {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "block {} is past the last block of the result ({} blocks)",
+                        block, blocks
+                    )));
+                }
+                None if offset > 0 && blocks > 1 => {
+                    return Err(ToolError::InvalidParameters(format!(
+                        "offset needs the block it continues in a result of {} blocks, as given by its truncation marker",
+                        blocks
+                    )));
+                }
+                _ => {}
+            }
+            let mut parts = Vec::new();
+            let mut index = 0;
+            for content in contents {
+                match content {
+                    Content::Text(text) => {
+                        index += 1;
+                        if block.is_some_and(|block| block != index - 1) {
+                            continue;
+                        }
+                        let text = if highlight_terms.is_empty() {
+                            text.text
+                        } else {
+                            highlight(&text.text, &highlight_terms)
+                        };
+                        let marked_block = (blocks > 1).then_some(index - 1);
+                        parts.extend(
+                            content_parts(format.render(text), offset, max_bytes, max_parts, marked_block)?
+                                .into_iter()
+                                .map(Content::text),
+                        )
+                    }
+                    other if block.is_none() => parts.push(other),
+                    _ => {}
+                }
+            }
+            Ok(parts)
+        };
+
+        // The whole call is timed in its span, and each phase in its own span below it
+        Box::pin(
+            async move {
+                let breakdown = CallBreakdown::default();
+                let (started, start) = (SystemTime::now(), Instant::now());
+                let (result, documents): (Result<Vec<Content>, ToolError>, _) =
+                    collect_documents(breakdown.scope(call)).await;
+                history.record(HistoryEntry::new(started, &call_name, call_arguments, documents, result.is_ok()));
+                let elapsed_ms = start.elapsed().as_millis() as u64;
+                tracing::Span::current().record("elapsed_ms", elapsed_ms);
+                tracing::debug!(elapsed_ms, ok = result.is_ok(), "Tool call took {}ms: {}", elapsed_ms, breakdown);
+                if let Some((_, tool, arguments)) = slow_call.filter(|(threshold, ..)| start.elapsed() >= *threshold) {
+                    tracing::warn!(
+                        %tool,
+                        %arguments,
+                        upstream_urls = ?breakdown.upstream_urls(),
+                        elapsed_ms,
+                        ok = result.is_ok(),
+                        "Slow tool call {} took {}ms: {}",
+                        tool,
+                        elapsed_ms,
+                        breakdown
+                    );
+                }
+                result
+            }
+            // Every error carries a code for the transport to send along (see `errors`)
+            .map(|result| result.map_err(ensure_error_data))
+            .instrument(span),
+        )
     }
 
     fn list_resources(&self) -> Vec<Resource> {
-        vec![]
+        [(UPDATES_RESOURCE_URI, "Crate updates"), (HISTORY_RESOURCE_URI, "Session history")]
+            .into_iter()
+            .filter_map(|(uri, name)| match Resource::new(uri, Some("text".to_string()), Some(name.to_string())) {
+                Ok(resource) => Some(resource),
+                Err(e) => {
+                    tracing::error!("Invalid resource {}: {}", uri, e);
+                    None
+                }
+            })
+            .collect()
     }
 
     fn read_resource(
         &self,
-        _uri: &str,
+        uri: &str,
     ) -> Pin<Box<dyn Future<Output = Result<String, ResourceError>> + Send + 'static>> {
+        let this = self.clone();
+        let uri = uri.to_string();
         Box::pin(async move {
-            Err(ResourceError::NotFound("Resource not found".to_string()))
+            // Reads of documentation pages are part of the session's history; reads of
+            // the updates and history resources aren't
+            if DocResource::parse(&uri).is_none() {
+                return this.resource_content(&uri).await;
+            }
+            let started = SystemTime::now();
+            let (result, documents) = collect_documents(this.resource_content(&uri)).await;
+            this.history.record(HistoryEntry::new(started, "resources/read", uri, documents, result.is_ok()));
+            result
         })
     }
 
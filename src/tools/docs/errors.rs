@@ -0,0 +1,256 @@
+use std::time::Duration;
+
+use mcp_core::ToolError;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::sparse_index::IndexVersion;
+use super::versions::Version;
+
+/// Number of versions suggested when a requested version doesn't exist
+const SUGGESTED_VERSIONS: usize = 5;
+
+// Separates a tool error's message from its serialized `ErrorData`
+const ERROR_DATA_MARKER: &str = "\n\nerror data: ";
+
+/// Machine-readable kind of a failed tool call, for agents to branch on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    CrateNotFound,
+    VersionNotFound,
+    ItemNotFound,
+    /// The operator's crate allowlist or denylist refuses the crate
+    CrateNotAllowed,
+    /// Something other than a crate, version or item doesn't exist (a tool, a chapter, ...)
+    NotFound,
+    InvalidArguments,
+    UpstreamRateLimited,
+    /// docs.rs, crates.io or another upstream failed or couldn't be reached
+    UpstreamUnavailable,
+    Timeout,
+    ExecutionFailed,
+}
+
+impl ErrorCode {
+    /// The code as sent to clients, e.g. `CRATE_NOT_FOUND`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::CrateNotFound => "CRATE_NOT_FOUND",
+            ErrorCode::VersionNotFound => "VERSION_NOT_FOUND",
+            ErrorCode::ItemNotFound => "ITEM_NOT_FOUND",
+            ErrorCode::CrateNotAllowed => "CRATE_NOT_ALLOWED",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::InvalidArguments => "INVALID_ARGUMENTS",
+            ErrorCode::UpstreamRateLimited => "UPSTREAM_RATE_LIMITED",
+            ErrorCode::UpstreamUnavailable => "UPSTREAM_UNAVAILABLE",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::ExecutionFailed => "EXECUTION_FAILED",
+        }
+    }
+}
+
+/// Structured part of a tool error, sent as the `data` of the JSON-RPC error
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorData {
+    pub code: ErrorCode,
+    /// Things to try instead, e.g. the versions that do exist
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+impl ErrorData {
+    pub fn new(code: ErrorCode) -> Self {
+        Self { code, suggestions: Vec::new() }
+    }
+
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Self {
+        self.suggestions = suggestions;
+        self
+    }
+
+    // The code an error gets when nothing more specific was attached to it
+    fn for_variant(error: &ToolError) -> Self {
+        Self::new(match error {
+            ToolError::InvalidParameters(_) | ToolError::SchemaError(_) => ErrorCode::InvalidArguments,
+            ToolError::NotFound(_) => ErrorCode::NotFound,
+            _ => ErrorCode::ExecutionFailed,
+        })
+    }
+}
+
+/// Attach `data` to a tool error, replacing any data it already carries
+///
+/// `ToolError` only holds a message, so the data is appended to it and lifted into the
+/// JSON-RPC error by the transport (see `split_error_data`).
+pub fn with_error_data(error: ToolError, data: ErrorData) -> ToolError {
+    let data = serde_json::to_string(&data).expect("error data always serializes");
+    let attach = |message: String| format!("{}{}{}", split_error_data(&message).0, ERROR_DATA_MARKER, data);
+    match error {
+        ToolError::InvalidParameters(message) => ToolError::InvalidParameters(attach(message)),
+        ToolError::ExecutionError(message) => ToolError::ExecutionError(attach(message)),
+        ToolError::SchemaError(message) => ToolError::SchemaError(attach(message)),
+        ToolError::NotFound(message) => ToolError::NotFound(attach(message)),
+    }
+}
+
+/// Give an error without data the code that fits its variant
+pub fn ensure_error_data(error: ToolError) -> ToolError {
+    if error_data(&error).is_some() {
+        return error;
+    }
+    let data = ErrorData::for_variant(&error);
+    with_error_data(error, data)
+}
+
+/// The data attached to a tool error, if any
+pub fn error_data(error: &ToolError) -> Option<ErrorData> {
+    split_error_data(&error.to_string()).1
+}
+
+/// Split a tool error message into the text meant for people and its attached data
+pub fn split_error_data(message: &str) -> (&str, Option<ErrorData>) {
+    message
+        .rsplit_once(ERROR_DATA_MARKER)
+        .and_then(|(text, data)| Some((text, serde_json::from_str(data).ok()?)))
+        .unwrap_or((message, None))
+}
+
+/// The message of a tool error without the variant's display prefix or its data, for building on
+pub fn error_message(error: ToolError) -> String {
+    match error {
+        ToolError::ExecutionError(message) | ToolError::InvalidParameters(message) | ToolError::NotFound(message) => {
+            split_error_data(&message).0.to_string()
+        }
+        other => split_error_data(&other.to_string()).0.to_string(),
+    }
+}
+
+/// What went wrong looking up documentation, kept typed until it leaves as a `ToolError`
+///
+/// Converting into a `ToolError` attaches the `ErrorData` the variant calls for, so
+/// clients get the same codes and suggestions whichever code path failed.
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum DocsError {
+    /// A crate that isn't published
+    #[error("{message}")]
+    CrateNotFound { crate_name: String, message: String },
+    /// A version that isn't published, with the versions to use instead
+    #[error("{message}")]
+    VersionNotFound { message: String, suggestions: Vec<String> },
+    /// An item the crate's docs don't have, with the paths to look up instead
+    #[error("{message}")]
+    ItemNotFound { message: String, suggestions: Vec<String> },
+    /// Something else upstream doesn't have
+    #[error("{0}")]
+    NotFound(String),
+    /// An upstream answered `action` with an error status other than 429
+    #[error("Failed to {action}. Status: {status}")]
+    UpstreamStatus { action: String, status: StatusCode },
+    /// An upstream answered `action` with 429 Too Many Requests
+    #[error("Failed to {action}. Status: {}", StatusCode::TOO_MANY_REQUESTS)]
+    RateLimited { action: String, retry_after: Option<Duration> },
+    /// An upstream couldn't be reached or was given up on for a while
+    #[error("{message}")]
+    Unavailable { message: String, retry_in: Option<Duration> },
+    /// A response that couldn't be read as what it should be
+    #[error("{0}")]
+    Decode(String),
+    /// Docs that were read but couldn't be turned into what a tool answers with,
+    /// such as rustdoc JSON into a crate's public API
+    #[error("{0}")]
+    Conversion(String),
+    /// A response over the configured size limit, where `what` names it
+    #[error("{what} exceeds the maximum size of {max_bytes} bytes")]
+    TooLarge { what: String, max_bytes: usize },
+    /// A tool call that ran past its time limit
+    #[error("Timed out: {tool} did not finish within {limit:?}")]
+    Timeout { tool: String, limit: Duration },
+}
+
+impl DocsError {
+    /// The code clients get for the error, `None` for errors of no particular kind
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            DocsError::CrateNotFound { .. } => Some(ErrorCode::CrateNotFound),
+            DocsError::VersionNotFound { .. } => Some(ErrorCode::VersionNotFound),
+            DocsError::ItemNotFound { .. } => Some(ErrorCode::ItemNotFound),
+            DocsError::NotFound(_) => Some(ErrorCode::NotFound),
+            DocsError::UpstreamStatus { status, .. } if *status == StatusCode::NOT_FOUND => Some(ErrorCode::NotFound),
+            DocsError::UpstreamStatus { status, .. } if status.is_server_error() => Some(ErrorCode::UpstreamUnavailable),
+            DocsError::UpstreamStatus { .. } | DocsError::Conversion(_) | DocsError::TooLarge { .. } => None,
+            DocsError::RateLimited { .. } => Some(ErrorCode::UpstreamRateLimited),
+            DocsError::Unavailable { .. } | DocsError::Decode(_) => Some(ErrorCode::UpstreamUnavailable),
+            DocsError::Timeout { .. } => Some(ErrorCode::Timeout),
+        }
+    }
+
+    /// Whether the same request may work when made again later
+    pub fn is_transient(&self) -> bool {
+        match self {
+            DocsError::UpstreamStatus { status, .. } => status.is_server_error(),
+            DocsError::RateLimited { .. } | DocsError::Unavailable { .. } | DocsError::Timeout { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// How long the upstream asked to wait before trying again, if it said
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            DocsError::RateLimited { retry_after, .. } => *retry_after,
+            DocsError::Unavailable { retry_in, .. } => *retry_in,
+            _ => None,
+        }
+    }
+
+    fn suggestions(&self) -> Vec<String> {
+        match self {
+            DocsError::CrateNotFound { crate_name, .. } => vec![format!(
+                "Check the spelling, or find the crate with search_crates {{\"query\": \"{}\"}}",
+                crate_name
+            )],
+            DocsError::VersionNotFound { suggestions, .. } | DocsError::ItemNotFound { suggestions, .. } => suggestions.clone(),
+            DocsError::RateLimited { retry_after: Some(wait), .. } => vec![format!("Retry after {} seconds", wait.as_secs())],
+            DocsError::Unavailable { retry_in: Some(wait), .. } => vec![format!("Retry in {} seconds", wait.as_secs())],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl From<DocsError> for ToolError {
+    fn from(error: DocsError) -> Self {
+        let data = error.code().map(|code| ErrorData::new(code).with_suggestions(error.suggestions()));
+        let tool_error = ToolError::ExecutionError(error.to_string());
+        match data {
+            Some(data) => with_error_data(tool_error, data),
+            None => tool_error,
+        }
+    }
+}
+
+/// Error for a crate that isn't published
+pub fn crate_not_found(message: String, crate_name: &str) -> ToolError {
+    DocsError::CrateNotFound {
+        crate_name: crate_name.to_string(),
+        message,
+    }
+    .into()
+}
+
+/// Error for a version that isn't published, suggesting the newest ones that are
+pub fn version_not_found(message: String, versions: &[IndexVersion]) -> ToolError {
+    let mut published: Vec<(Version, &str)> = versions
+        .iter()
+        .filter(|version| !version.yanked)
+        .filter_map(|version| Some((Version::parse(&version.version)?, version.version.as_str())))
+        .collect();
+    published.sort();
+    let suggestions = published
+        .iter()
+        .rev()
+        .take(SUGGESTED_VERSIONS)
+        .map(|(_, version)| format!("Use version {}", version))
+        .collect();
+    DocsError::VersionNotFound { message, suggestions }.into()
+}
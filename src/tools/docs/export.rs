@@ -0,0 +1,71 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Default cap on the item pages exported for one crate, so crates with huge APIs
+/// (bindings such as `windows-sys`) don't run for hours
+pub const DEFAULT_EXPORT_MAX_PAGES: usize = 5000;
+
+/// Number of failed pages named in an export summary; the rest are only counted
+const LISTED_FAILURES: usize = 10;
+
+/// What exporting one crate's docs wrote
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub crate_name: String,
+    /// The exported version, or `latest` when it couldn't be resolved
+    pub version: String,
+    /// Directory the pages were written to
+    pub dir: PathBuf,
+    /// Pages written, the crate root included
+    pub written: usize,
+    /// Item pages listed in the crate's search index, after `max_pages`
+    pub listed: usize,
+    /// Paths of the items whose pages couldn't be fetched or written
+    pub failed: Vec<String>,
+    /// Items left out by the `max_pages` cap
+    pub skipped: usize,
+    /// Whether the crate's search index could be read; without it only the root is exported
+    pub has_index: bool,
+}
+
+/// The file an item page is exported to, relative to the version directory: the page's
+/// docs.rs path with `.md` for `.html`, e.g. `sync/mpsc/struct.Sender.md` or
+/// `sync/index.md`
+///
+/// `None` for paths that would leave the directory, which index entries shouldn't have.
+pub fn export_file_path(page_path: &str) -> Option<PathBuf> {
+    let path = Path::new(page_path.strip_suffix(".html").unwrap_or(page_path)).with_extension("md");
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then_some(path)
+}
+
+/// One line on an export, e.g. "Exported 120 of 121 pages of tokio 1.35.0 to docs/tokio/1.35.0; ...",
+/// where `verb` names what was done with the pages (`exported`, `mirrored`)
+pub fn format_export_summary(summary: &ExportSummary, verb: &str) -> String {
+    let mut capitalized = verb.chars();
+    let mut line = format!(
+        "{}{} {} of {} pages of {} {} to {}",
+        capitalized.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default(),
+        capitalized.as_str(),
+        summary.written,
+        summary.listed + 1,
+        summary.crate_name,
+        summary.version,
+        summary.dir.display()
+    );
+    if !summary.has_index {
+        line.push_str(&format!("; no search index found, so only the crate root was {}", verb));
+    }
+    if !summary.failed.is_empty() {
+        let mut named: Vec<&str> = summary.failed.iter().take(LISTED_FAILURES).map(String::as_str).collect();
+        if summary.failed.len() > LISTED_FAILURES {
+            named.push("...");
+        }
+        line.push_str(&format!("; {} failed: {}", summary.failed.len(), named.join(", ")));
+    }
+    if summary.skipped > 0 {
+        line.push_str(&format!("; {} more items not {} (raise --max-pages)", summary.skipped, verb));
+    }
+    line.push('\n');
+    line
+}
@@ -0,0 +1,194 @@
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+/// Anchor prefixes rustdoc gives to members of a type page, in lookup order
+/// (`tymethod` marks required trait methods, `method` everything else)
+pub const MEMBER_ANCHOR_KINDS: [&str; 4] = ["method", "tymethod", "variant", "structfield"];
+
+/// Classes rustdoc gives to links to items, e.g. in a module's item listing
+const ITEM_LINK_CLASSES: [&str; 14] = [
+    "mod", "struct", "enum", "trait", "fn", "macro", "type", "constant", "static", "union", "attr", "derive",
+    "primitive", "traitalias",
+];
+
+/// Classes of the elements rustdoc places after a member header that still
+/// belong to that member (stability notes, docs, variant fields)
+const TRAILING_SECTION_CLASSES: [&str; 3] = ["item-info", "docblock", "sub-variant"];
+
+/// Extract the section (signature + docs) rustdoc renders for `anchor`
+///
+/// Current rustdoc wraps a member as
+/// `<details><summary><section id="method.send">…</section></summary><div class="docblock">…</div></details>`;
+/// older versions put the docblock directly after the header element. Returns
+/// the HTML of the section, or `None` if the page has no such anchor.
+pub fn anchor_section(html: &str, anchor: &str) -> Option<String> {
+    section_in(&Html::parse_document(html), anchor)
+}
+
+/// Find the section of member `name` on a type page, returning the anchor it was found under
+pub fn member_section(html: &str, name: &str) -> Option<(String, String)> {
+    let document = Html::parse_document(html);
+    MEMBER_ANCHOR_KINDS.iter().find_map(|kind| {
+        let anchor = format!("{}.{}", kind, name);
+        section_in(&document, &anchor).map(|section| (anchor, section))
+    })
+}
+
+fn section_in(document: &Html, anchor: &str) -> Option<String> {
+    let selector = Selector::parse(&format!("[id=\"{}\"]", anchor)).ok()?;
+    let target = document.select(&selector).next()?;
+
+    // section -> summary -> details; don't climb into the enclosing impl block
+    let details = target
+        .ancestors()
+        .take(2)
+        .filter_map(ElementRef::wrap)
+        .find(|element| element.value().name() == "details");
+    if let Some(details) = details {
+        return Some(details.html());
+    }
+
+    let mut section = target.html();
+    let trailing = target
+        .next_siblings()
+        .filter_map(ElementRef::wrap)
+        .take_while(|element| {
+            element
+                .value()
+                .classes()
+                .any(|class| TRAILING_SECTION_CLASSES.contains(&class))
+        });
+    for element in trailing {
+        section.push_str(&element.html());
+    }
+    Some(section)
+}
+
+/// Names of all members of one kind on a page (e.g. every `variant.*` of an enum), in page order
+pub fn member_names(html: &str, kind: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse(&format!("[id^=\"{}.\"]", kind)) else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .filter_map(|element| element.value().id())
+        .filter_map(|id| id.strip_prefix(kind)?.strip_prefix('.'))
+        // Nested anchors such as `variant.Foo.field.bar` belong to another member
+        .filter(|name| !name.contains('.'))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// The `[Source]` link of an extracted section, as written in the page
+pub fn source_href(section_html: &str) -> Option<String> {
+    let fragment = Html::parse_fragment(section_html);
+    let selector = Selector::parse("a.src").ok()?;
+    let link = fragment.select(&selector).next()?;
+    link.value().attr("href").map(|href| href.to_string())
+}
+
+/// `#![feature]` gates named by the nightly-only stability notes of a page or
+/// section, in page order without repeats
+pub fn unstable_features(html: &str) -> Vec<String> {
+    let fragment = Html::parse_fragment(html);
+    let Ok(selector) = Selector::parse(".stab.unstable code") else {
+        return Vec::new();
+    };
+    let mut features: Vec<String> = Vec::new();
+    for code in fragment.select(&selector) {
+        let feature = code.text().collect::<String>().trim().to_string();
+        let is_gate = !feature.is_empty() && feature.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_gate && !features.contains(&feature) {
+            features.push(feature);
+        }
+    }
+    features
+}
+
+/// An item or member of a documentation page with its canonical URL and, when
+/// rustdoc links it, its source
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageLink {
+    /// Name as written in the docs, e.g. `Builder` or `worker_threads`
+    pub name: String,
+    pub url: String,
+    pub source: Option<String>,
+}
+
+/// The item a page documents, its members and the items it links to, with absolute URLs
+pub fn page_links(html: &str, page_url: &str) -> Vec<PageLink> {
+    let Ok(mut base) = Url::parse(page_url) else {
+        return Vec::new();
+    };
+    base.set_fragment(None);
+    let absolute = |href: &str| base.join(href).ok().map(|url| url.to_string());
+    let document = Html::parse_document(html);
+    let (Ok(heading), Ok(source), Ok(link)) = (Selector::parse("h1"), Selector::parse("a.src"), Selector::parse("a[href]")) else {
+        return Vec::new();
+    };
+    let in_member = |element: &ElementRef| {
+        element.ancestors().filter_map(ElementRef::wrap).any(|ancestor| {
+            let id = ancestor.value().id().unwrap_or_default();
+            MEMBER_ANCHOR_KINDS.iter().any(|kind| id.strip_prefix(kind).is_some_and(|rest| rest.starts_with('.')))
+        })
+    };
+
+    let mut links: Vec<PageLink> = Vec::new();
+    // "Struct tokio::runtime::Builder" names the page's item last
+    let title = document.select(&heading).next().map(heading_text).unwrap_or_default();
+    if let Some(name) = title.replace('§', "").split_whitespace().last().and_then(|path| path.rsplit("::").next()) {
+        links.push(PageLink {
+            name: name.to_string(),
+            url: base.to_string(),
+            source: document
+                .select(&source)
+                .find(|element| !in_member(element))
+                .and_then(|element| absolute(element.value().attr("href")?)),
+        });
+    }
+
+    for kind in MEMBER_ANCHOR_KINDS {
+        let Ok(selector) = Selector::parse(&format!("[id^=\"{}.\"]", kind)) else {
+            continue;
+        };
+        for member in document.select(&selector) {
+            let id = member.value().id().unwrap_or_default();
+            let Some(name) = id.strip_prefix(kind).and_then(|rest| rest.strip_prefix('.')).filter(|name| !name.contains('.')) else {
+                continue;
+            };
+            links.push(PageLink {
+                name: name.to_string(),
+                url: format!("{}#{}", base, id),
+                source: member.select(&source).next().and_then(|element| absolute(element.value().attr("href")?)),
+            });
+        }
+    }
+
+    for element in document.select(&link) {
+        let is_item = element.value().classes().any(|class| ITEM_LINK_CLASSES.contains(&class));
+        let href = element.value().attr("href").unwrap_or_default();
+        let name = element.text().collect::<String>().trim().to_string();
+        if !is_item || href.starts_with('#') || name.is_empty() {
+            continue;
+        }
+        if let Some(url) = absolute(href).filter(|url| !links.iter().any(|link| link.url == *url)) {
+            links.push(PageLink { name, url, source: None });
+        }
+    }
+    links
+}
+
+// Text of a heading without the source link and `§` anchor older rustdoc puts inside it
+fn heading_text(heading: ElementRef) -> String {
+    heading
+        .descendants()
+        .filter(|node| {
+            !node.ancestors().filter_map(ElementRef::wrap).any(|element| {
+                element.value().classes().any(|class| matches!(class, "src" | "out-of-band" | "anchor"))
+            })
+        })
+        .filter_map(|node| node.value().as_text().map(|text| text.to_string()))
+        .collect()
+}
@@ -0,0 +1,222 @@
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+
+use super::docs::html_to_markdown;
+use super::errors::{error_message, DocsError};
+use super::fetcher::{DocFetcher, DocPage, FallbackDoc};
+use super::prefetch::parse_max_version;
+use super::repository::{parse_markdown_listing, parse_repository_url, RepoRef, MAX_REPOSITORY_DOCS};
+use super::sources::{Capabilities, DocSource};
+
+/// Name of the source falling back to the README crates.io shows for a crate
+pub const CRATES_IO_README_SOURCE: &str = "crates.io-readme";
+
+/// Name of the source falling back to the README and `docs/` folder of a crate's repository
+pub const REPOSITORY_SOURCE: &str = "repository";
+
+/// Files tried, in order, as a repository's README
+const README_FILES: [&str; 3] = ["README.md", "readme.md", "README"];
+
+// Fallback sources serve no docs pages, and the registry asks them for none
+fn no_pages<T>(source: &str) -> BoxFuture<'static, Result<T, ToolError>>
+where
+    T: Send + 'static,
+{
+    let error = ToolError::ExecutionError(format!("The {} source serves no docs pages", source));
+    Box::pin(async move { Err(error) })
+}
+
+/// The README crates.io shows for a crate version, rendered from its HTML
+pub struct CratesIoReadme;
+
+impl DocSource for CratesIoReadme {
+    fn name(&self) -> &str {
+        CRATES_IO_README_SOURCE
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::FALLBACK_DOCS
+    }
+
+    fn fallback_doc<'a>(
+        &'a self,
+        sources: &'a dyn DocFetcher,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        Box::pin(async move {
+            let version = match version {
+                Some(version) => version.to_string(),
+                None => {
+                    let crate_info = sources.get_crates_io_api(&format!("crates/{}", crate_name)).await?;
+                    parse_max_version(&crate_info)
+                        .ok_or_else(|| DocsError::NotFound(format!("{} has no published version", crate_name)))?
+                }
+            };
+            let readme = sources
+                .get_crates_io_api(&format!("crates/{}/{}/readme", crate_name, version))
+                .await?;
+            let markdown = html_to_markdown(&readme);
+            if markdown.trim().is_empty() {
+                return Err(DocsError::NotFound(format!("{} {} has no README", crate_name, version)).into());
+            }
+
+            Ok(FallbackDoc {
+                markdown: format!(
+                    "Documentation for {} {}\nSource: https://crates.io/crates/{}/{} (crates.io README fallback: {})\n\n{}",
+                    crate_name, version, crate_name, version, reason, markdown
+                ),
+                pinned: true,
+            })
+        })
+    }
+}
+
+impl DocFetcher for CratesIoReadme {
+    fn get_crate_page<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        no_pages(CRATES_IO_README_SOURCE)
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+        _relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        no_pages(CRATES_IO_README_SOURCE)
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+        _file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        no_pages(CRATES_IO_README_SOURCE)
+    }
+}
+
+/// The README and `docs/` folder of the GitHub or GitLab repository a crate lists,
+/// read at the version's tag (`v1.2.3` or `1.2.3`) and else at the default branch
+///
+/// Only docs read at a tag are pinned; the default branch may have moved on from the
+/// version, and the result's title says it's the default branch.
+pub struct RepositoryDocs;
+
+impl RepositoryDocs {
+    // The first README found at the repository's revision, with its path
+    async fn readme(sources: &dyn DocFetcher, repo: &RepoRef) -> Option<(&'static str, String)> {
+        for path in README_FILES {
+            if let Ok(body) = sources.get_repository_file(repo, path).await {
+                return Some((path, body));
+            }
+        }
+        None
+    }
+}
+
+impl DocSource for RepositoryDocs {
+    fn name(&self) -> &str {
+        REPOSITORY_SOURCE
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::FALLBACK_DOCS
+    }
+
+    fn fallback_doc<'a>(
+        &'a self,
+        sources: &'a dyn DocFetcher,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        Box::pin(async move {
+            let crate_info = sources.get_crates_io_api(&format!("crates/{}", crate_name)).await?;
+            let default_branch = parse_repository_url(&crate_info)
+                .and_then(|url| RepoRef::parse(&url))
+                .ok_or_else(|| DocsError::NotFound("no GitHub or GitLab repository is listed to fall back to".to_string()))?;
+
+            // The version's tag, under either naming convention, and the default branch as a last resort
+            let tags: Vec<RepoRef> = version
+                .into_iter()
+                .flat_map(|version| [format!("v{}", version), version.to_string()])
+                .map(|tag| default_branch.clone().at(&tag))
+                .collect();
+            let mut readme = None;
+            for revision in tags.iter().chain([&default_branch]) {
+                readme = Self::readme(sources, revision).await.map(|readme| (revision, readme));
+                if readme.is_some() {
+                    break;
+                }
+            }
+            let repo = readme.as_ref().map_or(&default_branch, |(revision, _)| *revision);
+            let mut sections: Vec<(String, String)> = readme
+                .into_iter()
+                .map(|(_, (path, body))| (path.to_string(), body))
+                .collect();
+
+            // The docs/ folder is optional; a missing folder is not an error
+            let listing = sources.list_repository_dir(repo, "docs").await.unwrap_or_default();
+            for path in parse_markdown_listing(&listing).into_iter().take(MAX_REPOSITORY_DOCS) {
+                match sources.get_repository_file(repo, &path).await {
+                    Ok(body) => sections.push((path, body)),
+                    Err(e) => tracing::debug!("Failed to fetch {} from {}: {}", path, repo.tree_url(), error_message(e)),
+                }
+            }
+
+            if sections.is_empty() {
+                return Err(DocsError::NotFound(format!("{} has no README or docs/ folder", repo.url())).into());
+            }
+
+            let pinned = repo.git_ref.is_some();
+            let title = match version.filter(|_| pinned) {
+                Some(version) => format!("{} {}", crate_name, version),
+                None => format!("{} (default branch)", crate_name),
+            };
+            let mut markdown = format!(
+                "Documentation for {}\nSource: {} (repository fallback: {})\n",
+                title,
+                repo.tree_url(),
+                reason
+            );
+            for (path, body) in sections {
+                markdown.push_str(&format!("\n---\n\n<!-- {} -->\n\n{}\n", path, body.trim_end()));
+            }
+            Ok(FallbackDoc { markdown, pinned })
+        })
+    }
+}
+
+impl DocFetcher for RepositoryDocs {
+    fn get_crate_page<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        no_pages(REPOSITORY_SOURCE)
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+        _relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        no_pages(REPOSITORY_SOURCE)
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+        _file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        no_pages(REPOSITORY_SOURCE)
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::sparse_index::IndexVersion;
+
+// Features a crate version ends up with, and what they switch on in its dependencies
+#[derive(Clone, Default)]
+pub(crate) struct Activation {
+    pub features: BTreeSet<String>,
+    /// Optional dependencies enabled, by manifest name
+    pub dependencies: BTreeSet<String>,
+    /// Features to enable on dependencies, by manifest name
+    pub dependency_features: BTreeMap<String, BTreeSet<String>>,
+}
+
+// Expand the requested features of a crate version through its feature table
+pub(crate) fn activate(entry: &IndexVersion, requested: &BTreeSet<String>) -> Activation {
+    let optional: BTreeSet<&str> = entry
+        .dependencies
+        .iter()
+        .filter(|dependency| dependency.optional)
+        .map(|dependency| dependency.manifest_name())
+        .collect();
+    // Mentioning `dep:name` anywhere removes the implicit feature of that dependency
+    let explicit: BTreeSet<&str> = entry
+        .features
+        .values()
+        .flatten()
+        .filter_map(|item| item.strip_prefix("dep:"))
+        .collect();
+
+    let mut activation = Activation::default();
+    let mut pending: Vec<String> = requested.iter().cloned().collect();
+    while let Some(feature) = pending.pop() {
+        if let Some(enables) = entry.features.get(&feature) {
+            if !activation.features.insert(feature) {
+                continue;
+            }
+            for item in enables {
+                if let Some(dependency) = item.strip_prefix("dep:") {
+                    activation.dependencies.insert(dependency.to_string());
+                } else if let Some((dependency, dependency_feature)) = item.split_once('/') {
+                    // `name?/feature` only applies if something else enables the dependency
+                    let weak = dependency.ends_with('?');
+                    let dependency = dependency.trim_end_matches('?');
+                    activation
+                        .dependency_features
+                        .entry(dependency.to_string())
+                        .or_default()
+                        .insert(dependency_feature.to_string());
+                    if !weak && optional.contains(dependency) {
+                        activation.dependencies.insert(dependency.to_string());
+                        if !explicit.contains(dependency) {
+                            activation.features.insert(dependency.to_string());
+                        }
+                    }
+                } else {
+                    pending.push(item.clone());
+                }
+            }
+        } else if optional.contains(feature.as_str()) && !explicit.contains(feature.as_str()) {
+            activation.dependencies.insert(feature.clone());
+            activation.features.insert(feature);
+        }
+    }
+    activation
+}
+
+
+// Optional dependencies, by manifest name
+fn optional_dependencies(entry: &IndexVersion) -> BTreeSet<&str> {
+    entry
+        .dependencies
+        .iter()
+        .filter(|dependency| dependency.optional)
+        .map(|dependency| dependency.manifest_name())
+        .collect()
+}
+
+// Optional dependencies that are features of their own, since no feature names them with `dep:`
+fn implicit_features(entry: &IndexVersion) -> BTreeSet<&str> {
+    let explicit: BTreeSet<&str> = entry
+        .features
+        .values()
+        .flatten()
+        .filter_map(|item| item.strip_prefix("dep:"))
+        .collect();
+    optional_dependencies(entry).into_iter().filter(|name| !explicit.contains(name)).collect()
+}
+
+/// Whether a crate version has the feature, declared or implied by an optional dependency
+pub fn has_feature(entry: &IndexVersion, feature: &str) -> bool {
+    entry.features.contains_key(feature) || implicit_features(entry).contains(feature)
+}
+
+/// The declared features that turn on an optional dependency, directly or through other features
+pub fn enabling_features(entry: &IndexVersion, dependency: &str) -> Vec<String> {
+    entry
+        .features
+        .keys()
+        .filter(|feature| activate(entry, &BTreeSet::from([feature.to_string()])).dependencies.contains(dependency))
+        .cloned()
+        .collect()
+}
+
+/// Render what each feature of a crate version turns on, as nested lists
+///
+/// With `feature`, only that feature is shown, followed by everything it turns on in total.
+pub fn format_feature_graph(crate_name: &str, entry: &IndexVersion, feature: Option<&str>) -> String {
+    let optional = optional_dependencies(entry);
+    let implicit = implicit_features(entry);
+
+    if let Some(feature) = feature {
+        let mut markdown = format!("# Feature `{}` of {} {}\n\n", feature, crate_name, entry.version);
+        push_feature(entry, feature, 0, &mut Vec::new(), &optional, &implicit, &mut markdown);
+
+        let activation = activate(entry, &BTreeSet::from([feature.to_string()]));
+        let dependency_features: Vec<String> = activation
+            .dependency_features
+            .iter()
+            .flat_map(|(dependency, features)| features.iter().map(move |feature| format!("`{}/{}`", dependency, feature)))
+            .collect();
+        markdown.push_str("\nTurns on in total:\n");
+        markdown.push_str(&format!("- Features: {}\n", code_list(&activation.features)));
+        markdown.push_str(&format!("- Optional dependencies: {}\n", code_list(&activation.dependencies)));
+        if !dependency_features.is_empty() {
+            markdown.push_str(&format!("- Features of dependencies: {}\n", dependency_features.join(", ")));
+        }
+        return markdown;
+    }
+
+    let mut markdown = format!("# Feature graph of {} {}\n\n", crate_name, entry.version);
+    let defaults = activate(entry, &BTreeSet::from(["default".to_string()])).features;
+    let defaults: BTreeSet<String> = defaults.into_iter().filter(|feature| feature != "default").collect();
+    markdown.push_str(&format!("Default features: {}\n", code_list(&defaults)));
+
+    markdown.push_str("\n## Features\n\n");
+    if entry.features.is_empty() {
+        markdown.push_str("No features declared.\n");
+    }
+    for feature in entry.features.keys() {
+        push_feature(entry, feature, 0, &mut Vec::new(), &optional, &implicit, &mut markdown);
+    }
+
+    if !optional.is_empty() {
+        markdown.push_str("\n## Optional dependencies\n\n");
+        for dependency in &optional {
+            let enabled_by = enabling_features(entry, dependency);
+            let mut notes = Vec::new();
+            if !enabled_by.is_empty() {
+                notes.push(format!("enabled by {}", code_list(&enabled_by.into_iter().collect())));
+            }
+            if implicit.contains(dependency) {
+                notes.push(format!("also the feature `{}`", dependency));
+            }
+            // Listed once per platform when it is only needed on some
+            let targets: Option<BTreeSet<&str>> = entry
+                .dependencies
+                .iter()
+                .filter(|candidate| candidate.optional && candidate.manifest_name() == *dependency)
+                .map(|candidate| candidate.target.as_deref())
+                .collect();
+            if let Some(targets) = targets {
+                notes.push(format!("only for {}", targets.into_iter().collect::<Vec<_>>().join(", ")));
+            }
+            markdown.push_str(&format!("- `{}`: {}\n", dependency, notes.join("; ")));
+        }
+    }
+    markdown
+}
+
+// Append a feature and, indented below it, what it turns on
+fn push_feature(
+    entry: &IndexVersion,
+    feature: &str,
+    depth: usize,
+    path: &mut Vec<String>,
+    optional: &BTreeSet<&str>,
+    implicit: &BTreeSet<&str>,
+    markdown: &mut String,
+) {
+    let indent = "  ".repeat(depth);
+    let Some(enables) = entry.features.get(feature) else {
+        if implicit.contains(feature) {
+            markdown.push_str(&format!("{}- `{}`\n{}  - optional dependency `{}`\n", indent, feature, indent, feature));
+        } else {
+            markdown.push_str(&format!("{}- `{}` (not declared)\n", indent, feature));
+        }
+        return;
+    };
+    // Cargo rejects cyclic features, but the index isn't trusted to
+    if path.iter().any(|seen| seen == feature) {
+        markdown.push_str(&format!("{}- `{}` (cycle)\n", indent, feature));
+        return;
+    }
+    markdown.push_str(&format!("{}- `{}`\n", indent, feature));
+
+    path.push(feature.to_string());
+    let indent = "  ".repeat(depth + 1);
+    for item in enables {
+        if let Some(dependency) = item.strip_prefix("dep:") {
+            markdown.push_str(&format!("{}- optional dependency `{}`\n", indent, dependency));
+        } else if let Some((dependency, dependency_feature)) = item.split_once('/') {
+            let line = match dependency.strip_suffix('?') {
+                Some(dependency) => format!("feature `{}` of `{}`, if `{}` is enabled", dependency_feature, dependency, dependency),
+                None if optional.contains(dependency) => {
+                    format!("optional dependency `{}`, with its feature `{}`", dependency, dependency_feature)
+                }
+                None => format!("feature `{}` of `{}`", dependency_feature, dependency),
+            };
+            markdown.push_str(&format!("{}- {}\n", indent, line));
+        } else {
+            push_feature(entry, item, depth + 1, path, optional, implicit, markdown);
+        }
+    }
+    path.pop();
+}
+
+fn code_list(items: &BTreeSet<String>) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.iter().map(|item| format!("`{}`", item)).collect::<Vec<_>>().join(", ")
+    }
+}
@@ -0,0 +1,715 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+use reqwest::Client;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::books::Book;
+use super::circuit::CircuitBreaker;
+use super::errors::DocsError;
+use super::timing::{record_upstream_url, timed, Phase};
+use super::repository::{RepoHost, RepoRef};
+use super::sparse_index::index_path;
+
+/// User-Agent sent with upstream requests unless the operator configures one
+pub const USER_AGENT: &str = "CrateDocs/0.1.0 (https://github.com/d6e/cratedocs-mcp)";
+
+/// Build a User-Agent identifying a deployment, as the crates.io crawling
+/// policy asks: `user_agent` (or the default) followed by how to reach the operator
+pub fn user_agent(user_agent: Option<&str>, contact: Option<&str>) -> String {
+    let user_agent = user_agent.map(str::trim).filter(|agent| !agent.is_empty()).unwrap_or(USER_AGENT);
+    match contact.map(str::trim).filter(|contact| !contact.is_empty()) {
+        Some(contact) => format!("{} contact: {}", user_agent, contact),
+        None => user_agent.to_string(),
+    }
+}
+
+/// Default cap on the size of a single upstream response body (10 MiB)
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Default cap on upstream requests in flight at once, across all sessions and tools
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 32;
+
+/// Crates documented on doc.rust-lang.org rather than docs.rs
+pub const STD_CRATES: [&str; 3] = ["std", "core", "alloc"];
+
+/// Whether `crate_name` is part of the standard library
+pub fn is_std_crate(crate_name: &str) -> bool {
+    STD_CRATES.contains(&crate_name)
+}
+
+/// Whether `version` names a standard library docs channel (`stable`, `beta`,
+/// `nightly`) or a specific release such as `1.75.0`
+pub fn is_rust_channel(version: &str) -> bool {
+    let is_number = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    matches!(version, "stable" | "beta" | "nightly")
+        || (version.split('.').count() == 3 && version.split('.').all(is_number))
+}
+
+/// Base URLs of the upstream services, configurable so tests and mirrors can
+/// point the fetcher somewhere other than the public hosts
+#[derive(Clone, Debug)]
+pub struct BaseUrls {
+    pub docs_rs: String,
+    pub crates_io: String,
+    /// Standard library documentation and books
+    pub rust_docs: String,
+    /// Books published with GitHub Pages, such as the Async Book
+    pub rust_lang_pages: String,
+    /// Raw file host for GitHub repositories
+    pub github_raw: String,
+    pub github_api: String,
+    pub gitlab: String,
+    pub lib_rs: String,
+    /// crates.io sparse registry index
+    pub sparse_index: String,
+    /// Host of the published `.crate` archives
+    pub static_crates: String,
+}
+
+impl Default for BaseUrls {
+    fn default() -> Self {
+        Self {
+            docs_rs: "https://docs.rs".to_string(),
+            crates_io: "https://crates.io".to_string(),
+            rust_docs: "https://doc.rust-lang.org".to_string(),
+            rust_lang_pages: "https://rust-lang.github.io".to_string(),
+            github_raw: "https://raw.githubusercontent.com".to_string(),
+            github_api: "https://api.github.com".to_string(),
+            gitlab: "https://gitlab.com".to_string(),
+            lib_rs: "https://lib.rs".to_string(),
+            sparse_index: "https://index.crates.io".to_string(),
+            static_crates: "https://static.crates.io".to_string(),
+        }
+    }
+}
+
+/// A documentation page together with the URL it was served from
+#[derive(Clone, Debug)]
+pub struct DocPage {
+    /// Final URL after following redirects
+    pub url: String,
+    pub html: String,
+}
+
+impl DocPage {
+    /// Concrete version of `crate_name` named in the page URL, if any
+    ///
+    /// docs.rs URLs carry the version right after the crate name
+    /// (`/crate/tokio/1.43.0/` or `/tokio/1.43.0/tokio/...`); `latest` and
+    /// other non-numeric segments don't count as a resolved version.
+    pub fn resolved_version(&self, crate_name: &str) -> Option<String> {
+        let url = reqwest::Url::parse(&self.url).ok()?;
+        let mut segments = url.path_segments()?;
+        segments.find(|segment| *segment == crate_name)?;
+        segments
+            .next()
+            .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+            .map(|version| version.to_string())
+    }
+}
+
+/// Docs standing in for a crate version docs.rs has no build of, such as its README
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FallbackDoc {
+    pub markdown: String,
+    /// Whether the docs are of the version itself rather than of something that moves
+    /// on, like a default branch, so they can be kept as the version's docs
+    pub pinned: bool,
+}
+
+/// Source of raw documentation pages and search results
+///
+/// `DocRouter` depends on this trait rather than on a concrete HTTP client so
+/// that upstream interactions can be mocked in tests or replaced entirely.
+/// Fetchers that only serve some of it, like a `DocSource`, keep the defaults
+/// of the rest.
+pub trait DocFetcher: Send + Sync {
+    /// Fetch the docs.rs crate page (HTML) for a crate
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>>;
+
+    /// Fetch a rustdoc item page (HTML), where `relative_path` is the page path
+    /// below the crate root, e.g. `sync/mpsc/struct.Sender.html`
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>>;
+
+    /// Fetch a file from the root of a crate version's docs, where `file` is
+    /// e.g. `search-index.js`
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>>;
+
+    /// Search crates.io, returning the raw response body
+    fn search<'a>(&'a self, _query: &'a str, _limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "crates.io search is not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Search lib.rs, returning the results page (HTML)
+    fn search_lib_rs<'a>(&'a self, _query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "lib.rs search is not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// GET a crates.io API endpoint (JSON), where `path` is relative to
+    /// `/api/v1/`, e.g. `crates/serde` or `crates/serde/1.0.0/dependencies`
+    fn get_crates_io_api<'a>(&'a self, _path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "The crates.io API is not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Fetch a crate's entry in the crates.io sparse index, which lists every
+    /// published version with its dependencies and features, one JSON object per line
+    ///
+    /// The index is served from a CDN without the API's rate limits; callers
+    /// fall back to the crates.io API when it's unavailable, as from fetchers
+    /// that keep this default.
+    fn get_index_entry<'a>(&'a self, _crate_name: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "The sparse index is not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Fetch the rustdoc JSON docs.rs built for a crate version
+    ///
+    /// docs.rs only has JSON for recent builds, so callers must be ready to
+    /// fall back to the HTML pages. Fetchers that can't provide it
+    /// keep this default.
+    fn get_rustdoc_json<'a>(&'a self, _crate_name: &'a str, _version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "rustdoc JSON is not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Fetch the `.crate` archive published for a crate version, unpacked from
+    /// gzip into a tar archive of its source files
+    fn get_crate_archive<'a>(&'a self, _crate_name: &'a str, _version: &'a str) -> BoxFuture<'a, Result<Vec<u8>, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "Crate archives are not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Fetch a file from a repository at its revision (the default branch unless
+    /// `RepoRef::at` picked a tag), where `path` is relative to the repository root,
+    /// e.g. `README.md`
+    fn get_repository_file<'a>(&'a self, _repo: &'a RepoRef, _path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "Repository files are not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// List a directory of a repository at its revision, returning the host API's
+    /// raw JSON listing
+    fn list_repository_dir<'a>(&'a self, _repo: &'a RepoRef, _path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "Repository listings are not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// List the releases published on a repository's code host, returning the
+    /// host API's raw JSON listing, newest first
+    fn list_repository_releases<'a>(&'a self, _repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "Repository releases are not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Fetch a page of an official Rust book, where `path` is relative to the
+    /// book root, e.g. `rust-2021/index.html`
+    fn get_book_page<'a>(&'a self, _book: Book, _path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async {
+            Err(ToolError::ExecutionError(
+                "Books are not available from this fetcher".to_string(),
+            ))
+        })
+    }
+
+    /// Render docs standing in for a crate version docs.rs can't serve, where `reason`
+    /// says why and is repeated in the result
+    ///
+    /// `SourceRegistry` answers this from its fallback sources in order; fetchers
+    /// without any keep this default.
+    fn get_fallback_doc<'a>(
+        &'a self,
+        _crate_name: &'a str,
+        _version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        Box::pin(async move {
+            Err(ToolError::ExecutionError(format!(
+                "{}; fallback docs are not available from this fetcher",
+                reason
+            )))
+        })
+    }
+}
+
+/// `DocFetcher` backed by a reqwest client talking to docs.rs and crates.io
+#[derive(Clone)]
+pub struct ReqwestFetcher {
+    client: Client,
+    base_urls: BaseUrls,
+    max_response_bytes: usize,
+    user_agent: String,
+    /// API token sent to crates.io, and nowhere else
+    crates_io_token: Option<String>,
+    /// Shared by every clone, so the limit holds for the whole server
+    request_permits: Arc<Semaphore>,
+    /// Shared by every clone, so an outage is noticed across all sessions
+    circuit_breaker: CircuitBreaker,
+    /// docs.rs build target read per crate instead of the crate's default one
+    doc_targets: HashMap<String, String>,
+}
+
+impl Default for ReqwestFetcher {
+    fn default() -> Self {
+        Self::new(Client::new())
+    }
+}
+
+impl ReqwestFetcher {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            base_urls: BaseUrls::default(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            user_agent: USER_AGENT.to_string(),
+            crates_io_token: None,
+            request_permits: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_REQUESTS)),
+            circuit_breaker: CircuitBreaker::default(),
+            doc_targets: HashMap::new(),
+        }
+    }
+
+    pub fn with_base_urls(mut self, base_urls: BaseUrls) -> Self {
+        self.base_urls = base_urls;
+        self
+    }
+
+    /// Abort downloads whose body exceeds `max_response_bytes`
+    pub fn with_max_response_bytes(mut self, max_response_bytes: usize) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Send `user_agent` instead of the default User-Agent (see `user_agent()`)
+    pub fn with_user_agent(mut self, user_agent: String) -> Self {
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Authenticate crates.io API requests with `token`, for its higher rate limits
+    pub fn with_crates_io_token(mut self, token: String) -> Self {
+        self.crates_io_token = Some(token);
+        self
+    }
+
+    /// Keep at most `limit` upstream requests in flight at once (0 for no limit);
+    /// further requests wait for one to finish
+    pub fn with_max_concurrent_requests(mut self, limit: usize) -> Self {
+        let limit = if limit == 0 { Semaphore::MAX_PERMITS } else { limit.min(Semaphore::MAX_PERMITS) };
+        self.request_permits = Arc::new(Semaphore::new(limit));
+        self
+    }
+
+    /// Stop sending requests to an upstream host for `cooldown` once `threshold`
+    /// requests to it failed in a row (a `threshold` of 0 never stops them)
+    pub fn with_circuit_breaker(mut self, threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breaker = CircuitBreaker::new(threshold, cooldown);
+        self
+    }
+
+    /// Read the item pages of these crates from the docs.rs build for the given target,
+    /// e.g. `x86_64-pc-windows-msvc`, rather than the crate's default target
+    pub fn with_doc_targets(mut self, doc_targets: HashMap<String, String>) -> Self {
+        self.doc_targets = doc_targets;
+        self
+    }
+
+    pub fn base_urls(&self) -> &BaseUrls {
+        &self.base_urls
+    }
+
+    // Root of a crate version's documentation: a doc.rust-lang.org channel for the
+    // standard library, the crate version on docs.rs otherwise
+    fn docs_root(&self, crate_name: &str, version: Option<&str>) -> String {
+        if is_std_crate(crate_name) {
+            format!("{}/{}", self.base_urls.rust_docs, version.unwrap_or("stable"))
+        } else {
+            format!("{}/{}/{}", self.base_urls.docs_rs, crate_name, version.unwrap_or("latest"))
+        }
+    }
+
+    // GET a crates.io API endpoint, with the API token if one is configured
+    fn crates_io_get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.get(url);
+        match &self.crates_io_token {
+            Some(token) => request.header(reqwest::header::AUTHORIZATION, token),
+            None => request,
+        }
+    }
+
+    // GET a URL and return the body, describing failures with `action`
+    async fn fetch_text(&self, request: reqwest::RequestBuilder, action: &str) -> Result<String, ToolError> {
+        let (response, _permit) = self.send(request, action).await?;
+        self.read_body(response).await
+    }
+
+    // GET a documentation page, keeping the final URL after redirects
+    async fn fetch_page(&self, request: reqwest::RequestBuilder, action: &str) -> Result<DocPage, ToolError> {
+        let (response, _permit) = self.send(request, action).await?;
+        let url = response.url().to_string();
+        let html = self.read_body(response).await?;
+        Ok(DocPage { url, html })
+    }
+
+    // Send a request once a request slot is free, turning transport failures and
+    // error statuses into `ToolError`s; the slot is held until the permit is dropped,
+    // so callers keep it while reading the body. Requests to an upstream whose circuit
+    // is open fail right away.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        action: &str,
+    ) -> Result<(reqwest::Response, OwnedSemaphorePermit), ToolError> {
+        timed(Phase::UpstreamFetch, async {
+            let (client, request) = request.header("User-Agent", &self.user_agent).build_split();
+            let request = request.map_err(|e| ToolError::ExecutionError(format!("Failed to {}: {}", action, e)))?;
+            record_upstream_url(request.url().as_str());
+            let upstream = request.url().host_str().unwrap_or_default().to_string();
+            if let Err(retry_in) = self.circuit_breaker.check(&upstream).await {
+                let retry_in = retry_in.as_secs().max(1);
+                return Err(DocsError::Unavailable {
+                    message: format!(
+                        "Failed to {}: {} is unavailable after repeated failures; retrying it in {}s",
+                        action, upstream, retry_in
+                    ),
+                    retry_in: Some(Duration::from_secs(retry_in)),
+                }
+                .into());
+            }
+
+            if self.request_permits.available_permits() == 0 {
+                tracing::debug!("Upstream request limit reached; waiting to {}", action);
+            }
+            let permit = self
+                .request_permits
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("the request semaphore is never closed");
+            let response = match client.execute(request).await {
+                Ok(response) => response,
+                Err(e) => {
+                    self.circuit_breaker.record_failure(&upstream).await;
+                    return Err(DocsError::Unavailable {
+                        message: format!("Failed to {}: {}", action, e),
+                        retry_in: None,
+                    }
+                    .into());
+                }
+            };
+
+            // Server errors and rate limiting mean the upstream is struggling; anything
+            // else, a 404 included, shows it is up
+            let status = response.status();
+            if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                self.circuit_breaker.record_failure(&upstream).await;
+            } else {
+                self.circuit_breaker.record_success(&upstream).await;
+            }
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok()?.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                return Err(DocsError::RateLimited {
+                    action: action.to_string(),
+                    retry_after,
+                }
+                .into());
+            }
+            if !status.is_success() {
+                return Err(DocsError::UpstreamStatus {
+                    action: action.to_string(),
+                    status,
+                }
+                .into());
+            }
+
+            Ok((response, permit))
+        })
+        .await
+    }
+
+    // Read the response body as text, refusing to buffer more than the configured limit
+    async fn read_body(&self, response: reqwest::Response) -> Result<String, ToolError> {
+        let body = self.read_bytes(response).await?;
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    // Stream the response body, refusing to buffer more than the configured limit
+    async fn read_bytes(&self, mut response: reqwest::Response) -> Result<Vec<u8>, ToolError> {
+        timed(Phase::UpstreamFetch, async {
+            let too_large = |url: &reqwest::Url| -> ToolError {
+                DocsError::TooLarge {
+                    what: format!("Response from {}", url),
+                    max_bytes: self.max_response_bytes,
+                }
+                .into()
+            };
+
+            // Reject early when the server announces an oversized body
+            if let Some(length) = response.content_length() {
+                if length > self.max_response_bytes as u64 {
+                    return Err(too_large(response.url()));
+                }
+            }
+
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(|e| DocsError::Unavailable {
+                message: format!("Failed to read response body: {}", e),
+                retry_in: None,
+            })? {
+                if body.len() + chunk.len() > self.max_response_bytes {
+                    return Err(too_large(response.url()));
+                }
+                body.extend_from_slice(&chunk);
+            }
+
+            Ok(body)
+        })
+        .await
+    }
+}
+
+impl DocFetcher for ReqwestFetcher {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let url = match version {
+                _ if is_std_crate(crate_name) => format!("{}/{}/index.html", self.docs_root(crate_name, version), crate_name),
+                Some(ver) => format!("{}/crate/{}/{}/", self.base_urls.docs_rs, crate_name, ver),
+                None => format!("{}/crate/{}/", self.base_urls.docs_rs, crate_name),
+            };
+            self.fetch_page(self.client.get(&url), "fetch documentation").await
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let root = match self.doc_targets.get(crate_name).filter(|_| !is_std_crate(crate_name)) {
+                Some(target) => format!("{}/{}", self.docs_root(crate_name, version), target),
+                None => self.docs_root(crate_name, version),
+            };
+            let url = format!("{}/{}/{}", root, crate_name.replace('-', "_"), relative_path);
+            self.fetch_page(self.client.get(&url), "fetch item documentation").await
+        })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let url = format!("{}/{}", self.docs_root(crate_name, version), file);
+            self.fetch_text(self.client.get(&url), "fetch documentation file").await
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let url = format!("{}/api/v1/crates", self.base_urls.crates_io);
+            let request = self
+                .crates_io_get(&url)
+                .query(&[("q", query.to_string()), ("per_page", limit.to_string())]);
+            self.fetch_text(request, "search crates.io").await
+        })
+    }
+
+    fn search_lib_rs<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let url = format!("{}/search", self.base_urls.lib_rs);
+            let request = self.client.get(&url).query(&[("q", query)]);
+            self.fetch_text(request, "search lib.rs").await
+        })
+    }
+
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let url = format!("{}/api/v1/{}", self.base_urls.crates_io, path);
+            self.fetch_text(self.crates_io_get(&url), "query crates.io").await
+        })
+    }
+
+    fn get_index_entry<'a>(&'a self, crate_name: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let url = format!("{}/{}", self.base_urls.sparse_index, index_path(crate_name));
+            self.fetch_text(self.client.get(&url), "read the sparse index").await
+        })
+    }
+
+    fn get_rustdoc_json<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            use std::io::Read;
+
+            let url = format!("{}/crate/{}/{}/json.gz", self.base_urls.docs_rs, crate_name, version);
+            let action = "fetch rustdoc JSON";
+            let (response, _permit) = self.send(self.client.get(&url), action).await?;
+            let compressed = self.read_bytes(response).await?;
+
+            // The decompressed document is bounded by the same limit as any other body
+            let mut json = String::new();
+            flate2::read::GzDecoder::new(compressed.as_slice())
+                .take(self.max_response_bytes as u64 + 1)
+                .read_to_string(&mut json)
+                .map_err(|e| DocsError::Decode(format!("Failed to decompress rustdoc JSON from {}: {}", url, e)))?;
+            if json.len() > self.max_response_bytes {
+                return Err(DocsError::TooLarge {
+                    what: format!("Rustdoc JSON from {}", url),
+                    max_bytes: self.max_response_bytes,
+                }
+                .into());
+            }
+            Ok(json)
+        })
+    }
+
+    fn get_crate_archive<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<Vec<u8>, ToolError>> {
+        Box::pin(async move {
+            use std::io::Read;
+
+            let url = format!("{}/crates/{}/{}-{}.crate", self.base_urls.static_crates, crate_name, crate_name, version);
+            let (response, _permit) = self.send(self.client.get(&url), "download the crate archive").await?;
+            let compressed = self.read_bytes(response).await?;
+
+            // The unpacked archive is bounded by the same limit as any other body
+            let mut archive = Vec::new();
+            flate2::read::GzDecoder::new(compressed.as_slice())
+                .take(self.max_response_bytes as u64 + 1)
+                .read_to_end(&mut archive)
+                .map_err(|e| DocsError::Decode(format!("Failed to decompress the crate archive from {}: {}", url, e)))?;
+            if archive.len() > self.max_response_bytes {
+                return Err(DocsError::TooLarge {
+                    what: format!("The crate archive from {}", url),
+                    max_bytes: self.max_response_bytes,
+                }
+                .into());
+            }
+            Ok(archive)
+        })
+    }
+
+    fn get_repository_file<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let revision = repo.revision();
+            let url = match repo.host {
+                RepoHost::GitHub => format!("{}/{}/{}/{}/{}", self.base_urls.github_raw, repo.owner, repo.name, revision, path),
+                RepoHost::GitLab => {
+                    format!("{}/{}/{}/-/raw/{}/{}", self.base_urls.gitlab, repo.owner, repo.name, revision, path)
+                }
+            };
+            self.fetch_text(self.client.get(&url), "fetch repository file").await
+        })
+    }
+
+    fn list_repository_dir<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let request = match repo.host {
+                RepoHost::GitHub => self.client.get(format!(
+                    "{}/repos/{}/{}/contents/{}",
+                    self.base_urls.github_api, repo.owner, repo.name, path
+                )),
+                RepoHost::GitLab => self
+                    .client
+                    .get(format!(
+                        "{}/api/v4/projects/{}%2F{}/repository/tree",
+                        self.base_urls.gitlab, repo.owner, repo.name
+                    ))
+                    .query(&[("path", path)]),
+            };
+            // Both APIs list the default branch unless given a `ref`
+            let request = match &repo.git_ref {
+                Some(git_ref) => request.query(&[("ref", git_ref)]),
+                None => request,
+            };
+            self.fetch_text(request, "list repository directory").await
+        })
+    }
+
+    fn list_repository_releases<'a>(&'a self, repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let request = match repo.host {
+                RepoHost::GitHub => self
+                    .client
+                    .get(format!("{}/repos/{}/{}/releases", self.base_urls.github_api, repo.owner, repo.name))
+                    .query(&[("per_page", "100")]),
+                RepoHost::GitLab => self
+                    .client
+                    .get(format!(
+                        "{}/api/v4/projects/{}%2F{}/releases",
+                        self.base_urls.gitlab, repo.owner, repo.name
+                    ))
+                    .query(&[("per_page", "100")]),
+            };
+            self.fetch_text(request, "list repository releases").await
+        })
+    }
+
+    fn get_book_page<'a>(&'a self, book: Book, path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let host = match book {
+                Book::AsyncBook => &self.base_urls.rust_lang_pages,
+                _ => &self.base_urls.rust_docs,
+            };
+            let url = format!("{}/{}/{}", host, book.path(), path);
+            self.fetch_page(self.client.get(&url), "fetch book page").await
+        })
+    }
+}
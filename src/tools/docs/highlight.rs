@@ -0,0 +1,141 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Most terms a single call can highlight
+pub const MAX_HIGHLIGHT_TERMS: usize = 10;
+
+/// Section name of matches above the first heading
+const TOP_OF_PAGE: &str = "top of page";
+
+// An occurrence of a term in the markdown
+struct Match {
+    range: Range<usize>,
+    term: usize,
+    // Whether it's in text where `**` renders as emphasis, rather than in code or already bold text
+    wrap: bool,
+}
+
+/// Wrap the occurrences of `terms` (ignoring ASCII case) in `**`, starting the
+/// result with a line counting them per term and per section
+///
+/// Occurrences in code and in text that's already bold are counted but left as
+/// they are, since emphasis wouldn't render there. Where terms overlap the
+/// longest one wins.
+pub fn highlight(markdown: &str, terms: &[String]) -> String {
+    // Terms as given, and lowercased for matching
+    let mut given: Vec<&str> = Vec::new();
+    let mut lowered: Vec<String> = Vec::new();
+    for term in terms.iter().map(|term| term.trim()).filter(|term| !term.is_empty()) {
+        if !lowered.contains(&term.to_ascii_lowercase()) {
+            given.push(term);
+            lowered.push(term.to_ascii_lowercase());
+        }
+    }
+    if given.is_empty() {
+        return markdown.to_string();
+    }
+
+    let lower = markdown.to_ascii_lowercase();
+    let mut matches: Vec<Match> = Vec::new();
+    // Where each heading starts, with its title
+    let mut sections: Vec<(usize, String)> = Vec::new();
+    let mut heading: Option<(usize, String)> = None;
+    let (mut in_code_block, mut strong_depth) = (false, 0);
+    for (event, range) in Parser::new_ext(markdown, Options::ENABLE_TABLES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading = Some((range.start, String::new())),
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((start, title)) = heading.take() {
+                    sections.push((start, title.replace('§', "").trim().to_string()));
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Start(Tag::Strong) => strong_depth += 1,
+            Event::End(TagEnd::Strong) => strong_depth -= 1,
+            Event::Text(text) => {
+                if let Some((_, title)) = heading.as_mut() {
+                    title.push_str(&text);
+                }
+                find_terms(&lower, range, &lowered, !in_code_block && strong_depth == 0, &mut matches);
+            }
+            Event::Code(text) => {
+                if let Some((_, title)) = heading.as_mut() {
+                    title.push_str(&text);
+                }
+                find_terms(&lower, range, &lowered, false, &mut matches);
+            }
+            _ => {}
+        }
+    }
+    matches.sort_by_key(|found| found.range.start);
+
+    let mut output = String::with_capacity(markdown.len() + matches.len() * 4);
+    output.push_str(&match_counts(&given, &matches, &sections));
+    output.push_str("\n\n");
+    let mut copied = 0;
+    for found in matches.iter().filter(|found| found.wrap) {
+        output.push_str(&markdown[copied..found.range.start]);
+        output.push_str("**");
+        output.push_str(&markdown[found.range.clone()]);
+        output.push_str("**");
+        copied = found.range.end;
+    }
+    output.push_str(&markdown[copied..]);
+    output
+}
+
+// Record the occurrences of `terms` in `range` of the lowercased markdown, longest term first
+fn find_terms(lower: &str, range: Range<usize>, terms: &[String], wrap: bool, matches: &mut Vec<Match>) {
+    let mut by_length: Vec<usize> = (0..terms.len()).collect();
+    by_length.sort_by_key(|&term| std::cmp::Reverse(terms[term].len()));
+    let mut i = range.start;
+    while i < range.end {
+        let found = if lower.is_char_boundary(i) {
+            by_length.iter().copied().find(|&term| lower[i..range.end].starts_with(terms[term].as_str()))
+        } else {
+            None
+        };
+        match found {
+            Some(term) => {
+                let end = i + terms[term].len();
+                matches.push(Match { range: i..end, term, wrap });
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+// The line counting matches, e.g. `[highlight: 3 matches (`spawn` 2, `join` 1); by section: Examples 2, Panics 1]`
+fn match_counts(terms: &[&str], matches: &[Match], sections: &[(usize, String)]) -> String {
+    if matches.is_empty() {
+        let terms: Vec<String> = terms.iter().map(|term| format!("`{}`", term)).collect();
+        return format!("[highlight: no matches of {}]", terms.join(", "));
+    }
+
+    let per_term: Vec<String> = terms
+        .iter()
+        .enumerate()
+        .map(|(index, term)| format!("`{}` {}", term, matches.iter().filter(|found| found.term == index).count()))
+        .collect();
+    // Matches come in page order, so each section's are next to each other; sections are
+    // told apart by position rather than title, which rustdoc repeats (e.g. "Examples")
+    let mut per_section: Vec<(Option<usize>, &str, usize)> = Vec::new();
+    for found in matches {
+        let section = sections.iter().rposition(|(start, _)| *start <= found.range.start);
+        match per_section.last_mut().filter(|(last, ..)| *last == section) {
+            Some((.., count)) => *count += 1,
+            None => per_section.push((section, section.map_or(TOP_OF_PAGE, |s| sections[s].1.as_str()), 1)),
+        }
+    }
+    let per_section: Vec<String> = per_section.iter().map(|(_, title, count)| format!("{} {}", title, count)).collect();
+    format!(
+        "[highlight: {} {} ({}); by section: {}]",
+        matches.len(),
+        if matches.len() == 1 { "match" } else { "matches" },
+        per_term.join(", "),
+        per_section.join(", ")
+    )
+}
@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use super::releases::utc_timestamp;
+
+/// URI of the resource listing what the reading session looked up
+pub const HISTORY_RESOURCE_URI: &str = "docs://history";
+
+/// Number of calls a session's history keeps, dropping the oldest first
+const MAX_HISTORY_ENTRIES: usize = 200;
+
+/// Number of documents listed per call, for calls such as `warm_cache` that fetch many
+const MAX_ENTRY_DOCUMENTS: usize = 20;
+
+/// A tool call or resource read of a session
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// UTC time the call was made, as an RFC 3339 timestamp
+    pub time: String,
+    /// The tool's name, or `resources/read`
+    pub call: String,
+    /// The call's arguments as compact JSON, or the URI read
+    pub arguments: String,
+    /// `docs://` URIs of the documents the call returned, with concrete versions where known
+    pub documents: Vec<String>,
+    pub ok: bool,
+}
+
+impl HistoryEntry {
+    /// An entry for a call made at `started`
+    pub fn new(started: SystemTime, call: &str, arguments: String, documents: Vec<String>, ok: bool) -> Self {
+        Self {
+            time: utc_timestamp(started),
+            call: call.to_string(),
+            arguments,
+            documents,
+            ok,
+        }
+    }
+}
+
+/// What one client session called and fetched, oldest first
+///
+/// Clones share the history, so every clone of a session's router records into it.
+#[derive(Clone, Debug, Default)]
+pub struct SessionHistory(Arc<Mutex<VecDeque<HistoryEntry>>>);
+
+impl SessionHistory {
+    pub fn record(&self, entry: HistoryEntry) {
+        let mut entries = self.lock();
+        if entries.len() == MAX_HISTORY_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.lock().iter().cloned().collect()
+    }
+
+    /// Render the history resource as markdown
+    pub fn markdown(&self) -> String {
+        let entries = self.lock();
+        let mut markdown = String::from("# Session history\n\n");
+        if entries.is_empty() {
+            markdown.push_str("Nothing was looked up in this session yet.\n");
+            return markdown;
+        }
+        markdown.push_str("Documents listed below can be read again through resources/read, from the cache.\n\n");
+        for entry in entries.iter() {
+            let failed = if entry.ok { "" } else { " (failed)" };
+            markdown.push_str(&format!("- {} `{}` {}{}\n", entry.time, entry.call, entry.arguments, failed));
+            for uri in entry.documents.iter().take(MAX_ENTRY_DOCUMENTS) {
+                markdown.push_str(&format!("  - {}\n", uri));
+            }
+            if entry.documents.len() > MAX_ENTRY_DOCUMENTS {
+                markdown.push_str(&format!("  - and {} more\n", entry.documents.len() - MAX_ENTRY_DOCUMENTS));
+            }
+        }
+        markdown
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<HistoryEntry>> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+tokio::task_local! {
+    static DOCUMENTS: Arc<Mutex<Vec<String>>>;
+}
+
+/// Run `future`, returning its output with the documents it noted with `record_document`
+pub async fn collect_documents<F: Future>(future: F) -> (F::Output, Vec<String>) {
+    let documents = Arc::new(Mutex::new(Vec::new()));
+    let output = DOCUMENTS.scope(documents.clone(), future).await;
+    let documents = std::mem::take(&mut *documents.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+    (output, documents)
+}
+
+/// Note a document returned by the current call, once per URI
+///
+/// Documents fetched outside `collect_documents`, such as background prefetches, aren't noted.
+pub fn record_document(uri: String) {
+    let _ = DOCUMENTS.try_with(|documents| {
+        let mut documents = documents.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !documents.contains(&uri) {
+            documents.push(uri);
+        }
+    });
+}
@@ -0,0 +1,277 @@
+/// Maximum number of paths `explain_imports` looks up; the rest are only named
+pub const MAX_EXPLAINED_IMPORTS: usize = 20;
+
+/// Path roots that name the standard library's crates
+pub const STD_ROOTS: [&str; 3] = ["std", "core", "alloc"];
+
+/// Path roots that refer to the current crate rather than a dependency
+const LOCAL_ROOTS: [&str; 4] = ["crate", "self", "super", "Self"];
+
+/// Where the items under one path root are documented
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportTarget {
+    /// A crate on docs.rs, at the version given or the latest
+    Crate(String, Option<String>),
+    /// A root that is a crate unless its lookup fails, when no manifest says otherwise
+    MaybeCrate(String),
+    /// A path or git dependency, described by its name and source
+    NotOnDocsRs(String),
+    /// Neither a dependency nor the standard library, e.g. a module of the project
+    Unknown,
+}
+
+/// The item paths a Rust source snippet uses, in order of first use: those its `use`
+/// declarations import, then fully qualified paths in the code whose root is imported,
+/// a crate in `known_roots` or the standard library
+///
+/// Paths into the current crate (`crate::`, `self::`, `super::`) are skipped, and
+/// paths to associated items or variants (`HashMap::new`, `Ordering::Less`) are cut
+/// back to their type. Glob imports (`tokio::prelude::*`) give their module.
+pub fn imported_paths(source: &str, known_roots: &[String]) -> Vec<String> {
+    let code = strip_comments_and_strings(source);
+    let mut paths: Vec<String> = Vec::new();
+    // Names `use` declarations bring into scope, with the paths they stand for
+    let mut names: Vec<(String, String)> = Vec::new();
+
+    // `use` declarations, which may span lines and nest groups
+    let mut rest = code.as_str();
+    let mut outside_uses = String::new();
+    while let Some(start) = find_keyword(rest, "use") {
+        outside_uses.push_str(&rest[..start]);
+        let after = &rest[start + 3..];
+        let end = after.find(';').unwrap_or(after.len());
+        for (path, name) in expand_use_tree(&after[..end]) {
+            let path = path.trim_start_matches("::").to_string();
+            if let Some(item) = item_path(&path) {
+                push_unique(&mut paths, item);
+            }
+            names.push((name, path));
+        }
+        rest = after.get(end + 1..).unwrap_or_default();
+    }
+    outside_uses.push_str(rest);
+
+    // Fully qualified paths elsewhere, only where the root is surely a crate or an imported name
+    let mut roots: Vec<&str> = names.iter().filter_map(|(_, path)| path.split("::").next()).collect();
+    roots.extend(known_roots.iter().map(String::as_str));
+    roots.extend(STD_ROOTS);
+    for path in qualified_paths(&outside_uses) {
+        let Some((root, rest)) = path.split_once("::") else {
+            continue;
+        };
+        let full = match names.iter().find(|(name, _)| name == root) {
+            Some((_, imported)) => format!("{}::{}", imported, rest),
+            None if roots.contains(&root) => path.clone(),
+            None => continue,
+        };
+        if let Some(item) = item_path(&full) {
+            push_unique(&mut paths, item);
+        }
+    }
+    paths
+}
+
+fn push_unique(paths: &mut Vec<String>, path: String) {
+    if !paths.contains(&path) {
+        paths.push(path);
+    }
+}
+
+// The path an item is documented under, or `None` for paths into the current crate,
+// paths starting with a type and lone crate names
+fn item_path(path: &str) -> Option<String> {
+    let segments: Vec<&str> = path
+        .split("::")
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty() && *segment != "*")
+        .collect();
+    let root = segments.first()?;
+    if segments.len() < 2 || LOCAL_ROOTS.contains(root) || root.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return None;
+    }
+    // Cut after the first type-like segment, dropping associated items and variants
+    let end = segments
+        .iter()
+        .skip(1)
+        .position(|segment| segment.starts_with(|c: char| c.is_ascii_uppercase()))
+        .map_or(segments.len(), |i| i + 2);
+    Some(segments[..end].join("::"))
+}
+
+// The paths a `use` tree imports with the names they go by, expanding `{..}` groups,
+// resolving `self` to its module and following `as` renames
+fn expand_use_tree(tree: &str) -> Vec<(String, String)> {
+    let tree = tree.trim();
+    let Some(open) = tree.find('{') else {
+        let (path, alias) = match tree.split_once(" as ") {
+            Some((path, alias)) => (path.trim(), Some(alias.trim())),
+            None => (tree, None),
+        };
+        let name = alias.unwrap_or_else(|| path.rsplit("::").next().unwrap_or(path));
+        return vec![(path.to_string(), name.to_string())];
+    };
+    let prefix = tree[..open].trim().trim_end_matches("::");
+    let close = tree.rfind('}').unwrap_or(tree.len());
+    let inner = &tree[open + 1..close];
+
+    let mut paths = Vec::new();
+    for part in split_top_level(inner) {
+        for (path, name) in expand_use_tree(part) {
+            let entry = match path.as_str() {
+                "" => continue,
+                // `self` goes by its module's name unless renamed
+                "self" if name == "self" => (prefix.to_string(), prefix.rsplit("::").next().unwrap_or(prefix).to_string()),
+                "self" => (prefix.to_string(), name),
+                _ if prefix.is_empty() => (path, name),
+                _ => (format!("{}::{}", prefix, path), name),
+            };
+            paths.push(entry);
+        }
+    }
+    paths
+}
+
+// Split at commas that aren't inside a nested group
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+// Every `a::b` path in the code, skipping turbofish and generic arguments
+fn qualified_paths(code: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let chars: Vec<char> = code.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let starts_path = is_ident_start(chars[i]) && (i == 0 || !(is_ident_char(chars[i - 1]) || chars[i - 1] == ':'));
+        if !starts_path {
+            i += 1;
+            continue;
+        }
+        let mut path = String::new();
+        let mut segments = 0;
+        loop {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            path.extend(&chars[start..i]);
+            segments += 1;
+            let continues = chars.get(i) == Some(&':')
+                && chars.get(i + 1) == Some(&':')
+                && chars.get(i + 2).is_some_and(|c| is_ident_start(*c));
+            if !continues {
+                break;
+            }
+            path.push_str("::");
+            i += 2;
+        }
+        if segments > 1 {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Byte offset of `keyword` standing as a word of its own
+fn find_keyword(text: &str, keyword: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(offset) = text[from..].find(keyword) {
+        let start = from + offset;
+        let end = start + keyword.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        if !before.is_some_and(is_ident_char) && after.is_some_and(char::is_whitespace) {
+            return Some(start);
+        }
+        from = end;
+    }
+    None
+}
+
+// The source with comments removed and string and char literals emptied, so that
+// neither is mistaken for code
+fn strip_comments_and_strings(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                out.push(' ');
+            }
+            // Char literals, which lifetimes (`'a`) look like the start of
+            '\'' => {
+                let mut lookahead = chars.clone();
+                match (lookahead.next(), lookahead.next()) {
+                    (Some('\\'), _) => {
+                        chars.next();
+                        for c in chars.by_ref() {
+                            if c == '\'' {
+                                break;
+                            }
+                        }
+                        out.push_str("' '");
+                    }
+                    (Some(_), Some('\'')) => {
+                        chars.next();
+                        chars.next();
+                        out.push_str("' '");
+                    }
+                    _ => out.push(c),
+                }
+            }
+            '"' => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                out.push_str("\"\"");
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+use tokio::sync::Mutex;
+
+use super::fetcher::{DocFetcher, DocPage};
+use super::sources::{Capabilities, DocSource, LOCAL_SOURCE};
+
+/// Longest a `cargo doc` run may take before it is killed
+pub const LOCAL_DOC_BUILD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Number of trailing `cargo doc` output lines included in a build failure
+const BUILD_ERROR_LINES: usize = 20;
+
+/// The only environment variables `cargo doc` is run with, so build scripts and proc
+/// macros don't see the server's tokens and credentials
+const BUILD_ENV_VARS: [&str; 5] = ["PATH", "HOME", "CARGO_HOME", "RUSTUP_HOME", "RUSTUP_TOOLCHAIN"];
+
+#[derive(Clone, Debug)]
+struct LocalCrate {
+    doc_dir: PathBuf,
+    build: u64,
+}
+
+/// Crates documented by local `cargo doc` builds
+///
+/// Builds are only allowed for workspaces below one of the configured roots,
+/// so clients can't make the server run cargo anywhere on the machine. A build
+/// still runs the workspace's build scripts and proc macros, so it is sandboxed
+/// as far as cargo allows: offline, with the lockfile as it is, a cleared
+/// environment and a target directory of the server's rather than the workspace's.
+#[derive(Clone, Default)]
+pub struct LocalDocs {
+    roots: Arc<Vec<PathBuf>>,
+    /// Directory the builds' target directories go in, a temporary one when `None`
+    target_root: Option<PathBuf>,
+    crates: Arc<Mutex<HashMap<String, LocalCrate>>>,
+    builds: Arc<AtomicU64>,
+}
+
+impl LocalDocs {
+    /// Allow builds of workspaces below `roots`; roots that don't exist are ignored
+    pub fn new(roots: Vec<PathBuf>) -> Self {
+        let roots = roots
+            .into_iter()
+            .filter_map(|root| match root.canonicalize() {
+                Ok(root) => Some(root),
+                Err(e) => {
+                    tracing::warn!("Ignoring local docs root {}: {}", root.display(), e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            roots: Arc::new(roots),
+            ..Self::default()
+        }
+    }
+
+    /// Put the builds' target directories in `dir` instead of a temporary directory
+    pub fn with_target_dir(mut self, dir: PathBuf) -> Self {
+        self.target_root = Some(dir);
+        self
+    }
+
+    /// Target directory of the builds of the workspace at `workspace`, one per workspace
+    pub fn target_dir(&self, workspace: &Path) -> PathBuf {
+        let root = self
+            .target_root
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("cratedocs-local-docs"));
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        workspace.hash(&mut hasher);
+        let name = workspace.file_name().and_then(|name| name.to_str()).unwrap_or("workspace");
+        root.join(format!("{}-{:016x}", name, hasher.finish()))
+    }
+
+    /// Check that `path` is a Cargo workspace below an allowed root
+    pub fn workspace_dir(&self, path: &str) -> Result<PathBuf, ToolError> {
+        let dir = Path::new(path)
+            .canonicalize()
+            .map_err(|e| ToolError::InvalidParameters(format!("Invalid path {}: {}", path, e)))?;
+        if !self.roots.iter().any(|root| dir.starts_with(root)) {
+            return Err(ToolError::InvalidParameters(format!(
+                "{} is outside the directories local documentation builds are allowed in",
+                dir.display()
+            )));
+        }
+        if !dir.join("Cargo.toml").is_file() {
+            return Err(ToolError::InvalidParameters(format!("{} has no Cargo.toml", dir.display())));
+        }
+        Ok(dir)
+    }
+
+    /// Run `cargo doc --no-deps` for the workspace at `path` and serve the
+    /// documented crates locally, returning their names
+    ///
+    /// Dependencies must already be in cargo's cache and match `Cargo.lock`,
+    /// since nothing is downloaded.
+    pub async fn build(&self, path: &str) -> Result<(PathBuf, Vec<String>), ToolError> {
+        let dir = self.workspace_dir(path)?;
+        let target_dir = self.target_dir(&dir);
+        let mut command = tokio::process::Command::new("cargo");
+        command
+            .args(["doc", "--no-deps", "--workspace", "--offline", "--locked", "--target-dir"])
+            .arg(&target_dir)
+            .current_dir(&dir)
+            .env_clear()
+            .env("CARGO_NET_OFFLINE", "true")
+            .kill_on_drop(true);
+        for name in BUILD_ENV_VARS {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        let run = command.output();
+        let output = tokio::time::timeout(LOCAL_DOC_BUILD_TIMEOUT, run)
+            .await
+            .map_err(|_| {
+                ToolError::ExecutionError(format!(
+                    "cargo doc did not finish within {} seconds",
+                    LOCAL_DOC_BUILD_TIMEOUT.as_secs()
+                ))
+            })?
+            .map_err(|e| ToolError::ExecutionError(format!("Failed to run cargo doc: {}", e)))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<&str> = stderr.lines().collect();
+            return Err(ToolError::ExecutionError(format!(
+                "cargo doc failed ({}):\n{}",
+                output.status,
+                lines[lines.len().saturating_sub(BUILD_ERROR_LINES)..].join("\n")
+            )));
+        }
+
+        let doc_dir = target_dir.join("doc");
+        let crates = documented_crates(&doc_dir);
+        for crate_name in &crates {
+            self.register(crate_name, doc_dir.clone()).await;
+        }
+        Ok((dir, crates))
+    }
+
+    /// Serve `crate_name` from the rustdoc output in `doc_dir`
+    pub async fn register(&self, crate_name: &str, doc_dir: PathBuf) {
+        let build = self.builds.fetch_add(1, Ordering::Relaxed) + 1;
+        self.crates
+            .lock()
+            .await
+            .insert(crate_name.replace('-', "_"), LocalCrate { doc_dir, build });
+    }
+
+    /// Version standing in for the local build of a crate, changing with every
+    /// rebuild so cached pages of earlier builds aren't served
+    pub async fn build_version(&self, crate_name: &str) -> Option<String> {
+        let crates = self.crates.lock().await;
+        crates
+            .get(&crate_name.replace('-', "_"))
+            .map(|local| format!("local-{}", local.build))
+    }
+
+    // Output directory of the local build of a crate, unless `version` asks for a published release
+    async fn doc_dir(&self, crate_name: &str, version: Option<&str>) -> Option<PathBuf> {
+        if version.is_some_and(|version| !version.starts_with("local-")) {
+            return None;
+        }
+        let crates = self.crates.lock().await;
+        crates.get(&crate_name.replace('-', "_")).map(|local| local.doc_dir.clone())
+    }
+
+    // Output directory of the local build of a crate, failing for crates this source doesn't serve
+    async fn built_doc_dir(&self, crate_name: &str, version: Option<&str>) -> Result<PathBuf, ToolError> {
+        self.doc_dir(crate_name, version)
+            .await
+            .ok_or_else(|| ToolError::NotFound(format!("{} has no local documentation build", crate_name)))
+    }
+}
+
+// Crate documentation roots in a rustdoc output directory; only crate roots
+// have an `all.html` item list
+fn documented_crates(doc_dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(doc_dir) else {
+        return Vec::new();
+    };
+    let mut crates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("all.html").is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    crates.sort();
+    crates
+}
+
+// Read a file below a rustdoc output directory, refusing paths that climb out of it
+async fn read_doc_file(doc_dir: &Path, relative_path: &str) -> Result<DocPage, ToolError> {
+    let relative = Path::new(relative_path);
+    if relative.components().any(|component| !matches!(component, Component::Normal(_))) {
+        return Err(ToolError::InvalidParameters(format!("Invalid documentation path {}", relative_path)));
+    }
+    let path = doc_dir.join(relative);
+    let html = tokio::fs::read_to_string(&path).await.map_err(|e| {
+        ToolError::ExecutionError(format!("Failed to read local documentation {}: {}", path.display(), e))
+    })?;
+    Ok(DocPage {
+        url: format!("file://{}", path.display()),
+        html,
+    })
+}
+
+/// Serves the crates built locally from disk, ahead of docs.rs
+impl DocSource for LocalDocs {
+    fn name(&self) -> &str {
+        LOCAL_SOURCE
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CRATE_DOCS
+    }
+
+    fn serves<'a>(&'a self, crate_name: &'a str, version: Option<&'a str>) -> BoxFuture<'a, bool> {
+        Box::pin(async move { self.doc_dir(crate_name, version).await.is_some() })
+    }
+}
+
+impl DocFetcher for LocalDocs {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let doc_dir = self.built_doc_dir(crate_name, version).await?;
+            read_doc_file(&doc_dir, &format!("{}/index.html", crate_name.replace('-', "_"))).await
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let doc_dir = self.built_doc_dir(crate_name, version).await?;
+            read_doc_file(&doc_dir, &format!("{}/{}", crate_name.replace('-', "_"), relative_path)).await
+        })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let doc_dir = self.built_doc_dir(crate_name, version).await?;
+            read_doc_file(&doc_dir, file).await.map(|page| page.html)
+        })
+    }
+}
@@ -0,0 +1,64 @@
+/// Sources `Cargo.lock` gives packages from crates.io, through the git index and the sparse index
+const CRATES_IO_SOURCES: [&str; 2] = [
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// A package locked in a `Cargo.lock`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// Whether the package comes from crates.io, rather than a path, a git repository or another registry
+    pub from_crates_io: bool,
+}
+
+/// The packages of a `Cargo.lock`, in file order
+///
+/// Only the `[[package]]` tables are read, and of those only the `name`, `version`
+/// and `source` keys, which Cargo always writes as single-line strings.
+pub fn parse_lockfile(lockfile: &str) -> Result<Vec<LockedPackage>, String> {
+    let mut tables: Vec<PackageTable> = Vec::new();
+    let mut in_package = false;
+    for line in lockfile.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[[package]]";
+            if in_package {
+                tables.push(PackageTable::default());
+            }
+            continue;
+        }
+        let (Some(table), Some((key, value))) = (tables.last_mut().filter(|_| in_package), line.split_once('=')) else {
+            continue;
+        };
+        let value = Some(value.trim().trim_matches('"').to_string());
+        match key.trim() {
+            "name" => table.name = value,
+            "version" => table.version = value,
+            "source" => table.source = value,
+            _ => {}
+        }
+    }
+
+    let packages: Vec<LockedPackage> = tables
+        .into_iter()
+        .filter_map(|table| {
+            Some(LockedPackage {
+                from_crates_io: table.source.is_some_and(|source| CRATES_IO_SOURCES.contains(&source.as_str())),
+                name: table.name?,
+                version: table.version?,
+            })
+        })
+        .collect();
+    if packages.is_empty() {
+        return Err("No [[package]] entries found; is this a Cargo.lock file?".to_string());
+    }
+    Ok(packages)
+}
+
+#[derive(Default)]
+struct PackageTable {
+    name: Option<String>,
+    version: Option<String>,
+    source: Option<String>,
+}
@@ -0,0 +1,389 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Section of a manifest a dependency is declared in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DependencyKind {
+    Normal,
+    Build,
+    Dev,
+}
+
+/// A dependency declared in a `Cargo.toml`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestDependency {
+    /// Name the dependency goes by in the manifest
+    pub name: String,
+    /// Name of the crate on crates.io when the dependency is renamed with `package`
+    pub package: Option<String>,
+    /// Version requirement, e.g. `1.0` or `^0.7.4`
+    pub req: Option<String>,
+    pub kind: DependencyKind,
+    /// Platform the dependency is limited to, e.g. `cfg(unix)`
+    pub target: Option<String>,
+    pub features: Vec<String>,
+    pub default_features: bool,
+    pub optional: bool,
+    /// Where the dependency comes from when it isn't crates.io, e.g. `path ../util`
+    pub source: Option<String>,
+    /// Whether the entry is inherited from the workspace with `workspace = true`
+    pub workspace: bool,
+}
+
+impl ManifestDependency {
+    /// Name of the crate on crates.io
+    pub fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// The name in the `[package]` section of a manifest, if it has one
+pub fn manifest_package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for statement in statements(manifest) {
+        match statement {
+            Statement::Header(header) => in_package = header == ["package"],
+            Statement::KeyValue(key, value) if in_package && key == ["name"] => return value.as_str().map(str::to_string),
+            Statement::KeyValue(..) => {}
+        }
+    }
+    None
+}
+
+/// The dependencies a `Cargo.toml` declares, in file order
+///
+/// Reads `[dependencies]`, `[dev-dependencies]` and `[build-dependencies]`, their
+/// `[target.'cfg(...)'.*]` variants and `[workspace.dependencies]`, in both the inline
+/// (`serde = { version = "1", features = ["derive"] }`) and the table
+/// (`[dependencies.serde]`) form. Only the part of TOML manifests use is understood.
+pub fn parse_manifest_dependencies(manifest: &str) -> Result<Vec<ManifestDependency>, String> {
+    let mut dependencies: Vec<ManifestDependency> = Vec::new();
+    // The dependency table being read: the section's kind and target, and for
+    // `[dependencies.serde]` tables the index of the dependency
+    let mut section: Option<(DependencyKind, Option<String>, Option<usize>)> = None;
+    let mut has_sections = false;
+
+    for statement in statements(manifest) {
+        match statement {
+            Statement::Header(header) => {
+                has_sections = true;
+                section = dependency_section(&header).map(|(kind, target, name)| {
+                    let index = name.map(|name| {
+                        dependencies.push(new_dependency(name, kind, target.clone()));
+                        dependencies.len() - 1
+                    });
+                    (kind, target, index)
+                });
+            }
+            Statement::KeyValue(key, value) => match &section {
+                Some((_, _, Some(index))) => {
+                    if let [key] = key.as_slice() {
+                        apply_field(&mut dependencies[*index], key, &value);
+                    }
+                }
+                // Dotted keys such as `serde.workspace = true` set one field
+                Some((kind, target, None)) if key.len() == 2 => {
+                    let existing = dependencies
+                        .iter()
+                        .position(|dependency| dependency.name == key[0] && dependency.kind == *kind && &dependency.target == target);
+                    let index = existing.unwrap_or_else(|| {
+                        dependencies.push(new_dependency(key[0].clone(), *kind, target.clone()));
+                        dependencies.len() - 1
+                    });
+                    apply_field(&mut dependencies[index], &key[1], &value);
+                }
+                Some((kind, target, None)) => {
+                    let mut dependency = new_dependency(key.join("."), *kind, target.clone());
+                    match &value {
+                        Value::Table(fields) => {
+                            for (field, value) in fields {
+                                apply_field(&mut dependency, field, value);
+                            }
+                        }
+                        value => dependency.req = value.as_str().map(str::to_string),
+                    }
+                    dependencies.push(dependency);
+                }
+                None => {}
+            },
+        }
+    }
+
+    if !has_sections {
+        return Err("The manifest has no sections; is this a Cargo.toml file?".to_string());
+    }
+    Ok(dependencies)
+}
+
+fn new_dependency(name: String, kind: DependencyKind, target: Option<String>) -> ManifestDependency {
+    ManifestDependency {
+        name,
+        package: None,
+        req: None,
+        kind,
+        target,
+        features: Vec::new(),
+        default_features: true,
+        optional: false,
+        source: None,
+        workspace: false,
+    }
+}
+
+// The kind and target of a dependency section header, and the dependency's name
+// for headers of a single dependency's table like `dependencies.serde`
+fn dependency_section(header: &[String]) -> Option<(DependencyKind, Option<String>, Option<String>)> {
+    let (target, rest) = match header {
+        [target_key, target, rest @ ..] if target_key == "target" => (Some(target.clone()), rest),
+        [workspace, rest @ ..] if workspace == "workspace" => (None, rest),
+        rest => (None, rest),
+    };
+    let (table, name) = match rest {
+        [table] => (table, None),
+        [table, name] => (table, Some(name.clone())),
+        _ => return None,
+    };
+    let kind = match table.as_str() {
+        "dependencies" => DependencyKind::Normal,
+        "build-dependencies" | "build_dependencies" => DependencyKind::Build,
+        "dev-dependencies" | "dev_dependencies" => DependencyKind::Dev,
+        _ => return None,
+    };
+    Some((kind, target, name))
+}
+
+fn apply_field(dependency: &mut ManifestDependency, field: &str, value: &Value) {
+    match field {
+        "version" => dependency.req = value.as_str().map(str::to_string),
+        "package" => dependency.package = value.as_str().map(str::to_string),
+        "features" => dependency.features = value.strings(),
+        "default-features" | "default_features" => dependency.default_features = value.as_bool().unwrap_or(true),
+        "optional" => dependency.optional = value.as_bool().unwrap_or(false),
+        "workspace" => dependency.workspace = value.as_bool().unwrap_or(false),
+        "path" | "git" => {
+            if let Some(location) = value.as_str() {
+                dependency.source = Some(format!("{} {}", field, location));
+            }
+        }
+        "registry" => {
+            if let Some(registry) = value.as_str() {
+                dependency.source = Some(format!("registry {}", registry));
+            }
+        }
+        _ => {}
+    }
+}
+
+// A TOML value, as far as manifests need one
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    String(String),
+    Bool(bool),
+    Array(Vec<Value>),
+    Table(Vec<(String, Value)>),
+    // Numbers and dates, which manifests don't need read
+    Other,
+}
+
+impl Value {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn strings(&self) -> Vec<String> {
+        match self {
+            Value::Array(values) => values.iter().filter_map(|v| v.as_str()).map(str::to_string).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+enum Statement {
+    /// `[a.b]` or `[[a.b]]`, split into keys
+    Header(Vec<String>),
+    /// `a.b = value`
+    KeyValue(Vec<String>, Value),
+}
+
+// The headers and key-value pairs of a TOML document, joining values that span
+// lines (multi-line arrays) and skipping what can't be read
+fn statements(toml: &str) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut pending = String::new();
+    for line in toml.lines() {
+        pending.push_str(strip_comment(line));
+        pending.push('\n');
+        if !is_complete(&pending) {
+            continue;
+        }
+        let text = std::mem::take(&mut pending);
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if text.starts_with('[') {
+            let inner = text.trim_start_matches('[').trim_end_matches(']');
+            statements.push(Statement::Header(split_key(inner)));
+        } else if let Some((key, value)) = split_key_value(text) {
+            statements.push(Statement::KeyValue(split_key(key), parse_value(&mut value.chars().peekable())));
+        }
+    }
+    statements
+}
+
+// A line without its `#` comment, leaving `#` inside strings alone
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+// Whether every bracket and brace opened outside strings is closed again
+fn is_complete(text: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+// Split `key = value` at the first `=` outside a quoted key
+fn split_key_value(text: &str) -> Option<(&str, &str)> {
+    let mut quote: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '=') => return Some((&text[..i], &text[i + 1..])),
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+// Split a dotted key such as `target.'cfg(unix)'.dependencies` into its parts
+fn split_key(key: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quote: Option<char> = None;
+    for c in key.chars() {
+        match (quote, c) {
+            (None, '.') => parts.push(std::mem::take(&mut part)),
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => part.push(c),
+        }
+    }
+    parts.push(part);
+    parts.into_iter().map(|part| part.trim().to_string()).collect()
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Value {
+    skip_whitespace(chars);
+    match chars.peek().copied() {
+        Some(quote @ ('"' | '\'')) => {
+            chars.next();
+            let mut s = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    c if c == quote => break,
+                    // Only basic strings have escapes
+                    '\\' if quote == '"' => {
+                        if let Some(escaped) = chars.next() {
+                            s.push(escaped);
+                        }
+                    }
+                    c => s.push(c),
+                }
+            }
+            Value::String(s)
+        }
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                match chars.peek() {
+                    None => break,
+                    Some(']') => {
+                        chars.next();
+                        break;
+                    }
+                    Some(',') => {
+                        chars.next();
+                    }
+                    Some(_) => values.push(parse_value(chars)),
+                }
+            }
+            Value::Array(values)
+        }
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            loop {
+                skip_whitespace(chars);
+                match chars.peek() {
+                    None => break,
+                    Some('}') => {
+                        chars.next();
+                        break;
+                    }
+                    Some(',') => {
+                        chars.next();
+                    }
+                    Some(_) => {
+                        let mut key = String::new();
+                        while let Some(c) = chars.next_if(|c| *c != '=' && *c != '}') {
+                            key.push(c);
+                        }
+                        if chars.next_if_eq(&'=').is_none() {
+                            break;
+                        }
+                        let value = parse_value(chars);
+                        fields.push((split_key(&key).join("."), value));
+                    }
+                }
+            }
+            Value::Table(fields)
+        }
+        _ => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| !matches!(c, ',' | ']' | '}') && !c.is_whitespace()) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => Value::Other,
+            }
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
@@ -0,0 +1,289 @@
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+
+use super::books::Book;
+use super::errors::{with_error_data, ErrorCode, ErrorData};
+use super::fetcher::{DocFetcher, DocPage, FallbackDoc};
+use super::repository::RepoRef;
+use super::versions::Version;
+
+/// File in a crate's mirror directory holding its sparse index entry
+const INDEX_ENTRY_FILE: &str = "index-entry.json";
+
+/// File in a crate version's mirror directory holding its docs.rs crate page
+const CRATE_PAGE_FILE: &str = "crate-page.html";
+
+/// Host the URLs of mirrored pages name, so results cite where the pages came from
+const MIRRORED_DOCS_HOST: &str = "https://docs.rs";
+
+/// Crate docs saved to disk by `cratedocs mirror`, for serving without docs.rs
+///
+/// Each crate version has a `<crate>/<version>/` directory laid out like docs.rs: pages
+/// below the crate's `<crate_ident>/` directory (`tokio/sync/struct.Mutex.html`) and
+/// doc files such as the search index next to it, along with the docs.rs crate page in
+/// `crate-page.html`. The crate's sparse index entry is kept in `<crate>/index-entry.json`
+/// so versions resolve offline too.
+#[derive(Clone, Debug)]
+pub struct Mirror {
+    dir: PathBuf,
+}
+
+impl Mirror {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory of one mirrored crate version
+    pub fn version_dir(&self, crate_name: &str, version: &str) -> PathBuf {
+        self.dir.join(crate_name).join(version)
+    }
+
+    /// Save the docs.rs crate page of a crate version
+    pub async fn write_crate_page(&self, crate_name: &str, version: &str, html: &str) -> io::Result<()> {
+        self.write_file(crate_name, version, CRATE_PAGE_FILE, html).await
+    }
+
+    /// Save a page below a crate version's docs, e.g. `sync/struct.Mutex.html`
+    pub async fn write_page(&self, crate_name: &str, version: &str, relative_path: &str, html: &str) -> io::Result<()> {
+        self.write_file(crate_name, version, &page_file(crate_name, relative_path), html).await
+    }
+
+    /// Save a file at the root of a crate version's docs, e.g. `search-index.js`
+    pub async fn write_file(&self, crate_name: &str, version: &str, file: &str, contents: &str) -> io::Result<()> {
+        let path = checked_join(&self.version_dir(crate_name, version), file)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid documentation path {}", file)))?;
+        write_atomically(&path, contents).await
+    }
+
+    /// Save the sparse index entry of a crate
+    pub async fn write_index_entry(&self, crate_name: &str, entry: &str) -> io::Result<()> {
+        write_atomically(&self.dir.join(crate_name).join(INDEX_ENTRY_FILE), entry).await
+    }
+
+    /// The mirrored versions of a crate, oldest first
+    pub fn versions(&self, crate_name: &str) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(self.dir.join(crate_name)) else {
+            return Vec::new();
+        };
+        let mut versions: Vec<(Version, String)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter_map(|name| Some((Version::parse(&name)?, name)))
+            .collect();
+        versions.sort();
+        versions.into_iter().map(|(_, name)| name).collect()
+    }
+
+    // A file of a crate version, of the newest mirrored version when none is asked for,
+    // with the version it was read from
+    async fn read(&self, crate_name: &str, version: Option<&str>, file: &str) -> Option<(String, String)> {
+        let version = match version {
+            Some(version) => version.to_string(),
+            None => self.versions(crate_name).pop()?,
+        };
+        let path = checked_join(&self.version_dir(crate_name, &version), file)?;
+        let contents = tokio::fs::read_to_string(path).await.ok()?;
+        Some((version, contents))
+    }
+
+    async fn read_crate_page(&self, crate_name: &str, version: Option<&str>) -> Option<DocPage> {
+        let (version, html) = self.read(crate_name, version, CRATE_PAGE_FILE).await?;
+        Some(DocPage {
+            url: format!("{}/crate/{}/{}/", MIRRORED_DOCS_HOST, crate_name, version),
+            html,
+        })
+    }
+
+    async fn read_page(&self, crate_name: &str, version: Option<&str>, relative_path: &str) -> Option<DocPage> {
+        let (version, html) = self.read(crate_name, version, &page_file(crate_name, relative_path)).await?;
+        Some(DocPage {
+            url: format!("{}/{}/{}/{}", MIRRORED_DOCS_HOST, crate_name, version, page_file(crate_name, relative_path)),
+            html,
+        })
+    }
+
+    async fn read_index_entry(&self, crate_name: &str) -> Option<String> {
+        tokio::fs::read_to_string(self.dir.join(crate_name).join(INDEX_ENTRY_FILE)).await.ok()
+    }
+}
+
+// Path of a page below a crate version's directory
+fn page_file(crate_name: &str, relative_path: &str) -> String {
+    format!("{}/{}", crate_name.replace('-', "_"), relative_path)
+}
+
+// `dir` joined with a relative path, refusing paths that climb out of it
+fn checked_join(dir: &Path, relative: &str) -> Option<PathBuf> {
+    let relative = Path::new(relative);
+    relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+        .then(|| dir.join(relative))
+}
+
+// Write then rename so readers never see a partially written file
+async fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    tokio::fs::write(&tmp_path, contents).await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
+
+/// `DocFetcher` serving mirrored crate docs from disk and everything else through
+/// the wrapped fetcher, or, offline, nothing else at all
+pub struct MirrorFetcher {
+    mirror: Mirror,
+    upstream: Option<Arc<dyn DocFetcher>>,
+}
+
+impl MirrorFetcher {
+    /// Serve `mirror` ahead of `upstream`; without an upstream, requests for anything
+    /// that isn't mirrored fail instead of going to the network
+    pub fn new(mirror: Mirror, upstream: Option<Arc<dyn DocFetcher>>) -> Self {
+        Self { mirror, upstream }
+    }
+
+    // The wrapped fetcher, or the error offline mode gives for `what`
+    fn upstream(&self, what: &str) -> Result<&Arc<dyn DocFetcher>, ToolError> {
+        self.upstream.as_ref().ok_or_else(|| {
+            let error = ToolError::ExecutionError(format!("{} is not available offline", what));
+            with_error_data(error, ErrorData::new(ErrorCode::UpstreamUnavailable))
+        })
+    }
+
+    // The error offline mode gives for docs that weren't mirrored
+    fn not_mirrored(crate_name: &str, version: Option<&str>) -> ToolError {
+        let error = ToolError::ExecutionError(format!(
+            "{} {} is not in the mirror, and the server is offline",
+            crate_name,
+            version.unwrap_or("latest")
+        ));
+        let suggestion = format!("Mirror it first with `cratedocs mirror --crates {}`", crate_name);
+        with_error_data(error, ErrorData::new(ErrorCode::NotFound).with_suggestions(vec![suggestion]))
+    }
+}
+
+impl DocFetcher for MirrorFetcher {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            if let Some(page) = self.mirror.read_crate_page(crate_name, version).await {
+                return Ok(page);
+            }
+            match &self.upstream {
+                Some(upstream) => upstream.get_crate_page(crate_name, version).await,
+                None => Err(Self::not_mirrored(crate_name, version)),
+            }
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            if let Some(page) = self.mirror.read_page(crate_name, version, relative_path).await {
+                return Ok(page);
+            }
+            match &self.upstream {
+                Some(upstream) => upstream.get_item_page(crate_name, version, relative_path).await,
+                None => Err(Self::not_mirrored(crate_name, version)),
+            }
+        })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            if let Some((_, contents)) = self.mirror.read(crate_name, version, file).await {
+                return Ok(contents);
+            }
+            match &self.upstream {
+                Some(upstream) => upstream.get_doc_file(crate_name, version, file).await,
+                None => Err(Self::not_mirrored(crate_name, version)),
+            }
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("crates.io search")?.search(query, limit).await })
+    }
+
+    fn search_lib_rs<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("lib.rs search")?.search_lib_rs(query).await })
+    }
+
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("The crates.io API")?.get_crates_io_api(path).await })
+    }
+
+    // The index moves with every release, so it's only read from the mirror offline
+    // or when the upstream index can't be reached
+    fn get_index_entry<'a>(&'a self, crate_name: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let upstream_error = match &self.upstream {
+                Some(upstream) => match upstream.get_index_entry(crate_name).await {
+                    Ok(entry) => return Ok(entry),
+                    Err(e) => e,
+                },
+                None => Self::not_mirrored(crate_name, None),
+            };
+            self.mirror.read_index_entry(crate_name).await.ok_or(upstream_error)
+        })
+    }
+
+    fn get_rustdoc_json<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("rustdoc JSON")?.get_rustdoc_json(crate_name, version).await })
+    }
+
+    fn get_crate_archive<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<Vec<u8>, ToolError>> {
+        Box::pin(async move { self.upstream("Crate source")?.get_crate_archive(crate_name, version).await })
+    }
+
+    fn get_repository_file<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("The crate's repository")?.get_repository_file(repo, path).await })
+    }
+
+    fn list_repository_dir<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("The crate's repository")?.list_repository_dir(repo, path).await })
+    }
+
+    fn list_repository_releases<'a>(&'a self, repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { self.upstream("The crate's repository")?.list_repository_releases(repo).await })
+    }
+
+    fn get_book_page<'a>(&'a self, book: Book, path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move { self.upstream("The Rust books")?.get_book_page(book, path).await })
+    }
+
+    fn get_fallback_doc<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        Box::pin(async move {
+            self.upstream("Fallback documentation")?
+                .get_fallback_doc(crate_name, version, reason)
+                .await
+        })
+    }
+}
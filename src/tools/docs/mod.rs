@@ -1,6 +1,91 @@
+pub mod batch;
+pub mod books;
+pub mod builder;
+pub mod changelog;
+pub mod circuit;
+pub mod diff;
+pub mod doc_url;
 pub mod docs;
+pub mod errors;
+pub mod export;
+pub mod extract;
+pub mod fallback;
+pub mod features;
+pub mod fetcher;
+pub mod highlight;
+pub mod history;
+pub mod imports;
+pub mod local;
+pub mod lockfile;
+pub mod manifest;
+pub mod mirror;
+pub mod overrides;
+pub mod overview;
+pub mod persist;
+pub mod plain_text;
+pub mod policy;
+pub mod prefetch;
+pub mod releases;
+pub mod repository;
+pub mod resolve;
+pub mod resources;
+pub mod rust_releases;
+pub mod rustdoc_json;
+pub mod search;
+pub mod search_index;
+pub mod sections;
+#[cfg(feature = "semver")]
+pub mod semver;
+pub mod session;
+pub mod signature;
+pub mod snippet;
+pub mod source_stats;
+pub mod sources;
+pub mod sparse_index;
+pub mod subscriptions;
+pub mod targets;
+pub mod timing;
+pub mod usage;
+pub mod versions;
+pub mod watch;
+pub mod weight;
 
-pub use docs::DocRouter;
+pub use batch::{run_batch_line, BatchCall};
+pub use builder::DocRouterBuilder;
+pub use circuit::{CircuitBreaker, DEFAULT_CIRCUIT_COOLDOWN, DEFAULT_CIRCUIT_FAILURE_THRESHOLD};
+pub use doc_url::{DocUrl, ALLOWED_DOC_HOSTS};
+pub use docs::{
+    html_to_markdown, item_probe_order, sanitize_html, DocRouter, LatestVersions, OversizePolicy, DEFAULT_HTTP_MAX_CONTENT_BYTES,
+    DEFAULT_REFRESH_INTERVAL_SECS, DEFAULT_REFRESH_TOP, DEFAULT_STDIO_MAX_CONTENT_BYTES, ITEM_PAGE_KINDS, MAX_TRACKED_ACCESSES,
+};
+pub use export::{format_export_summary, ExportSummary, DEFAULT_EXPORT_MAX_PAGES};
+pub use errors::{error_data, split_error_data, with_error_data, DocsError, ErrorCode, ErrorData};
+pub use fallback::{CratesIoReadme, RepositoryDocs, CRATES_IO_README_SOURCE, REPOSITORY_SOURCE};
+pub use fetcher::{
+    user_agent, BaseUrls, DocFetcher, DocPage, FallbackDoc, ReqwestFetcher, DEFAULT_MAX_CONCURRENT_REQUESTS, DEFAULT_MAX_RESPONSE_BYTES,
+    USER_AGENT,
+};
+pub use history::{HistoryEntry, SessionHistory, HISTORY_RESOURCE_URI};
+pub use local::{LocalDocs, LOCAL_DOC_BUILD_TIMEOUT};
+pub use mirror::{Mirror, MirrorFetcher};
+pub use overrides::{CrateOverride, CrateOverrides};
+pub use persist::{DiskStore, SchemaMarker};
+pub use plain_text::{markdown_to_html, markdown_to_text, OutputFormat};
+pub use policy::{CratePolicy, PolicyFetcher};
+pub use prefetch::PrefetchConfig;
+pub use resolve::{DisabledDependency, Resolution, ResolvedCrate, MAX_RESOLVED_CRATES};
+pub use resources::{DocResource, DOCS_URI_PREFIX};
+pub use rustdoc_json::{JsonItem, RustdocJson, RustdocJsonCache, RUSTDOC_JSON_ABSENT_TTL, RUSTDOC_JSON_CACHE_CRATES};
+pub use search::SearchBackend;
+pub use search_index::{IndexItem, SearchIndex, SearchIndexCache, DEFAULT_INDEX_CACHE_BYTES, DEFAULT_INDEX_TTL};
+pub use session::{SessionDefaults, SessionSettings, BYTES_PER_TOKEN};
+pub use snippet::VersionPolicy;
+pub use sources::{Capabilities, DocSource, FetcherSource, SourceRegistry, LOCAL_SOURCE, UPSTREAM_SOURCES};
+pub use sparse_index::{IndexDependency, IndexVersion};
+pub use subscriptions::{ResourceSubscriptions, SessionSubscriptions, DEFAULT_SUBSCRIPTION_REFRESH_SECS};
+pub use usage::{UsageStats, USAGE_FILE};
+pub use versions::{Version, VersionReq};
+pub use watch::{Update, WatchList, DEFAULT_WATCH_INTERVAL_SECS, UPDATES_RESOURCE_URI};
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// Defaults for one crate, from the `crates` section of the config file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrateOverride {
+    /// Version used by calls that give neither a version nor a channel
+    pub version: Option<String>,
+    /// Features enabled by calls of feature-aware tools that give none
+    pub features: Vec<String>,
+    /// docs.rs build target whose item pages are read, e.g. `x86_64-pc-windows-msvc`
+    pub target: Option<String>,
+    /// Other names the crate can be looked up by
+    pub aliases: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    crates: HashMap<String, CrateOverride>,
+}
+
+/// Per-crate defaults filled into tool calls, so heavy users of e.g. tokio or sqlx
+/// don't have to repeat their version and features in every call
+///
+/// ```json
+/// { "crates": { "sqlx": { "version": "0.7.4", "features": ["postgres"], "aliases": ["sqlx-core"] } } }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CrateOverrides {
+    crates: HashMap<String, CrateOverride>,
+    /// Alias to crate name
+    aliases: HashMap<String, String>,
+}
+
+impl CrateOverrides {
+    /// Read the `crates` section of a JSON config file; other sections are left to their readers
+    pub fn parse(config: &str) -> Result<Self, String> {
+        let config: ConfigFile = serde_json::from_str(config).map_err(|e| format!("Invalid config: {}", e))?;
+        let mut aliases = HashMap::new();
+        for (crate_name, overrides) in &config.crates {
+            for alias in &overrides.aliases {
+                if config.crates.contains_key(alias) {
+                    return Err(format!("Alias {} of {} is itself configured as a crate", alias, crate_name));
+                }
+                if let Some(other) = aliases.insert(alias.clone(), crate_name.clone()) {
+                    return Err(format!("Alias {} is given to both {} and {}", alias, other, crate_name));
+                }
+            }
+        }
+        Ok(Self { crates: config.crates, aliases })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let config = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&config)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.crates.is_empty()
+    }
+
+    pub fn get(&self, crate_name: &str) -> Option<&CrateOverride> {
+        self.crates.get(crate_name)
+    }
+
+    /// The crate an alias stands for, or the name itself
+    pub fn crate_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// docs.rs build targets configured per crate
+    pub fn targets(&self) -> HashMap<String, String> {
+        self.crates
+            .iter()
+            .filter_map(|(crate_name, overrides)| Some((crate_name.clone(), overrides.target.clone()?)))
+            .collect()
+    }
+
+    /// Resolve aliases in the arguments of a tool call and fill in the defaults the
+    /// tool's `schema` has parameters for, as well as in each entry of an `items` list
+    pub fn apply(&self, schema: &Value, arguments: &mut Value) {
+        let accepts = |property: &str| schema.get("properties").and_then(|p| p.get(property)).is_some();
+        self.apply_to(arguments, &accepts);
+        if let Some(items) = arguments.get_mut("items").and_then(|items| items.as_array_mut()) {
+            // Entries of `lookup_items` take the parameters of `lookup_item`
+            for item in items {
+                self.apply_to(item, &|property| matches!(property, "version"));
+            }
+        }
+    }
+
+    fn apply_to(&self, arguments: &mut Value, accepts: &dyn Fn(&str) -> bool) {
+        let Some(arguments) = arguments.as_object_mut() else {
+            return;
+        };
+        let Some(name) = arguments.get("crate_name").and_then(|v| v.as_str()) else {
+            return;
+        };
+        let crate_name = self.crate_name(name).to_string();
+        arguments.insert("crate_name".to_string(), json!(crate_name));
+        let Some(overrides) = self.crates.get(&crate_name) else {
+            return;
+        };
+
+        let unversioned = !arguments.contains_key("version") && !arguments.contains_key("channel");
+        if let Some(version) = overrides.version.as_ref().filter(|_| unversioned && accepts("version")) {
+            arguments.insert("version".to_string(), json!(version));
+        }
+        if !overrides.features.is_empty() && !arguments.contains_key("features") && accepts("features") {
+            arguments.insert("features".to_string(), json!(overrides.features));
+        }
+    }
+}
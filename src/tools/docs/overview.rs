@@ -0,0 +1,206 @@
+use serde_json::Value;
+
+use super::manifest::{DependencyKind, ManifestDependency};
+use super::prefetch::parse_max_version;
+
+/// Maximum number of crates `compare_crates` accepts in one call
+pub const MAX_COMPARED_CRATES: usize = 10;
+
+/// Maximum number of dependencies `project_dependencies` looks up; the rest are only named
+pub const MAX_DIGEST_DEPENDENCIES: usize = 40;
+
+/// Features listed per dependency besides the enabled and default ones
+const MAX_DIGEST_FEATURES: usize = 10;
+
+/// The facts about a crate shown in a comparison table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrateSummary {
+    pub name: String,
+    pub version: String,
+    pub downloads: u64,
+    pub license: Option<String>,
+    /// `rust-version` declared by the latest version
+    pub msrv: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Summarize a crate from a crates.io `crates/{name}` response
+///
+/// License and MSRV come from the entry for the latest stable version in the
+/// response's `versions` list.
+pub fn parse_crate_summary(json: &str) -> Option<CrateSummary> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let krate = value.get("crate")?;
+    let version = parse_max_version(json)?;
+    let latest = value
+        .get("versions")
+        .and_then(|v| v.as_array())
+        .and_then(|versions| versions.iter().find(|v| v.get("num").and_then(|n| n.as_str()) == Some(version.as_str())));
+    let text = |value: Option<&Value>, field: &str| {
+        value
+            .and_then(|v| v.get(field))
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    Some(CrateSummary {
+        name: krate.get("name")?.as_str()?.to_string(),
+        downloads: krate.get("downloads").and_then(|v| v.as_u64()).unwrap_or(0),
+        license: text(latest, "license"),
+        msrv: text(latest, "rust_version"),
+        description: text(Some(krate), "description"),
+        version,
+    })
+}
+
+/// Render crate summaries as a markdown table, followed by the crates that couldn't be looked up
+pub fn format_comparison(summaries: &[CrateSummary], failed: &[String]) -> String {
+    let mut table = String::from(
+        "| Crate | Latest | Downloads | License | MSRV | Description |\n\
+         |---|---|---|---|---|---|\n",
+    );
+    for summary in summaries {
+        // Descriptions are free text; keep them on one line and out of the table syntax
+        let description = summary
+            .description
+            .as_deref()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .replace('|', "\\|");
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            summary.name,
+            summary.version,
+            summary.downloads,
+            summary.license.as_deref().unwrap_or("—"),
+            summary.msrv.as_deref().unwrap_or("—"),
+            description
+        ));
+    }
+    if !failed.is_empty() {
+        table.push_str(&format!("\nNot found on crates.io: {}\n", failed.join(", ")));
+    }
+    table
+}
+
+/// What `project_dependencies` reports about one dependency of a project
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DependencyDigest {
+    pub dependency: ManifestDependency,
+    /// `None` for dependencies that aren't from crates.io or couldn't be looked up
+    pub summary: Option<CrateSummary>,
+    /// Newest published version matching the dependency's requirement
+    pub resolved: Option<String>,
+    /// Features enabled by default, from the crate's sparse index entry
+    pub default_features: Vec<String>,
+    /// The crate's other features
+    pub other_features: Vec<String>,
+}
+
+/// Render a digest of a project's dependencies, grouped by the section they are declared in
+///
+/// `unlisted` are dependencies beyond `MAX_DIGEST_DEPENDENCIES`, named without a digest.
+pub fn format_dependency_digest(project: &str, digests: &[DependencyDigest], unlisted: &[String]) -> String {
+    let mut markdown = format!("# Dependencies of {}\n", project);
+    if digests.is_empty() && unlisted.is_empty() {
+        markdown.push_str("\nThe manifest declares no dependencies.\n");
+        return markdown;
+    }
+    for (kind, title) in [
+        (DependencyKind::Normal, "Dependencies"),
+        (DependencyKind::Build, "Build dependencies"),
+        (DependencyKind::Dev, "Dev dependencies"),
+    ] {
+        let section: Vec<&DependencyDigest> = digests.iter().filter(|digest| digest.dependency.kind == kind).collect();
+        if section.is_empty() {
+            continue;
+        }
+        markdown.push_str(&format!("\n## {} ({})\n", title, section.len()));
+        for digest in section {
+            markdown.push_str(&format_digest(digest));
+        }
+    }
+    if !unlisted.is_empty() {
+        markdown.push_str(&format!("\nNot looked up (too many dependencies): {}\n", unlisted.join(", ")));
+    }
+    markdown
+}
+
+fn format_digest(digest: &DependencyDigest) -> String {
+    let dependency = &digest.dependency;
+    let mut heading = format!("\n### {}", dependency.name);
+    if dependency.package.is_some() {
+        heading.push_str(&format!(" (crate {})", dependency.crate_name()));
+    }
+    if let Some(target) = &dependency.target {
+        heading.push_str(&format!(" [{}]", target));
+    }
+    if dependency.optional {
+        heading.push_str(" (optional)");
+    }
+    let mut out = format!("{}\n", heading);
+
+    if let Some(source) = &dependency.source {
+        out.push_str(&format!("- Not from crates.io: {}\n", source));
+        return out;
+    }
+    let Some(summary) = &digest.summary else {
+        out.push_str("- Not found on crates.io\n");
+        return out;
+    };
+    if let Some(description) = &summary.description {
+        out.push_str(&format!("{}\n", description.split_whitespace().collect::<Vec<_>>().join(" ")));
+    }
+
+    let requirement = match (&dependency.req, dependency.workspace) {
+        (Some(req), _) => format!("`{}`", req),
+        (None, true) => "inherited from the workspace".to_string(),
+        (None, false) => "no version requirement".to_string(),
+    };
+    let version = match &digest.resolved {
+        Some(resolved) if *resolved == summary.version => format!("{} resolves to {}, the latest", requirement, resolved),
+        Some(resolved) => format!("{} resolves to {}; latest is {}", requirement, resolved, summary.version),
+        None => format!("{}; latest is {}", requirement, summary.version),
+    };
+    out.push_str(&format!("- Version: {}\n", version));
+    out.push_str(&format!(
+        "- Docs: https://docs.rs/{}/{}\n",
+        summary.name,
+        digest.resolved.as_deref().unwrap_or(&summary.version)
+    ));
+
+    let mut features = Vec::new();
+    if !dependency.features.is_empty() {
+        features.push(format!("enabled {}", code_list(&dependency.features)));
+    }
+    if !dependency.default_features {
+        features.push("default features off".to_string());
+    } else if !digest.default_features.is_empty() {
+        features.push(format!("default {}", code_list(&digest.default_features)));
+    }
+    let others: Vec<String> = digest
+        .other_features
+        .iter()
+        .filter(|feature| !dependency.features.contains(feature))
+        .cloned()
+        .collect();
+    if !others.is_empty() {
+        let shown = others.len().min(MAX_DIGEST_FEATURES);
+        let mut available = format!("available {}", code_list(&others[..shown]));
+        if others.len() > shown {
+            available.push_str(&format!(" and {} more", others.len() - shown));
+        }
+        features.push(available);
+    }
+    if !features.is_empty() {
+        out.push_str(&format!("- Features: {}\n", features.join("; ")));
+    }
+    out
+}
+
+fn code_list(names: &[String]) -> String {
+    names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ")
+}
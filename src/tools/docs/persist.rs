@@ -0,0 +1,233 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the on-disk layout (marker file, entry naming); bump when it changes
+pub const CACHE_SCHEMA_VERSION: u32 = 1;
+
+/// Version of the HTML-to-markdown conversion; bump whenever `html_to_markdown`
+/// output changes so entries converted by an older release are discarded
+pub const CONVERTER_VERSION: u32 = 2;
+
+/// Version of the item-kind resolution in `lookup_item`; bump when the same
+/// cache key may resolve to a different page than before
+pub const RESOLVER_VERSION: u32 = 1;
+
+const MARKER_FILE: &str = "schema.json";
+const ENTRIES_DIR: &str = "entries";
+
+/// `format` of the first line of a cache bundle
+const BUNDLE_FORMAT: &str = "cratedocs-cache-bundle";
+
+/// Identifies the code that produced the entries of a persistent cache
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaMarker {
+    pub schema: u32,
+    pub converter: u32,
+    pub resolver: u32,
+}
+
+impl SchemaMarker {
+    /// Marker for entries written by this build
+    pub fn current() -> Self {
+        Self {
+            schema: CACHE_SCHEMA_VERSION,
+            converter: CONVERTER_VERSION,
+            resolver: RESOLVER_VERSION,
+        }
+    }
+}
+
+/// Directory-backed store for cached markdown that survives restarts
+///
+/// The directory holds a `schema.json` marker next to the entries. When the
+/// marker doesn't match the running build, the entries are dropped on open so
+/// an upgrade never serves markdown produced by an older converter.
+#[derive(Debug)]
+pub struct DiskStore {
+    entries_dir: PathBuf,
+    marker: SchemaMarker,
+    /// Held shared by every write in flight, and exclusively by `flush`
+    writes: tokio::sync::RwLock<()>,
+}
+
+impl DiskStore {
+    /// Open (or create) a store for this build
+    pub fn open(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with_marker(dir, SchemaMarker::current())
+    }
+
+    /// Open (or create) a store, invalidating entries written under another marker
+    pub fn open_with_marker(dir: impl AsRef<Path>, marker: SchemaMarker) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let entries_dir = dir.join(ENTRIES_DIR);
+        let marker_path = dir.join(MARKER_FILE);
+        std::fs::create_dir_all(dir)?;
+
+        let existing = std::fs::read_to_string(&marker_path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<SchemaMarker>(&text).ok());
+        if existing != Some(marker) {
+            if entries_dir.exists() {
+                tracing::info!(
+                    "Cache schema changed ({:?} -> {:?}), clearing {}",
+                    existing,
+                    marker,
+                    entries_dir.display()
+                );
+                std::fs::remove_dir_all(&entries_dir)?;
+            }
+            let marker_json = serde_json::to_string(&marker).map_err(io::Error::other)?;
+            std::fs::write(&marker_path, marker_json)?;
+        }
+
+        std::fs::create_dir_all(&entries_dir)?;
+        // A process killed mid-write leaves its temporary file behind
+        for entry in std::fs::read_dir(&entries_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "tmp") {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(Self {
+            entries_dir,
+            marker,
+            writes: tokio::sync::RwLock::new(()),
+        })
+    }
+
+    pub async fn get(&self, key: &str) -> Option<String> {
+        tokio::fs::read_to_string(self.entry_path(key)).await.ok()
+    }
+
+    /// Persist an entry; failures are logged since the in-memory copy still works
+    pub async fn set(&self, key: &str, value: &str) {
+        let _write = self.writes.read().await;
+        let path = self.entry_path(key);
+        // Write then rename so readers never see a partially written entry
+        let tmp_path = path.with_extension("tmp");
+        let result = async {
+            tokio::fs::write(&tmp_path, value).await?;
+            tokio::fs::rename(&tmp_path, &path).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to persist cache entry {}: {}", key, e);
+        }
+    }
+
+    /// Wait for the writes in flight to finish and make the entries durable, e.g.
+    /// before the process exits
+    pub async fn flush(&self) -> io::Result<()> {
+        let _writes = self.writes.write().await;
+        // Syncing the directory persists the renames that completed the writes
+        #[cfg(unix)]
+        tokio::fs::File::open(&self.entries_dir).await?.sync_all().await?;
+        Ok(())
+    }
+
+    /// Write the entries of the store, or only those of `crates` when any are given,
+    /// to a gzip-compressed bundle that `import_bundle` reads back elsewhere
+    ///
+    /// The bundle is JSON lines: a header naming the schema marker, then one
+    /// `{"key": ..., "value": ...}` object per entry. Returns the number of entries written.
+    pub fn export_bundle(&self, writer: impl Write, crates: &[String]) -> io::Result<usize> {
+        let mut bundle = GzEncoder::new(writer, Compression::default());
+        let header = serde_json::json!({ "format": BUNDLE_FORMAT, "marker": self.marker });
+        writeln!(bundle, "{}", header)?;
+
+        let mut written = 0;
+        for file in std::fs::read_dir(&self.entries_dir)? {
+            let path = file?.path();
+            let Some(key) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".md"))
+                .and_then(decode_key)
+            else {
+                continue;
+            };
+            if !crates.is_empty() && !crates.iter().any(|crate_name| key_names_crate(&key, crate_name)) {
+                continue;
+            }
+            let value = std::fs::read_to_string(&path)?;
+            writeln!(bundle, "{}", serde_json::json!({ "key": key, "value": value }))?;
+            written += 1;
+        }
+        bundle.finish()?.flush()?;
+        Ok(written)
+    }
+
+    /// Add the entries of a bundle written by `export_bundle`, replacing entries with
+    /// the same key, and return how many were added
+    ///
+    /// Bundles written under another schema marker are refused, since their entries
+    /// would be discarded the next time the store is opened by that build.
+    pub fn import_bundle(&self, reader: impl Read) -> io::Result<usize> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut lines = BufReader::new(GzDecoder::new(reader)).lines();
+        let header: serde_json::Value = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|e| invalid(format!("Not a cache bundle: {}", e)))?,
+            None => return Err(invalid("The bundle is empty".to_string())),
+        };
+        if header.get("format").and_then(|v| v.as_str()) != Some(BUNDLE_FORMAT) {
+            return Err(invalid("Not a cache bundle".to_string()));
+        }
+        let marker: Option<SchemaMarker> = header.get("marker").and_then(|v| serde_json::from_value(v.clone()).ok());
+        if marker != Some(self.marker) {
+            return Err(invalid(format!(
+                "The bundle was written for cache schema {:?}, this build uses {:?}; export it again with this version",
+                marker, self.marker
+            )));
+        }
+
+        let mut imported = 0;
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: BundleEntry = serde_json::from_str(&line).map_err(|e| invalid(format!("Invalid bundle entry: {}", e)))?;
+            let path = self.entry_path(&entry.key);
+            let tmp_path = path.with_extension("tmp");
+            std::fs::write(&tmp_path, entry.value)?;
+            std::fs::rename(&tmp_path, &path)?;
+            imported += 1;
+        }
+        Ok(imported)
+    }
+
+    // Keys contain `:` and `::`, so hex-encode them into portable file names
+    fn entry_path(&self, key: &str) -> PathBuf {
+        let name: String = key.bytes().map(|b| format!("{:02x}", b)).collect();
+        self.entries_dir.join(format!("{}.md", name))
+    }
+}
+
+#[derive(Deserialize)]
+struct BundleEntry {
+    key: String,
+    value: String,
+}
+
+// The key an entry's file name encodes, or `None` for files that aren't entries
+fn decode_key(name: &str) -> Option<String> {
+    if name.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..name.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(name.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+// Whether a cache key belongs to a crate: keys start with the crate's name
+// (`tokio:1.0.0:sync::Mutex`) or a kind of entry followed by it (`release-notes:tokio:..`)
+fn key_names_crate(key: &str, crate_name: &str) -> bool {
+    let mut segments = key.split(':');
+    segments.next() == Some(crate_name) || segments.next() == Some(crate_name)
+}
@@ -0,0 +1,187 @@
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use super::docs::sanitize_html;
+
+/// Indentation of code block lines in plain text output
+const CODE_INDENT: &str = "    ";
+
+/// How tool output is rendered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    /// Markdown rendered as plain text by `markdown_to_text`
+    Text,
+    /// Markdown rendered as sanitized HTML by `markdown_to_html`
+    Html,
+}
+
+impl OutputFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format {
+            "markdown" => Some(Self::Markdown),
+            "text" => Some(Self::Text),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Text => "text",
+            Self::Html => "html",
+        }
+    }
+
+    /// Render markdown output in this format
+    pub fn render(self, markdown: String) -> String {
+        match self {
+            Self::Markdown => markdown,
+            Self::Text => markdown_to_text(&markdown),
+            Self::Html => markdown_to_html(&markdown),
+        }
+    }
+}
+
+/// Render markdown as HTML, sanitized because markdown converted from upstream
+/// pages can carry inline HTML through
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, Parser::new_ext(markdown, options));
+    sanitize_html(&html)
+}
+
+/// Render markdown as plain text
+///
+/// Markup is dropped rather than stripped character by character, so code
+/// blocks (indented by four spaces) and inline code keep their `*`, `#` and
+/// backticks. Headings of the first two levels are underlined, list items keep
+/// their bullets or numbers, and links are followed by their URL.
+pub fn markdown_to_text(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut out = String::new();
+    // Next number of each open list, `None` for bullet lists
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut links: Vec<String> = Vec::new();
+    let mut heading_start = 0;
+    let mut in_code_block = false;
+    // Set right after a list marker, where the item's first block must not break the line
+    let mut at_item_start = false;
+    let mut table_cells = 0;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::Paragraph | Tag::BlockQuote(_)) => start_block(&mut out, &lists, &mut at_item_start),
+            Event::Start(Tag::Heading { .. }) => {
+                start_block(&mut out, &lists, &mut at_item_start);
+                heading_start = out.len();
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let underline = match level {
+                    HeadingLevel::H1 => '=',
+                    HeadingLevel::H2 => '-',
+                    _ => continue,
+                };
+                let width = out[heading_start..].chars().count();
+                out.push('\n');
+                out.extend(std::iter::repeat(underline).take(width));
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                start_block(&mut out, &lists, &mut at_item_start);
+                out.push_str(CODE_INDENT);
+                in_code_block = true;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                let code_end = out.trim_end().len();
+                out.truncate(code_end);
+            }
+            Event::Start(Tag::List(first)) => {
+                if lists.is_empty() {
+                    start_block(&mut out, &lists, &mut at_item_start);
+                } else {
+                    break_lines(&mut out, 1);
+                }
+                at_item_start = false;
+                lists.push(first);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                break_lines(&mut out, 1);
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        out.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+                at_item_start = true;
+            }
+            Event::Start(Tag::Table(_)) => start_block(&mut out, &lists, &mut at_item_start),
+            Event::Start(Tag::TableHead | Tag::TableRow) => {
+                break_lines(&mut out, 1);
+                table_cells = 0;
+            }
+            Event::Start(Tag::TableCell) => {
+                if table_cells > 0 {
+                    out.push_str(" | ");
+                }
+                table_cells += 1;
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url.to_string()),
+            Event::End(TagEnd::Link) => {
+                // Fragment links only point within the original page
+                if let Some(url) = links.pop().filter(|url| !url.starts_with('#') && !out.ends_with(url.as_str())) {
+                    out.push_str(&format!(" ({})", url));
+                }
+            }
+            Event::Text(text) => {
+                at_item_start = false;
+                if in_code_block {
+                    out.push_str(&text.replace('\n', &format!("\n{}", CODE_INDENT)));
+                } else {
+                    out.push_str(&text);
+                }
+            }
+            Event::Code(code) => {
+                at_item_start = false;
+                out.push_str(&code);
+            }
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Rule => {
+                start_block(&mut out, &lists, &mut at_item_start);
+                out.push_str("----");
+            }
+            Event::TaskListMarker(done) => out.push_str(if done { "[x] " } else { "[ ] " }),
+            // Raw HTML, footnotes and the like have no plain text form
+            _ => {}
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+// Separate a new block from the previous one: a blank line at the top level,
+// a line break inside lists, and nothing right after a list marker
+fn start_block(out: &mut String, lists: &[Option<u64>], at_item_start: &mut bool) {
+    if std::mem::take(at_item_start) {
+        return;
+    }
+    break_lines(out, if lists.is_empty() { 2 } else { 1 });
+}
+
+// End the output with at least `count` newlines, unless it is empty
+fn break_lines(out: &mut String, count: usize) {
+    if out.is_empty() {
+        return;
+    }
+    let existing = out.len() - out.trim_end_matches('\n').len();
+    for _ in existing..count {
+        out.push('\n');
+    }
+}
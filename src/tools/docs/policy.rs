@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+
+use super::books::Book;
+use super::errors::{with_error_data, ErrorCode, ErrorData};
+use super::fetcher::{is_std_crate, DocFetcher, DocPage, FallbackDoc};
+use super::repository::RepoRef;
+
+/// Which crates an operator lets this server look up
+///
+/// Entries are crate names or prefixes ending in `*` (`tokio-*`), matched the way
+/// crates.io matches names: ignoring case and treating `-` and `_` alike. A crate
+/// on the denylist is refused even when it is also allowed; with an allowlist,
+/// crates not on it are refused. The standard library is always allowed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CratePolicy {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl CratePolicy {
+    pub fn new(allow: &[String], deny: &[String]) -> Self {
+        let normalize = |entries: &[String]| {
+            entries
+                .iter()
+                .map(|entry| normalize_name(entry.trim()))
+                .filter(|entry| !entry.is_empty())
+                .collect()
+        };
+        Self {
+            allow: normalize(allow),
+            deny: normalize(deny),
+        }
+    }
+
+    /// Whether the policy allows every crate
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Refuse a crate the policy doesn't allow, with an error saying why
+    pub fn check(&self, crate_name: &str) -> Result<(), ToolError> {
+        if is_std_crate(crate_name) {
+            return Ok(());
+        }
+        let name = normalize_name(crate_name);
+        let reason = if self.deny.iter().any(|entry| matches_entry(entry, &name)) {
+            "is on this server's crate denylist"
+        } else if !self.allow.is_empty() && !self.allow.iter().any(|entry| matches_entry(entry, &name)) {
+            "is not on this server's crate allowlist"
+        } else {
+            return Ok(());
+        };
+        let error = ToolError::ExecutionError(format!("Crate {} {}, so it can't be looked up", crate_name, reason));
+        Err(with_error_data(error, ErrorData::new(ErrorCode::CrateNotAllowed)))
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace('_', "-")
+}
+
+fn matches_entry(entry: &str, name: &str) -> bool {
+    match entry.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => entry == name,
+    }
+}
+
+/// `DocFetcher` refusing requests for crates a `CratePolicy` doesn't allow before
+/// they reach the wrapped fetcher
+///
+/// Searches, books and repositories aren't tied to a crate name and pass through;
+/// a crate's repository is only found through its crates.io entry, which is checked.
+pub struct PolicyFetcher {
+    policy: CratePolicy,
+    inner: Arc<dyn DocFetcher>,
+}
+
+impl PolicyFetcher {
+    pub fn new(policy: CratePolicy, inner: Arc<dyn DocFetcher>) -> Self {
+        Self { policy, inner }
+    }
+}
+
+impl DocFetcher for PolicyFetcher {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_crate_page(crate_name, version).await
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_item_page(crate_name, version, relative_path).await
+        })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_doc_file(crate_name, version, file).await
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.inner.search(query, limit)
+    }
+
+    fn search_lib_rs<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.inner.search_lib_rs(query)
+    }
+
+    // Paths below `crates/<name>` are about that crate; other paths (searches,
+    // summaries) aren't
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let crate_name = path
+                .strip_prefix("crates/")
+                .and_then(|rest| rest.split(['/', '?']).next())
+                .filter(|name| !name.is_empty());
+            if let Some(crate_name) = crate_name {
+                self.policy.check(crate_name)?;
+            }
+            self.inner.get_crates_io_api(path).await
+        })
+    }
+
+    fn get_index_entry<'a>(&'a self, crate_name: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_index_entry(crate_name).await
+        })
+    }
+
+    fn get_rustdoc_json<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_rustdoc_json(crate_name, version).await
+        })
+    }
+
+    fn get_crate_archive<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<Vec<u8>, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_crate_archive(crate_name, version).await
+        })
+    }
+
+    fn get_repository_file<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.inner.get_repository_file(repo, path)
+    }
+
+    fn list_repository_dir<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.inner.list_repository_dir(repo, path)
+    }
+
+    fn list_repository_releases<'a>(&'a self, repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.inner.list_repository_releases(repo)
+    }
+
+    fn get_book_page<'a>(&'a self, book: Book, path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        self.inner.get_book_page(book, path)
+    }
+
+    fn get_fallback_doc<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        Box::pin(async move {
+            self.policy.check(crate_name)?;
+            self.inner.get_fallback_doc(crate_name, version, reason).await
+        })
+    }
+}
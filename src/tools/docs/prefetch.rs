@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::versions::Version;
+
+/// Item page kinds as they appear in rustdoc file names (`struct.Foo.html`)
+const ITEM_PAGE_KINDS: [&str; 11] = [
+    "struct", "enum", "trait", "fn", "macro", "type", "constant", "static", "union", "attr", "derive",
+];
+
+/// Settings for background prefetching of related documentation
+///
+/// After a crate page is fetched, the items it links to most often and the
+/// crate's direct dependencies are looked up in the background so that the
+/// follow-up questions agents almost always ask are served from the cache.
+#[derive(Clone, Debug)]
+pub struct PrefetchConfig {
+    /// Number of most-linked item pages to prefetch
+    pub max_items: usize,
+    /// Number of direct dependency crate pages to prefetch
+    pub max_dependencies: usize,
+    /// Maximum number of prefetch requests in flight at once
+    pub concurrency: usize,
+}
+
+impl Default for PrefetchConfig {
+    fn default() -> Self {
+        Self {
+            max_items: 5,
+            max_dependencies: 5,
+            concurrency: 4,
+        }
+    }
+}
+
+/// Collect the item paths (`module::Item`) a page links to within `crate_name`,
+/// most-linked first
+pub fn linked_item_paths(html: &str, crate_name: &str) -> Vec<String> {
+    let crate_ident = crate_name.replace('-', "_");
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut first_seen = Vec::new();
+
+    for (idx, _) in html.match_indices("href=\"") {
+        let rest = &html[idx + "href=\"".len()..];
+        let Some(end) = rest.find('"') else { continue };
+        let href = rest[..end].split(['#', '?']).next().unwrap_or_default();
+
+        let segments: Vec<&str> = href.split('/').collect();
+        let Some((file, dirs)) = segments.split_last() else { continue };
+        let Some(stem) = file.strip_suffix(".html") else { continue };
+        let Some((kind, name)) = stem.split_once('.') else { continue };
+        if !ITEM_PAGE_KINDS.contains(&kind) {
+            continue;
+        }
+        // Only absolute links into the crate's own rustdoc tree are usable
+        let Some(root) = dirs.iter().rposition(|segment| *segment == crate_ident) else { continue };
+
+        let mut path: Vec<&str> = dirs[root + 1..].to_vec();
+        path.push(name);
+        let item_path = path.join("::");
+
+        let count = counts.entry(item_path.clone()).or_insert(0);
+        if *count == 0 {
+            first_seen.push(item_path);
+        }
+        *count += 1;
+    }
+
+    // Stable sort keeps first-appearance order among equally linked items
+    first_seen.sort_by(|a, b| counts[b].cmp(&counts[a]));
+    first_seen
+}
+
+/// Extract the latest version from a crates.io `crates/{name}` response
+pub fn parse_max_version(json: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let krate = value.get("crate")?;
+    krate
+        .get("max_stable_version")
+        .and_then(|v| v.as_str())
+        .or_else(|| krate.get("max_version").and_then(|v| v.as_str()))
+        .map(|v| v.to_string())
+}
+
+/// Extract the latest release and, when it's newer, the latest pre-release from a
+/// crates.io `crates/{name}` response
+pub fn parse_latest_versions(json: &str) -> Option<(Option<String>, Option<String>)> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let krate = value.get("crate")?;
+    let stable = krate.get("max_stable_version").and_then(|v| v.as_str()).map(str::to_string);
+    let prerelease = krate
+        .get("max_version")
+        .and_then(|v| v.as_str())
+        .filter(|max| Version::parse(max).is_some_and(|v| v.is_prerelease()))
+        .map(str::to_string);
+    Some((stable, prerelease))
+}
+
+/// Extract the names of required (non-optional, normal) dependencies from a
+/// crates.io `crates/{name}/{version}/dependencies` response
+pub fn parse_direct_dependencies(json: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        return Vec::new();
+    };
+    value
+        .get("dependencies")
+        .and_then(|deps| deps.as_array())
+        .map(|deps| {
+            deps.iter()
+                .filter(|dep| dep.get("kind").and_then(|k| k.as_str()) == Some("normal"))
+                .filter(|dep| !dep.get("optional").and_then(|o| o.as_bool()).unwrap_or(false))
+                .filter_map(|dep| dep.get("crate_id").and_then(|c| c.as_str()))
+                .map(|name| name.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
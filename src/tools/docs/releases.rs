@@ -0,0 +1,135 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+/// Default window of `recent_releases` when no start date is given
+pub const DEFAULT_RELEASE_WINDOW_DAYS: u64 = 30;
+
+/// A published crate version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    pub crate_name: String,
+    pub version: String,
+    /// RFC 3339 publication timestamp as reported by crates.io
+    pub published: String,
+    pub yanked: bool,
+}
+
+/// Versions of one crate from a crates.io `crates/{name}/versions` response
+pub fn parse_crate_versions(json: &str, crate_name: &str) -> Option<Vec<Release>> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let versions = value.get("versions")?.as_array()?;
+    Some(
+        versions
+            .iter()
+            .filter_map(|version| {
+                Some(Release {
+                    crate_name: crate_name.to_string(),
+                    version: version.get("num")?.as_str()?.to_string(),
+                    published: version.get("created_at")?.as_str()?.to_string(),
+                    yanked: version.get("yanked").and_then(|v| v.as_bool()).unwrap_or(false),
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Recently updated crates from a crates.io `summary` response
+pub fn parse_just_updated(json: &str) -> Option<Vec<Release>> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let crates = value.get("just_updated")?.as_array()?;
+    Some(
+        crates
+            .iter()
+            .filter_map(|krate| {
+                let version = krate
+                    .get("newest_version")
+                    .or_else(|| krate.get("max_version"))?
+                    .as_str()?;
+                Some(Release {
+                    crate_name: krate.get("name")?.as_str()?.to_string(),
+                    version: version.to_string(),
+                    published: krate.get("updated_at")?.as_str()?.to_string(),
+                    yanked: false,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Whether `date` is a `YYYY-MM-DD` date
+pub fn is_iso_date(date: &str) -> bool {
+    let bytes = date.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(i, b)| match i {
+            4 | 7 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// The UTC date `days` days before `now`, as `YYYY-MM-DD`
+pub fn date_days_ago(now: SystemTime, days: u64) -> String {
+    let today = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs() / 86_400).unwrap_or(0);
+    let (year, month, day) = civil_from_days(today.saturating_sub(days) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `now` in UTC as an RFC 3339 timestamp to the second, e.g. `2025-01-31T09:05:00Z`
+pub fn utc_timestamp(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian date (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Keep releases published on or after `since` (`YYYY-MM-DD`), newest first
+pub fn releases_since(mut releases: Vec<Release>, since: &str) -> Vec<Release> {
+    // RFC 3339 timestamps in UTC compare correctly as strings
+    releases.retain(|release| release.published.get(..10).is_some_and(|date| date >= since));
+    releases.sort_by(|a, b| b.published.cmp(&a.published));
+    releases
+}
+
+/// Render releases as a markdown list
+pub fn format_releases(scope: &str, since: &str, releases: &[Release], limit: usize) -> String {
+    let mut markdown = format!("# Releases of {} since {}\n\n", scope, since);
+    if releases.is_empty() {
+        markdown.push_str("No releases found.\n");
+        return markdown;
+    }
+    for release in releases.iter().take(limit) {
+        markdown.push_str(&format!(
+            "- **{}** {} — {}{}\n",
+            release.crate_name,
+            release.version,
+            release.published.get(..10).unwrap_or(&release.published),
+            if release.yanked { " (yanked)" } else { "" }
+        ));
+    }
+    if releases.len() > limit {
+        markdown.push_str(&format!("\n{} more not shown.\n", releases.len() - limit));
+    }
+    markdown
+}
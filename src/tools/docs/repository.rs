@@ -0,0 +1,114 @@
+use serde_json::Value;
+
+/// Maximum number of files from a repository's `docs/` folder included in a fallback
+pub const MAX_REPOSITORY_DOCS: usize = 5;
+
+/// Code hosts whose repositories can stand in for missing docs.rs builds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepoHost {
+    GitHub,
+    GitLab,
+}
+
+/// A repository on a supported code host
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RepoRef {
+    pub host: RepoHost,
+    pub owner: String,
+    pub name: String,
+    /// Tag or branch files are read from, the default branch when `None`
+    pub git_ref: Option<String>,
+}
+
+impl RepoRef {
+    /// Parse a repository URL as listed on crates.io, e.g.
+    /// `https://github.com/tokio-rs/tokio` or `https://gitlab.com/owner/repo.git`
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = reqwest::Url::parse(url.trim()).ok()?;
+        let host = match url.host_str()? {
+            "github.com" | "www.github.com" => RepoHost::GitHub,
+            "gitlab.com" | "www.gitlab.com" => RepoHost::GitLab,
+            _ => return None,
+        };
+        // Only the first two segments name the repository; the rest is usually `/tree/main/...`
+        let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+        let owner = segments.next()?.to_string();
+        let name = segments.next()?.trim_end_matches(".git").to_string();
+        Some(Self { host, owner, name, git_ref: None })
+    }
+
+    /// The same repository at a tag or branch rather than its default branch
+    pub fn at(mut self, git_ref: &str) -> Self {
+        self.git_ref = Some(git_ref.to_string());
+        self
+    }
+
+    /// Revision files are read from: `git_ref`, else `HEAD`, which resolves to the
+    /// default branch on both hosts
+    pub fn revision(&self) -> &str {
+        self.git_ref.as_deref().unwrap_or("HEAD")
+    }
+
+    /// Web URL of the repository
+    pub fn url(&self) -> String {
+        match self.host {
+            RepoHost::GitHub => format!("https://github.com/{}/{}", self.owner, self.name),
+            RepoHost::GitLab => format!("https://gitlab.com/{}/{}", self.owner, self.name),
+        }
+    }
+
+    /// Web URL of the files at the revision
+    pub fn tree_url(&self) -> String {
+        match self.host {
+            RepoHost::GitHub => format!("{}/tree/{}", self.url(), self.revision()),
+            RepoHost::GitLab => format!("{}/-/tree/{}", self.url(), self.revision()),
+        }
+    }
+
+    /// Web URL of a file at the revision
+    pub fn file_url(&self, path: &str) -> String {
+        match self.host {
+            RepoHost::GitHub => format!("{}/blob/{}/{}", self.url(), self.revision(), path),
+            RepoHost::GitLab => format!("{}/-/blob/{}/{}", self.url(), self.revision(), path),
+        }
+    }
+
+    /// Web URL of the releases page
+    pub fn releases_url(&self) -> String {
+        match self.host {
+            RepoHost::GitHub => format!("{}/releases", self.url()),
+            RepoHost::GitLab => format!("{}/-/releases", self.url()),
+        }
+    }
+}
+
+/// Repository URL from a crates.io `crates/{name}` response
+pub fn parse_repository_url(json: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    value
+        .get("crate")?
+        .get("repository")?
+        .as_str()
+        .map(|url| url.to_string())
+}
+
+/// Paths of the markdown files in a directory listing from the GitHub contents
+/// API or the GitLab repository tree API, in listing order
+pub fn parse_markdown_listing(json: &str) -> Vec<String> {
+    let Ok(Value::Array(entries)) = serde_json::from_str::<Value>(json) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        // GitHub calls files "file", GitLab calls them "blob"
+        .filter(|entry| matches!(entry.get("type").and_then(|t| t.as_str()), Some("file" | "blob")))
+        .filter_map(|entry| entry.get("path").and_then(|p| p.as_str()))
+        .filter(|path| path.ends_with(".md"))
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// Whether a docs.rs crate page reports a failed documentation build
+pub fn is_failed_build_page(html: &str) -> bool {
+    html.contains("failed to build")
+}
@@ -0,0 +1,465 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use super::features::{activate, enabling_features, has_feature};
+use super::sparse_index::{find_version, IndexVersion};
+use super::versions::{Version, VersionReq};
+
+/// Most crates `resolve_dependencies` reads from the index before reporting the rest as not looked up
+pub const MAX_RESOLVED_CRATES: usize = 300;
+
+/// Levels of dependencies `resolve_dependencies` draws in tree form unless asked for more
+pub const DEFAULT_TREE_DEPTH: usize = 3;
+
+/// A crate version picked for the dependency graph
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedCrate {
+    pub name: String,
+    pub version: String,
+    /// Enabled features, unifying what every dependent asks for
+    pub features: BTreeSet<String>,
+    /// Dependents, as `name version`
+    pub required_by: BTreeSet<String>,
+    /// Fewest dependency edges from the root (1 for direct dependencies)
+    pub depth: usize,
+    /// Platforms the crate is limited to (empty when something needs it everywhere)
+    pub targets: BTreeSet<String>,
+    /// Whether only build scripts need it
+    pub build_only: bool,
+    /// For optional direct dependencies, the enabled features of the root crate that turn it on
+    pub enabled_by: Option<BTreeSet<String>>,
+}
+
+/// An optional dependency of the root crate that the chosen features leave out
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisabledDependency {
+    pub name: String,
+    pub req: String,
+    /// Platform the dependency is limited to, e.g. `cfg(windows)`
+    pub target: Option<String>,
+    /// Features of the root crate that would turn it on
+    pub enabled_by: BTreeSet<String>,
+}
+
+/// The dependency graph of a crate version, as far as the index entries at hand allow
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Resolution {
+    pub crate_name: String,
+    pub version: String,
+    /// Features enabled on the root crate
+    pub features: BTreeSet<String>,
+    /// Requested features the root crate doesn't have
+    pub unknown_features: Vec<String>,
+    /// Dependencies ordered by depth, then name
+    pub crates: Vec<ResolvedCrate>,
+    /// Optional dependencies of the root crate that aren't enabled
+    pub disabled: Vec<DisabledDependency>,
+    /// Crates whose index entries are needed to go on
+    pub missing: BTreeSet<String>,
+    /// Requirements nothing published satisfies, e.g. `foo ^9 (required by bar 1.0.0)`
+    pub unsatisfied: BTreeSet<String>,
+}
+
+/// Resolve the dependencies of `crate_name` `version` against the index entries fetched so far
+///
+/// Like a fresh `cargo update`, this picks the newest published version matching
+/// each requirement, keeps one version per semver-compatible range and unifies the
+/// features dependents enable. Dev-dependencies are skipped; build and
+/// platform-specific dependencies are kept and labelled. Crates without an entry
+/// in `index` are listed in `missing`, so callers can fetch them and resolve
+/// again, unless they are `unavailable`, which makes their requirements unsatisfied.
+pub fn resolve(
+    crate_name: &str,
+    version: &str,
+    features: &[String],
+    default_features: bool,
+    transitive: bool,
+    index: &HashMap<String, Vec<IndexVersion>>,
+    unavailable: &BTreeSet<String>,
+) -> Resolution {
+    let mut resolution = Resolution {
+        crate_name: crate_name.to_string(),
+        version: version.to_string(),
+        ..Resolution::default()
+    };
+    let Some(root) = index.get(crate_name).and_then(|versions| find_version(versions, version)) else {
+        resolution.missing.insert(crate_name.to_string());
+        return resolution;
+    };
+
+    let mut requested: BTreeSet<String> = features.iter().cloned().collect();
+    if default_features {
+        requested.insert("default".to_string());
+    }
+    let root_activation = activate(root, &requested);
+    resolution.unknown_features = features
+        .iter()
+        .filter(|feature| !root_activation.features.contains(*feature))
+        .cloned()
+        .collect();
+    resolution.features = root_activation.features.clone();
+    resolution.disabled = root
+        .dependencies
+        .iter()
+        .filter(|dependency| {
+            dependency.optional && dependency.kind != "dev" && !root_activation.dependencies.contains(dependency.manifest_name())
+        })
+        .map(|dependency| DisabledDependency {
+            name: dependency.name.clone(),
+            req: dependency.req.clone(),
+            target: dependency.target.clone(),
+            enabled_by: features_enabling(root, dependency.manifest_name()),
+        })
+        .collect();
+
+    // Crates are keyed by name and compatible range, as Cargo allows one version per range
+    let mut nodes: BTreeMap<(String, String), ResolvedCrate> = BTreeMap::new();
+    // What dependents ask for, kept apart from what activating it gives
+    let mut requested_features: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+    let mut queue: VecDeque<Option<(String, String)>> = VecDeque::from([None]);
+    while let Some(key) = queue.pop_front() {
+        let (parent, entry, activation, depth, targets, build_only) = match &key {
+            None => (crate_label(crate_name, version), root, root_activation.clone(), 0, BTreeSet::new(), false),
+            Some(key) => {
+                let node = &nodes[key];
+                let Some(entry) = index.get(&node.name).and_then(|versions| find_version(versions, &node.version)) else {
+                    continue;
+                };
+                let activation = activate(entry, &requested_features[key]);
+                let node = nodes.get_mut(key).expect("queued crates are resolved");
+                node.features = activation.features.clone();
+                let parent = crate_label(&node.name, &node.version);
+                (parent, entry, activation, node.depth, node.targets.clone(), node.build_only)
+            }
+        };
+        if depth > 0 && !transitive {
+            continue;
+        }
+
+        for dependency in &entry.dependencies {
+            if dependency.kind == "dev"
+                || (dependency.optional && !activation.dependencies.contains(dependency.manifest_name()))
+            {
+                continue;
+            }
+            let Some(versions) = index.get(&dependency.name) else {
+                if unavailable.contains(&dependency.name) {
+                    resolution
+                        .unsatisfied
+                        .insert(format!("{} {} (required by {}; not in the index)", dependency.name, dependency.req, parent));
+                } else {
+                    resolution.missing.insert(dependency.name.clone());
+                }
+                continue;
+            };
+            let Some(picked) = VersionReq::parse(&dependency.req).and_then(|req| newest_matching(versions, &req)) else {
+                resolution
+                    .unsatisfied
+                    .insert(format!("{} {} (required by {})", dependency.name, dependency.req, parent));
+                continue;
+            };
+
+            let mut dependency_features: BTreeSet<String> = dependency.features.iter().cloned().collect();
+            if let Some(enabled) = activation.dependency_features.get(dependency.manifest_name()) {
+                dependency_features.extend(enabled.iter().cloned());
+            }
+            if dependency.default_features {
+                dependency_features.insert("default".to_string());
+            }
+            // A dependency of a platform-specific crate is just as platform-specific
+            let edge_targets = match &dependency.target {
+                Some(target) => BTreeSet::from([target.clone()]),
+                None => targets.clone(),
+            };
+            let edge_build_only = build_only || dependency.kind == "build";
+            let edge_enabled_by = (depth == 0 && dependency.optional).then(|| {
+                features_enabling(root, dependency.manifest_name())
+                    .intersection(&root_activation.features)
+                    .cloned()
+                    .collect::<BTreeSet<String>>()
+            });
+
+            let child_key = (dependency.name.clone(), compatibility(&picked));
+            match nodes.get_mut(&child_key) {
+                Some(child) => {
+                    child.required_by.insert(parent.clone());
+                    let requested = requested_features.entry(child_key.clone()).or_default();
+                    let mut changed = !dependency_features.is_subset(requested);
+                    requested.extend(dependency_features.iter().cloned());
+                    child.features.extend(dependency_features);
+                    if depth + 1 < child.depth {
+                        child.depth = depth + 1;
+                        changed = true;
+                    }
+                    if !child.targets.is_empty() && edge_targets.is_empty() {
+                        child.targets.clear();
+                        changed = true;
+                    } else if !child.targets.is_empty() && !edge_targets.is_subset(&child.targets) {
+                        child.targets.extend(edge_targets);
+                        changed = true;
+                    }
+                    if child.build_only && !edge_build_only {
+                        child.build_only = false;
+                        changed = true;
+                    }
+                    // A crate the root depends on both optionally and unconditionally isn't optional
+                    if depth == 0 {
+                        child.enabled_by = match (child.enabled_by.take(), edge_enabled_by) {
+                            (Some(mut enabled_by), Some(edge_enabled_by)) => {
+                                enabled_by.extend(edge_enabled_by);
+                                Some(enabled_by)
+                            }
+                            _ => None,
+                        };
+                    }
+                    if changed {
+                        queue.push_back(Some(child_key));
+                    }
+                }
+                None => {
+                    requested_features.insert(child_key.clone(), dependency_features.clone());
+                    nodes.insert(
+                        child_key.clone(),
+                        ResolvedCrate {
+                            name: dependency.name.clone(),
+                            version: picked,
+                            features: dependency_features,
+                            required_by: BTreeSet::from([parent.clone()]),
+                            depth: depth + 1,
+                            targets: edge_targets,
+                            build_only: edge_build_only,
+                            enabled_by: edge_enabled_by,
+                        },
+                    );
+                    queue.push_back(Some(child_key));
+                }
+            }
+        }
+    }
+
+    let mut crates: Vec<ResolvedCrate> = nodes.into_values().collect();
+    crates.sort_by(|a, b| (a.depth, &a.name).cmp(&(b.depth, &b.name)));
+    resolution.crates = crates;
+    resolution
+}
+
+// Features of `entry` that turn on its optional dependency `name`, including the implicit one named after it
+fn features_enabling(entry: &IndexVersion, name: &str) -> BTreeSet<String> {
+    let mut features: BTreeSet<String> = enabling_features(entry, name).into_iter().collect();
+    if !entry.features.contains_key(name) && has_feature(entry, name) {
+        features.insert(name.to_string());
+    }
+    features
+}
+
+fn crate_label(crate_name: &str, version: &str) -> String {
+    format!("{} {}", crate_name, version)
+}
+
+/// The newest version that isn't yanked and satisfies `req`
+pub fn newest_matching(versions: &[IndexVersion], req: &VersionReq) -> Option<String> {
+    versions
+        .iter()
+        .filter(|entry| !entry.yanked)
+        .filter_map(|entry| Some((Version::parse(&entry.version)?, &entry.version)))
+        .filter(|(version, _)| req.matches(version))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, published)| published.clone())
+}
+
+// The semver-compatible range a version belongs to, e.g. `1` for 1.4.2 and `0.3` for 0.3.9
+fn compatibility(version: &str) -> String {
+    match Version::parse(version) {
+        Some(version) if version.major > 0 => version.major.to_string(),
+        Some(version) if version.minor > 0 => format!("0.{}", version.minor),
+        Some(version) => format!("0.0.{}", version.patch),
+        None => version.to_string(),
+    }
+}
+
+/// Render a resolution as markdown, direct dependencies first
+pub fn format_resolution(resolution: &Resolution) -> String {
+    let mut markdown = format!(
+        "# Resolved dependencies of {} {}\n\nFeatures: {}\n",
+        resolution.crate_name,
+        resolution.version,
+        join_or_none(&resolution.features)
+    );
+    if !resolution.unknown_features.is_empty() {
+        markdown.push_str(&format!(
+            "Unknown features (ignored): {}\n",
+            resolution.unknown_features.join(", ")
+        ));
+    }
+    markdown.push_str(
+        "\nEach requirement resolves to the newest published version that satisfies it, as in a \
+         fresh `cargo update`; a lock file may pin older versions. Dev-dependencies are not included.\n",
+    );
+
+    let (direct, transitive): (Vec<&ResolvedCrate>, Vec<&ResolvedCrate>) =
+        resolution.crates.iter().partition(|resolved| resolved.depth == 1);
+    markdown.push_str(&format!("\n## Direct dependencies ({})\n\n", direct.len()));
+    for resolved in &direct {
+        markdown.push_str(&crate_line(resolved, false));
+    }
+    if !transitive.is_empty() {
+        markdown.push_str(&format!("\n## Transitive dependencies ({})\n\n", transitive.len()));
+        for resolved in &transitive {
+            markdown.push_str(&crate_line(resolved, true));
+        }
+    }
+
+    if !resolution.disabled.is_empty() {
+        markdown.push_str(&format!("\n## Optional dependencies not enabled ({})\n\n", resolution.disabled.len()));
+        for disabled in &resolution.disabled {
+            let mut notes = vec![if disabled.enabled_by.is_empty() {
+                "no feature enables it".to_string()
+            } else {
+                format!("enabled by {}", join_or_none(&disabled.enabled_by))
+            }];
+            if let Some(target) = &disabled.target {
+                notes.push(format!("only for {}", target));
+            }
+            markdown.push_str(&format!("- {} {} ({})\n", disabled.name, disabled.req, notes.join("; ")));
+        }
+    }
+
+    push_unresolved(resolution, &mut markdown);
+    markdown
+}
+
+/// Render a resolution as a `cargo tree` style tree, `max_depth` levels deep
+///
+/// Like `cargo tree`, a crate's dependencies are drawn where it first appears and
+/// later appearances are marked `(*)`.
+pub fn format_tree(resolution: &Resolution, max_depth: usize) -> String {
+    let mut tree = Tree {
+        children: std::collections::HashMap::new(),
+        max_depth,
+        expanded: BTreeSet::new(),
+        truncated: false,
+        lines: vec![format!("{} v{}", resolution.crate_name, resolution.version)],
+    };
+    for resolved in &resolution.crates {
+        for parent in &resolved.required_by {
+            tree.children.entry(parent.as_str()).or_default().push(resolved);
+        }
+    }
+    for children in tree.children.values_mut() {
+        children.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    }
+    let root = crate_label(&resolution.crate_name, &resolution.version);
+    tree.expanded.insert(root.clone());
+    tree.push_children(&root, "", 1);
+
+    let mut markdown = format!(
+        "# Dependency tree of {} {}\n\nFeatures: {}\n\n```text\n{}\n```\n",
+        resolution.crate_name,
+        resolution.version,
+        join_or_none(&resolution.features),
+        tree.lines.join("\n")
+    );
+    if tree.lines.iter().any(|line| line.ends_with(" (*)")) {
+        markdown.push_str("\n(*) marks crates whose dependencies are drawn further up.\n");
+    }
+    if tree.truncated {
+        markdown.push_str(&format!(
+            "\nDependencies below depth {} are left out; ask for a greater depth to see them.\n",
+            max_depth
+        ));
+    }
+    push_unresolved(resolution, &mut markdown);
+    markdown
+}
+
+// Lines of a dependency tree being drawn
+struct Tree<'a> {
+    /// Dependencies of each crate, keyed by `name version`
+    children: std::collections::HashMap<&'a str, Vec<&'a ResolvedCrate>>,
+    max_depth: usize,
+    /// Crates whose dependencies have been drawn
+    expanded: BTreeSet<String>,
+    truncated: bool,
+    lines: Vec<String>,
+}
+
+impl Tree<'_> {
+    fn push_children(&mut self, parent: &str, prefix: &str, depth: usize) {
+        let Some(children) = self.children.get(parent).cloned() else {
+            return;
+        };
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let label = crate_label(&child.name, &child.version);
+            let has_children = self.children.contains_key(label.as_str());
+            let expand = has_children && depth < self.max_depth;
+            self.truncated |= has_children && !expand;
+            let repeated = expand && !self.expanded.insert(label.clone());
+            let mut line = format!("{}{} {} v{}", prefix, if last { "└──" } else { "├──" }, child.name, child.version);
+            if child.build_only {
+                line.push_str(" (build)");
+            }
+            if !child.targets.is_empty() {
+                line.push_str(&format!(" [{}]", child.targets.iter().cloned().collect::<Vec<_>>().join(", ")));
+            }
+            if repeated {
+                line.push_str(" (*)");
+            }
+            self.lines.push(line);
+            if expand && !repeated {
+                let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                self.push_children(&label, &prefix, depth + 1);
+            }
+        }
+    }
+}
+
+// Append the requirements that couldn't be resolved and the crates not looked up
+fn push_unresolved(resolution: &Resolution, markdown: &mut String) {
+    if !resolution.unsatisfied.is_empty() {
+        markdown.push_str("\n## Unsatisfied requirements\n\n");
+        for requirement in &resolution.unsatisfied {
+            markdown.push_str(&format!("- {}\n", requirement));
+        }
+    }
+    if !resolution.missing.is_empty() {
+        markdown.push_str(&format!(
+            "\nNot looked up (limit of {} crates reached): {}\n",
+            MAX_RESOLVED_CRATES,
+            resolution.missing.iter().cloned().collect::<Vec<_>>().join(", ")
+        ));
+    }
+}
+
+fn crate_line(resolved: &ResolvedCrate, show_dependents: bool) -> String {
+    let mut notes = Vec::new();
+    match &resolved.enabled_by {
+        Some(enabled_by) if !enabled_by.is_empty() => notes.push(format!("optional, enabled by {}", join_or_none(enabled_by))),
+        Some(_) => notes.push("optional".to_string()),
+        None => {}
+    }
+    if resolved.build_only {
+        notes.push("build only".to_string());
+    }
+    if !resolved.targets.is_empty() {
+        notes.push(format!("only for {}", join_or_none(&resolved.targets)));
+    }
+    if !resolved.features.is_empty() {
+        notes.push(format!("features: {}", join_or_none(&resolved.features)));
+    }
+    if show_dependents {
+        notes.push(format!("required by {}", join_or_none(&resolved.required_by)));
+    }
+    if notes.is_empty() {
+        format!("- {} {}\n", resolved.name, resolved.version)
+    } else {
+        format!("- {} {} ({})\n", resolved.name, resolved.version, notes.join("; "))
+    }
+}
+
+fn join_or_none(items: &BTreeSet<String>) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.iter().cloned().collect::<Vec<_>>().join(", ")
+    }
+}
@@ -0,0 +1,73 @@
+use serde_json::{json, Value};
+
+/// Scheme of the documentation resources
+pub const DOCS_URI_PREFIX: &str = "docs://";
+
+/// A documentation page addressed by a `docs://` URI
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DocResource {
+    /// `docs://{crate}/{version}`
+    Crate { crate_name: String, version: Option<String> },
+    /// `docs://{crate}/{version}/{item_path}`
+    Item {
+        crate_name: String,
+        version: Option<String>,
+        item_path: String,
+    },
+}
+
+impl DocResource {
+    /// Parse a documentation URI; a version of `latest` stands for no version
+    ///
+    /// The item path may use `::` or `/` between segments, so both
+    /// `docs://tokio/latest/sync::Mutex` and `docs://tokio/latest/sync/Mutex` work.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix(DOCS_URI_PREFIX)?;
+        let mut segments = rest.splitn(3, '/');
+        let crate_name = segments.next().filter(|name| !name.is_empty())?.to_string();
+        let version = segments.next().filter(|version| !version.is_empty())?;
+        let version = (version != "latest").then(|| version.to_string());
+        match segments.next().map(|path| path.trim_matches('/')) {
+            None | Some("") => Some(Self::Crate { crate_name, version }),
+            Some(path) => Some(Self::Item {
+                crate_name,
+                version,
+                item_path: path.replace('/', "::"),
+            }),
+        }
+    }
+
+    /// The canonical URI of this resource
+    pub fn uri(&self) -> String {
+        match self {
+            Self::Crate { crate_name, version } => {
+                format!("{}{}/{}", DOCS_URI_PREFIX, crate_name, version.as_deref().unwrap_or("latest"))
+            }
+            Self::Item { crate_name, version, item_path } => format!(
+                "{}{}/{}/{}",
+                DOCS_URI_PREFIX,
+                crate_name,
+                version.as_deref().unwrap_or("latest"),
+                item_path
+            ),
+        }
+    }
+}
+
+/// The `resources/templates/list` entries for documentation URIs
+pub fn resource_templates() -> Vec<Value> {
+    vec![
+        json!({
+            "uriTemplate": "docs://{crate}/{version}",
+            "name": "Crate documentation",
+            "description": "Documentation of a crate's root page, as returned by lookup_crate; use latest as the version for the newest release",
+            "mimeType": "text/markdown"
+        }),
+        json!({
+            "uriTemplate": "docs://{crate}/{version}/{item_path}",
+            "name": "Item documentation",
+            "description": "Documentation of an item such as sync::mpsc::Sender, as returned by lookup_item; use latest as the version for the newest release",
+            "mimeType": "text/markdown"
+        }),
+    ]
+}
@@ -0,0 +1,104 @@
+/// Path of the release notes in the rust-lang/rust repository
+pub const RUST_RELEASES_FILE: &str = "RELEASES.md";
+
+/// Maximum number of releases `rust_release_notes` returns for a version range
+pub const MAX_RUST_RELEASES: usize = 12;
+
+/// The notes of one Rust release from `RELEASES.md`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RustRelease {
+    /// e.g. `1.75.0`
+    pub version: String,
+    /// Release date as written in the heading, e.g. `2023-12-28`
+    pub date: Option<String>,
+    /// The release's sections, with setext headings turned into `###` headings
+    pub notes: String,
+}
+
+impl RustRelease {
+    pub fn number(&self) -> Option<(u64, u64, u64)> {
+        parse_rust_version(&self.version)
+    }
+}
+
+/// Parse a toolchain version such as `1.75`, `1.75.0` or `v1.75.0`
+pub fn parse_rust_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = match parts.next() {
+        Some(patch) => patch.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// Split `RELEASES.md` into releases, newest first as in the file
+///
+/// Each release starts with a `Version 1.75.0 (2023-12-28)` heading underlined
+/// with `=`; its sections (`Language`, `Libraries`, …) are underlined with `-`.
+pub fn parse_release_notes(markdown: &str) -> Vec<RustRelease> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let underlined = |i: usize, c: char| {
+        lines
+            .get(i + 1)
+            .is_some_and(|next| next.len() >= 3 && next.trim_end().chars().all(|n| n == c))
+    };
+
+    let mut releases: Vec<RustRelease> = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i].trim();
+        if let Some(heading) = line.strip_prefix("Version ").filter(|_| underlined(i, '=')) {
+            let mut words = heading.split_whitespace();
+            releases.push(RustRelease {
+                version: words.next().unwrap_or_default().to_string(),
+                date: words
+                    .next()
+                    .map(|date| date.trim_matches(|c| c == '(' || c == ')').to_string()),
+                notes: String::new(),
+            });
+            i += 2;
+            continue;
+        }
+        if let Some(release) = releases.last_mut() {
+            if !line.is_empty() && underlined(i, '-') {
+                release.notes.push_str(&format!("### {}\n", line));
+                i += 2;
+                continue;
+            }
+            // Anchors like `<a id="1.75.0-Language"></a>` only serve GitHub's renderer
+            if !(line.starts_with("<a id=") && line.ends_with("</a>")) {
+                release.notes.push_str(lines[i]);
+                release.notes.push('\n');
+            }
+        }
+        i += 1;
+    }
+    for release in &mut releases {
+        release.notes = release.notes.trim().to_string();
+    }
+    releases
+}
+
+/// Render releases as markdown, one `##` section per release
+pub fn format_release_notes(title: &str, releases: &[&RustRelease], omitted: usize) -> String {
+    let mut markdown = format!("# {}\n", title);
+    for release in releases {
+        markdown.push_str(&format!("\n## Rust {}", release.version));
+        if let Some(date) = &release.date {
+            markdown.push_str(&format!(" ({})", date));
+        }
+        markdown.push_str(&format!("\n\n{}\n", release.notes));
+    }
+    if omitted > 0 {
+        markdown.push_str(&format!(
+            "\n{} older releases in the range are not shown; narrow the range to see them.\n",
+            omitted
+        ));
+    }
+    markdown
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde_json::{Map, Value};
+use tokio::sync::Mutex;
+
+/// Number of crate versions whose rustdoc JSON (or its absence) is remembered
+pub const RUSTDOC_JSON_CACHE_CRATES: usize = 16;
+
+/// How long a crate version is remembered to have no rustdoc JSON; docs.rs may still
+/// be building it
+pub const RUSTDOC_JSON_ABSENT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// rustdoc JSON item kinds with a page of their own: (kind, page prefix, heading label)
+const PAGE_KINDS: [(&str, &str, &str); 13] = [
+    ("module", "mod", "Module"),
+    ("struct", "struct", "Struct"),
+    ("enum", "enum", "Enum"),
+    ("union", "union", "Union"),
+    ("trait", "trait", "Trait"),
+    ("trait_alias", "traitalias", "Trait Alias"),
+    ("function", "fn", "Function"),
+    ("type_alias", "type", "Type Alias"),
+    ("constant", "constant", "Constant"),
+    ("static", "static", "Static"),
+    ("macro", "macro", "Macro"),
+    ("proc_attribute", "attr", "Attribute Macro"),
+    ("proc_derive", "derive", "Derive Macro"),
+];
+
+/// What the rustdoc JSON says about one item with its own page
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JsonItem {
+    /// rustdoc page prefix, e.g. `struct` or `fn`
+    pub kind: &'static str,
+    /// Heading label, e.g. `Struct` or `Function`
+    pub label: &'static str,
+    /// Doc comment (markdown)
+    pub docs: String,
+    /// Member lists such as `Variants` or `Methods`, each entry `name` or `name — summary`
+    pub sections: Vec<(&'static str, Vec<String>)>,
+    /// Traits implemented by the type, by name
+    pub implements: Vec<String>,
+}
+
+/// The public items of a crate version, from the rustdoc JSON docs.rs built for it
+#[derive(Debug, Default)]
+pub struct RustdocJson {
+    /// Keyed by full path, e.g. `tokio::sync::Mutex`
+    items: HashMap<String, JsonItem>,
+}
+
+impl RustdocJson {
+    /// Read the items of the documented crate out of a rustdoc JSON document
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let doc: Value = serde_json::from_str(json).map_err(|e| format!("Invalid rustdoc JSON: {}", e))?;
+        let index = doc.get("index").and_then(|v| v.as_object()).ok_or("rustdoc JSON has no index")?;
+        let paths = doc.get("paths").and_then(|v| v.as_object()).ok_or("rustdoc JSON has no paths")?;
+
+        let mut items = HashMap::new();
+        for (id, summary) in paths {
+            // crate_id 0 is the documented crate; everything else is a dependency
+            if summary.get("crate_id").and_then(|v| v.as_u64()) != Some(0) {
+                continue;
+            }
+            let Some(kind) = summary.get("kind").and_then(|v| v.as_str()) else { continue };
+            let Some(&(_, prefix, label)) = PAGE_KINDS.iter().find(|(name, _, _)| *name == kind) else { continue };
+            let Some(path) = summary.get("path").and_then(|v| v.as_array()) else { continue };
+            let path: Vec<&str> = path.iter().filter_map(|segment| segment.as_str()).collect();
+            let Some(item) = index.get(id) else { continue };
+            if item.get("visibility").and_then(|v| v.as_str()) != Some("public") {
+                continue;
+            }
+            items.insert(path.join("::"), JsonItem::new(prefix, label, item, index));
+        }
+        Ok(Self { items })
+    }
+
+    /// The item at `path`, including the crate, e.g. `tokio::sync::Mutex`
+    pub fn item(&self, path: &str) -> Option<&JsonItem> {
+        self.items.get(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl JsonItem {
+    fn new(kind: &'static str, label: &'static str, item: &Value, index: &Map<String, Value>) -> Self {
+        let inner = item.get("inner");
+        let field = |name: &str| inner.and_then(|inner| inner.get(name));
+        let members = |ids: Option<&Value>| {
+            ids.and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|id| index.get(&id_key(id)))
+                .collect::<Vec<&Value>>()
+        };
+
+        let mut sections: Vec<(&'static str, Vec<&Value>)> = Vec::new();
+        let mut impls: Vec<&Value> = Vec::new();
+        match kind {
+            "struct" => {
+                let fields = field("struct")
+                    .and_then(|s| s.get("kind"))
+                    .and_then(|k| k.get("plain"))
+                    .and_then(|p| p.get("fields"));
+                sections.push(("Fields", members(fields).into_iter().filter(|m| is_public(m)).collect()));
+                impls = members(field("struct").and_then(|s| s.get("impls")));
+            }
+            "union" => {
+                let fields = field("union").and_then(|u| u.get("fields"));
+                sections.push(("Fields", members(fields).into_iter().filter(|m| is_public(m)).collect()));
+                impls = members(field("union").and_then(|u| u.get("impls")));
+            }
+            "enum" => {
+                sections.push(("Variants", members(field("enum").and_then(|e| e.get("variants")))));
+                impls = members(field("enum").and_then(|e| e.get("impls")));
+            }
+            "trait" => {
+                let (provided, required): (Vec<&Value>, Vec<&Value>) = members(field("trait").and_then(|t| t.get("items")))
+                    .into_iter()
+                    .partition(|member| {
+                        member
+                            .get("inner")
+                            .and_then(|inner| inner.get("function"))
+                            .and_then(|function| function.get("has_body"))
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false)
+                    });
+                sections.push(("Required items", required));
+                sections.push(("Provided methods", provided));
+            }
+            "mod" => {
+                let module_items = members(field("module").and_then(|m| m.get("items")));
+                sections.push(("Items", module_items.into_iter().filter(|m| is_public(m)).collect()));
+            }
+            _ => {}
+        }
+
+        // Inherent impls contribute methods; trait impls the list of implemented traits.
+        // Auto-trait and blanket impls are left out, as on the HTML page's main list.
+        let mut methods: Vec<&Value> = Vec::new();
+        let mut implements = Vec::new();
+        for block in impls {
+            let Some(block) = block.get("inner").and_then(|inner| inner.get("impl")) else { continue };
+            if block.get("is_synthetic").and_then(|v| v.as_bool()).unwrap_or(false)
+                || block.get("blanket_impl").is_some_and(|v| !v.is_null())
+            {
+                continue;
+            }
+            match block.get("trait").filter(|t| !t.is_null()) {
+                Some(trait_ref) => {
+                    let name = trait_ref.get("path").or_else(|| trait_ref.get("name")).and_then(|v| v.as_str());
+                    if let Some(name) = name.map(|name| name.rsplit("::").next().unwrap_or(name).to_string()) {
+                        if !implements.contains(&name) {
+                            implements.push(name);
+                        }
+                    }
+                }
+                None => methods.extend(members(block.get("items")).into_iter().filter(|m| is_public(m))),
+            }
+        }
+        if !methods.is_empty() {
+            sections.push(("Methods", methods));
+        }
+
+        Self {
+            kind,
+            label,
+            docs: item.get("docs").and_then(|v| v.as_str()).unwrap_or_default().trim().to_string(),
+            sections: sections
+                .into_iter()
+                .map(|(title, members)| (title, members.into_iter().filter_map(member_line).collect::<Vec<_>>()))
+                .filter(|(_, lines)| !lines.is_empty())
+                .collect(),
+            implements,
+        }
+    }
+}
+
+fn is_public(item: &Value) -> bool {
+    item.get("visibility").and_then(|v| v.as_str()) == Some("public")
+}
+
+// `name — first sentence of the docs` for a member list
+fn member_line(member: &Value) -> Option<String> {
+    let name = member.get("name")?.as_str()?;
+    let summary = member
+        .get("docs")
+        .and_then(|v| v.as_str())
+        .and_then(|docs| docs.lines().map(str::trim).find(|line| !line.is_empty()));
+    Some(match summary {
+        Some(summary) => format!("`{}` — {}", name, summary),
+        None => format!("`{}`", name),
+    })
+}
+
+// Ids are integers in newer format versions and strings in older ones
+fn id_key(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Render an item from rustdoc JSON as markdown, for when its HTML page can't be fetched
+pub fn format_json_item(path: &str, item: &JsonItem) -> String {
+    let mut markdown = format!("# {} {}\n", item.label, path);
+    if !item.docs.is_empty() {
+        markdown.push_str(&format!("\n{}\n", item.docs));
+    }
+    for (title, lines) in &item.sections {
+        markdown.push_str(&format!("\n## {}\n\n", title));
+        for line in lines {
+            markdown.push_str(&format!("- {}\n", line));
+        }
+    }
+    if !item.implements.is_empty() {
+        markdown.push_str(&format!("\n## Trait Implementations\n\n{}\n", item.implements.join(", ")));
+    }
+    markdown
+}
+
+#[derive(Default)]
+struct JsonEntries {
+    /// Each crate version's JSON (or its absence), with when it was stored
+    entries: HashMap<String, (Option<Arc<RustdocJson>>, Instant)>,
+    order: VecDeque<String>,
+}
+
+/// Parsed rustdoc JSON by crate and version, remembering versions docs.rs has
+/// no JSON for so they aren't requested again for `RUSTDOC_JSON_ABSENT_TTL`
+#[derive(Clone)]
+pub struct RustdocJsonCache {
+    inner: Arc<Mutex<JsonEntries>>,
+    capacity: usize,
+    absent_ttl: Duration,
+}
+
+impl Default for RustdocJsonCache {
+    fn default() -> Self {
+        Self::new(RUSTDOC_JSON_CACHE_CRATES)
+    }
+}
+
+impl RustdocJsonCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::default(),
+            capacity,
+            absent_ttl: RUSTDOC_JSON_ABSENT_TTL,
+        }
+    }
+
+    /// Forget that a crate version has no JSON after `ttl`, so it's requested again
+    pub fn with_absent_ttl(mut self, ttl: Duration) -> Self {
+        self.absent_ttl = ttl;
+        self
+    }
+
+    /// `Some(None)` when the crate version is known to have no usable JSON
+    pub async fn get(&self, key: &str) -> Option<Option<Arc<RustdocJson>>> {
+        let inner = self.inner.lock().await;
+        let (doc, stored_at) = inner.entries.get(key)?;
+        if doc.is_none() && stored_at.elapsed() >= self.absent_ttl {
+            return None;
+        }
+        Some(doc.clone())
+    }
+
+    /// Remember a crate version's JSON (or its absence), evicting the oldest entry when full
+    pub async fn set(&self, key: String, doc: Option<Arc<RustdocJson>>) {
+        let mut inner = self.inner.lock().await;
+        if inner.entries.insert(key.clone(), (doc, Instant::now())).is_none() {
+            inner.order.push_back(key);
+        }
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.entries.remove(&oldest);
+            }
+        }
+    }
+}
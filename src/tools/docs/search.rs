@@ -0,0 +1,191 @@
+use scraper::{Html, Selector};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::search_index::IndexItem;
+
+/// Widely used crates whose indexes `find_defining_crate` always searches, since
+/// the item a user's code mentions most often comes from one of them
+pub const POPULAR_CRATES: [&str; 20] = [
+    "serde", "serde_json", "tokio", "futures", "anyhow", "thiserror", "chrono", "time", "regex", "rand",
+    "reqwest", "hyper", "axum", "clap", "tracing", "log", "bytes", "uuid", "itertools", "once_cell",
+];
+
+/// Service `search_crates` queries
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SearchBackend {
+    /// The crates.io search API (results as `CrateSearchResults` JSON)
+    #[default]
+    CratesIo,
+    /// lib.rs, which ranks by relevance and popularity rather than name match
+    LibRs,
+}
+
+impl SearchBackend {
+    pub fn parse(backend: &str) -> Option<Self> {
+        match backend {
+            "crates-io" => Some(Self::CratesIo),
+            "lib-rs" => Some(Self::LibRs),
+            _ => None,
+        }
+    }
+}
+
+/// What `search_crates` returns for the crates.io backend, whatever shape the API has
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CrateSearchResults {
+    pub query: String,
+    /// Number of crates matching the query, of which `crates` are the first
+    pub total: Option<u64>,
+    pub crates: Vec<CrateSearchResult>,
+}
+
+/// One crate found by a crates.io search
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct CrateSearchResult {
+    pub name: String,
+    pub description: Option<String>,
+    /// Newest stable version, or the newest version if there is no stable one
+    pub version: Option<String>,
+    /// All-time downloads
+    pub downloads: Option<u64>,
+    /// The crate's documentation on docs.rs
+    pub docs_url: String,
+    pub repository: Option<String>,
+}
+
+/// Read a crates.io search API response, `None` when it isn't one
+pub fn parse_crates_io_results(query: &str, json: &str) -> Option<CrateSearchResults> {
+    let value = serde_json::from_str::<Value>(json).ok()?;
+    let text = |krate: &Value, field: &str| {
+        krate
+            .get(field)
+            .and_then(|v| v.as_str())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let crates = value
+        .get("crates")?
+        .as_array()?
+        .iter()
+        .filter_map(|krate| {
+            let name = text(krate, "name")?;
+            let version = text(krate, "max_stable_version")
+                .or_else(|| text(krate, "max_version"))
+                .or_else(|| text(krate, "newest_version"));
+            Some(CrateSearchResult {
+                docs_url: format!("https://docs.rs/{}/{}", name, version.as_deref().unwrap_or("latest")),
+                description: text(krate, "description"),
+                downloads: krate.get("downloads").and_then(|v| v.as_u64()),
+                repository: text(krate, "repository"),
+                name,
+                version,
+            })
+        })
+        .collect();
+    Some(CrateSearchResults {
+        query: query.to_string(),
+        total: value.get("meta").and_then(|meta| meta.get("total")).and_then(|v| v.as_u64()),
+        crates,
+    })
+}
+
+/// One crate from a lib.rs search results page
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LibRsCrate {
+    pub name: String,
+    pub description: String,
+    pub version: Option<String>,
+    pub categories: Vec<String>,
+}
+
+/// Read the result list of a lib.rs search page, in ranking order
+pub fn parse_lib_rs_results(html: &str, limit: usize) -> Vec<LibRsCrate> {
+    let document = Html::parse_document(html);
+    let (Ok(result), Ok(name), Ok(description), Ok(version), Ok(category)) = (
+        Selector::parse("main li > a[href^=\"/crates/\"]"),
+        Selector::parse("h4"),
+        Selector::parse(".desc"),
+        Selector::parse(".version"),
+        Selector::parse(".category"),
+    ) else {
+        return Vec::new();
+    };
+    let text = |element: scraper::ElementRef| element.text().collect::<String>().trim().to_string();
+
+    document
+        .select(&result)
+        .filter_map(|link| {
+            let crate_name = link
+                .select(&name)
+                .next()
+                .map(text)
+                .or_else(|| link.value().attr("href")?.strip_prefix("/crates/").map(str::to_string))?;
+            Some(LibRsCrate {
+                name: crate_name,
+                description: link.select(&description).next().map(text).unwrap_or_default(),
+                version: link
+                    .select(&version)
+                    .next()
+                    .map(text)
+                    .map(|v| v.trim_start_matches('v').to_string()),
+                categories: link.select(&category).map(text).collect(),
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+/// Render lib.rs results as a markdown list
+pub fn format_lib_rs_results(query: &str, results: &[LibRsCrate]) -> String {
+    let mut markdown = format!("# lib.rs results for \"{}\"\n\n", query);
+    if results.is_empty() {
+        markdown.push_str("No crates found.\n");
+        return markdown;
+    }
+    for (rank, krate) in results.iter().enumerate() {
+        markdown.push_str(&format!("{}. **{}**", rank + 1, krate.name));
+        if let Some(version) = &krate.version {
+            markdown.push_str(&format!(" {}", version));
+        }
+        if !krate.description.is_empty() {
+            markdown.push_str(&format!(" — {}", krate.description));
+        }
+        if !krate.categories.is_empty() {
+            markdown.push_str(&format!(" ({})", krate.categories.join(", ")));
+        }
+        markdown.push_str(&format!("\n   https://lib.rs/crates/{}\n", krate.name));
+    }
+    markdown
+}
+
+/// Crate names from a crates.io search response, in ranking order
+pub fn parse_search_crate_names(json: &str) -> Vec<String> {
+    let Ok(value) = serde_json::from_str::<Value>(json) else {
+        return Vec::new();
+    };
+    value
+        .get("crates")
+        .and_then(|crates| crates.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|krate| krate.get("name")?.as_str())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Render the items named `name` found in each crate's index as a markdown list
+pub fn format_definitions(name: &str, definitions: &[(String, Vec<IndexItem>)], searched: usize) -> String {
+    let mut markdown = format!("# Crates defining `{}`\n\n", name);
+    if definitions.is_empty() {
+        markdown.push_str(&format!("No item named `{}` found in the {} crates searched.\n", name, searched));
+        return markdown;
+    }
+    for (crate_name, items) in definitions {
+        for item in items {
+            markdown.push_str(&format!("- `{}` ({}) in crate {}\n", item.path, item.kind, crate_name));
+        }
+    }
+    markdown.push_str(&format!("\nSearched {} crates.\n", searched));
+    markdown
+}
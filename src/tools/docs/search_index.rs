@@ -0,0 +1,305 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use scraper::{Html, Selector};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+/// How long a downloaded search index is reused before it is fetched again
+pub const DEFAULT_INDEX_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Default memory budget for cached search indexes (64 MiB)
+pub const DEFAULT_INDEX_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Page-level item kinds by rustdoc `ItemType` number; members (methods,
+/// fields, variants, associated items), impls and imports have no page of their own
+const ITEM_KINDS: [Option<&str>; 26] = [
+    Some("mod"),
+    None, // extern crate
+    None, // import
+    Some("struct"),
+    Some("enum"),
+    Some("fn"),
+    Some("type"),
+    Some("static"),
+    Some("trait"),
+    None, // impl
+    None, // required trait method
+    None, // method
+    None, // struct field
+    None, // enum variant
+    Some("macro"),
+    Some("primitive"),
+    None, // associated type
+    Some("constant"),
+    None, // associated constant
+    Some("union"),
+    Some("foreigntype"),
+    Some("keyword"),
+    None, // opaque type
+    Some("attr"),
+    Some("derive"),
+    Some("traitalias"),
+];
+
+/// An item with its own documentation page, as listed in a rustdoc search index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexItem {
+    /// Full path including the crate, e.g. `tokio::sync::mpsc::Sender`
+    pub path: String,
+    /// rustdoc page prefix, e.g. `struct` or `fn`
+    pub kind: &'static str,
+}
+
+impl IndexItem {
+    pub fn name(&self) -> &str {
+        self.path.rsplit("::").next().unwrap_or(&self.path)
+    }
+
+    /// Page path below the crate root, e.g. `sync/mpsc/struct.Sender.html`
+    pub fn page_path(&self) -> String {
+        let mut segments: Vec<&str> = self.path.split("::").skip(1).collect();
+        if self.kind == "mod" {
+            segments.push("index.html");
+            return segments.join("/");
+        }
+        let name = segments.pop().unwrap_or_default();
+        segments.push("");
+        format!("{}{}.{}.html", segments.join("/"), self.kind, name)
+    }
+}
+
+/// The items of one crate from its rustdoc search index
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    items: Vec<IndexItem>,
+}
+
+impl SearchIndex {
+    /// Parse the `search-index.js` docs.rs serves for a crate
+    ///
+    /// Understands the `JSON.parse('…')` layouts rustdoc has written since 1.52,
+    /// both the object and the `new Map([…])` form, with item types as a number
+    /// array or a letter string and paths as a dense or `[index, path]` list.
+    /// Returns `None` for other layouts.
+    pub fn parse(js: &str, crate_name: &str) -> Option<Self> {
+        let crate_ident = crate_name.replace('-', "_");
+        let json: Value = serde_json::from_str(&js_string_argument(js, "JSON.parse(")?).ok()?;
+        let corpus = match &json {
+            Value::Object(crates) => crates.get(&crate_ident)?,
+            Value::Array(crates) => crates
+                .iter()
+                .find(|entry| entry.get(0).and_then(|v| v.as_str()) == Some(crate_ident.as_str()))?
+                .get(1)?,
+            _ => return None,
+        };
+
+        let names = corpus.get("n")?.as_array()?;
+        let types: Vec<usize> = match corpus.get("t")? {
+            Value::String(letters) => letters.bytes().map(|b| b.saturating_sub(b'A') as usize).collect(),
+            Value::Array(numbers) => numbers.iter().map(|n| n.as_u64().unwrap_or(u64::MAX) as usize).collect(),
+            _ => return None,
+        };
+        let module_paths = module_paths(corpus.get("q"), names.len());
+
+        let items = names
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let name = name.as_str().filter(|name| !name.is_empty())?;
+                let kind = (*ITEM_KINDS.get(*types.get(i)?)?)?;
+                let module = module_paths.get(i).map(String::as_str).filter(|m| !m.is_empty()).unwrap_or(crate_ident.as_str());
+                Some(IndexItem {
+                    path: format!("{}::{}", module, name),
+                    kind,
+                })
+            })
+            .collect();
+        Some(Self { items })
+    }
+
+    pub fn items(&self) -> &[IndexItem] {
+        &self.items
+    }
+
+    /// Items whose name is `name`, in index order
+    pub fn find(&self, name: &str) -> Vec<&IndexItem> {
+        self.items.iter().filter(|item| item.name() == name).collect()
+    }
+
+    /// Approximate memory held by the index, for cache accounting
+    pub fn size_bytes(&self) -> usize {
+        self.items
+            .iter()
+            .map(|item| item.path.len() + std::mem::size_of::<IndexItem>())
+            .sum()
+    }
+}
+
+// Module path of every item: `q` is either one entry per item ("" repeats the
+// previous path) or sparse `[index, path]` pairs that apply until the next pair
+fn module_paths(paths: Option<&Value>, len: usize) -> Vec<String> {
+    let mut resolved = Vec::with_capacity(len);
+    let Some(Value::Array(paths)) = paths else {
+        return resolved;
+    };
+    let mut current = String::new();
+    if paths.iter().all(|entry| entry.is_string()) {
+        for entry in paths {
+            let path = entry.as_str().unwrap_or_default();
+            if !path.is_empty() {
+                current = path.to_string();
+            }
+            resolved.push(current.clone());
+        }
+        return resolved;
+    }
+
+    let mut changes = paths
+        .iter()
+        .filter_map(|pair| Some((pair.get(0)?.as_u64()? as usize, pair.get(1)?.as_str()?)))
+        .peekable();
+    for i in 0..len {
+        while let Some((_, path)) = changes.next_if(|(index, _)| *index <= i) {
+            current = path.to_string();
+        }
+        resolved.push(current.clone());
+    }
+    resolved
+}
+
+// The single-quoted string passed to `call` (e.g. `JSON.parse(`), unescaped
+fn js_string_argument(js: &str, call: &str) -> Option<String> {
+    let start = js.find(call)? + call.len();
+    let mut chars = js[start..].chars();
+    if chars.next()? != '\'' {
+        return None;
+    }
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '\'' => return Some(value),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// File name of the search index a rustdoc page loads, relative to the
+/// version root (e.g. `search-index-20240101-1.77.0-nightly.js`)
+pub fn search_index_file(page_html: &str) -> String {
+    let document = Html::parse_document(page_html);
+    let vars = Selector::parse("meta[name=\"rustdoc-vars\"]")
+        .ok()
+        .and_then(|selector| document.select(&selector).next());
+    let Some(vars) = vars else {
+        return "search-index.js".to_string();
+    };
+    // Some rustdoc versions write the path relative to the page; only the file name matters
+    if let Some(file) = vars.value().attr("data-search-index-js") {
+        return file.rsplit('/').next().unwrap_or(file).to_string();
+    }
+    let suffix = vars.value().attr("data-resource-suffix").unwrap_or_default();
+    format!("search-index{}.js", suffix)
+}
+
+struct CachedIndex {
+    index: Arc<SearchIndex>,
+    bytes: usize,
+    inserted: Instant,
+}
+
+#[derive(Default)]
+struct IndexEntries {
+    entries: HashMap<String, CachedIndex>,
+    total_bytes: usize,
+}
+
+/// Parsed search indexes keyed by crate and version
+///
+/// Kept apart from `DocCache` because indexes are structured data shared by
+/// several lookups, expire on their own schedule and can be large, so the
+/// cache tracks their size and evicts the oldest indexes past its budget.
+#[derive(Clone)]
+pub struct SearchIndexCache {
+    inner: Arc<Mutex<IndexEntries>>,
+    ttl: Duration,
+    max_bytes: usize,
+}
+
+impl Default for SearchIndexCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_INDEX_TTL, DEFAULT_INDEX_CACHE_BYTES)
+    }
+}
+
+impl SearchIndexCache {
+    pub fn new(ttl: Duration, max_bytes: usize) -> Self {
+        Self {
+            inner: Arc::default(),
+            ttl,
+            max_bytes,
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Arc<SearchIndex>> {
+        let mut inner = self.inner.lock().await;
+        match inner.entries.get(key) {
+            Some(cached) if cached.inserted.elapsed() < self.ttl => Some(cached.index.clone()),
+            Some(_) => {
+                if let Some(expired) = inner.entries.remove(key) {
+                    inner.total_bytes -= expired.bytes;
+                }
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache an index, evicting the oldest ones if the budget would be exceeded
+    ///
+    /// An index larger than the whole budget is not cached.
+    pub async fn set(&self, key: String, index: Arc<SearchIndex>) {
+        let bytes = index.size_bytes();
+        if bytes > self.max_bytes {
+            tracing::debug!("Search index {} ({} bytes) exceeds the index cache budget", key, bytes);
+            return;
+        }
+
+        let mut inner = self.inner.lock().await;
+        if let Some(previous) = inner.entries.remove(&key) {
+            inner.total_bytes -= previous.bytes;
+        }
+        while inner.total_bytes + bytes > self.max_bytes {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.inserted)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = inner.entries.remove(&oldest) {
+                inner.total_bytes -= evicted.bytes;
+            }
+        }
+        inner.total_bytes += bytes;
+        inner.entries.insert(
+            key,
+            CachedIndex {
+                index,
+                bytes,
+                inserted: Instant::now(),
+            },
+        );
+    }
+
+    /// Memory currently accounted to cached indexes
+    pub async fn total_bytes(&self) -> usize {
+        self.inner.lock().await.total_bytes
+    }
+}
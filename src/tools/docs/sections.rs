@@ -0,0 +1,227 @@
+use std::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+use super::signature::format_signature;
+
+/// Section names that stand for headings with other titles, e.g. the methods of a
+/// type are listed under "Implementations"
+const SECTION_ALIASES: [(&str, &[&str]); 3] = [
+    ("methods", &["implementations", "required methods", "provided methods"]),
+    ("impls", &["implementations", "trait implementations"]),
+    ("trait impls", &["trait implementations"]),
+];
+
+/// Titles of rustdoc's sections listing impls it generates for every type (the last
+/// one is followed by the `Deref` target, e.g. "Methods from Deref<Target = str>")
+const GENERATED_IMPL_SECTIONS: [&str; 3] = ["auto trait implementations", "blanket implementations", "methods from deref"];
+
+/// A heading of a markdown page
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Heading {
+    /// 1 for `#`, up to 6
+    pub level: usize,
+    pub title: String,
+    /// Fragment of the `#anchor` link in the heading, as rustdoc gives its section headings
+    pub anchor: Option<String>,
+    // The heading and everything below it up to the next heading of the same or a higher level
+    range: Range<usize>,
+    // Where the heading itself ends
+    heading_end: usize,
+}
+
+impl Heading {
+    /// The section the heading starts, heading included
+    pub fn section<'a>(&self, markdown: &'a str) -> &'a str {
+        markdown[self.range.clone()].trim_end()
+    }
+
+    /// The section without its heading
+    pub fn body<'a>(&self, markdown: &'a str) -> &'a str {
+        markdown[self.heading_end..self.range.end].trim()
+    }
+}
+
+/// The headings of a markdown page in page order, both ATX (`## Title`) and setext
+/// (`Title` underlined), skipping lines of code blocks that merely look like headings
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut current: Option<Heading> = None;
+    for (event, range) in Parser::new_ext(markdown, Options::ENABLE_TABLES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                current = Some(Heading {
+                    level: level as usize,
+                    title: String::new(),
+                    anchor: None,
+                    range: range.start..markdown.len(),
+                    heading_end: range.end,
+                });
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                if let Some(heading) = current.as_mut().filter(|heading| heading.anchor.is_none()) {
+                    heading.anchor = dest_url.strip_prefix('#').map(str::to_string);
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = current.as_mut() {
+                    heading.title.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(mut heading) = current.take() {
+                    // Drop rustdoc's `§` self-link
+                    heading.title = heading.title.replace('§', "").trim().to_string();
+                    headings.push(heading);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for i in 0..headings.len() {
+        let level = headings[i].level;
+        if let Some(next) = headings[i + 1..].iter().find(|heading| heading.level <= level) {
+            headings[i].range.end = next.range.start;
+        }
+    }
+    headings
+}
+
+/// Everything before the first heading, such as the version and source lines of a lookup
+pub fn preamble(markdown: &str) -> &str {
+    let end = headings(markdown).first().map_or(markdown.len(), |heading| heading.range.start);
+    markdown[..end].trim_end()
+}
+
+/// The opening of a page: everything before its second heading, which for an item
+/// page is the lookup's version lines, the item's title, declaration and summary
+pub fn page_lead(markdown: &str) -> &str {
+    let end = headings(markdown).get(1).map_or(markdown.len(), |heading| heading.range.start);
+    markdown[..end].trim_end()
+}
+
+/// The declaration of the item an item page documents: the first code block of the
+/// page's opening (see `page_lead`), e.g. `pub struct Sender<T> { /* private fields */ }`,
+/// with long lines reflowed by `format_signature`
+pub fn declaration(markdown: &str) -> Option<String> {
+    let mut code: Option<String> = None;
+    for event in Parser::new_ext(page_lead(markdown), Options::ENABLE_TABLES) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => code = Some(String::new()),
+            Event::Text(text) => {
+                if let Some(code) = code.as_mut() {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => return code.map(|code| format_signature(code.trim_end())),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The page with the long lines of its declaration (see `declaration`) reflowed by
+/// `format_signature`; declarations in indented rather than fenced code blocks are
+/// left as they are
+pub fn reflow_declaration(markdown: &str) -> String {
+    let lead = page_lead(markdown);
+    let mut code: Option<Range<usize>> = None;
+    for (event, range) in Parser::new_ext(lead, Options::ENABLE_TABLES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_))) => code = Some(range.end..range.end),
+            Event::Start(Tag::CodeBlock(_)) => break,
+            Event::Text(_) => {
+                if let Some(code) = code.as_mut() {
+                    if code.is_empty() {
+                        code.start = range.start;
+                    }
+                    code.end = range.end;
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => break,
+            _ => {}
+        }
+    }
+    match code.filter(|code| !code.is_empty()) {
+        Some(code) => {
+            let source = &markdown[code.clone()];
+            let newline = if source.ends_with('\n') { "\n" } else { "" };
+            format!("{}{}{}{}", &markdown[..code.start], format_signature(source.trim_end()), newline, &markdown[code.end..])
+        }
+        None => markdown.to_string(),
+    }
+}
+
+/// The headings of a page as a nested list, each followed by its `#anchor` when it has one
+pub fn format_outline(markdown: &str) -> String {
+    let headings = headings(markdown);
+    let Some(top) = headings.iter().map(|heading| heading.level).min() else {
+        return "The page has no headings\n".to_string();
+    };
+    let mut out = String::new();
+    for heading in &headings {
+        out.push_str(&"  ".repeat(heading.level - top));
+        out.push_str("- ");
+        out.push_str(&heading.title);
+        if let Some(anchor) = &heading.anchor {
+            out.push_str(&format!(" (#{})", anchor));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The sections of a page whose heading matches `name`, joined in page order
+///
+/// Titles match case-insensitively and regardless of plural, so "example" finds
+/// "Examples"; a few aliases cover rustdoc's titles (see `SECTION_ALIASES`).
+/// Sections nested in an earlier match are part of it and not repeated.
+pub fn select_sections(markdown: &str, name: &str) -> Option<String> {
+    let wanted = normalize_title(name);
+    let aliases: Vec<String> = SECTION_ALIASES
+        .iter()
+        .filter(|(alias, _)| normalize_title(alias) == wanted)
+        .flat_map(|(_, titles)| titles.iter().map(|title| normalize_title(title)))
+        .collect();
+
+    let mut sections: Vec<&str> = Vec::new();
+    let mut covered_until = 0;
+    for heading in headings(markdown) {
+        if heading.range.start < covered_until {
+            continue;
+        }
+        let title = normalize_title(&heading.title);
+        if title == wanted || aliases.contains(&title) {
+            sections.push(heading.section(markdown));
+            covered_until = heading.range.end;
+        }
+    }
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// The page without the sections of auto trait impls, blanket impls and methods
+/// reached through `Deref`, which rarely matter but often make up most of an item page
+pub fn without_generated_impls(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut kept_from = 0;
+    for heading in headings(markdown) {
+        if heading.range.start < kept_from {
+            continue;
+        }
+        let title = heading.title.to_lowercase();
+        if GENERATED_IMPL_SECTIONS.iter().any(|generated| title.starts_with(generated)) {
+            out.push_str(&markdown[kept_from..heading.range.start]);
+            kept_from = heading.range.end;
+        }
+    }
+    out.push_str(&markdown[kept_from..]);
+    out
+}
+
+// Lowercased, with single spaces, no trailing colon and no plural `s`
+fn normalize_title(title: &str) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let title = title.trim_end_matches(':');
+    title.strip_suffix('s').unwrap_or(title).to_string()
+}
@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value;
+
+/// Item kinds (as named in rustdoc JSON `paths`) whose removal breaks users
+const PUBLIC_ITEM_KINDS: [&str; 12] = [
+    "module", "struct", "enum", "union", "trait", "function", "type_alias", "constant", "static",
+    "macro", "proc_attribute", "proc_derive",
+];
+
+/// A semver rule broken between two versions, named after the matching
+/// cargo-semver-checks lint where there is one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: String,
+    pub path: String,
+    pub detail: String,
+}
+
+/// The parts of a crate's public API the comparison looks at
+#[derive(Debug, Default)]
+pub struct PublicApi {
+    items: BTreeMap<String, ApiItem>,
+}
+
+#[derive(Debug, Default)]
+struct ApiItem {
+    kind: String,
+    /// Number of parameters, for functions
+    params: Option<usize>,
+    /// Variant names, public field names or trait method names, depending on the kind
+    members: BTreeSet<String>,
+    non_exhaustive: bool,
+}
+
+impl PublicApi {
+    /// Read the public API out of a rustdoc JSON document
+    pub fn from_rustdoc_json(json: &str) -> Result<Self, String> {
+        let doc: Value = serde_json::from_str(json).map_err(|e| format!("Invalid rustdoc JSON: {}", e))?;
+        let index = doc.get("index").and_then(|v| v.as_object()).ok_or("rustdoc JSON has no index")?;
+        let paths = doc.get("paths").and_then(|v| v.as_object()).ok_or("rustdoc JSON has no paths")?;
+
+        let mut items = BTreeMap::new();
+        for (id, summary) in paths {
+            // crate_id 0 is the documented crate; everything else is a dependency
+            if summary.get("crate_id").and_then(|v| v.as_u64()) != Some(0) {
+                continue;
+            }
+            let Some(kind) = summary.get("kind").and_then(|v| v.as_str()) else { continue };
+            if !PUBLIC_ITEM_KINDS.contains(&kind) {
+                continue;
+            }
+            let Some(path) = summary.get("path").and_then(|v| v.as_array()) else { continue };
+            let path: Vec<&str> = path.iter().filter_map(|segment| segment.as_str()).collect();
+
+            let item = index.get(id);
+            if item.is_some_and(|item| item.get("visibility").and_then(|v| v.as_str()) != Some("public")) {
+                continue;
+            }
+            items.insert(path.join("::"), ApiItem::new(kind, item, index));
+        }
+
+        Ok(Self { items })
+    }
+
+    /// List the breaking changes from `self` (the old version) to `new`
+    pub fn breaking_changes(&self, new: &PublicApi) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (path, old_item) in &self.items {
+            let Some(new_item) = new.items.get(path) else {
+                violations.push(Violation {
+                    rule: format!("{}_missing", old_item.kind),
+                    path: path.clone(),
+                    detail: format!("{} was removed or is no longer public", old_item.kind.replace('_', " ")),
+                });
+                continue;
+            };
+            old_item.compare(path, new_item, &mut violations);
+        }
+        violations
+    }
+}
+
+impl ApiItem {
+    fn new(kind: &str, item: Option<&Value>, index: &serde_json::Map<String, Value>) -> Self {
+        let mut api_item = Self {
+            kind: kind.to_string(),
+            ..Self::default()
+        };
+        let Some(item) = item else { return api_item };
+        let inner = item.get("inner");
+        let member_names = |ids: Option<&Value>, public_only: bool| -> BTreeSet<String> {
+            ids.and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|id| index.get(&id_key(id)))
+                .filter(|member| !public_only || member.get("visibility").and_then(|v| v.as_str()) == Some("public"))
+                .filter_map(|member| member.get("name").and_then(|v| v.as_str()))
+                .map(|name| name.to_string())
+                .collect()
+        };
+
+        // Attributes are strings in older format versions and objects in newer ones
+        api_item.non_exhaustive = item
+            .get("attrs")
+            .is_some_and(|attrs| attrs.to_string().contains("non_exhaustive"));
+
+        match kind {
+            "function" => {
+                let function = inner.and_then(|v| v.get("function"));
+                // `sig` replaced `decl` in newer format versions
+                let signature = function.and_then(|f| f.get("sig").or_else(|| f.get("decl")));
+                api_item.params = signature
+                    .and_then(|sig| sig.get("inputs"))
+                    .and_then(|inputs| inputs.as_array())
+                    .map(|inputs| inputs.len());
+            }
+            "enum" => {
+                let variants = inner.and_then(|v| v.get("enum")).and_then(|e| e.get("variants"));
+                api_item.members = member_names(variants, false);
+            }
+            "struct" => {
+                let fields = inner
+                    .and_then(|v| v.get("struct"))
+                    .and_then(|s| s.get("kind"))
+                    .and_then(|k| k.get("plain"))
+                    .and_then(|p| p.get("fields"));
+                api_item.members = member_names(fields, true);
+            }
+            "trait" => {
+                let trait_items = inner.and_then(|v| v.get("trait")).and_then(|t| t.get("items"));
+                api_item.members = member_names(trait_items, false);
+            }
+            _ => {}
+        }
+        api_item
+    }
+
+    fn compare(&self, path: &str, new: &ApiItem, violations: &mut Vec<Violation>) {
+        let mut push = |rule: &str, detail: String| {
+            violations.push(Violation {
+                rule: rule.to_string(),
+                path: path.to_string(),
+                detail,
+            })
+        };
+
+        if self.kind != new.kind {
+            push(
+                &format!("{}_missing", self.kind),
+                format!("{} became a {}", self.kind.replace('_', " "), new.kind.replace('_', " ")),
+            );
+            return;
+        }
+
+        let (member_missing, member_label) = match self.kind.as_str() {
+            "enum" => ("enum_variant_missing", "variant"),
+            "struct" => ("struct_pub_field_missing", "public field"),
+            "trait" => ("trait_method_missing", "item"),
+            _ => ("", ""),
+        };
+        for removed in self.members.difference(&new.members) {
+            push(member_missing, format!("{} `{}` was removed", member_label, removed));
+        }
+
+        if let (Some(old), Some(new_params)) = (self.params, new.params) {
+            if old != new_params {
+                push(
+                    "function_parameter_count_changed",
+                    format!("takes {} parameters instead of {}", new_params, old),
+                );
+            }
+        }
+
+        if self.kind == "enum" && !self.non_exhaustive {
+            for added in new.members.difference(&self.members) {
+                push(
+                    "enum_variant_added",
+                    format!("variant `{}` was added to an exhaustive enum", added),
+                );
+            }
+        }
+
+        if matches!(self.kind.as_str(), "enum" | "struct") && !self.non_exhaustive && new.non_exhaustive {
+            push(
+                &format!("{}_marked_non_exhaustive", self.kind),
+                "is now #[non_exhaustive]".to_string(),
+            );
+        }
+    }
+}
+
+// Ids are integers in newer format versions and strings in older ones
+fn id_key(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Render a breaking-change report as markdown
+pub fn format_report(crate_name: &str, from: &str, to: &str, violations: &[Violation]) -> String {
+    let mut report = format!("# Semver check: {} {} → {}\n\n", crate_name, from, to);
+    if violations.is_empty() {
+        report.push_str(
+            "No breaking changes found in the public API.\n\n\
+            This compares rustdoc JSON item by item and covers removed items, removed variants, \
+            fields and trait items, changed function arity and exhaustiveness changes; it does not \
+            check every cargo-semver-checks rule.\n",
+        );
+        return report;
+    }
+
+    report.push_str(&format!("{} breaking change(s) found:\n\n", violations.len()));
+    for violation in violations {
+        report.push_str(&format!(
+            "- `{}` — `{}`: {}\n",
+            violation.rule, violation.path, violation.detail
+        ));
+    }
+    report
+}
@@ -0,0 +1,92 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use super::fetcher::is_std_crate;
+use super::plain_text::OutputFormat;
+
+/// Bytes of output a token stands for under a token budget, a rough average for
+/// English prose and Rust code
+pub const BYTES_PER_TOKEN: usize = 4;
+
+/// Defaults a client set for its session with `configure_session`, filled into the
+/// calls that don't set them themselves
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionSettings {
+    /// Channel of std, core and alloc lookups given no version
+    pub channel: Option<String>,
+    /// Format of results given no format
+    pub format: Option<OutputFormat>,
+    /// Whether `lookup_crate` and `lookup_item` return only the opening of a page
+    pub summary: bool,
+    /// Rough cap on the size of each result, in tokens
+    pub token_budget: Option<usize>,
+}
+
+impl SessionSettings {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fill the settings into the arguments of a tool call where the tool's `schema`
+    /// takes them and the call doesn't give them
+    pub fn apply(&self, schema: &Value, arguments: &mut Value) {
+        let accepts = |property: &str| schema.get("properties").and_then(|p| p.get(property)).is_some();
+        let Some(arguments) = arguments.as_object_mut() else {
+            return;
+        };
+        let std_crate = arguments.get("crate_name").and_then(|v| v.as_str()).is_some_and(is_std_crate);
+        let unversioned = !arguments.contains_key("version") && !arguments.contains_key("channel");
+        if let Some(channel) = self.channel.as_ref().filter(|_| std_crate && unversioned && accepts("channel")) {
+            arguments.insert("channel".to_string(), json!(channel));
+        }
+        if let Some(format) = self.format.filter(|_| !arguments.contains_key("format") && accepts("format")) {
+            arguments.insert("format".to_string(), json!(format.as_str()));
+        }
+        // A section asked for is returned whole
+        let whole_page = arguments.contains_key("summary") || arguments.contains_key("section");
+        if self.summary && !whole_page && accepts("summary") {
+            arguments.insert("summary".to_string(), json!(true));
+        }
+    }
+
+    /// Largest result in bytes the token budget allows, within `max_content_bytes`
+    pub fn max_content_bytes(&self, max_content_bytes: Option<usize>) -> Option<usize> {
+        let budget = self.token_budget.map(|tokens| tokens.saturating_mul(BYTES_PER_TOKEN));
+        match (budget, max_content_bytes) {
+            (Some(budget), Some(max)) => Some(budget.min(max)),
+            (budget, max) => budget.or(max),
+        }
+    }
+
+    /// The settings as JSON, as `configure_session` returns them
+    pub fn to_json(&self) -> Value {
+        json!({
+            "channel": self.channel,
+            "format": self.format.unwrap_or_default().as_str(),
+            "summary": self.summary,
+            "token_budget": self.token_budget,
+        })
+    }
+}
+
+/// The settings of one client session
+///
+/// Clones share the settings, so every clone of a session's router sees what the
+/// session configured.
+#[derive(Clone, Debug, Default)]
+pub struct SessionDefaults(Arc<Mutex<SessionSettings>>);
+
+impl SessionDefaults {
+    pub fn get(&self) -> SessionSettings {
+        self.lock().clone()
+    }
+
+    pub fn set(&self, settings: SessionSettings) {
+        *self.lock() = settings;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SessionSettings> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
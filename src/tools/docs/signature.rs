@@ -0,0 +1,175 @@
+/// Signature lines longer than this many bytes are reflowed
+pub const MAX_SIGNATURE_WIDTH: usize = 100;
+
+/// Indentation added for each level a reflowed signature nests
+const INDENT: &str = "    ";
+
+/// Reflow the long lines of a signature into multi-line Rust, roughly as rustfmt
+/// lays it out: parameters and generics one per line, and a `where` clause below
+/// the signature with one predicate per line
+///
+/// rustdoc renders most declarations on one line, however many generics and bounds
+/// they have. Lines that fit in `MAX_SIGNATURE_WIDTH` are kept as they are, so
+/// declarations rustdoc already wrapped (e.g. struct fields) don't change.
+pub fn format_signature(signature: &str) -> String {
+    signature
+        .lines()
+        .map(|line| {
+            if line.len() <= MAX_SIGNATURE_WIDTH {
+                line.to_string()
+            } else {
+                reflow(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Reflow one line of a signature, keeping its indentation
+fn reflow(line: &str) -> String {
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let text = line.trim();
+    // A body (`{ /* private fields */ }`) or `;` may follow the signature
+    let (signature, tail) = match find_open(text, '{') {
+        Some(body) => (text[..body].trim_end(), &text[body..]),
+        None => match text.strip_suffix(';') {
+            Some(signature) => (signature, ";"),
+            None => (text, ""),
+        },
+    };
+    let (head, predicates) = match find_top_level(signature, " where ") {
+        Some(start) => (&signature[..start], Some(&signature[start + " where ".len()..])),
+        None => (signature, None),
+    };
+
+    let mut output = reflow_head(head, indent);
+    match predicates {
+        Some(predicates) => {
+            output.push('\n');
+            output.push_str(indent);
+            output.push_str("where");
+            let predicates: Vec<&str> = split_top_level(predicates).into_iter().filter(|p| !p.is_empty()).collect();
+            for (index, predicate) in predicates.iter().enumerate() {
+                output.push('\n');
+                output.push_str(indent);
+                output.push_str(INDENT);
+                output.push_str(predicate);
+                // rustfmt ends the clause of a bodiless item with its `;`
+                output.push(if tail == ";" && index + 1 == predicates.len() { ';' } else { ',' });
+            }
+            if !tail.is_empty() && tail != ";" {
+                output.push('\n');
+                output.push_str(indent);
+                output.push_str(tail);
+            }
+        }
+        None if tail == ";" => output.push(';'),
+        None if !tail.is_empty() => {
+            output.push(' ');
+            output.push_str(tail);
+        }
+        None => {}
+    }
+    output
+}
+
+// The part of a signature before its `where` clause, with its parameters one per line
+// when it's too long, and its generics too when what comes before the parameters is
+fn reflow_head(head: &str, indent: &str) -> String {
+    if indent.len() + head.len() <= MAX_SIGNATURE_WIDTH {
+        return format!("{}{}", indent, head);
+    }
+    let params = find_open(head, '(');
+    let (before, params) = match params {
+        Some(start) => (&head[..start], &head[start..]),
+        None => (head, ""),
+    };
+    let before = match find_open(before, '<') {
+        Some(generics) if params.is_empty() || indent.len() + before.len() > MAX_SIGNATURE_WIDTH => {
+            break_list(before, generics, indent).unwrap_or_else(|| before.to_string())
+        }
+        _ => before.to_string(),
+    };
+    let params = match params {
+        "" => String::new(),
+        params => break_list(params, 0, indent).unwrap_or_else(|| params.to_string()),
+    };
+    format!("{}{}{}", indent, before, params)
+}
+
+// `text` with the items of the bracketed list opening at `open` one per line, or
+// `None` when the list is empty
+fn break_list(text: &str, open: usize, indent: &str) -> Option<String> {
+    let close = matching_close(text, open)?;
+    let items: Vec<&str> = split_top_level(&text[open + 1..close]).into_iter().filter(|item| !item.is_empty()).collect();
+    if items.is_empty() {
+        return None;
+    }
+    let mut output = text[..=open].to_string();
+    for item in items {
+        output.push('\n');
+        output.push_str(indent);
+        output.push_str(INDENT);
+        output.push_str(item);
+        output.push(',');
+    }
+    output.push('\n');
+    output.push_str(indent);
+    output.push_str(&text[close..]);
+    Some(output)
+}
+
+// How the nesting depth changes at byte `index` of `text`; the `>` of `->` closes nothing
+fn depth_change(text: &str, index: usize) -> i32 {
+    match text.as_bytes()[index] {
+        b'(' | b'[' | b'{' | b'<' => 1,
+        b'>' if index > 0 && text.as_bytes()[index - 1] == b'-' => 0,
+        b')' | b']' | b'}' | b'>' => -1,
+        _ => 0,
+    }
+}
+
+// Byte offset of the first `pattern` outside any brackets
+fn find_top_level(text: &str, pattern: &str) -> Option<usize> {
+    let mut depth = 0;
+    for index in 0..text.len() {
+        if depth == 0 && text.is_char_boundary(index) && text[index..].starts_with(pattern) {
+            return Some(index);
+        }
+        depth += depth_change(text, index);
+    }
+    None
+}
+
+// Byte offset of the first `open` bracket outside any brackets
+fn find_open(text: &str, open: char) -> Option<usize> {
+    let mut buffer = [0; 4];
+    find_top_level(text, open.encode_utf8(&mut buffer))
+}
+
+// Byte offset of the bracket closing the one at `open`
+fn matching_close(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for index in open..text.len() {
+        depth += depth_change(text, index);
+        if depth == 0 {
+            return Some(index);
+        }
+    }
+    None
+}
+
+// The comma-separated items of `text` outside any brackets, trimmed
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for index in 0..text.len() {
+        if depth == 0 && text.as_bytes()[index] == b',' {
+            items.push(text[start..index].trim());
+            start = index + 1;
+        }
+        depth += depth_change(text, index);
+    }
+    items.push(text[start..].trim());
+    items
+}
@@ -0,0 +1,75 @@
+use serde_json::Value;
+
+/// How strictly the generated requirement pins the resolved version
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VersionPolicy {
+    /// `"1.0.200"`: Cargo's default caret requirement on the full version
+    #[default]
+    Caret,
+    /// `"1.0"`: any compatible release from this minor version on
+    Minor,
+    /// `"1"`: any release with this major version
+    Major,
+    /// `"=1.0.200"`: exactly this version
+    Exact,
+}
+
+impl VersionPolicy {
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy {
+            "caret" => Some(Self::Caret),
+            "minor" => Some(Self::Minor),
+            "major" => Some(Self::Major),
+            "exact" => Some(Self::Exact),
+            _ => None,
+        }
+    }
+
+    /// Turn a concrete version into a requirement string following this policy
+    ///
+    /// For `0.x` releases the minor version is the compatibility boundary, so
+    /// `Major` keeps it (`0.7.5` becomes `"0.7"`, not `"0"`).
+    pub fn requirement(self, version: &str) -> String {
+        let parts: Vec<&str> = version.split(['-', '+']).next().unwrap_or(version).split('.').collect();
+        match (self, parts.as_slice()) {
+            (Self::Exact, _) => format!("={}", version),
+            (Self::Minor, [major, minor, ..]) => format!("{}.{}", major, minor),
+            (Self::Major, ["0", minor, ..]) => format!("0.{}", minor),
+            (Self::Major, [major, ..]) => major.to_string(),
+            _ => version.to_string(),
+        }
+    }
+}
+
+/// Render the `[dependencies]` entry and matching `cargo add` command for a crate
+pub fn dependency_snippet(crate_name: &str, requirement: &str, features: &[String]) -> String {
+    let toml_line = if features.is_empty() {
+        format!("{} = \"{}\"", crate_name, requirement)
+    } else {
+        let quoted: Vec<String> = features.iter().map(|f| format!("\"{}\"", f)).collect();
+        format!(
+            "{} = {{ version = \"{}\", features = [{}] }}",
+            crate_name,
+            requirement,
+            quoted.join(", ")
+        )
+    };
+
+    let mut cargo_add = format!("cargo add {}@{}", crate_name, requirement);
+    if !features.is_empty() {
+        cargo_add.push_str(&format!(" --features {}", features.join(",")));
+    }
+
+    format!(
+        "```toml\n[dependencies]\n{}\n```\n\n```sh\n{}\n```\n",
+        toml_line, cargo_add
+    )
+}
+
+/// Feature names declared by a crate version, from a crates.io
+/// `crates/{name}/{version}` response (`None` if the response can't be read)
+pub fn parse_version_features(json: &str) -> Option<Vec<String>> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    let features = value.get("version")?.get("features")?.as_object()?;
+    Some(features.keys().cloned().collect())
+}
@@ -0,0 +1,297 @@
+use super::weight::format_size;
+
+/// Number of Rust files listed in the largest-files table of `source_stats`
+pub const LARGEST_FILES_SHOWN: usize = 10;
+
+/// Line and `unsafe` counts of one Rust source file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RustFileStats {
+    /// Path within the crate, e.g. `src/lib.rs`
+    pub path: String,
+    pub lines: usize,
+    /// Lines with code on them, leaving out blank lines and comments
+    pub code_lines: usize,
+    pub unsafe_blocks: usize,
+    pub unsafe_fns: usize,
+    pub unsafe_impls: usize,
+}
+
+impl RustFileStats {
+    fn unsafe_total(&self) -> usize {
+        self.unsafe_blocks + self.unsafe_fns + self.unsafe_impls
+    }
+}
+
+/// Approximate size of a crate version's published source
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceStats {
+    pub files: usize,
+    /// Total unpacked size of the files
+    pub bytes: u64,
+    pub rust_files: Vec<RustFileStats>,
+    pub has_build_script: bool,
+}
+
+/// Count the files, lines and `unsafe` uses in a `.crate` tar archive
+pub fn source_stats(archive: &[u8]) -> Result<SourceStats, String> {
+    let mut stats = SourceStats::default();
+    for (path, contents) in read_tar(archive)? {
+        // Everything sits under a `{name}-{version}/` directory
+        let path = path.split_once('/').map_or(path.as_str(), |(_, path)| path).to_string();
+        stats.files += 1;
+        stats.bytes += contents.len() as u64;
+        if path == "build.rs" {
+            stats.has_build_script = true;
+        }
+        if path.ends_with(".rs") {
+            stats.rust_files.push(rust_file_stats(path, &String::from_utf8_lossy(contents)));
+        }
+    }
+    Ok(stats)
+}
+
+// The regular files of a tar archive, with their paths
+fn read_tar(archive: &[u8]) -> Result<Vec<(String, &[u8])>, String> {
+    let field = |header: &[u8], start: usize, end: usize| {
+        let bytes = &header[start..end];
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..len]).into_owned()
+    };
+
+    let mut files = Vec::new();
+    // Paths too long for the header come in a PAX or GNU entry before the file
+    let mut long_path: Option<String> = None;
+    let mut offset = 0;
+    while offset + 512 <= archive.len() {
+        let header = &archive[offset..offset + 512];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size_field = field(header, 124, 136);
+        let size = usize::from_str_radix(size_field.trim(), 8)
+            .map_err(|_| format!("invalid entry size {:?} in the crate archive", size_field))?;
+        let start = offset + 512;
+        let contents = archive
+            .get(start..start + size)
+            .ok_or_else(|| "the crate archive is truncated".to_string())?;
+
+        let mut path = field(header, 0, 100);
+        if &header[257..262] == b"ustar" {
+            let prefix = field(header, 345, 500);
+            if !prefix.is_empty() {
+                path = format!("{}/{}", prefix, path);
+            }
+        }
+        match header[156] {
+            b'x' => {
+                long_path = String::from_utf8_lossy(contents)
+                    .lines()
+                    .find_map(|record| record.split_once(" path=").map(|(_, path)| path.to_string()));
+            }
+            b'L' => {
+                let len = contents.iter().position(|&b| b == 0).unwrap_or(contents.len());
+                long_path = Some(String::from_utf8_lossy(&contents[..len]).into_owned());
+            }
+            b'0' | 0 => files.push((long_path.take().unwrap_or(path), contents)),
+            _ => long_path = None,
+        }
+        offset = start + size.div_ceil(512) * 512;
+    }
+    Ok(files)
+}
+
+// Count the lines and `unsafe` uses of a Rust file
+fn rust_file_stats(path: String, source: &str) -> RustFileStats {
+    let code = strip_comments_and_strings(source);
+    let mut stats = RustFileStats {
+        path,
+        lines: source.lines().count(),
+        code_lines: code.lines().filter(|line| !line.trim().is_empty()).count(),
+        ..RustFileStats::default()
+    };
+
+    let tokens = tokens(&code);
+    for (i, token) in tokens.iter().enumerate() {
+        if *token != "unsafe" {
+            continue;
+        }
+        // `unsafe extern "C" fn` has had its ABI string emptied to `""`
+        let next = tokens[i + 1..].iter().find(|token| !matches!(**token, "extern" | "\"")).copied();
+        let extern_block = tokens.get(i + 1) == Some(&"extern");
+        match next {
+            Some("{") if !extern_block => stats.unsafe_blocks += 1,
+            Some("fn") => stats.unsafe_fns += 1,
+            Some("impl") => stats.unsafe_impls += 1,
+            _ => {}
+        }
+    }
+    stats
+}
+
+// Identifiers and single punctuation characters
+fn tokens(code: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in code.char_indices() {
+        let is_ident = c.is_alphanumeric() || c == '_';
+        match (is_ident, start) {
+            (true, None) => start = Some(i),
+            (true, Some(_)) => {}
+            (false, Some(s)) => {
+                tokens.push(&code[s..i]);
+                start = None;
+            }
+            (false, None) => {}
+        }
+        if !is_ident && !c.is_whitespace() {
+            tokens.push(&code[i..i + c.len_utf8()]);
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&code[s..]);
+    }
+    tokens
+}
+
+// Blank out comments and the contents of string and character literals, keeping
+// line breaks so line counts still hold
+fn strip_comments_and_strings(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let is_ident = |at: usize| chars[at].is_alphanumeric() || chars[at] == '_';
+        // Raw strings may be byte strings too, as in `br"..."`
+        let raw_start = match i {
+            0 => true,
+            1 => chars[0] == 'b' || !is_ident(0),
+            _ => !is_ident(i - 1) || (chars[i - 1] == 'b' && !is_ident(i - 2)),
+        };
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            // Block comments nest
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    if chars[i] == '\n' {
+                        out.push('\n');
+                    }
+                    i += 1;
+                }
+            }
+        } else if c == 'r' && raw_start && matches!(next, Some('#' | '"')) {
+            // Raw strings end at a quote followed by as many `#` as they opened with
+            let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+            if chars.get(i + 1 + hashes) != Some(&'"') {
+                out.push(c);
+                i += 1;
+                continue;
+            }
+            out.push('"');
+            i += hashes + 2;
+            while i < chars.len() {
+                if chars[i] == '"' && chars[i + 1..].iter().take(hashes).filter(|&&c| c == '#').count() == hashes {
+                    i += hashes + 1;
+                    break;
+                }
+                if chars[i] == '\n' {
+                    out.push('\n');
+                }
+                i += 1;
+            }
+            out.push('"');
+        } else if c == '"' {
+            out.push('"');
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&'\n') {
+                    out.push('\n');
+                }
+                i += 1;
+            }
+            out.push('"');
+            i += 1;
+        } else if c == '\'' && next == Some('\\') {
+            // An escaped character literal such as '\n', '\'' or '\u{1F600}'
+            i += 3;
+            while i < chars.len() && chars[i] != '\'' {
+                i += 1;
+            }
+            out.push_str("' '");
+            i += 1;
+        } else if c == '\'' && chars.get(i + 2) == Some(&'\'') {
+            out.push_str("' '");
+            i += 3;
+        } else {
+            // Anything else, lifetimes included, is kept as is
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Render source statistics as markdown
+pub fn format_source_stats(crate_name: &str, version: &str, stats: &SourceStats) -> String {
+    let lines: usize = stats.rust_files.iter().map(|file| file.lines).sum();
+    let code_lines: usize = stats.rust_files.iter().map(|file| file.code_lines).sum();
+    let blocks: usize = stats.rust_files.iter().map(|file| file.unsafe_blocks).sum();
+    let fns: usize = stats.rust_files.iter().map(|file| file.unsafe_fns).sum();
+    let impls: usize = stats.rust_files.iter().map(|file| file.unsafe_impls).sum();
+    let unsafe_files = stats.rust_files.iter().filter(|file| file.unsafe_total() > 0).count();
+
+    let mut markdown = format!(
+        "# Source statistics of {} {}\n\n\
+         Counted from the published `.crate` archive; the numbers are approximate.\n\n\
+         - Files: {} ({} Rust), {} unpacked\n\
+         - Lines of Rust: {} ({} of code, without blank lines and comments)\n\
+         - `unsafe`: {} blocks, {} functions, {} impls, in {} of {} Rust files\n\
+         - Build script: {}\n",
+        crate_name,
+        version,
+        stats.files,
+        stats.rust_files.len(),
+        format_size(stats.bytes),
+        lines,
+        code_lines,
+        blocks,
+        fns,
+        impls,
+        unsafe_files,
+        stats.rust_files.len(),
+        if stats.has_build_script { "yes" } else { "no" }
+    );
+
+    if !stats.rust_files.is_empty() {
+        let mut largest: Vec<&RustFileStats> = stats.rust_files.iter().collect();
+        largest.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.path.cmp(&b.path)));
+        markdown.push_str("\n## Largest Rust files\n\n| File | Lines | Code lines | `unsafe` uses |\n|---|---|---|---|\n");
+        for file in largest.into_iter().take(LARGEST_FILES_SHOWN) {
+            markdown.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                file.path,
+                file.lines,
+                file.code_lines,
+                file.unsafe_total()
+            ));
+        }
+    }
+    markdown
+}
@@ -0,0 +1,481 @@
+use std::fmt;
+use std::ops::BitOr;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+
+use super::books::Book;
+use super::errors::{error_message, DocsError};
+use super::fallback::{CratesIoReadme, RepositoryDocs, CRATES_IO_README_SOURCE, REPOSITORY_SOURCE};
+use super::fetcher::{is_std_crate, DocFetcher, DocPage, FallbackDoc};
+use super::repository::RepoRef;
+
+/// Names of the sources `SourceRegistry::upstream` registers, in the order they're asked
+pub const UPSTREAM_SOURCES: [&str; 7] =
+    ["std", "docs.rs", "crates.io", "lib.rs", "github", CRATES_IO_README_SOURCE, REPOSITORY_SOURCE];
+
+/// Name of the source serving crates built with `build_local_docs`
+pub const LOCAL_SOURCE: &str = "local";
+
+/// What a documentation source can serve, one flag per group of `DocFetcher` methods
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities(u16);
+
+impl Capabilities {
+    /// Crate pages, item pages and doc files such as the search index
+    pub const CRATE_DOCS: Self = Self(1);
+    /// Rustdoc JSON of crate versions
+    pub const RUSTDOC_JSON: Self = Self(1 << 1);
+    /// crates.io search
+    pub const SEARCH: Self = Self(1 << 2);
+    /// lib.rs search
+    pub const LIB_RS_SEARCH: Self = Self(1 << 3);
+    /// The crates.io API and sparse index
+    pub const REGISTRY: Self = Self(1 << 4);
+    /// Published `.crate` archives
+    pub const ARCHIVES: Self = Self(1 << 5);
+    /// Files, listings and releases of GitHub and GitLab repositories
+    pub const REPOSITORIES: Self = Self(1 << 6);
+    /// The official Rust books
+    pub const BOOKS: Self = Self(1 << 7);
+    /// Docs standing in for crate versions the crate docs sources have no build of
+    pub const FALLBACK_DOCS: Self = Self(1 << 8);
+    pub const ALL: Self = Self((1 << 9) - 1);
+
+    const NAMES: [(Self, &'static str); 9] = [
+        (Self::CRATE_DOCS, "crate_docs"),
+        (Self::RUSTDOC_JSON, "rustdoc_json"),
+        (Self::SEARCH, "search"),
+        (Self::LIB_RS_SEARCH, "lib_rs_search"),
+        (Self::REGISTRY, "registry"),
+        (Self::ARCHIVES, "archives"),
+        (Self::REPOSITORIES, "repositories"),
+        (Self::BOOKS, "books"),
+        (Self::FALLBACK_DOCS, "fallback_docs"),
+    ];
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// e.g. `crate_docs | books`
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = Self::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+        if names.is_empty() {
+            return f.write_str("(none)");
+        }
+        f.write_str(&names.join(" | "))
+    }
+}
+
+/// A place documentation comes from, such as docs.rs or local `cargo doc` builds
+///
+/// A source only has to implement the `DocFetcher` methods its capabilities name;
+/// `SourceRegistry` never calls the others.
+pub trait DocSource: DocFetcher {
+    /// Name operators enable and disable the source by, e.g. `docs.rs`
+    fn name(&self) -> &str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Whether the source has a crate's docs, archive or index entry, so the next
+    /// source is asked when it doesn't
+    fn serves<'a>(&'a self, _crate_name: &'a str, _version: Option<&'a str>) -> BoxFuture<'a, bool> {
+        Box::pin(async { true })
+    }
+
+    /// Render docs standing in for a crate version, fetching what they're made of through
+    /// `sources` (the registry asking) so disabled sources stay unused; only called on
+    /// sources with `Capabilities::FALLBACK_DOCS`
+    fn fallback_doc<'a>(
+        &'a self,
+        _sources: &'a dyn DocFetcher,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        self.get_fallback_doc(crate_name, version, reason)
+    }
+}
+
+/// A source answering through a `DocFetcher`, limited to some of what it can fetch
+/// and, for crate requests, to the crates `serves_crate` accepts
+///
+/// The upstream sources share one `ReqwestFetcher`, so they share its connection
+/// pool, rate limits and circuit breaker too.
+pub struct FetcherSource {
+    name: String,
+    capabilities: Capabilities,
+    serves_crate: fn(&str) -> bool,
+    fetcher: Arc<dyn DocFetcher>,
+}
+
+impl FetcherSource {
+    pub fn new(name: &str, capabilities: Capabilities, fetcher: Arc<dyn DocFetcher>) -> Self {
+        Self {
+            name: name.to_string(),
+            capabilities,
+            serves_crate: |_| true,
+            fetcher,
+        }
+    }
+
+    /// Only serve the crates `serves_crate` accepts
+    pub fn for_crates(mut self, serves_crate: fn(&str) -> bool) -> Self {
+        self.serves_crate = serves_crate;
+        self
+    }
+}
+
+impl DocSource for FetcherSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    fn serves<'a>(&'a self, crate_name: &'a str, _version: Option<&'a str>) -> BoxFuture<'a, bool> {
+        Box::pin(futures::future::ready((self.serves_crate)(crate_name)))
+    }
+}
+
+impl DocFetcher for FetcherSource {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        self.fetcher.get_crate_page(crate_name, version)
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        self.fetcher.get_item_page(crate_name, version, relative_path)
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.get_doc_file(crate_name, version, file)
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.search(query, limit)
+    }
+
+    fn search_lib_rs<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.search_lib_rs(query)
+    }
+
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.get_crates_io_api(path)
+    }
+
+    fn get_index_entry<'a>(&'a self, crate_name: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.get_index_entry(crate_name)
+    }
+
+    fn get_rustdoc_json<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.get_rustdoc_json(crate_name, version)
+    }
+
+    fn get_crate_archive<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<Vec<u8>, ToolError>> {
+        self.fetcher.get_crate_archive(crate_name, version)
+    }
+
+    fn get_repository_file<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.get_repository_file(repo, path)
+    }
+
+    fn list_repository_dir<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.list_repository_dir(repo, path)
+    }
+
+    fn list_repository_releases<'a>(&'a self, repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        self.fetcher.list_repository_releases(repo)
+    }
+
+    fn get_book_page<'a>(&'a self, book: Book, path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        self.fetcher.get_book_page(book, path)
+    }
+
+    fn get_fallback_doc<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        self.fetcher.get_fallback_doc(crate_name, version, reason)
+    }
+}
+
+/// The documentation sources a router fetches from, asked in order
+///
+/// Each request goes to the first source that has the capability it needs and, for
+/// requests about a crate, serves that crate. Tools only see the `DocFetcher` the
+/// registry implements, so adding a source doesn't touch them.
+#[derive(Clone, Default)]
+pub struct SourceRegistry {
+    sources: Vec<Arc<dyn DocSource>>,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The upstream sources, all fetching through `fetcher`: doc.rust-lang.org for the
+    /// standard library and the books, docs.rs for every other crate, crates.io, lib.rs,
+    /// and GitHub and GitLab for repositories. Crates docs.rs has no build of fall back
+    /// to their crates.io README, then to their repository.
+    pub fn upstream(fetcher: Arc<dyn DocFetcher>) -> Self {
+        let source = |name: &str, capabilities| Arc::new(FetcherSource::new(name, capabilities, fetcher.clone()));
+        Self::new()
+            .with_source(Arc::new(
+                FetcherSource::new("std", Capabilities::CRATE_DOCS | Capabilities::BOOKS, fetcher.clone())
+                    .for_crates(is_std_crate),
+            ))
+            .with_source(Arc::new(
+                FetcherSource::new("docs.rs", Capabilities::CRATE_DOCS | Capabilities::RUSTDOC_JSON, fetcher.clone())
+                    .for_crates(|crate_name| !is_std_crate(crate_name)),
+            ))
+            .with_source(source(
+                "crates.io",
+                Capabilities::SEARCH | Capabilities::REGISTRY | Capabilities::ARCHIVES,
+            ))
+            .with_source(source("lib.rs", Capabilities::LIB_RS_SEARCH))
+            .with_source(source("github", Capabilities::REPOSITORIES))
+            .with_source(Arc::new(CratesIoReadme))
+            .with_source(Arc::new(RepositoryDocs))
+    }
+
+    /// Ask `source` after the sources already registered
+    pub fn with_source(mut self, source: Arc<dyn DocSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Ask `source` before the sources already registered
+    pub fn with_source_first(mut self, source: Arc<dyn DocSource>) -> Self {
+        self.sources.insert(0, source);
+        self
+    }
+
+    /// Drop the sources named in `names`, failing on names no source has so a
+    /// misspelled source isn't silently left enabled
+    pub fn disable(mut self, names: &[String]) -> Result<Self, String> {
+        let names: Vec<&str> = names.iter().map(|name| name.trim()).filter(|name| !name.is_empty()).collect();
+        if let Some(unknown) = names.iter().find(|name| !self.sources.iter().any(|source| source.name() == **name)) {
+            return Err(format!("Unknown documentation source {} (expected one of: {})", unknown, self.names().join(", ")));
+        }
+        self.sources.retain(|source| !names.contains(&source.name()));
+        Ok(self)
+    }
+
+    /// Names of the enabled sources, in the order they're asked
+    pub fn names(&self) -> Vec<&str> {
+        self.sources.iter().map(|source| source.name()).collect()
+    }
+
+    // The first source able to answer a request for `what`, about `crate_name` when given
+    async fn source_for(
+        &self,
+        capability: Capabilities,
+        crate_name: Option<&str>,
+        version: Option<&str>,
+        what: &str,
+    ) -> Result<&dyn DocSource, ToolError> {
+        for source in &self.sources {
+            if !source.capabilities().contains(capability) {
+                continue;
+            }
+            let serves = match crate_name {
+                Some(crate_name) => source.serves(crate_name, version).await,
+                None => true,
+            };
+            if serves {
+                return Ok(source.as_ref());
+            }
+        }
+        let subject = match crate_name {
+            Some(crate_name) => format!("{} of {}", what, crate_name),
+            None => what.to_string(),
+        };
+        Err(DocsError::Unavailable {
+            message: format!("No enabled documentation source serves {}", subject),
+            retry_in: None,
+        }
+        .into())
+    }
+}
+
+impl DocFetcher for SourceRegistry {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::CRATE_DOCS, Some(crate_name), version, "the docs").await?;
+            source.get_crate_page(crate_name, version).await
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::CRATE_DOCS, Some(crate_name), version, "the docs").await?;
+            source.get_item_page(crate_name, version, relative_path).await
+        })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::CRATE_DOCS, Some(crate_name), version, "the docs").await?;
+            source.get_doc_file(crate_name, version, file).await
+        })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::SEARCH, None, None, "crate search").await?;
+            source.search(query, limit).await
+        })
+    }
+
+    fn search_lib_rs<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::LIB_RS_SEARCH, None, None, "lib.rs search").await?;
+            source.search_lib_rs(query).await
+        })
+    }
+
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::REGISTRY, None, None, "the crates.io API").await?;
+            source.get_crates_io_api(path).await
+        })
+    }
+
+    fn get_index_entry<'a>(&'a self, crate_name: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::REGISTRY, Some(crate_name), None, "the index entry").await?;
+            source.get_index_entry(crate_name).await
+        })
+    }
+
+    fn get_rustdoc_json<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self
+                .source_for(Capabilities::RUSTDOC_JSON, Some(crate_name), Some(version), "rustdoc JSON")
+                .await?;
+            source.get_rustdoc_json(crate_name, version).await
+        })
+    }
+
+    fn get_crate_archive<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<Vec<u8>, ToolError>> {
+        Box::pin(async move {
+            let source = self
+                .source_for(Capabilities::ARCHIVES, Some(crate_name), Some(version), "the crate archive")
+                .await?;
+            source.get_crate_archive(crate_name, version).await
+        })
+    }
+
+    fn get_repository_file<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::REPOSITORIES, None, None, "repository files").await?;
+            source.get_repository_file(repo, path).await
+        })
+    }
+
+    fn list_repository_dir<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::REPOSITORIES, None, None, "repository listings").await?;
+            source.list_repository_dir(repo, path).await
+        })
+    }
+
+    fn list_repository_releases<'a>(&'a self, repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::REPOSITORIES, None, None, "repository releases").await?;
+            source.list_repository_releases(repo).await
+        })
+    }
+
+    fn get_book_page<'a>(&'a self, book: Book, path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let source = self.source_for(Capabilities::BOOKS, None, None, "the Rust books").await?;
+            source.get_book_page(book, path).await
+        })
+    }
+
+    // Every fallback source serving the crate is asked in turn, each told why the ones
+    // before it couldn't stand in, until one can; failing with the whole story otherwise
+    fn get_fallback_doc<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        reason: &'a str,
+    ) -> BoxFuture<'a, Result<FallbackDoc, ToolError>> {
+        Box::pin(async move {
+            let mut reason = reason.to_string();
+            let mut asked = false;
+            for source in &self.sources {
+                if !source.capabilities().contains(Capabilities::FALLBACK_DOCS) || !source.serves(crate_name, version).await {
+                    continue;
+                }
+                asked = true;
+                match source.fallback_doc(self, crate_name, version, &reason).await {
+                    Ok(doc) => return Ok(doc),
+                    Err(e) => {
+                        let message = error_message(e);
+                        // A registry registered as a source already tells the story from `reason` on
+                        reason = if message.starts_with(reason.as_str()) {
+                            message
+                        } else {
+                            format!("{}; {} fallback unavailable: {}", reason, source.name(), message)
+                        };
+                    }
+                }
+            }
+            if !asked {
+                reason = format!("{}; no enabled documentation source falls back for {}", reason, crate_name);
+            }
+            Err(ToolError::ExecutionError(reason))
+        })
+    }
+}
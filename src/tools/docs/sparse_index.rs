@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use super::versions::Version;
+
+/// A dependency of one version of a crate, as listed in the sparse index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexDependency {
+    /// Name of the crate depended on
+    pub name: String,
+    /// Name the dependency is known by in the manifest when it was renamed
+    pub alias: Option<String>,
+    /// Version requirement, e.g. `^1.0.100`
+    pub req: String,
+    /// `normal`, `build` or `dev`
+    pub kind: String,
+    pub optional: bool,
+    pub default_features: bool,
+    pub features: Vec<String>,
+    /// Platform the dependency is limited to, e.g. `cfg(unix)`
+    pub target: Option<String>,
+}
+
+impl IndexDependency {
+    /// Name the dependency goes by in the manifest and in feature lists
+    pub fn manifest_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// One published version of a crate, as listed in the sparse index
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexVersion {
+    pub version: String,
+    pub dependencies: Vec<IndexDependency>,
+    /// Features and what each enables (`features` and `features2` combined)
+    pub features: BTreeMap<String, Vec<String>>,
+    pub yanked: bool,
+}
+
+/// Path of a crate's entry in the sparse index, e.g. `se/rd/serde` or `3/s/syn`
+pub fn index_path(crate_name: &str) -> String {
+    let name = crate_name.to_lowercase();
+    let chars: Vec<char> = name.chars().collect();
+    match chars.len() {
+        0 | 1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", chars[0], name),
+        _ => format!("{}/{}/{}", String::from_iter(&chars[..2]), String::from_iter(&chars[2..4]), name),
+    }
+}
+
+/// Parse a sparse index entry, which has one JSON object per published version
+///
+/// Lines that can't be read are skipped.
+pub fn parse_index_entry(body: &str) -> Vec<IndexVersion> {
+    body.lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|entry| {
+            let version = entry.get("vers")?.as_str()?.to_string();
+            let mut features = BTreeMap::new();
+            for key in ["features", "features2"] {
+                for (feature, enables) in entry.get(key).and_then(|v| v.as_object()).into_iter().flatten() {
+                    features.insert(feature.clone(), string_list(enables));
+                }
+            }
+            let dependencies = entry
+                .get("deps")
+                .and_then(|deps| deps.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(parse_dependency)
+                .collect();
+            Some(IndexVersion {
+                version,
+                dependencies,
+                features,
+                yanked: entry.get("yanked").and_then(|v| v.as_bool()).unwrap_or(false),
+            })
+        })
+        .collect()
+}
+
+fn parse_dependency(dep: &Value) -> Option<IndexDependency> {
+    let manifest_name = dep.get("name")?.as_str()?.to_string();
+    let (name, alias) = match dep.get("package").and_then(|v| v.as_str()) {
+        Some(package) => (package.to_string(), Some(manifest_name)),
+        None => (manifest_name, None),
+    };
+    Some(IndexDependency {
+        name,
+        alias,
+        req: dep.get("req").and_then(|v| v.as_str()).unwrap_or("*").to_string(),
+        kind: dep.get("kind").and_then(|v| v.as_str()).unwrap_or("normal").to_string(),
+        optional: dep.get("optional").and_then(|v| v.as_bool()).unwrap_or(false),
+        default_features: dep.get("default_features").and_then(|v| v.as_bool()).unwrap_or(true),
+        features: dep.get("features").map(string_list).unwrap_or_default(),
+        target: dep.get("target").and_then(|v| v.as_str()).map(str::to_string),
+    })
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The newest release that isn't yanked, or the newest pre-release when a
+/// crate has no releases, matching what crates.io reports as its latest version
+pub fn latest_version(versions: &[IndexVersion]) -> Option<String> {
+    latest_stable_version(versions).or_else(|| newest_published(versions, |_| true))
+}
+
+/// The newest release that is neither yanked nor a pre-release
+pub fn latest_stable_version(versions: &[IndexVersion]) -> Option<String> {
+    newest_published(versions, |version| !version.is_prerelease())
+}
+
+/// The newest pre-release that isn't yanked, if it's newer than every release
+pub fn latest_prerelease(versions: &[IndexVersion]) -> Option<String> {
+    newest_published(versions, |_| true).filter(|newest| Version::parse(newest).is_some_and(|v| v.is_prerelease()))
+}
+
+// The newest version that isn't yanked among those `keep` accepts
+fn newest_published(versions: &[IndexVersion], keep: impl Fn(&Version) -> bool) -> Option<String> {
+    // The published string is kept, since it may carry build metadata
+    versions
+        .iter()
+        .filter(|entry| !entry.yanked)
+        .filter_map(|entry| Some((Version::parse(&entry.version)?, entry.version.as_str())))
+        .filter(|(version, _)| keep(version))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, published)| published.to_string())
+}
+
+/// The entry of one version of a crate
+pub fn find_version<'a>(versions: &'a [IndexVersion], version: &str) -> Option<&'a IndexVersion> {
+    versions.iter().find(|entry| entry.version == version)
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use tokio::sync::Mutex;
+
+/// Default time between re-reads of subscribed resources, matching how long
+/// "latest" keeps resolving to the same version
+pub const DEFAULT_SUBSCRIPTION_REFRESH_SECS: u64 = 300;
+
+#[derive(Default)]
+struct Subscribed {
+    /// Number of sessions subscribed to the resource
+    sessions: usize,
+    /// Digest of the content last read (`None` until a read succeeds)
+    digest: Option<u64>,
+}
+
+/// Resources at least one session subscribed to, shared by every session of a router
+///
+/// Remembers a digest of each resource's content so a refresh can tell
+/// whether it changed.
+#[derive(Clone, Default)]
+pub struct ResourceSubscriptions {
+    resources: Arc<Mutex<HashMap<String, Subscribed>>>,
+}
+
+impl ResourceSubscriptions {
+    /// Count a new subscriber of `uri`, with the content it currently has
+    pub async fn add(&self, uri: &str, content: &str) {
+        let mut resources = self.resources.lock().await;
+        let subscribed = resources.entry(uri.to_string()).or_default();
+        subscribed.sessions += 1;
+        subscribed.digest.get_or_insert(digest(content));
+    }
+
+    /// Drop a subscriber of `uri`, forgetting the resource after the last one
+    pub async fn remove(&self, uri: &str) {
+        let mut resources = self.resources.lock().await;
+        if let Some(subscribed) = resources.get_mut(uri) {
+            subscribed.sessions = subscribed.sessions.saturating_sub(1);
+            if subscribed.sessions == 0 {
+                resources.remove(uri);
+            }
+        }
+    }
+
+    pub async fn uris(&self) -> Vec<String> {
+        let mut uris: Vec<String> = self.resources.lock().await.keys().cloned().collect();
+        uris.sort();
+        uris
+    }
+
+    /// Record the content just read for `uri`, returning whether it differs from the previous read
+    ///
+    /// Resources nobody subscribes to any more are never reported as changed.
+    pub async fn record(&self, uri: &str, content: &str) -> bool {
+        let mut resources = self.resources.lock().await;
+        let Some(subscribed) = resources.get_mut(uri) else { return false };
+        let digest = digest(content);
+        subscribed.digest.replace(digest).is_some_and(|previous| previous != digest)
+    }
+}
+
+/// Resources one client session subscribed to
+#[derive(Clone, Default)]
+pub struct SessionSubscriptions {
+    uris: Arc<Mutex<HashSet<String>>>,
+}
+
+impl SessionSubscriptions {
+    /// Add `uri`, returning `false` if the session was already subscribed
+    pub async fn insert(&self, uri: &str) -> bool {
+        self.uris.lock().await.insert(uri.to_string())
+    }
+
+    /// Remove `uri`, returning `false` if the session wasn't subscribed
+    pub async fn remove(&self, uri: &str) -> bool {
+        self.uris.lock().await.remove(uri)
+    }
+
+    pub async fn contains(&self, uri: &str) -> bool {
+        self.uris.lock().await.contains(uri)
+    }
+
+    /// Remove and return every subscription, e.g. when the session ends
+    pub async fn take(&self) -> Vec<String> {
+        self.uris.lock().await.drain().collect()
+    }
+}
+
+fn digest(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
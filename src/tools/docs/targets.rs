@@ -0,0 +1,44 @@
+use scraper::{Html, Selector};
+
+/// Read the targets a docs.rs crate page's platform menu lists, default target first
+///
+/// docs.rs links every target a build documented as
+/// `/crate/<name>/<version>/target-redirect/<target>/<path>`, listing the
+/// crate's default target first.
+pub fn parse_doc_targets(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse("a[href*=\"/target-redirect/\"]") else {
+        return Vec::new();
+    };
+    let mut targets: Vec<String> = Vec::new();
+    for link in document.select(&selector) {
+        let Some(href) = link.value().attr("href") else { continue };
+        let Some((_, rest)) = href.split_once("/target-redirect/") else { continue };
+        let target = rest.split('/').next().unwrap_or_default();
+        if !target.is_empty() && !targets.iter().any(|known| known == target) {
+            targets.push(target.to_string());
+        }
+    }
+    targets
+}
+
+/// Format the targets of a crate version as markdown, saying first whether
+/// `target` is one of them when asked about one
+pub fn format_doc_targets(crate_name: &str, version: &str, targets: &[String], target: Option<&str>) -> String {
+    let mut output = format!("# Documented targets of {} {}\n\n", crate_name, version);
+    if let Some(target) = target {
+        if targets.iter().any(|known| known == target) {
+            output.push_str(&format!("{} is documented on docs.rs for `{}`.\n\n", crate_name, target));
+        } else {
+            output.push_str(&format!(
+                "{} is not documented on docs.rs for `{}`; use one of the targets below.\n\n",
+                crate_name, target
+            ));
+        }
+    }
+    for (index, known) in targets.iter().enumerate() {
+        let default = if index == 0 { " (default)" } else { "" };
+        output.push_str(&format!("- `{}`{}\n", known, default));
+    }
+    output
+}
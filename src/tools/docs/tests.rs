@@ -1,10 +1,215 @@
-use crate::tools::{DocCache, DocRouter};
+use crate::tools::{
+    html_to_markdown, item_probe_order, CrateOverrides, sanitize_html, split_error_data, DocsError, ErrorCode, ErrorData, Capabilities, CratePolicy, DiskStore, DocCache, DocFetcher, DocPage, DocRouter, FetcherSource, LocalDocs, Mirror, MirrorFetcher, OversizePolicy,
+    PrefetchConfig, ReqwestFetcher, RustdocJsonCache, SchemaMarker, SearchBackend, SearchIndex, SearchIndexCache, SourceRegistry, VersionPolicy, WatchList,
+    BYTES_PER_TOKEN, HISTORY_RESOURCE_URI, MAX_TRACKED_ACCESSES, UPDATES_RESOURCE_URI, UPSTREAM_SOURCES,
+    CRATES_IO_README_SOURCE,
+};
+use crate::tools::docs::batch::run_batch_line;
+use crate::tools::docs::books::{find_chapter, parse_chapters, Book, Chapter};
+use crate::tools::docs::changelog::{parse_changelog, parse_loose_version, parse_repository_releases, releases_between};
+use crate::tools::docs::circuit::CircuitBreaker;
+use crate::tools::docs::diff::unified_diff;
+use crate::tools::docs::doc_url::DocUrl;
+use crate::tools::docs::errors::{ensure_error_data, error_data, version_not_found, with_error_data};
+use crate::tools::docs::export::{export_file_path, format_export_summary};
+use crate::tools::docs::extract::{anchor_section, member_names, member_section, page_links, unstable_features, PageLink};
+use crate::tools::docs::features::{enabling_features, format_feature_graph, has_feature};
+use crate::tools::docs::fetcher::is_rust_channel;
+use crate::tools::docs::highlight::highlight;
+use crate::tools::docs::imports::imported_paths;
+use crate::tools::docs::lockfile::{parse_lockfile, LockedPackage};
+use crate::tools::docs::manifest::{manifest_package_name, parse_manifest_dependencies, DependencyKind};
+use crate::tools::docs::overview::parse_crate_summary;
+use crate::tools::docs::plain_text::{markdown_to_html, markdown_to_text};
+use crate::tools::docs::prefetch::{linked_item_paths, parse_direct_dependencies, parse_latest_versions};
+use crate::tools::docs::releases::{date_days_ago, is_iso_date, parse_crate_versions, releases_since, utc_timestamp};
+use crate::tools::docs::repository::{parse_markdown_listing, RepoHost, RepoRef};
+use crate::tools::docs::resolve::{format_tree, resolve};
+use crate::tools::docs::resources::DocResource;
+use crate::tools::docs::rust_releases::{parse_release_notes, parse_rust_version};
+use crate::tools::docs::rustdoc_json::{format_json_item, RustdocJson};
+use crate::tools::docs::search::{parse_crates_io_results, parse_lib_rs_results};
+use crate::tools::docs::search_index::search_index_file;
+use crate::tools::docs::sections::{declaration, headings, select_sections, without_generated_impls};
+use crate::tools::docs::signature::format_signature;
+use crate::tools::docs::source_stats::source_stats;
+use crate::tools::docs::sparse_index::{
+    find_version, index_path, latest_prerelease, latest_stable_version, latest_version, parse_index_entry,
+};
+use crate::tools::docs::subscriptions::SessionSubscriptions;
+use crate::tools::docs::targets::parse_doc_targets;
+use crate::tools::docs::usage::UsageStats;
+use crate::tools::docs::timing::{record_upstream_url, timed, timed_sync, CallBreakdown, Phase};
+use crate::tools::docs::versions::{Version, VersionReq};
+use crate::tools::docs::weight::{format_size, parse_crate_size};
+use futures::future::BoxFuture;
 use mcp_core::{Content, ToolError};
 use mcp_server::Router;
 use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use reqwest::Client;
 
+// In-memory fetcher serving canned pages and recording every request
+#[derive(Default)]
+struct MockFetcher {
+    pages: HashMap<String, String>,
+    // Final URLs for pages that "redirect", keyed like `pages`
+    urls: HashMap<String, String>,
+    requests: Mutex<Vec<String>>,
+    // Time every crates.io search takes to answer
+    search_delay: Duration,
+    // Keys answered as if their upstream were down, rather than with a 404
+    outages: BTreeSet<String>,
+}
+
+impl MockFetcher {
+    fn with_page(mut self, key: &str, body: &str) -> Self {
+        self.pages.insert(key.to_string(), body.to_string());
+        self
+    }
+
+    fn with_final_url(mut self, key: &str, url: &str) -> Self {
+        self.urls.insert(key.to_string(), url.to_string());
+        self
+    }
+
+    fn with_search_delay(mut self, delay: Duration) -> Self {
+        self.search_delay = delay;
+        self
+    }
+
+    fn with_outage(mut self, key: &str) -> Self {
+        self.outages.insert(key.to_string());
+        self
+    }
+
+    fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(&self, key: String) -> Result<String, ToolError> {
+        self.requests.lock().unwrap().push(key.clone());
+        if self.outages.contains(&key) {
+            return Err(DocsError::UpstreamStatus {
+                action: format!("fetch {}", key),
+                status: reqwest::StatusCode::SERVICE_UNAVAILABLE,
+            }
+            .into());
+        }
+        self.pages
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| ToolError::ExecutionError("Status code: 404 Not Found".to_string()))
+    }
+
+    fn respond_page(&self, key: String, default_url: String) -> Result<DocPage, ToolError> {
+        let url = self.urls.get(&key).cloned().unwrap_or(default_url);
+        self.respond(key).map(|html| DocPage { url, html })
+    }
+}
+
+// `owner/name` of a repository, with `@tag` when it isn't read from the default branch
+fn repo_key(repo: &RepoRef) -> String {
+    match &repo.git_ref {
+        Some(git_ref) => format!("{}/{}@{}", repo.owner, repo.name, git_ref),
+        None => format!("{}/{}", repo.owner, repo.name),
+    }
+}
+
+impl DocFetcher for MockFetcher {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        let version = version.unwrap_or("latest");
+        let key = format!("crate:{}@{}", crate_name, version);
+        let url = format!("https://docs.rs/crate/{}/{}/", crate_name, version);
+        // docs.rs answers crates it has no build of with a 404, as the real fetcher reports it
+        Box::pin(async move {
+            self.respond_page(key, url).map_err(|e| match error_data(&e) {
+                Some(_) => e,
+                None => with_error_data(e, ErrorData::new(ErrorCode::NotFound)),
+            })
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        let version = version.unwrap_or("latest");
+        let key = format!("item:{}@{}/{}", crate_name, version, relative_path);
+        let url = format!("https://docs.rs/{}/{}/{}/{}", crate_name, version, crate_name.replace('-', "_"), relative_path);
+        Box::pin(async move { self.respond_page(key, url) })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("file:{}@{}/{}", crate_name, version.unwrap_or("latest"), file);
+        Box::pin(async move { self.respond(key) })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("search:{}:{}", query, limit);
+        Box::pin(async move {
+            tokio::time::sleep(self.search_delay).await;
+            self.respond(key)
+        })
+    }
+
+    fn search_lib_rs<'a>(&'a self, query: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("librs:{}", query);
+        Box::pin(async move { self.respond(key) })
+    }
+
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("api:{}", path);
+        Box::pin(async move { self.respond(key) })
+    }
+
+    fn get_rustdoc_json<'a>(&'a self, crate_name: &'a str, version: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("rustdoc:{}@{}", crate_name, version);
+        // docs.rs answers versions without JSON with a 404, as the real fetcher reports it
+        Box::pin(async move {
+            self.respond(key).map_err(|e| match error_data(&e) {
+                Some(_) => e,
+                None => with_error_data(e, ErrorData::new(ErrorCode::NotFound)),
+            })
+        })
+    }
+
+    fn get_repository_file<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("repo:{}/{}", repo_key(repo), path);
+        Box::pin(async move { self.respond(key) })
+    }
+
+    fn list_repository_dir<'a>(&'a self, repo: &'a RepoRef, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("repo-dir:{}/{}", repo_key(repo), path);
+        Box::pin(async move { self.respond(key) })
+    }
+
+    fn list_repository_releases<'a>(&'a self, repo: &'a RepoRef) -> BoxFuture<'a, Result<String, ToolError>> {
+        let key = format!("releases:{}/{}", repo.owner, repo.name);
+        Box::pin(async move { self.respond(key) })
+    }
+
+    fn get_book_page<'a>(&'a self, book: Book, path: &'a str) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        let key = format!("book:{}/{}", book.path(), path);
+        let url = format!("https://doc.rust-lang.org/{}/{}", book.path(), path);
+        Box::pin(async move { self.respond_page(key, url) })
+    }
+}
+
 // Test DocCache functionality
 #[tokio::test]
 async fn test_doc_cache() {
@@ -25,6 +230,27 @@ async fn test_doc_cache() {
     assert_eq!(result, Some("updated_value".to_string()));
 }
 
+#[tokio::test]
+async fn test_cache_access_counts() {
+    let cache = DocCache::new();
+    cache.get("typo").await;
+    assert!(cache.most_accessed(10).await.is_empty());
+
+    cache.set("hot".to_string(), "value".to_string()).await;
+    for _ in 0..3 {
+        cache.get("hot").await;
+    }
+    // Past the limit of counted keys the least accessed are forgotten
+    for i in 0..MAX_TRACKED_ACCESSES {
+        let key = format!("key{}", i);
+        cache.set(key.clone(), "value".to_string()).await;
+        cache.get(&key).await;
+    }
+    let counted = cache.most_accessed(usize::MAX).await;
+    assert_eq!(counted.len(), MAX_TRACKED_ACCESSES);
+    assert_eq!(counted[0], "hot");
+}
+
 #[tokio::test]
 async fn test_cache_concurrent_access() {
     let cache = DocCache::new();
@@ -75,14 +301,42 @@ async fn test_list_tools() {
     let router = DocRouter::new();
     let tools = router.list_tools();
     
-    // Should have exactly 3 tools
-    assert_eq!(tools.len(), 3);
+    // Should have exactly 9 tools, plus semver_check when that feature is enabled
+    let expected_tools = if cfg!(feature = "semver") { 31 } else { 30 };
+    assert_eq!(tools.len(), expected_tools);
     
     // Check tool names
     let tool_names: Vec<String> = tools.iter().map(|t| t.name.clone()).collect();
     assert!(tool_names.contains(&"lookup_crate".to_string()));
     assert!(tool_names.contains(&"search_crates".to_string()));
     assert!(tool_names.contains(&"lookup_item".to_string()));
+    assert!(tool_names.contains(&"lookup_items".to_string()));
+    assert!(tool_names.contains(&"dependency_snippet".to_string()));
+    assert!(tool_names.contains(&"resolve_dependencies".to_string()));
+    assert!(tool_names.contains(&"feature_graph".to_string()));
+    assert!(tool_names.contains(&"dependency_weight".to_string()));
+    assert!(tool_names.contains(&"source_stats".to_string()));
+    assert!(tool_names.contains(&"find_defining_crate".to_string()));
+    assert!(tool_names.contains(&"compare_crates".to_string()));
+    assert!(tool_names.contains(&"recent_releases".to_string()));
+    assert!(tool_names.contains(&"watch_crates".to_string()));
+    assert!(tool_names.contains(&"rust_release_notes".to_string()));
+    assert!(tool_names.contains(&"edition_guide".to_string()));
+    assert!(tool_names.contains(&"cargo_book".to_string()));
+    assert!(tool_names.contains(&"lookup_book".to_string()));
+    assert!(tool_names.contains(&"lookup_url".to_string()));
+    assert!(tool_names.contains(&"page_outline".to_string()));
+    assert!(tool_names.contains(&"crate_exists".to_string()));
+    assert!(tool_names.contains(&"version_exists".to_string()));
+    assert!(tool_names.contains(&"doc_targets".to_string()));
+    assert!(tool_names.contains(&"diff_item".to_string()));
+    assert!(tool_names.contains(&"latest_version".to_string()));
+    assert!(tool_names.contains(&"release_notes".to_string()));
+    assert!(tool_names.contains(&"warm_cache".to_string()));
+    assert!(tool_names.contains(&"project_dependencies".to_string()));
+    assert!(tool_names.contains(&"explain_imports".to_string()));
+    assert!(tool_names.contains(&"usage_top".to_string()));
+    assert!(tool_names.contains(&"configure_session".to_string()));
     
     // Verify schema properties
     for tool in &tools {
@@ -92,13 +346,716 @@ async fn test_list_tools() {
         // Every schema should have properties
         let properties = schema.get("properties").unwrap().as_object().unwrap();
         
-        // Every schema should have required fields
+        // Every schema should list its required fields, which must be declared properties
+        // (only recent_releases, edition_guide, usage_top and configure_session have no required arguments)
         let required = schema.get("required").unwrap().as_array().unwrap();
         
         // Ensure non-empty
         assert!(!properties.is_empty());
-        assert!(!required.is_empty());
+        assert!(!required.is_empty() || ["recent_releases", "edition_guide", "usage_top", "configure_session"].contains(&tool.name.as_str()));
+        for field in required {
+            assert!(properties.contains_key(field.as_str().unwrap()));
+        }
+        assert!(properties.contains_key("format"));
+        assert!(properties.contains_key("offset"));
+        assert!(properties.contains_key("block"));
+        assert!(properties.contains_key("highlight"));
+    }
+}
+
+#[test]
+fn test_markdown_to_text_keeps_code_verbatim() {
+    let markdown = "# Glob *patterns*\n\nUse `**/*.rs` with [glob](https://docs.rs/glob).\n\n```rust\n// # not a heading\nlet x = 2 * 3;\n```\n\n## Steps\n\n1. **Build**\n2. Run\n   - `cargo test`\n";
+    assert_eq!(
+        markdown_to_text(markdown),
+        "Glob patterns\n=============\n\nUse **/*.rs with glob (https://docs.rs/glob).\n\n    // # not a heading\n    let x = 2 * 3;\n\nSteps\n-----\n\n1. Build\n2. Run\n  - cargo test"
+    );
+}
+
+#[tokio::test]
+async fn test_tool_text_format() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:serde@1.0.0", "<h1>serde</h1><p>A <strong>serialization</strong> framework</p>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0", "format": "text" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("A serialization framework"));
+    assert!(!text.text.contains("**"));
+
+    let err = router.call_tool("lookup_crate", json!({ "crate_name": "serde", "format": "pdf" })).await.unwrap_err();
+    assert!(matches!(err, ToolError::InvalidParameters(_)));
+}
+
+#[test]
+fn test_highlight() {
+    let markdown = "Spawns a task.\n\n## Examples\n\n```rust\ntokio::spawn(work());\n```\n\nCall `spawn` or **spawn_blocking**.\n\n## Panics\n\nPanics outside a runtime; see [spawn](fn.spawn.html).\n";
+    let terms = vec!["spawn".to_string(), "SPAWN".to_string(), "spawn_blocking".to_string(), "runtime".to_string()];
+    let highlighted = highlight(markdown, &terms);
+    assert_eq!(
+        highlighted.lines().next().unwrap(),
+        "[highlight: 6 matches (`spawn` 4, `spawn_blocking` 1, `runtime` 1); by section: top of page 1, Examples 3, Panics 2]"
+    );
+    // Code, bold text and link targets are left alone
+    assert!(highlighted.contains("**Spawn**s a task."));
+    assert!(highlighted.contains("tokio::spawn(work());"));
+    assert!(highlighted.contains("Call `spawn` or **spawn_blocking**."));
+    assert!(highlighted.contains("outside a **runtime**; see [**spawn**](fn.spawn.html)."));
+
+    assert_eq!(highlight(markdown, &[" ".to_string()]), markdown);
+    assert!(highlight(markdown, &["select".to_string()]).starts_with("[highlight: no matches of `select`]\n\nSpawns a task."));
+}
+
+#[tokio::test]
+async fn test_tool_highlight() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:serde@1.0.0", "<h1>serde</h1><p>A serialization framework</p>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let args = json!({ "crate_name": "serde", "version": "1.0.0", "highlight": ["Serialization"] });
+    let result = router.call_tool("lookup_crate", args).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("[highlight: 1 match (`Serialization` 1); by section: serde 1]"));
+    assert!(text.text.contains("A **serialization** framework"));
+
+    let terms: Vec<String> = (0..11).map(|i| format!("term{}", i)).collect();
+    let err = router.call_tool("lookup_crate", json!({ "crate_name": "serde", "highlight": terms })).await.unwrap_err();
+    assert!(matches!(err, ToolError::InvalidParameters(_)));
+}
+
+#[tokio::test]
+async fn test_call_breakdown_times_phases() {
+    let cache = DocCache::new();
+    let breakdown = CallBreakdown::default();
+    breakdown
+        .scope(async {
+            assert_eq!(cache.get("serde").await, None);
+            timed(Phase::UpstreamFetch, async { record_upstream_url("https://docs.rs/serde/") }).await;
+            timed(Phase::UpstreamFetch, async {}).await;
+            let markdown = html_to_markdown("<p>A <strong>serialization</strong> framework</p>");
+            cache.set("serde".to_string(), markdown).await;
+        })
+        .await;
+
+    let phases: Vec<(Phase, usize)> = breakdown.phases().into_iter().map(|(phase, _, runs)| (phase, runs)).collect();
+    assert_eq!(
+        phases,
+        vec![(Phase::CacheLookup, 1), (Phase::UpstreamFetch, 2), (Phase::Conversion, 1), (Phase::CacheStore, 1)]
+    );
+    assert_eq!(breakdown.upstream_urls(), vec!["https://docs.rs/serde/".to_string()]);
+    assert!(breakdown.to_string().contains("upstream_fetch 0ms (2x)"));
+
+    // Phases outside a call's scope aren't counted anywhere
+    timed_sync(Phase::Arguments, || ());
+    assert_eq!(breakdown.phases().len(), 4);
+    assert_eq!(CallBreakdown::default().to_string(), "no phases timed");
+}
+
+#[test]
+fn test_sanitize_html_strips_active_content() {
+    let html = r#"<html><head><title>demo - Rust</title><style>p { color: red }</style></head>
+        <body><p onclick="steal()">Safe <b>text</b></p><script>alert(1)</script>
+        <a href="javascript:alert(2)">link</a><img src="x.png" onerror="alert(3)"></body></html>"#;
+    let clean = sanitize_html(html);
+    for injected in ["<script", "alert", "onclick", "onerror", "color: red", "javascript:", "demo - Rust"] {
+        assert!(!clean.contains(injected), "{} survived: {}", injected, clean);
+    }
+    assert!(clean.contains("Safe <b>text</b>"));
+
+    let markdown = html_to_markdown(html);
+    assert!(markdown.contains("Safe **text**"));
+    assert!(!markdown.contains("alert"));
+}
+
+#[tokio::test]
+async fn test_tool_html_format_is_sanitized() {
+    // Markdown conversion passes inline HTML it doesn't understand through
+    let markdown = "# Demo\n\n<iframe src=\"https://evil.example\"></iframe><span onmouseover=\"x()\">hover</span>";
+    let html = markdown_to_html(markdown);
+    assert!(html.contains("<h1>Demo</h1>"));
+    assert!(html.contains("hover"));
+    assert!(!html.contains("iframe") && !html.contains("onmouseover"));
+
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:serde@1.0.0", "<h1>serde</h1><p>A <strong>serialization</strong> framework</p>"));
+    let router = DocRouter::with_fetcher(fetcher);
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0", "format": "html" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("<strong>serialization</strong>"));
+}
+
+#[tokio::test]
+async fn test_max_content_bytes_truncates_with_offset_marker() {
+    let lines: String = (0..200).map(|i| format!("<p>Line {:03}</p>", i)).collect();
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:demo@1.0.0", &lines));
+    let router = DocRouter::with_fetcher(fetcher).with_max_content_bytes(512);
+
+    let mut offset = 0;
+    let mut parts = Vec::new();
+    loop {
+        let result = router.call_tool("lookup_crate", json!({ "crate_name": "demo", "version": "1.0.0", "offset": offset })).await.unwrap();
+        let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+        assert!(text.text.len() <= 512);
+        match text.text.rsplit_once("\n[truncated: use offset=") {
+            Some((part, marker)) => {
+                parts.push(part.to_string());
+                offset = marker.trim_end_matches(" to continue]").parse().unwrap();
+            }
+            None => {
+                parts.push(text.text.clone());
+                break;
+            }
+        }
+    }
+    // Parts are cut at line breaks and together contain every line exactly once
+    assert!(parts.len() > 2);
+    let joined = parts.join("\n");
+    assert_eq!((0..200).filter(|i| joined.contains(&format!("Line {:03}", i))).count(), 200);
+    assert_eq!(joined.matches("Line 100").count(), 1);
+
+    let err = router.call_tool("lookup_crate", json!({ "crate_name": "demo", "version": "1.0.0", "offset": 1_000_000 })).await.unwrap_err();
+    assert!(matches!(err, ToolError::InvalidParameters(_)));
+}
+
+#[tokio::test]
+async fn test_max_content_parts_splits_large_results() {
+    let lines: String = (0..200).map(|i| format!("<p>Line {:03}</p>", i)).collect();
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:demo@1.0.0", &lines));
+
+    // Enough parts for the whole document: no truncation marker
+    let router = DocRouter::with_fetcher(fetcher.clone()).with_max_content_bytes(512).with_max_content_parts(20);
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "demo", "version": "1.0.0" })).await.unwrap();
+    assert!(result.len() > 2);
+    let texts: Vec<&str> = result.iter().map(|content| match content {
+        Content::Text(text) => text.text.as_str(),
+        _ => panic!("Expected text content"),
+    }).collect();
+    assert!(texts.iter().all(|text| text.len() <= 512 && !text.contains("[truncated")));
+    assert!(texts.last().unwrap().contains("Line 199"));
+
+    // Too few parts: the last one carries the marker
+    let router = DocRouter::with_fetcher(fetcher).with_max_content_bytes(512).with_max_content_parts(2);
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "demo", "version": "1.0.0" })).await.unwrap();
+    assert_eq!(result.len(), 2);
+    let Content::Text(last) = &result[1] else { panic!("Expected text content") };
+    assert!(last.text.contains("[truncated: use offset="));
+}
+
+#[test]
+fn test_doc_resource_uris() {
+    assert_eq!(DocResource::parse("docs://serde/1.0.0"), Some(DocResource::Crate {
+        crate_name: "serde".to_string(),
+        version: Some("1.0.0".to_string()),
+    }));
+    let item = DocResource::parse("docs://tokio/latest/sync/mpsc/Sender").unwrap();
+    assert_eq!(item, DocResource::Item {
+        crate_name: "tokio".to_string(),
+        version: None,
+        item_path: "sync::mpsc::Sender".to_string(),
+    });
+    assert_eq!(item.uri(), "docs://tokio/latest/sync::mpsc::Sender");
+    assert_eq!(DocResource::parse(&item.uri()), Some(item));
+    assert_eq!(DocResource::parse("docs://updates"), None);
+    assert_eq!(DocResource::parse("https://docs.rs/serde"), None);
+}
+
+#[tokio::test]
+async fn test_read_doc_resources() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:serde@1.0.0", "<h1>serde</h1>")
+        .with_page("item:serde@1.0.0/trait.Serialize.html", "<h1>Trait serde::Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let doc = router.read_resource("docs://serde/1.0.0").await.unwrap();
+    assert!(doc.starts_with("Documentation for serde 1.0.0"));
+    let item = router.read_resource("docs://serde/1.0.0/Serialize").await.unwrap();
+    assert!(item.contains("Trait serde::Serialize"));
+
+    // Resources share the tools' cache
+    let requests = fetcher.requests().len();
+    router.call_tool("lookup_item", json!({ "crate_name": "serde", "item_path": "Serialize", "version": "1.0.0" })).await.unwrap();
+    assert_eq!(fetcher.requests().len(), requests);
+
+    assert!(router.read_resource("docs://nothing-here").await.is_err());
+}
+
+#[tokio::test]
+async fn test_session_history() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:serde@1.0.200", "<h1>serde</h1>")
+        .with_page("item:serde@1.0.200/trait.Serialize.html", "<h1>Trait serde::Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+    router.latest_versions.set("serde", "1.0.200").await;
+    let other_session = router.for_session();
+
+    let history = router.read_resource(HISTORY_RESOURCE_URI).await.unwrap();
+    assert_eq!(history, "# Session history\n\nNothing was looked up in this session yet.\n");
+
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    router.call_tool("lookup_item", json!({ "crate_name": "serde", "item_path": "serde::Serialize" })).await.unwrap();
+    assert!(router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "0.0.1" })).await.is_err());
+    router.read_resource("docs://serde/latest/Serialize").await.unwrap();
+
+    let entries = router.history.entries();
+    let calls: Vec<(&str, &str, Vec<String>, bool)> = entries
+        .iter()
+        .map(|entry| (entry.call.as_str(), entry.arguments.as_str(), entry.documents.clone(), entry.ok))
+        .collect();
+    assert_eq!(
+        calls,
+        vec![
+            ("lookup_crate", r#"{"crate_name":"serde"}"#, vec!["docs://serde/1.0.200".to_string()], true),
+            (
+                "lookup_item",
+                r#"{"crate_name":"serde","item_path":"serde::Serialize"}"#,
+                vec!["docs://serde/1.0.200/Serialize".to_string()],
+                true
+            ),
+            ("lookup_crate", r#"{"crate_name":"serde","version":"0.0.1"}"#, vec![], false),
+            ("resources/read", "docs://serde/latest/Serialize", vec!["docs://serde/1.0.200/Serialize".to_string()], true),
+        ]
+    );
+    assert!(entries.iter().all(|entry| entry.time.len() == "2025-01-31T09:05:00Z".len()));
+
+    let history = router.read_resource(HISTORY_RESOURCE_URI).await.unwrap();
+    assert!(history.contains(&format!("- {} `lookup_crate` {{\"crate_name\":\"serde\"}}\n  - docs://serde/1.0.200\n", entries[0].time)));
+    assert!(history.contains(r#"`lookup_crate` {"crate_name":"serde","version":"0.0.1"} (failed)"#));
+    // Reading the history isn't part of it
+    assert_eq!(router.history.entries().len(), 4);
+
+    // Sessions share the cache but not their history
+    assert!(other_session.history.entries().is_empty());
+    assert!(router.list_resources().iter().any(|resource| resource.uri == HISTORY_RESOURCE_URI));
+}
+
+#[tokio::test]
+async fn test_subscription_refresh_announces_changed_resources() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:serde@1.0.200", "<h1>serde</h1>")
+        .with_page("crate:serde@1.0.201", "<h1>serde</h1><p>New in this release</p>"));
+    let router = DocRouter::with_fetcher(fetcher);
+    router.latest_versions.set("serde", "1.0.200").await;
+    let session = SessionSubscriptions::default();
+    let other_session = SessionSubscriptions::default();
+    router.subscribe_resource(&session, "docs://serde/latest").await.unwrap();
+    router.subscribe_resource(&other_session, "docs://serde/latest").await.unwrap();
+    let mut changes = router.watch.subscribe();
+
+    assert!(router.refresh_subscriptions().await.is_empty());
+    // "latest" moves to a new release once the alias expires
+    router.latest_versions.set("serde", "1.0.201").await;
+    assert_eq!(router.refresh_subscriptions().await, vec!["docs://serde/latest".to_string()]);
+    assert_eq!(changes.try_recv().unwrap(), "docs://serde/latest");
+    assert!(router.refresh_subscriptions().await.is_empty());
+
+    // The resource stays subscribed until its last session is gone
+    router.end_session(&session).await;
+    assert_eq!(router.subscriptions.uris().await, vec!["docs://serde/latest".to_string()]);
+    router.unsubscribe_resource(&other_session, "docs://serde/latest").await;
+    assert!(router.subscriptions.uris().await.is_empty());
+
+    // Resources that can't be read can't be subscribed to
+    assert!(router.subscribe_resource(&session, "docs://serde").await.is_err());
+    assert!(!session.contains("docs://serde").await);
+}
+
+#[tokio::test]
+async fn test_tool_timeout() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_search_delay(Duration::from_millis(500))
+        .with_page("crate:serde@1.0.0", "<h1>serde</h1>"));
+    let router = DocRouter::with_fetcher(fetcher)
+        .with_tool_timeout("search_crates", Duration::from_millis(20))
+        .with_tool_timeout("lookup_crate", Duration::from_secs(5));
+
+    let result = router.call_tool("search_crates", json!({ "query": "serde" })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(msg))
+        if split_error_data(&msg) == ("Timed out: search_crates did not finish within 20ms", Some(ErrorData::new(ErrorCode::Timeout)))));
+
+    // Calls that finish in time are unaffected
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" })).await.unwrap();
+    assert_eq!(result.len(), 1);
+}
+
+// Log lines written while a test runs, for checking what it logged
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_slow_call_log() {
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _logging = tracing::subscriber::set_default(subscriber);
+
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_search_delay(Duration::from_millis(200))
+        .with_page("crate:serde@1.0.0", "<h1>serde</h1>"));
+    let router = DocRouter::with_fetcher(fetcher).with_slow_call_threshold(Duration::from_millis(100));
+
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" })).await.unwrap();
+    assert!(logs.0.lock().unwrap().is_empty());
+
+    router.call_tool("search_crates", json!({ "query": "serde", "limit": 3 })).await.unwrap();
+    let logged = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logged.contains("WARN"), "{}", logged);
+    assert!(logged.contains("Slow tool call search_crates took"), "{}", logged);
+    assert!(logged.contains(r#"arguments={"limit":3,"query":"serde"}"#), "{}", logged);
+    assert!(logged.contains("upstream_urls=[]"), "{}", logged);
+    assert!(logged.contains("arguments 0ms"), "{}", logged);
+}
+
+#[test]
+fn test_index_path() {
+    assert_eq!(index_path("a"), "1/a");
+    assert_eq!(index_path("cc"), "2/cc");
+    assert_eq!(index_path("syn"), "3/s/syn");
+    assert_eq!(index_path("Serde"), "se/rd/serde");
+}
+
+#[test]
+fn test_parse_index_entry() {
+    let body = concat!(
+        r#"{"name":"demo","vers":"1.0.0","deps":[{"name":"serde","req":"^1.0","features":["derive"],"optional":true,"default_features":true,"target":null,"kind":"normal"}],"features":{"default":["std"],"std":[]},"yanked":false}"#,
+        "\n",
+        r#"{"name":"demo","vers":"1.1.0","deps":[{"name":"json","package":"serde_json","req":"^1","features":[],"optional":false,"default_features":false,"target":"cfg(unix)","kind":null},{"name":"tempfile","req":"^3","features":[],"optional":false,"default_features":true,"target":null,"kind":"dev"}],"features":{"std":[]},"features2":{"json":["dep:json"]},"yanked":true,"v":2}"#,
+        "\n",
+        "not json\n",
+    );
+    let versions = parse_index_entry(body);
+    assert_eq!(versions.len(), 2);
+
+    let first = find_version(&versions, "1.0.0").unwrap();
+    assert!(!first.yanked);
+    assert_eq!(first.features["default"], vec!["std".to_string()]);
+    assert!(first.dependencies[0].optional);
+    assert_eq!(first.dependencies[0].features, vec!["derive".to_string()]);
+
+    let second = find_version(&versions, "1.1.0").unwrap();
+    assert!(second.yanked);
+    // features2 is merged into the feature map
+    assert_eq!(second.features["json"], vec!["dep:json".to_string()]);
+    let renamed = &second.dependencies[0];
+    assert_eq!((renamed.name.as_str(), renamed.manifest_name()), ("serde_json", "json"));
+    assert_eq!(renamed.kind, "normal");
+    assert!(!renamed.default_features);
+    assert_eq!(renamed.target.as_deref(), Some("cfg(unix)"));
+    assert_eq!(second.dependencies[1].kind, "dev");
+}
+
+#[test]
+fn test_latest_version_from_index() {
+    let entry = |version: &str, yanked: bool| format!(r#"{{"name":"demo","vers":"{}","deps":[],"features":{{}},"yanked":{}}}"#, version, yanked);
+    let body = [entry("1.9.0", false), entry("1.10.0+build.5", false), entry("2.0.0-rc.1", false), entry("1.11.0", true)].join("\n");
+    // Yanked releases and pre-releases are skipped; build metadata is kept
+    assert_eq!(latest_version(&parse_index_entry(&body)).as_deref(), Some("1.10.0+build.5"));
+
+    let body = [entry("0.1.0-alpha.2", false), entry("0.1.0-alpha.10", false), entry("0.1.0-beta", false)].join("\n");
+    assert_eq!(latest_version(&parse_index_entry(&body)).as_deref(), Some("0.1.0-beta"));
+    assert_eq!(latest_version(&[]), None);
+}
+
+#[test]
+fn test_latest_stable_version_and_prerelease() {
+    let entry = |version: &str, yanked: bool| format!(r#"{{"name":"demo","vers":"{}","deps":[],"features":{{}},"yanked":{}}}"#, version, yanked);
+    let body = [entry("1.9.0", false), entry("2.0.0-rc.1", false), entry("2.0.0-rc.2", true), entry("1.0.0-beta", false)].join("\n");
+    let versions = parse_index_entry(&body);
+    assert_eq!(latest_stable_version(&versions).as_deref(), Some("1.9.0"));
+    assert_eq!(latest_prerelease(&versions).as_deref(), Some("2.0.0-rc.1"));
+
+    // Pre-releases older than the latest release aren't reported
+    let body = [entry("1.9.0", false), entry("1.9.0-rc.1", false)].join("\n");
+    assert_eq!(latest_prerelease(&parse_index_entry(&body)), None);
+
+    let body = [entry("0.1.0-alpha.1", false)].join("\n");
+    assert_eq!(latest_stable_version(&parse_index_entry(&body)), None);
+    assert_eq!(latest_prerelease(&parse_index_entry(&body)).as_deref(), Some("0.1.0-alpha.1"));
+
+    assert_eq!(
+        parse_latest_versions(r#"{"crate":{"max_stable_version":"1.0.200","max_version":"1.0.201-rc.1"}}"#),
+        Some((Some("1.0.200".to_string()), Some("1.0.201-rc.1".to_string())))
+    );
+    assert_eq!(
+        parse_latest_versions(r#"{"crate":{"max_stable_version":"1.0.200","max_version":"1.0.200"}}"#),
+        Some((Some("1.0.200".to_string()), None))
+    );
+    assert_eq!(parse_latest_versions(r#"{"errors":[]}"#), None);
+}
+
+#[test]
+fn test_version_ordering() {
+    let parse = |version: &str| Version::parse(version).unwrap();
+    assert!(parse("1.10.0") > parse("1.9.9"));
+    assert!(parse("1.0.0") > parse("1.0.0-rc.1"));
+    assert!(parse("1.0.0-alpha.10") > parse("1.0.0-alpha.2"));
+    assert!(parse("1.0.0-alpha.beta") > parse("1.0.0-alpha.1"));
+    assert!(parse("1.0.0-alpha.1") > parse("1.0.0-alpha"));
+    assert_eq!(parse("1.2.3+build"), parse("1.2.3"));
+    assert_eq!(parse("1.2.3-rc.1").to_string(), "1.2.3-rc.1");
+    assert_eq!(Version::parse("1.2"), None);
+    assert_eq!(Version::parse("1.2.x"), None);
+}
+
+#[test]
+fn test_version_req_matching() {
+    let matches = |req: &str, version: &str| VersionReq::parse(req).unwrap().matches(&Version::parse(version).unwrap());
+    // Bare requirements are caret requirements
+    assert!(matches("1.2.3", "1.9.0"));
+    assert!(!matches("1.2.3", "1.2.2"));
+    assert!(!matches("^1.2.3", "2.0.0"));
+    assert!(matches("^0.3.1", "0.3.9"));
+    assert!(!matches("^0.3.1", "0.4.0"));
+    assert!(!matches("^0.0.3", "0.0.4"));
+    assert!(matches("~1.2.3", "1.2.9"));
+    assert!(!matches("~1.2.3", "1.3.0"));
+    assert!(matches("~1", "1.9.0"));
+    assert!(matches("1.*", "1.4.0"));
+    assert!(!matches("1.*", "2.0.0"));
+    assert!(matches("*", "0.1.0"));
+    assert!(matches(">=1.2, <1.5", "1.4.9"));
+    assert!(!matches(">=1.2, <1.5", "1.5.0"));
+    assert!(matches("=1.0.200", "1.0.200"));
+    assert!(!matches("=1.0.200", "1.0.201"));
+    // Pre-releases only match requirements naming a pre-release of the same version
+    assert!(!matches("^1.0", "1.1.0-rc.1"));
+    assert!(matches("^1.1.0-rc.1", "1.1.0-rc.2"));
+    assert!(!matches("^1.1.0-rc.1", "1.2.0-rc.1"));
+    assert!(matches("^1.1.0-rc.1", "1.1.0"));
+    assert_eq!(VersionReq::parse("1.*.3"), None);
+    assert_eq!(VersionReq::parse("one"), None);
+}
+
+#[test]
+fn test_resolve_dependencies() {
+    let entries = [
+        r#"{"name":"app","vers":"1.0.0","deps":[
+            {"name":"serde","req":"^1.0.100","features":["derive"],"optional":false,"default_features":true,"target":null,"kind":"normal"},
+            {"name":"tokio","req":"^1","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"},
+            {"name":"cc","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"build"},
+            {"name":"winapi","req":"^0.3","features":[],"optional":false,"default_features":true,"target":"cfg(windows)","kind":"normal"},
+            {"name":"tempfile","req":"^3","features":[],"optional":false,"default_features":true,"target":null,"kind":"dev"}
+        ],"features":{"default":["std"],"std":["serde/std"],"rt":["dep:tokio","tokio?/rt"]},"yanked":false}"#,
+        r#"{"name":"serde","vers":"1.0.100","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"serde","vers":"1.0.200","deps":[
+            {"name":"serde_derive","req":"=1.0.200","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"}
+        ],"features":{"default":["std"],"std":[],"derive":["serde_derive"]},"yanked":false}"#,
+        r#"{"name":"serde","vers":"1.0.300","deps":[],"features":{},"yanked":true}"#,
+        r#"{"name":"serde","vers":"2.0.0-alpha.1","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"serde_derive","vers":"1.0.199","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"serde_derive","vers":"1.0.200","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"tokio","vers":"1.40.0","deps":[],"features":{"rt":[]},"yanked":false}"#,
+        r#"{"name":"cc","vers":"1.2.0","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"winapi","vers":"0.3.9","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"winapi","vers":"0.4.0","deps":[],"features":{},"yanked":false}"#,
+    ];
+    let mut index: HashMap<String, Vec<_>> = HashMap::new();
+    for entry in entries {
+        let versions = parse_index_entry(&entry.replace('\n', ""));
+        let name = serde_json::from_str::<serde_json::Value>(&entry.replace('\n', "")).unwrap()["name"].as_str().unwrap().to_string();
+        index.entry(name).or_default().extend(versions);
     }
+    let features = vec!["rt".to_string(), "nope".to_string()];
+
+    let resolution = resolve("app", "1.0.0", &features, true, true, &index, &BTreeSet::new());
+    assert_eq!(resolution.features, BTreeSet::from(["default", "rt", "std"].map(String::from)));
+    assert_eq!(resolution.unknown_features, vec!["nope".to_string()]);
+    assert!(resolution.missing.is_empty() && resolution.unsatisfied.is_empty());
+
+    let picked: Vec<(&str, &str, usize)> = resolution
+        .crates
+        .iter()
+        .map(|resolved| (resolved.name.as_str(), resolved.version.as_str(), resolved.depth))
+        .collect();
+    // Dev-dependencies are skipped, yanked versions and pre-releases passed over
+    assert_eq!(
+        picked,
+        vec![
+            ("cc", "1.2.0", 1),
+            ("serde", "1.0.200", 1),
+            ("tokio", "1.40.0", 1),
+            ("winapi", "0.3.9", 1),
+            ("serde_derive", "1.0.200", 2),
+        ]
+    );
+    let serde = &resolution.crates[1];
+    assert_eq!(serde.features, BTreeSet::from(["default", "derive", "serde_derive", "std"].map(String::from)));
+    // `tokio?/rt` applies since the rt feature enables tokio too
+    assert_eq!(resolution.crates[2].features, BTreeSet::from(["rt".to_string()]));
+    assert!(resolution.crates[0].build_only);
+    assert_eq!(resolution.crates[3].targets, BTreeSet::from(["cfg(windows)".to_string()]));
+    assert_eq!(resolution.crates[2].enabled_by, Some(BTreeSet::from(["rt".to_string()])));
+    assert_eq!(resolution.crates[1].enabled_by, None);
+    assert!(resolution.disabled.is_empty());
+    assert_eq!(resolution.crates[4].required_by, BTreeSet::from(["serde 1.0.200".to_string()]));
+
+    let tree = format_tree(&resolution, 3);
+    assert!(tree.contains(concat!(
+        "```text\n",
+        "app v1.0.0\n",
+        "├── cc v1.2.0 (build)\n",
+        "├── serde v1.0.200\n",
+        "│   └── serde_derive v1.0.200\n",
+        "├── tokio v1.40.0\n",
+        "└── winapi v0.3.9 [cfg(windows)]\n",
+        "```\n",
+    )));
+    assert!(!tree.contains("left out"));
+    // Crates whose dependencies are too deep to draw are noted
+    let tree = format_tree(&resolution, 1);
+    assert!(tree.contains("├── serde v1.0.200\n├── tokio"));
+    assert!(tree.contains("below depth 1 are left out"));
+
+    // Without defaults or transitive resolution only the direct requirements are picked
+    let resolution = resolve("app", "1.0.0", &[], false, false, &index, &BTreeSet::new());
+    let names: Vec<&str> = resolution.crates.iter().map(|resolved| resolved.name.as_str()).collect();
+    assert_eq!(names, vec!["cc", "serde", "winapi"]);
+    // Optional dependencies left out are listed with the features that would enable them
+    assert_eq!(resolution.disabled.len(), 1);
+    assert_eq!(resolution.disabled[0].name, "tokio");
+    assert_eq!(resolution.disabled[0].enabled_by, BTreeSet::from(["rt".to_string()]));
+
+    // Crates not fetched yet are asked for, unless they can't be fetched
+    index.remove("serde_derive");
+    let resolution = resolve("app", "1.0.0", &[], true, true, &index, &BTreeSet::new());
+    assert_eq!(resolution.missing, BTreeSet::from(["serde_derive".to_string()]));
+    let unavailable = BTreeSet::from(["serde_derive".to_string()]);
+    let resolution = resolve("app", "1.0.0", &[], true, true, &index, &unavailable);
+    assert!(resolution.missing.is_empty());
+    assert_eq!(resolution.unsatisfied.len(), 1);
+}
+
+#[test]
+fn test_feature_graph() {
+    let entry = parse_index_entry(concat!(
+        r#"{"name":"demo","vers":"1.0.0","deps":["#,
+        r#"{"name":"serde","req":"^1","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"},"#,
+        r#"{"name":"tokio","req":"^1","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"},"#,
+        r#"{"name":"log","req":"^0.4","features":[],"optional":true,"default_features":true,"target":"cfg(windows)","kind":"normal"}],"#,
+        r#""features":{"default":["std"],"std":["serde?/std"],"full":["std","rt","serde"],"rt":["dep:tokio","tokio/rt"]},"yanked":false}"#,
+    ))
+    .remove(0);
+
+    assert!(has_feature(&entry, "full"));
+    // Optional dependencies not named with `dep:` are features too
+    assert!(has_feature(&entry, "serde") && has_feature(&entry, "log"));
+    assert!(!has_feature(&entry, "tokio"));
+    assert_eq!(enabling_features(&entry, "tokio"), vec!["full".to_string(), "rt".to_string()]);
+    assert!(enabling_features(&entry, "log").is_empty());
+
+    let graph = format_feature_graph("demo", &entry, None);
+    assert!(graph.starts_with("# Feature graph of demo 1.0.0\n\nDefault features: `std`\n"));
+    assert!(graph.contains("- `full`\n  - `std`\n    - feature `std` of `serde`, if `serde` is enabled\n  - `rt`\n    - optional dependency `tokio`\n    - optional dependency `tokio`, with its feature `rt`\n  - `serde`\n    - optional dependency `serde`\n"));
+    assert!(graph.contains("- `log`: also the feature `log`; only for cfg(windows)\n"));
+    assert!(graph.contains("- `serde`: enabled by `full`; also the feature `serde`\n"));
+    assert!(graph.contains("- `tokio`: enabled by `full`, `rt`\n"));
+
+    let graph = format_feature_graph("demo", &entry, Some("full"));
+    assert!(graph.starts_with("# Feature `full` of demo 1.0.0\n\n- `full`\n"));
+    assert!(graph.contains("- Features: `full`, `rt`, `serde`, `std`\n"));
+    assert!(graph.contains("- Optional dependencies: `serde`, `tokio`\n"));
+    assert!(graph.contains("- Features of dependencies: `serde/std`, `tokio/rt`\n"));
+}
+
+#[tokio::test]
+async fn test_doc_targets() {
+    let page = r#"<ul id="platforms">
+        <li><a href="/crate/tokio/1.43.0/target-redirect/x86_64-unknown-linux-gnu/tokio/">x86_64-unknown-linux-gnu</a></li>
+        <li><a href="/crate/tokio/1.43.0/target-redirect/x86_64-pc-windows-msvc/tokio/">x86_64-pc-windows-msvc</a></li>
+        <li><a href="/crate/tokio/1.43.0/target-redirect/x86_64-unknown-linux-gnu/tokio/">x86_64-unknown-linux-gnu</a></li>
+        <li><a href="/crate/tokio/1.43.0/source/">Source</a></li>
+    </ul>"#;
+    assert_eq!(parse_doc_targets(page), vec!["x86_64-unknown-linux-gnu", "x86_64-pc-windows-msvc"]);
+
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:tokio@1.43.0", page));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+    let args = json!({ "crate_name": "tokio", "version": "1.43.0", "target": "aarch64-apple-darwin" });
+    let result = router.call_tool("doc_targets", args).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# Documented targets of tokio 1.43.0\n\ntokio is not documented on docs.rs for `aarch64-apple-darwin`"));
+    assert!(text.text.ends_with("- `x86_64-unknown-linux-gnu` (default)\n- `x86_64-pc-windows-msvc`\n"));
+
+    // The targets of a version are cached
+    let args = json!({ "crate_name": "tokio", "version": "1.43.0", "target": "x86_64-pc-windows-msvc" });
+    let result = router.call_tool("doc_targets", args).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("tokio is documented on docs.rs for `x86_64-pc-windows-msvc`.\n"));
+    assert_eq!(fetcher.requests(), vec!["crate:tokio@1.43.0".to_string()]);
+
+    let err = router.call_tool("doc_targets", json!({ "crate_name": "std" })).await.unwrap_err();
+    assert!(matches!(err, ToolError::InvalidParameters(_)));
+}
+
+#[test]
+fn test_crate_sizes() {
+    assert_eq!(parse_crate_size(r#"{"version":{"num":"1.0.0","crate_size":40960}}"#), Some(40960));
+    assert_eq!(parse_crate_size(r#"{"version":{"num":"1.0.0","crate_size":null}}"#), None);
+    assert_eq!(parse_crate_size("not json"), None);
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(14_541), "14.2 KiB");
+    assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+}
+
+// A ustar entry for a regular file, padded to whole blocks
+fn tar_entry(path: &str, contents: &str) -> Vec<u8> {
+    let mut header = [0u8; 512];
+    header[..path.len()].copy_from_slice(path.as_bytes());
+    header[100..107].copy_from_slice(b"0000644");
+    header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+    header[156] = b'0';
+    header[257..262].copy_from_slice(b"ustar");
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+    header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+
+    let mut entry = header.to_vec();
+    entry.extend_from_slice(contents.as_bytes());
+    entry.resize(512 + contents.len().div_ceil(512) * 512, 0);
+    entry
+}
+
+#[test]
+fn test_source_stats() {
+    let lib_rs = concat!(
+        "// unsafe { in a comment }\n",
+        "/* nested /* unsafe {} */ still a comment */\n",
+        "pub unsafe fn raw() {}\n",
+        "\n",
+        "unsafe impl Send for Thing {}\n",
+        "fn main() {\n",
+        "    let s = \"unsafe { not code }\";\n",
+        "    let r = r#\"unsafe {\"#;\n",
+        "    let c = '\"';\n",
+        "    unsafe { raw() }\n",
+        "}\n",
+    );
+    let mut archive = tar_entry("demo-0.1.0/Cargo.toml", "[package]\nname = \"demo\"\n");
+    archive.extend(tar_entry("demo-0.1.0/build.rs", "fn main() {}\n"));
+    archive.extend(tar_entry("demo-0.1.0/src/lib.rs", lib_rs));
+    archive.extend([0u8; 1024]);
+
+    let stats = source_stats(&archive).unwrap();
+    assert_eq!(stats.files, 3);
+    assert!(stats.has_build_script);
+    assert_eq!(stats.rust_files.len(), 2);
+    let lib = stats.rust_files.iter().find(|file| file.path == "src/lib.rs").unwrap();
+    assert_eq!((lib.lines, lib.code_lines), (11, 8));
+    // Only the real uses count, not those in comments or strings
+    assert_eq!((lib.unsafe_blocks, lib.unsafe_fns, lib.unsafe_impls), (1, 1, 1));
+
+    // A file cut short is reported rather than miscounted
+    assert!(source_stats(&archive[..1540]).is_err());
 }
 
 // Test error cases
@@ -174,9 +1131,8 @@ async fn test_lookup_crate_network_error() {
         .build()
         .unwrap();
     
-    let mut router = DocRouter::new();
-    // Override the client field
-    router.client = client;
+    // Inject a fetcher built around the short-timeout client
+    let router = DocRouter::with_fetcher(Arc::new(ReqwestFetcher::new(client)));
     
     let result = router.call_tool("lookup_crate", json!({
         "crate_name": "serde"
@@ -191,175 +1147,3167 @@ async fn test_lookup_crate_network_error() {
 
 #[tokio::test]
 async fn test_lookup_crate_with_mocks() {
-    // Since we can't easily modify the URL in the implementation to use a mock server,
-    // we'll skip the actual test but demonstrate the approach that would work if
-    // the URL was configurable for testing.
-    
-    // In a real scenario, we'd either:
-    // 1. Make the URL configurable for testing
-    // 2. Use dependency injection for the HTTP client
-    // 3. Use a test-specific implementation
-    
-    // For now, we'll just assert true to avoid test failure
-    assert!(true);
-}
-
-#[tokio::test]
-async fn test_lookup_crate_not_found() {
-    // Similar to the above test, we can't easily mock the HTTP responses without
-    // modifying the implementation. In a real scenario, we'd make the code more testable.
-    
-    assert!(true);
-}
-
-// Cache functionality tests
-#[tokio::test]
-async fn test_lookup_crate_uses_cache() {
-    let router = DocRouter::new();
-    
-    // Manually insert a cache entry to simulate a previous lookup
-    router.cache.set(
-        "test_crate".to_string(),
-        "Cached documentation for test_crate".to_string()
-    ).await;
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "crate:serde@1.0.0",
+        "<html><body><h1>serde 1.0.0</h1><p>A serialization framework</p></body></html>",
+    ));
+    let router = DocRouter::with_fetcher(fetcher.clone());
     
-    // Call the tool which should use the cache
     let result = router.call_tool("lookup_crate", json!({
-        "crate_name": "test_crate"
+        "crate_name": "serde",
+        "version": "1.0.0"
     })).await;
     
-    // Should succeed with cached content
-    assert!(result.is_ok());
+    // The HTML page should come back converted to markdown
     let contents = result.unwrap();
     assert_eq!(contents.len(), 1);
     if let Content::Text(text) = &contents[0] {
-        assert_eq!(text.text, "Cached documentation for test_crate");
+        assert!(text.text.contains("serde 1.0.0"));
+        assert!(text.text.contains("A serialization framework"));
+        assert!(!text.text.contains("<h1>"));
     } else {
         panic!("Expected text content");
     }
+    
+    // A second lookup should be served from the cache
+    router.call_tool("lookup_crate", json!({
+        "crate_name": "serde",
+        "version": "1.0.0"
+    })).await.unwrap();
+    assert_eq!(fetcher.requests().len(), 1);
 }
 
 #[tokio::test]
-async fn test_lookup_item_uses_cache() {
-    let router = DocRouter::new();
-    
-    // Manually insert a cache entry to simulate a previous lookup
-    router.cache.set(
-        "test_crate:test::path".to_string(),
-        "Cached documentation for test_crate::test::path".to_string()
-    ).await;
+async fn test_lookup_item_probes_item_types_with_mocks() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:tokio@latest/sync/mpsc/trait.Sender.html",
+        "<h1>Trait tokio::sync::mpsc::Sender</h1>",
+    ));
+    let router = DocRouter::with_fetcher(fetcher.clone());
     
-    // Call the tool which should use the cache
     let result = router.call_tool("lookup_item", json!({
-        "crate_name": "test_crate",
-        "item_path": "test::path"
-    })).await;
+        "crate_name": "tokio",
+        "item_path": "tokio::sync::mpsc::Sender"
+    })).await.unwrap();
     
-    // Should succeed with cached content
-    assert!(result.is_ok());
-    let contents = result.unwrap();
-    assert_eq!(contents.len(), 1);
-    if let Content::Text(text) = &contents[0] {
-        assert_eq!(text.text, "Cached documentation for test_crate::test::path");
+    if let Content::Text(text) = &result[0] {
+        assert!(text.text.contains("Trait tokio::sync::mpsc::Sender"));
     } else {
         panic!("Expected text content");
     }
+    
+    // struct and enum have higher priority, so they must have been probed too
+    let requests = fetcher.requests();
+    assert!(requests.contains(&"item:tokio@latest/sync/mpsc/struct.Sender.html".to_string()));
+    assert!(requests.contains(&"item:tokio@latest/sync/mpsc/enum.Sender.html".to_string()));
 }
 
-// The following tests require network access and are marked as ignored
-// These test the real API integration and should be run when specifically testing
-// network functionality
+#[tokio::test]
+async fn test_lookup_item_attribute_and_derive_macros() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("item:async-trait@0.1.0/attr.async_trait.html", "<h1>Attribute Macro async_trait</h1>")
+        .with_page("item:serde_derive@1.0.0/derive.Serialize.html", "<h1>Derive Macro Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let attr = router.call_tool("lookup_item", json!({
+        "crate_name": "async-trait",
+        "item_path": "async_trait",
+        "version": "0.1.0"
+    })).await.unwrap();
+    let Content::Text(attr) = &attr[0] else { panic!("Expected text content") };
+    assert!(attr.text.contains("Attribute Macro async_trait"));
+    
+    let derive = router.call_tool("lookup_item", json!({
+        "crate_name": "serde_derive",
+        "item_path": "Serialize",
+        "version": "1.0.0"
+    })).await.unwrap();
+    let Content::Text(derive) = &derive[0] else { panic!("Expected text content") };
+    assert!(derive.text.contains("Derive Macro Serialize"));
+}
+
+#[test]
+fn test_version_policy_requirements() {
+    assert_eq!(VersionPolicy::Caret.requirement("1.0.200"), "1.0.200");
+    assert_eq!(VersionPolicy::Minor.requirement("1.0.200"), "1.0");
+    assert_eq!(VersionPolicy::Major.requirement("1.0.200"), "1");
+    assert_eq!(VersionPolicy::Major.requirement("0.7.5"), "0.7");
+    assert_eq!(VersionPolicy::Exact.requirement("1.0.200"), "=1.0.200");
+    assert_eq!(VersionPolicy::Minor.requirement("2.0.0-rc.1"), "2.0");
+    assert_eq!(VersionPolicy::parse("exact"), Some(VersionPolicy::Exact));
+    assert_eq!(VersionPolicy::parse("loose"), None);
+}
 
 #[tokio::test]
-#[ignore = "Requires network access"]
-async fn test_lookup_crate_integration() {
-    let router = DocRouter::new();
-    let result = router.call_tool("lookup_crate", json!({
-        "crate_name": "serde"
-    })).await;
+async fn test_dependency_snippet_with_mocks() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/serde", r#"{"crate":{"max_stable_version":"1.0.200"}}"#)
+        .with_page("api:crates/serde/1.0.200", r#"{"version":{"num":"1.0.200","features":{"default":["std"],"derive":[],"std":[]}}}"#));
+    let router = DocRouter::with_fetcher(fetcher);
     
-    assert!(result.is_ok());
-    let contents = result.unwrap();
-    assert_eq!(contents.len(), 1);
-    if let Content::Text(text) = &contents[0] {
-        assert!(text.text.contains("serde"));
-    } else {
-        panic!("Expected text content");
-    }
+    let result = router.call_tool("dependency_snippet", json!({
+        "crate_name": "serde",
+        "features": ["derive", "rc"],
+        "version_policy": "minor"
+    })).await.unwrap();
+    
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("serde 1.0.200\n"));
+    assert!(text.text.contains("[dependencies]\nserde = { version = \"1.0\", features = [\"derive\", \"rc\"] }"));
+    assert!(text.text.contains("cargo add serde@1.0 --features derive,rc"));
+    // "rc" isn't declared by this version, so it gets flagged
+    assert!(text.text.contains("does not declare these features: rc"));
 }
 
 #[tokio::test]
-#[ignore = "Requires network access"]
-async fn test_search_crates_integration() {
-    let router = DocRouter::new();
-    let result = router.call_tool("search_crates", json!({
-        "query": "json",
-        "limit": 5
-    })).await;
+async fn test_dependency_snippet_errors() {
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()));
     
-    // Check for specific known error due to API changes
-    if let Err(ToolError::ExecutionError(e)) = &result {
-        if e.contains("Failed to search crates.io") {
-            // API may have changed, skip test
+    let result = router.call_tool("dependency_snippet", json!({ "crate_name": "no-such-crate" })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(msg)) if msg.contains("not found on crates.io")));
+    
+    let result = router.call_tool("dependency_snippet", json!({
+        "crate_name": "serde",
+        "version_policy": "loose"
+    })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_search_crates_with_mocks() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("search:json:5", r#"{"crates":[{"name":"serde_json"}]}"#));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("search_crates", json!({
+        "query": "json",
+        "limit": 5
+    })).await.unwrap();
+    
+    if let Content::Text(text) = &result[0] {
+        assert!(text.text.contains("serde_json"));
+    } else {
+        panic!("Expected text content");
+    }
+}
+
+#[test]
+fn test_doc_page_resolved_version() {
+    let page = |url: &str| DocPage { url: url.to_string(), html: String::new() };
+    
+    assert_eq!(page("https://docs.rs/crate/tokio/1.43.0/").resolved_version("tokio"), Some("1.43.0".to_string()));
+    assert_eq!(
+        page("https://docs.rs/tokio/1.43.0/tokio/sync/struct.Mutex.html").resolved_version("tokio"),
+        Some("1.43.0".to_string())
+    );
+    assert_eq!(page("https://docs.rs/crate/tokio/latest/").resolved_version("tokio"), None);
+    assert_eq!(page("https://docs.rs/crate/serde/1.0.0/").resolved_version("tokio"), None);
+}
+
+#[test]
+fn test_is_rust_channel() {
+    assert!(is_rust_channel("stable"));
+    assert!(is_rust_channel("nightly"));
+    assert!(is_rust_channel("1.75.0"));
+    assert!(!is_rust_channel("latest"));
+    assert!(!is_rust_channel("1.75"));
+    assert!(!is_rust_channel("1.x.0"));
+}
+
+#[test]
+fn test_unstable_features() {
+    let html = r#"<span class="item-info"><div class="stab unstable"><span class="emoji">🔬</span>
+        <span>This is a nightly-only experimental API. (<code>allocator_api</code>&nbsp;<a href="https://github.com/rust-lang/rust/issues/32838">#32838</a>)</span></div></span>
+        <div class="stab unstable"><span>(<code>vec_push_within_capacity</code>)</span></div>
+        <div class="stab unstable"><span>(<code>allocator_api</code>)</span></div>
+        <div class="stab deprecated"><code>old_api</code></div>"#;
+    assert_eq!(unstable_features(html), vec!["allocator_api", "vec_push_within_capacity"]);
+    assert!(unstable_features("<h1>Struct std::vec::Vec</h1>").is_empty());
+}
+
+#[tokio::test]
+async fn test_lookup_item_nightly_channel_lists_feature_gates() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:alloc@nightly/boxed/struct.ThinBox.html",
+        r#"<h1>Struct alloc::boxed::ThinBox</h1><div class="stab unstable">(<code>thin_box</code>)</div>"#,
+    ));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let result = router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "alloc", "item_path": "boxed::ThinBox", "channel": "nightly" }),
+        )
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for alloc nightly\n"));
+    assert!(text.text.contains(
+        "`#![feature(thin_box)]` — https://doc.rust-lang.org/nightly/unstable-book/library-features/thin-box.html"
+    ));
+    // The standard library has no crates.io entry to resolve a version from
+    assert!(fetcher.requests().iter().all(|key| !key.starts_with("api:")));
+}
+
+#[tokio::test]
+async fn test_channel_argument_validation() {
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()));
+    let cases = [
+        json!({ "crate_name": "serde", "item_path": "Serialize", "channel": "nightly" }),
+        json!({ "crate_name": "std", "item_path": "vec::Vec", "channel": "dev" }),
+        json!({ "crate_name": "std", "item_path": "vec::Vec", "channel": "beta", "version": "1.75.0" }),
+    ];
+    for arguments in cases {
+        let result = router.call_tool("lookup_item", arguments.clone()).await;
+        assert!(matches!(result, Err(ToolError::InvalidParameters(_))), "{}", arguments);
+    }
+}
+
+#[tokio::test]
+async fn test_configure_session() {
+    let overview = format!("<h1>serde</h1><p>A serialization framework</p><h2>Modules</h2><p>{}</p>", "de ser ".repeat(200));
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:serde@1.0.0", &overview)
+        .with_page("item:alloc@nightly/boxed/struct.ThinBox.html", "<h1>Struct alloc::boxed::ThinBox</h1>"));
+    let shared = DocRouter::with_fetcher(fetcher);
+    let (router, other_session) = (shared.for_session(), shared.for_session());
+    let text = |result: Vec<Content>| match &result[0] {
+        Content::Text(text) => text.text.clone(),
+        _ => panic!("Expected text content"),
+    };
+
+    let settings = text(router.call_tool("configure_session", json!({})).await.unwrap());
+    assert_eq!(settings, r#"{"channel":null,"format":"markdown","summary":false,"token_budget":null}"#);
+
+    let settings = text(router
+        .call_tool("configure_session", json!({ "channel": "nightly", "default_format": "text", "summary": true }))
+        .await
+        .unwrap());
+    assert_eq!(settings, r#"{"channel":"nightly","format":"text","summary":true,"token_budget":null}"#);
+
+    // Summaries in plain text, unless a call asks otherwise
+    let doc = text(router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" })).await.unwrap());
+    assert!(doc.contains("A serialization framework") && !doc.contains("Modules") && !doc.contains("# serde"), "{}", doc);
+    assert!(doc.contains("[summary: pass summary=false for the whole page]"), "{}", doc);
+    let doc = text(router
+        .call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0", "summary": false, "format": "markdown" }))
+        .await
+        .unwrap());
+    assert!(doc.contains("## Modules"));
+
+    // std lookups without a version use the session's channel
+    let doc = text(router.call_tool("lookup_item", json!({ "crate_name": "alloc", "item_path": "boxed::ThinBox" })).await.unwrap());
+    assert!(doc.starts_with("Documentation for alloc nightly"));
+
+    // A token budget cuts results off with the offset to continue from
+    router.call_tool("configure_session", json!({ "reset": true, "token_budget": 100 })).await.unwrap();
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" })).await.unwrap();
+    assert_eq!(result.len(), 1);
+    let doc = text(result);
+    assert!(doc.len() <= 100 * BYTES_PER_TOKEN && doc.contains("[truncated: use offset="), "{}", doc);
+
+    // Other sessions keep their own settings
+    let doc = text(other_session.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" })).await.unwrap());
+    assert!(doc.contains("## Modules") && !doc.contains("[truncated"));
+
+    for arguments in [json!({ "channel": "dev" }), json!({ "default_format": "pdf" })] {
+        let result = router.call_tool("configure_session", arguments.clone()).await;
+        assert!(matches!(result, Err(ToolError::InvalidParameters(_))), "{}", arguments);
+    }
+}
+
+#[tokio::test]
+async fn test_lookup_crate_reports_resolved_version() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:tokio@latest", "<h1>tokio</h1>")
+        .with_final_url("crate:tokio@latest", "https://docs.rs/crate/tokio/1.43.0/"));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "tokio" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for tokio 1.43.0\nSource: https://docs.rs/crate/tokio/1.43.0/\n"));
+    
+    // The page is cached under the concrete version as well
+    assert_eq!(router.cache.get("tokio:1.43.0").await, Some(text.text.clone()));
+}
+
+#[tokio::test]
+async fn test_latest_version_tool() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/serde", r#"{"crate":{"max_stable_version":"1.0.200","max_version":"1.0.201-rc.1"}}"#)
+        .with_page("crate:serde@1.0.200", "<h1>serde</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    // Without a sparse index the crates.io API answers
+    let result = router.call_tool("latest_version", json!({ "crate_name": "serde" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&text.text).unwrap(),
+        json!({ "crate_name": "serde", "latest": "1.0.200", "latest_prerelease": "1.0.201-rc.1" })
+    );
+
+    // The version is remembered for unversioned lookups
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    assert_eq!(fetcher.requests(), vec!["api:crates/serde".to_string(), "crate:serde@1.0.200".to_string()]);
+}
+
+#[tokio::test]
+async fn test_unversioned_lookup_cached_under_latest_version() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/serde", r#"{"crate":{"max_stable_version":"1.0.200","max_version":"1.0.201-rc.1"}}"#)
+        .with_page("crate:serde@1.0.200", "<h1>serde</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+    
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.200" })).await.unwrap();
+    
+    // One resolution and one page fetch, shared with the explicitly versioned lookup
+    assert_eq!(fetcher.requests(), vec!["api:crates/serde".to_string(), "crate:serde@1.0.200".to_string()]);
+    assert!(router.cache.get("serde:1.0.200").await.is_some());
+    assert!(router.cache.get("serde").await.is_none());
+}
+
+#[test]
+fn test_crate_policy() {
+    let policy = CratePolicy::new(&["tokio".to_string(), "Serde_*".to_string()], &["serde-xml".to_string()]);
+    assert!(policy.check("tokio").is_ok());
+    assert!(policy.check("serde_json").is_ok());
+    assert!(policy.check("std").is_ok());
+    let err = policy.check("serde_xml").unwrap_err();
+    assert_eq!(error_data(&err).unwrap().code, ErrorCode::CrateNotAllowed);
+    assert!(err.to_string().contains("Crate serde_xml is on this server's crate denylist"));
+    assert!(policy.check("axum").unwrap_err().to_string().contains("is not on this server's crate allowlist"));
+
+    assert!(CratePolicy::new(&[], &[]).is_empty());
+    let deny_only = CratePolicy::new(&[], &["left-pad".to_string()]);
+    assert!(deny_only.check("axum").is_ok());
+    assert!(deny_only.check("left_pad").is_err());
+}
+
+#[tokio::test]
+async fn test_crate_policy_refuses_before_fetching() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/tokio", r#"{"crate":{"max_version":"1.0.0"}}"#)
+        .with_page("crate:tokio@1.0.0", "<h1>tokio</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone()).with_crate_policy(CratePolicy::new(&[], &["serde".to_string()]));
+
+    let err = router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap_err();
+    assert_eq!(error_data(&err).unwrap().code, ErrorCode::CrateNotAllowed);
+    let items = json!({ "items": [{ "crate_name": "tokio", "item_path": "spawn" }, { "crate_name": "serde", "item_path": "Serialize" }] });
+    assert!(router.call_tool("lookup_items", items).await.is_err());
+    assert!(fetcher.requests().is_empty());
+
+    // Requests for refused crates made on behalf of other tools stop at the fetcher
+    assert!(router.fetcher.get_crates_io_api("crates/serde/1.0.0/readme").await.is_err());
+    assert!(router.fetcher.get_index_entry("serde").await.is_err());
+    assert!(fetcher.requests().is_empty());
+
+    router.call_tool("lookup_crate", json!({ "crate_name": "tokio" })).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_crate_policy_wraps_fetchers_added_later() {
+    let policy = CratePolicy::new(&[], &["serde".to_string()]);
+    let refused = |result: Result<DocPage, ToolError>| {
+        result.is_err_and(|e| error_data(&e).is_some_and(|data| data.code == ErrorCode::CrateNotAllowed))
+    };
+
+    // Mirrored and locally built docs are refused as well as upstream ones
+    let mirror_dir = std::env::temp_dir().join(format!("cratedocs-policy-mirror-{}", std::process::id()));
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()))
+        .with_crate_policy(policy.clone())
+        .with_mirror(Mirror::new(&mirror_dir), true);
+    assert!(refused(router.fetcher.get_crate_page("serde", Some("1.0.0")).await));
+
+    let local = LocalDocs::new(Vec::new());
+    local.register("serde", std::env::temp_dir()).await;
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()))
+        .with_crate_policy(policy)
+        .with_local_docs(local);
+    assert!(refused(router.fetcher.get_crate_page("serde", None).await));
+}
+
+#[tokio::test]
+async fn test_latest_alias_expires() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/serde", r#"{"crate":{"max_version":"1.0.200"}}"#)
+        .with_page("crate:serde@1.0.200", "<h1>serde</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone()).with_latest_ttl(Duration::ZERO);
+    
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    
+    // "latest" is re-resolved every time, but the page itself stays cached
+    assert_eq!(fetcher.requests(), vec![
+        "api:crates/serde".to_string(),
+        "crate:serde@1.0.200".to_string(),
+        "api:crates/serde".to_string(),
+    ]);
+}
+
+#[tokio::test]
+async fn test_refresh_popular() {
+    let old = Arc::new(MockFetcher::default()
+        .with_page("api:crates/demo", r#"{"crate":{"max_version":"1.0.0"}}"#)
+        .with_page("crate:demo@1.0.0", "<h1>demo 1.0.0</h1>")
+        .with_page("item:demo@1.0.0/struct.Widget.html", "<h1>Struct Widget</h1>"));
+    let router = DocRouter::with_fetcher(old).with_latest_ttl(Duration::ZERO);
+    router.call_tool("lookup_crate", json!({ "crate_name": "demo" })).await.unwrap();
+    router.call_tool("lookup_crate", json!({ "crate_name": "demo" })).await.unwrap();
+    router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Widget" })).await.unwrap();
+    assert_eq!(router.cache.most_accessed(1).await, vec!["demo:1.0.0".to_string()]);
+    // Entries that aren't docs don't take the places of docs entries
+    for _ in 0..5 {
+        router.cache.get("probe-stats:demo").await;
+    }
+    assert_eq!(router.cache.most_accessed(1).await, vec!["probe-stats:demo".to_string()]);
+
+    // 1.1.0 is published; the popular entries are fetched again at it
+    let new = Arc::new(MockFetcher::default()
+        .with_page("api:crates/demo", r#"{"crate":{"max_version":"1.1.0"}}"#)
+        .with_page("crate:demo@1.1.0", "<h1>demo 1.1.0</h1>")
+        .with_page("item:demo@1.1.0/struct.Widget.html", "<h1>Struct Widget</h1>"));
+    let mut refreshing = DocRouter::with_fetcher(new.clone()).with_cache(router.cache.clone());
+    refreshing.latest_versions = router.latest_versions.clone();
+    assert_eq!(refreshing.refresh_popular(2).await, 2);
+    let requests = new.requests();
+    assert!(requests.contains(&"crate:demo@1.1.0".to_string()));
+    assert!(requests.contains(&"item:demo@1.1.0/struct.Widget.html".to_string()));
+    assert_eq!(refreshing.latest_versions.get_stale("demo").await, Some("1.1.0".to_string()));
+
+    // Nothing is due while the resolved version is fresh
+    let fresh = DocRouter::with_fetcher(new.clone()).with_cache(router.cache.clone());
+    fresh.latest_versions.set("demo", "1.0.0").await;
+    let count = new.requests().len();
+    assert_eq!(fresh.refresh_popular(10).await, 0);
+    assert_eq!(new.requests().len(), count);
+}
+
+#[test]
+fn test_member_section_extracts_single_method() {
+    let html = include_str!("../../../tests/fixtures/struct_page.html");
+    
+    let (anchor, section) = member_section(html, "send").unwrap();
+    assert_eq!(anchor, "method.send");
+    let markdown = crate::tools::html_to_markdown(&section);
+    assert!(markdown.contains("pub async fn"));
+    assert!(markdown.contains("Sends a value, waiting until there is capacity."));
+    assert!(!markdown.contains("Checks if the channel has been closed"));
+    
+    assert!(member_section(html, "no_such_method").is_none());
+}
+
+#[test]
+fn test_anchor_section_older_rustdoc_layout() {
+    let html = r#"<h4 id="method.len" class="method"><code>pub fn len(&amp;self) -&gt; usize</code></h4>
+        <div class="docblock"><p>Returns the length.</p></div>
+        <h4 id="method.is_empty" class="method"><code>pub fn is_empty(&amp;self) -&gt; bool</code></h4>
+        <div class="docblock"><p>Returns true if empty.</p></div>"#;
+    
+    let section = anchor_section(html, "method.len").unwrap();
+    assert!(section.contains("pub fn len"));
+    assert!(section.contains("Returns the length."));
+    assert!(!section.contains("is_empty"));
+}
+
+#[tokio::test]
+async fn test_lookup_item_method_of_type() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:tokio@latest/sync/mpsc/struct.Sender.html",
+        include_str!("../../../tests/fixtures/struct_page.html"),
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("lookup_item", json!({
+        "crate_name": "tokio",
+        "item_path": "tokio::sync::mpsc::Sender::send"
+    })).await.unwrap();
+    
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("/sync/mpsc/struct.Sender.html#method.send"));
+    assert!(text.text.contains("Sends a value, waiting until there is capacity."));
+    assert!(!text.text.contains("Checks if the channel has been closed"));
+}
+
+#[tokio::test]
+async fn test_lookup_item_trait_methods() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:futures@0.3.0/stream/trait.Stream.html",
+        include_str!("../../../tests/fixtures/trait_page.html"),
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let required = router.call_tool("lookup_item", json!({
+        "crate_name": "futures",
+        "item_path": "stream::Stream::poll_next",
+        "version": "0.3.0"
+    })).await.unwrap();
+    let Content::Text(required) = &required[0] else { panic!("Expected text content") };
+    assert!(required.text.contains("#tymethod.poll_next"));
+    assert!(required.text.contains("Required method of trait Stream"));
+    assert!(required.text.contains("poll_next"));
+    assert!(!required.text.contains("size_hint"));
+    
+    let provided = router.call_tool("lookup_item", json!({
+        "crate_name": "futures",
+        "item_path": "stream::Stream::size_hint",
+        "version": "0.3.0"
+    })).await.unwrap();
+    let Content::Text(provided) = &provided[0] else { panic!("Expected text content") };
+    assert!(provided.text.contains("Provided method of trait Stream, with a default implementation"));
+    assert!(provided.text.contains("https://docs.rs/futures/0.3.0/src/futures_core/stream.rs.html#94-96"));
+}
+
+#[tokio::test]
+async fn test_lookup_item_enum_variant() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:serde_json@1.0.0/enum.Value.html",
+        include_str!("../../../tests/fixtures/enum_page.html"),
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("lookup_item", json!({
+        "crate_name": "serde_json",
+        "item_path": "serde_json::Value::Bool",
+        "version": "1.0.0"
+    })).await.unwrap();
+    
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("#variant.Bool"));
+    assert!(text.text.contains("Represents a JSON boolean."));
+    assert!(!text.text.contains("Represents a JSON number"));
+    assert!(text.text.contains("Other variants of Value:"));
+    assert!(text.text.contains("Null"));
+    assert!(text.text.contains("Object"));
+}
+
+#[tokio::test]
+async fn test_lookup_item_struct_field() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:core@1.0.0/ops/struct.Range.html",
+        include_str!("../../../tests/fixtures/struct_fields_page.html"),
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("lookup_item", json!({
+        "crate_name": "core",
+        "item_path": "ops::Range::start",
+        "version": "1.0.0"
+    })).await.unwrap();
+    
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("#structfield.start"));
+    // The field's declaration carries its type
+    assert!(text.text.contains("start: Idx"));
+    assert!(text.text.contains("The lower bound of the range (inclusive)."));
+    assert!(!text.text.contains("The upper bound"));
+    assert!(text.text.contains("Other fields of Range:"));
+}
+
+#[test]
+fn test_member_names_skips_nested_anchors() {
+    let html = r#"<section id="variant.Unit"></section>
+        <section id="variant.Struct"></section>
+        <span id="variant.Struct.field.inner"></span>
+        <section id="method.new"></section>"#;
+    assert_eq!(member_names(html, "variant"), vec!["Unit".to_string(), "Struct".to_string()]);
+}
+
+#[tokio::test]
+async fn test_preload_caches_listed_crates() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:tokio@latest", "<h1>tokio</h1>")
+        .with_page("crate:axum@0.7", "<h1>axum 0.7</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+    
+    let specs = vec!["tokio".to_string(), "axum@0.7".to_string(), "missing".to_string()];
+    assert_eq!(router.preload(&specs).await, 2);
+    assert!(router.cache.get("tokio").await.is_some());
+    assert!(router.cache.get("axum:0.7").await.is_some());
+    
+    // Preloaded crates are served without another upstream request
+    router.call_tool("lookup_crate", json!({ "crate_name": "axum", "version": "0.7" })).await.unwrap();
+    // tokio and missing are resolved via crates.io (404 here) before their pages are fetched,
+    // and missing asks crates.io once more for a repository to fall back to
+    assert_eq!(fetcher.requests().len(), 6);
+}
+
+const CARGO_LOCK: &str = r#"# This file is automatically @generated by Cargo.
+# It is not intended for manual editing.
+version = 4
+
+[[package]]
+name = "axum"
+version = "0.7.5"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "3a6c9af12842a67734c9a2e355436e5d03b22383ed60cf13cd0c18fbfe3dcbcf"
+dependencies = [
+ "tokio",
+]
+
+[[package]]
+name = "myapp"
+version = "0.1.0"
+dependencies = [
+ "axum",
+]
+
+[[package]]
+name = "private-utils"
+version = "1.2.0"
+source = "git+https://github.com/someone/private-utils#0f3b2a1c"
+
+[[package]]
+name = "tokio"
+version = "1.43.0"
+source = "sparse+https://index.crates.io/"
+
+[metadata]
+name = "not a package"
+"#;
+
+#[test]
+fn test_parse_lockfile() {
+    let package = |name: &str, version: &str, from_crates_io: bool| LockedPackage {
+        name: name.to_string(),
+        version: version.to_string(),
+        from_crates_io,
+    };
+    assert_eq!(
+        parse_lockfile(CARGO_LOCK).unwrap(),
+        [
+            package("axum", "0.7.5", true),
+            package("myapp", "0.1.0", false),
+            package("private-utils", "1.2.0", false),
+            package("tokio", "1.43.0", true),
+        ]
+    );
+    assert!(parse_lockfile("[package]\nname = \"myapp\"\n").is_err());
+}
+
+#[tokio::test]
+async fn test_warm_cache_from_lockfile() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:axum@0.7.5", "<h1>axum</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let result = router.call_tool("warm_cache", json!({ "lockfile": CARGO_LOCK })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert_eq!(
+        text.text,
+        "Cached the docs of 1 of 2 crates.io packages locked in Cargo.lock; 1 could not be fetched; \
+         skipped 2 path, git and other-registry packages\n"
+    );
+    assert!(router.cache.get("axum:0.7.5").await.is_some());
+
+    // Locked versions are fetched as they are, without resolving "latest"
+    let requests = fetcher.requests();
+    assert!(requests.contains(&"crate:axum@0.7.5".to_string()));
+    assert!(requests.contains(&"crate:tokio@1.43.0".to_string()));
+    assert!(!requests.iter().any(|request| request.ends_with("@latest")));
+
+    let result = router.call_tool("warm_cache", json!({ "lockfile": "not a lockfile" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+const PROJECT_MANIFEST: &str = r#"[package]
+name = "myapp"  # the app
+version = "0.1.0"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+tokio = "1.38"
+util = { path = "../util" }
+json = { package = "serde_json", version = "1", optional = true }
+log.workspace = true
+
+[dependencies.axum]
+version = "0.7"
+default-features = false
+features = [
+    "json",   # request bodies
+    "tokio",
+]
+
+[target.'cfg(unix)'.dependencies]
+nix = "0.29"
+
+[dev-dependencies]
+wiremock = "0.6"
+
+[features]
+default = ["json"]
+"#;
+
+#[test]
+fn test_parse_manifest_dependencies() {
+    assert_eq!(manifest_package_name(PROJECT_MANIFEST).as_deref(), Some("myapp"));
+    let dependencies = parse_manifest_dependencies(PROJECT_MANIFEST).unwrap();
+    let names: Vec<(&str, &str, DependencyKind)> = dependencies
+        .iter()
+        .map(|dependency| (dependency.name.as_str(), dependency.crate_name(), dependency.kind))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("serde", "serde", DependencyKind::Normal),
+            ("tokio", "tokio", DependencyKind::Normal),
+            ("util", "util", DependencyKind::Normal),
+            ("json", "serde_json", DependencyKind::Normal),
+            ("log", "log", DependencyKind::Normal),
+            ("axum", "axum", DependencyKind::Normal),
+            ("nix", "nix", DependencyKind::Normal),
+            ("wiremock", "wiremock", DependencyKind::Dev),
+        ]
+    );
+    assert_eq!(dependencies[0].features, ["derive"]);
+    assert_eq!(dependencies[1].req.as_deref(), Some("1.38"));
+    assert_eq!(dependencies[2].source.as_deref(), Some("path ../util"));
+    assert!(dependencies[3].optional);
+    assert!(dependencies[4].workspace && dependencies[4].req.is_none());
+    assert_eq!(dependencies[5].req.as_deref(), Some("0.7"));
+    assert!(!dependencies[5].default_features);
+    assert_eq!(dependencies[5].features, ["json", "tokio"]);
+    assert_eq!(dependencies[6].target.as_deref(), Some("cfg(unix)"));
+
+    // The crate's own manifest
+    let dependencies = parse_manifest_dependencies(include_str!("../../../Cargo.toml")).unwrap();
+    let tokio = dependencies.iter().find(|dependency| dependency.name == "tokio").unwrap();
+    assert_eq!(tokio.features, ["full"]);
+    let mcp_server = dependencies.iter().find(|dependency| dependency.name == "mcp-server").unwrap();
+    assert!(mcp_server.source.as_deref().is_some_and(|source| source.starts_with("git https://github.com/")));
+
+    assert!(parse_manifest_dependencies("just some text").is_err());
+}
+
+#[tokio::test]
+async fn test_project_dependencies() {
+    let manifest = r#"[package]
+name = "myapp"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+util = { path = "../util" }
+
+[dev-dependencies]
+nosuchcrate = "1"
+"#;
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "api:crates/serde",
+        r#"{"crate": {"name": "serde", "max_stable_version": "1.0.215", "description": "A generic serialization/deserialization\n framework", "downloads": 1}}"#,
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("project_dependencies", json!({ "manifest": manifest })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert_eq!(
+        text.text,
+        "# Dependencies of myapp\n\
+         \n## Dependencies (2)\n\
+         \n### serde\n\
+         A generic serialization/deserialization framework\n\
+         - Version: `1.0`; latest is 1.0.215\n\
+         - Docs: https://docs.rs/serde/1.0.215\n\
+         - Features: enabled `derive`\n\
+         \n### util\n\
+         - Not from crates.io: path ../util\n\
+         \n## Dev dependencies (1)\n\
+         \n### nosuchcrate\n\
+         - Not found on crates.io\n"
+    );
+
+    // Reading a manifest by path is limited to the local docs roots
+    let result = router.call_tool("project_dependencies", json!({ "path": "." })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[test]
+fn test_imported_paths() {
+    let source = r#"
+use std::collections::{HashMap, hash_map::Entry};
+use serde::{Deserialize, Serialize as Ser};
+use crate::config::Config;
+use serde_json as json;
+use tokio::sync::mpsc;
+
+// use commented::Out;
+fn main() {
+    let value: json::Value = json::from_str("use quoted::Thing;").unwrap();
+    let (tx, rx) = mpsc::channel::<u8>(1);
+    let order = std::cmp::Ordering::Less;
+    let quote = '"';
+    let client = reqwest::Client::new();
+    let user = models::User::default();
+}
+"#;
+    assert_eq!(
+        imported_paths(source, &["reqwest".to_string()]),
+        vec![
+            "std::collections::HashMap",
+            "std::collections::hash_map::Entry",
+            "serde::Deserialize",
+            "serde::Serialize",
+            "tokio::sync::mpsc",
+            "serde_json::Value",
+            "serde_json::from_str",
+            "tokio::sync::mpsc::channel",
+            "std::cmp::Ordering",
+            "reqwest::Client",
+        ]
+    );
+    assert!(imported_paths("fn main() {}", &[]).is_empty());
+}
+
+#[tokio::test]
+async fn test_explain_imports() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page(
+            "item:serde@latest/trait.Serialize.html",
+            "<h1>Trait serde::Serialize</h1><p>A data structure that can be serialized.</p><h2>Required Methods</h2><p>fn serialize</p>",
+        )
+        .with_page("item:serde_json@latest/enum.Value.html", "<h1>Enum serde_json::Value</h1><p>Any valid JSON value.</p>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router
+        .call_tool("explain_imports", json!({ "source": "use serde::Serialize;\nuse crate::util::helper;\n" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# Imports\n\n## serde::Serialize\n\n"));
+    assert!(text.text.contains("A data structure that can be serialized."));
+    // Only the opening of the item's page is kept
+    assert!(!text.text.contains("Required Methods"));
+    assert!(!text.text.contains("helper"));
+
+    // The manifest maps renamed dependencies, and roots it doesn't know are searched for
+    let manifest = r#"[package]
+name = "myapp"
+
+[dependencies]
+json = { package = "serde_json", version = "1" }
+util = { path = "../util" }
+"#;
+    let source = "use json::Value;\nuse util::Helper;\nuse models::User;\n";
+    let result = router.call_tool("explain_imports", json!({ "source": source, "manifest": manifest })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("## json::Value\n\n"));
+    assert!(text.text.contains("Any valid JSON value."));
+    assert!(text.text.contains("## util::Helper\n\nFrom the util (path ../util) dependency, which has no docs on docs.rs"));
+    assert!(text.text.contains("## models::User\n\nNo docs at this path; crates defining the name:\n\nNo item named `User` found"));
+
+    let result = router.call_tool("explain_imports", json!({ "source": "fn main() {}" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_run_batch_line() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("item:serde@1.0.0/trait.Serialize.html", "<h1>Trait Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let line = r#"{"id": "a", "tool": "lookup_item", "arguments": {"crate_name": "serde", "item_path": "Serialize", "version": "1.0.0"}}"#;
+    let result = run_batch_line(&router, 1, line).await.unwrap();
+    assert_eq!(result["line"], 1);
+    assert_eq!(result["id"], "a");
+    assert_eq!(result["tool"], "lookup_item");
+    assert!(result["content"][0].as_str().unwrap().contains("Trait Serialize"));
+    assert!(result.get("error").is_none());
+
+    // `name` works as in MCP requests, and a failed call is reported rather than ending the batch
+    let result = run_batch_line(&router, 2, r#"{"name": "lookup_item", "arguments": {"crate_name": "serde"}}"#).await.unwrap();
+    assert_eq!(result["id"], serde_json::Value::Null);
+    assert!(result["error"]["message"].as_str().unwrap().ends_with("item_path is required"));
+    assert_eq!(result["error"]["code"], "INVALID_ARGUMENTS");
+    assert!(result.get("content").is_none());
+
+    let result = run_batch_line(&router, 3, "not json").await.unwrap();
+    assert_eq!(result["line"], 3);
+    assert!(result["error"]["message"].as_str().unwrap().starts_with("Invalid tool invocation"));
+
+    assert!(run_batch_line(&router, 4, "   ").await.is_none());
+}
+
+#[test]
+fn test_linked_item_paths_orders_by_link_count() {
+    let html = r#"
+        <a href="https://docs.rs/my-crate/latest/my_crate/struct.Client.html">Client</a>
+        <a href="/my-crate/1.0.0/my_crate/sync/struct.Mutex.html#method.lock">lock</a>
+        <a href="/my-crate/1.0.0/my_crate/sync/struct.Mutex.html">Mutex</a>
+        <a href="/my-crate/1.0.0/my_crate/sync/index.html">sync</a>
+        <a href="https://docs.rs/other/latest/other/struct.Thing.html">other crate</a>
+        <a href="struct.Relative.html">relative</a>
+    "#;
+    
+    let paths = linked_item_paths(html, "my-crate");
+    assert_eq!(paths, vec!["sync::Mutex".to_string(), "Client".to_string()]);
+}
+
+#[test]
+fn test_parse_direct_dependencies_skips_optional_and_dev() {
+    let json = r#"{"dependencies":[
+        {"crate_id":"bytes","kind":"normal","optional":false},
+        {"crate_id":"serde","kind":"normal","optional":true},
+        {"crate_id":"tokio-test","kind":"dev","optional":false}
+    ]}"#;
+    assert_eq!(parse_direct_dependencies(json), vec!["bytes".to_string()]);
+    assert!(parse_direct_dependencies("not json").is_empty());
+}
+
+#[tokio::test]
+async fn test_prefetch_warms_items_and_dependencies() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page(
+            "crate:demo@1.2.0",
+            r#"<p>See <a href="/demo/1.2.0/demo/widgets/struct.Widget.html">Widget</a></p>"#,
+        )
+        .with_page("item:demo@1.2.0/widgets/struct.Widget.html", "<h1>Struct Widget</h1>")
+        .with_page("api:crates/demo", r#"{"crate":{"max_version":"1.2.0"}}"#)
+        .with_page(
+            "api:crates/demo/1.2.0/dependencies",
+            r#"{"dependencies":[{"crate_id":"dep_a","kind":"normal","optional":false}]}"#,
+        )
+        .with_page("crate:dep_a@latest", "<h1>dep_a</h1>"));
+    let router = DocRouter::with_fetcher(fetcher).with_prefetch(PrefetchConfig::default());
+    
+    router.call_tool("lookup_crate", json!({ "crate_name": "demo" })).await.unwrap();
+    
+    // Prefetching happens in the background; give it a moment to finish
+    for _ in 0..100 {
+        if router.cache.get("demo:1.2.0:widgets::Widget").await.is_some()
+            && router.cache.get("dep_a").await.is_some()
+        {
             return;
         }
+        tokio::time::sleep(Duration::from_millis(10)).await;
     }
+    panic!("Related documentation was not prefetched");
+}
+
+#[tokio::test]
+async fn test_prefetch_is_disabled_by_default() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:demo@latest", r#"<a href="/demo/latest/demo/struct.Widget.html">W</a>"#));
+    let router = DocRouter::with_fetcher(fetcher.clone());
     
-    // If it's not a known API error, proceed with normal assertions
-    assert!(result.is_ok(), "Error: {:?}", result);
+    router.call_tool("lookup_crate", json!({ "crate_name": "demo" })).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    
+    assert_eq!(fetcher.requests(), vec!["api:crates/demo".to_string(), "crate:demo@latest".to_string()]);
+}
+
+// Cache functionality tests
+#[tokio::test]
+async fn test_lookup_crate_uses_cache() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/test_crate", r#"{"crate":{"max_version":"1.0.0"}}"#));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    // Manually insert a cache entry to simulate a previous lookup of the latest version
+    router.cache.set(
+        "test_crate:1.0.0".to_string(),
+        "Cached documentation for test_crate".to_string()
+    ).await;
+    
+    // Call the tool which should use the cache
+    let result = router.call_tool("lookup_crate", json!({
+        "crate_name": "test_crate"
+    })).await;
+    
+    // Should succeed with cached content
+    assert!(result.is_ok());
     let contents = result.unwrap();
     assert_eq!(contents.len(), 1);
     if let Content::Text(text) = &contents[0] {
-        assert!(text.text.contains("crates"));
+        assert_eq!(text.text, "Cached documentation for test_crate");
     } else {
         panic!("Expected text content");
     }
 }
 
 #[tokio::test]
-#[ignore = "Requires network access"]
-async fn test_lookup_item_integration() {
-    let router = DocRouter::new();
+async fn test_lookup_item_uses_cache() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/test_crate", r#"{"crate":{"max_version":"1.0.0"}}"#));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    // Manually insert a cache entry to simulate a previous lookup of the latest version
+    router.cache.set(
+        "test_crate:1.0.0:test::path".to_string(),
+        "Cached documentation for test_crate::test::path".to_string()
+    ).await;
+    
+    // Call the tool which should use the cache
     let result = router.call_tool("lookup_item", json!({
-        "crate_name": "serde",
-        "item_path": "ser::Serializer"
+        "crate_name": "test_crate",
+        "item_path": "test::path"
     })).await;
     
-    // Check for specific known error due to API changes
-    if let Err(ToolError::ExecutionError(e)) = &result {
-        if e.contains("Failed to fetch item documentation") {
-            // API may have changed, skip test
-            return;
-        }
-    }
-    
-    // If it's not a known API error, proceed with normal assertions
-    assert!(result.is_ok(), "Error: {:?}", result);
+    // Should succeed with cached content
+    assert!(result.is_ok());
     let contents = result.unwrap();
     assert_eq!(contents.len(), 1);
     if let Content::Text(text) = &contents[0] {
-        assert!(text.text.contains("Serializer"));
+        assert_eq!(text.text, "Cached documentation for test_crate::test::path");
     } else {
         panic!("Expected text content");
     }
 }
 
+// The following tests require network access and are marked as ignored
+// These test the real API integration and should be run when specifically testing
+// network functionality
+
 #[tokio::test]
 #[ignore = "Requires network access"]
-async fn test_search_crates_with_version() {
+async fn test_lookup_crate_integration() {
     let router = DocRouter::new();
     let result = router.call_tool("lookup_crate", json!({
-        "crate_name": "tokio",
-        "version": "1.0.0"
+        "crate_name": "serde"
     })).await;
     
     assert!(result.is_ok());
     let contents = result.unwrap();
     assert_eq!(contents.len(), 1);
     if let Content::Text(text) = &contents[0] {
-        assert!(text.text.contains("tokio"));
-        assert!(text.text.contains("1.0.0"));
+        assert!(text.text.contains("serde"));
     } else {
         panic!("Expected text content");
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+#[ignore = "Requires network access"]
+async fn test_search_crates_integration() {
+    let router = DocRouter::new();
+    let result = router.call_tool("search_crates", json!({
+        "query": "json",
+        "limit": 5
+    })).await;
+    
+    // Check for specific known error due to API changes
+    if let Err(ToolError::ExecutionError(e)) = &result {
+        if e.contains("Failed to search crates.io") {
+            // API may have changed, skip test
+            return;
+        }
+    }
+    
+    // If it's not a known API error, proceed with normal assertions
+    assert!(result.is_ok(), "Error: {:?}", result);
+    let contents = result.unwrap();
+    assert_eq!(contents.len(), 1);
+    if let Content::Text(text) = &contents[0] {
+        assert!(text.text.contains("crates"));
+    } else {
+        panic!("Expected text content");
+    }
+}
+
+#[tokio::test]
+#[ignore = "Requires network access"]
+async fn test_lookup_item_integration() {
+    let router = DocRouter::new();
+    let result = router.call_tool("lookup_item", json!({
+        "crate_name": "serde",
+        "item_path": "ser::Serializer"
+    })).await;
+    
+    // Check for specific known error due to API changes
+    if let Err(ToolError::ExecutionError(e)) = &result {
+        if e.contains("Failed to fetch item documentation") {
+            // API may have changed, skip test
+            return;
+        }
+    }
+    
+    // If it's not a known API error, proceed with normal assertions
+    assert!(result.is_ok(), "Error: {:?}", result);
+    let contents = result.unwrap();
+    assert_eq!(contents.len(), 1);
+    if let Content::Text(text) = &contents[0] {
+        assert!(text.text.contains("Serializer"));
+    } else {
+        panic!("Expected text content");
+    }
+}
+
+#[tokio::test]
+#[ignore = "Requires network access"]
+async fn test_search_crates_with_version() {
+    let router = DocRouter::new();
+    let result = router.call_tool("lookup_crate", json!({
+        "crate_name": "tokio",
+        "version": "1.0.0"
+    })).await;
+    
+    assert!(result.is_ok());
+    let contents = result.unwrap();
+    assert_eq!(contents.len(), 1);
+    if let Content::Text(text) = &contents[0] {
+        assert!(text.text.contains("tokio"));
+        assert!(text.text.contains("1.0.0"));
+    } else {
+        panic!("Expected text content");
+    }
+}
+// Scratch directory for persistent cache tests
+fn temp_cache_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("cratedocs-cache-test-{:016x}", rand::random::<u64>()))
+}
+
+#[tokio::test]
+async fn test_disk_cache_survives_restart() {
+    let dir = temp_cache_dir();
+    
+    let cache = DocCache::with_disk(DiskStore::open(&dir).unwrap());
+    cache.set("tokio:1.0.0:sync::Mutex".to_string(), "Mutex docs".to_string()).await;
+    
+    // A new cache over the same directory sees the persisted entry
+    let reopened = DocCache::with_disk(DiskStore::open(&dir).unwrap());
+    assert_eq!(reopened.get("tokio:1.0.0:sync::Mutex").await, Some("Mutex docs".to_string()));
+    assert_eq!(reopened.get("tokio:1.0.0:sync::RwLock").await, None);
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_usage_top() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("crate:serde@1.0.0", "<h1>serde</h1>")
+        .with_page("crate:tokio@1.0.0", "<h1>tokio</h1>")
+        .with_page("item:serde@1.0.0/trait.Serialize.html", "<h1>Trait serde::Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let usage = router.call_tool("usage_top", json!({})).await.unwrap();
+    let Content::Text(text) = &usage[0] else { panic!("Expected text content") };
+    assert_eq!(text.text, "# Most looked up\n\n## Crates\n\nNothing looked up yet.\n\n## Items\n\nNothing looked up yet.\n");
+
+    router.call_tool("lookup_crate", json!({ "crate_name": "tokio", "version": "1.0.0" })).await.unwrap();
+    router.call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" })).await.unwrap();
+    router.call_tool("lookup_item", json!({ "crate_name": "serde", "item_path": "serde::Serialize", "version": "1.0.0" })).await.unwrap();
+    router.call_tool("lookup_items", json!({ "items": [{ "crate_name": "serde", "item_path": "Serialize", "version": "1.0.0" }] })).await.unwrap();
+    // Failed lookups aren't counted
+    assert!(router.call_tool("lookup_crate", json!({ "crate_name": "serde-typo", "version": "1.0.0" })).await.is_err());
+
+    assert_eq!(router.usage.top_crates(10), vec![("serde".to_string(), 3), ("tokio".to_string(), 1)]);
+    assert_eq!(router.usage.top_items(10), vec![("serde::Serialize".to_string(), 2)]);
+
+    let usage = router.call_tool("usage_top", json!({ "kind": "crates", "limit": 1 })).await.unwrap();
+    let Content::Text(text) = &usage[0] else { panic!("Expected text content") };
+    assert_eq!(text.text, "# Most looked up\n\n## Crates\n\n1. `serde`: 3 lookups\n");
+
+    let err = router.call_tool("usage_top", json!({ "kind": "modules" })).await.unwrap_err();
+    assert!(matches!(err, ToolError::InvalidParameters(_)));
+}
+
+#[tokio::test]
+async fn test_usage_survives_restart() {
+    let dir = temp_cache_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("usage.json");
+
+    let usage = UsageStats::load(&path).unwrap();
+    assert!(usage.top_crates(10).is_empty());
+    usage.record("tokio", Some("sync::Mutex"));
+    usage.record("Tokio", None);
+    usage.save().await.unwrap();
+
+    let reopened = UsageStats::load(&path).unwrap();
+    reopened.record("tokio", None);
+    assert_eq!(reopened.top_crates(10), vec![("tokio".to_string(), 3)]);
+    assert_eq!(reopened.top_items(10), vec![("tokio::sync::Mutex".to_string(), 1)]);
+
+    // Counts that can't be read are an error rather than silently reset
+    std::fs::write(&path, "not json").unwrap();
+    assert!(UsageStats::load(&path).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_disk_cache_flush_and_interrupted_writes() {
+    let dir = temp_cache_dir();
+    let cache = DocCache::with_disk(DiskStore::open(&dir).unwrap());
+    cache.set("serde:1.0.0".to_string(), "serde docs".to_string()).await;
+    cache.flush().await.unwrap();
+    assert!(DocCache::new().flush().await.is_ok());
+
+    // A write cut short by a killed process leaves only its temporary file, removed on open
+    let leftover = dir.join("entries").join("00.tmp");
+    std::fs::write(&leftover, "half an ent").unwrap();
+    let reopened = DocCache::with_disk(DiskStore::open(&dir).unwrap());
+    assert!(!leftover.exists());
+    assert_eq!(reopened.get("serde:1.0.0").await, Some("serde docs".to_string()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_disk_cache_invalidated_on_converter_change() {
+    let dir = temp_cache_dir();
+    let old = SchemaMarker { converter: SchemaMarker::current().converter + 1, ..SchemaMarker::current() };
+    
+    let cache = DocCache::with_disk(DiskStore::open_with_marker(&dir, old).unwrap());
+    cache.set("serde".to_string(), "old conversion".to_string()).await;
+    
+    // Reopening under a different converter version drops the stale entry
+    let upgraded = DocCache::with_disk(DiskStore::open(&dir).unwrap());
+    assert_eq!(upgraded.get("serde").await, None);
+    
+    // Entries written under the current marker are kept across reopens
+    upgraded.set("serde".to_string(), "new conversion".to_string()).await;
+    let reopened = DocCache::with_disk(DiskStore::open(&dir).unwrap());
+    assert_eq!(reopened.get("serde").await, Some("new conversion".to_string()));
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_cache_bundle_round_trip() {
+    let (source_dir, target_dir) = (temp_cache_dir(), temp_cache_dir());
+    let source = DocCache::with_disk(DiskStore::open(&source_dir).unwrap());
+    source.set("tokio:1.0.0:sync::Mutex".to_string(), "Mutex docs".to_string()).await;
+    source.set("release-notes:tokio:1.0.0..1.1.0".to_string(), "notes".to_string()).await;
+    source.set("serde:1.0.0".to_string(), "serde docs".to_string()).await;
+
+    let mut bundle = Vec::new();
+    let exported = DiskStore::open(&source_dir).unwrap().export_bundle(&mut bundle, &["tokio".to_string()]).unwrap();
+    assert_eq!(exported, 2);
+
+    let target = DiskStore::open(&target_dir).unwrap();
+    assert_eq!(target.import_bundle(bundle.as_slice()).unwrap(), 2);
+    let imported = DocCache::with_disk(DiskStore::open(&target_dir).unwrap());
+    assert_eq!(imported.get("tokio:1.0.0:sync::Mutex").await, Some("Mutex docs".to_string()));
+    assert_eq!(imported.get("release-notes:tokio:1.0.0..1.1.0").await, Some("notes".to_string()));
+    assert_eq!(imported.get("serde:1.0.0").await, None);
+
+    // Without a filter everything is exported
+    let mut bundle = Vec::new();
+    assert_eq!(DiskStore::open(&source_dir).unwrap().export_bundle(&mut bundle, &[]).unwrap(), 3);
+
+    // A bundle from another schema would be discarded on the next open, so it's refused
+    let other = SchemaMarker { converter: SchemaMarker::current().converter + 1, ..SchemaMarker::current() };
+    let other_dir = temp_cache_dir();
+    assert!(DiskStore::open_with_marker(&other_dir, other).unwrap().import_bundle(bundle.as_slice()).is_err());
+    assert!(target.import_bundle(&b"not a bundle"[..]).is_err());
+
+    for dir in [source_dir, target_dir, other_dir] {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_entry_truncated() {
+    let cache = DocCache::new().with_max_entry_bytes(10, OversizePolicy::Truncate);
+    
+    cache.set("small".to_string(), "fits".to_string()).await;
+    cache.set("big".to_string(), "é".repeat(20)).await;
+    
+    assert_eq!(cache.get("small").await, Some("fits".to_string()));
+    let big = cache.get("big").await.unwrap();
+    // Cut on a char boundary at or below the limit, with an explicit note
+    assert!(big.starts_with(&"é".repeat(5)));
+    assert!(big.contains("[truncated: cached entry exceeded 10 bytes (was 40 bytes)]"));
+}
+
+#[tokio::test]
+async fn test_oversized_entry_spilled_to_disk() {
+    let dir = temp_cache_dir();
+    let cache = DocCache::with_disk(DiskStore::open(&dir).unwrap())
+        .with_max_entry_bytes(10, OversizePolicy::SpillToDisk);
+    
+    let page = "x".repeat(100);
+    cache.set("big".to_string(), page.clone()).await;
+    
+    // Served in full from disk on every hit, never promoted to memory
+    assert_eq!(cache.get("big").await, Some(page.clone()));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(cache.get("big").await, None);
+}
+
+#[tokio::test]
+async fn test_oversized_entry_not_cached_without_disk() {
+    let cache = DocCache::new().with_max_entry_bytes(10, OversizePolicy::SpillToDisk);
+    cache.set("big".to_string(), "x".repeat(100)).await;
+    assert_eq!(cache.get("big").await, None);
+}
+
+// Minimal rustdoc JSON document: `items` are (id, path, kind, inner) of public items
+fn rustdoc_json(items: &[(u32, &str, &str, serde_json::Value)], extra_index: serde_json::Value) -> String {
+    let mut index = extra_index.as_object().cloned().unwrap_or_default();
+    let mut paths = serde_json::Map::new();
+    for (id, path, kind, inner) in items {
+        let path: Vec<&str> = path.split("::").collect();
+        index.insert(id.to_string(), json!({
+            "id": id,
+            "name": path.last(),
+            "visibility": "public",
+            "attrs": [],
+            "inner": inner,
+        }));
+        paths.insert(id.to_string(), json!({ "crate_id": 0, "path": path, "kind": kind }));
+    }
+    json!({ "index": index, "paths": paths }).to_string()
+}
+
+// rustdoc JSON for `demo` with a struct (one public field, an inherent method and
+// a Clone impl) and a type alias
+fn demo_rustdoc_json() -> String {
+    rustdoc_json(&[
+        (1, "demo::Widget", "struct", json!({ "struct": { "kind": { "plain": { "fields": [10, 11] } }, "impls": [20, 21] } })),
+        (2, "demo::widgets::Handle", "type_alias", json!({ "type_alias": {} })),
+    ], json!({
+        "10": { "name": "size", "visibility": "public", "docs": "Size in pixels.\n\nMore detail.", "inner": { "struct_field": {} } },
+        "11": { "name": "secret", "visibility": "default", "inner": { "struct_field": {} } },
+        "20": { "visibility": "default", "inner": { "impl": { "is_synthetic": false, "trait": null, "blanket_impl": null, "items": [30] } } },
+        "21": { "visibility": "default", "inner": { "impl": { "is_synthetic": false, "trait": { "path": "Clone" }, "blanket_impl": null, "items": [] } } },
+        "30": { "name": "new", "visibility": "public", "docs": "Create a widget.", "inner": { "function": { "has_body": true } } },
+    }))
+}
+
+#[test]
+fn test_rustdoc_json_items() {
+    let json = RustdocJson::parse(&demo_rustdoc_json()).unwrap();
+    assert_eq!(json.len(), 2);
+    assert_eq!(json.item("demo::widgets::Handle").unwrap().kind, "type");
+
+    let widget = json.item("demo::Widget").unwrap();
+    assert_eq!(widget.kind, "struct");
+    assert_eq!(widget.sections, vec![
+        ("Fields", vec!["`size` — Size in pixels.".to_string()]),
+        ("Methods", vec!["`new` — Create a widget.".to_string()]),
+    ]);
+    assert_eq!(widget.implements, vec!["Clone".to_string()]);
+
+    let markdown = format_json_item("demo::Widget", widget);
+    assert!(markdown.starts_with("# Struct demo::Widget\n"));
+    assert!(markdown.contains("## Trait Implementations\n\nClone"));
+    assert!(RustdocJson::parse("{}").is_err());
+}
+
+#[tokio::test]
+async fn test_lookup_item_resolves_kind_from_rustdoc_json() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("rustdoc:demo@1.0.0", &demo_rustdoc_json())
+        .with_page("item:demo@1.0.0/widgets/type.Handle.html", "<h1>Type Alias demo::widgets::Handle</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    // Type aliases aren't among the probed kinds, so only the JSON can find this page
+    let result = router.call_tool("lookup_item", json!({
+        "crate_name": "demo",
+        "item_path": "widgets::Handle",
+        "version": "1.0.0"
+    })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Type Alias demo::widgets::Handle"));
+    assert_eq!(fetcher.requests(), vec!["rustdoc:demo@1.0.0", "item:demo@1.0.0/widgets/type.Handle.html"]);
+
+    // The parsed JSON is reused for the next item of the same version
+    router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Widget", "version": "1.0.0" })).await.ok();
+    assert_eq!(fetcher.requests().iter().filter(|key| key.starts_with("rustdoc:")).count(), 1);
+}
+
+#[tokio::test]
+async fn test_lookup_item_renders_rustdoc_json_without_page() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("rustdoc:demo@1.0.0", &demo_rustdoc_json()));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("lookup_item", json!({
+        "crate_name": "demo",
+        "item_path": "demo::Widget",
+        "version": "1.0.0"
+    })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for demo 1.0.0\nSource: docs.rs rustdoc JSON"));
+    assert!(text.text.contains("## Fields\n\n- `size` — Size in pixels."));
+}
+
+#[tokio::test]
+async fn test_lookup_item_without_rustdoc_json_probes_pages() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("item:demo@1.0.0/struct.Widget.html", "<h1>Struct demo::Widget</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Widget", "version": "1.0.0" })).await.unwrap();
+    router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Other", "version": "1.0.0" })).await.ok();
+    // docs.rs having no JSON for the version is remembered
+    assert_eq!(fetcher.requests().iter().filter(|key| key.starts_with("rustdoc:")).count(), 1);
+    assert!(router.rustdoc_json.get("demo:1.0.0").await.is_some_and(|json| json.is_none()));
+}
+
+#[tokio::test]
+async fn test_rustdoc_json_failures_are_not_cached_as_absence() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_outage("rustdoc:demo@1.0.0")
+        .with_page("rustdoc:demo@2.0.0", "not json"));
+    let mut router = DocRouter::with_fetcher(fetcher.clone());
+    let rustdoc_requests = || fetcher.requests().iter().filter(|key| key.starts_with("rustdoc:")).count();
+
+    // An upstream that's down may be back on the next lookup
+    assert!(router.rustdoc_json("demo", Some("1.0.0")).await.is_none());
+    assert!(router.rustdoc_json("demo", Some("1.0.0")).await.is_none());
+    assert_eq!(rustdoc_requests(), 2);
+    assert!(router.rustdoc_json.get("demo:1.0.0").await.is_none());
+
+    // JSON that doesn't parse is as absent as a 404, until the absence expires
+    router.rustdoc_json("demo", Some("2.0.0")).await;
+    router.rustdoc_json("demo", Some("2.0.0")).await;
+    assert_eq!(rustdoc_requests(), 3);
+    router.rustdoc_json = RustdocJsonCache::default().with_absent_ttl(Duration::ZERO);
+    router.rustdoc_json("demo", Some("2.0.0")).await;
+    router.rustdoc_json("demo", Some("2.0.0")).await;
+    assert_eq!(rustdoc_requests(), 5);
+}
+
+#[cfg(feature = "semver")]
+#[tokio::test]
+async fn test_semver_check_reports_breaking_changes() {
+    let variant = |name: &str| json!({ "name": name, "visibility": "default", "inner": { "variant": {} } });
+    let old = rustdoc_json(&[
+        (1, "demo::connect", "function", json!({ "function": { "sig": { "inputs": [["addr", {}]] } } })),
+        (2, "demo::legacy", "function", json!({ "function": { "sig": { "inputs": [] } } })),
+        (3, "demo::Mode", "enum", json!({ "enum": { "variants": [10, 11] } })),
+    ], json!({ "10": variant("Fast"), "11": variant("Slow") }));
+    let new = rustdoc_json(&[
+        (1, "demo::connect", "function", json!({ "function": { "sig": { "inputs": [["addr", {}], ["timeout", {}]] } } })),
+        (3, "demo::Mode", "enum", json!({ "enum": { "variants": [10, 12] } })),
+    ], json!({ "10": variant("Fast"), "12": variant("Balanced") }));
+    
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("rustdoc:demo@1.0.0", &old)
+        .with_page("rustdoc:demo@2.0.0", &new));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("semver_check", json!({
+        "crate_name": "demo",
+        "from_version": "1.0.0",
+        "to_version": "2.0.0"
+    })).await.unwrap();
+    
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("# Semver check: demo 1.0.0 → 2.0.0"));
+    assert!(text.text.contains("`function_missing` — `demo::legacy`"));
+    assert!(text.text.contains("`function_parameter_count_changed` — `demo::connect`"));
+    assert!(text.text.contains("`enum_variant_missing` — `demo::Mode`: variant `Slow` was removed"));
+    assert!(text.text.contains("`enum_variant_added` — `demo::Mode`: variant `Balanced`"));
+}
+
+#[cfg(feature = "semver")]
+#[tokio::test]
+async fn test_semver_check_identical_versions() {
+    let api = rustdoc_json(&[(1, "demo::run", "function", json!({ "function": { "sig": { "inputs": [] } } }))], json!({}));
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("rustdoc:demo@1.0.0", &api)
+        .with_page("rustdoc:demo@1.0.1", &api));
+    let router = DocRouter::with_fetcher(fetcher);
+    
+    let result = router.call_tool("semver_check", json!({
+        "crate_name": "demo",
+        "from_version": "1.0.0",
+        "to_version": "1.0.1"
+    })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("No breaking changes found"));
+}
+
+#[test]
+fn test_repo_ref_parse() {
+    let repo = RepoRef::parse("https://github.com/tokio-rs/tokio/tree/master/tokio").unwrap();
+    assert_eq!(repo.host, RepoHost::GitHub);
+    assert_eq!((repo.owner.as_str(), repo.name.as_str()), ("tokio-rs", "tokio"));
+
+    let repo = RepoRef::parse("https://gitlab.com/owner/project.git").unwrap();
+    assert_eq!(repo.host, RepoHost::GitLab);
+    assert_eq!(repo.url(), "https://gitlab.com/owner/project");
+
+    assert!(RepoRef::parse("https://example.com/owner/project").is_none());
+    assert!(RepoRef::parse("https://github.com/owner").is_none());
+}
+
+#[test]
+fn test_parse_markdown_listing() {
+    let github = r#"[
+        {"name": "guide.md", "path": "docs/guide.md", "type": "file"},
+        {"name": "img", "path": "docs/img", "type": "dir"},
+        {"name": "logo.png", "path": "docs/logo.png", "type": "file"}
+    ]"#;
+    assert_eq!(parse_markdown_listing(github), vec!["docs/guide.md".to_string()]);
+
+    let gitlab = r#"[{"name": "usage.md", "path": "docs/usage.md", "type": "blob"}]"#;
+    assert_eq!(parse_markdown_listing(gitlab), vec!["docs/usage.md".to_string()]);
+
+    assert!(parse_markdown_listing(r#"{"message": "Not Found"}"#).is_empty());
+}
+
+#[tokio::test]
+async fn test_lookup_crate_falls_back_to_repository() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/newcrate", r#"{"crate": {"max_version": "0.1.0", "repository": "https://github.com/someone/newcrate"}}"#)
+        .with_page("repo:someone/newcrate@v0.1.0/README.md", "# newcrate\n\nDoes new things.")
+        .with_page("repo-dir:someone/newcrate@v0.1.0/docs", r#"[{"path": "docs/guide.md", "type": "file"}]"#)
+        .with_page("repo:someone/newcrate@v0.1.0/docs/guide.md", "# Guide\n\nStep one.")
+        .with_page("repo:someone/newcrate/README.md", "# newcrate\n\nDoes newer things."));
+    let router = DocRouter::with_sources(SourceRegistry::upstream(fetcher.clone()));
+
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "newcrate" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    // The version's tag is read rather than the default branch
+    assert!(text.text.starts_with("Documentation for newcrate 0.1.0\nSource: https://github.com/someone/newcrate/tree/v0.1.0"));
+    assert!(text.text.contains("Does new things."));
+    assert!(!text.text.contains("Does newer things."));
+    assert!(text.text.contains("<!-- docs/guide.md -->"));
+    assert!(text.text.contains("Step one."));
+    assert!(router.cache.get("newcrate:0.1.0").await.is_some());
+}
+
+#[tokio::test]
+async fn test_lookup_crate_falls_back_on_failed_build() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/broken", r#"{"crate": {"max_version": "1.0.0", "repository": "https://gitlab.com/someone/broken"}}"#)
+        .with_page("crate:broken@1.0.0", "<h1>docs.rs failed to build broken-1.0.0</h1>")
+        .with_page("repo:someone/broken/README.md", "Broken but documented."));
+    let router = DocRouter::with_sources(SourceRegistry::upstream(fetcher));
+
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "broken" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("docs.rs failed to build broken"));
+    assert!(text.text.contains("Broken but documented."));
+    // Without a tag for the version, the default branch stands in, labelled as such and
+    // not cached as the version's docs
+    assert!(text.text.starts_with("Documentation for broken (default branch)\nSource: https://gitlab.com/someone/broken/-/tree/HEAD"));
+    assert!(router.cache.get("broken:1.0.0").await.is_none());
+}
+
+#[tokio::test]
+async fn test_lookup_crate_falls_back_to_crates_io_readme() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/readmeonly", r#"{"crate": {"max_version": "0.3.0", "repository": "https://github.com/someone/readmeonly"}}"#)
+        .with_page("api:crates/readmeonly/0.3.0/readme", "<h1>readmeonly</h1><p>Rendered by crates.io.</p>")
+        .with_page("repo:someone/readmeonly/README.md", "Repository README."));
+    let router = DocRouter::with_sources(SourceRegistry::upstream(fetcher.clone()));
+
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "readmeonly" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with(
+        "Documentation for readmeonly 0.3.0\nSource: https://crates.io/crates/readmeonly/0.3.0 (crates.io README fallback: "
+    ));
+    assert!(text.text.contains("Rendered by crates.io."));
+    // crates.io comes before the repository in the chain
+    assert!(!text.text.contains("Repository README."));
+    assert!(router.cache.get("readmeonly:0.3.0").await.is_some());
+
+    // The chain is the registry's, so disabling a fallback source skips it
+    let sources = SourceRegistry::upstream(fetcher).disable(&[CRATES_IO_README_SOURCE.to_string()]).unwrap();
+    let result = DocRouter::with_sources(sources).call_tool("lookup_crate", json!({ "crate_name": "readmeonly" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Repository README."));
+}
+
+#[tokio::test]
+async fn test_lookup_crate_outage_fallback_is_not_cached() {
+    let down = Arc::new(MockFetcher::default()
+        .with_outage("crate:demo@1.0.0")
+        .with_page("api:crates/demo/1.0.0/readme", "<p>The README.</p>"));
+    let router = DocRouter::with_sources(SourceRegistry::upstream(down));
+    let arguments = json!({ "crate_name": "demo", "version": "1.0.0" });
+
+    let result = router.call_tool("lookup_crate", arguments.clone()).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("crates.io README fallback: Failed to fetch crate:demo@1.0.0. Status: 503"));
+    assert!(router.cache.get("demo:1.0.0").await.is_none());
+
+    // Once docs.rs answers again, the real page is served rather than the README
+    let up = Arc::new(MockFetcher::default().with_page("crate:demo@1.0.0", "<h1>Crate demo</h1><p>Real docs.</p>"));
+    let router = DocRouter::with_fetcher(up).with_cache(router.cache.clone());
+    let result = router.call_tool("lookup_crate", arguments).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Real docs."));
+    assert!(!text.text.contains("The README."));
+}
+
+#[tokio::test]
+async fn test_lookup_crate_without_repository_keeps_docs_rs_error() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/binonly", r#"{"crate": {"max_version": "0.2.0", "repository": null}}"#));
+    let router = DocRouter::with_sources(SourceRegistry::upstream(fetcher));
+
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "binonly" })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(msg))
+        if msg.contains("404") && msg.contains("no GitHub or GitLab repository")));
+}
+
+const LIB_RS_SEARCH_FIXTURE: &str = r#"<html><body><header><a href="/crates/not-a-result">nav</a></header><main><ol>
+    <li><a href="/crates/log"><div class="h"><h4>log</h4><p class="desc">A lightweight logging facade</p></div>
+        <div class="meta"><span class="version">v0.4.22</span><span class="category">Development tools</span></div></a></li>
+    <li><a href="/crates/env_logger"><div class="h"><h4>env_logger</h4><p class="desc">A logging implementation</p></div>
+        <div class="meta"><span class="version">v0.11.5</span></div></a></li>
+</ol></main></body></html>"#;
+
+#[test]
+fn test_parse_lib_rs_results() {
+    let results = parse_lib_rs_results(LIB_RS_SEARCH_FIXTURE, 10);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].name, "log");
+    assert_eq!(results[0].description, "A lightweight logging facade");
+    assert_eq!(results[0].version.as_deref(), Some("0.4.22"));
+    assert_eq!(results[0].categories, vec!["Development tools".to_string()]);
+    assert_eq!(results[1].name, "env_logger");
+
+    assert_eq!(parse_lib_rs_results(LIB_RS_SEARCH_FIXTURE, 1).len(), 1);
+}
+
+#[tokio::test]
+async fn test_search_crates_backend_selection() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("librs:logger", LIB_RS_SEARCH_FIXTURE)
+        .with_page("search:logger:10", r#"{"crates": []}"#));
+
+    // The argument overrides the default backend
+    let router = DocRouter::with_fetcher(fetcher.clone());
+    let result = router.call_tool("search_crates", json!({ "query": "logger", "backend": "lib-rs" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("1. **log** 0.4.22 — A lightweight logging facade (Development tools)"));
+    assert!(text.text.contains("https://lib.rs/crates/env_logger"));
+
+    // The configured backend is used when the call doesn't pick one
+    let router = DocRouter::with_fetcher(fetcher.clone()).with_search_backend(SearchBackend::LibRs);
+    router.call_tool("search_crates", json!({ "query": "logger" })).await.unwrap();
+    let result = router.call_tool("search_crates", json!({ "query": "logger", "backend": "crates-io" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert_eq!(text.text, r#"{"crates": []}"#);
+
+    assert_eq!(fetcher.requests(), vec!["librs:logger", "librs:logger", "search:logger:10"]);
+}
+
+#[tokio::test]
+async fn test_search_crates_unknown_backend() {
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()));
+    let result = router.call_tool("search_crates", json!({ "query": "logger", "backend": "google" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(msg)) if msg.contains("google")));
+}
+
+#[test]
+fn test_date_days_ago() {
+    let epoch = std::time::UNIX_EPOCH;
+    assert_eq!(date_days_ago(epoch, 0), "1970-01-01");
+    assert_eq!(utc_timestamp(epoch + Duration::from_secs(19_388 * 86_400 + 9 * 3_600 + 5 * 60 + 7)), "2023-01-31T09:05:07Z");
+    // 2024-03-01 is day 19783; the window crosses a leap day
+    assert_eq!(date_days_ago(epoch + Duration::from_secs(19_783 * 86_400 + 3_600), 1), "2024-02-29");
+    assert_eq!(date_days_ago(epoch + Duration::from_secs(19_783 * 86_400), 366), "2023-03-01");
+
+    assert!(is_iso_date("2024-02-29"));
+    assert!(!is_iso_date("2024-2-29"));
+    assert!(!is_iso_date("last week"));
+}
+
+#[test]
+fn test_releases_since_filters_and_sorts() {
+    let json = r#"{"versions": [
+        {"num": "0.7.5", "created_at": "2024-03-20T10:00:00.000000+00:00", "yanked": false},
+        {"num": "0.7.4", "created_at": "2024-01-05T10:00:00.000000+00:00", "yanked": false},
+        {"num": "0.8.0-rc.1", "created_at": "2024-03-25T08:00:00.000000+00:00", "yanked": true}
+    ]}"#;
+    let releases = releases_since(parse_crate_versions(json, "axum").unwrap(), "2024-03-01");
+    let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+    assert_eq!(versions, vec!["0.8.0-rc.1", "0.7.5"]);
+    assert!(releases[0].yanked);
+}
+
+#[tokio::test]
+async fn test_recent_releases_for_crates() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/axum/versions", r#"{"versions": [
+            {"num": "0.7.5", "created_at": "2024-03-20T10:00:00+00:00", "yanked": false},
+            {"num": "0.7.4", "created_at": "2024-01-05T10:00:00+00:00", "yanked": false}
+        ]}"#));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("recent_releases", json!({
+        "crate_names": ["axum", "missing"],
+        "since": "2024-03-01"
+    })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# Releases of axum, missing since 2024-03-01"));
+    assert!(text.text.contains("- **axum** 0.7.5 — 2024-03-20"));
+    assert!(!text.text.contains("0.7.4"));
+    assert!(text.text.contains("could not fetch versions of missing"));
+}
+
+#[tokio::test]
+async fn test_recent_releases_across_crates_io() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:summary", r#"{"just_updated": [
+            {"name": "tokio", "max_version": "1.43.0", "newest_version": "1.43.0", "updated_at": "2024-03-21T09:00:00+00:00"},
+            {"name": "old", "max_version": "0.1.0", "updated_at": "2023-01-01T09:00:00+00:00"}
+        ]}"#));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("recent_releases", json!({ "since": "2024-03-01" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("- **tokio** 1.43.0 — 2024-03-21"));
+    assert!(!text.text.contains("old"));
+
+    let result = router.call_tool("recent_releases", json!({ "since": "March" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_watch_list_records_new_releases() {
+    let watch = WatchList::default();
+    let mut changes = watch.subscribe();
+    watch.add(&["axum".to_string()]).await;
+
+    // The first check only records the baseline
+    assert!(watch.record(vec![("axum".to_string(), "0.7.4".to_string())]).await.is_empty());
+    assert!(changes.try_recv().is_err());
+
+    let updates = watch.record(vec![("axum".to_string(), "0.7.5".to_string())]).await;
+    assert_eq!(updates.len(), 1);
+    assert_eq!((updates[0].previous.as_str(), updates[0].version.as_str()), ("0.7.4", "0.7.5"));
+    assert_eq!(changes.try_recv().unwrap(), UPDATES_RESOURCE_URI);
+
+    // Unchanged versions and crates that are no longer watched are not reported
+    assert!(watch.record(vec![("axum".to_string(), "0.7.5".to_string())]).await.is_empty());
+    assert!(watch.record(vec![("tokio".to_string(), "1.43.0".to_string())]).await.is_empty());
+
+    let markdown = watch.updates_markdown().await;
+    assert!(markdown.contains("Watching: axum 0.7.5"));
+    assert!(markdown.contains("- **axum** 0.7.4 → 0.7.5"));
+}
+
+#[tokio::test]
+async fn test_watch_crates_tool_and_updates_resource() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/axum", r#"{"crate": {"max_version": "0.7.5"}}"#));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("watch_crates", json!({ "crate_names": ["axum"] })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Watching 1 crate(s): axum"));
+
+    // Adding a crate takes its current version as the baseline
+    let updates = router.read_resource(UPDATES_RESOURCE_URI).await.unwrap();
+    assert!(updates.contains("Watching: axum 0.7.5"));
+    assert!(updates.contains("No new releases"));
+
+    router.call_tool("watch_crates", json!({ "crate_names": ["axum"], "remove": true })).await.unwrap();
+    assert!(router.watch.crates().await.is_empty());
+
+    let result = router.call_tool("watch_crates", json!({ "crate_names": [] })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+// `new Map(...)` layout with letter-coded types and `[index, path]` pairs
+const SEARCH_INDEX_FIXTURE: &str = r#"var searchIndex = new Map(JSON.parse('[["demo",{"t":"ADFL","n":["widgets","Widget","make","paint"],"q":[[0,"demo"],[1,"demo::widgets"]],"d":["It\'s a module","","",""]}]]'));
+if (typeof exports !== 'undefined') exports.searchIndex = searchIndex;"#;
+
+#[test]
+fn test_search_index_parse_map_layout() {
+    let index = SearchIndex::parse(SEARCH_INDEX_FIXTURE, "demo").unwrap();
+    let paths: Vec<(&str, &str)> = index.items().iter().map(|item| (item.path.as_str(), item.kind)).collect();
+    // `paint` is a method (type 11) and has no page of its own
+    assert_eq!(paths, vec![
+        ("demo::widgets", "mod"),
+        ("demo::widgets::Widget", "struct"),
+        ("demo::widgets::make", "fn"),
+    ]);
+    assert_eq!(index.find("Widget")[0].page_path(), "widgets/struct.Widget.html");
+    assert_eq!(index.find("widgets")[0].page_path(), "widgets/index.html");
+}
+
+#[test]
+fn test_search_index_parse_object_layout() {
+    let js = r#"var searchIndex = JSON.parse('{"my_crate":{"t":[3,5],"n":["Client","connect"],"q":["my_crate","my_crate::net"]}}');"#;
+    let index = SearchIndex::parse(js, "my-crate").unwrap();
+    assert_eq!(index.find("Client")[0].page_path(), "struct.Client.html");
+    assert_eq!(index.find("connect")[0].path, "my_crate::net::connect");
+
+    assert!(SearchIndex::parse(js, "other").is_none());
+    assert!(SearchIndex::parse("searchIndex = {};", "my-crate").is_none());
+}
+
+#[test]
+fn test_search_index_file_from_rustdoc_vars() {
+    let html = r#"<meta name="rustdoc-vars" data-root-path="../" data-search-index-js="../search-index-20240101.js">"#;
+    assert_eq!(search_index_file(html), "search-index-20240101.js");
+    let html = r#"<meta name="rustdoc-vars" data-resource-suffix="-1.60.0">"#;
+    assert_eq!(search_index_file(html), "search-index-1.60.0.js");
+    assert_eq!(search_index_file("<html></html>"), "search-index.js");
+}
+
+#[tokio::test]
+async fn test_search_index_cache_budget_and_ttl() {
+    let index = Arc::new(SearchIndex::parse(SEARCH_INDEX_FIXTURE, "demo").unwrap());
+    let size = index.size_bytes();
+
+    // Room for exactly two indexes: adding a third evicts the oldest
+    let cache = SearchIndexCache::new(Duration::from_secs(60), size * 2);
+    cache.set("a:1".to_string(), index.clone()).await;
+    cache.set("b:1".to_string(), index.clone()).await;
+    cache.set("c:1".to_string(), index.clone()).await;
+    assert!(cache.get("a:1").await.is_none());
+    assert!(cache.get("c:1").await.is_some());
+    assert_eq!(cache.total_bytes().await, size * 2);
+
+    // Indexes larger than the whole budget are not cached
+    let small = SearchIndexCache::new(Duration::from_secs(60), size - 1);
+    small.set("a:1".to_string(), index.clone()).await;
+    assert_eq!(small.total_bytes().await, 0);
+
+    let expiring = SearchIndexCache::new(Duration::from_millis(10), size * 2);
+    expiring.set("a:1".to_string(), index).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(expiring.get("a:1").await.is_none());
+    assert_eq!(expiring.total_bytes().await, 0);
+}
+
+#[tokio::test]
+async fn test_lookup_item_corrects_path_from_search_index() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/demo", r#"{"crate": {"max_version": "1.0.0"}}"#)
+        .with_page("item:demo@1.0.0/index.html", r#"<meta name="rustdoc-vars" data-search-index-js="search-index-x.js">"#)
+        .with_page("file:demo@1.0.0/search-index-x.js", SEARCH_INDEX_FIXTURE)
+        .with_page("item:demo@1.0.0/widgets/struct.Widget.html", "<h1>Struct demo::widgets::Widget</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    // `Widget` is only reachable as `widgets::Widget`
+    let result = router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Widget" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("demo::Widget is documented at demo::widgets::Widget"));
+    assert!(text.text.contains("Struct demo::widgets::Widget"));
+
+    // The index is reused for later corrections
+    let index_requests = |fetcher: &MockFetcher| fetcher.requests().iter().filter(|r| r.starts_with("file:")).count();
+    router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "other::make" })).await.ok();
+    assert_eq!(index_requests(&fetcher), 1);
+    assert!(router.search_indexes.get("demo:1.0.0").await.is_some());
+}
+
+#[tokio::test]
+async fn test_export_crate() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/demo", r#"{"crate": {"max_version": "1.0.0"}}"#)
+        .with_page("crate:demo@1.0.0", "<h1>Crate demo</h1>")
+        .with_page("item:demo@1.0.0/index.html", r#"<meta name="rustdoc-vars" data-search-index-js="search-index-x.js">"#)
+        .with_page("file:demo@1.0.0/search-index-x.js", SEARCH_INDEX_FIXTURE)
+        .with_page("item:demo@1.0.0/widgets/index.html", "<h1>Module widgets</h1>")
+        .with_page("item:demo@1.0.0/widgets/struct.Widget.html", "<h1>Struct Widget</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+    let out_dir = temp_cache_dir();
+
+    let summary = router.export_crate("demo", None, &out_dir, 10).await.unwrap();
+    let dir = out_dir.join("demo").join("1.0.0");
+    assert_eq!(summary.dir, dir);
+    assert_eq!((summary.written, summary.listed, summary.skipped), (3, 3, 0));
+    // `make` has no page, so it's reported without failing the export
+    assert_eq!(summary.failed, vec!["demo::widgets::make".to_string()]);
+    assert!(std::fs::read_to_string(dir.join("index.md")).unwrap().contains("Crate demo"));
+    assert!(std::fs::read_to_string(dir.join("widgets/index.md")).unwrap().contains("Module widgets"));
+    let widget = std::fs::read_to_string(dir.join("widgets/struct.Widget.md")).unwrap();
+    assert!(widget.starts_with("Documentation for demo 1.0.0\n"));
+    assert_eq!(
+        format_export_summary(&summary, "exported"),
+        format!("Exported 3 of 4 pages of demo 1.0.0 to {}; 1 failed: demo::widgets::make\n", dir.display())
+    );
+
+    let summary = router.export_crate("demo", Some("1.0.0"), &out_dir, 1).await.unwrap();
+    assert_eq!((summary.written, summary.skipped), (2, 2));
+    std::fs::remove_dir_all(&out_dir).ok();
+}
+
+#[tokio::test]
+async fn test_mirror_crate() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/demo", r#"{"crate": {"max_version": "1.0.0"}}"#)
+        .with_page("crate:demo@1.0.0", "<h1>Crate demo</h1>")
+        .with_page("item:demo@1.0.0/index.html", r#"<meta name="rustdoc-vars" data-search-index-js="search-index-x.js">"#)
+        .with_page("file:demo@1.0.0/search-index-x.js", SEARCH_INDEX_FIXTURE)
+        .with_page("item:demo@1.0.0/widgets/index.html", "<h1>Module widgets</h1>")
+        .with_page("item:demo@1.0.0/widgets/struct.Widget.html", "<h1>Struct Widget</h1><p>A widget.</p>"));
+    let mirror_dir = temp_cache_dir();
+    let mirror = Mirror::new(&mirror_dir);
+
+    let summary = DocRouter::with_fetcher(fetcher).mirror_crate(&mirror, "demo", None, 10).await.unwrap();
+    assert_eq!(summary.dir, mirror_dir.join("demo").join("1.0.0"));
+    assert_eq!((summary.written, summary.listed), (3, 3));
+    assert_eq!(summary.failed, vec!["demo::widgets::make".to_string()]);
+    assert_eq!(mirror.versions("demo"), vec!["1.0.0".to_string()]);
+
+    // Offline, lookups are answered from the mirror alone
+    let offline = Arc::new(MirrorFetcher::new(mirror.clone(), None));
+    let router = DocRouter::with_fetcher(offline.clone());
+    let result = router
+        .call_tool("lookup_item", json!({"crate_name": "demo", "item_path": "demo::widgets::Widget", "version": "1.0.0"}))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("A widget."));
+    let page = offline.get_crate_page("demo", None).await.unwrap();
+    assert_eq!(page.url, "https://docs.rs/crate/demo/1.0.0/");
+    assert!(page.html.contains("Crate demo"));
+
+    let err = offline.get_item_page("other", None, "index.html").await.unwrap_err().to_string();
+    assert!(err.contains("not in the mirror"));
+    assert!(err.contains("cratedocs mirror --crates other"));
+    let err = offline.search("demo", 10).await.unwrap_err().to_string();
+    assert!(err.contains("not available offline"));
+    std::fs::remove_dir_all(&mirror_dir).ok();
+}
+
+#[test]
+fn test_export_file_path() {
+    assert_eq!(export_file_path("sync/mpsc/struct.Sender.html"), Some(PathBuf::from("sync/mpsc/struct.Sender.md")));
+    assert_eq!(export_file_path("sync/index.html"), Some(PathBuf::from("sync/index.md")));
+    assert_eq!(export_file_path("../struct.Escape.html"), None);
+    assert_eq!(export_file_path("/etc/struct.Escape.html"), None);
+}
+
+#[tokio::test]
+async fn test_lookup_item_lists_ambiguous_candidates() {
+    let js = r#"var searchIndex = JSON.parse('{"demo":{"t":[3,3],"n":["Error","Error"],"q":["demo::io","demo::fmt"]}}');"#;
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/demo", r#"{"crate": {"max_version": "1.0.0"}}"#)
+        .with_page("file:demo@1.0.0/search-index.js", js));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Error" })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(msg))
+        if msg.contains("Did you mean one of: demo::io::Error, demo::fmt::Error")));
+}
+
+#[tokio::test]
+async fn test_lookup_items_returns_one_result_per_item() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("item:tokio@1.0.0/sync/mpsc/struct.Sender.html", "<h1>Struct Sender</h1>")
+        .with_page("item:serde@1.0.0/trait.Serialize.html", "<h1>Trait Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("lookup_items", json!({ "items": [
+        { "crate_name": "tokio", "item_path": "sync::mpsc::Sender", "version": "1.0.0" },
+        { "crate_name": "serde", "item_path": "Nope", "version": "1.0.0" },
+        { "crate_name": "serde", "item_path": "Serialize", "version": "1.0.0" }
+    ]})).await.unwrap();
+    assert_eq!(result.len(), 3);
+
+    let texts: Vec<&str> = result.iter().map(|content| match content {
+        Content::Text(text) => text.text.as_str(),
+        _ => panic!("Expected text content"),
+    }).collect();
+    assert!(texts[0].starts_with("## tokio::sync::mpsc::Sender\n\nDocumentation for tokio 1.0.0"));
+    assert!(texts[0].contains("Struct Sender"));
+    // A failed item is reported in place without failing the batch
+    assert!(texts[1].starts_with("## serde::Nope\n\nError: Failed to fetch item documentation"));
+    assert!(texts[2].contains("Trait Serialize"));
+}
+
+#[tokio::test]
+async fn test_lookup_items_continues_truncated_item() {
+    let lines: String = (0..200).map(|i| format!("<p>Line {:03}</p>", i)).collect();
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("item:demo@1.0.0/struct.Short.html", "<h1>Struct Short</h1>")
+        .with_page("item:demo@1.0.0/struct.Long.html", &format!("<h1>Struct Long</h1>{}", lines)));
+    let router = DocRouter::with_fetcher(fetcher).with_max_content_bytes(512);
+    let items = json!([
+        { "crate_name": "demo", "item_path": "Short", "version": "1.0.0" },
+        { "crate_name": "demo", "item_path": "Long", "version": "1.0.0" }
+    ]);
+
+    let result = router.call_tool("lookup_items", json!({ "items": items })).await.unwrap();
+    let Content::Text(long) = &result[1] else { panic!("Expected text content") };
+    let (_, marker) = long.text.rsplit_once("\n[truncated: use offset=").unwrap();
+    let (offset, block) = marker.trim_end_matches(" to continue]").split_once(" and block=").unwrap();
+    assert_eq!(block, "1");
+    let offset: u64 = offset.parse().unwrap();
+
+    // Only the truncated item continues, from its own offset
+    let result = router.call_tool("lookup_items", json!({ "items": items, "offset": offset, "block": 1 })).await.unwrap();
+    assert_eq!(result.len(), 1);
+    let Content::Text(rest) = &result[0] else { panic!("Expected text content") };
+    assert!(!rest.text.contains("Struct Short"));
+    assert!(rest.text.trim_start().starts_with("Line ") && !rest.text.contains("Line 000"));
+    assert!(rest.text.ends_with(" and block=1 to continue]"));
+
+    // An offset without its block is ambiguous, and blocks past the last don't exist
+    let result = router.call_tool("lookup_items", json!({ "items": items, "offset": offset })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+    let result = router.call_tool("lookup_items", json!({ "items": items, "offset": offset, "block": 2 })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_lookup_items_validates_entries() {
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()));
+
+    let result = router.call_tool("lookup_items", json!({ "items": [] })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+
+    let result = router.call_tool("lookup_items", json!({ "items": [
+        { "crate_name": "tokio", "item_path": "sync::Mutex" },
+        { "crate_name": "tokio" }
+    ]})).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(msg)) if split_error_data(&msg).0 == "items[1].item_path is required"));
+
+    let too_many: Vec<_> = (0..21).map(|i| json!({ "crate_name": "c", "item_path": format!("I{}", i) })).collect();
+    let result = router.call_tool("lookup_items", json!({ "items": too_many })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+const CRATE_INFO_FIXTURE: &str = r#"{
+    "crate": {
+        "name": "chrono",
+        "max_version": "0.5.0-alpha.1",
+        "max_stable_version": "0.4.38",
+        "downloads": 250000000,
+        "description": "Date and time library for Rust"
+    },
+    "versions": [
+        { "num": "0.5.0-alpha.1", "license": "MIT", "rust_version": "1.70" },
+        { "num": "0.4.38", "license": "MIT OR Apache-2.0", "rust_version": "1.61.0" }
+    ]
+}"#;
+
+#[test]
+fn test_parse_crate_summary_uses_latest_stable_version() {
+    let summary = parse_crate_summary(CRATE_INFO_FIXTURE).unwrap();
+    assert_eq!(summary.name, "chrono");
+    assert_eq!(summary.version, "0.4.38");
+    assert_eq!(summary.downloads, 250_000_000);
+    assert_eq!(summary.license.as_deref(), Some("MIT OR Apache-2.0"));
+    assert_eq!(summary.msrv.as_deref(), Some("1.61.0"));
+}
+
+#[tokio::test]
+async fn test_compare_crates_table() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/chrono", CRATE_INFO_FIXTURE)
+        .with_page("api:crates/time", r#"{
+            "crate": { "name": "time", "max_version": "0.3.36", "downloads": 300000000,
+                       "description": "Date and time library.\n Fully | interoperable" },
+            "versions": [{ "num": "0.3.36", "license": "MIT OR Apache-2.0", "rust_version": null }]
+        }"#));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("compare_crates", json!({ "crate_names": ["chrono", "time", "jiffy"] })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("| Crate | Latest | Downloads | License | MSRV | Description |"));
+    assert!(text.text.contains("| chrono | 0.4.38 | 250000000 | MIT OR Apache-2.0 | 1.61.0 | Date and time library for Rust |"));
+    assert!(text.text.contains("| time | 0.3.36 | 300000000 | MIT OR Apache-2.0 | — | Date and time library. Fully \\| interoperable |"));
+    assert!(text.text.contains("Not found on crates.io: jiffy"));
+
+    // The versions seen pin later unversioned lookups
+    assert_eq!(router.latest_versions.get("chrono").await, Some("0.4.38".to_string()));
+
+    let result = router.call_tool("compare_crates", json!({ "crate_names": ["jiffy"] })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(_))));
+}
+
+#[tokio::test]
+async fn test_find_defining_crate() {
+    let chrono_index = r#"var searchIndex = JSON.parse('{"chrono":{"t":[3,3],"n":["DateTime","NaiveDateTime"],"q":["chrono",""]}}');"#;
+    let local_index = r#"var searchIndex = JSON.parse('{"mycrate":{"t":[6],"n":["DateTime"],"q":["mycrate::compat"]}}');"#;
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("search:DateTime:5", r#"{"crates": [{"name": "chrono"}, {"name": "datetime"}]}"#)
+        .with_page("file:chrono@latest/search-index.js", chrono_index)
+        .with_page("file:mycrate@latest/search-index.js", local_index));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("find_defining_crate", json!({
+        "name": "chrono::DateTime",
+        "crates": ["mycrate"]
+    })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# Crates defining `DateTime`"));
+    // Requested crates come first, then search results and popular crates (chrono only once)
+    let mycrate = text.text.find("- `mycrate::compat::DateTime` (type) in crate mycrate").unwrap();
+    let chrono = text.text.find("- `chrono::DateTime` (struct) in crate chrono").unwrap();
+    assert!(mycrate < chrono);
+    assert!(!text.text.contains("NaiveDateTime"));
+    assert!(text.text.contains("Searched 22 crates."));
+
+    let result = router.call_tool("find_defining_crate", json!({ "name": "Nothing" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("No item named `Nothing` found in the 20 crates searched."));
+}
+
+const RELEASES_MD: &str = r#"Version 1.76.0 (2024-02-08)
+==========================
+
+<a id="1.76.0-Language"></a>
+
+Language
+--------
+- [Document Rust ABI compatibility between various types](https://github.com/rust-lang/rust/pull/115476/)
+
+Version 1.75.0 (2023-12-28)
+==========================
+
+Libraries
+---------
+- [Add `Option::as_slice`](https://github.com/rust-lang/rust/pull/105871/)
+
+Version 1.74.1 (2023-12-07)
+===========================
+
+- [Resolved spurious STATUS_ACCESS_VIOLATIONs in LLVM](https://github.com/rust-lang/rust/pull/118464)
+"#;
+
+#[test]
+fn test_parse_release_notes() {
+    let releases = parse_release_notes(RELEASES_MD);
+    let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+    assert_eq!(versions, ["1.76.0", "1.75.0", "1.74.1"]);
+    assert_eq!(releases[0].date.as_deref(), Some("2024-02-08"));
+    assert_eq!(
+        releases[0].notes,
+        "### Language\n- [Document Rust ABI compatibility between various types](https://github.com/rust-lang/rust/pull/115476/)"
+    );
+    assert!(releases[1].notes.starts_with("### Libraries\n"));
+
+    assert_eq!(parse_rust_version("1.75"), Some((1, 75, 0)));
+    assert_eq!(parse_rust_version("v1.74.1"), Some((1, 74, 1)));
+    assert_eq!(parse_rust_version("nightly"), None);
+    assert_eq!(parse_rust_version("1.75.0.1"), None);
+}
+
+#[tokio::test]
+async fn test_rust_release_notes() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("repo:rust-lang/rust/RELEASES.md", RELEASES_MD));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let result = router.call_tool("rust_release_notes", json!({ "version": "1.75" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# Rust 1.75.0 release notes\n\n## Rust 1.75.0 (2023-12-28)\n\n### Libraries\n"));
+    assert!(!text.text.contains("1.76.0"));
+
+    let result = router
+        .call_tool("rust_release_notes", json!({ "version": "1.76.0", "from_version": "1.74.0" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    let newest = text.text.find("## Rust 1.76.0").unwrap();
+    let oldest = text.text.find("## Rust 1.74.1").unwrap();
+    assert!(newest < oldest);
+
+    let result = router.call_tool("rust_release_notes", json!({ "version": "1.99" })).await;
+    assert!(matches!(result, Err(ToolError::NotFound(_))));
+    let result = router
+        .call_tool("rust_release_notes", json!({ "version": "1.74", "from_version": "1.75" }))
+        .await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+const TOKIO_CHANGELOG: &str = r#"# Changelog
+
+## [Unreleased]
+
+- Nothing yet
+
+## [0.7.1] - 2024-02-01
+
+### Fixes since 0.7.0
+
+- `Framed` dropped a trailing frame
+
+## [0.7.0] - 2024-01-02
+
+### Changed
+
+- `Decoder::decode` takes `&mut BytesMut`
+
+## 0.6.3 (2023-11-20)
+
+- Added `Encoder` for `String`
+"#;
+
+#[test]
+fn test_parse_changelog() {
+    let releases = parse_changelog(TOKIO_CHANGELOG);
+    let versions: Vec<String> = releases.iter().map(|release| release.version.to_string()).collect();
+    // Only `##` headings are releases, so the `###` heading naming 0.7.0 is part of 0.7.1
+    assert_eq!(versions, ["0.7.1", "0.7.0", "0.6.3"]);
+    assert_eq!(releases[0].title, "[0.7.1] - 2024-02-01");
+    assert_eq!(releases[0].notes, "### Fixes since 0.7.0\n\n- `Framed` dropped a trailing frame");
+
+    let from = parse_loose_version("0.6").unwrap();
+    let to = parse_loose_version("0.7.0").unwrap();
+    let selected: Vec<String> = releases_between(&releases, &from, Some(&to))
+        .iter()
+        .map(|release| release.version.to_string())
+        .collect();
+    assert_eq!(selected, ["0.7.0", "0.6.3"]);
+    assert_eq!(releases_between(&releases, &to, None).len(), 1);
+
+    assert_eq!(parse_loose_version("v1.2").map(|v| v.to_string()).as_deref(), Some("1.2.0"));
+    assert_eq!(parse_loose_version("1.2-rc.1").map(|v| v.to_string()).as_deref(), Some("1.2.0-rc.1"));
+    assert_eq!(parse_loose_version("2024-01-02"), None);
+
+    // Releases of other crates in the repository are skipped
+    let listing = r#"[
+        {"tag_name": "tokio-util-0.7.2", "name": "tokio-util 0.7.2", "body": "Util notes", "draft": false},
+        {"tag_name": "tokio-1.43.0", "name": "Tokio v1.43.0", "body": "Tokio notes", "draft": false},
+        {"tag_name": "v1.44.0", "name": "", "body": "Draft notes", "draft": true},
+        {"tag_name": "v1.42.0", "name": "", "description": "GitLab notes"}
+    ]"#;
+    let releases = parse_repository_releases(listing, "tokio").unwrap();
+    let tags: Vec<(String, &str, &str)> = releases
+        .iter()
+        .map(|release| (release.version.to_string(), release.title.as_str(), release.notes.as_str()))
+        .collect();
+    assert_eq!(
+        tags,
+        [("1.43.0".to_string(), "Tokio v1.43.0", "Tokio notes"), ("1.42.0".to_string(), "v1.42.0", "GitLab notes")]
+    );
+    assert_eq!(parse_repository_releases(r#"{"message": "Not Found"}"#, "tokio"), None);
+}
+
+#[tokio::test]
+async fn test_release_notes() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("api:crates/tokio-util", r#"{"crate": {"max_stable_version": "0.7.1", "repository": "https://github.com/tokio-rs/tokio"}}"#)
+        .with_page("repo:tokio-rs/tokio/tokio-util/CHANGELOG.md", TOKIO_CHANGELOG)
+        .with_page("api:crates/bytes", r#"{"crate": {"max_stable_version": "1.6.0", "repository": "https://github.com/tokio-rs/bytes"}}"#)
+        .with_page("releases:tokio-rs/bytes", r#"[
+            {"tag_name": "v1.6.0", "name": "", "body": "Added `Buf::get_int`"},
+            {"tag_name": "v1.5.0", "name": "", "body": "Added `BytesMut::zeroed`"}
+        ]"#));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    // The changelog of the crate's directory in a workspace repository, up to the latest version
+    let result = router
+        .call_tool("release_notes", json!({ "crate_name": "tokio-util", "from_version": "0.6" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with(
+        "# Changes in tokio-util after 0.6 up to 0.7.1\nSource: https://github.com/tokio-rs/tokio/blob/HEAD/tokio-util/CHANGELOG.md\n\n## [0.7.1] - 2024-02-01\n"
+    ));
+    assert!(text.text.contains("## 0.6.3 (2023-11-20)"));
+    assert!(!text.text.contains("Unreleased"));
+
+    // Without a changelog, the GitHub releases
+    let result = router
+        .call_tool("release_notes", json!({ "crate_name": "bytes", "from_version": "1.5.0", "to_version": "1.6.0" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert_eq!(
+        text.text,
+        "# Changes in bytes after 1.5.0 up to 1.6.0\nSource: https://github.com/tokio-rs/bytes/releases\n\n## v1.6.0\n\nAdded `Buf::get_int`\n"
+    );
+
+    let result = router
+        .call_tool("release_notes", json!({ "crate_name": "bytes", "from_version": "1.6.0" }))
+        .await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+    let result = router
+        .call_tool("release_notes", json!({ "crate_name": "bytes", "from_version": "0.4", "to_version": "0.5" }))
+        .await;
+    assert!(matches!(result, Err(ToolError::NotFound(_))));
+}
+
+const EDITION_GUIDE_TOC: &str = r#"<ol class="chapter">
+    <li class="chapter-item expanded "><a href="introduction.html"><strong aria-hidden="true">1.</strong> Introduction</a></li>
+    <li class="chapter-item expanded "><a href="rust-2021/index.html"><strong aria-hidden="true">2.</strong> Rust 2021</a></li>
+    <li><ol class="section">
+        <li class="chapter-item expanded "><a href="rust-2021/disjoint-capture-in-closures.html"><strong aria-hidden="true">2.1.</strong> Disjoint capture in closures</a></li>
+        <li class="chapter-item expanded "><a href="rust-2021/reserving-syntax.html"><strong aria-hidden="true">2.2.</strong> Reserving syntax</a></li>
+    </ol></li>
+    <li class="chapter-item expanded "><a href="rust-2024/index.html"><strong aria-hidden="true">3.</strong> Rust 2024</a></li>
+    <li><ol class="section">
+        <li class="chapter-item expanded "><a href="rust-2024/rpit-lifetime-capture.html"><strong aria-hidden="true">3.1.</strong> RPIT lifetime capture rules</a></li>
+    </ol></li>
+</ol>"#;
+
+#[test]
+fn test_parse_and_find_chapters() {
+    let chapters = parse_chapters(EDITION_GUIDE_TOC);
+    assert_eq!(chapters.len(), 6);
+    assert_eq!(
+        chapters[2],
+        Chapter {
+            title: "Disjoint capture in closures".to_string(),
+            path: "rust-2021/disjoint-capture-in-closures.html".to_string(),
+        }
+    );
+
+    let title = |topic: &str| find_chapter(&chapters, topic).map(|chapter| chapter.title.as_str());
+    assert_eq!(title("rust 2021"), Some("Rust 2021"));
+    assert_eq!(title("closures capture"), Some("Disjoint capture in closures"));
+    assert_eq!(title("RPIT lifetime"), Some("RPIT lifetime capture rules"));
+    assert_eq!(title("async closures"), Some("Disjoint capture in closures"));
+    assert_eq!(title("const generics"), None);
+}
+
+#[tokio::test]
+async fn test_edition_guide() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("book:edition-guide/toc.html", EDITION_GUIDE_TOC)
+        .with_page(
+            "book:edition-guide/rust-2024/rpit-lifetime-capture.html",
+            "<nav>Sidebar</nav><main><h1>RPIT lifetime capture rules</h1><p>In Rust 2024, all in-scope generic parameters are captured.</p></main>",
+        ));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let result = router
+        .call_tool("edition_guide", json!({ "topic": "lifetime capture", "edition": "2024" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with(
+        "The Rust Edition Guide: RPIT lifetime capture rules\nSource: https://doc.rust-lang.org/edition-guide/rust-2024/rpit-lifetime-capture.html\n"
+    ));
+    assert!(text.text.contains("all in-scope generic parameters are captured"));
+    assert!(!text.text.contains("Sidebar"));
+
+    // Without a topic the chapters of the edition are listed; the table of contents is fetched once
+    let result = router.call_tool("edition_guide", json!({ "edition": "2021" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("- Reserving syntax (`rust-2021/reserving-syntax.html`)"));
+    assert!(!text.text.contains("Rust 2024"));
+    let toc_requests = fetcher.requests().iter().filter(|key| key.ends_with("toc.html")).count();
+    assert_eq!(toc_requests, 1);
+
+    let result = router.call_tool("edition_guide", json!({ "edition": "2027" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_cargo_book() {
+    let toc = r#"<ol class="chapter">
+        <li class="chapter-item"><a href="reference/features.html"><strong>3.4.</strong> Features</a></li>
+        <li class="chapter-item"><a href="reference/features-examples.html"><strong>3.4.1.</strong> Features Examples</a></li>
+        <li class="chapter-item"><a href="reference/profiles.html"><strong>3.5.</strong> Profiles</a></li>
+    </ol>"#;
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("book:cargo/index.html", toc)
+        .with_page("book:cargo/reference/features.html", "<main><h1>Features</h1><p>Cargo features provide a mechanism to express conditional compilation.</p></main>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    // Books built with older mdBook versions have no toc.html; the sidebar of index.html is used
+    let result = router.call_tool("cargo_book", json!({ "topic": "features" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("The Cargo Book: Features\nSource: https://doc.rust-lang.org/cargo/reference/features.html\n"));
+    assert!(fetcher.requests().contains(&"book:cargo/toc.html".to_string()));
+
+    let result = router.call_tool("cargo_book", json!({ "topic": "build scripts" })).await;
+    assert!(matches!(result, Err(ToolError::NotFound(_))));
+    let result = router.call_tool("cargo_book", json!({})).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_lookup_book() {
+    let toc = r#"<ol class="chapter">
+        <li class="chapter-item"><a href="subtyping.html"><strong>3.8.</strong> Subtyping and Variance</a></li>
+        <li class="chapter-item"><a href="phantom-data.html"><strong>3.10.</strong> PhantomData</a></li>
+    </ol>"#;
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("book:nomicon/toc.html", toc)
+        .with_page("book:nomicon/subtyping.html", "<main><h1>Subtyping and Variance</h1><p>Variance is a property of type constructors.</p></main>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router.call_tool("lookup_book", json!({ "book": "nomicon", "topic": "variance" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("The Rustonomicon: Subtyping and Variance\n"));
+    assert!(text.text.contains("Variance is a property of type constructors."));
+
+    let result = router.call_tool("lookup_book", json!({ "book": "nomicon" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# The Rustonomicon\n\n- Subtyping and Variance (`subtyping.html`)\n- PhantomData"));
+
+    let result = router.call_tool("lookup_book", json!({ "book": "rust-by-example" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(_))));
+}
+
+#[tokio::test]
+async fn test_local_docs_served_through_lookup_tools() {
+    let doc_dir = std::env::temp_dir().join(format!("cratedocs-local-test-{}", std::process::id()));
+    std::fs::create_dir_all(doc_dir.join("my_private")).unwrap();
+    std::fs::write(doc_dir.join("my_private/index.html"), "<h1>Crate my_private</h1>").unwrap();
+    std::fs::write(doc_dir.join("my_private/struct.Widget.html"), "<h1>Struct my_private::Widget</h1>").unwrap();
+
+    let local_docs = LocalDocs::default();
+    let fetcher = Arc::new(MockFetcher::default().with_page("item:serde@latest/trait.Serialize.html", "<h1>Trait serde::Serialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher.clone()).with_local_docs(local_docs.clone());
+    assert!(router.list_tools().iter().any(|tool| tool.name == "build_local_docs"));
+    local_docs.register("my-private", doc_dir.clone()).await;
+
+    let result = router.call_tool("lookup_item", json!({ "crate_name": "my-private", "item_path": "Widget" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for my-private local-1\nSource: file://"));
+    assert!(text.text.contains("Struct my_private::Widget"));
+
+    // Rebuilt docs are not shadowed by pages cached from the previous build
+    std::fs::write(doc_dir.join("my_private/struct.Widget.html"), "<h1>Struct my_private::Widget v2</h1>").unwrap();
+    local_docs.register("my-private", doc_dir.clone()).await;
+    let result = router.call_tool("lookup_item", json!({ "crate_name": "my-private", "item_path": "Widget" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Widget v2"));
+
+    // Other crates still come from the wrapped fetcher, and nothing local is looked up upstream
+    router.call_tool("lookup_item", json!({ "crate_name": "serde", "item_path": "Serialize" })).await.unwrap();
+    assert!(fetcher.requests().iter().all(|key| !key.contains("my-private") && !key.contains("my_private")));
+
+    std::fs::remove_dir_all(&doc_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_source_registry_dispatch() {
+    let std_docs = Arc::new(MockFetcher::default().with_page("crate:std@latest", "<h1>Crate std</h1>"));
+    let docs_rs = Arc::new(
+        MockFetcher::default()
+            .with_page("crate:serde@latest", "<h1>Crate serde</h1>")
+            .with_page("search:serde:5", r#"{"crates":[]}"#),
+    );
+    let sources = SourceRegistry::new()
+        .with_source(Arc::new(
+            FetcherSource::new("std", Capabilities::CRATE_DOCS, std_docs.clone()).for_crates(|crate_name| crate_name == "std"),
+        ))
+        .with_source(Arc::new(FetcherSource::new("docs.rs", Capabilities::CRATE_DOCS | Capabilities::SEARCH, docs_rs.clone())));
+    assert_eq!(sources.names(), ["std", "docs.rs"]);
+
+    // Crate requests go to the first source serving the crate
+    assert_eq!(sources.get_crate_page("std", None).await.unwrap().html, "<h1>Crate std</h1>");
+    assert_eq!(sources.get_crate_page("serde", None).await.unwrap().html, "<h1>Crate serde</h1>");
+    assert_eq!(sources.search("serde", 5).await.unwrap(), r#"{"crates":[]}"#);
+    assert_eq!(std_docs.requests(), ["crate:std@latest"]);
+    assert_eq!(docs_rs.requests(), ["crate:serde@latest", "search:serde:5"]);
+
+    // What no enabled source can serve fails without a request
+    let error = sources.get_book_page(Book::Cargo, "index.html").await.unwrap_err();
+    assert_eq!(error_data(&error).unwrap().code, ErrorCode::UpstreamUnavailable);
+    let sources = sources.disable(&["docs.rs".to_string()]).unwrap();
+    let error = sources.get_crate_page("serde", None).await.unwrap_err();
+    assert!(error.to_string().contains("No enabled documentation source serves the docs of serde"));
+    assert_eq!(docs_rs.requests().len(), 2);
+
+    // Misspelled sources are refused instead of ignored
+    let error = SourceRegistry::upstream(docs_rs.clone()).disable(&["docsrs".to_string()]).err().unwrap();
+    assert!(error.contains("Unknown documentation source docsrs"));
+    assert_eq!(SourceRegistry::upstream(docs_rs.clone()).names(), UPSTREAM_SOURCES);
+
+    // Tools fail the same way for a disabled source
+    let router = DocRouter::with_sources(SourceRegistry::upstream(docs_rs.clone()).disable(&["crates.io".to_string()]).unwrap());
+    let result = router.call_tool("search_crates", json!({ "query": "serde" })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(msg)) if msg.contains("serves crate search")));
+    assert_eq!(format!("{:?}", Capabilities::CRATE_DOCS | Capabilities::BOOKS), "crate_docs | books");
+}
+
+#[tokio::test]
+async fn test_build_local_docs_is_guarded() {
+    // Not offered at all unless enabled
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()));
+    assert!(router.list_tools().iter().all(|tool| tool.name != "build_local_docs"));
+    let result = router.call_tool("build_local_docs", json!({ "path": "." })).await;
+    assert!(matches!(result, Err(ToolError::ExecutionError(msg)) if msg.contains("--local-docs-root")));
+
+    // Workspaces outside the allowed roots are refused before cargo runs
+    let root = std::env::temp_dir().join(format!("cratedocs-local-root-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default())).with_local_docs(LocalDocs::new(vec![root.clone()]));
+    let outside = env!("CARGO_MANIFEST_DIR");
+    let result = router.call_tool("build_local_docs", json!({ "path": outside })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(msg)) if msg.contains("outside")));
+    let result = router.call_tool("build_local_docs", json!({ "path": root.to_str().unwrap() })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(msg)) if msg.contains("no Cargo.toml")));
+
+    // Builds write to a target directory of the server's, one per workspace
+    let local = LocalDocs::new(vec![root.clone()]).with_target_dir(root.join("targets"));
+    let (first, second) = (root.join("first"), root.join("second"));
+    assert!(local.target_dir(&first).starts_with(root.join("targets")));
+    assert!(!local.target_dir(&first).starts_with(&first));
+    assert_ne!(local.target_dir(&first), local.target_dir(&second));
+    assert_eq!(local.target_dir(&first), local.target_dir(&first));
+    assert!(!LocalDocs::new(vec![root.clone()]).target_dir(&first).starts_with(&first));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[tokio::test]
+async fn test_circuit_breaker() {
+    let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+    assert!(!breaker.record_failure("docs.rs").await);
+    // A success in between starts the count over
+    breaker.record_success("docs.rs").await;
+    assert!(!breaker.record_failure("docs.rs").await);
+    assert!(breaker.check("docs.rs").await.is_ok());
+    assert!(breaker.record_failure("docs.rs").await);
+    assert!(breaker.check("docs.rs").await.is_err());
+    // Other upstreams are unaffected
+    assert!(breaker.check("crates.io").await.is_ok());
+
+    // After the cooldown a single trial goes through, and a failed trial reopens the circuit
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(breaker.check("docs.rs").await.is_ok());
+    assert!(breaker.check("docs.rs").await.is_err());
+    assert!(breaker.record_failure("docs.rs").await);
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(breaker.check("docs.rs").await.is_ok());
+    breaker.record_success("docs.rs").await;
+    assert!(breaker.check("docs.rs").await.is_ok());
+
+    // A threshold of 0 never opens a circuit
+    let disabled = CircuitBreaker::new(0, Duration::from_secs(60));
+    for _ in 0..10 {
+        assert!(!disabled.record_failure("docs.rs").await);
+    }
+    assert!(disabled.check("docs.rs").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_error_data() {
+    let error = with_error_data(
+        ToolError::ExecutionError("Failed to search crates.io".to_string()),
+        ErrorData::new(ErrorCode::UpstreamRateLimited).with_suggestions(vec!["Retry after 60 seconds".to_string()]),
+    );
+    let ToolError::ExecutionError(message) = &error else { panic!("Expected ExecutionError") };
+    let (text, data) = split_error_data(message);
+    assert_eq!(text, "Failed to search crates.io");
+    assert_eq!(data.unwrap().suggestions, vec!["Retry after 60 seconds".to_string()]);
+    // Attached data is kept, and replaced rather than stacked
+    let error = ensure_error_data(error);
+    assert_eq!(error_data(&error).unwrap().code, ErrorCode::UpstreamRateLimited);
+    let replaced = with_error_data(error, ErrorData::new(ErrorCode::Timeout));
+    let ToolError::ExecutionError(message) = &replaced else { panic!("Expected ExecutionError") };
+    assert_eq!(split_error_data(message), ("Failed to search crates.io", Some(ErrorData::new(ErrorCode::Timeout))));
+    assert_eq!(split_error_data("no data here"), ("no data here", None));
+
+    let versions = parse_index_entry(&[
+        r#"{"name":"demo","vers":"1.2.0","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"demo","vers":"1.10.0","deps":[],"features":{},"yanked":false}"#,
+        r#"{"name":"demo","vers":"1.11.0","deps":[],"features":{},"yanked":true}"#,
+    ].join("\n"));
+    let data = error_data(&version_not_found("Version 9.0.0 of demo not found".to_string(), &versions)).unwrap();
+    assert_eq!(data.code, ErrorCode::VersionNotFound);
+    assert_eq!(data.suggestions, vec!["Use version 1.10.0".to_string(), "Use version 1.2.0".to_string()]);
+    assert_eq!(
+        serde_json::to_value(&data).unwrap(),
+        json!({ "code": "VERSION_NOT_FOUND", "suggestions": ["Use version 1.10.0", "Use version 1.2.0"] })
+    );
+
+    // Every error leaving a tool call has a code, by default the one its variant implies
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()));
+    let missing_argument = router.call_tool("lookup_crate", json!({})).await.unwrap_err();
+    assert_eq!(error_data(&missing_argument).unwrap().code, ErrorCode::InvalidArguments);
+    let unknown_tool = router.call_tool("no_such_tool", json!({})).await.unwrap_err();
+    assert_eq!(error_data(&unknown_tool).unwrap().code, ErrorCode::NotFound);
+}
+
+#[test]
+fn test_docs_error() {
+    let rate_limited = DocsError::RateLimited {
+        action: "search crates.io".to_string(),
+        retry_after: Some(Duration::from_secs(60)),
+    };
+    assert!(rate_limited.is_transient());
+    assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(60)));
+    let error = ToolError::from(rate_limited);
+    let ToolError::ExecutionError(message) = &error else { panic!("Expected ExecutionError") };
+    let (text, data) = split_error_data(message);
+    assert_eq!(text, "Failed to search crates.io. Status: 429 Too Many Requests");
+    let data = data.unwrap();
+    assert_eq!(data.code, ErrorCode::UpstreamRateLimited);
+    assert_eq!(data.suggestions, vec!["Retry after 60 seconds".to_string()]);
+
+    // Statuses map to the codes clients branch on; others carry no code of their own
+    let status = |status: reqwest::StatusCode| DocsError::UpstreamStatus { action: "fetch documentation".to_string(), status };
+    assert_eq!(status(reqwest::StatusCode::NOT_FOUND).code(), Some(ErrorCode::NotFound));
+    assert!(!status(reqwest::StatusCode::NOT_FOUND).is_transient());
+    assert_eq!(status(reqwest::StatusCode::BAD_GATEWAY).code(), Some(ErrorCode::UpstreamUnavailable));
+    assert!(status(reqwest::StatusCode::BAD_GATEWAY).is_transient());
+    let forbidden = ToolError::from(status(reqwest::StatusCode::FORBIDDEN));
+    assert_eq!(error_data(&forbidden), None);
+    assert_eq!(forbidden.to_string(), ToolError::ExecutionError("Failed to fetch documentation. Status: 403 Forbidden".to_string()).to_string());
+
+    let timeout = ToolError::from(DocsError::Timeout { tool: "lookup_crate".to_string(), limit: Duration::from_secs(5) });
+    assert_eq!(error_data(&timeout).unwrap().code, ErrorCode::Timeout);
+    assert!(timeout.to_string().contains("Timed out: lookup_crate did not finish within 5s"));
+    let too_large = DocsError::TooLarge { what: "Response from https://docs.rs/".to_string(), max_bytes: 10 };
+    assert_eq!(too_large.to_string(), "Response from https://docs.rs/ exceeds the maximum size of 10 bytes");
+    let conversion = DocsError::Conversion("demo 1.0.0: unsupported format version".to_string());
+    assert_eq!((conversion.code(), conversion.is_transient()), (None, false));
+    assert_eq!(error_data(&ensure_error_data(conversion.into())).unwrap().code, ErrorCode::ExecutionFailed);
+}
+
+#[test]
+fn test_parse_doc_url() {
+    let page = |crate_name: &str, version: Option<&str>, path: &str| DocUrl::Page {
+        crate_name: crate_name.to_string(),
+        version: version.map(str::to_string),
+        path: path.to_string(),
+    };
+    assert_eq!(
+        DocUrl::parse("https://docs.rs/tokio/latest/tokio/runtime/struct.Builder.html#method.worker_threads"),
+        Ok((page("tokio", None, "runtime/struct.Builder.html"), Some("method.worker_threads".to_string())))
+    );
+    assert_eq!(DocUrl::parse("https://docs.rs/serde/1.0.200/serde/"), Ok((page("serde", Some("1.0.200"), "index.html"), None)));
+    assert_eq!(DocUrl::parse("https://docs.rs/serde"), Ok((page("serde", None, "index.html"), None)));
+    assert_eq!(
+        DocUrl::parse("https://docs.rs/tokio/1.43.0/tokio/sync/"),
+        Ok((page("tokio", Some("1.43.0"), "sync/index.html"), None))
+    );
+    assert_eq!(
+        DocUrl::parse("https://docs.rs/crate/serde/1.0.200"),
+        Ok((DocUrl::CrateOverview { crate_name: "serde".to_string(), version: Some("1.0.200".to_string()) }, None))
+    );
+    assert_eq!(
+        DocUrl::parse("https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push"),
+        Ok((page("std", None, "vec/struct.Vec.html"), Some("method.push".to_string())))
+    );
+    assert_eq!(
+        DocUrl::parse("https://doc.rust-lang.org/nightly/core/option/enum.Option.html"),
+        Ok((page("core", Some("nightly"), "option/enum.Option.html"), None))
+    );
+    assert_eq!(
+        DocUrl::parse("https://doc.rust-lang.org/cargo/reference/features.html"),
+        Ok((DocUrl::Book { book: Book::Cargo, path: "reference/features.html".to_string() }, None))
+    );
+
+    // Other hosts, schemes and unsupported pages are refused
+    assert!(DocUrl::parse("https://evil.example/tokio/latest/tokio/").unwrap_err().contains("allowed: docs.rs, doc.rust-lang.org"));
+    assert!(DocUrl::parse("https://docs.rs.evil.example/tokio").is_err());
+    assert!(DocUrl::parse("file:///etc/passwd").is_err());
+    assert!(DocUrl::parse("not a url").is_err());
+    assert!(DocUrl::parse("https://docs.rs/").is_err());
+    assert!(DocUrl::parse("https://docs.rs/tokio/1.43.0/src/tokio/lib.rs.html").is_err());
+    assert!(DocUrl::parse("https://doc.rust-lang.org/reference/introduction.html").is_err());
+}
+
+#[tokio::test]
+async fn test_lookup_url() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page(
+            "item:tokio@1.43.0/runtime/struct.Builder.html",
+            r#"<h1>Struct Builder</h1><p>Builds a runtime.</p>
+               <details><summary><section id="method.worker_threads"><h4>pub fn worker_threads(&mut self, val: usize)</h4></section></summary>
+               <div class="docblock"><p>Sets the number of worker threads.</p></div></details>
+               <details><summary><section id="method.enable_all"><h4>pub fn enable_all(&mut self)</h4></section></summary>
+               <div class="docblock"><p>Enables both I/O and time drivers.</p></div></details>"#,
+        )
+        .with_page("book:cargo/reference/features.html", "<nav>sidebar</nav><main><h1>Features</h1><p>Conditional compilation.</p></main>"));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let result = router
+        .call_tool("lookup_url", json!({ "url": "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.worker_threads" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with(
+        "Documentation for tokio 1.43.0\nSource: https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.worker_threads\n"
+    ));
+    assert!(text.text.contains("Sets the number of worker threads."));
+    assert!(!text.text.contains("Enables both I/O"));
+
+    // Without an anchor the whole page is returned; pinned pages come from the cache the second time
+    let result = router
+        .call_tool("lookup_url", json!({ "url": "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Builds a runtime.") && text.text.contains("Enables both I/O"));
+    router
+        .call_tool("lookup_url", json!({ "url": "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html" }))
+        .await
+        .unwrap();
+    let page_requests = fetcher.requests().iter().filter(|key| key.starts_with("item:tokio@1.43.0/")).count();
+    assert_eq!(page_requests, 2);
+
+    let result = router
+        .call_tool("lookup_url", json!({ "url": "https://doc.rust-lang.org/cargo/reference/features.html" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("The Cargo Book\nSource: https://doc.rust-lang.org/cargo/reference/features.html\n"));
+    assert!(text.text.contains("Conditional compilation.") && !text.text.contains("sidebar"));
+
+    let result = router.call_tool("lookup_url", json!({ "url": "https://example.com/tokio" })).await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(msg)) if msg.contains("not a documentation host")));
+}
+
+#[test]
+fn test_unified_diff() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\n";
+    assert_eq!(
+        unified_diff(old, new, "old", "new", 1).unwrap(),
+        "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -9,2 +9 @@\n i\n-j\n"
+    );
+    // Changes within twice the context of each other share a hunk
+    assert_eq!(
+        unified_diff("a\nb\nc\nd\n", "A\nb\nc\nD\n", "old", "new", 1).unwrap(),
+        "--- old\n+++ new\n@@ -1,4 +1,4 @@\n-a\n+A\n b\n c\n-d\n+D\n"
+    );
+    assert_eq!(unified_diff("", "a\n", "old", "new", 3).unwrap(), "--- old\n+++ new\n@@ -0,0 +1 @@\n+a\n");
+    assert_eq!(unified_diff(old, old, "old", "new", 3), None);
+}
+
+#[test]
+fn test_declaration() {
+    let markdown = "Documentation for demo 1.0.0\nSource: https://docs.rs/demo\n\nStruct Widget\n=============\n\n```\npub struct Widget {\n    pub size: u8,\n}\n```\n\nA widget.\n\n\
+        Examples\n--------\n\n```\nlet w = Widget { size: 1 };\n```\n";
+    assert_eq!(declaration(markdown).as_deref(), Some("pub struct Widget {\n    pub size: u8,\n}"));
+    assert_eq!(declaration("Struct Widget\n=============\n\nA widget.\n\nExamples\n--------\n\n```\nlet w = Widget;\n```\n"), None);
+}
+
+#[tokio::test]
+async fn test_diff_item() {
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("item:demo@1.0.0/struct.Widget.html", "<h1>Struct Widget</h1><pre><code>pub struct Widget;</code></pre><p>A widget.</p>")
+        .with_page("item:demo@2.0.0/struct.Widget.html", "<h1>Struct Widget</h1><pre><code>pub struct Widget(u8);</code></pre><p>A sized widget.</p>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let args = json!({ "crate_name": "demo", "item_path": "demo::Widget", "from_version": "1.0.0", "to_version": "2.0.0" });
+    let result = router.call_tool("diff_item", args).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("# Widget of demo: 1.0.0 to 2.0.0\n\nSource (1.0.0): "));
+    assert!(text.text.contains("## Declaration\n\n````diff\n--- demo 1.0.0 Widget\n+++ demo 2.0.0 Widget\n@@ -1 +1 @@\n-pub struct Widget;\n+pub struct Widget(u8);\n````\n"));
+    assert!(text.text.contains("-A widget.\n+A sized widget.\n"));
+
+    let args = json!({ "crate_name": "demo", "item_path": "Widget", "from_version": "1.0.0", "to_version": "1.0.0" });
+    let result = router.call_tool("diff_item", args).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("## Declaration\n\nUnchanged:\n\n```rust\npub struct Widget;\n```\n"));
+    assert!(text.text.ends_with("## Documentation\n\nUnchanged.\n"));
+}
+
+#[test]
+fn test_format_signature() {
+    let function = "pub fn spawn_blocking_with_a_long_name<F, R>(runtime_handle: &Handle, function: F, name: Option<&str>) -> JoinHandle<R> where F: FnOnce() -> R + Send + 'static, R: Send + 'static";
+    assert_eq!(
+        format_signature(function),
+        "pub fn spawn_blocking_with_a_long_name<F, R>(\n    runtime_handle: &Handle,\n    function: F,\n    name: Option<&str>,\n) -> JoinHandle<R>\nwhere\n    F: FnOnce() -> R + Send + 'static,\n    R: Send + 'static,"
+    );
+
+    // Generics too long to fit in front of the parameters go one per line as well
+    let method = "    pub fn connect_with<A: ToSocketAddrs + Send + Sync + 'static, C: Connector<Stream = TcpStream> + Clone + 'static>(addrs: A, connector: C) -> Result<Connection<C::Stream>>;";
+    assert_eq!(
+        format_signature(method),
+        "    pub fn connect_with<\n        A: ToSocketAddrs + Send + Sync + 'static,\n        C: Connector<Stream = TcpStream> + Clone + 'static,\n    >(\n        addrs: A,\n        connector: C,\n    ) -> Result<Connection<C::Stream>>;"
+    );
+
+    let structure = "pub struct HashMapWrapperWithLongName<K, V, S = RandomState, A: Allocator + Clone = Global, B = DefaultBuilder> { /* private fields */ }";
+    assert_eq!(
+        format_signature(structure),
+        "pub struct HashMapWrapperWithLongName<\n    K,\n    V,\n    S = RandomState,\n    A: Allocator + Clone = Global,\n    B = DefaultBuilder,\n> { /* private fields */ }"
+    );
+    let bounded = "pub struct Wrapper<T>(pub T) where T: Clone + Send + Sync + std::fmt::Debug + std::fmt::Display + std::hash::Hash + 'static;";
+    assert_eq!(
+        format_signature(bounded),
+        "pub struct Wrapper<T>(pub T)\nwhere\n    T: Clone + Send + Sync + std::fmt::Debug + std::fmt::Display + std::hash::Hash + 'static;"
+    );
+
+    // Lines that fit are kept as rustdoc wrapped them
+    let short = "pub struct Sender<T> {\n    pub capacity: usize,\n}";
+    assert_eq!(format_signature(short), short);
+}
+
+#[tokio::test]
+async fn test_summary_reflows_declaration() {
+    let declaration = "pub fn spawn&lt;F, R&gt;(handle: Handle, function: F, name: Option&lt;String&gt;) -&gt; JoinHandle&lt;R&gt; where F: FnOnce() -&gt; R + Send + 'static, R: Send + 'static";
+    let page = format!("<h1>Function demo::spawn</h1><pre><code>{}</code></pre><p>Spawns a task.</p><h2>Examples</h2><p>Call it.</p>", declaration);
+    let fetcher = Arc::new(MockFetcher::default().with_page("item:demo@1.0.0/fn.spawn.html", &page));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let args = json!({ "crate_name": "demo", "item_path": "spawn", "version": "1.0.0", "summary": true });
+    let result = router.call_tool("lookup_item", args).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains(
+        "pub fn spawn<F, R>(handle: Handle, function: F, name: Option<String>) -> JoinHandle<R>\nwhere\n    F: FnOnce() -> R + Send + 'static,\n    R: Send + 'static,\n```"
+    ));
+    assert!(text.text.contains("Spawns a task."));
+}
+
+#[test]
+fn test_select_sections() {
+    let markdown = "Documentation for demo 1.0.0\nSource: https://docs.rs/demo\n\nStruct Widget\n=============\n\nA widget.\n\n\
+        Examples[§](#examples)\n----------------------\n\n    // # not a heading\n    let w = Widget::new();\n\n\
+        Implementations[§](#implementations)\n------------------------------------\n\n### impl Widget\n\n#### pub fn new() -> Widget\n\n\
+        ##### Panics\n\nNever.\n\n\
+        Trait Implementations[§](#trait-implementations)\n------------------------------------------------\n\n### impl Clone for Widget\n";
+
+    let outline: Vec<(usize, String, Option<String>)> = headings(markdown)
+        .into_iter()
+        .map(|heading| (heading.level, heading.title, heading.anchor))
+        .collect();
+    let heading = |level: usize, title: &str, anchor: Option<&str>| (level, title.to_string(), anchor.map(str::to_string));
+    assert_eq!(outline, vec![
+        heading(1, "Struct Widget", None),
+        heading(2, "Examples", Some("examples")),
+        heading(2, "Implementations", Some("implementations")),
+        heading(3, "impl Widget", None),
+        heading(4, "pub fn new() -> Widget", None),
+        heading(5, "Panics", None),
+        heading(2, "Trait Implementations", Some("trait-implementations")),
+        heading(3, "impl Clone for Widget", None),
+    ]);
+
+    let examples = select_sections(markdown, "example").unwrap();
+    assert!(examples.starts_with("Examples[§](#examples)") && examples.contains("// # not a heading"));
+    assert!(!examples.contains("Implementations"));
+
+    // Methods are listed under "Implementations", which ends at the next second-level heading
+    let methods = select_sections(markdown, "Methods").unwrap();
+    assert!(methods.contains("pub fn new() -> Widget") && methods.contains("Never."));
+    assert!(!methods.contains("impl Clone"));
+    assert_eq!(select_sections(markdown, "trait implementations").unwrap().lines().last(), Some("### impl Clone for Widget"));
+    assert_eq!(select_sections(markdown, "panics").unwrap(), "##### Panics\n\nNever.");
+    assert_eq!(select_sections(markdown, "Safety"), None);
+}
+
+#[tokio::test]
+async fn test_lookup_section() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:demo@1.0.0/struct.Widget.html",
+        "<h1>Struct Widget</h1><p>A widget.</p><h2>Examples</h2><pre><code>let w = Widget::new();</code></pre>\
+         <h2>Trait Implementations</h2><h3>impl Clone for Widget</h3>",
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router
+        .call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Widget", "version": "1.0.0", "section": "Examples" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for demo 1.0.0\nSource: https://docs.rs/demo/1.0.0/demo/struct.Widget.html\n\nExamples"));
+    assert!(text.text.contains("Widget::new()"));
+    assert!(!text.text.contains("A widget.") && !text.text.contains("impl Clone"));
+
+    let error = router
+        .call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Widget", "version": "1.0.0", "section": "Safety" }))
+        .await
+        .unwrap_err();
+    assert!(matches!(&error, ToolError::NotFound(msg) if msg.starts_with("The page has no Safety section")));
+    let data = error_data(&error).unwrap();
+    assert_eq!(data.code, ErrorCode::NotFound);
+    assert_eq!(data.suggestions, vec!["Use section Examples".to_string(), "Use section Trait Implementations".to_string(), "Use section impl Clone for Widget".to_string()]);
+}
+
+#[tokio::test]
+async fn test_page_outline() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:demo@1.0.0/struct.Widget.html",
+        r##"<h1>Struct Widget</h1><p>A widget.</p>
+           <h2 id="examples">Examples<a href="#examples" class="anchor">§</a></h2><pre><code>let w = Widget::new();</code></pre>
+           <h2 id="implementations">Implementations<a href="#implementations" class="anchor">§</a></h2>
+           <h4 id="method.new">pub fn <a href="#method.new">new</a>() -&gt; Widget</h4>"##,
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let result = router
+        .call_tool("page_outline", json!({ "crate_name": "demo", "item_path": "Widget", "version": "1.0.0" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for demo 1.0.0\nSource: https://docs.rs/demo/1.0.0/demo/struct.Widget.html\n\n"));
+    assert!(text.text.ends_with(
+        "- Struct Widget\n  - Examples (#examples)\n  - Implementations (#implementations)\n      - pub fn new() -> Widget (#method.new)\n"
+    ));
+    assert!(!text.text.contains("A widget."));
+}
+
+#[tokio::test]
+async fn test_lookup_item_with_anchor() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:tokio@1.43.0/runtime/struct.Builder.html",
+        r##"<h1>Struct Builder</h1><p>Builds a runtime.</p>
+           <h2 id="examples">Examples<a href="#examples" class="anchor">§</a></h2><pre><code>let rt = Builder::new_multi_thread();</code></pre>
+           <h2 id="implementations">Implementations<a href="#implementations" class="anchor">§</a></h2>
+           <details><summary><section id="method.worker_threads"><h4>pub fn worker_threads(&amp;mut self, val: usize)</h4></section></summary>
+           <div class="docblock"><p>Sets the number of worker threads.</p></div></details>
+           <details><summary><section id="method.enable_all"><h4>pub fn enable_all(&amp;mut self)</h4></section></summary>
+           <div class="docblock"><p>Enables both I/O and time drivers.</p></div></details>"##,
+    ));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+    let lookup = |item_path: &str| {
+        router.call_tool("lookup_item", json!({ "crate_name": "tokio", "item_path": item_path, "version": "1.43.0" }))
+    };
+
+    let result = lookup("tokio::runtime::Builder#method.worker_threads").await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with(
+        "Documentation for tokio 1.43.0\nSource: https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.worker_threads\n"
+    ));
+    assert!(text.text.contains("Sets the number of worker threads."));
+    assert!(!text.text.contains("Builds a runtime.") && !text.text.contains("Enables both I/O"));
+
+    // Heading anchors select the heading's section
+    let result = lookup("runtime::Builder#examples").await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Builder::new_multi_thread()") && !text.text.contains("worker threads"));
+
+    // The page is fetched once per anchor and cached separately from the whole page
+    lookup("runtime::Builder#examples").await.unwrap();
+    let result = lookup("runtime::Builder").await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("Builds a runtime.") && text.text.contains("Enables both I/O"));
+    let page_requests = fetcher.requests().iter().filter(|key| key.as_str() == "item:tokio@1.43.0/runtime/struct.Builder.html").count();
+    assert_eq!(page_requests, 3);
+
+    let error = lookup("runtime::Builder#method.nope").await.unwrap_err();
+    assert!(matches!(&error, ToolError::NotFound(msg) if msg.starts_with("runtime::Builder has no anchor #method.nope")));
+    assert_eq!(error_data(&error).unwrap().code, ErrorCode::NotFound);
+}
+
+const BUILDER_PAGE_WITH_SOURCES: &str = r##"<h1>Struct <a class="mod" href="../index.html">tokio</a>::<a class="mod" href="index.html">runtime</a>::<span>Builder</span><span class="out-of-band"><a class="src" href="../../src/tokio/runtime/builder.rs.html#50">Source</a></span></h1>
+    <p>Builds a <a class="struct" href="struct.Runtime.html">Runtime</a>.</p>
+    <details><summary><section id="method.worker_threads"><a class="src" href="../../src/tokio/runtime/builder.rs.html#300">Source</a>
+    <h4>pub fn worker_threads(&amp;mut self, val: usize)</h4></section></summary>
+    <div class="docblock"><p>Sets the number of worker threads.</p></div></details>
+    <details><summary><section id="method.enable_all"><h4>pub fn enable_all(&amp;mut self)</h4></section></summary>
+    <div class="docblock"><p>Enables both I/O and time drivers.</p></div></details>"##;
+
+#[test]
+fn test_page_links() {
+    let link = |name: &str, url: &str, source: Option<&str>| PageLink {
+        name: name.to_string(),
+        url: url.to_string(),
+        source: source.map(str::to_string),
+    };
+    let links = page_links(BUILDER_PAGE_WITH_SOURCES, "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.enable_all");
+    assert_eq!(links, vec![
+        link(
+            "Builder",
+            "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html",
+            Some("https://docs.rs/tokio/1.43.0/src/tokio/runtime/builder.rs.html#50"),
+        ),
+        link(
+            "worker_threads",
+            "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.worker_threads",
+            Some("https://docs.rs/tokio/1.43.0/src/tokio/runtime/builder.rs.html#300"),
+        ),
+        link("enable_all", "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.enable_all", None),
+        link("tokio", "https://docs.rs/tokio/1.43.0/tokio/index.html", None),
+        link("runtime", "https://docs.rs/tokio/1.43.0/tokio/runtime/index.html", None),
+        link("Runtime", "https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Runtime.html", None),
+    ]);
+    assert!(page_links("<h1>Struct Builder</h1>", "not a url").is_empty());
+}
+
+#[tokio::test]
+async fn test_lookup_with_source_links() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("item:tokio@1.43.0/runtime/struct.Builder.html", BUILDER_PAGE_WITH_SOURCES));
+    let router = DocRouter::with_fetcher(fetcher.clone());
+
+    let result = router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "tokio", "item_path": "runtime::Builder#method.worker_threads", "version": "1.43.0", "source_links": true }),
+        )
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    // Only the page and what the narrowed result mentions are linked
+    assert!(text.text.ends_with(
+        "\n\nSource links:\n\
+         - `Builder`: https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html ([src](https://docs.rs/tokio/1.43.0/src/tokio/runtime/builder.rs.html#50))\n\
+         - `worker_threads`: https://docs.rs/tokio/1.43.0/tokio/runtime/struct.Builder.html#method.worker_threads ([src](https://docs.rs/tokio/1.43.0/src/tokio/runtime/builder.rs.html#300))\n"
+    ));
+
+    // Without the argument no links are added
+    let result = router
+        .call_tool("lookup_item", json!({ "crate_name": "tokio", "item_path": "runtime::Builder", "version": "1.43.0" }))
+        .await
+        .unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(!text.text.contains("Source links:"));
+
+    // Links of pinned pages are cached, so asking again doesn't fetch the page again
+    router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "tokio", "item_path": "runtime::Builder", "version": "1.43.0", "source_links": true }),
+        )
+        .await
+        .unwrap();
+    let page_requests = fetcher.requests().iter().filter(|key| key.as_str() == "item:tokio@1.43.0/runtime/struct.Builder.html").count();
+    assert_eq!(page_requests, 3);
+}
+
+#[tokio::test]
+async fn test_hide_generated_impls() {
+    let fetcher = Arc::new(MockFetcher::default().with_page(
+        "item:demo@1.0.0/struct.Name.html",
+        "<h1>Struct Name</h1><p>A name.</p>\
+         <h2>Methods from Deref&lt;Target = str&gt;</h2><h3>impl str</h3><h4>pub fn len(&amp;self) -&gt; usize</h4>\
+         <h2>Trait Implementations</h2><h3>impl Clone for Name</h3>\
+         <h2>Auto Trait Implementations</h2><h3>impl Send for Name</h3>\
+         <h2>Blanket Implementations</h2><h3>impl&lt;T&gt; From&lt;T&gt; for T</h3>",
+    ));
+    let router = DocRouter::with_fetcher(fetcher);
+    let lookup = |hide: bool| {
+        router.call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Name", "version": "1.0.0", "hide_generated_impls": hide }))
+    };
+
+    let result = lookup(true).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("A name.") && text.text.contains("impl Clone for Name"));
+    assert!(!text.text.contains("Deref") && !text.text.contains("len(") && !text.text.contains("impl Send"));
+    assert!(!text.text.contains("Blanket") && !text.text.contains("From"));
+
+    let result = lookup(false).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.contains("impl Send for Name") && text.text.contains("Methods from Deref"));
+
+    // Nothing else is touched
+    let markdown = "Intro\n\n## Examples\n\nSome code.\n";
+    assert_eq!(without_generated_impls(markdown), markdown);
+}
+
+#[test]
+fn test_item_probe_order() {
+    let preferred = |kinds: &[&str]| item_probe_order(&kinds.iter().map(|kind| kind.to_string()).collect::<Vec<_>>());
+    assert_eq!(preferred(&[]).unwrap(), vec!["struct", "enum", "trait", "fn", "macro", "attr", "derive"]);
+    assert_eq!(preferred(&["trait", "fn", "trait"]).unwrap(), vec!["trait", "fn", "struct", "enum", "macro", "attr", "derive"]);
+    assert!(preferred(&["module"]).unwrap_err().starts_with("Unknown item kind module"));
+}
+
+#[tokio::test]
+async fn test_probe_order() {
+    // `Visitor` has both pages, so the probe order decides which one is returned
+    let fetcher = Arc::new(MockFetcher::default()
+        .with_page("item:serde@1.0.0/struct.Visitor.html", "<h1>Struct Visitor</h1>")
+        .with_page("item:serde@1.0.0/de/trait.Visitor.html", "<h1>Trait Visitor</h1>")
+        .with_page("item:serde@1.0.0/de/struct.Visitor.html", "<h1>Struct Visitor</h1>")
+        .with_page("item:serde@1.0.0/ser/trait.Visitor.html", "<h1>Trait Visitor</h1>")
+        .with_page("item:serde@1.0.0/ser/struct.Visitor.html", "<h1>Struct Visitor</h1>")
+        .with_page("item:serde@1.0.0/trait.Serialize.html", "<h1>Trait Serialize</h1>")
+        .with_page("item:serde@1.0.0/trait.Deserialize.html", "<h1>Trait Deserialize</h1>"));
+    let router = DocRouter::with_fetcher(fetcher);
+    let lookup = |item_path: &str, probe_order: Option<Vec<&str>>| {
+        let mut arguments = json!({ "crate_name": "serde", "item_path": item_path, "version": "1.0.0" });
+        if let Some(probe_order) = probe_order {
+            arguments["probe_order"] = json!(probe_order);
+        }
+        router.call_tool("lookup_item", arguments)
+    };
+    let text = |result: Vec<Content>| match &result[0] {
+        Content::Text(text) => text.text.clone(),
+        _ => panic!("Expected text content"),
+    };
+
+    assert!(text(lookup("Visitor", None).await.unwrap()).contains("Struct Visitor"));
+    assert!(text(lookup("de::Visitor", Some(vec!["trait"])).await.unwrap()).contains("Trait Visitor"));
+    assert!(matches!(lookup("Visitor", Some(vec!["module"])).await, Err(ToolError::InvalidParameters(_))));
+
+    // Traits found in the crate before are tried first from then on
+    lookup("Serialize", None).await.unwrap();
+    lookup("Deserialize", None).await.unwrap();
+    assert_eq!(router.cache.get("probe-stats:serde").await.as_deref(), Some("struct=1,trait=3"));
+    assert!(text(lookup("ser::Visitor", None).await.unwrap()).contains("Trait Visitor"));
+
+    // The configured order comes after what was learned
+    let router = DocRouter::with_fetcher(Arc::new(MockFetcher::default()
+        .with_page("item:serde@1.0.0/struct.Visitor.html", "<h1>Struct Visitor</h1>")
+        .with_page("item:serde@1.0.0/fn.Visitor.html", "<h1>Function Visitor</h1>")))
+        .with_probe_order(item_probe_order(&["fn".to_string()]).unwrap());
+    let result = router
+        .call_tool("lookup_item", json!({ "crate_name": "serde", "item_path": "Visitor", "version": "1.0.0" }))
+        .await
+        .unwrap();
+    assert!(text(result).contains("Function Visitor"));
+}
+
+#[test]
+fn test_crate_overrides() {
+    let overrides = CrateOverrides::parse(
+        r#"{
+            "crates": {
+                "tokio": { "version": "1.40.0", "features": ["full"], "aliases": ["tokio-rt"] },
+                "sqlx": { "features": ["postgres"] }
+            },
+            "other": { "ignored": true }
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(overrides.crate_name("tokio-rt"), "tokio");
+    assert_eq!(overrides.crate_name("serde"), "serde");
+    assert!(overrides.targets().is_empty());
+
+    let schema = json!({ "properties": { "crate_name": {}, "version": {}, "features": {} } });
+    let mut arguments = json!({ "crate_name": "tokio-rt" });
+    overrides.apply(&schema, &mut arguments);
+    assert_eq!(arguments, json!({ "crate_name": "tokio", "version": "1.40.0", "features": ["full"] }));
+
+    // What the call gives wins, and only parameters the tool has are filled in
+    let mut arguments = json!({ "crate_name": "tokio", "channel": "stable", "features": [] });
+    overrides.apply(&schema, &mut arguments);
+    assert_eq!(arguments, json!({ "crate_name": "tokio", "channel": "stable", "features": [] }));
+    let mut arguments = json!({ "crate_name": "sqlx" });
+    overrides.apply(&json!({ "properties": { "crate_name": {} } }), &mut arguments);
+    assert_eq!(arguments, json!({ "crate_name": "sqlx" }));
+
+    // Entries of lookup_items get their versions too
+    let mut arguments = json!({ "items": [{ "crate_name": "tokio-rt", "item_path": "spawn" }] });
+    overrides.apply(&json!({ "properties": { "items": {} } }), &mut arguments);
+    assert_eq!(arguments["items"][0], json!({ "crate_name": "tokio", "item_path": "spawn", "version": "1.40.0" }));
+
+    assert!(CrateOverrides::parse(r#"{ "crates": { "tokio": { "verison": "1" } } }"#).is_err());
+    assert!(CrateOverrides::parse(r#"{ "crates": { "a": { "aliases": ["b"] }, "b": {} } }"#)
+        .unwrap_err()
+        .contains("itself configured as a crate"));
+}
+
+#[tokio::test]
+async fn test_crate_overrides_fill_tool_calls() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("crate:tokio@1.40.0", "<h1>tokio 1.40</h1>"));
+    let overrides = CrateOverrides::parse(r#"{ "crates": { "tokio": { "version": "1.40.0", "aliases": ["tokio-rt"] } } }"#).unwrap();
+    let router = DocRouter::with_fetcher(fetcher.clone()).with_crate_overrides(overrides);
+
+    let result = router.call_tool("lookup_crate", json!({ "crate_name": "tokio-rt" })).await.unwrap();
+    let Content::Text(text) = &result[0] else { panic!("Expected text content") };
+    assert!(text.text.starts_with("Documentation for tokio 1.40.0\n"));
+    assert!(fetcher.requests().contains(&"crate:tokio@1.40.0".to_string()));
+}
+
+#[test]
+fn test_parse_crates_io_results() {
+    let body = r#"{
+        "crates": [
+            {
+                "name": "tokio", "description": "  An async runtime.\n", "max_version": "2.0.0-alpha.1",
+                "max_stable_version": "1.43.0", "downloads": 300, "repository": "https://github.com/tokio-rs/tokio",
+                "homepage": "https://tokio.rs", "exact_match": false
+            },
+            { "name": "tokio-new", "newest_version": "0.1.0", "description": "", "repository": null },
+            { "description": "no name" }
+        ],
+        "meta": { "total": 1520, "next_page": "?page=2" }
+    }"#;
+    let results = parse_crates_io_results("async runtime", body).unwrap();
+    assert_eq!(
+        serde_json::to_value(&results).unwrap(),
+        json!({
+            "query": "async runtime",
+            "total": 1520,
+            "crates": [
+                {
+                    "name": "tokio",
+                    "description": "An async runtime.",
+                    "version": "1.43.0",
+                    "downloads": 300,
+                    "docs_url": "https://docs.rs/tokio/1.43.0",
+                    "repository": "https://github.com/tokio-rs/tokio"
+                },
+                {
+                    "name": "tokio-new",
+                    "description": null,
+                    "version": "0.1.0",
+                    "downloads": null,
+                    "docs_url": "https://docs.rs/tokio-new/0.1.0",
+                    "repository": null
+                }
+            ]
+        })
+    );
+    assert!(parse_crates_io_results("q", "<html>Service Unavailable</html>").is_none());
+    assert!(parse_crates_io_results("q", r#"{"errors": [{"detail": "Not Found"}]}"#).is_none());
+}
+
+#[tokio::test]
+async fn test_search_crates_rejects_unexpected_responses() {
+    let fetcher = Arc::new(MockFetcher::default().with_page("search:json:10", "<html><h1>Maintenance</h1></html>"));
+    let router = DocRouter::with_fetcher(fetcher);
+
+    let error = router.call_tool("search_crates", json!({ "query": "json" })).await.unwrap_err();
+    assert!(matches!(&error, ToolError::ExecutionError(msg) if msg.starts_with("Failed to search crates.io")));
+    assert_eq!(error_data(&error).unwrap().code, ErrorCode::UpstreamUnavailable);
+}
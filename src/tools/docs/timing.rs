@@ -0,0 +1,138 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::{field, Instrument, Span};
+
+/// A phase of a tool call, timed in a span of its own and in the call's breakdown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Arguments,
+    CacheLookup,
+    UpstreamFetch,
+    Conversion,
+    CacheStore,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Arguments => "arguments",
+            Phase::CacheLookup => "cache_lookup",
+            Phase::UpstreamFetch => "upstream_fetch",
+            Phase::Conversion => "conversion",
+            Phase::CacheStore => "cache_store",
+        }
+    }
+
+    // Span names have to be literals
+    fn span(self) -> Span {
+        match self {
+            Phase::Arguments => tracing::debug_span!("arguments", elapsed_ms = field::Empty),
+            Phase::CacheLookup => tracing::debug_span!("cache_lookup", elapsed_ms = field::Empty),
+            Phase::UpstreamFetch => tracing::debug_span!("upstream_fetch", url = field::Empty, elapsed_ms = field::Empty),
+            Phase::Conversion => tracing::debug_span!("conversion", elapsed_ms = field::Empty),
+            Phase::CacheStore => tracing::debug_span!("cache_store", elapsed_ms = field::Empty),
+        }
+    }
+}
+
+tokio::task_local! {
+    static BREAKDOWN: CallBreakdown;
+}
+
+/// Where the time of one tool call went: the time spent in each phase, and the
+/// upstream URLs fetched
+///
+/// Phases timed outside `scope`, such as background prefetches, aren't counted.
+#[derive(Clone, Debug, Default)]
+pub struct CallBreakdown(Arc<Mutex<Breakdown>>);
+
+#[derive(Debug, Default)]
+struct Breakdown {
+    // Total time and number of runs per phase, in the order phases first ran
+    phases: Vec<(Phase, Duration, usize)>,
+    upstream_urls: Vec<String>,
+}
+
+impl CallBreakdown {
+    /// Run `future`, adding the phases it times to this breakdown
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        BREAKDOWN.scope(self.clone(), future).await
+    }
+
+    /// Time spent in each phase that ran and how many times it ran
+    pub fn phases(&self) -> Vec<(Phase, Duration, usize)> {
+        self.lock().phases.clone()
+    }
+
+    pub fn upstream_urls(&self) -> Vec<String> {
+        self.lock().upstream_urls.clone()
+    }
+
+    fn add(&self, phase: Phase, elapsed: Duration) {
+        let mut breakdown = self.lock();
+        match breakdown.phases.iter_mut().find(|(p, _, _)| *p == phase) {
+            Some((_, total, runs)) => {
+                *total += elapsed;
+                *runs += 1;
+            }
+            None => breakdown.phases.push((phase, elapsed, 1)),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Breakdown> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// e.g. `cache_lookup 0ms, upstream_fetch 7950ms (2x), conversion 41ms, cache_store 1ms`
+impl fmt::Display for CallBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = self.phases();
+        if phases.is_empty() {
+            return f.write_str("no phases timed");
+        }
+        for (i, (phase, total, runs)) in phases.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} {}ms", phase.as_str(), total.as_millis())?;
+            if *runs > 1 {
+                write!(f, " ({}x)", runs)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `future` as `phase` of the current tool call
+pub async fn timed<F: Future>(phase: Phase, future: F) -> F::Output {
+    let span = phase.span();
+    let start = Instant::now();
+    let output = future.instrument(span.clone()).await;
+    finish(phase, &span, start.elapsed());
+    output
+}
+
+/// Run `f` as `phase` of the current tool call
+pub fn timed_sync<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let span = phase.span();
+    let start = Instant::now();
+    let output = span.in_scope(f);
+    finish(phase, &span, start.elapsed());
+    output
+}
+
+/// Note the URL the current `UpstreamFetch` phase requests, in its span and in the
+/// call's breakdown
+pub fn record_upstream_url(url: &str) {
+    Span::current().record("url", url);
+    let _ = BREAKDOWN.try_with(|breakdown| breakdown.lock().upstream_urls.push(url.to_string()));
+}
+
+fn finish(phase: Phase, span: &Span, elapsed: Duration) {
+    span.record("elapsed_ms", elapsed.as_millis() as u64);
+    let _ = BREAKDOWN.try_with(|breakdown| breakdown.add(phase, elapsed));
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// Name of the file usage counts are kept in, inside the cache directory
+pub const USAGE_FILE: &str = "usage.json";
+
+/// Number of entries `usage_top` lists by default
+pub const DEFAULT_USAGE_TOP: usize = 10;
+
+/// Largest number of entries `usage_top` lists
+pub const MAX_USAGE_TOP: usize = 100;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Counts {
+    #[serde(default)]
+    crates: BTreeMap<String, u64>,
+    /// Keyed by `crate::item_path`
+    #[serde(default)]
+    items: BTreeMap<String, u64>,
+}
+
+/// How often each crate and item was looked up, shared by every session of a router
+///
+/// Counts are kept in memory, and in a JSON file when loaded from one, which `save`
+/// writes back so they add up across restarts.
+#[derive(Clone, Debug, Default)]
+pub struct UsageStats {
+    counts: Arc<Mutex<Counts>>,
+    path: Option<PathBuf>,
+}
+
+impl UsageStats {
+    /// Counts kept in the file at `path`, starting from what it holds if it exists
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let counts = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Counts::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            counts: Arc::new(Mutex::new(counts)),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Count a lookup of `crate_name`, and of one of its items when `item_path` is given
+    pub fn record(&self, crate_name: &str, item_path: Option<&str>) {
+        let crate_name = crate_name.trim().to_ascii_lowercase();
+        if crate_name.is_empty() {
+            return;
+        }
+        let mut counts = self.lock();
+        if let Some(item_path) = item_path.map(str::trim).filter(|path| !path.is_empty()) {
+            let item_path = item_path.strip_prefix(&format!("{}::", crate_name)).unwrap_or(item_path);
+            *counts.items.entry(format!("{}::{}", crate_name, item_path)).or_default() += 1;
+        }
+        *counts.crates.entry(crate_name).or_default() += 1;
+    }
+
+    /// The `limit` most looked up crates, most first, ties by name
+    pub fn top_crates(&self, limit: usize) -> Vec<(String, u64)> {
+        top(&self.lock().crates, limit)
+    }
+
+    /// The `limit` most looked up items as `crate::item_path`, most first, ties by path
+    pub fn top_items(&self, limit: usize) -> Vec<(String, u64)> {
+        top(&self.lock().items, limit)
+    }
+
+    /// Write the counts to the file they were loaded from, if any
+    pub async fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let json = serde_json::to_vec_pretty(&*self.lock()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        // Written aside and renamed, so a crash mid-write keeps the previous counts
+        let partial = path.with_extension("json.partial");
+        tokio::fs::write(&partial, json).await?;
+        tokio::fs::rename(&partial, path).await
+    }
+
+    /// Render the top crates and items as markdown for `usage_top`
+    pub fn top_markdown(&self, limit: usize, crates: bool, items: bool) -> String {
+        let mut markdown = String::from("# Most looked up\n");
+        let mut section = |title: &str, entries: Vec<(String, u64)>| {
+            markdown.push_str(&format!("\n## {}\n\n", title));
+            if entries.is_empty() {
+                markdown.push_str("Nothing looked up yet.\n");
+            }
+            for (rank, (name, count)) in entries.iter().enumerate() {
+                let plural = if *count == 1 { "" } else { "s" };
+                markdown.push_str(&format!("{}. `{}`: {} lookup{}\n", rank + 1, name, count, plural));
+            }
+        };
+        if crates {
+            section("Crates", self.top_crates(limit));
+        }
+        if items {
+            section("Items", self.top_items(limit));
+        }
+        markdown
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Counts> {
+        self.counts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn top(counts: &BTreeMap<String, u64>, limit: usize) -> Vec<(String, u64)> {
+    let mut entries: Vec<(String, u64)> = counts.iter().map(|(name, count)| (name.clone(), *count)).collect();
+    // Stable, so equal counts stay in name order
+    entries.sort_by(|a, b| b.1.cmp(&a.1));
+    entries.truncate(limit);
+    entries
+}
@@ -0,0 +1,287 @@
+use std::cmp::Ordering;
+
+/// A semantic version as published on crates.io (build metadata is ignored)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifiers, e.g. `beta.2` (empty for releases)
+    pub pre: String,
+}
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.split_once('+').map_or(version, |(version, _build)| version);
+        let (numbers, pre) = version.split_once('-').unwrap_or((version, ""));
+        let mut numbers = numbers.split('.').map(|number| number.parse::<u64>().ok());
+        let version = Self {
+            major: numbers.next()??,
+            minor: numbers.next()??,
+            patch: numbers.next()??,
+            pre: pre.to_string(),
+        };
+        numbers.next().is_none().then_some(version)
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.is_prerelease() {
+            write!(f, "-{}", self.pre)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.is_prerelease(), other.is_prerelease()) {
+                (false, false) => Ordering::Equal,
+                // A pre-release comes before the release it leads up to
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (true, true) => compare_pre(&self.pre, &other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Compare pre-release identifiers as SemVer does: numeric ones numerically and
+// before alphanumeric ones, and a shorter list first when one is a prefix of the other
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let mut a_ids = a.split('.');
+    let mut b_ids = b.split('.');
+    loop {
+        let ordering = match (a_ids.next(), b_ids.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_id), Some(b_id)) => match (a_id.parse::<u64>(), b_id.parse::<u64>()) {
+                (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a_id.cmp(b_id),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+// Order pre-release strings, where no pre-release sorts after every pre-release
+fn cmp_pre(a: &str, b: &str) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => compare_pre(a, b),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    Wildcard,
+}
+
+/// One comparator of a requirement, e.g. `>=1.2` (missing parts stand for any value)
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    major: u64,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: String,
+}
+
+impl Comparator {
+    fn parse(comparator: &str) -> Option<Self> {
+        let comparator = comparator.trim();
+        let (op, rest) = [
+            (">=", Op::GreaterEq),
+            ("<=", Op::LessEq),
+            ("=", Op::Exact),
+            (">", Op::Greater),
+            ("<", Op::Less),
+            ("~", Op::Tilde),
+            ("^", Op::Caret),
+        ]
+        .into_iter()
+        .find_map(|(prefix, op)| comparator.strip_prefix(prefix).map(|rest| (op, rest)))
+        .unwrap_or((Op::Caret, comparator));
+        let rest = rest.trim();
+        let rest = rest.split_once('+').map_or(rest, |(rest, _build)| rest);
+        let (numbers, pre) = rest.split_once('-').unwrap_or((rest, ""));
+
+        let mut parts = numbers.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let mut wildcard = false;
+        let mut part = |part: Option<&str>| -> Option<Option<u64>> {
+            match part {
+                None => Some(None),
+                Some("*" | "x" | "X") => {
+                    wildcard = true;
+                    Some(None)
+                }
+                // Nothing may follow a wildcard, as in `1.*.3`
+                Some(_) if wildcard => None,
+                Some(number) => number.parse().ok().map(Some),
+            }
+        };
+        let minor = part(parts.next())?;
+        let patch = part(parts.next())?;
+        if parts.next().is_some() || (patch.is_none() && !pre.is_empty()) {
+            return None;
+        }
+        let op = match op {
+            Op::Caret if wildcard => Op::Wildcard,
+            op => op,
+        };
+        Some(Self {
+            op,
+            major,
+            minor,
+            patch,
+            pre: pre.to_string(),
+        })
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        match self.op {
+            Op::Exact | Op::Wildcard => self.matches_exact(version),
+            Op::Greater => self.matches_greater(version),
+            Op::GreaterEq => self.matches_exact(version) || self.matches_greater(version),
+            Op::Less => self.matches_less(version),
+            Op::LessEq => self.matches_exact(version) || self.matches_less(version),
+            Op::Tilde => self.matches_tilde(version),
+            Op::Caret => self.matches_caret(version),
+        }
+    }
+
+    fn matches_exact(&self, version: &Version) -> bool {
+        version.major == self.major
+            && self.minor.unwrap_or(version.minor) == version.minor
+            && self.patch.unwrap_or(version.patch) == version.patch
+            && version.pre == self.pre
+    }
+
+    fn matches_greater(&self, version: &Version) -> bool {
+        if version.major != self.major {
+            return version.major > self.major;
+        }
+        let Some(minor) = self.minor else { return false };
+        if version.minor != minor {
+            return version.minor > minor;
+        }
+        let Some(patch) = self.patch else { return false };
+        if version.patch != patch {
+            return version.patch > patch;
+        }
+        cmp_pre(&version.pre, &self.pre) == Ordering::Greater
+    }
+
+    fn matches_less(&self, version: &Version) -> bool {
+        if version.major != self.major {
+            return version.major < self.major;
+        }
+        let Some(minor) = self.minor else { return false };
+        if version.minor != minor {
+            return version.minor < minor;
+        }
+        let Some(patch) = self.patch else { return false };
+        if version.patch != patch {
+            return version.patch < patch;
+        }
+        cmp_pre(&version.pre, &self.pre) == Ordering::Less
+    }
+
+    fn matches_tilde(&self, version: &Version) -> bool {
+        if version.major != self.major || self.minor.is_some_and(|minor| version.minor != minor) {
+            return false;
+        }
+        match self.patch {
+            Some(patch) if version.patch != patch => version.patch > patch,
+            _ => cmp_pre(&version.pre, &self.pre) != Ordering::Less,
+        }
+    }
+
+    fn matches_caret(&self, version: &Version) -> bool {
+        if version.major != self.major {
+            return false;
+        }
+        let Some(minor) = self.minor else { return true };
+        let Some(patch) = self.patch else {
+            return if self.major > 0 { version.minor >= minor } else { version.minor == minor };
+        };
+        if self.major > 0 {
+            if version.minor != minor {
+                return version.minor > minor;
+            }
+            if version.patch != patch {
+                return version.patch > patch;
+            }
+        } else if minor > 0 {
+            if version.minor != minor {
+                return false;
+            }
+            if version.patch != patch {
+                return version.patch > patch;
+            }
+        } else if version.minor != minor || version.patch != patch {
+            return false;
+        }
+        cmp_pre(&version.pre, &self.pre) != Ordering::Less
+    }
+
+    // Pre-releases only match requirements that mention a pre-release of the same version
+    fn allows_pre_of(&self, version: &Version) -> bool {
+        !self.pre.is_empty()
+            && self.major == version.major
+            && self.minor == Some(version.minor)
+            && self.patch == Some(version.patch)
+    }
+}
+
+/// A Cargo version requirement such as `^1.2`, `~0.4.1`, `>=1.0, <2` or `1.*`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionReq {
+    comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    pub fn parse(requirement: &str) -> Option<Self> {
+        let requirement = requirement.trim();
+        if requirement.is_empty() || requirement == "*" {
+            return Some(Self { comparators: Vec::new() });
+        }
+        let comparators = requirement.split(',').map(Comparator::parse).collect::<Option<Vec<_>>>()?;
+        Some(Self { comparators })
+    }
+
+    /// Whether `version` satisfies the requirement, with Cargo's rules for pre-releases
+    pub fn matches(&self, version: &Version) -> bool {
+        self.comparators.iter().all(|comparator| comparator.matches(version))
+            && (!version.is_prerelease() || self.comparators.iter().any(|comparator| comparator.allows_pre_of(version)))
+    }
+}
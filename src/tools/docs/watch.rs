@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, sync::Arc, time::SystemTime};
+
+use tokio::sync::{broadcast, Mutex};
+
+use super::releases::date_days_ago;
+
+/// URI of the resource listing new releases of watched crates
+pub const UPDATES_RESOURCE_URI: &str = "docs://updates";
+
+/// Default time between checks of the watch list
+pub const DEFAULT_WATCH_INTERVAL_SECS: u64 = 900;
+
+/// Number of detected releases kept for the updates resource
+const MAX_UPDATES: usize = 100;
+
+/// A new release found while checking the watch list
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Update {
+    pub crate_name: String,
+    pub previous: String,
+    pub version: String,
+    /// UTC date the release was detected on, as `YYYY-MM-DD`
+    pub detected: String,
+}
+
+#[derive(Default)]
+struct WatchState {
+    /// Watched crates and the latest version seen (`None` until the first check)
+    crates: BTreeMap<String, Option<String>>,
+    /// Detected releases, newest first
+    updates: Vec<Update>,
+    last_checked: Option<String>,
+}
+
+/// Crates clients asked to be told about, shared by every session of a router
+///
+/// Resource URIs whose content changed are announced on a broadcast channel
+/// that transports turn into `notifications/resources/updated` messages.
+#[derive(Clone)]
+pub struct WatchList {
+    state: Arc<Mutex<WatchState>>,
+    changes: broadcast::Sender<String>,
+}
+
+impl Default for WatchList {
+    fn default() -> Self {
+        let (changes, _) = broadcast::channel(16);
+        Self {
+            state: Arc::default(),
+            changes,
+        }
+    }
+}
+
+impl WatchList {
+    /// Add crates to the watch list; their current version becomes the baseline at the next check
+    pub async fn add(&self, crate_names: &[String]) {
+        let mut state = self.state.lock().await;
+        for crate_name in crate_names {
+            state.crates.entry(crate_name.clone()).or_insert(None);
+        }
+    }
+
+    pub async fn remove(&self, crate_names: &[String]) {
+        let mut state = self.state.lock().await;
+        for crate_name in crate_names {
+            state.crates.remove(crate_name);
+        }
+    }
+
+    pub async fn crates(&self) -> Vec<String> {
+        self.state.lock().await.crates.keys().cloned().collect()
+    }
+
+    /// Record the latest version of each checked crate, returning the releases
+    /// that are new since the previous check
+    pub async fn record(&self, latest: Vec<(String, String)>) -> Vec<Update> {
+        let today = date_days_ago(SystemTime::now(), 0);
+        let mut state = self.state.lock().await;
+        state.last_checked = Some(today.clone());
+
+        let mut found = Vec::new();
+        for (crate_name, version) in latest {
+            // Crates removed while the check was running are ignored
+            let Some(seen) = state.crates.get_mut(&crate_name) else { continue };
+            if let Some(previous) = seen.replace(version.clone()).filter(|previous| *previous != version) {
+                found.push(Update {
+                    crate_name,
+                    previous,
+                    version,
+                    detected: today.clone(),
+                });
+            }
+        }
+
+        if !found.is_empty() {
+            let older = std::mem::take(&mut state.updates);
+            state.updates = found.iter().cloned().chain(older).take(MAX_UPDATES).collect();
+            self.announce(UPDATES_RESOURCE_URI);
+        }
+        found
+    }
+
+    /// Tell listeners that the content of the resource at `uri` changed
+    pub fn announce(&self, uri: &str) {
+        // Nobody listening is fine; readers still get the new content
+        let _ = self.changes.send(uri.to_string());
+    }
+
+    /// Receive the URIs of resources whose content changed
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.changes.subscribe()
+    }
+
+    /// Render the updates resource as markdown
+    pub async fn updates_markdown(&self) -> String {
+        let state = self.state.lock().await;
+        let mut markdown = String::from("# Crate updates\n\n");
+        if state.crates.is_empty() {
+            markdown.push_str("No crates are watched. Use the watch_crates tool to add some.\n");
+            return markdown;
+        }
+
+        let watched: Vec<String> = state
+            .crates
+            .iter()
+            .map(|(name, version)| match version {
+                Some(version) => format!("{} {}", name, version),
+                None => name.clone(),
+            })
+            .collect();
+        markdown.push_str(&format!("Watching: {}\n", watched.join(", ")));
+        markdown.push_str(&format!(
+            "Last checked: {}\n\n",
+            state.last_checked.as_deref().unwrap_or("not yet")
+        ));
+
+        if state.updates.is_empty() {
+            markdown.push_str("No new releases since watching started.\n");
+        }
+        for update in &state.updates {
+            markdown.push_str(&format!(
+                "- **{}** {} → {} (detected {})\n",
+                update.crate_name, update.previous, update.version, update.detected
+            ));
+        }
+        markdown
+    }
+}
@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+use super::resolve::{Resolution, ResolvedCrate};
+
+/// Number of crates listed in the largest-crates table of `dependency_weight`
+pub const LARGEST_CRATES_SHOWN: usize = 10;
+
+/// Size in bytes of a version's `.crate` file, from a crates.io `crates/{name}/{version}` response
+pub fn parse_crate_size(json: &str) -> Option<u64> {
+    let value: Value = serde_json::from_str(json).ok()?;
+    value.get("version")?.get("crate_size")?.as_u64()
+}
+
+/// Format a byte count for people, e.g. `512 B`, `14.2 KiB` or `3.0 MiB`
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Render what depending on a crate adds, given `.crate` sizes keyed by `name version`
+///
+/// Sizes are of the compressed `.crate` downloads, so they say more about download
+/// and unpacking cost than about build time.
+pub fn format_weight(resolution: &Resolution, sizes: &std::collections::HashMap<String, u64>) -> String {
+    let size_of = |name: &str, version: &str| sizes.get(&format!("{} {}", name, version)).copied();
+    let crates = &resolution.crates;
+    let direct = crates.iter().filter(|resolved| resolved.depth == 1).count();
+    let build_only = crates.iter().filter(|resolved| resolved.build_only).count();
+    let platform_specific = crates.iter().filter(|resolved| !resolved.targets.is_empty()).count();
+
+    let mut markdown = format!(
+        "# Dependency weight of {} {}\n\nFeatures: {}\n\n",
+        resolution.crate_name,
+        resolution.version,
+        if resolution.features.is_empty() {
+            "none".to_string()
+        } else {
+            resolution.features.iter().cloned().collect::<Vec<_>>().join(", ")
+        }
+    );
+    markdown.push_str(&format!(
+        "Adds {} {}: {} direct and {} transitive",
+        crates.len(),
+        if crates.len() == 1 { "crate" } else { "crates" },
+        direct,
+        crates.len() - direct
+    ));
+    if build_only > 0 || platform_specific > 0 {
+        markdown.push_str(&format!(
+            " ({} only used by build scripts, {} only on some platforms)",
+            build_only, platform_specific
+        ));
+    }
+    markdown.push_str(".\n");
+
+    let root_size = size_of(&resolution.crate_name, &resolution.version);
+    let known: Vec<(&ResolvedCrate, u64)> = crates
+        .iter()
+        .filter_map(|resolved| Some((resolved, size_of(&resolved.name, &resolved.version)?)))
+        .collect();
+    let dependencies_size: u64 = known.iter().map(|(_, size)| size).sum();
+    markdown.push_str(&format!(
+        "Download size of the dependencies: {} compressed",
+        format_size(dependencies_size)
+    ));
+    if let Some(root_size) = root_size {
+        markdown.push_str(&format!(
+            " ({} with {} itself)",
+            format_size(dependencies_size + root_size),
+            resolution.crate_name
+        ));
+    }
+    markdown.push_str(".\n");
+    let unknown = crates.len() - known.len();
+    if unknown > 0 {
+        markdown.push_str(&format!("Sizes of {} crates couldn't be read and aren't counted.\n", unknown));
+    }
+    if !resolution.missing.is_empty() || !resolution.unsatisfied.is_empty() {
+        markdown.push_str(
+            "Some dependencies couldn't be resolved, so the real numbers are higher; \
+             see resolve_dependencies for details.\n",
+        );
+    }
+
+    if !known.is_empty() {
+        let mut largest = known;
+        largest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+        markdown.push_str("\n## Largest dependencies\n\n| Crate | Version | Size | Required by |\n|---|---|---|---|\n");
+        for (resolved, size) in largest.into_iter().take(LARGEST_CRATES_SHOWN) {
+            let required_by = if resolved.depth == 1 {
+                "direct".to_string()
+            } else {
+                resolved.required_by.iter().cloned().collect::<Vec<_>>().join(", ")
+            };
+            markdown.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                resolved.name,
+                resolved.version,
+                format_size(size),
+                required_by
+            ));
+        }
+    }
+    markdown
+}
@@ -1,4 +1,39 @@
 pub mod docs;
 
-pub use docs::DocRouter;
-pub use docs::docs::DocCache;
\ No newline at end of file
+pub use docs::{html_to_markdown, sanitize_html, DocRouter};
+pub use docs::batch::{run_batch_line, BatchCall};
+pub use docs::builder::DocRouterBuilder;
+pub use docs::circuit::{CircuitBreaker, DEFAULT_CIRCUIT_COOLDOWN, DEFAULT_CIRCUIT_FAILURE_THRESHOLD};
+pub use docs::docs::{
+    item_probe_order, DocCache, LatestVersions, OversizePolicy, DEFAULT_HTTP_MAX_CONTENT_BYTES, DEFAULT_REFRESH_INTERVAL_SECS,
+    DEFAULT_REFRESH_TOP, DEFAULT_STDIO_MAX_CONTENT_BYTES, ITEM_PAGE_KINDS, MAX_TRACKED_ACCESSES,
+};
+pub use docs::errors::{error_data, split_error_data, with_error_data, DocsError, ErrorCode, ErrorData};
+pub use docs::export::{format_export_summary, ExportSummary, DEFAULT_EXPORT_MAX_PAGES};
+pub use docs::fallback::{CratesIoReadme, RepositoryDocs, CRATES_IO_README_SOURCE, REPOSITORY_SOURCE};
+pub use docs::fetcher::{
+    user_agent, BaseUrls, DocFetcher, DocPage, FallbackDoc, ReqwestFetcher, DEFAULT_MAX_CONCURRENT_REQUESTS, DEFAULT_MAX_RESPONSE_BYTES,
+    USER_AGENT,
+};
+pub use docs::history::{HistoryEntry, SessionHistory, HISTORY_RESOURCE_URI};
+pub use docs::local::{LocalDocs, LOCAL_DOC_BUILD_TIMEOUT};
+pub use docs::mirror::{Mirror, MirrorFetcher};
+pub use docs::overrides::{CrateOverride, CrateOverrides};
+pub use docs::persist::{DiskStore, SchemaMarker};
+pub use docs::plain_text::{markdown_to_html, markdown_to_text, OutputFormat};
+pub use docs::policy::{CratePolicy, PolicyFetcher};
+pub use docs::prefetch::PrefetchConfig;
+pub use docs::resolve::{DisabledDependency, Resolution, ResolvedCrate, MAX_RESOLVED_CRATES};
+pub use docs::resources::{DocResource, DOCS_URI_PREFIX};
+pub use docs::rustdoc_json::{JsonItem, RustdocJson, RustdocJsonCache, RUSTDOC_JSON_ABSENT_TTL, RUSTDOC_JSON_CACHE_CRATES};
+pub use docs::search::SearchBackend;
+pub use docs::search_index::{IndexItem, SearchIndex, SearchIndexCache, DEFAULT_INDEX_CACHE_BYTES, DEFAULT_INDEX_TTL};
+pub use docs::session::{SessionDefaults, SessionSettings, BYTES_PER_TOKEN};
+pub use docs::snippet::VersionPolicy;
+pub use docs::sources::{Capabilities, DocSource, FetcherSource, SourceRegistry, LOCAL_SOURCE, UPSTREAM_SOURCES};
+pub use docs::sparse_index::{IndexDependency, IndexVersion};
+pub use docs::subscriptions::{ResourceSubscriptions, SessionSubscriptions, DEFAULT_SUBSCRIPTION_REFRESH_SECS};
+pub use docs::timing::{CallBreakdown, Phase};
+pub use docs::usage::{UsageStats, USAGE_FILE};
+pub use docs::versions::{Version, VersionReq};
+pub use docs::watch::{Update, WatchList, DEFAULT_WATCH_INTERVAL_SECS, UPDATES_RESOURCE_URI};
\ No newline at end of file
@@ -0,0 +1,212 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    io::{self, AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+use tokio_util::{
+    bytes::Bytes,
+    codec::{FramedRead, FramedWrite},
+};
+
+use mcp_core::{handler::ResourceError, Content};
+use mcp_server::Router;
+
+use crate::{
+    tools::{split_error_data, DocRouter, ErrorCode, SessionSubscriptions},
+    transport::jsonrpc_frame_codec::{Framing, JsonRpcFrameCodec, SharedFraming},
+};
+
+/// JSON-RPC error code for a resource that doesn't exist
+const RESOURCE_NOT_FOUND: i64 = -32002;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Answer a client request for a method the MCP server SDK doesn't implement, or
+/// a JSON-RPC batch, which the SDK doesn't take at all
+///
+/// Subscriptions made through `resources/subscribe` are kept in `session`.
+/// Returns the JSON-RPC response, or `None` for messages the server handles
+/// itself, which should be passed on unchanged. A batch holding only notifications
+/// gets an empty response, which isn't sent.
+pub async fn extension_response(router: &DocRouter, session: &SessionSubscriptions, message: &[u8]) -> Option<String> {
+    let request: Value = serde_json::from_slice(message).ok()?;
+    if let Value::Array(batch) = request {
+        return Some(batch_response(router, session, &batch).await);
+    }
+    extension_reply(router, session, &request).await.map(|response| response.to_string())
+}
+
+async fn extension_reply(router: &DocRouter, session: &SessionSubscriptions, request: &Value) -> Option<Value> {
+    let id = request.get("id")?.clone();
+    let uri = request.pointer("/params/uri").and_then(Value::as_str);
+    let result = match request.get("method")?.as_str()? {
+        "resources/templates/list" => Ok(json!({ "resourceTemplates": router.resource_templates() })),
+        "resources/subscribe" => match uri {
+            Some(uri) => router.subscribe_resource(session, uri).await.map(|_| json!({})).map_err(resource_error),
+            None => Err((INVALID_PARAMS, "Missing uri".to_string())),
+        },
+        "resources/unsubscribe" => match uri {
+            Some(uri) => {
+                router.unsubscribe_resource(session, uri).await;
+                Ok(json!({}))
+            }
+            None => Err((INVALID_PARAMS, "Missing uri".to_string())),
+        },
+        _ => return None,
+    };
+    Some(response(id, result))
+}
+
+// The responses to the requests of a batch, in the batch's order, run concurrently
+async fn batch_response(router: &DocRouter, session: &SessionSubscriptions, batch: &[Value]) -> String {
+    if batch.is_empty() {
+        return response(Value::Null, Err((INVALID_REQUEST, "Empty batch".to_string()))).to_string();
+    }
+    let responses = futures::future::join_all(batch.iter().map(|request| batch_reply(router, session, request))).await;
+    let responses: Vec<Value> = responses.into_iter().flatten().collect();
+    if responses.is_empty() {
+        return String::new();
+    }
+    Value::Array(responses).to_string()
+}
+
+// The response to one request of a batch, or `None` for a notification, which
+// needs no answer from this server
+//
+// Batched requests can't go through the server SDK, so the methods it answers
+// are dispatched to the router here, answered the way the SDK answers them.
+async fn batch_reply(router: &DocRouter, session: &SessionSubscriptions, request: &Value) -> Option<Value> {
+    let Some(object) = request.as_object() else {
+        return Some(response(Value::Null, Err((INVALID_REQUEST, "Invalid request".to_string()))));
+    };
+    let id = object.get("id")?.clone();
+    if let Some(response) = extension_reply(router, session, request).await {
+        return Some(response);
+    }
+    let Some(method) = object.get("method").and_then(Value::as_str) else {
+        return Some(response(id, Err((INVALID_REQUEST, "Invalid request".to_string()))));
+    };
+    let params = object.get("params").cloned().unwrap_or_else(|| json!({}));
+    let result = match method {
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": router.list_tools() })),
+        "tools/call" => match params.get("name").and_then(Value::as_str) {
+            Some(name) => {
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                let result = match router.call_tool(name, arguments).await {
+                    Ok(content) => json!({ "content": content, "isError": false }),
+                    Err(e) => json!({ "content": [Content::text(e.to_string())], "isError": true }),
+                };
+                // Failed calls become errors with their data, as on the server's way out
+                let lifted = lift_error_data(Bytes::from(response(id, Ok(result)).to_string()));
+                return serde_json::from_slice(&lifted).ok();
+            }
+            None => Err((INVALID_PARAMS, "Missing tool name".to_string())),
+        },
+        "resources/list" => Ok(json!({ "resources": router.list_resources() })),
+        "resources/read" => match params.get("uri").and_then(Value::as_str) {
+            Some(uri) => router
+                .read_resource(uri)
+                .await
+                .map(|text| json!({ "contents": [{ "uri": uri, "mimeType": "text", "text": text }] }))
+                .map_err(resource_error),
+            None => Err((INVALID_PARAMS, "Missing uri".to_string())),
+        },
+        "prompts/list" => Ok(json!({ "prompts": router.list_prompts() })),
+        _ => Err((METHOD_NOT_FOUND, format!("Method {} can't be used in a batch", method))),
+    };
+    Some(response(id, result))
+}
+
+fn response(id: Value, result: Result<Value, (i64, String)>) -> Value {
+    match result {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result
+        }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message }
+        }),
+    }
+}
+
+fn resource_error(error: ResourceError) -> (i64, String) {
+    match error {
+        ResourceError::NotFound(message) => (RESOURCE_NOT_FOUND, message),
+        ResourceError::ExecutionError(message) => (INTERNAL_ERROR, message),
+    }
+}
+
+/// Turn a failed `tools/call` result carrying error data (see `DocRouter::call_tool`)
+/// into a JSON-RPC error with that data, leaving every other server message as is
+///
+/// The MCP server SDK reports tool errors as results with `isError` and only the
+/// error's text, so the code and suggestions are lifted out of the text here.
+pub fn lift_error_data(message: Bytes) -> Bytes {
+    let Ok(response) = serde_json::from_slice::<Value>(&message) else {
+        return message;
+    };
+    if response.pointer("/result/isError") != Some(&Value::Bool(true)) {
+        return message;
+    }
+    let Some(text) = response.pointer("/result/content/0/text").and_then(Value::as_str) else {
+        return message;
+    };
+    let (text, Some(data)) = split_error_data(text) else {
+        return message;
+    };
+    let code = if data.code == ErrorCode::InvalidArguments { INVALID_PARAMS } else { INTERNAL_ERROR };
+    let error = json!({
+        "jsonrpc": "2.0",
+        "id": response.get("id").cloned().unwrap_or(Value::Null),
+        "error": { "code": code, "message": text, "data": data }
+    });
+    Bytes::from(error.to_string())
+}
+
+/// Copy messages from the client to the server, answering the requests
+/// `extension_response` handles through `replies` instead
+///
+/// The client's messages are framed as `framing` says or, when it doesn't, as
+/// detected from the first one (see `JsonRpcFrameCodec`); the server gets them one
+/// per line. Returns once the client closes its input, which closes the server's input
+/// too; the session's subscriptions are dropped then.
+pub async fn route_client_messages<R, W>(
+    client: R,
+    server_input: W,
+    router: DocRouter,
+    session: SessionSubscriptions,
+    replies: mpsc::Sender<String>,
+    framing: SharedFraming,
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut messages = FramedRead::new(client, JsonRpcFrameCodec::new().with_framing(framing));
+    let server_framing = SharedFraming::fixed(Framing::Newline);
+    let mut server_input = FramedWrite::new(server_input, JsonRpcFrameCodec::new().with_framing(server_framing));
+    let routed = async {
+        while let Some(message) = messages.next().await {
+            let message = message?;
+            match extension_response(&router, &session, &message).await {
+                Some(reply) => {
+                    if !reply.is_empty() && replies.send(reply).await.is_err() {
+                        break;
+                    }
+                }
+                None => server_input.send(message).await?,
+            }
+        }
+        Ok(())
+    }
+    .await;
+    router.end_session(&session).await;
+    routed
+}
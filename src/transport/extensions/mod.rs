@@ -0,0 +1,5 @@
+mod extensions;
+pub use extensions::{extension_response, lift_error_data, route_client_messages};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,178 @@
+use crate::tools::{with_error_data, DocRouter, ErrorCode, ErrorData, SessionSubscriptions};
+use crate::transport::extensions::{extension_response, lift_error_data, route_client_messages};
+use crate::transport::jsonrpc_frame_codec::{Framing, SharedFraming};
+use mcp_core::ToolError;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio_util::bytes::Bytes;
+
+#[tokio::test]
+async fn test_resource_templates_list_is_answered() {
+    let router = DocRouter::new();
+    let request = br#"{"jsonrpc":"2.0","id":3,"method":"resources/templates/list","params":{}}"#;
+    let reply: Value = serde_json::from_str(&extension_response(&router, &SessionSubscriptions::default(), request).await.unwrap()).unwrap();
+    assert_eq!(reply["id"], 3);
+    let templates = reply["result"]["resourceTemplates"].as_array().unwrap();
+    assert!(templates.iter().any(|template| template["uriTemplate"] == "docs://{crate}/{version}/{item_path}"));
+}
+
+#[tokio::test]
+async fn test_server_methods_pass_through() {
+    let router = DocRouter::new();
+    for message in [
+        br#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#.as_slice(),
+        br#"{"jsonrpc":"2.0","method":"resources/templates/list"}"#.as_slice(),
+        b"not json".as_slice(),
+    ] {
+        assert_eq!(extension_response(&router, &SessionSubscriptions::default(), message).await, None);
+    }
+}
+
+#[tokio::test]
+async fn test_subscription_requests() {
+    let router = DocRouter::new();
+    let session = SessionSubscriptions::default();
+    let respond = |message: &'static str| {
+        let (router, session) = (router.clone(), session.clone());
+        async move {
+            let reply = extension_response(&router, &session, message.as_bytes()).await.unwrap();
+            serde_json::from_str::<Value>(&reply).unwrap()
+        }
+    };
+
+    let reply = respond(r#"{"jsonrpc":"2.0","id":1,"method":"resources/subscribe","params":{}}"#).await;
+    assert_eq!(reply["error"]["code"], -32602);
+    let reply = respond(r#"{"jsonrpc":"2.0","id":2,"method":"resources/subscribe","params":{"uri":"file:///etc/hosts"}}"#).await;
+    assert_eq!(reply["id"], 2);
+    assert_eq!(reply["error"]["code"], -32002);
+    assert!(!session.contains("file:///etc/hosts").await);
+
+    // The updates resource is always readable
+    let reply = respond(r#"{"jsonrpc":"2.0","id":3,"method":"resources/subscribe","params":{"uri":"docs://updates"}}"#).await;
+    assert_eq!(reply["result"], serde_json::json!({}));
+    assert!(session.contains("docs://updates").await);
+    let reply = respond(r#"{"jsonrpc":"2.0","id":4,"method":"resources/unsubscribe","params":{"uri":"docs://updates"}}"#).await;
+    assert_eq!(reply["result"], serde_json::json!({}));
+    assert!(!session.contains("docs://updates").await);
+}
+
+#[tokio::test]
+async fn test_batch_requests() {
+    let router = DocRouter::new();
+    let session = SessionSubscriptions::default();
+    let batch = json!([
+        { "jsonrpc": "2.0", "id": 1, "method": "tools/list" },
+        { "jsonrpc": "2.0", "method": "notifications/initialized" },
+        { "jsonrpc": "2.0", "id": "two", "method": "resources/subscribe", "params": { "uri": "docs://updates" } },
+        { "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "lookup_crate", "arguments": {} } },
+        { "jsonrpc": "2.0", "id": 4, "method": "resources/read", "params": { "uri": "docs://updates" } },
+        { "jsonrpc": "2.0", "id": 5, "method": "initialize" },
+        42,
+    ]);
+    let reply = extension_response(&router, &session, batch.to_string().as_bytes()).await.unwrap();
+    let responses: Vec<Value> = serde_json::from_str(&reply).unwrap();
+
+    // One response per request, in the batch's order; the notification gets none
+    let ids: Vec<&Value> = responses.iter().map(|response| &response["id"]).collect();
+    assert_eq!(ids, vec![&json!(1), &json!("two"), &json!(3), &json!(4), &json!(5), &Value::Null]);
+    assert!(responses[0]["result"]["tools"].as_array().unwrap().iter().any(|tool| tool["name"] == "lookup_crate"));
+    assert_eq!(responses[1]["result"], json!({}));
+    assert!(session.contains("docs://updates").await);
+    // Tool errors carry their data, as outside batches
+    assert_eq!(responses[2]["error"]["code"], -32602);
+    assert!(responses[2]["error"]["data"]["code"].is_string());
+    assert_eq!(responses[3]["result"]["contents"][0]["uri"], "docs://updates");
+    assert_eq!(responses[4]["error"]["code"], -32601);
+    assert_eq!(responses[5]["error"]["code"], -32600);
+}
+
+#[tokio::test]
+async fn test_batch_edge_cases() {
+    let router = DocRouter::new();
+    let session = SessionSubscriptions::default();
+
+    let reply = extension_response(&router, &session, b"[]").await.unwrap();
+    let reply: Value = serde_json::from_str(&reply).unwrap();
+    assert_eq!(reply["error"]["code"], -32600);
+    assert_eq!(reply["id"], Value::Null);
+
+    // Notifications alone get no response at all
+    let notifications = br#"[{"jsonrpc":"2.0","method":"notifications/initialized"}]"#;
+    assert_eq!(extension_response(&router, &session, notifications).await, Some(String::new()));
+}
+
+#[tokio::test]
+async fn test_route_client_messages() {
+    let (client_reader, mut client_writer) = tokio::io::simplex(4096);
+    let (mut server_reader, server_writer) = tokio::io::simplex(4096);
+    let (replies, mut reply_receiver) = mpsc::channel(4);
+
+    let route = tokio::spawn(route_client_messages(client_reader, server_writer, DocRouter::new(), SessionSubscriptions::default(), replies, SharedFraming::default()));
+    client_writer
+        .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/list\"}\n{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"resources/templates/list\"}\n")
+        .await
+        .unwrap();
+    drop(client_writer);
+    route.await.unwrap().unwrap();
+
+    let mut forwarded = String::new();
+    server_reader.read_to_string(&mut forwarded).await.unwrap();
+    assert_eq!(forwarded, "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/list\"}\n");
+    let reply: Value = serde_json::from_str(&reply_receiver.recv().await.unwrap()).unwrap();
+    assert_eq!(reply["id"], 2);
+}
+
+#[tokio::test]
+async fn test_route_client_messages_with_content_length_framing() {
+    let (client_reader, mut client_writer) = tokio::io::simplex(4096);
+    let (mut server_reader, server_writer) = tokio::io::simplex(4096);
+    let (replies, _reply_receiver) = mpsc::channel(4);
+    let framing = SharedFraming::default();
+
+    let route = tokio::spawn(route_client_messages(client_reader, server_writer, DocRouter::new(), SessionSubscriptions::default(), replies, framing.clone()));
+    let request = br#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+    let framed = format!("Content-Length: {}\r\n\r\n{}", request.len(), std::str::from_utf8(request).unwrap());
+    client_writer.write_all(framed.repeat(2).as_bytes()).await.unwrap();
+    drop(client_writer);
+    route.await.unwrap().unwrap();
+
+    // The server gets the messages one per line, and the framing is known for replies
+    let mut forwarded = String::new();
+    server_reader.read_to_string(&mut forwarded).await.unwrap();
+    let line = format!("{}\n", std::str::from_utf8(request).unwrap());
+    assert_eq!(forwarded, line.repeat(2));
+    assert_eq!(framing.get(), Some(Framing::ContentLength));
+}
+
+#[test]
+fn test_tool_errors_get_their_data() {
+    let error = with_error_data(
+        ToolError::ExecutionError("Crate nope not found on crates.io".to_string()),
+        ErrorData::new(ErrorCode::CrateNotFound).with_suggestions(vec!["Search for it".to_string()]),
+    );
+    let result = json!({
+        "jsonrpc": "2.0",
+        "id": 7,
+        "result": { "content": [{ "type": "text", "text": error.to_string() }], "isError": true }
+    });
+    let lifted: Value = serde_json::from_slice(&lift_error_data(Bytes::from(result.to_string()))).unwrap();
+    assert_eq!(lifted["id"], 7);
+    assert_eq!(lifted["error"]["code"], -32603);
+    assert!(lifted["error"]["message"].as_str().unwrap().ends_with("Crate nope not found on crates.io"));
+    assert_eq!(lifted["error"]["data"], json!({ "code": "CRATE_NOT_FOUND", "suggestions": ["Search for it"] }));
+
+    let invalid = with_error_data(ToolError::InvalidParameters("crate_name is required".to_string()), ErrorData::new(ErrorCode::InvalidArguments));
+    let result = json!({ "jsonrpc": "2.0", "id": 8, "result": { "content": [{ "type": "text", "text": invalid.to_string() }], "isError": true } });
+    let lifted: Value = serde_json::from_slice(&lift_error_data(Bytes::from(result.to_string()))).unwrap();
+    assert_eq!(lifted["error"]["code"], -32602);
+
+    // Successful results and errors without data pass through untouched
+    for message in [
+        r#"{"jsonrpc":"2.0","id":1,"result":{"content":[{"type":"text","text":"docs"}]}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"result":{"content":[{"type":"text","text":"Execution failed: boom"}],"isError":true}}"#,
+        "not json",
+    ] {
+        assert_eq!(lift_error_data(Bytes::from(message)), Bytes::from(message));
+    }
+}
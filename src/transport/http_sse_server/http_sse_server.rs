@@ -2,45 +2,174 @@ use axum::{
     body::Body,
     extract::{Query, State},
     http::StatusCode,
-    response::sse::{Event, Sse},
-    routing::get,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post},
     Router,
 };
 use futures::{Stream, StreamExt, TryStreamExt};
 use mcp_server::{ByteTransport, Server};
 use std::collections::HashMap;
-use tokio_util::codec::FramedRead;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio_util::{
+    bytes::{Bytes, BytesMut},
+    codec::{Encoder, FramedRead},
+};
 
 #[cfg(test)]
 // Tests in ../tests.rs
 
 use anyhow::Result;
 use mcp_server::router::RouterService;
-use crate::{transport::jsonrpc_frame_codec::JsonRpcFrameCodec, tools::DocRouter};
+use super::playground::{playground_call_handler, playground_handler};
+use crate::{
+    transport::{
+        extensions::{extension_response, lift_error_data},
+        jsonrpc_frame_codec::{Framing, JsonRpcFrameCodec, SharedFraming},
+        notifications::{channel_messages, resource_updates},
+    },
+    tools::{DocRouter, SessionSubscriptions},
+};
 use std::sync::Arc;
 use tokio::{
     io::{self, AsyncWriteExt},
-    sync::Mutex,
+    sync::{mpsc, Mutex},
 };
 
 type C2SWriter = Arc<Mutex<io::WriteHalf<io::SimplexStream>>>;
 type SessionId = Arc<str>;
 
-#[derive(Clone, Default)]
+/// Responses given by the transport itself that may wait for a session's event stream
+const REPLY_BUFFER_SIZE: usize = 16;
+
+/// Interval of the comments sent on idle event streams, which keep proxies from
+/// closing them and let sessions whose client left be noticed and ended
+pub const DEFAULT_SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Port of bind addresses given without one
+pub const DEFAULT_HTTP_PORT: u16 = 8080;
+
+/// The socket addresses to bind for an `--address`: `IP:PORT`, `[IPv6]:PORT`, a bare
+/// IPv4 or IPv6 address (bracketed or not), or a host name such as `localhost:8080`,
+/// which gives every address it resolves to; the port defaults to `DEFAULT_HTTP_PORT`
+pub async fn resolve_bind_address(spec: &str) -> io::Result<Vec<SocketAddr>> {
+    let spec = spec.trim();
+    if let Ok(addr) = spec.parse::<SocketAddr>() {
+        return Ok(vec![addr]);
+    }
+    let bare = spec.strip_prefix('[').and_then(|ip| ip.strip_suffix(']')).unwrap_or(spec);
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(ip, DEFAULT_HTTP_PORT)]);
+    }
+
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid address {}: expected IP:PORT, [IPv6]:PORT, an IP address or a host name", spec),
+        )
+    };
+    // An unbracketed IPv6 address with a port is ambiguous, so only names get here
+    let (host, port) = match spec.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse::<u16>().map_err(|_| invalid())?),
+        None => (spec, DEFAULT_HTTP_PORT),
+    };
+    if host.is_empty() || host.contains(':') {
+        return Err(invalid());
+    }
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for addr in tokio::net::lookup_host((host, port)).await? {
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    if addrs.is_empty() {
+        return Err(invalid());
+    }
+    Ok(addrs)
+}
+
+#[derive(Clone)]
 pub struct App {
     pub txs: Arc<tokio::sync::RwLock<HashMap<SessionId, C2SWriter>>>,
+    /// Per session, the responses to requests answered without the MCP server
+    pub replies: Arc<tokio::sync::RwLock<HashMap<SessionId, mpsc::Sender<String>>>>,
+    /// Per session, the resources it subscribed to
+    pub subscriptions: Arc<tokio::sync::RwLock<HashMap<SessionId, SessionSubscriptions>>>,
+    /// Per session, the router serving it, which keeps the session's history
+    pub routers: Arc<tokio::sync::RwLock<HashMap<SessionId, DocRouter>>>,
+    /// Router every session's router is made from, so they all use the same cache and
+    /// connection pool
+    pub docs: DocRouter,
+    /// Interval of the keep-alive comments on idle event streams
+    pub keep_alive: Duration,
+    /// Whether `/` serves the playground page for trying lookups from a browser
+    pub playground: bool,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
     pub fn new() -> Self {
+        Self::with_router(DocRouter::new())
+    }
+
+    /// Create an app whose sessions all serve requests through clones of `docs`
+    pub fn with_router(docs: DocRouter) -> Self {
         Self {
             txs: Default::default(),
+            replies: Default::default(),
+            subscriptions: Default::default(),
+            routers: Default::default(),
+            docs,
+            keep_alive: DEFAULT_SSE_KEEP_ALIVE,
+            playground: true,
         }
     }
+
+    /// Send keep-alive comments on idle event streams every `interval`
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = interval;
+        self
+    }
+
+    /// Serve the playground page at `/`, or don't
+    pub fn with_playground(mut self, enabled: bool) -> Self {
+        self.playground = enabled;
+        self
+    }
+
     pub fn router(&self) -> Router {
-        Router::new()
-            .route("/sse", get(sse_handler).post(post_event_handler))
-            .with_state(self.clone())
+        let router = Router::new().route("/sse", get(sse_handler).post(post_event_handler));
+        let router = if self.playground {
+            router
+                .route("/", get(playground_handler))
+                .route("/playground/call", post(playground_call_handler))
+        } else {
+            router
+        };
+        router.with_state(self.clone())
+    }
+}
+
+// Closes a session's input when its event stream is dropped, i.e. when its client
+// left, which ends the session's server and with it the rest of the session's state
+struct SessionGuard {
+    txs: Arc<tokio::sync::RwLock<HashMap<SessionId, C2SWriter>>>,
+    session: SessionId,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let (txs, session) = (self.txs.clone(), self.session.clone());
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                txs.write().await.remove(&session);
+            });
+        }
     }
 }
 
@@ -67,30 +196,50 @@ async fn post_event_handler(
             .ok_or(StatusCode::NOT_FOUND)?
             .clone()
     };
-    let mut write_stream = write_stream.lock().await;
     let mut body = body.into_data_stream();
     if let (_, Some(size)) = body.size_hint() {
         if size > BODY_BYTES_LIMIT {
             return Err(StatusCode::PAYLOAD_TOO_LARGE);
         }
     }
-    // calculate the body size
-    let mut size = 0;
+    // The whole message is needed to tell whether the server handles it
+    let mut message = Vec::new();
     while let Some(chunk) = body.next().await {
         let Ok(chunk) = chunk else {
             return Err(StatusCode::BAD_REQUEST);
         };
-        size += chunk.len();
-        if size > BODY_BYTES_LIMIT {
+        if message.len() + chunk.len() > BODY_BYTES_LIMIT {
             return Err(StatusCode::PAYLOAD_TOO_LARGE);
         }
-        write_stream
-            .write_all(&chunk)
+        message.extend_from_slice(&chunk);
+    }
+
+    let subscriptions = app.subscriptions.read().await.get(session_id.as_str()).cloned();
+    let subscriptions = subscriptions.ok_or(StatusCode::NOT_FOUND)?;
+    let router = app.routers.read().await.get(session_id.as_str()).cloned();
+    let router = router.ok_or(StatusCode::NOT_FOUND)?;
+    if let Some(reply) = extension_response(&router, &subscriptions, &message).await {
+        if reply.is_empty() {
+            return Ok(StatusCode::ACCEPTED);
+        }
+        let replies = app.replies.read().await.get(session_id.as_str()).cloned();
+        replies
+            .ok_or(StatusCode::NOT_FOUND)?
+            .send(reply)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        return Ok(StatusCode::ACCEPTED);
     }
+
+    // The server reads one message per line
+    let mut frame = BytesMut::new();
+    JsonRpcFrameCodec::new()
+        .with_framing(SharedFraming::fixed(Framing::Newline))
+        .encode(Bytes::from(message), &mut frame)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut write_stream = write_stream.lock().await;
     write_stream
-        .write_u8(b'\n')
+        .write_all(&frame)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     Ok(StatusCode::ACCEPTED)
@@ -107,11 +256,17 @@ async fn sse_handler(State(app): State<App>) -> Sse<impl Stream<Item = Result<Ev
         .write()
         .await
         .insert(session.clone(), Arc::new(Mutex::new(c2s_write)));
+    let (replies, reply_receiver) = mpsc::channel(REPLY_BUFFER_SIZE);
+    app.replies.write().await.insert(session.clone(), replies);
+    let subscriptions = SessionSubscriptions::default();
+    app.subscriptions.write().await.insert(session.clone(), subscriptions.clone());
+    let docs = app.docs.for_session();
+    app.routers.write().await.insert(session.clone(), docs.clone());
     {
         let app_clone = app.clone();
         let session = session.clone();
         tokio::spawn(async move {
-            let router = RouterService(DocRouter::new());
+            let router = RouterService(docs);
             let server = Server::new(router);
             let bytes_transport = ByteTransport::new(c2s_read, s2c_write);
             let _result = server
@@ -119,21 +274,48 @@ async fn sse_handler(State(app): State<App>) -> Sse<impl Stream<Item = Result<Ev
                 .await
                 .inspect_err(|e| tracing::error!(?e, "server run error"));
             app_clone.txs.write().await.remove(&session);
+            app_clone.replies.write().await.remove(&session);
+            app_clone.routers.write().await.remove(&session);
+            let subscriptions = app_clone.subscriptions.write().await.remove(&session);
+            if let Some(subscriptions) = subscriptions {
+                app_clone.docs.end_session(&subscriptions).await;
+            }
         });
     }
 
+    let guard = SessionGuard {
+        txs: app.txs.clone(),
+        session: session.clone(),
+    };
     let stream = futures::stream::once(futures::future::ok(
         Event::default()
             .event("endpoint")
             .data(format!("?sessionId={session}")),
     ))
-    .chain(
-        FramedRead::new(s2c_read, JsonRpcFrameCodec)
+    .chain({
+        // Responses end with the session (the trailing `None`); notifications and
+        // replies given by the transport are merged in for as long as it lasts. The
+        // server's own output isn't capped, as documents can be large
+        let messages = FramedRead::new(s2c_read, JsonRpcFrameCodec::with_max_frame_length(usize::MAX))
+            .map_ok(lift_error_data)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
             .and_then(move |bytes| match std::str::from_utf8(bytes.as_ref()) {
                 Ok(message) => futures::future::ok(Event::default().event("message").data(message)),
                 Err(e) => futures::future::err(io::Error::new(io::ErrorKind::InvalidData, e)),
-            }),
-    );
-    Sse::new(stream)
+            })
+            .map(Some)
+            .chain(futures::stream::once(async { None }));
+        let notifications = futures::stream::select(
+            resource_updates(app.docs.watch.subscribe(), subscriptions),
+            channel_messages(reply_receiver),
+        )
+            .map(|message| Some(Ok(Event::default().event("message").data(message))));
+        futures::stream::select(messages, notifications)
+            .take_while(|event| futures::future::ready(event.is_some()))
+            .filter_map(move |event| {
+                let _session = &guard;
+                futures::future::ready(event)
+            })
+    });
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(app.keep_alive))
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 mod http_sse_server;
+mod playground;
 
 pub use http_sse_server::*;
+pub use playground::{PlaygroundCall, PLAYGROUND_HTML, PLAYGROUND_TOOLS};
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
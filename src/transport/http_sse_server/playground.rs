@@ -0,0 +1,68 @@
+use axum::{extract::State, http::StatusCode, response::Html, Json};
+use mcp_core::{Content, ToolError};
+use mcp_server::Router;
+use serde_json::{json, Map, Value};
+
+use super::App;
+use crate::tools::split_error_data;
+
+/// Tools the playground page can call
+pub const PLAYGROUND_TOOLS: [&str; 3] = ["lookup_crate", "lookup_item", "search_crates"];
+
+/// The playground page: a form calling one of `PLAYGROUND_TOOLS` and showing its result
+pub const PLAYGROUND_HTML: &str = include_str!("playground.html");
+
+/// A tool call made from the playground page
+#[derive(Debug, serde::Deserialize)]
+pub struct PlaygroundCall {
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+pub(super) async fn playground_handler() -> Html<&'static str> {
+    Html(PLAYGROUND_HTML)
+}
+
+// Run a call from the playground page, answering with `{"html": ...}` or `{"error": ...}`
+pub(super) async fn playground_call_handler(
+    State(app): State<App>,
+    Json(call): Json<PlaygroundCall>,
+) -> (StatusCode, Json<Value>) {
+    let invalid = |message: String| (StatusCode::BAD_REQUEST, Json(json!({ "error": message })));
+    if !PLAYGROUND_TOOLS.contains(&call.tool.as_str()) {
+        return invalid(format!("The playground only calls {}", PLAYGROUND_TOOLS.join(", ")));
+    }
+    let mut arguments = match call.arguments {
+        Value::Object(arguments) => arguments,
+        Value::Null => Map::new(),
+        _ => return invalid("arguments must be an object".to_string()),
+    };
+    // The page shows results as they are; the router sanitizes the HTML it renders
+    arguments.insert("format".to_string(), json!("html"));
+
+    // A session of its own, so playground calls stay out of the clients' histories
+    let router = app.docs.for_session();
+    match router.call_tool(&call.tool, Value::Object(arguments)).await {
+        Ok(contents) => {
+            let html: Vec<String> = contents
+                .into_iter()
+                .filter_map(|content| match content {
+                    Content::Text(text) => Some(text.text),
+                    _ => None,
+                })
+                .collect();
+            (StatusCode::OK, Json(json!({ "html": html.join("\n") })))
+        }
+        Err(error) => {
+            let (status, message) = match &error {
+                ToolError::InvalidParameters(message) => (StatusCode::BAD_REQUEST, message),
+                ToolError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+                ToolError::ExecutionError(message) | ToolError::SchemaError(message) => {
+                    (StatusCode::BAD_GATEWAY, message)
+                }
+            };
+            (status, Json(json!({ "error": split_error_data(message).0 })))
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use std::sync::Arc;
-use crate::transport::http_sse_server::App;
+use std::time::SystemTime;
+use axum::{extract::State, http::StatusCode, Json};
+use serde_json::json;
+use crate::transport::http_sse_server::{resolve_bind_address, App, PlaygroundCall, DEFAULT_HTTP_PORT, PLAYGROUND_HTML, PLAYGROUND_TOOLS};
+use crate::transport::http_sse_server::playground::{playground_call_handler, playground_handler};
+use crate::tools::{DocRouter, HistoryEntry};
 
 #[tokio::test]
 async fn test_app_initialization() {
@@ -8,6 +13,85 @@ async fn test_app_initialization() {
     assert!(app.txs.read().await.is_empty());
 }
 
+#[tokio::test]
+async fn test_sessions_share_router_cache() {
+    let app = App::with_router(DocRouter::new());
+    let session_view = app.clone();
+    
+    // Clones handed to sessions must see entries cached through the app's router
+    app.docs.cache.set("serde".to_string(), "cached docs".to_string()).await;
+    assert_eq!(session_view.docs.cache.get("serde").await, Some("cached docs".to_string()));
+}
+
+#[tokio::test]
+async fn test_session_routers_keep_separate_histories() {
+    let app = App::new();
+    let (first, second) = (app.docs.for_session(), app.docs.for_session());
+
+    first.cache.set("serde".to_string(), "cached docs".to_string()).await;
+    assert_eq!(second.cache.get("serde").await, Some("cached docs".to_string()));
+
+    first.history.record(HistoryEntry::new(SystemTime::now(), "lookup_crate", "{}".to_string(), vec![], true));
+    assert_eq!(first.history.entries().len(), 1);
+    assert!(second.history.entries().is_empty());
+    assert!(app.docs.history.entries().is_empty());
+}
+
+#[tokio::test]
+async fn test_resolve_bind_address() {
+    let addrs = |spec: &str| {
+        let spec = spec.to_string();
+        async move { resolve_bind_address(&spec).await.map(|addrs| addrs.iter().map(|a| a.to_string()).collect::<Vec<_>>()) }
+    };
+    assert_eq!(addrs("127.0.0.1:3000").await.unwrap(), vec!["127.0.0.1:3000"]);
+    assert_eq!(addrs("[::1]:3000").await.unwrap(), vec!["[::1]:3000"]);
+    assert_eq!(addrs(" [::]:3000 ").await.unwrap(), vec!["[::]:3000"]);
+    // Bare addresses get the default port, bracketed or not
+    let default_port = format!("[::1]:{}", DEFAULT_HTTP_PORT);
+    assert_eq!(addrs("::1").await.unwrap(), vec![default_port.clone()]);
+    assert_eq!(addrs("[::1]").await.unwrap(), vec![default_port]);
+    assert_eq!(addrs("0.0.0.0").await.unwrap(), vec![format!("0.0.0.0:{}", DEFAULT_HTTP_PORT)]);
+    // Host names give every address they resolve to
+    let localhost = addrs("localhost:3000").await.unwrap();
+    assert!(!localhost.is_empty());
+    assert!(localhost.iter().all(|addr| addr.ends_with(":3000")));
+
+    for invalid in ["127.0.0.1:http", "[::1]:99999", "", ":3000"] {
+        let err = resolve_bind_address(invalid).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", invalid);
+    }
+}
+
+#[tokio::test]
+async fn test_playground() {
+    let page = playground_handler().await.0;
+    assert_eq!(page, PLAYGROUND_HTML);
+    for tool in PLAYGROUND_TOOLS {
+        assert!(page.contains(&format!("<option value=\"{}\">", tool)), "{}", tool);
+    }
+    assert!(page.contains("/playground/call"));
+    assert!(App::new().playground);
+    assert!(!App::new().with_playground(false).playground);
+
+    let app = App::new();
+    let call = |tool: &str, arguments: serde_json::Value| {
+        let call = PlaygroundCall { tool: tool.to_string(), arguments };
+        playground_call_handler(State(app.clone()), Json(call))
+    };
+    // Only the lookups the page offers can be called
+    let (status, Json(body)) = call("clear_cache", json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["error"].as_str().unwrap().contains("lookup_crate, lookup_item, search_crates"));
+    let (status, _) = call("lookup_crate", json!(["serde"])).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // Tool errors come back as the message alone, without the attached data
+    let (status, Json(body)) = call("lookup_crate", json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "crate_name is required");
+    // Playground calls stay out of the app's history
+    assert!(app.docs.history.entries().is_empty());
+}
+
 // Since we're having integration issues with Tower's ServiceExt, we'll provide
 // simplified versions of the tests that verify the basic functionality without
 // making actual HTTP requests through the router.
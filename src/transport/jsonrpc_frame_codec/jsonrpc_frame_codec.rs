@@ -1,25 +1,316 @@
-use tokio_util::codec::Decoder;
+use std::sync::{Arc, OnceLock};
 
-#[derive(Default)]
-pub struct JsonRpcFrameCodec;
+use serde::Serialize;
+use tokio::io;
+use tokio_util::bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
 
-impl Decoder for JsonRpcFrameCodec {
-    type Item = tokio_util::bytes::Bytes;
-    type Error = tokio::io::Error;
-    fn decode(
-        &mut self,
-        src: &mut tokio_util::bytes::BytesMut,
-    ) -> Result<Option<Self::Item>, Self::Error> {
-        if let Some(end) = src
-            .iter()
-            .enumerate()
-            .find_map(|(idx, &b)| (b == b'\n').then_some(idx))
-        {
-            let line = src.split_to(end);
-            let _char_next_line = src.split_to(1);
-            Ok(Some(line.freeze()))
+/// Default cap on the length of one frame, beyond which decoding fails instead of
+/// buffering without bound; the same as the HTTP transport's cap on message bodies
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 1 << 22;
+
+/// Cap on the header section of a `Content-Length` framed message
+const MAX_HEADERS_LENGTH: usize = 1 << 12;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// How JSON-RPC messages are delimited on a byte stream
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Framing {
+    /// One message per line
+    Newline,
+    /// LSP-style `Content-Length: <bytes>` headers, a blank line, then the message
+    ContentLength,
+}
+
+impl Framing {
+    // Append `message` to `dst`, framed
+    fn encode(self, message: &[u8], dst: &mut BytesMut) {
+        match self {
+            Framing::Newline => {
+                // JSON only allows line breaks as whitespace, so pretty-printed
+                // messages stay on one line with spaces in their place
+                dst.reserve(message.len() + 1);
+                dst.extend(message.iter().map(|&b| if b == b'\n' || b == b'\r' { b' ' } else { b }));
+                dst.put_u8(b'\n');
+            }
+            Framing::ContentLength => {
+                let headers = format!("Content-Length: {}\r\n\r\n", message.len());
+                dst.reserve(headers.len() + message.len());
+                dst.put_slice(headers.as_bytes());
+                dst.put_slice(message);
+            }
+        }
+    }
+}
+
+/// A message the codec serializes to JSON when encoding it, e.g. `JsonMessage(json!({...}))`
+/// or any other `Serialize` value
+#[derive(Clone, Debug)]
+pub struct JsonMessage<T>(pub T);
+
+/// The framing of a stream, fixed up front or detected from its first message
+///
+/// Clones share the framing, so a client's replies can be framed the way the client
+/// framed its requests.
+#[derive(Clone, Debug, Default)]
+pub struct SharedFraming(Arc<OnceLock<Framing>>);
+
+impl SharedFraming {
+    /// Framing decided up front rather than detected
+    pub fn fixed(framing: Framing) -> Self {
+        Self(Arc::new(OnceLock::from(framing)))
+    }
+
+    /// The framing, once decided
+    pub fn get(&self) -> Option<Framing> {
+        self.0.get().copied()
+    }
+
+    fn detect(&self, framing: Framing) -> Framing {
+        *self.0.get_or_init(|| framing)
+    }
+}
+
+/// Splits a byte stream into JSON-RPC messages, one per line or `Content-Length` framed,
+/// and frames messages the same way for writing
+///
+/// The framing is detected from the first message unless given: messages start with
+/// `{` or `[`, headers with a letter. Lines may end in `\n` or `\r\n` and messages may
+/// start with a UTF-8 byte order mark, which is left out of the frame. Blank lines, which
+/// some clients send as keep-alives, give no frame. A message longer than the maximum
+/// frame length fails with `InvalidData`, as do headers without a `Content-Length`.
+///
+/// Encoding frames messages as the stream's framing says, one per line until it's
+/// known; the maximum frame length only applies to decoding.
+pub struct JsonRpcFrameCodec {
+    max_frame_length: usize,
+    framing: SharedFraming,
+    // How far the buffer has been searched for a newline, so partial lines aren't
+    // searched again with every read
+    searched: usize,
+    // Length of the `Content-Length` framed message whose headers were read
+    content_length: Option<usize>,
+}
+
+impl JsonRpcFrameCodec {
+    pub fn new() -> Self {
+        Self::with_max_frame_length(DEFAULT_MAX_FRAME_LENGTH)
+    }
+
+    /// Fail on frames longer than `max_frame_length` bytes
+    pub fn with_max_frame_length(max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length,
+            framing: SharedFraming::default(),
+            searched: 0,
+            content_length: None,
+        }
+    }
+
+    /// Decode and encode with `framing` rather than a framing of the codec's own
+    pub fn with_framing(mut self, framing: SharedFraming) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    /// The stream's framing, shared with the codec
+    pub fn framing(&self) -> SharedFraming {
+        self.framing.clone()
+    }
+
+    fn decode_line(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        loop {
+            let Some(offset) = src[self.searched..].iter().position(|&b| b == b'\n') else {
+                // Room for a byte order mark and a `\r` besides the frame itself
+                if src.len() > self.max_frame_length.saturating_add(UTF8_BOM.len() + 1) {
+                    src.clear();
+                    self.searched = 0;
+                    return Err(self.too_long());
+                }
+                self.searched = src.len();
+                return Ok(None);
+            };
+            let mut line = src.split_to(self.searched + offset + 1);
+            self.searched = 0;
+            line.truncate(line.len() - 1);
+            if let Some(frame) = self.frame(line)? {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    fn decode_content_length(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, io::Error> {
+        loop {
+            let length = match self.content_length {
+                Some(length) => length,
+                None => {
+                    // Stray line breaks between messages are skipped like blank lines
+                    let start = src.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(src.len());
+                    src.advance(start);
+                    let Some((headers_length, separator_length)) = headers_end(src) else {
+                        if src.len() > MAX_HEADERS_LENGTH {
+                            src.clear();
+                            return Err(invalid_headers(format!("longer than {} bytes", MAX_HEADERS_LENGTH)));
+                        }
+                        return Ok(None);
+                    };
+                    let headers = src.split_to(headers_length);
+                    src.advance(separator_length);
+                    let length = content_length(&headers)?;
+                    if length > self.max_frame_length {
+                        return Err(self.too_long());
+                    }
+                    self.content_length = Some(length);
+                    length
+                }
+            };
+            if src.len() < length {
+                src.reserve(length - src.len());
+                return Ok(None);
+            }
+            self.content_length = None;
+            let message = src.split_to(length);
+            if let Some(frame) = self.frame(message)? {
+                return Ok(Some(frame));
+            }
+        }
+    }
+
+    // The frame of a message without its line ending, or `None` for a blank one
+    fn frame(&self, mut message: BytesMut) -> Result<Option<Bytes>, io::Error> {
+        if message.starts_with(UTF8_BOM) {
+            message.advance(UTF8_BOM.len());
+        }
+        if message.last() == Some(&b'\r') {
+            message.truncate(message.len() - 1);
+        }
+        if message.len() > self.max_frame_length {
+            return Err(self.too_long());
+        }
+        if message.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        Ok(Some(message.freeze()))
+    }
+
+    fn too_long(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("JSON-RPC message longer than {} bytes", self.max_frame_length),
+        )
+    }
+}
+
+impl Default for JsonRpcFrameCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Length of the header section at the start of `buf` and of the blank line ending
+// it, once the whole section was read
+fn headers_end(buf: &[u8]) -> Option<(usize, usize)> {
+    buf.iter().enumerate().find_map(|(idx, &b)| {
+        if b != b'\n' {
+            return None;
+        }
+        let rest = &buf[idx + 1..];
+        if rest.starts_with(b"\n") {
+            Some((idx, 2))
+        } else if rest.starts_with(b"\r\n") {
+            Some((idx, 3))
         } else {
-            Ok(None)
+            None
+        }
+    })
+}
+
+// The `Content-Length` of a header section; other headers, such as `Content-Type`,
+// are ignored
+fn content_length(headers: &[u8]) -> Result<usize, io::Error> {
+    let headers = std::str::from_utf8(headers).map_err(|_| invalid_headers("not UTF-8".to_string()))?;
+    let value = headers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim())
+        .ok_or_else(|| invalid_headers("without a Content-Length".to_string()))?;
+    value
+        .parse()
+        .map_err(|_| invalid_headers(format!("with an invalid Content-Length {}", value)))
+}
+
+fn invalid_headers(reason: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("JSON-RPC message headers {}", reason))
+}
+
+impl Decoder for JsonRpcFrameCodec {
+    type Item = Bytes;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let framing = match self.framing.get() {
+            Some(framing) => framing,
+            None => {
+                let first = src
+                    .iter()
+                    .find(|&&b| !b.is_ascii_whitespace() && !UTF8_BOM.contains(&b));
+                match first {
+                    Some(b) if b.is_ascii_alphabetic() => self.framing.detect(Framing::ContentLength),
+                    Some(_) => self.framing.detect(Framing::Newline),
+                    // Only blank lines and byte order marks so far, which give no frame
+                    // in either framing
+                    None => {
+                        src.clear();
+                        return Ok(None);
+                    }
+                }
+            }
+        };
+        match framing {
+            Framing::Newline => self.decode_line(src),
+            Framing::ContentLength => self.decode_content_length(src),
+        }
+    }
+
+    // A last line without a trailing newline is still a message
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
         }
+        if self.framing.get() == Some(Framing::ContentLength) {
+            if src.iter().all(u8::is_ascii_whitespace) {
+                src.clear();
+                return Ok(None);
+            }
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "JSON-RPC message cut short"));
+        }
+        self.searched = 0;
+        let rest = src.split();
+        self.frame(rest)
+    }
+}
+
+impl Encoder<Bytes> for JsonRpcFrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, message: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.framing.get().unwrap_or(Framing::Newline).encode(&message, dst);
+        Ok(())
+    }
+}
+
+impl<T: Serialize> Encoder<JsonMessage<T>> for JsonRpcFrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, JsonMessage(message): JsonMessage<T>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let message = serde_json::to_vec(&message).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.framing.get().unwrap_or(Framing::Newline).encode(&message, dst);
+        Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,5 @@
 mod jsonrpc_frame_codec;
-pub use jsonrpc_frame_codec::JsonRpcFrameCodec;
+pub use jsonrpc_frame_codec::{Framing, JsonMessage, JsonRpcFrameCodec, SharedFraming, DEFAULT_MAX_FRAME_LENGTH};
 
 #[cfg(test)]
-mod tests;
\ No newline at end of file
+mod tests;
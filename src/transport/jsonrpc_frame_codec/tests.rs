@@ -1,7 +1,9 @@
 
-use crate::transport::jsonrpc_frame_codec::JsonRpcFrameCodec;
-use tokio_util::bytes::BytesMut;
-use tokio_util::codec::Decoder;
+use crate::transport::jsonrpc_frame_codec::{Framing, JsonMessage, JsonRpcFrameCodec, SharedFraming};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::json;
+use tokio_util::bytes::{Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
 
 #[test]
 fn test_decode_single_line() {
@@ -64,11 +66,337 @@ fn test_decode_empty_line() {
     let mut codec = JsonRpcFrameCodec::default();
     let mut buffer = BytesMut::from("\n");
     
-    // Should return an empty frame
+    // Blank lines are keep-alives, not frames
     let result = codec.decode(&mut buffer).unwrap();
-    assert!(result.is_some());
-    assert_eq!(result.unwrap().len(), 0);
+    assert!(result.is_none());
     
     // Buffer should be empty
     assert_eq!(buffer.len(), 0);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_decode_skips_blank_lines() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from("\n\r\n  \t\n{\"id\":1}\n\n");
+
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+    assert!(codec.decode(&mut buffer).unwrap().is_none());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_decode_crlf_and_bom() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::new();
+    buffer.extend_from_slice(b"\xEF\xBB\xBF{\"id\":1}\r\n{\"id\":2}\r\n\xEF\xBB\xBF\r\n");
+
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":2}"#);
+    // A line holding only a byte order mark is blank too
+    assert!(codec.decode(&mut buffer).unwrap().is_none());
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_decode_crlf_split_across_reads() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from("{\"id\":1}\r");
+    assert!(codec.decode(&mut buffer).unwrap().is_none());
+
+    buffer.extend_from_slice(b"\n");
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+}
+
+#[test]
+fn test_decode_max_frame_length() {
+    let mut codec = JsonRpcFrameCodec::with_max_frame_length(8);
+    assert_eq!(codec.max_frame_length(), 8);
+
+    // Frames up to the limit pass, line endings and byte order marks not counted
+    let mut buffer = BytesMut::new();
+    buffer.extend_from_slice(b"\xEF\xBB\xBF12345678\r\n");
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), "12345678");
+
+    // A complete line over the limit fails
+    let mut buffer = BytesMut::from("123456789\n");
+    let err = codec.decode(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(err.to_string().contains("longer than 8 bytes"));
+
+    // So does a partial one, without waiting for its end, and its bytes are dropped
+    let mut buffer = BytesMut::from("1234567890123");
+    let err = codec.decode(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_decode_eof_without_trailing_newline() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from("{\"id\":1}\n{\"id\":2}");
+
+    assert_eq!(codec.decode_eof(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+    assert_eq!(codec.decode_eof(&mut buffer).unwrap().unwrap(), r#"{"id":2}"#);
+    assert!(codec.decode_eof(&mut buffer).unwrap().is_none());
+
+    let mut blank = BytesMut::from("\r\n  ");
+    assert!(codec.decode_eof(&mut blank).unwrap().is_none());
+}
+
+// Property: however a stream of messages, blank lines, `\r\n` endings and byte order
+// marks is split into reads, exactly the messages come out, in order
+#[test]
+fn test_decode_arbitrary_splits() {
+    let mut rng = StdRng::seed_from_u64(0x6a73_6f6e_7270_63);
+    for _ in 0..500 {
+        let messages: Vec<String> = (0..rng.gen_range(0..8))
+            .map(|i| {
+                let text: String = (0..rng.gen_range(0..40))
+                    .map(|_| *b"abc \\\"\t{}[],:\r\xC3".get(rng.gen_range(0..16)).unwrap_or(&b'x') as char)
+                    .collect();
+                format!("{{\"id\":{},\"text\":{:?}}}", i, text)
+            })
+            .collect();
+
+        let mut stream = Vec::new();
+        for message in &messages {
+            for _ in 0..rng.gen_range(0..3) {
+                stream.extend_from_slice(["\n", "\r\n", " \n"][rng.gen_range(0..3)].as_bytes());
+            }
+            if rng.gen_bool(0.2) {
+                stream.extend_from_slice(b"\xEF\xBB\xBF");
+            }
+            stream.extend_from_slice(message.as_bytes());
+            stream.extend_from_slice(if rng.gen_bool(0.5) { b"\r\n" } else { b"\n" });
+        }
+
+        let mut codec = JsonRpcFrameCodec::default();
+        let mut buffer = BytesMut::new();
+        let mut decoded = Vec::new();
+        let mut rest = stream.as_slice();
+        while !rest.is_empty() {
+            let (read, remaining) = rest.split_at(rng.gen_range(1..=rest.len().min(16)));
+            buffer.extend_from_slice(read);
+            rest = remaining;
+            while let Some(frame) = codec.decode(&mut buffer).unwrap() {
+                decoded.push(String::from_utf8(frame.to_vec()).unwrap());
+            }
+        }
+        assert!(codec.decode_eof(&mut buffer).unwrap().is_none());
+        assert_eq!(decoded, messages);
+    }
+}
+
+// Fuzz: arbitrary bytes never panic the decoder, and frames never exceed the limit
+#[test]
+fn test_decode_arbitrary_bytes() {
+    let mut rng = StdRng::seed_from_u64(0x66_757a_7a);
+    for _ in 0..500 {
+        let max_frame_length = rng.gen_range(1..64);
+        let mut codec = JsonRpcFrameCodec::with_max_frame_length(max_frame_length)
+            .with_framing(SharedFraming::fixed(Framing::Newline));
+        let mut buffer = BytesMut::new();
+        for _ in 0..rng.gen_range(1..20) {
+            let read: Vec<u8> = (0..rng.gen_range(0..32))
+                .map(|_| *b"\n\r\xEF\xBB\xBF {}a".get(rng.gen_range(0..12)).unwrap_or(&rng.gen::<u8>()))
+                .collect();
+            buffer.extend_from_slice(&read);
+            loop {
+                match codec.decode(&mut buffer) {
+                    Ok(Some(frame)) => {
+                        assert!(frame.len() <= max_frame_length);
+                        assert!(!frame.contains(&b'\n'));
+                    }
+                    Ok(None) => break,
+                    Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidData),
+                }
+            }
+            assert!(buffer.len() <= max_frame_length + 4);
+        }
+    }
+}
+
+#[test]
+fn test_decode_content_length() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from(
+        "Content-Length: 8\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n{\"id\":1}content-length:8\n\n{\"id\":2}",
+    );
+
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+    assert_eq!(codec.framing().get(), Some(Framing::ContentLength));
+    // Header names are case-insensitive, and bare `\n` line endings pass too
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":2}"#);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_decode_content_length_split_across_reads() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let framed = content_length_framed(br#"{"id":1,"text":"a\nb"}"#);
+    let mut buffer = BytesMut::new();
+    for (i, byte) in framed.iter().enumerate() {
+        buffer.extend_from_slice(&[*byte]);
+        let frame = codec.decode(&mut buffer).unwrap();
+        assert_eq!(frame.is_some(), i == framed.len() - 1);
+        if let Some(frame) = frame {
+            assert_eq!(frame, r#"{"id":1,"text":"a\nb"}"#);
+        }
+    }
+}
+
+#[test]
+fn test_decode_detects_newline_framing() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::new();
+    buffer.extend_from_slice(b"\r\n\xEF\xBB\xBF{\"id\":1}\n");
+
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+    assert_eq!(codec.framing().get(), Some(Framing::Newline));
+
+    // A fixed framing isn't detected over
+    let mut codec = JsonRpcFrameCodec::default().with_framing(SharedFraming::fixed(Framing::ContentLength));
+    let mut buffer = BytesMut::from("{\"id\":1}\n\n");
+    assert!(codec.decode(&mut buffer).is_err());
+}
+
+#[test]
+fn test_decode_content_length_errors() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from("Content-Type: application/json\r\n\r\n{}");
+    let err = codec.decode(&mut buffer).unwrap_err();
+    assert!(err.to_string().contains("without a Content-Length"));
+
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from("Content-Length: many\r\n\r\n{}");
+    let err = codec.decode(&mut buffer).unwrap_err();
+    assert!(err.to_string().contains("invalid Content-Length many"));
+
+    // Too long messages fail as soon as their headers are read
+    let mut codec = JsonRpcFrameCodec::with_max_frame_length(8);
+    let mut buffer = BytesMut::from("Content-Length: 9\r\n\r\n");
+    let err = codec.decode(&mut buffer).unwrap_err();
+    assert!(err.to_string().contains("longer than 8 bytes"));
+
+    // The stream may not end inside a message
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::from("Content-Length: 8\r\n\r\n{\"id\"");
+    assert!(codec.decode(&mut buffer).unwrap().is_none());
+    let err = codec.decode_eof(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+// Property: `Content-Length` framed messages, with stray line breaks and byte order
+// marks between them, come out exactly however they are split into reads
+#[test]
+fn test_decode_content_length_arbitrary_splits() {
+    let mut rng = StdRng::seed_from_u64(0x6c_7370);
+    for _ in 0..500 {
+        let messages: Vec<String> = (0..rng.gen_range(1..8))
+            .map(|i| {
+                let text: String = (0..rng.gen_range(0..40))
+                    .map(|_| *b"abc \n\r{}:".get(rng.gen_range(0..12)).unwrap_or(&b'x') as char)
+                    .collect();
+                format!("{{\"id\":{},\"text\":{:?}}}", i, text)
+            })
+            .collect();
+
+        let mut stream = Vec::new();
+        for message in &messages {
+            if rng.gen_bool(0.2) {
+                stream.extend_from_slice(b"\r\n");
+            }
+            let mut body = Vec::new();
+            if rng.gen_bool(0.2) {
+                body.extend_from_slice(b"\xEF\xBB\xBF");
+            }
+            body.extend_from_slice(message.as_bytes());
+            stream.extend_from_slice(&content_length_framed(&body));
+        }
+
+        let mut codec = JsonRpcFrameCodec::default();
+        let mut buffer = BytesMut::new();
+        let mut decoded = Vec::new();
+        let mut rest = stream.as_slice();
+        while !rest.is_empty() {
+            let (read, remaining) = rest.split_at(rng.gen_range(1..=rest.len().min(16)));
+            buffer.extend_from_slice(read);
+            rest = remaining;
+            while let Some(frame) = codec.decode(&mut buffer).unwrap() {
+                decoded.push(String::from_utf8(frame.to_vec()).unwrap());
+            }
+        }
+        assert!(codec.decode_eof(&mut buffer).unwrap().is_none());
+        assert_eq!(decoded, messages);
+    }
+}
+
+// `message` as a client using `Content-Length` framing sends it
+fn content_length_framed(message: &[u8]) -> BytesMut {
+    let mut codec = JsonRpcFrameCodec::new().with_framing(SharedFraming::fixed(Framing::ContentLength));
+    let mut framed = BytesMut::new();
+    codec.encode(Bytes::copy_from_slice(message), &mut framed).unwrap();
+    framed
+}
+
+#[test]
+fn test_encode_newline() {
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::new();
+    codec.encode(Bytes::from_static(br#"{"id":1}"#), &mut buffer).unwrap();
+    // Pretty-printed messages are put on one line
+    codec.encode(Bytes::from_static(b"{\r\n  \"id\": 2\n}"), &mut buffer).unwrap();
+    assert_eq!(buffer, "{\"id\":1}\n{    \"id\": 2 }\n");
+
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{    "id": 2 }"#);
+}
+
+#[test]
+fn test_encode_content_length() {
+    let mut buffer = content_length_framed(br#"{"id":1}"#);
+    assert_eq!(buffer, "Content-Length: 8\r\n\r\n{\"id\":1}");
+
+    let mut codec = JsonRpcFrameCodec::default();
+    assert_eq!(codec.decode(&mut buffer).unwrap().unwrap(), r#"{"id":1}"#);
+}
+
+#[test]
+fn test_encode_follows_detected_framing() {
+    let framing = SharedFraming::default();
+    let mut decoder = JsonRpcFrameCodec::default().with_framing(framing.clone());
+    let mut encoder = JsonRpcFrameCodec::default().with_framing(framing);
+
+    // Newline-delimited until the client's framing is known
+    let mut output = BytesMut::new();
+    encoder.encode(Bytes::from_static(b"{}"), &mut output).unwrap();
+    assert_eq!(output, "{}\n");
+
+    let mut input = content_length_framed(br#"{"id":1}"#);
+    decoder.decode(&mut input).unwrap().unwrap();
+    let mut output = BytesMut::new();
+    encoder.encode(Bytes::from_static(b"{}"), &mut output).unwrap();
+    assert_eq!(output, "Content-Length: 2\r\n\r\n{}");
+}
+
+#[test]
+fn test_encode_json_message() {
+    #[derive(serde::Serialize)]
+    struct Notification {
+        jsonrpc: &'static str,
+        method: &'static str,
+    }
+
+    let mut codec = JsonRpcFrameCodec::default();
+    let mut buffer = BytesMut::new();
+    codec
+        .encode(JsonMessage(Notification { jsonrpc: "2.0", method: "notifications/initialized" }), &mut buffer)
+        .unwrap();
+    codec.encode(JsonMessage(json!({ "id": 1, "text": "two\nlines" })), &mut buffer).unwrap();
+
+    let first = codec.decode(&mut buffer).unwrap().unwrap();
+    assert_eq!(first, r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#);
+    // Line breaks inside strings are escaped, so the message keeps them
+    let second: serde_json::Value = serde_json::from_slice(&codec.decode(&mut buffer).unwrap().unwrap()).unwrap();
+    assert_eq!(second["text"], "two\nlines");
+}
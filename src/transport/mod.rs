@@ -1,2 +1,4 @@
+pub mod extensions;
 pub mod http_sse_server;
-pub mod jsonrpc_frame_codec;
\ No newline at end of file
+pub mod jsonrpc_frame_codec;
+pub mod notifications;
@@ -0,0 +1,5 @@
+mod notifications;
+pub use notifications::{channel_messages, forward_with_notifications, resource_updated, resource_updates};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,92 @@
+use futures::{stream, SinkExt, Stream, StreamExt};
+use serde_json::json;
+use tokio::{
+    io::{self, AsyncRead, AsyncWrite},
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc,
+    },
+};
+use tokio_util::{
+    bytes::Bytes,
+    codec::{FramedRead, FramedWrite},
+};
+
+use crate::{
+    tools::{SessionSubscriptions, UPDATES_RESOURCE_URI},
+    transport::{extensions::lift_error_data, jsonrpc_frame_codec::{JsonRpcFrameCodec, SharedFraming}},
+};
+
+/// JSON-RPC notification telling the client that a resource's content changed
+pub fn resource_updated(uri: &str) -> String {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/resources/updated",
+        "params": { "uri": uri }
+    })
+    .to_string()
+}
+
+/// Turn the changed-resource URIs a router announces into notification messages
+/// for the resources `session` subscribed to
+///
+/// The updates resource is announced to every session, subscribed or not.
+/// Missed announcements are skipped; the next one still tells the client to re-read.
+pub fn resource_updates(changes: broadcast::Receiver<String>, session: SessionSubscriptions) -> impl Stream<Item = String> {
+    stream::unfold((changes, session), |(mut changes, session)| async move {
+        loop {
+            match changes.recv().await {
+                Ok(uri) if uri == UPDATES_RESOURCE_URI || session.contains(&uri).await => {
+                    return Some((resource_updated(&uri), (changes, session)))
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+/// Turn a channel of messages into a stream that ends when every sender is gone
+pub fn channel_messages(messages: mpsc::Receiver<String>) -> impl Stream<Item = String> {
+    stream::unfold(messages, |mut messages| async move {
+        messages.recv().await.map(|message| (message, messages))
+    })
+}
+
+/// Copy newline-delimited messages from the server to the client, inserting
+/// update notifications for the resources `session` subscribed to and
+/// `replies` between them, framed as `framing` says
+///
+/// The MCP server only writes responses, so notifications, and responses the
+/// transport gives itself (see `route_client_messages`), are interleaved on the
+/// way out, and tool errors get their data (see `lift_error_data`). Returns once
+/// the server closes its output.
+pub async fn forward_with_notifications<R, W>(
+    server_output: R,
+    client: W,
+    changes: broadcast::Receiver<String>,
+    session: SessionSubscriptions,
+    replies: mpsc::Receiver<String>,
+    framing: SharedFraming,
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // The trailing `None` ends the merged stream when the server's output ends. The
+    // server's own output isn't capped, as documents can be large
+    let messages = FramedRead::new(server_output, JsonRpcFrameCodec::with_max_frame_length(usize::MAX))
+        .map(|message| message.map(lift_error_data))
+        .map(Some)
+        .chain(stream::once(async { None }));
+    let notifications = stream::select(resource_updates(changes, session), channel_messages(replies))
+        .map(|message| Some(Ok(Bytes::from(message))));
+    let mut merged = std::pin::pin!(stream::select(messages, notifications));
+
+    // Replies follow the client's framing, newline-delimited until it's known
+    let mut client = FramedWrite::new(client, JsonRpcFrameCodec::new().with_framing(framing));
+    while let Some(Some(message)) = merged.next().await {
+        client.send(message?).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::tools::SessionSubscriptions;
+use crate::transport::jsonrpc_frame_codec::{Framing, SharedFraming};
+use crate::transport::notifications::{forward_with_notifications, resource_updated, resource_updates};
+use futures::StreamExt;
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, mpsc};
+
+#[test]
+fn test_resource_updated_message() {
+    let message: Value = serde_json::from_str(&resource_updated("docs://updates")).unwrap();
+    assert_eq!(message["jsonrpc"], "2.0");
+    assert_eq!(message["method"], "notifications/resources/updated");
+    assert_eq!(message["params"]["uri"], "docs://updates");
+    assert!(message.get("id").is_none());
+}
+
+#[tokio::test]
+async fn test_forward_interleaves_notifications() {
+    let (server_output, mut server_writer) = tokio::io::simplex(1024);
+    let (mut client_reader, client_writer) = tokio::io::simplex(4096);
+    let (changes, receiver) = broadcast::channel(4);
+    let (_replies, reply_receiver) = mpsc::channel(4);
+
+    let forward = tokio::spawn(forward_with_notifications(server_output, client_writer, receiver, SessionSubscriptions::default(), reply_receiver, SharedFraming::default()));
+
+    server_writer.write_all(b"{\"id\":1}\n").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    changes.send("docs://updates".to_string()).unwrap();
+    // Give the forwarder a chance to pass the notification on before the next response
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    server_writer.write_all(b"{\"id\":2}\n").await.unwrap();
+    drop(server_writer);
+
+    forward.await.unwrap().unwrap();
+    let mut output = String::new();
+    client_reader.read_to_string(&mut output).await.unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines, vec![
+        "{\"id\":1}",
+        resource_updated("docs://updates").as_str(),
+        "{\"id\":2}",
+    ]);
+}
+
+#[tokio::test]
+async fn test_forward_interleaves_replies() {
+    let (server_output, mut server_writer) = tokio::io::simplex(1024);
+    let (mut client_reader, client_writer) = tokio::io::simplex(4096);
+    let (_changes, receiver) = broadcast::channel(4);
+    let (replies, reply_receiver) = mpsc::channel(4);
+
+    let forward = tokio::spawn(forward_with_notifications(server_output, client_writer, receiver, SessionSubscriptions::default(), reply_receiver, SharedFraming::default()));
+
+    replies.send("{\"id\":7}".to_string()).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    server_writer.write_all(b"{\"id\":8}\n").await.unwrap();
+    drop(server_writer);
+
+    forward.await.unwrap().unwrap();
+    let mut output = String::new();
+    client_reader.read_to_string(&mut output).await.unwrap();
+    assert_eq!(output.lines().collect::<Vec<_>>(), vec!["{\"id\":7}", "{\"id\":8}"]);
+}
+
+#[tokio::test]
+async fn test_forward_with_content_length_framing() {
+    let (server_output, mut server_writer) = tokio::io::simplex(1024);
+    let (mut client_reader, client_writer) = tokio::io::simplex(4096);
+    let (_changes, receiver) = broadcast::channel(4);
+    let (_replies, reply_receiver) = mpsc::channel(4);
+
+    let framing = SharedFraming::fixed(Framing::ContentLength);
+    let forward = tokio::spawn(forward_with_notifications(server_output, client_writer, receiver, SessionSubscriptions::default(), reply_receiver, framing));
+    server_writer.write_all(b"{\"id\":1}\n{\"id\":22}\n").await.unwrap();
+    drop(server_writer);
+
+    forward.await.unwrap().unwrap();
+    let mut output = String::new();
+    client_reader.read_to_string(&mut output).await.unwrap();
+    assert_eq!(output, "Content-Length: 8\r\n\r\n{\"id\":1}Content-Length: 9\r\n\r\n{\"id\":22}");
+}
+
+#[tokio::test]
+async fn test_resource_updates_only_for_subscribed_resources() {
+    let (changes, receiver) = broadcast::channel(8);
+    let session = SessionSubscriptions::default();
+    session.insert("docs://tokio/latest").await;
+    let updates = resource_updates(receiver, session);
+
+    changes.send("docs://serde/latest".to_string()).unwrap();
+    changes.send("docs://tokio/latest".to_string()).unwrap();
+    changes.send("docs://updates".to_string()).unwrap();
+    drop(changes);
+
+    assert_eq!(updates.collect::<Vec<_>>().await, vec![
+        resource_updated("docs://tokio/latest"),
+        resource_updated("docs://updates"),
+    ]);
+}
@@ -0,0 +1,88 @@
+//! Golden-file tests for HTML-to-markdown conversion.
+//!
+//! Each fixture in `tests/fixtures` is a captured rustdoc/docs.rs page. The
+//! converted markdown is snapshot-tested with insta; after an intentional
+//! conversion change, review and accept the new output with
+//! `cargo insta review`.
+
+use std::path::Path;
+
+use cratedocs_mcp::tools::html_to_markdown;
+
+fn convert_fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name);
+    let html = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path.display(), e));
+    html_to_markdown(&html)
+}
+
+#[test]
+fn test_crate_page_snapshot() {
+    insta::assert_snapshot!(convert_fixture("crate_page.html"));
+}
+
+#[test]
+fn test_crate_root_snapshot() {
+    insta::assert_snapshot!(convert_fixture("crate_root.html"));
+}
+
+#[test]
+fn test_struct_page_snapshot() {
+    insta::assert_snapshot!(convert_fixture("struct_page.html"));
+}
+
+#[test]
+fn test_struct_fields_page_snapshot() {
+    insta::assert_snapshot!(convert_fixture("struct_fields_page.html"));
+}
+
+#[test]
+fn test_trait_page_snapshot() {
+    insta::assert_snapshot!(convert_fixture("trait_page.html"));
+}
+
+#[test]
+fn test_enum_page_snapshot() {
+    insta::assert_snapshot!(convert_fixture("enum_page.html"));
+}
+
+// Structural checks that hold regardless of the exact snapshot contents
+
+#[test]
+fn test_code_blocks_are_fenced() {
+    for fixture in ["crate_page.html", "crate_root.html", "struct_page.html", "enum_page.html"] {
+        let markdown = convert_fixture(fixture);
+        let fences = markdown.lines().filter(|line| line.trim_start().starts_with("```")).count();
+        assert!(fences >= 2, "{} lost its code fences", fixture);
+        assert_eq!(fences % 2, 0, "{} has an unbalanced code fence", fixture);
+    }
+}
+
+#[test]
+fn test_code_block_contents_survive() {
+    let markdown = convert_fixture("struct_page.html");
+    assert!(markdown.contains("mpsc::channel("));
+    assert!(!markdown.contains("<span"));
+}
+
+#[test]
+fn test_tables_are_converted() {
+    let markdown = convert_fixture("crate_root.html");
+    let table_rows: Vec<&str> = markdown
+        .lines()
+        .filter(|line| line.contains('|'))
+        .collect();
+    assert!(table_rows.iter().any(|row| row.contains("Format") && row.contains("Crate")));
+    assert!(table_rows.iter().any(|row| row.contains("JSON") && row.contains("serde_json")));
+    assert!(table_rows.iter().any(|row| row.contains("TOML")));
+}
+
+#[test]
+fn test_entities_are_decoded() {
+    let markdown = convert_fixture("struct_page.html");
+    assert!(markdown.contains("Sender<T>") || markdown.contains("Sender\\<T\\>"));
+    assert!(!markdown.contains("&lt;"));
+}
@@ -13,7 +13,8 @@ async fn test_doc_router_initialization() {
     
     // Tools should be available and correctly configured
     let tools = router.list_tools();
-    assert_eq!(tools.len(), 3);
+    let expected_tools = if cfg!(feature = "semver") { 31 } else { 30 };
+    assert_eq!(tools.len(), expected_tools);
     
     // Check specific tool schemas
     let lookup_crate_tool = tools.iter().find(|t| t.name == "lookup_crate").unwrap();
@@ -24,7 +25,7 @@ async fn test_doc_router_initialization() {
 
 #[test]
 fn test_jsonrpc_codec_functionality() {
-    let mut codec = JsonRpcFrameCodec;
+    let mut codec = JsonRpcFrameCodec::new();
     let json_rpc = r#"{"jsonrpc":"2.0","method":"lookup_crate","params":{"crate_name":"tokio"},"id":1}"#;
     
     let mut buffer = tokio_util::bytes::BytesMut::from(json_rpc);
@@ -75,15 +76,18 @@ async fn test_end_to_end_crate_lookup() {
     }
 }
 
-// Test resource and prompt API error cases (since they're not implemented)
+// Test resource and prompt API error cases (prompts aren't implemented)
 #[tokio::test]
 async fn test_unimplemented_apis() {
     let router = DocRouter::new();
     
-    // Resources should return an empty list
-    assert!(router.list_resources().is_empty());
+    // The only resources are the watch list's updates feed and the session's history
+    let resources = router.list_resources();
+    assert_eq!(resources.len(), 2);
+    assert_eq!(resources[0].uri, "docs://updates");
+    assert_eq!(resources[1].uri, "docs://history");
     
-    // Reading a resource should fail
+    // Reading an unknown resource should fail
     let result = router.read_resource("test").await;
     assert!(result.is_err());
     
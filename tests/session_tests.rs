@@ -0,0 +1,68 @@
+//! Many concurrent sessions on the HTTP/SSE transport, checking that each gets
+//! exactly its own responses and that the server releases sessions whose clients left.
+
+mod support;
+
+use std::time::Duration;
+
+use reqwest::Client;
+use support::{crate_name, request_id, run_session, start_server, SseSession};
+
+const SESSIONS: usize = 48;
+const CALLS_PER_SESSION: usize = 8;
+const ROUNDS: usize = 3;
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_sessions_get_only_their_own_responses() {
+    let server = start_server().await;
+    let client = Client::new();
+
+    // Every round must leave nothing behind, so session state can't grow with traffic
+    for round in 0..ROUNDS {
+        let sessions = (0..SESSIONS).map(|session| run_session(&client, server.addr, session, CALLS_PER_SESSION));
+        let results = futures::future::join_all(sessions).await;
+
+        for (session, responses) in results.iter().enumerate() {
+            let mut ids: Vec<u64> = responses
+                .iter()
+                .map(|response| response["id"].as_u64().unwrap_or_else(|| panic!("Response without an id: {}", response)))
+                .collect();
+            ids.sort_unstable();
+            let expected: Vec<u64> = (1..=CALLS_PER_SESSION).map(|call| request_id(session, call)).collect();
+            assert_eq!(ids, expected, "Session {} got other sessions' responses in round {}", session, round);
+
+            for response in responses {
+                let call = (response["id"].as_u64().unwrap() - request_id(session, 0)) as usize;
+                let text = response["result"]["content"][0]["text"]
+                    .as_str()
+                    .unwrap_or_else(|| panic!("Not a tool result: {}", response));
+                assert!(text.contains(&crate_name(session, call)), "Session {} got {}", session, text);
+            }
+        }
+
+        assert!(
+            server.wait_for_sessions_released(RELEASE_TIMEOUT).await,
+            "{} sessions still open after round {}",
+            server.open_sessions().await,
+            round
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_idle_sessions_are_released_when_clients_leave() {
+    let server = start_server().await;
+    let client = Client::new();
+
+    let sessions = futures::future::join_all((0..SESSIONS).map(|_| SseSession::open(&client, server.addr))).await;
+    assert_eq!(server.open_sessions().await, SESSIONS);
+
+    // Nothing is sent to these sessions, so only the keep-alives notice they're gone
+    drop(sessions);
+    assert!(
+        server.wait_for_sessions_released(RELEASE_TIMEOUT).await,
+        "{} idle sessions still open",
+        server.open_sessions().await
+    );
+}
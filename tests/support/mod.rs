@@ -0,0 +1,250 @@
+//! Concurrent MCP sessions over the HTTP/SSE transport, shared by
+//! `tests/session_tests.rs` and `benches/sessions.rs`.
+//!
+//! The server answers every crate lookup locally through `EchoFetcher`, so the
+//! sessions exercise the transport's multiplexing rather than upstream fetches.
+
+// The bench uses only part of the harness
+#![allow(dead_code)]
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use cratedocs_mcp::tools::{DocFetcher, DocPage, DocRouter};
+use cratedocs_mcp::transport::http_sse_server::App;
+use futures::future::BoxFuture;
+use mcp_core::ToolError;
+use reqwest::Client;
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+
+/// Keep-alive interval of the test server, short so sessions of clients that left
+/// are noticed quickly
+pub const KEEP_ALIVE: Duration = Duration::from_millis(20);
+
+/// Fetcher answering every crate page with one naming the crate and version, after
+/// a delay that varies by crate so the calls of different sessions interleave
+pub struct EchoFetcher;
+
+impl DocFetcher for EchoFetcher {
+    fn get_crate_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move {
+            let delay = crate_name.bytes().map(u64::from).sum::<u64>() % 5;
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            let version = version.unwrap_or("latest");
+            Ok(DocPage {
+                url: format!("https://docs.rs/crate/{}/{}/", crate_name, version),
+                html: format!(
+                    "<html><body><h1>{0} {1}</h1><p>Documentation of {0} {1}</p></body></html>",
+                    crate_name, version
+                ),
+            })
+        })
+    }
+
+    fn get_item_page<'a>(
+        &'a self,
+        crate_name: &'a str,
+        _version: Option<&'a str>,
+        relative_path: &'a str,
+    ) -> BoxFuture<'a, Result<DocPage, ToolError>> {
+        Box::pin(async move { Err(ToolError::NotFound(format!("No page {} in {}", relative_path, crate_name))) })
+    }
+
+    fn get_doc_file<'a>(
+        &'a self,
+        crate_name: &'a str,
+        _version: Option<&'a str>,
+        file: &'a str,
+    ) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { Err(ToolError::NotFound(format!("No file {} in {}", file, crate_name))) })
+    }
+
+    fn search<'a>(&'a self, _query: &'a str, _limit: u32) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async { Ok(json!({ "crates": [] }).to_string()) })
+    }
+
+    fn get_crates_io_api<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<String, ToolError>> {
+        Box::pin(async move { Err(ToolError::NotFound(format!("No crates.io endpoint {}", path))) })
+    }
+}
+
+/// HTTP/SSE server on a local port, stopped when dropped
+pub struct TestServer {
+    pub app: App,
+    pub addr: SocketAddr,
+    server: JoinHandle<()>,
+}
+
+impl TestServer {
+    /// Number of sessions the server still holds any state for
+    pub async fn open_sessions(&self) -> usize {
+        let txs = self.app.txs.read().await.len();
+        let replies = self.app.replies.read().await.len();
+        let subscriptions = self.app.subscriptions.read().await.len();
+        let routers = self.app.routers.read().await.len();
+        txs.max(replies).max(subscriptions).max(routers)
+    }
+
+    /// Wait for the server to release every session, giving up after `timeout`;
+    /// whether it did
+    pub async fn wait_for_sessions_released(&self, timeout: Duration) -> bool {
+        let released = async {
+            while self.open_sessions().await > 0 {
+                tokio::time::sleep(KEEP_ALIVE).await;
+            }
+        };
+        tokio::time::timeout(timeout, released).await.is_ok()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+pub async fn start_server() -> TestServer {
+    let router = DocRouter::with_fetcher(Arc::new(EchoFetcher));
+    let app = App::with_router(router).with_keep_alive(KEEP_ALIVE);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind the test server");
+    let addr = listener.local_addr().expect("Test server has no address");
+    let service = app.router();
+    let server = tokio::spawn(async move {
+        axum::serve(listener, service).await.expect("Test server failed");
+    });
+    TestServer { app, addr, server }
+}
+
+/// Client side of one session: its event stream and the endpoint it posts to
+pub struct SseSession {
+    client: Client,
+    events: reqwest::Response,
+    buffer: Vec<u8>,
+    endpoint: String,
+}
+
+impl SseSession {
+    /// Open a session, reading its event stream up to the `endpoint` event
+    pub async fn open(client: &Client, addr: SocketAddr) -> Self {
+        let url = format!("http://{}/sse", addr);
+        let events = client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .expect("Failed to open an event stream");
+        let mut session = Self {
+            client: client.clone(),
+            events,
+            buffer: Vec::new(),
+            endpoint: String::new(),
+        };
+        let (event, data) = session.next_event().await.expect("Event stream ended before the endpoint");
+        assert_eq!(event, "endpoint");
+        session.endpoint = format!("{}{}", url, data);
+        session
+    }
+
+    /// Post a JSON-RPC message to the session
+    pub async fn send(&self, message: &Value) {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .json(message)
+            .send()
+            .await
+            .expect("Failed to post a message");
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED, "{}", message);
+    }
+
+    /// The next JSON-RPC message on the event stream
+    pub async fn next_message(&mut self) -> Value {
+        loop {
+            let (event, data) = self.next_event().await.expect("Event stream ended");
+            if event == "message" {
+                return serde_json::from_str(&data).expect("Message is not JSON");
+            }
+        }
+    }
+
+    // The name and data of the next event, skipping keep-alive comments
+    async fn next_event(&mut self) -> Option<(String, String)> {
+        loop {
+            if let Some(end) = self.buffer.windows(2).position(|pair| pair == b"\n\n") {
+                let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                let block = String::from_utf8_lossy(&block);
+                let mut event = "message".to_string();
+                let mut data = Vec::new();
+                for line in block.lines() {
+                    if let Some(name) = line.strip_prefix("event:") {
+                        event = name.trim_start().to_string();
+                    } else if let Some(value) = line.strip_prefix("data:") {
+                        data.push(value.strip_prefix(' ').unwrap_or(value));
+                    }
+                }
+                if !data.is_empty() {
+                    return Some((event, data.join("\n")));
+                }
+                continue;
+            }
+            let chunk = self.events.chunk().await.ok()??;
+            self.buffer.extend_from_slice(&chunk);
+        }
+    }
+}
+
+/// JSON-RPC id of a session's `call`th request, unique across sessions
+pub fn request_id(session: usize, call: usize) -> u64 {
+    (session * 10_000 + call) as u64
+}
+
+/// Crate a session looks up in its `call`th request, unique across sessions
+pub fn crate_name(session: usize, call: usize) -> String {
+    format!("stress-{}-{}", session, call)
+}
+
+/// Run one session: initialize it, send `calls` crate lookups without waiting for
+/// their answers, and return the responses to them in the order they arrived
+///
+/// The session is closed when this returns, like a client that leaves.
+pub async fn run_session(client: &Client, addr: SocketAddr, session: usize, calls: usize) -> Vec<Value> {
+    let mut sse = SseSession::open(client, addr).await;
+    sse.send(&json!({
+        "jsonrpc": "2.0",
+        "id": request_id(session, 0),
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": { "name": "session-stress", "version": "0.1.0" },
+        },
+    }))
+    .await;
+    let initialized = sse.next_message().await;
+    assert_eq!(initialized["id"], json!(request_id(session, 0)), "{}", initialized);
+    sse.send(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+
+    for call in 1..=calls {
+        sse.send(&json!({
+            "jsonrpc": "2.0",
+            "id": request_id(session, call),
+            "method": "tools/call",
+            "params": {
+                "name": "lookup_crate",
+                "arguments": { "crate_name": crate_name(session, call), "version": format!("1.0.{}", call) },
+            },
+        }))
+        .await;
+    }
+    let mut responses = Vec::with_capacity(calls);
+    for _ in 0..calls {
+        responses.push(sse.next_message().await);
+    }
+    responses
+}
@@ -0,0 +1,932 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use cratedocs_mcp::client::DocsClient;
+use cratedocs_mcp::tools::{
+    split_error_data, user_agent, BaseUrls, CrateOverrides, CratePolicy, DocFetcher, DocRouter, ErrorCode, ReqwestFetcher, USER_AGENT,
+};
+use mcp_core::{Content, ToolError};
+use mcp_server::Router;
+use reqwest::Client;
+use serde_json::json;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+// Build a router whose fetcher talks to the mock server for every upstream
+fn router_for(server: &MockServer) -> DocRouter {
+    let fetcher = ReqwestFetcher::new(Client::new()).with_base_urls(BaseUrls {
+        docs_rs: server.uri(),
+        crates_io: server.uri(),
+        rust_docs: server.uri(),
+        rust_lang_pages: server.uri(),
+        github_raw: server.uri(),
+        github_api: server.uri(),
+        gitlab: server.uri(),
+        lib_rs: server.uri(),
+        sparse_index: server.uri(),
+        static_crates: server.uri(),
+    });
+    DocRouter::with_fetcher(Arc::new(fetcher))
+}
+
+fn text(contents: &[Content]) -> &str {
+    match &contents[0] {
+        Content::Text(text) => &text.text,
+        _ => panic!("Expected text content"),
+    }
+}
+
+#[tokio::test]
+async fn test_lookup_crate_success() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.0/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "<html><body><h1>serde</h1><p>A generic serialization framework</p></body></html>",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let args = json!({ "crate_name": "serde", "version": "1.0.0" });
+
+    let result = router.call_tool("lookup_crate", args.clone()).await.unwrap();
+    assert!(text(&result).contains("A generic serialization framework"));
+
+    // Second call must be a cache hit (the mock expects exactly one request)
+    router.call_tool("lookup_crate", args).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lookup_crate_not_found() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/no-such-crate/"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("lookup_crate", json!({ "crate_name": "no-such-crate" }))
+        .await;
+
+    match result {
+        Err(ToolError::ExecutionError(msg)) => {
+            assert!(msg.contains("Failed to fetch documentation"));
+            assert!(msg.contains("404"));
+            assert_eq!(split_error_data(&msg).1.unwrap().code, ErrorCode::CrateNotFound);
+        }
+        other => panic!("Expected ExecutionError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_lookup_item_falls_back_through_item_types() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/futures/latest/futures/stream/trait.Stream.html"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string("<h1>Trait futures::stream::Stream</h1>"),
+        )
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    // Everything else (struct., enum., ...) is a 404
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "futures", "item_path": "stream::Stream" }),
+        )
+        .await
+        .unwrap();
+    assert!(text(&result).contains("Trait futures::stream::Stream"));
+
+    // Item types are probed concurrently; the higher-priority struct and enum
+    // pages must have been tried (and missed) for the trait page to win
+    let requested: Vec<String> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| r.url.path().to_string())
+        .collect();
+    for expected in [
+        "/futures/latest/futures/stream/struct.Stream.html",
+        "/futures/latest/futures/stream/enum.Stream.html",
+        "/futures/latest/futures/stream/trait.Stream.html",
+    ] {
+        assert!(requested.iter().any(|p| p == expected), "{} was not probed", expected);
+    }
+}
+
+#[tokio::test]
+async fn test_lookup_item_prefers_higher_priority_item_type() {
+    let server = MockServer::start().await;
+    // Both a struct and a fn page exist; the struct must win even if it is slower
+    Mock::given(method("GET"))
+        .and(path("/demo/latest/demo/struct.Thing.html"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("<h1>Struct demo::Thing</h1>")
+                .set_delay(std::time::Duration::from_millis(200)),
+        )
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/demo/latest/demo/fn.Thing.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>Function demo::Thing</h1>"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("lookup_item", json!({ "crate_name": "demo", "item_path": "Thing" }))
+        .await
+        .unwrap();
+    assert!(text(&result).contains("Struct demo::Thing"));
+}
+
+#[tokio::test]
+async fn test_lookup_item_reports_last_error_when_nothing_matches() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "serde", "item_path": "DoesNotExist" }),
+        )
+        .await;
+
+    match result {
+        Err(ToolError::ExecutionError(msg)) => {
+            assert!(msg.contains("No matching item found"));
+            assert!(msg.contains("404"));
+        }
+        other => panic!("Expected ExecutionError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_lookup_item_routes_std_to_rust_docs() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/stable/std/vec/struct.Vec.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>Struct std::vec::Vec</h1>"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/nightly/core/option/enum.Option.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>Enum core::option::Option</h1>"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    // The standard library is never looked up on crates.io
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates/std"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("lookup_item", json!({ "crate_name": "std", "item_path": "std::vec::Vec" }))
+        .await
+        .unwrap();
+    assert!(text(&result).contains("Struct std::vec::Vec"));
+    assert!(text(&result).contains(&format!("Source: {}/stable/std/vec/struct.Vec.html", server.uri())));
+
+    let result = router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "core", "item_path": "option::Option", "version": "nightly" }),
+        )
+        .await
+        .unwrap();
+    assert!(text(&result).contains("Documentation for core nightly"));
+}
+
+#[tokio::test]
+async fn test_lookup_item_rejects_unknown_std_channel() {
+    let server = MockServer::start().await;
+    let router = router_for(&server);
+    let result = router
+        .call_tool(
+            "lookup_item",
+            json!({ "crate_name": "alloc", "item_path": "vec::Vec", "version": "latest" }),
+        )
+        .await;
+    assert!(matches!(result, Err(ToolError::InvalidParameters(msg)) if msg.contains("stable, beta, nightly")));
+}
+
+#[tokio::test]
+async fn test_crate_overrides_pick_version_and_target() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/demo/1.2.0/x86_64-pc-windows-msvc/demo/struct.Widget.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>Struct demo::Widget</h1>"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let overrides = CrateOverrides::parse(
+        r#"{ "crates": { "demo": { "version": "1.2.0", "target": "x86_64-pc-windows-msvc", "aliases": ["demo-alias"] } } }"#,
+    )
+    .unwrap();
+    let fetcher = ReqwestFetcher::new(Client::new())
+        .with_base_urls(BaseUrls {
+            docs_rs: server.uri(),
+            crates_io: server.uri(),
+            rust_docs: server.uri(),
+            rust_lang_pages: server.uri(),
+            github_raw: server.uri(),
+            github_api: server.uri(),
+            gitlab: server.uri(),
+            lib_rs: server.uri(),
+            sparse_index: server.uri(),
+            static_crates: server.uri(),
+        })
+        .with_doc_targets(overrides.targets());
+    let router = DocRouter::with_fetcher(Arc::new(fetcher)).with_crate_overrides(overrides);
+
+    let result = router
+        .call_tool("lookup_item", json!({ "crate_name": "demo-alias", "item_path": "Widget" }))
+        .await
+        .unwrap();
+    assert!(text(&result).starts_with(&format!(
+        "Documentation for demo 1.2.0\nSource: {}/demo/1.2.0/x86_64-pc-windows-msvc/demo/struct.Widget.html",
+        server.uri()
+    )));
+}
+
+#[tokio::test]
+async fn test_lookup_book_fetches_async_book_from_github_pages() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/async-book/toc.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<ol class="chapter"><li><a href="part-guide/async-await.html"><strong>1.</strong> Async and await</a></li></ol>"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/async-book/part-guide/async-await.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<main><h1>Async and await</h1></main>"))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("lookup_book", json!({ "book": "async-book", "topic": "await" }))
+        .await
+        .unwrap();
+    assert!(text(&result).contains(&format!("Source: {}/async-book/part-guide/async-await.html", server.uri())));
+}
+
+#[tokio::test]
+async fn test_search_crates_rate_limited() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "60"))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("search_crates", json!({ "query": "json" }))
+        .await;
+
+    match result {
+        Err(ToolError::ExecutionError(msg)) => {
+            assert!(msg.contains("Failed to search crates.io"));
+            assert!(msg.contains("429"));
+            let data = split_error_data(&msg).1.unwrap();
+            assert_eq!(data.code, ErrorCode::UpstreamRateLimited);
+            assert_eq!(data.suggestions, vec!["Retry after 60 seconds".to_string()]);
+        }
+        other => panic!("Expected ExecutionError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_requests_are_limited() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"crates":[],"meta":{"total":0}}"#)
+                .set_delay(std::time::Duration::from_millis(200)),
+        )
+        .mount(&server)
+        .await;
+
+    let fetcher = ReqwestFetcher::new(Client::new())
+        .with_base_urls(BaseUrls { crates_io: server.uri(), ..BaseUrls::default() })
+        .with_max_concurrent_requests(1);
+    // Clones share the limit, as sessions share the server's fetcher
+    let other = fetcher.clone();
+
+    let started = std::time::Instant::now();
+    let (first, second) = tokio::join!(fetcher.search("json", 5), other.search("yaml", 5));
+    first.unwrap();
+    second.unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(400));
+}
+
+#[tokio::test]
+async fn test_circuit_opens_after_repeated_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let fetcher = ReqwestFetcher::new(Client::new())
+        .with_base_urls(BaseUrls { crates_io: server.uri(), ..BaseUrls::default() })
+        .with_circuit_breaker(2, std::time::Duration::from_secs(60));
+    for _ in 0..2 {
+        let error = fetcher.search("json", 5).await.unwrap_err();
+        assert!(matches!(error, ToolError::ExecutionError(message) if message.contains("503")));
+    }
+
+    // The third request fails without reaching the upstream
+    match fetcher.search("json", 5).await {
+        Err(ToolError::ExecutionError(message)) => {
+            assert!(message.contains("is unavailable after repeated failures"), "{}", message);
+        }
+        other => panic!("expected a fast failure, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[tokio::test]
+async fn test_stale_latest_version_served_during_outage() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/se/rd/serde"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"name":"serde","vers":"1.0.201","deps":[],"features":{},"yanked":false}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.201/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>serde</h1>"))
+        .mount(&server)
+        .await;
+
+    // "latest" expires at once, so the second lookup has to ask again
+    let router = router_for(&server).with_latest_ttl(std::time::Duration::ZERO);
+    let fresh = router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    assert!(text(&fresh).starts_with("Documentation for serde 1.0.201"));
+
+    server.reset().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let stale = router.call_tool("lookup_crate", json!({ "crate_name": "serde" })).await.unwrap();
+    assert!(text(&stale).starts_with("[stale: crates.io can't be reached, so this is serde 1.0.201"));
+    assert!(text(&stale).contains("Documentation for serde 1.0.201"));
+}
+
+#[tokio::test]
+async fn test_search_crates_encodes_query() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates"))
+        .and(query_param("q", "async runtime"))
+        .and(query_param("per_page", "5"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"crates":[{"name":"tokio"}],"meta":{"total":1}}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("search_crates", json!({ "query": "async runtime", "limit": 5 }))
+        .await
+        .unwrap();
+    assert!(text(&result).contains("tokio"));
+}
+
+#[tokio::test]
+async fn test_docs_client_returns_typed_results() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.0/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>serde</h1><p>A serialization framework</p>"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/serde/1.0.0/serde/trait.Serialize.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>Trait serde::Serialize</h1>"))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"crates":[{"name":"serde","max_stable_version":"1.0.0","downloads":10}],"meta":{"total":1}}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+
+    let client = DocsClient::with_router(router_for(&server));
+    let doc = client.lookup_crate("serde", Some("1.0.0")).await.unwrap();
+    assert_eq!(doc.crate_name, "serde");
+    assert_eq!(doc.version, "1.0.0");
+    assert_eq!(doc.source_url, format!("{}/crate/serde/1.0.0/", server.uri()));
+    assert_eq!(doc.fallback, None);
+    assert!(!doc.stale);
+    assert!(doc.markdown.contains("A serialization framework"));
+    assert!(!doc.markdown.contains("Source:"));
+
+    let item = client.lookup_item("serde", "Serialize", Some("1.0.0")).await.unwrap();
+    assert_eq!(item.item_path, "Serialize");
+    assert_eq!(item.source_url, format!("{}/serde/1.0.0/serde/trait.Serialize.html", server.uri()));
+    assert!(item.markdown.contains("Trait serde::Serialize"));
+
+    let results = client.search("serde", 5).await.unwrap();
+    assert_eq!(results.total, Some(1));
+    assert_eq!(results.crates[0].name, "serde");
+    assert_eq!(results.crates[0].downloads, Some(10));
+
+    // Failed lookups fail the same way the tools do
+    assert!(client.lookup_crate("serde", Some("9.9.9")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_lookup_crate_follows_redirects() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/tokio/"))
+        .respond_with(
+            ResponseTemplate::new(302)
+                .insert_header("Location", format!("{}/crate/tokio/1.43.0/", server.uri()).as_str()),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/crate/tokio/1.43.0/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>tokio 1.43.0</h1>"))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("lookup_crate", json!({ "crate_name": "tokio" }))
+        .await
+        .unwrap();
+    assert!(text(&result).contains("tokio 1.43.0"));
+
+    // The version and URL the redirect landed on are reported up front
+    let header = format!("Documentation for tokio 1.43.0\nSource: {}/crate/tokio/1.43.0/\n", server.uri());
+    assert!(text(&result).starts_with(&header), "{}", text(&result));
+    assert!(router.cache.get("tokio:1.43.0").await.is_some());
+}
+
+#[tokio::test]
+async fn test_lookup_crate_tolerates_malformed_html() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/broken/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "<html><body><div><h1>broken<p>Unclosed <b>tags <code>everywhere</div></span>",
+        ))
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    let result = router
+        .call_tool("lookup_crate", json!({ "crate_name": "broken" }))
+        .await
+        .unwrap();
+    let doc = text(&result);
+    assert!(doc.contains("broken"));
+    assert!(doc.contains("everywhere"));
+}
+
+#[tokio::test]
+async fn test_oversized_response_is_rejected() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/huge/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(64 * 1024)))
+        .mount(&server)
+        .await;
+
+    let fetcher = ReqwestFetcher::new(Client::new())
+        .with_base_urls(BaseUrls {
+            docs_rs: server.uri(),
+            crates_io: server.uri(),
+            rust_docs: server.uri(),
+            rust_lang_pages: server.uri(),
+            github_raw: server.uri(),
+            github_api: server.uri(),
+            gitlab: server.uri(),
+            lib_rs: server.uri(),
+            sparse_index: server.uri(),
+            static_crates: server.uri(),
+        })
+        .with_max_response_bytes(1024);
+    let router = DocRouter::with_fetcher(Arc::new(fetcher));
+
+    let result = router
+        .call_tool("lookup_crate", json!({ "crate_name": "huge" }))
+        .await;
+    match result {
+        Err(ToolError::ExecutionError(msg)) => {
+            assert!(msg.contains("exceeds the maximum size of 1024 bytes"), "{}", msg)
+        }
+        other => panic!("Expected ExecutionError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_configured_user_agent_is_sent() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.0/"))
+        .and(header("User-Agent", "docs-bot/2.0 contact: ops@example.com"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>serde</h1>"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fetcher = ReqwestFetcher::new(Client::new())
+        .with_base_urls(BaseUrls {
+            docs_rs: server.uri(),
+            crates_io: server.uri(),
+            rust_docs: server.uri(),
+            rust_lang_pages: server.uri(),
+            github_raw: server.uri(),
+            github_api: server.uri(),
+            gitlab: server.uri(),
+            lib_rs: server.uri(),
+            sparse_index: server.uri(),
+            static_crates: server.uri(),
+        })
+        .with_user_agent(user_agent(Some("docs-bot/2.0"), Some("ops@example.com")));
+    let router = DocRouter::with_fetcher(Arc::new(fetcher));
+
+    router
+        .call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" }))
+        .await
+        .unwrap();
+}
+
+#[test]
+fn test_user_agent_defaults() {
+    assert_eq!(user_agent(None, None), USER_AGENT);
+    assert_eq!(user_agent(Some("  "), Some("")), USER_AGENT);
+    assert_eq!(
+        user_agent(None, Some("https://example.com/abuse")),
+        format!("{} contact: https://example.com/abuse", USER_AGENT)
+    );
+}
+
+#[tokio::test]
+async fn test_doc_router_builder() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.0/"))
+        .and(header("User-Agent", "docs-bot/2.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>serde</h1>"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let base_urls = BaseUrls {
+        docs_rs: server.uri(),
+        crates_io: server.uri(),
+        rust_docs: server.uri(),
+        rust_lang_pages: server.uri(),
+        github_raw: server.uri(),
+        github_api: server.uri(),
+        gitlab: server.uri(),
+        lib_rs: server.uri(),
+        sparse_index: server.uri(),
+        static_crates: server.uri(),
+    };
+    let router = DocRouter::builder()
+        .client(Client::new())
+        .base_urls(base_urls)
+        .user_agent("docs-bot/2.0".to_string())
+        .crate_policy(CratePolicy::new(&[], &["left-pad".to_string()]))
+        .tool_timeout("lookup_crate", Duration::from_secs(30))
+        .build()
+        .unwrap();
+
+    router
+        .call_tool("lookup_crate", json!({ "crate_name": "serde", "version": "1.0.0" }))
+        .await
+        .unwrap();
+    let err = router
+        .call_tool("lookup_crate", json!({ "crate_name": "left-pad", "version": "1.0.0" }))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("crate denylist"));
+
+    let err = DocRouter::builder().tool_timeout("lookup_everything", Duration::from_secs(1)).build().err().unwrap();
+    assert!(err.contains("Unknown tool in a tool timeout: lookup_everything"));
+    let err = DocRouter::builder().disable_sources(&["gitlab".to_string()]).build().err().unwrap();
+    assert!(err.contains("Unknown documentation source gitlab"));
+    assert!(DocRouter::builder().disable_sources(&["local".to_string(), "lib.rs".to_string()]).build().is_ok());
+}
+
+#[tokio::test]
+async fn test_crates_io_token_only_sent_to_crates_io() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates/serde"))
+        .and(header("Authorization", "cio-secret"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"crate":{"max_version":"1.0.200"}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.200/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>serde</h1>"))
+        .mount(&server)
+        .await;
+
+    let fetcher = ReqwestFetcher::new(Client::new())
+        .with_base_urls(BaseUrls {
+            docs_rs: server.uri(),
+            crates_io: server.uri(),
+            rust_docs: server.uri(),
+            rust_lang_pages: server.uri(),
+            github_raw: server.uri(),
+            github_api: server.uri(),
+            gitlab: server.uri(),
+            lib_rs: server.uri(),
+            sparse_index: server.uri(),
+            static_crates: server.uri(),
+        })
+        .with_crates_io_token("cio-secret".to_string());
+    let router = DocRouter::with_fetcher(Arc::new(fetcher));
+
+    router
+        .call_tool("lookup_crate", json!({ "crate_name": "serde" }))
+        .await
+        .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let docs_request = requests.iter().find(|request| request.url.path() == "/crate/serde/1.0.200/").unwrap();
+    assert!(!docs_request.headers.contains_key("Authorization"));
+}
+
+#[tokio::test]
+async fn test_latest_version_from_sparse_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/se/rd/serde"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            r#"{"name":"serde","vers":"1.0.200","deps":[],"features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"serde","vers":"1.0.201","deps":[],"features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"serde","vers":"1.0.202","deps":[],"features":{},"yanked":true}"#,
+        )))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/crate/serde/1.0.201/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<h1>serde</h1>"))
+        .mount(&server)
+        .await;
+
+    let result = router_for(&server)
+        .call_tool("lookup_crate", json!({ "crate_name": "serde" }))
+        .await
+        .unwrap();
+    assert!(text(&result).starts_with("Documentation for serde 1.0.201"));
+
+    // The crates.io API isn't needed when the index answers
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|request| !request.url.path().starts_with("/api/")));
+}
+
+#[tokio::test]
+async fn test_existence_checks_read_only_the_sparse_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/se/rd/serde_json"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            r#"{"name":"serde_json","vers":"1.0.116","deps":[],"features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"serde_json","vers":"1.0.117","deps":[],"features":{},"yanked":true}"#,
+        )))
+        .mount(&server)
+        .await;
+    let router = router_for(&server);
+    let check = |tool: &'static str, args: serde_json::Value| {
+        let call = router.call_tool(tool, args);
+        async move { serde_json::from_str::<serde_json::Value>(text(&call.await.unwrap())).unwrap() }
+    };
+
+    assert_eq!(
+        check("crate_exists", json!({ "crate_name": "serde_json" })).await,
+        json!({ "crate_name": "serde_json", "exists": true })
+    );
+    // Unpublished names that differ only in `-` and `_` point at the published one
+    assert_eq!(
+        check("crate_exists", json!({ "crate_name": "serde-json" })).await,
+        json!({ "crate_name": "serde-json", "exists": false, "did_you_mean": "serde_json" })
+    );
+    assert_eq!(
+        check("crate_exists", json!({ "crate_name": "no-such-crate" })).await,
+        json!({ "crate_name": "no-such-crate", "exists": false })
+    );
+
+    assert_eq!(
+        check("version_exists", json!({ "crate_name": "serde_json", "version": "1.0.117" })).await,
+        json!({ "crate_name": "serde_json", "version": "1.0.117", "exists": true, "yanked": true })
+    );
+    assert_eq!(
+        check("version_exists", json!({ "crate_name": "serde_json", "version": "2.0.0" })).await,
+        json!({
+            "crate_name": "serde_json",
+            "version": "2.0.0",
+            "exists": false,
+            "crate_exists": true,
+            "latest_version": "1.0.116",
+        })
+    );
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|request| !request.url.path().starts_with("/api/")));
+}
+
+#[tokio::test]
+async fn test_latest_version_tool_reads_sparse_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/to/ki/tokio"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            r#"{"name":"tokio","vers":"1.43.0","deps":[],"features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"tokio","vers":"2.0.0-alpha.1","deps":[],"features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"tokio","vers":"1.44.0","deps":[],"features":{},"yanked":true}"#,
+        )))
+        .mount(&server)
+        .await;
+    let router = router_for(&server);
+
+    let result = router.call_tool("latest_version", json!({ "crate_name": "tokio" })).await.unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(text(&result)).unwrap(),
+        json!({ "crate_name": "tokio", "latest": "1.43.0", "latest_prerelease": "2.0.0-alpha.1" })
+    );
+
+    match router.call_tool("latest_version", json!({ "crate_name": "no-such-crate" })).await {
+        Err(ToolError::ExecutionError(msg)) => {
+            assert_eq!(split_error_data(&msg).1.unwrap().code, ErrorCode::CrateNotFound);
+        }
+        other => panic!("Expected ExecutionError, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_dependencies_from_sparse_index() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/de/mo/demo"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"name":"demo","vers":"0.2.0","deps":[{"name":"itoa","req":"^1.0.1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}],"features":{},"yanked":false}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/it/oa/itoa"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            r#"{"name":"itoa","vers":"1.0.11","deps":[],"features":{"std":[]},"yanked":false}"#,
+            "\n",
+            r#"{"name":"itoa","vers":"1.0.14","deps":[],"features":{"std":[]},"yanked":false}"#,
+        )))
+        .mount(&server)
+        .await;
+
+    let result = router_for(&server)
+        .call_tool("resolve_dependencies", json!({ "crate_name": "demo" }))
+        .await
+        .unwrap();
+    let resolved = text(&result);
+    assert!(resolved.starts_with("# Resolved dependencies of demo 0.2.0"));
+    assert!(resolved.contains("## Direct dependencies (1)\n\n- itoa 1.0.14\n"));
+}
+
+#[tokio::test]
+async fn test_dependency_weight() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/de/mo/demo"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"name":"demo","vers":"0.2.0","deps":[{"name":"itoa","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}],"features":{},"yanked":false}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/it/oa/itoa"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"name":"itoa","vers":"1.0.14","deps":[],"features":{},"yanked":false}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates/demo/0.2.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"version":{"num":"0.2.0","crate_size":1024}}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/v1/crates/itoa/1.0.14"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"version":{"num":"1.0.14","crate_size":11264}}"#))
+        .mount(&server)
+        .await;
+
+    let result = router_for(&server)
+        .call_tool("dependency_weight", json!({ "crate_name": "demo" }))
+        .await
+        .unwrap();
+    let weight = text(&result);
+    assert!(weight.contains("Adds 1 crate: 1 direct and 0 transitive.\n"));
+    assert!(weight.contains("Download size of the dependencies: 11.0 KiB compressed (12.0 KiB with demo itself).\n"));
+    assert!(weight.contains("| itoa | 1.0.14 | 11.0 KiB | direct |\n"));
+}
+
+#[tokio::test]
+async fn test_source_stats_from_crate_archive() {
+    use std::io::Write;
+
+    let source = "pub fn add(a: u8, b: u8) -> u8 {\n    unsafe { a.unchecked_add(b) }\n}\n";
+    let mut header = [0u8; 512];
+    header[..17].copy_from_slice(b"demo-0.1.0/lib.rs");
+    header[124..135].copy_from_slice(format!("{:011o}", source.len()).as_bytes());
+    header[156] = b'0';
+    header[257..262].copy_from_slice(b"ustar");
+    let mut archive = header.to_vec();
+    archive.extend_from_slice(source.as_bytes());
+    archive.resize(2048, 0);
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&archive).unwrap();
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/crates/demo/demo-0.1.0.crate"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(encoder.finish().unwrap()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let router = router_for(&server);
+    for _ in 0..2 {
+        let result = router
+            .call_tool("source_stats", json!({ "crate_name": "demo", "version": "0.1.0" }))
+            .await
+            .unwrap();
+        let stats = text(&result);
+        assert!(stats.starts_with("# Source statistics of demo 0.1.0"));
+        assert!(stats.contains("- Lines of Rust: 3 (3 of code"));
+        assert!(stats.contains("- `unsafe`: 1 blocks, 0 functions, 0 impls, in 1 of 1 Rust files\n"));
+    }
+}